@@ -0,0 +1,35 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+# Fill in the sha256 of every toolchain in src/provision/catalog.json that
+# has none, by downloading its archive. xcargo refuses to install a
+# catalog toolchain without a valid sha256.
+
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+CATALOG="${SCRIPT_DIR}/../src/provision/catalog.json"
+
+python3 - "${CATALOG}" <<'PY'
+import hashlib, json, sys, urllib.request
+
+path = sys.argv[1]
+with open(path) as f:
+    catalog = json.load(f)
+
+hashes = {}
+for entry in catalog["toolchains"]:
+    if entry.get("sha256"):
+        continue
+    url = entry["url"]
+    if url not in hashes:
+        print(f"Downloading {url}", file=sys.stderr)
+        digest = hashlib.sha256()
+        with urllib.request.urlopen(url) as response:
+            for chunk in iter(lambda: response.read(1 << 20), b""):
+                digest.update(chunk)
+        hashes[url] = digest.hexdigest()
+    entry["sha256"] = hashes[url]
+
+with open(path, "w") as f:
+    json.dump(catalog, f, indent=2)
+    f.write("\n")
+PY
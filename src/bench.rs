@@ -0,0 +1,470 @@
+//! Benchmark results across targets
+//!
+//! `xcargo bench` runs `cargo bench` per target, through the target's
+//! runner where it needs one (a configured runner, QEMU, a WASI runtime or
+//! an iOS simulator), and reads the results from the output: libtest's
+//! `bench:` lines and Criterion's `time:` estimates. They are kept per
+//! target in `.xcargo/bench/<triple>.json` in the workspace, together with
+//! the run before, and combined into one table with a column per target.
+//! A benchmark that got slower on one target only (e.g., on musl, whose
+//! allocator is slower, or on aarch64) stands out.
+//!
+//! ```no_run
+//! use xcargo::bench;
+//!
+//! # fn example() -> xcargo::Result<()> {
+//! let recorded = bench::load_since(&bench::bench_dir()?, 0);
+//! bench::print_report(&recorded);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::build::artifacts;
+use crate::error::Result;
+use crate::output::{self, helpers};
+use crate::state::{self, State};
+use crate::timings::now;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Results location inside the workspace root
+const BENCH_DIR: &str = ".xcargo/bench";
+
+/// How much slower than its last run a benchmark must be to be reported
+const REGRESSION: f64 = 0.10;
+
+/// One benchmark's result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchResult {
+    /// Benchmark name (e.g., "parse/small")
+    pub name: String,
+    /// Nanoseconds per iteration
+    pub nanos: f64,
+    /// Nanoseconds either way the measurement may be off, if reported
+    #[serde(default)]
+    pub spread: Option<f64>,
+}
+
+impl BenchResult {
+    /// Parse a libtest result line
+    /// (`test parse ... bench:       1,234 ns/iter (+/- 56)`)
+    #[must_use]
+    pub fn from_libtest(line: &str) -> Option<Self> {
+        let (name, rest) = line.strip_prefix("test ")?.split_once(" ... bench:")?;
+        let (nanos, rest) = rest.trim_start().split_once(" ns/iter")?;
+        let spread = rest
+            .trim()
+            .strip_prefix("(+/-")
+            .and_then(|spread| spread.strip_suffix(')'))
+            .and_then(|spread| number(spread.trim()));
+        Some(Self {
+            name: name.trim().to_string(),
+            nanos: number(nanos.trim())?,
+            spread,
+        })
+    }
+
+    /// Parse the estimates of a Criterion `time:` line
+    /// (`[1.2345 µs 1.2456 µs 1.2567 µs]`), with the benchmark's name
+    #[must_use]
+    pub fn from_criterion(name: &str, line: &str) -> Option<Self> {
+        let estimates = line
+            .split_once("time:")?
+            .1
+            .trim()
+            .strip_prefix('[')?
+            .strip_suffix(']')?;
+        let fields: Vec<&str> = estimates.split_whitespace().collect();
+        let [low, low_unit, mid, mid_unit, high, high_unit] = fields[..] else {
+            return None;
+        };
+        let low = duration(low, low_unit)?;
+        let high = duration(high, high_unit)?;
+        Some(Self {
+            name: name.to_string(),
+            nanos: duration(mid, mid_unit)?,
+            spread: Some((high - low) / 2.0),
+        })
+    }
+}
+
+/// A number as libtest prints it, with `,` between thousands
+fn number(text: &str) -> Option<f64> {
+    text.replace(',', "").parse().ok()
+}
+
+/// Nanoseconds in a Criterion duration
+fn duration(value: &str, unit: &str) -> Option<f64> {
+    let scale = match unit {
+        "ps" => 1e-3,
+        "ns" => 1.0,
+        "µs" | "us" => 1e3,
+        "ms" => 1e6,
+        "s" => 1e9,
+        _ => return None,
+    };
+    Some(value.parse::<f64>().ok()? * scale)
+}
+
+/// Benchmark results of one target, with those of the run before
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TargetBench {
+    /// Target triple
+    pub target: String,
+    /// When the run finished, in seconds since the Unix epoch
+    pub recorded: u64,
+    /// Every benchmark that reported a result
+    pub results: Vec<BenchResult>,
+    /// Results of the previous run, to compare against
+    #[serde(default)]
+    pub previous: Vec<BenchResult>,
+}
+
+impl State for TargetBench {
+    const SCHEMA: u32 = 1;
+    const DESCRIPTION: &'static str = "benchmark results";
+}
+
+impl TargetBench {
+    /// Results of a run on `target` that finished now
+    #[must_use]
+    pub fn new(target: &str, results: Vec<BenchResult>) -> Self {
+        Self {
+            target: target.to_string(),
+            recorded: now(),
+            results,
+            previous: Vec::new(),
+        }
+    }
+
+    /// Result of the benchmark `name`
+    #[must_use]
+    pub fn result(&self, name: &str) -> Option<&BenchResult> {
+        self.results.iter().find(|result| result.name == name)
+    }
+
+    /// Benchmarks slower than in the run before by more than 10%, with the
+    /// previous and current times
+    #[must_use]
+    pub fn regressions(&self) -> Vec<(&str, f64, f64)> {
+        self.results
+            .iter()
+            .filter_map(|result| {
+                let before = self.previous.iter().find(|p| p.name == result.name)?;
+                (result.nanos > before.nanos * (1.0 + REGRESSION)).then_some((
+                    result.name.as_str(),
+                    before.nanos,
+                    result.nanos,
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Gathers benchmark results from cargo output read on other threads
+#[derive(Debug, Clone, Default)]
+pub struct Collector {
+    state: Arc<Mutex<CollectorState>>,
+}
+
+#[derive(Debug, Default)]
+struct CollectorState {
+    results: Vec<BenchResult>,
+    /// Name Criterion printed on its own line, before a long name's `time:`
+    pending: Option<String>,
+}
+
+impl Collector {
+    /// Create an empty collector
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Look for a result in `line`
+    pub fn observe(&self, line: &str) {
+        let line = output::strip_ansi(line);
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if let Some(result) = BenchResult::from_libtest(&line) {
+            state.results.push(result);
+            return;
+        }
+        if let Some((name, _)) = line.split_once("time:") {
+            let name = match name.trim() {
+                "" => state.pending.take(),
+                name => Some(name.to_string()),
+            };
+            if let Some(result) = name.and_then(|name| BenchResult::from_criterion(&name, &line)) {
+                state.results.push(result);
+            }
+            return;
+        }
+        let starts_name = !line.starts_with(char::is_whitespace)
+            && !line.trim().is_empty()
+            && !line.starts_with("Benchmarking ");
+        if starts_name {
+            state.pending = Some(line.trim().to_string());
+        }
+    }
+
+    /// Results collected so far
+    #[must_use]
+    pub fn results(&self) -> Vec<BenchResult> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .results
+            .clone()
+    }
+}
+
+/// Directory of the current workspace's benchmark results
+pub fn bench_dir() -> Result<PathBuf> {
+    Ok(artifacts::workspace_root()?.join(BENCH_DIR))
+}
+
+/// Write one target's results to `dir`, keeping the ones they replace as
+/// the previous run
+pub fn save(dir: &Path, bench: &TargetBench) -> Result<()> {
+    let path = dir.join(format!("{}.json", bench.target));
+    let mut bench = bench.clone();
+    if path.exists() {
+        let before = state::load::<TargetBench>(&path);
+        if !before.results.is_empty() {
+            bench.previous = before.results;
+        }
+    }
+    state::save(&path, &bench)
+}
+
+/// Results in `dir` recorded at or after `since` (seconds since the Unix
+/// epoch), sorted by target
+#[must_use]
+pub fn load_since(dir: &Path, since: u64) -> Vec<TargetBench> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut loaded: Vec<TargetBench> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .map(|path| state::load::<TargetBench>(&path))
+        .filter(|bench| !bench.results.is_empty() && bench.recorded >= since)
+        .collect();
+    loaded.sort_by(|a, b| a.target.cmp(&b.target));
+    loaded
+}
+
+/// Print each benchmark's time on each target, then the benchmarks that
+/// got slower on a target since its previous run
+///
+/// Times on other targets than the fastest show how many times slower
+/// they are. A `-` marks a target that did not run the benchmark.
+pub fn print_report(benches: &[TargetBench]) {
+    if benches.is_empty() {
+        helpers::warning("No benchmark results were recorded");
+        return;
+    }
+
+    helpers::section("Benchmarks");
+    let names: BTreeSet<&str> = benches
+        .iter()
+        .flat_map(|bench| &bench.results)
+        .map(|result| result.name.as_str())
+        .collect();
+    let cell = |name: &str, bench: &TargetBench| {
+        let fastest = benches
+            .iter()
+            .filter_map(|bench| bench.result(name))
+            .map(|result| result.nanos)
+            .fold(f64::INFINITY, f64::min);
+        bench.result(name).map_or_else(
+            || "-".to_string(),
+            |result| {
+                let ratio = result.nanos / fastest;
+                if ratio >= 1.005 {
+                    format!("{} ({ratio:.2}x)", time(result.nanos))
+                } else {
+                    time(result.nanos)
+                }
+            },
+        )
+    };
+
+    if output::is_plain() {
+        for name in &names {
+            let times: Vec<String> = benches
+                .iter()
+                .map(|bench| format!("{} {}", bench.target, cell(name, bench)))
+                .collect();
+            println!("{name}: {}", times.join(", "));
+        }
+    } else {
+        let header: Vec<String> = std::iter::once("Benchmark".to_string())
+            .chain(benches.iter().map(|bench| bench.target.clone()))
+            .collect();
+        let rows: Vec<Vec<String>> = names
+            .iter()
+            .map(|name| {
+                std::iter::once((*name).to_string())
+                    .chain(benches.iter().map(|bench| cell(name, bench)))
+                    .collect()
+            })
+            .collect();
+        let mut widths: Vec<usize> = header.iter().map(String::len).collect();
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        // Names align left, times right
+        let line = |cells: &[String]| {
+            let cells: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(column, (cell, width))| {
+                    if column == 0 {
+                        format!("{cell:<width$}")
+                    } else {
+                        format!("{cell:>width$}")
+                    }
+                })
+                .collect();
+            format!("  {}", cells.join("  "))
+        };
+        println!("{}", line(&header));
+        for row in &rows {
+            println!("{}", line(row));
+        }
+    }
+
+    for bench in benches {
+        for (name, before, now) in bench.regressions() {
+            helpers::warning(format!(
+                "{name} is {:.0}% slower on {} than in the previous run ({} -> {})",
+                (now / before - 1.0) * 100.0,
+                bench.target,
+                time(before),
+                time(now)
+            ));
+        }
+    }
+}
+
+/// A time per iteration in the largest unit below 1000 ("1.23 µs")
+fn time(nanos: f64) -> String {
+    let (value, unit) = if nanos >= 1e9 {
+        (nanos / 1e9, "s")
+    } else if nanos >= 1e6 {
+        (nanos / 1e6, "ms")
+    } else if nanos >= 1e3 {
+        (nanos / 1e3, "µs")
+    } else {
+        (nanos, "ns")
+    };
+    format!("{value:.2} {unit}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn result(name: &str, nanos: f64) -> BenchResult {
+        BenchResult {
+            name: name.to_string(),
+            nanos,
+            spread: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_results() {
+        assert_eq!(
+            BenchResult::from_libtest("test parse_small ... bench:       1,234 ns/iter (+/- 56)"),
+            Some(BenchResult {
+                name: "parse_small".to_string(),
+                nanos: 1234.0,
+                spread: Some(56.0),
+            })
+        );
+        let newer = BenchResult::from_libtest(
+            "test parse_large ... bench:   1,234,567.50 ns/iter (+/- 8,901.25)",
+        )
+        .unwrap();
+        assert!((newer.nanos - 1_234_567.5).abs() < 1e-6);
+        assert_eq!(BenchResult::from_libtest("test parse ... ok"), None);
+
+        let criterion = BenchResult::from_criterion(
+            "parse/small",
+            "parse/small             time:   [1.2000 µs 1.2500 µs 1.3000 µs]",
+        )
+        .unwrap();
+        assert!((criterion.nanos - 1250.0).abs() < 1e-6);
+        assert!((criterion.spread.unwrap() - 50.0).abs() < 1e-6);
+        assert_eq!(
+            BenchResult::from_criterion("x", "time:   [change: +1.2% +2.0% +3.1%]"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_collector() {
+        let collector = Collector::new();
+        let shared = collector.clone();
+        for line in [
+            "running 2 tests",
+            "test parse_small ... bench:         850 ns/iter (+/- 20)",
+            "Benchmarking a_long_group_name/with_a_long_input: Warming up for 3.0000 s",
+            "a_long_group_name/with_a_long_input",
+            "                        time:   [10.000 ms 10.100 ms 10.200 ms]",
+            "                        change: [+1.0000% +2.0000% +3.0000%] (p = 0.00 < 0.05)",
+            "short                   time:   [950.00 ps 1.0000 ns 1.0500 ns]",
+        ] {
+            shared.observe(line);
+        }
+        let names: Vec<String> = collector.results().into_iter().map(|r| r.name).collect();
+        assert_eq!(
+            names,
+            [
+                "parse_small",
+                "a_long_group_name/with_a_long_input",
+                "short"
+            ]
+        );
+        assert!((collector.results()[1].nanos - 10_100_000.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_save_keeps_previous_run() {
+        let temp = TempDir::new().unwrap();
+        let target = "aarch64-unknown-linux-musl";
+        let mut first =
+            TargetBench::new(target, vec![result("parse", 100.0), result("render", 50.0)]);
+        first.recorded = 100;
+        save(temp.path(), &first).unwrap();
+        let second = TargetBench::new(target, vec![result("parse", 125.0), result("render", 52.0)]);
+        save(temp.path(), &second).unwrap();
+
+        let loaded = load_since(temp.path(), 200);
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].previous, first.results);
+        assert_eq!(loaded[0].regressions(), [("parse", 100.0, 125.0)]);
+        assert!(load_since(&temp.path().join("missing"), 0).is_empty());
+    }
+
+    #[test]
+    fn test_time() {
+        assert_eq!(time(850.0), "850.00 ns");
+        assert_eq!(time(1250.0), "1.25 µs");
+        assert_eq!(time(10_100_000.0), "10.10 ms");
+    }
+}
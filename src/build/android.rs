@@ -0,0 +1,483 @@
+//! Android libraries for Gradle and `CMakeLists.txt` consumers
+//!
+//! With `[artifacts.android] dir = "android"`, each build of an Android
+//! target copies the workspace's cdylibs into `android/jniLibs/<abi>/`, the
+//! layout Gradle's `jniLibs` source sets expect. ABIs built separately add
+//! up, and after every build xcargo rewrites from what is in `jniLibs/`:
+//!
+//! - `rust-libs.cmake`, for `include()` in an app's `CMakeLists.txt`, with
+//!   an imported `rust::<name>` library per `.so` for `${ANDROID_ABI}`
+//! - `xcargo-android.toml`, the ABIs, their targets, the API level and the
+//!   libraries, as cargo-ndk takes them
+//! - with `aar = true` (and the `android-aar` feature), `<package>.aar`, an
+//!   Android library Gradle can depend on directly
+//!
+//! Libraries for the 64-bit ABIs are checked for 16 KB page alignment,
+//! which devices with 16 KB pages (from Android 15) need to load them; with
+//! `require_16k_pages = true` a misaligned library fails the build.
+//!
+//! ```toml
+//! [artifacts.android]
+//! dir = "android"
+//! aar = true
+//! package = "com.example.rust"
+//! min_sdk = 24
+//! require_16k_pages = true
+//! ```
+
+use crate::config::AndroidExportConfig;
+use crate::error::{Error, Result};
+use crate::target::Target;
+use crate::verify;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::artifacts::{ArtifactKind, Product};
+
+/// Directory the libraries are laid out in, inside the export directory
+pub const JNI_LIBS_DIR: &str = "jniLibs";
+
+/// `include()` file declaring the libraries
+pub const CMAKE_FILE: &str = "rust-libs.cmake";
+
+/// Build settings and contents of the export
+pub const CONFIG_FILE: &str = "xcargo-android.toml";
+
+/// Android ABIs and the Rust targets building them
+const ABIS: &[(&str, &str)] = &[
+    ("arm64-v8a", "aarch64-linux-android"),
+    ("armeabi-v7a", "armv7-linux-androideabi"),
+    ("x86", "i686-linux-android"),
+    ("x86_64", "x86_64-linux-android"),
+];
+
+/// Android ABI of `target`, `None` for targets other than Android's
+#[must_use]
+pub fn abi(target: &Target) -> Option<&'static str> {
+    if target.os != "android" {
+        return None;
+    }
+    match target.triple_arch() {
+        "aarch64" => Some("arm64-v8a"),
+        "armv7" | "thumbv7neon" => Some("armeabi-v7a"),
+        "i686" => Some("x86"),
+        "x86_64" => Some("x86_64"),
+        _ => None,
+    }
+}
+
+/// What `xcargo-android.toml` records
+#[derive(Debug, Serialize)]
+struct ExportConfig {
+    /// API level, as cargo-ndk's `--platform`
+    platform: u32,
+    /// Where the libraries are, as cargo-ndk's `--output-dir`
+    output_dir: &'static str,
+    /// Rust target of each ABI present
+    abis: BTreeMap<String, String>,
+    /// ABIs each library was built for
+    libraries: BTreeMap<String, Vec<String>>,
+}
+
+/// Export directory for Android consumers
+pub struct AndroidExport<'a> {
+    dir: PathBuf,
+    config: &'a AndroidExportConfig,
+}
+
+impl<'a> AndroidExport<'a> {
+    /// Export configured by `config`, `None` if it sets no directory
+    #[must_use]
+    pub fn new(config: &'a AndroidExportConfig) -> Option<Self> {
+        let dir = PathBuf::from(config.dir.as_ref()?);
+        Some(Self { dir, config })
+    }
+
+    /// Export directory
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Copy the cdylibs built in `target_dir/<triple>/<profile>/` into
+    /// `jniLibs/<abi>/` and rewrite the generated files
+    ///
+    /// Returns the libraries copied; products that were not built are
+    /// skipped, and targets other than Android's export nothing.
+    pub fn export(
+        &self,
+        target: &Target,
+        profile: &str,
+        target_dir: &Path,
+        products: &[Product],
+    ) -> Result<Vec<PathBuf>> {
+        let Some(abi) = abi(target) else {
+            return Ok(Vec::new());
+        };
+        let build_dir = target_dir.join(&target.triple).join(profile);
+        let abi_dir = self.dir.join(JNI_LIBS_DIR).join(abi);
+
+        let mut exported = Vec::new();
+        for product in products {
+            if product.kind != ArtifactKind::Cdylib {
+                continue;
+            }
+            let file = product.file_name(target);
+            let source = build_dir.join(&file);
+            if !source.is_file() {
+                continue;
+            }
+            fs::create_dir_all(&abi_dir)?;
+            let dest = abi_dir.join(&file);
+            fs::copy(&source, &dest)?;
+            exported.push(dest);
+        }
+
+        if !exported.is_empty() {
+            self.write_generated()?;
+        }
+        Ok(exported)
+    }
+
+    /// Libraries among `exported` for `target` that are not aligned to
+    /// 16 KB pages, with their alignment
+    ///
+    /// Only 64-bit ABIs run on devices with 16 KB pages, so 32-bit ones
+    /// never have misaligned libraries.
+    pub fn misaligned(target: &Target, exported: &[PathBuf]) -> Result<Vec<(PathBuf, u64)>> {
+        if !verify::needs_16k_pages(target) {
+            return Ok(Vec::new());
+        }
+        let mut misaligned = Vec::new();
+        for library in exported {
+            if let Some(alignment) = verify::load_alignment(&fs::read(library)?)
+                .filter(|alignment| *alignment < verify::ANDROID_PAGE_SIZE)
+            {
+                misaligned.push((library.clone(), alignment));
+            }
+        }
+        Ok(misaligned)
+    }
+
+    /// Libraries in `jniLibs/`, by ABI
+    pub fn contents(&self) -> Result<BTreeMap<String, Vec<String>>> {
+        let mut contents = BTreeMap::new();
+        for (abi, _) in ABIS {
+            let Ok(entries) = fs::read_dir(self.dir.join(JNI_LIBS_DIR).join(abi)) else {
+                continue;
+            };
+            let mut libraries: Vec<String> = entries
+                .flatten()
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .filter(|name| {
+                    name.starts_with("lib")
+                        && Path::new(name).extension().is_some_and(|ext| ext == "so")
+                })
+                .collect();
+            if !libraries.is_empty() {
+                libraries.sort();
+                contents.insert((*abi).to_string(), libraries);
+            }
+        }
+        Ok(contents)
+    }
+
+    /// Rewrite the generated files from what is in `jniLibs/`
+    fn write_generated(&self) -> Result<()> {
+        let contents = self.contents()?;
+        fs::write(self.dir.join(CMAKE_FILE), cmake_include(&contents))?;
+        fs::write(
+            self.dir.join(CONFIG_FILE),
+            export_config(&contents, self.config.min_sdk)?,
+        )?;
+        if self.config.aar {
+            self.write_aar(&contents)?;
+        }
+        Ok(())
+    }
+
+    /// Package `jniLibs/` as `<package>.aar`
+    #[cfg(feature = "android-aar")]
+    fn write_aar(&self, contents: &BTreeMap<String, Vec<String>>) -> Result<()> {
+        use std::io::Write;
+        use zip::write::FileOptions;
+        use zip::{CompressionMethod, ZipWriter};
+
+        let package = self.config.package.as_deref().ok_or_else(|| {
+            Error::Config("artifacts.android.aar needs artifacts.android.package".to_string())
+        })?;
+        let failed =
+            |e: zip::result::ZipError| Error::Build(format!("Failed to write the AAR: {e}"));
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        // An AAR needs a classes.jar even without Java code
+        let classes = ZipWriter::new(std::io::Cursor::new(Vec::new()))
+            .finish()
+            .map_err(failed)?
+            .into_inner();
+
+        let path = self.dir.join(format!("{package}.aar"));
+        let partial = path.with_extension("aar.partial");
+        let mut aar = ZipWriter::new(fs::File::create(&partial)?);
+        aar.start_file("AndroidManifest.xml", options)
+            .map_err(failed)?;
+        aar.write_all(android_manifest(package, self.config.min_sdk).as_bytes())?;
+        aar.start_file("classes.jar", options).map_err(failed)?;
+        aar.write_all(&classes)?;
+        aar.start_file("R.txt", options).map_err(failed)?;
+        for (abi, libraries) in contents {
+            for library in libraries {
+                aar.start_file(format!("jni/{abi}/{library}"), options)
+                    .map_err(failed)?;
+                aar.write_all(&fs::read(
+                    self.dir.join(JNI_LIBS_DIR).join(abi).join(library),
+                )?)?;
+            }
+        }
+        aar.finish().map_err(failed)?;
+        fs::rename(&partial, &path)?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "android-aar"))]
+    #[allow(clippy::unused_self, clippy::unnecessary_wraps)]
+    fn write_aar(&self, _contents: &BTreeMap<String, Vec<String>>) -> Result<()> {
+        crate::output::helpers::warning(
+            "Skipping the .aar: xcargo was built without the android-aar feature",
+        );
+        Ok(())
+    }
+}
+
+/// `rust-libs.cmake` for the libraries in `contents`
+fn cmake_include(contents: &BTreeMap<String, Vec<String>>) -> String {
+    let mut libraries: Vec<&String> = contents.values().flatten().collect();
+    libraries.sort();
+    libraries.dedup();
+
+    let mut cmake = String::from(
+        "# Generated by xcargo; include() it from CMakeLists.txt\n\
+         set(XCARGO_JNI_LIBS \"${CMAKE_CURRENT_LIST_DIR}/jniLibs\")\n",
+    );
+    for library in libraries {
+        let name = library.trim_start_matches("lib").trim_end_matches(".so");
+        let _ = write!(
+            cmake,
+            "\nif(NOT TARGET rust::{name})\n\
+             \x20 add_library(rust::{name} SHARED IMPORTED)\n\
+             \x20 set_target_properties(rust::{name} PROPERTIES\n\
+             \x20   IMPORTED_LOCATION \"${{XCARGO_JNI_LIBS}}/${{ANDROID_ABI}}/{library}\"\n\
+             \x20   IMPORTED_NO_SONAME TRUE)\n\
+             endif()\n"
+        );
+    }
+    cmake
+}
+
+/// `xcargo-android.toml` for the libraries in `contents`
+fn export_config(contents: &BTreeMap<String, Vec<String>>, min_sdk: u32) -> Result<String> {
+    let mut config = ExportConfig {
+        platform: min_sdk,
+        output_dir: JNI_LIBS_DIR,
+        abis: BTreeMap::new(),
+        libraries: BTreeMap::new(),
+    };
+    for (abi, libraries) in contents {
+        if let Some((_, triple)) = ABIS.iter().find(|(name, _)| name == abi) {
+            config.abis.insert(abi.clone(), (*triple).to_string());
+        }
+        for library in libraries {
+            config
+                .libraries
+                .entry(library.clone())
+                .or_default()
+                .push(abi.clone());
+        }
+    }
+    let toml = toml::to_string(&config)
+        .map_err(|e| Error::Config(format!("Cannot serialize {CONFIG_FILE}: {e}")))?;
+    Ok(format!(
+        "# Generated by xcargo from {JNI_LIBS_DIR}/\n{toml}"
+    ))
+}
+
+/// The AAR's `AndroidManifest.xml`
+#[cfg_attr(not(feature = "android-aar"), allow(dead_code))]
+fn android_manifest(package: &str, min_sdk: u32) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <manifest xmlns:android=\"http://schemas.android.com/apk/res/android\" package=\"{package}\">\n\
+         \x20   <uses-sdk android:minSdkVersion=\"{min_sdk}\" />\n\
+         </manifest>\n"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn cdylib(name: &str) -> Product {
+        Product {
+            package: name.to_string(),
+            name: name.to_string(),
+            kind: ArtifactKind::Cdylib,
+        }
+    }
+
+    fn build(target_dir: &Path, triple: &str, file: &str) {
+        let dir = target_dir.join(triple).join("release");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(file), triple).unwrap();
+    }
+
+    #[test]
+    fn test_abi() {
+        let abi = |triple: &str| abi(&Target::from_triple(triple).unwrap());
+        assert_eq!(abi("aarch64-linux-android"), Some("arm64-v8a"));
+        assert_eq!(abi("armv7-linux-androideabi"), Some("armeabi-v7a"));
+        assert_eq!(abi("i686-linux-android"), Some("x86"));
+        assert_eq!(abi("x86_64-linux-android"), Some("x86_64"));
+        assert_eq!(abi("aarch64-unknown-linux-gnu"), None);
+        for (name, triple) in ABIS {
+            assert_eq!(abi(triple), Some(*name));
+        }
+    }
+
+    #[test]
+    fn test_misaligned() {
+        let dir = TempDir::new().unwrap();
+        // A 64-bit ELF with one PT_LOAD segment aligned to 4 KiB
+        let mut elf = vec![0u8; 0x40 + 56];
+        elf[..4].copy_from_slice(b"\x7fELF");
+        elf[4] = 2;
+        elf[5] = 1;
+        elf[0x20..0x28].copy_from_slice(&0x40u64.to_le_bytes());
+        elf[0x36..0x38].copy_from_slice(&56u16.to_le_bytes());
+        elf[0x38..0x3a].copy_from_slice(&1u16.to_le_bytes());
+        elf[0x40..0x44].copy_from_slice(&1u32.to_le_bytes());
+        elf[0x70..0x78].copy_from_slice(&0x1000u64.to_le_bytes());
+        let library = dir.path().join("libcore.so");
+        fs::write(&library, &elf).unwrap();
+
+        let aarch64 = Target::from_triple("aarch64-linux-android").unwrap();
+        let arm = Target::from_triple("arm-linux-androideabi").unwrap();
+        let exported = [library.clone()];
+        assert_eq!(
+            AndroidExport::misaligned(&aarch64, &exported).unwrap(),
+            [(library.clone(), 0x1000)]
+        );
+        assert!(AndroidExport::misaligned(&arm, &exported)
+            .unwrap()
+            .is_empty());
+
+        elf[0x70..0x78].copy_from_slice(&0x4000u64.to_le_bytes());
+        fs::write(&library, &elf).unwrap();
+        assert!(AndroidExport::misaligned(&aarch64, &exported)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_export_adds_up_across_abis() {
+        let dir = TempDir::new().unwrap();
+        let target_dir = dir.path().join("target");
+        build(&target_dir, "aarch64-linux-android", "libcore_ffi.so");
+        build(&target_dir, "x86_64-linux-android", "libcore_ffi.so");
+        let config = AndroidExportConfig {
+            dir: Some(dir.path().join("android").display().to_string()),
+            ..Default::default()
+        };
+        let export = AndroidExport::new(&config).unwrap();
+        let products = [
+            cdylib("core-ffi"),
+            cdylib("missing"),
+            Product {
+                package: "cli".to_string(),
+                name: "cli".to_string(),
+                kind: ArtifactKind::Bin,
+            },
+        ];
+
+        for triple in ["aarch64-linux-android", "x86_64-linux-android"] {
+            let target = Target::from_triple(triple).unwrap();
+            let exported = export
+                .export(&target, "release", &target_dir, &products)
+                .unwrap();
+            assert_eq!(exported.len(), 1);
+        }
+        let linux = Target::from_triple("x86_64-unknown-linux-gnu").unwrap();
+        assert!(export
+            .export(&linux, "release", &target_dir, &products)
+            .unwrap()
+            .is_empty());
+
+        let jni = export.dir().join("jniLibs");
+        assert_eq!(
+            fs::read_to_string(jni.join("arm64-v8a/libcore_ffi.so")).unwrap(),
+            "aarch64-linux-android"
+        );
+        assert!(jni.join("x86_64/libcore_ffi.so").is_file());
+
+        let cmake = fs::read_to_string(export.dir().join(CMAKE_FILE)).unwrap();
+        assert!(cmake.contains("add_library(rust::core_ffi SHARED IMPORTED)"));
+        assert!(cmake.contains("${XCARGO_JNI_LIBS}/${ANDROID_ABI}/libcore_ffi.so"));
+
+        let config: toml::Value =
+            toml::from_str(&fs::read_to_string(export.dir().join(CONFIG_FILE)).unwrap()).unwrap();
+        assert_eq!(config["platform"].as_integer(), Some(21));
+        assert_eq!(
+            config["abis"]["arm64-v8a"].as_str(),
+            Some("aarch64-linux-android")
+        );
+        assert_eq!(
+            config["libraries"]["libcore_ffi.so"],
+            toml::Value::Array(vec!["arm64-v8a".into(), "x86_64".into()])
+        );
+    }
+
+    #[cfg(feature = "android-aar")]
+    #[test]
+    fn test_aar() {
+        let dir = TempDir::new().unwrap();
+        let target_dir = dir.path().join("target");
+        build(&target_dir, "aarch64-linux-android", "libcore.so");
+        let config = AndroidExportConfig {
+            dir: Some(dir.path().join("android").display().to_string()),
+            aar: true,
+            package: Some("com.example.core".to_string()),
+            min_sdk: 24,
+            require_16k_pages: false,
+        };
+        let export = AndroidExport::new(&config).unwrap();
+        let target = Target::from_triple("aarch64-linux-android").unwrap();
+        export
+            .export(&target, "release", &target_dir, &[cdylib("core")])
+            .unwrap();
+
+        let file = fs::File::open(export.dir().join("com.example.core.aar")).unwrap();
+        let mut aar = zip::ZipArchive::new(file).unwrap();
+        let mut names: Vec<&str> = aar.file_names().collect();
+        names.sort_unstable();
+        assert_eq!(
+            names,
+            [
+                "AndroidManifest.xml",
+                "R.txt",
+                "classes.jar",
+                "jni/arm64-v8a/libcore.so"
+            ]
+        );
+        let mut manifest = String::new();
+        std::io::Read::read_to_string(
+            &mut aar.by_name("AndroidManifest.xml").unwrap(),
+            &mut manifest,
+        )
+        .unwrap();
+        assert!(manifest.contains("package=\"com.example.core\""));
+        assert!(manifest.contains("android:minSdkVersion=\"24\""));
+    }
+}
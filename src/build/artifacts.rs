@@ -0,0 +1,481 @@
+//! Stable artifact output directory
+//!
+//! With `[artifacts] dir = "dist"`, the final binaries and libraries of a
+//! build are copied (or hardlinked) from cargo's target directory into
+//! `dist/<triple>/<profile>/`, and `dist/artifacts.json` describes every
+//! file placed there, including the outcome of its pre-publish
+//! [gates](super::gates).
+
+use crate::error::{Error, Result};
+use crate::output::helpers;
+use crate::paths;
+use crate::state;
+use crate::target::Target;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::gates::GateResult;
+
+/// Name of the manifest written to the artifact directory
+pub const MANIFEST_FILE: &str = "artifacts.json";
+
+/// Manifest format version
+const MANIFEST_VERSION: u32 = 1;
+
+/// Kind of build product
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactKind {
+    /// Executable
+    Bin,
+    /// C-compatible dynamic library
+    Cdylib,
+    /// C-compatible static library
+    Staticlib,
+    /// Example executable (`examples/`)
+    Example,
+}
+
+impl ArtifactKind {
+    /// Map a cargo target kind to an artifact kind
+    fn from_cargo(kind: &str) -> Option<Self> {
+        match kind {
+            "bin" => Some(Self::Bin),
+            "cdylib" => Some(Self::Cdylib),
+            "staticlib" => Some(Self::Staticlib),
+            "example" => Some(Self::Example),
+            _ => None,
+        }
+    }
+}
+
+/// A product a workspace package declares
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Product {
+    /// Package the product belongs to
+    pub package: String,
+    /// Cargo target name
+    pub name: String,
+    /// Product kind
+    pub kind: ArtifactKind,
+}
+
+impl Product {
+    /// File name cargo gives this product for `target`
+    #[must_use]
+    pub fn file_name(&self, target: &Target) -> String {
+        let windows = target.os == "windows";
+        let wasm = target.arch.starts_with("wasm");
+        let lib = self.name.replace('-', "_");
+
+        match self.kind {
+            ArtifactKind::Bin | ArtifactKind::Example if windows => format!("{}.exe", self.name),
+            ArtifactKind::Bin | ArtifactKind::Example if wasm => format!("{}.wasm", self.name),
+            ArtifactKind::Bin | ArtifactKind::Example => self.name.clone(),
+            ArtifactKind::Cdylib if windows => format!("{lib}.dll"),
+            ArtifactKind::Cdylib if wasm => format!("{lib}.wasm"),
+            ArtifactKind::Cdylib if target.vendor == "apple" => format!("lib{lib}.dylib"),
+            ArtifactKind::Cdylib => format!("lib{lib}.so"),
+            ArtifactKind::Staticlib if target.env.as_deref() == Some("msvc") => {
+                format!("{lib}.lib")
+            }
+            ArtifactKind::Staticlib => format!("lib{lib}.a"),
+        }
+    }
+}
+
+/// A file placed in the artifact directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Artifact {
+    /// Target triple
+    pub target: String,
+    /// Build profile ("debug" or "release")
+    pub profile: String,
+    /// Package that produced the file
+    pub package: String,
+    /// Cargo target name
+    pub name: String,
+    /// Product kind
+    pub kind: ArtifactKind,
+    /// Path relative to the artifact directory, `/`-separated
+    pub path: String,
+    /// Path the file was taken from
+    pub source: PathBuf,
+    /// File size in bytes
+    pub size: u64,
+    /// Outcome of each pre-publish gate, empty if none ran
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gates: Vec<GateResult>,
+}
+
+impl Artifact {
+    /// Whether every gate that ran on the artifact passed
+    #[must_use]
+    pub fn passed_gates(&self) -> bool {
+        self.gates.iter().all(|gate| gate.passed)
+    }
+}
+
+/// Machine-readable list of everything in the artifact directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactManifest {
+    /// Manifest format version
+    pub version: u32,
+    /// All artifacts, across targets and profiles
+    pub artifacts: Vec<Artifact>,
+}
+
+impl Default for ArtifactManifest {
+    fn default() -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            artifacts: Vec::new(),
+        }
+    }
+}
+
+impl ArtifactManifest {
+    /// Load the manifest from `dir`, or an empty one if there is none
+    ///
+    /// A manifest that cannot be parsed or has a newer format version is set
+    /// aside and rebuilt as targets are collected again.
+    #[must_use]
+    pub fn load(dir: &Path) -> Self {
+        let path = dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Self::default();
+        }
+
+        let parsed = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| {
+                serde_json::from_str::<Self>(&contents).map_err(|e| e.to_string())
+            });
+        match parsed {
+            Ok(manifest) if manifest.version <= MANIFEST_VERSION => manifest,
+            Ok(manifest) => {
+                state::discard(
+                    &path,
+                    "artifact manifest",
+                    &format!("has a newer format (version {})", manifest.version),
+                );
+                Self::default()
+            }
+            Err(e) => {
+                state::discard(&path, "artifact manifest", &format!("is unreadable ({e})"));
+                Self::default()
+            }
+        }
+    }
+
+    /// Write the manifest to `dir`
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Config(format!("Failed to serialize artifact manifest: {e}")))?;
+        state::write_atomic(&dir.join(MANIFEST_FILE), json)
+    }
+
+    /// Replace the entries for one target and profile
+    pub fn replace(&mut self, target: &str, profile: &str, artifacts: Vec<Artifact>) {
+        self.artifacts
+            .retain(|a| a.target != target || a.profile != profile);
+        self.artifacts.extend(artifacts);
+        self.artifacts.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+}
+
+/// Copies build products into the artifact directory
+pub struct ArtifactCollector {
+    dir: PathBuf,
+    hardlink: bool,
+}
+
+impl ArtifactCollector {
+    /// Create a collector for `dir`; `mode` is "copy" or "hardlink"
+    pub fn new(dir: impl Into<PathBuf>, mode: &str) -> Self {
+        Self {
+            dir: dir.into(),
+            hardlink: mode == "hardlink",
+        }
+    }
+
+    /// Artifact directory
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Place the products built in `target_dir/<triple>/<profile>/` and
+    /// update the manifest
+    ///
+    /// Products that were not built (e.g., excluded with `-p`) are skipped.
+    /// When two packages produce the same file name, later ones are
+    /// prefixed with their package name.
+    pub fn collect(
+        &self,
+        target: &Target,
+        profile: &str,
+        target_dir: &Path,
+        products: &[Product],
+    ) -> Result<Vec<Artifact>> {
+        let build_dir = target_dir.join(&target.triple).join(profile);
+        let out_dir = self.dir.join(&target.triple).join(profile);
+
+        let mut artifacts = Vec::new();
+        let mut used = HashSet::new();
+        for product in products {
+            let file = product.file_name(target);
+            let source = if product.kind == ArtifactKind::Example {
+                build_dir.join("examples").join(&file)
+            } else {
+                build_dir.join(&file)
+            };
+            if !paths::long_path(&source).is_file() {
+                continue;
+            }
+
+            let mut dest_name = file.clone();
+            if !used.insert(dest_name.clone()) {
+                dest_name = format!("{}-{file}", product.package);
+                helpers::warning(format!(
+                    "{file} is produced by more than one package, storing {}'s as {dest_name}",
+                    product.package
+                ));
+                used.insert(dest_name.clone());
+            }
+
+            fs::create_dir_all(paths::long_path(&out_dir))?;
+            let dest = out_dir.join(&dest_name);
+            self.place(&source, &dest)?;
+
+            artifacts.push(Artifact {
+                target: target.triple.clone(),
+                profile: profile.to_string(),
+                package: product.package.clone(),
+                name: product.name.clone(),
+                kind: product.kind,
+                path: format!("{}/{profile}/{dest_name}", target.triple),
+                size: fs::metadata(paths::long_path(&dest))?.len(),
+                source,
+                gates: Vec::new(),
+            });
+        }
+
+        if !artifacts.is_empty() {
+            let mut manifest = ArtifactManifest::load(&self.dir);
+            manifest.replace(&target.triple, profile, artifacts.clone());
+            manifest.save(&self.dir)?;
+        }
+
+        Ok(artifacts)
+    }
+
+    /// Copy or hardlink `source` to `dest`, replacing any previous file
+    fn place(&self, source: &Path, dest: &Path) -> Result<()> {
+        let (source, dest) = (paths::long_path(source), paths::long_path(dest));
+        if dest.exists() {
+            fs::remove_file(&dest)?;
+        }
+        // Hardlinks fail across filesystems; fall back to a copy
+        if !self.hardlink || fs::hard_link(&source, &dest).is_err() {
+            fs::copy(&source, &dest)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    target_directory: PathBuf,
+    #[serde(default)]
+    workspace_root: PathBuf,
+    packages: Vec<MetadataPackage>,
+}
+
+#[derive(Deserialize)]
+struct MetadataPackage {
+    name: String,
+    #[serde(default)]
+    version: String,
+    targets: Vec<MetadataTarget>,
+}
+
+#[derive(Deserialize)]
+struct MetadataTarget {
+    name: String,
+    kind: Vec<String>,
+}
+
+/// Cargo's target directory and the products of all workspace packages
+pub fn workspace_products() -> Result<(PathBuf, Vec<Product>)> {
+    parse_metadata(&cargo_metadata()?)
+}
+
+/// Root directory of the current workspace
+pub fn workspace_root() -> Result<PathBuf> {
+    let metadata: Metadata = serde_json::from_str(&cargo_metadata()?)
+        .map_err(|e| Error::Build(format!("Failed to parse cargo metadata: {e}")))?;
+    Ok(metadata.workspace_root)
+}
+
+/// Versions of the workspace packages, by package name
+pub fn workspace_versions() -> Result<HashMap<String, String>> {
+    let metadata: Metadata = serde_json::from_str(&cargo_metadata()?)
+        .map_err(|e| Error::Build(format!("Failed to parse cargo metadata: {e}")))?;
+    Ok(metadata
+        .packages
+        .into_iter()
+        .map(|package| (package.name, package.version))
+        .collect())
+}
+
+/// `cargo metadata` output for the workspace, without dependencies
+fn cargo_metadata() -> Result<String> {
+    let output = Command::new("cargo")
+        .args(["metadata", "--no-deps", "--format-version", "1"])
+        .output()
+        .map_err(|e| Error::Build(format!("Failed to run cargo metadata: {e}")))?;
+    if !output.status.success() {
+        return Err(Error::Build(format!(
+            "cargo metadata failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Binaries and shared libraries built for `target` in `profile`
+pub fn built_binaries(target: &Target, profile: &str) -> Result<Vec<PathBuf>> {
+    let (target_dir, products) = workspace_products()?;
+    let build_dir = target_dir.join(&target.triple).join(profile);
+    Ok(products
+        .iter()
+        .filter(|product| matches!(product.kind, ArtifactKind::Bin | ArtifactKind::Cdylib))
+        .map(|product| build_dir.join(product.file_name(target)))
+        .filter(|path| path.is_file())
+        .collect())
+}
+
+/// Parse `cargo metadata` output into the target directory and products
+pub fn parse_metadata(json: &str) -> Result<(PathBuf, Vec<Product>)> {
+    let metadata: Metadata = serde_json::from_str(json)
+        .map_err(|e| Error::Build(format!("Failed to parse cargo metadata: {e}")))?;
+
+    let products = metadata
+        .packages
+        .iter()
+        .flat_map(|package| {
+            package.targets.iter().flat_map(move |target| {
+                target
+                    .kind
+                    .iter()
+                    .filter_map(|kind| ArtifactKind::from_cargo(kind))
+                    .map(move |kind| Product {
+                        package: package.name.clone(),
+                        name: target.name.clone(),
+                        kind,
+                    })
+            })
+        })
+        .collect();
+
+    Ok((metadata.target_directory, products))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn product(package: &str, name: &str, kind: ArtifactKind) -> Product {
+        Product {
+            package: package.to_string(),
+            name: name.to_string(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_file_names() {
+        let linux = Target::from_triple("x86_64-unknown-linux-gnu").unwrap();
+        let windows = Target::from_triple("x86_64-pc-windows-msvc").unwrap();
+        let macos = Target::from_triple("aarch64-apple-darwin").unwrap();
+
+        let bin = product("app", "my-app", ArtifactKind::Bin);
+        assert_eq!(bin.file_name(&linux), "my-app");
+        assert_eq!(bin.file_name(&windows), "my-app.exe");
+        let example = product("app", "demo", ArtifactKind::Example);
+        assert_eq!(example.file_name(&windows), "demo.exe");
+
+        let dylib = product("ffi", "my-ffi", ArtifactKind::Cdylib);
+        assert_eq!(dylib.file_name(&linux), "libmy_ffi.so");
+        assert_eq!(dylib.file_name(&macos), "libmy_ffi.dylib");
+        assert_eq!(dylib.file_name(&windows), "my_ffi.dll");
+
+        let staticlib = product("ffi", "my-ffi", ArtifactKind::Staticlib);
+        assert_eq!(staticlib.file_name(&linux), "libmy_ffi.a");
+        assert_eq!(staticlib.file_name(&windows), "my_ffi.lib");
+    }
+
+    #[test]
+    fn test_parse_metadata() {
+        let json = r#"{
+            "target_directory": "/work/target",
+            "packages": [{
+                "name": "app",
+                "targets": [
+                    {"name": "app", "kind": ["bin"]},
+                    {"name": "app", "kind": ["lib"]},
+                    {"name": "ffi", "kind": ["cdylib", "staticlib"]},
+                    {"name": "demo", "kind": ["example"]}
+                ]
+            }]
+        }"#;
+        let (target_dir, products) = parse_metadata(json).unwrap();
+        assert_eq!(target_dir, PathBuf::from("/work/target"));
+        assert_eq!(products.len(), 4);
+        assert_eq!(products[0].kind, ArtifactKind::Bin);
+        assert_eq!(products[3].kind, ArtifactKind::Example);
+    }
+
+    #[test]
+    fn test_collect_and_manifest() {
+        let temp = TempDir::new().unwrap();
+        let target = Target::from_triple("x86_64-unknown-linux-gnu").unwrap();
+        let build_dir = temp.path().join("target/x86_64-unknown-linux-gnu/release");
+        fs::create_dir_all(&build_dir).unwrap();
+        fs::write(build_dir.join("app"), b"binary").unwrap();
+
+        let products = vec![
+            product("app", "app", ArtifactKind::Bin),
+            product("tools", "app", ArtifactKind::Bin),
+            product("app", "missing", ArtifactKind::Bin),
+        ];
+        let collector = ArtifactCollector::new(temp.path().join("dist"), "hardlink");
+        let artifacts = collector
+            .collect(&target, "release", &temp.path().join("target"), &products)
+            .unwrap();
+
+        assert_eq!(artifacts.len(), 2);
+        assert_eq!(artifacts[0].path, "x86_64-unknown-linux-gnu/release/app");
+        assert_eq!(
+            artifacts[1].path,
+            "x86_64-unknown-linux-gnu/release/tools-app"
+        );
+        assert_eq!(artifacts[0].size, 6);
+
+        let manifest = ArtifactManifest::load(collector.dir());
+        assert_eq!(manifest.artifacts.len(), 2);
+
+        // Collecting again replaces the entries instead of duplicating them
+        collector
+            .collect(&target, "release", &temp.path().join("target"), &products)
+            .unwrap();
+        let manifest = ArtifactManifest::load(collector.dir());
+        assert_eq!(manifest.artifacts.len(), 2);
+    }
+}
@@ -0,0 +1,117 @@
+//! `xcargo clean`: remove per-target build output and xcargo state
+
+use crate::error::Result;
+use crate::paths;
+use crate::target::Target;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Something removed by `xcargo clean`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cleaned {
+    /// What was removed (e.g., a path or image name)
+    pub item: String,
+    /// Disk space reclaimed, in bytes
+    pub bytes: u64,
+}
+
+/// Total size of all files below `path`
+#[must_use]
+pub fn dir_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(std::fs::Metadata::is_file)
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Per-target output directories (`target/<triple>`) in `target_dir`
+///
+/// Cargo's own `debug`, `release`, `doc` etc. are not target triples and
+/// are left out.
+#[must_use]
+pub fn target_triples(target_dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(target_dir) else {
+        return Vec::new();
+    };
+
+    let mut triples: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.contains('-') && Target::from_triple(name).is_ok())
+        .collect();
+    triples.sort();
+    triples
+}
+
+/// Remove `target_dir/<triple>`, returning `None` if it does not exist
+pub fn clean_target(target_dir: &Path, triple: &str) -> Result<Option<Cleaned>> {
+    remove_dir(&target_dir.join(triple))
+}
+
+/// Remove a directory and report its size, returning `None` if missing
+pub fn remove_dir(path: &Path) -> Result<Option<Cleaned>> {
+    if !path.is_dir() {
+        return Ok(None);
+    }
+
+    let bytes = dir_size(path);
+    fs::remove_dir_all(paths::long_path(path))?;
+    Ok(Some(Cleaned {
+        item: path.display().to_string(),
+        bytes,
+    }))
+}
+
+/// Cargo's target directory for the current workspace
+pub fn target_directory() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("CARGO_TARGET_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    super::artifacts::workspace_products().map(|(dir, _)| dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_target_triples_and_clean() {
+        let temp = TempDir::new().unwrap();
+        let target_dir = temp.path();
+        for dir in [
+            "debug",
+            "release",
+            "x86_64-pc-windows-gnu/debug",
+            "aarch64-unknown-linux-gnu/release",
+        ] {
+            fs::create_dir_all(target_dir.join(dir)).unwrap();
+        }
+        fs::write(
+            target_dir.join("x86_64-pc-windows-gnu/debug/app.exe"),
+            [0u8; 100],
+        )
+        .unwrap();
+
+        assert_eq!(
+            target_triples(target_dir),
+            vec!["aarch64-unknown-linux-gnu", "x86_64-pc-windows-gnu"]
+        );
+
+        let cleaned = clean_target(target_dir, "x86_64-pc-windows-gnu")
+            .unwrap()
+            .unwrap();
+        assert_eq!(cleaned.bytes, 100);
+        assert!(!target_dir.join("x86_64-pc-windows-gnu").exists());
+        assert!(target_dir.join("debug").exists());
+
+        assert!(clean_target(target_dir, "x86_64-pc-windows-gnu")
+            .unwrap()
+            .is_none());
+    }
+}
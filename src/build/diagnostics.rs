@@ -0,0 +1,302 @@
+//! Compiler errors summarized across the targets of a build
+//!
+//! Multi-target builds run cargo with JSON messages and record each
+//! target's compiler errors and warnings. The same error on several
+//! targets (e.g., an item missing behind `cfg(windows)`) is listed once at
+//! the end with every target it broke, "failed on x86_64-pc-windows-gnu
+//! and aarch64-pc-windows-msvc", instead of being repeated per target.
+//!
+//! Parallel builds hold back the compiler output while building, since
+//! the targets' messages would interleave, and show each error in full in
+//! the summary.
+
+use crate::output::{ci, colors, helpers, is_plain};
+use serde_json::Value;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Compiler messages that only count the others
+const TALLIES: &[&str] = &["aborting due to", "warning emitted", "warnings emitted"];
+
+/// One distinct diagnostic and the targets that reported it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SummaryEntry {
+    /// Error rather than warning
+    pub is_error: bool,
+    /// Error code (e.g., `E0433`), if any
+    pub code: Option<String>,
+    /// Message without the source snippet
+    pub message: String,
+    /// `file:line:column` of the primary span, if any
+    pub location: Option<String>,
+    /// Text rustc printed for it
+    pub rendered: String,
+    /// Targets that reported it, in the order they did
+    pub targets: Vec<String>,
+}
+
+impl SummaryEntry {
+    /// `error[E0433]: message` as rustc starts it
+    #[must_use]
+    pub fn headline(&self) -> String {
+        let level = if self.is_error { "error" } else { "warning" };
+        match &self.code {
+            Some(code) => format!("{level}[{code}]: {}", self.message),
+            None => format!("{level}: {}", self.message),
+        }
+    }
+
+    fn same_as(&self, other: &SummaryEntry) -> bool {
+        self.is_error == other.is_error
+            && self.code == other.code
+            && self.message == other.message
+            && self.location == other.location
+    }
+}
+
+#[derive(Debug, Default)]
+struct Collected {
+    active: bool,
+    live: bool,
+    entries: Vec<SummaryEntry>,
+}
+
+/// Diagnostics of a multi-target build, shared by the builds of its
+/// targets
+///
+/// Clones share what was collected.
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticSummary {
+    collected: Arc<Mutex<Collected>>,
+}
+
+impl DiagnosticSummary {
+    /// An empty summary that is not collecting
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start collecting, forgetting earlier diagnostics
+    ///
+    /// With `live`, compiler output is still printed as it arrives.
+    pub fn start(&self, live: bool) {
+        *self.lock() = Collected {
+            active: true,
+            live,
+            entries: Vec::new(),
+        };
+    }
+
+    /// Whether builds should run cargo with JSON messages for the summary
+    #[must_use]
+    pub fn is_collecting(&self) -> bool {
+        self.lock().active
+    }
+
+    /// Whether compiler output is printed while building
+    #[must_use]
+    pub fn is_live(&self) -> bool {
+        let collected = self.lock();
+        !collected.active || collected.live
+    }
+
+    /// Forget what `target` reported, before it is built again
+    pub fn clear_target(&self, target: &str) {
+        let mut collected = self.lock();
+        for entry in &mut collected.entries {
+            entry.targets.retain(|t| t != target);
+        }
+        collected.entries.retain(|entry| !entry.targets.is_empty());
+    }
+
+    /// Record one line of cargo's JSON output for `target`
+    ///
+    /// Lines that are not compiler errors or warnings are ignored.
+    pub fn record_line(&self, target: &str, line: &str) {
+        let Ok(message) = serde_json::from_str::<Value>(line) else {
+            return;
+        };
+        if message["reason"].as_str() != Some("compiler-message") {
+            return;
+        }
+        if let Some(entry) = Self::entry(target, &message["message"]) {
+            self.add(entry);
+        }
+    }
+
+    /// Distinct diagnostics so far, errors first
+    #[must_use]
+    pub fn entries(&self) -> Vec<SummaryEntry> {
+        let mut entries = self.lock().entries.clone();
+        entries.sort_by_key(|entry| !entry.is_error);
+        entries
+    }
+
+    /// Stop collecting and print the errors and a count of the warnings
+    ///
+    /// Errors are shown in full when they were not printed while building.
+    pub fn finish(&self) {
+        let live = self.is_live();
+        let entries = self.entries();
+        self.lock().active = false;
+
+        let errors: Vec<&SummaryEntry> = entries.iter().filter(|e| e.is_error).collect();
+        let warnings = entries.len() - errors.len();
+        if errors.is_empty() && warnings == 0 {
+            return;
+        }
+
+        helpers::section("Compiler Errors by Target");
+        for entry in &errors {
+            if live {
+                helpers::error(entry.headline());
+                if let Some(location) = &entry.location {
+                    println!("    --> {location}");
+                }
+            } else {
+                eprintln!("{}", rendered(&entry.rendered));
+            }
+            helpers::hint(format!("failed on {}", join(&entry.targets)));
+        }
+        if warnings > 0 {
+            helpers::warning(format!(
+                "{warnings} distinct warning(s), see the output of each target"
+            ));
+        }
+    }
+
+    fn add(&self, entry: SummaryEntry) {
+        let mut collected = self.lock();
+        match collected.entries.iter_mut().find(|e| e.same_as(&entry)) {
+            Some(existing) => {
+                for target in entry.targets {
+                    if !existing.targets.contains(&target) {
+                        existing.targets.push(target);
+                    }
+                }
+            }
+            None => collected.entries.push(entry),
+        }
+    }
+
+    fn entry(target: &str, message: &Value) -> Option<SummaryEntry> {
+        let is_error = match message["level"].as_str()? {
+            "error" | "error: internal compiler error" => true,
+            "warning" => false,
+            _ => return None,
+        };
+        let text = message["message"].as_str()?;
+        if message["spans"].as_array().map_or(true, Vec::is_empty)
+            && TALLIES.iter().any(|tally| text.contains(tally))
+        {
+            return None;
+        }
+
+        let location = ci::Diagnostic::from_message(message)
+            .map(|d| format!("{}:{}:{}", d.file, d.line, d.column));
+        Some(SummaryEntry {
+            is_error,
+            code: message["code"]["code"].as_str().map(str::to_string),
+            message: text.to_string(),
+            location,
+            rendered: message["rendered"]
+                .as_str()
+                .unwrap_or(text)
+                .trim_end()
+                .to_string(),
+            targets: vec![target.to_string()],
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Collected> {
+        self.collected
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Rendered compiler output, without colors in plain mode
+fn rendered(text: &str) -> String {
+    if is_plain() {
+        crate::output::strip_ansi(text)
+    } else {
+        format!("{text}{}", colors::RESET)
+    }
+}
+
+/// "a", "a and b", "a, b and c"
+fn join(targets: &[String]) -> String {
+    match targets {
+        [] => String::new(),
+        [only] => only.clone(),
+        [rest @ .., last] => format!("{} and {last}", rest.join(", ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_line(code: &str, line: u64) -> String {
+        format!(
+            r#"{{"reason":"compiler-message","message":{{"message":"failed to resolve: use of undeclared crate `winapi`","code":{{"code":"{code}"}},"level":"error","spans":[{{"file_name":"src/main.rs","line_start":{line},"line_end":{line},"column_start":5,"is_primary":true}}],"rendered":"error[{code}]: failed to resolve\n"}}}}"#
+        )
+    }
+
+    #[test]
+    fn test_deduplicates_across_targets() {
+        let summary = DiagnosticSummary::new();
+        summary.start(true);
+        assert!(summary.is_collecting());
+
+        summary.record_line("x86_64-pc-windows-gnu", &error_line("E0433", 3));
+        summary.record_line("x86_64-unknown-linux-musl", &error_line("E0433", 3));
+        summary.record_line("x86_64-unknown-linux-musl", &error_line("E0433", 9));
+        summary.record_line(
+            "x86_64-pc-windows-gnu",
+            r#"{"reason":"compiler-message","message":{"message":"aborting due to 1 previous error","code":null,"level":"error","spans":[],"rendered":"error: aborting due to 1 previous error\n"}}"#,
+        );
+        summary.record_line("x86_64-pc-windows-gnu", r#"{"reason":"build-finished"}"#);
+        summary.record_line("x86_64-pc-windows-gnu", "not json");
+
+        let entries = summary.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].targets,
+            ["x86_64-pc-windows-gnu", "x86_64-unknown-linux-musl"]
+        );
+        assert_eq!(entries[0].location.as_deref(), Some("src/main.rs:3:5"));
+        assert_eq!(
+            entries[0].headline(),
+            "error[E0433]: failed to resolve: use of undeclared crate `winapi`"
+        );
+        assert_eq!(entries[1].targets, ["x86_64-unknown-linux-musl"]);
+
+        // A retried target starts over
+        summary.clear_target("x86_64-unknown-linux-musl");
+        let entries = summary.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].targets, ["x86_64-pc-windows-gnu"]);
+
+        summary.finish();
+        assert!(!summary.is_collecting());
+    }
+
+    #[test]
+    fn test_live_output() {
+        let summary = DiagnosticSummary::new();
+        assert!(summary.is_live());
+        summary.start(false);
+        assert!(!summary.is_live());
+        summary.start(true);
+        assert!(summary.is_live());
+    }
+
+    #[test]
+    fn test_join() {
+        let targets = |names: &[&str]| names.iter().map(|n| (*n).to_string()).collect::<Vec<_>>();
+        assert_eq!(join(&targets(&["a"])), "a");
+        assert_eq!(join(&targets(&["a", "b"])), "a and b");
+        assert_eq!(join(&targets(&["a", "b", "c"])), "a, b and c");
+    }
+}
@@ -0,0 +1,408 @@
+//! Firmware images and flashing for embedded targets
+//!
+//! Bare-metal ARM (`thumbv*`) and RISC-V (`riscv*`) builds produce ELF
+//! files. With `[embedded] formats`, each build also writes the images
+//! bootloaders and flashing tools take next to every binary:
+//!
+//! - `bin`, the raw memory image (`llvm-objcopy -O binary`)
+//! - `hex`, Intel HEX (`llvm-objcopy -O ihex`)
+//! - `uf2`, for drag-and-drop UF2 bootloaders (RP2040, SAMD, nRF52),
+//!   made from the Intel HEX and tagged with `uf2_family`
+//!
+//! `xcargo flash` builds and then runs `[embedded] flash`, probe-rs by
+//! default, with `{elf}`, `{bin}`, `{hex}`, `{uf2}`, `{chip}` and
+//! `{target}` replaced:
+//!
+//! ```toml
+//! [embedded]
+//! formats = ["bin", "hex"]
+//! flash = ["dfu-util", "-a", "0", "-s", "0x08000000:leave", "-D", "{bin}"]
+//!
+//! [targets."thumbv7em-none-eabihf"]
+//! chip = "STM32F407VGTx"
+//! ```
+
+use crate::error::{Error, Result};
+use crate::target::Target;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Flash command used unless `[embedded] flash` is set
+pub const DEFAULT_FLASH: &[&str] = &["probe-rs", "download", "--chip", "{chip}", "{elf}"];
+
+/// UF2 family IDs by board family name
+const UF2_FAMILIES: &[(&str, u32)] = &[
+    ("rp2040", 0xe48b_ff56),
+    ("rp2350", 0xe48b_ff59),
+    ("samd21", 0x68ed_2b88),
+    ("samd51", 0x5511_4460),
+    ("nrf52840", 0xada5_2840),
+    ("stm32f4", 0x5775_5a57),
+];
+
+/// Bytes of firmware in each UF2 block
+const UF2_PAYLOAD: u32 = 256;
+
+/// Image formats firmware can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Raw memory image
+    Bin,
+    /// Intel HEX
+    Hex,
+    /// USB Flashing Format
+    Uf2,
+}
+
+impl ImageFormat {
+    /// Every format, in the order they are written
+    pub const ALL: [Self; 3] = [Self::Bin, Self::Hex, Self::Uf2];
+
+    /// Format named `name` ("bin", "hex" or "uf2")
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extension() == name)
+    }
+
+    /// File extension, which is also the format's name
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Bin => "bin",
+            Self::Hex => "hex",
+            Self::Uf2 => "uf2",
+        }
+    }
+
+    /// Path of this image of `elf`
+    #[must_use]
+    pub fn path(self, elf: &Path) -> PathBuf {
+        elf.with_extension(self.extension())
+    }
+}
+
+/// Whether `target` is a bare-metal ARM or RISC-V target
+#[must_use]
+pub fn is_embedded(target: &Target) -> bool {
+    let bare_metal = target.vendor == "none" || target.os == "none";
+    bare_metal && (target.triple_arch().starts_with("thumb") || target.arch.starts_with("riscv"))
+}
+
+/// UF2 family ID for `name`: a known family or a number like `0xe48bff56`
+pub fn uf2_family(name: &str) -> Result<u32> {
+    if let Some((_, id)) = UF2_FAMILIES
+        .iter()
+        .find(|(family, _)| family.eq_ignore_ascii_case(name))
+    {
+        return Ok(*id);
+    }
+    let digits = name.trim_start_matches("0x").trim_start_matches("0X");
+    u32::from_str_radix(digits, 16).map_err(|_| {
+        Error::Config(format!(
+            "Unknown UF2 family '{name}': use one of {} or an ID like 0xe48bff56",
+            UF2_FAMILIES
+                .iter()
+                .map(|(family, _)| *family)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    })
+}
+
+/// llvm-objcopy, from `PATH` (also as cargo-binutils' `rust-objcopy`) or
+/// the toolchain's `llvm-tools` component
+pub fn find_objcopy() -> Result<PathBuf> {
+    for program in ["llvm-objcopy", "rust-objcopy"] {
+        if let Ok(path) = which::which(program) {
+            return Ok(path);
+        }
+    }
+
+    let sysroot = Command::new("rustc")
+        .args(["--print", "sysroot"])
+        .output()
+        .ok()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+    if let (Some(sysroot), Ok(host)) = (sysroot, Target::detect_host()) {
+        let path = Path::new(&sysroot)
+            .join("lib/rustlib")
+            .join(&host.triple)
+            .join("bin")
+            .join(format!("llvm-objcopy{}", std::env::consts::EXE_SUFFIX));
+        if path.is_file() {
+            return Ok(path);
+        }
+    }
+    Err(Error::Toolchain(
+        "llvm-objcopy not found: rustup component add llvm-tools".to_string(),
+    ))
+}
+
+/// Write `format`'s image of `elf` next to it with `objcopy`, returning its
+/// path; UF2 images need the board's `family`
+pub fn write_image(
+    objcopy: &Path,
+    elf: &Path,
+    format: ImageFormat,
+    family: Option<u32>,
+) -> Result<PathBuf> {
+    let output = format.path(elf);
+    match format {
+        ImageFormat::Bin => run_objcopy(objcopy, "binary", elf, &output)?,
+        ImageFormat::Hex => run_objcopy(objcopy, "ihex", elf, &output)?,
+        ImageFormat::Uf2 => {
+            let hex = elf.with_extension("uf2.hex");
+            run_objcopy(objcopy, "ihex", elf, &hex)?;
+            let text = fs::read_to_string(&hex);
+            let _ = fs::remove_file(&hex);
+            fs::write(&output, uf2(&parse_ihex(&text?)?, family))?;
+        }
+    }
+    Ok(output)
+}
+
+/// The flash command for `elf` from `template`, with its placeholders
+/// replaced
+pub fn flash_command(
+    template: &[String],
+    elf: &Path,
+    chip: Option<&str>,
+    target: &str,
+) -> Result<Vec<String>> {
+    if template.is_empty() {
+        return Err(Error::Config("[embedded] flash is empty".to_string()));
+    }
+    let needs_chip = template.iter().any(|arg| arg.contains("{chip}"));
+    let chip = match chip {
+        Some(chip) => chip,
+        None if needs_chip => {
+            return Err(Error::Config(format!(
+                "Flashing {target} needs a chip: pass --chip or set [targets.\"{target}\"] chip"
+            )))
+        }
+        None => "",
+    };
+    Ok(template
+        .iter()
+        .map(|arg| {
+            let mut arg = arg
+                .replace("{elf}", &elf.display().to_string())
+                .replace("{chip}", chip)
+                .replace("{target}", target);
+            for format in ImageFormat::ALL {
+                arg = arg.replace(
+                    &format!("{{{}}}", format.extension()),
+                    &format.path(elf).display().to_string(),
+                );
+            }
+            arg
+        })
+        .collect())
+}
+
+/// Images `template` passes to the flash tool
+#[must_use]
+pub fn formats_used(template: &[String]) -> Vec<ImageFormat> {
+    ImageFormat::ALL
+        .into_iter()
+        .filter(|format| {
+            let placeholder = format!("{{{}}}", format.extension());
+            template.iter().any(|arg| arg.contains(&placeholder))
+        })
+        .collect()
+}
+
+fn run_objcopy(objcopy: &Path, format: &str, elf: &Path, output: &Path) -> Result<()> {
+    let result = Command::new(objcopy)
+        .args(["-O", format])
+        .arg(elf)
+        .arg(output)
+        .output()
+        .map_err(|e| Error::Toolchain(format!("Failed to run {}: {e}", objcopy.display())))?;
+    if result.status.success() {
+        Ok(())
+    } else {
+        Err(Error::Build(format!(
+            "objcopy -O {format} failed for {}: {}",
+            elf.display(),
+            String::from_utf8_lossy(&result.stderr).trim()
+        )))
+    }
+}
+
+/// Data records of an Intel HEX file, as address and bytes
+fn parse_ihex(text: &str) -> Result<Vec<(u32, Vec<u8>)>> {
+    let invalid =
+        |line: usize, why: &str| Error::Build(format!("Invalid Intel HEX line {line}: {why}"));
+    let mut base = 0u32;
+    let mut records = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let hex = line
+            .strip_prefix(':')
+            .ok_or_else(|| invalid(number, "no start code"))?;
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| {
+                hex.get(i..i + 2)
+                    .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+            })
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(|| invalid(number, "not hexadecimal"))?;
+        if bytes.len() < 5 || bytes.len() != usize::from(bytes[0]) + 5 {
+            return Err(invalid(number, "wrong length"));
+        }
+        if bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+            return Err(invalid(number, "bad checksum"));
+        }
+
+        let data = &bytes[4..bytes.len() - 1];
+        let offset = u32::from(u16::from_be_bytes([bytes[1], bytes[2]]));
+        match bytes[3] {
+            0x00 => records.push((base + offset, data.to_vec())),
+            0x01 => break,
+            0x02 if data.len() == 2 => {
+                base = u32::from(u16::from_be_bytes([data[0], data[1]])) << 4;
+            }
+            0x04 if data.len() == 2 => {
+                base = u32::from(u16::from_be_bytes([data[0], data[1]])) << 16;
+            }
+            0x03 | 0x05 => {}
+            _ => return Err(invalid(number, "unknown record")),
+        }
+    }
+    Ok(records)
+}
+
+/// UF2 image of `records`, in 256-byte blocks with gaps zero-filled
+fn uf2(records: &[(u32, Vec<u8>)], family: Option<u32>) -> Vec<u8> {
+    let mut pages: BTreeMap<u32, Vec<u8>> = BTreeMap::new();
+    for (address, data) in records {
+        for (i, byte) in data.iter().enumerate() {
+            let address = address + u32::try_from(i).unwrap_or(u32::MAX);
+            let page = pages
+                .entry(address - address % UF2_PAYLOAD)
+                .or_insert_with(|| vec![0; UF2_PAYLOAD as usize]);
+            page[(address % UF2_PAYLOAD) as usize] = *byte;
+        }
+    }
+
+    let count = u32::try_from(pages.len()).unwrap_or(u32::MAX);
+    let mut image = Vec::with_capacity(pages.len() * 512);
+    for (number, (address, page)) in (0u32..).zip(&pages) {
+        let words = [
+            0x0A32_4655,                               // first magic
+            0x9E5D_5157,                               // second magic
+            if family.is_some() { 0x2000 } else { 0 }, // family ID present
+            *address,
+            UF2_PAYLOAD,
+            number,
+            count,
+            family.unwrap_or(0),
+        ];
+        for word in words {
+            image.extend_from_slice(&word.to_le_bytes());
+        }
+        image.extend_from_slice(page);
+        image.resize(image.len() + 476 - page.len(), 0);
+        image.extend_from_slice(&0x0AB1_6F30_u32.to_le_bytes());
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(triple: &str) -> Target {
+        Target::from_triple(triple).unwrap()
+    }
+
+    #[test]
+    fn test_is_embedded() {
+        assert!(is_embedded(&target("thumbv7em-none-eabihf")));
+        assert!(is_embedded(&target("thumbv6m-none-eabi")));
+        assert!(is_embedded(&target("riscv32imac-unknown-none-elf")));
+        assert!(!is_embedded(&target("riscv64gc-unknown-linux-gnu")));
+        assert!(!is_embedded(&target("aarch64-unknown-linux-gnu")));
+    }
+
+    #[test]
+    fn test_uf2_family() {
+        assert_eq!(uf2_family("rp2040").unwrap(), 0xe48b_ff56);
+        assert_eq!(uf2_family("RP2040").unwrap(), 0xe48b_ff56);
+        assert_eq!(uf2_family("0x68ed2b88").unwrap(), 0x68ed_2b88);
+        assert!(uf2_family("esp8266").is_err());
+    }
+
+    #[test]
+    fn test_flash_command() {
+        let template: Vec<String> = DEFAULT_FLASH.iter().map(ToString::to_string).collect();
+        let elf = Path::new("target/thumbv7em-none-eabihf/release/blinky");
+        assert_eq!(
+            flash_command(
+                &template,
+                elf,
+                Some("STM32F407VGTx"),
+                "thumbv7em-none-eabihf"
+            )
+            .unwrap(),
+            [
+                "probe-rs",
+                "download",
+                "--chip",
+                "STM32F407VGTx",
+                "target/thumbv7em-none-eabihf/release/blinky"
+            ]
+        );
+        assert!(flash_command(&template, elf, None, "thumbv7em-none-eabihf").is_err());
+        assert!(formats_used(&template).is_empty());
+
+        let dfu: Vec<String> = ["dfu-util", "-D", "{bin}"].map(String::from).to_vec();
+        assert_eq!(
+            flash_command(&dfu, elf, None, "thumbv7em-none-eabihf").unwrap()[2],
+            "target/thumbv7em-none-eabihf/release/blinky.bin"
+        );
+        assert_eq!(formats_used(&dfu), [ImageFormat::Bin]);
+    }
+
+    #[test]
+    fn test_parse_ihex() {
+        let hex = ":020000041000EA\n:0400000001020304F2\n:020010000506E3\n:00000001FF\n";
+        assert_eq!(
+            parse_ihex(hex).unwrap(),
+            [(0x1000_0000, vec![1, 2, 3, 4]), (0x1000_0010, vec![5, 6])]
+        );
+        assert!(parse_ihex(":0400000001020304F3\n").is_err());
+        assert!(parse_ihex("0400000001020304F2\n").is_err());
+    }
+
+    #[test]
+    fn test_uf2() {
+        let records = [(0x1000_0000, vec![1, 2]), (0x1000_0100, vec![3])];
+        let image = uf2(&records, Some(0xe48b_ff56));
+        assert_eq!(image.len(), 1024);
+
+        let word = |block: usize, index: usize| {
+            let at = block * 512 + index * 4;
+            u32::from_le_bytes(image[at..at + 4].try_into().unwrap())
+        };
+        assert_eq!(word(0, 0), 0x0A32_4655);
+        assert_eq!(word(0, 2), 0x2000);
+        assert_eq!(word(0, 3), 0x1000_0000);
+        assert_eq!(word(1, 3), 0x1000_0100);
+        assert_eq!(word(1, 5), 1);
+        assert_eq!(word(1, 6), 2);
+        assert_eq!(word(0, 7), 0xe48b_ff56);
+        assert_eq!(word(0, 127), 0x0AB1_6F30);
+        assert_eq!(image[32..35], [1, 2, 0]);
+    }
+}
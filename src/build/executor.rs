@@ -1,16 +1,54 @@
 //! Build execution and orchestration
 
-use crate::config::Config;
+use crate::bench::{self, TargetBench};
+use crate::cache::{self, BuildCache, CacheKey, CompilerWrapper};
+use crate::cancel::{self, CancellationToken, RunLimits, Stream};
+use crate::config::{CargoTargetConfig, Config};
+use crate::coverage::{self, TargetCoverage};
+use crate::deps::{self, DepResolution, Lockfile};
+use crate::error::explain;
 use crate::error::{Error, Result};
-use crate::output::{helpers, tips};
+use crate::history::{
+    self,
+    runs::{self, Outcome, Run},
+};
+use crate::output::{self, ci, helpers, tips};
+use crate::plugin::{BuildCommand, BuildsSummary, PluginContext, PluginHook, PluginRegistry};
+use crate::provision::{self, Provision};
+use crate::support;
 use crate::target::Target;
+use crate::timings::{self, phases, Phase, Stopwatch, TargetTimings};
+use crate::toolchain::cargo::{Capability, CargoCapabilities};
+#[cfg(feature = "zig")]
 use crate::toolchain::zig::ZigToolchain;
-use crate::toolchain::ToolchainManager;
+use crate::toolchain::{self, apple, msvc, ToolchainManager};
+use crate::trace;
+use crate::verify;
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::process::Command;
-
-use super::options::{BuildOptions, CargoOperation};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::android::{self, AndroidExport};
+use super::artifacts::{self, ArtifactCollector};
+use super::diagnostics::DiagnosticSummary;
+use super::embedded::{self, ImageFormat};
+use super::gates::Gates;
+use super::glibc::{self, GlibcVersion};
+use super::matrix::MatrixRow;
+use super::nix::NixShell;
+use super::offline::{self, Missing};
+use super::options::{BuildOptions, CargoOperation, TargetSelection};
+use super::plan::BuildPlan;
+use super::qemu;
+use super::rustflags::{FlagSource, RustFlags};
+use super::static_link;
+use super::strategy::{self, Availability, Facts, PluginFacts, Selection, Strategy};
+use super::wasm::{self, WasmExport};
+use super::xcframework::{AppleExport, Slice};
 
 /// Build executor
 pub struct Builder {
@@ -21,7 +59,17 @@ pub struct Builder {
     config: Config,
 
     /// Zig toolchain (if available)
+    #[cfg(feature = "zig")]
     zig_toolchain: Option<ZigToolchain>,
+
+    /// Stops running and pending builds when cancelled
+    cancel: CancellationToken,
+
+    /// Plugins whose hooks run during builds
+    plugins: Arc<PluginRegistry>,
+
+    /// Compiler errors of a multi-target build, by target
+    diagnostics: DiagnosticSummary,
 }
 
 impl Builder {
@@ -38,45 +86,117 @@ impl Builder {
     /// # }
     /// ```
     pub fn new() -> Result<Self> {
-        let toolchain_manager = ToolchainManager::new()?;
-        let config = Config::discover()?.map(|(c, _)| c).unwrap_or_default();
-
-        // Try to detect Zig for cross-compilation
-        let zig_toolchain = ZigToolchain::detect().ok().flatten();
-
-        Ok(Self {
-            toolchain_manager,
-            config,
-            zig_toolchain,
-        })
+        let config = Config::effective()?;
+        Self::with_config(config)
     }
 
     /// Create a builder with a specific configuration
     pub fn with_config(config: Config) -> Result<Self> {
         let toolchain_manager = ToolchainManager::new()?;
-        let zig_toolchain = ZigToolchain::detect().ok().flatten();
 
         Ok(Self {
             toolchain_manager,
             config,
-            zig_toolchain,
+            // Try to detect Zig for cross-compilation
+            #[cfg(feature = "zig")]
+            zig_toolchain: ZigToolchain::detect().ok().flatten(),
+            cancel: CancellationToken::global().clone(),
+            plugins: Arc::default(),
+            diagnostics: DiagnosticSummary::new(),
         })
     }
 
+    /// Watch `token` instead of the process-wide interrupt token
+    #[must_use]
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Token that stops this builder's builds when cancelled
+    #[must_use]
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancel
+    }
+
+    /// Run the hooks of `plugins`, e.g. their pre-publish gates
+    #[must_use]
+    pub fn with_plugins(mut self, plugins: Arc<PluginRegistry>) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    /// Plugins whose hooks run during builds
+    #[must_use]
+    pub fn plugins(&self) -> &Arc<PluginRegistry> {
+        &self.plugins
+    }
+
+    /// Record compiler errors in `diagnostics`, shared with the builds of
+    /// other targets
+    #[must_use]
+    pub fn with_diagnostics(mut self, diagnostics: DiagnosticSummary) -> Self {
+        self.diagnostics = diagnostics;
+        self
+    }
+
+    /// Compiler errors of the current multi-target build
+    #[must_use]
+    pub fn diagnostics(&self) -> &DiagnosticSummary {
+        &self.diagnostics
+    }
+
+    /// Configuration builds use
+    pub(super) fn config(&self) -> &Config {
+        &self.config
+    }
+
     /// Check if a Cargo.toml exists in current directory or parent directories
     fn has_cargo_toml() -> bool {
+        Self::find_cargo_toml().is_some()
+    }
+
+    /// Find the nearest Cargo.toml in current directory or parent directories
+    fn find_cargo_toml() -> Option<PathBuf> {
         let mut current_dir = std::env::current_dir().ok();
 
         while let Some(dir) = current_dir {
             let cargo_toml = dir.join("Cargo.toml");
             if cargo_toml.exists() {
-                return true;
+                return Some(cargo_toml);
             }
 
             current_dir = dir.parent().map(|p| p.to_path_buf());
         }
 
-        false
+        None
+    }
+
+    /// Fail early if the build uses cargo features the toolchain's cargo lacks
+    fn check_cargo_capabilities(&self, options: &BuildOptions) -> Result<()> {
+        let args: Vec<String> = self
+            .config
+            .build
+            .cargo_flags
+            .iter()
+            .chain(&options.cargo_args)
+            .cloned()
+            .chain(self.keep_going_arg(options).map(ToString::to_string))
+            .collect();
+        let manifest = Self::find_cargo_toml().and_then(|path| std::fs::read_to_string(path).ok());
+        let used =
+            Capability::required_by(&args, manifest.as_deref(), |key| std::env::var(key).ok());
+        if used.is_empty() {
+            return Ok(());
+        }
+
+        let capabilities = CargoCapabilities::detect(options.toolchain.as_deref());
+        if options.verbose > 0 {
+            if let Some(version) = capabilities.version() {
+                helpers::info(format!("Detected cargo {version}"));
+            }
+        }
+        capabilities.require_all(&used)
     }
 
     /// Build the current project
@@ -111,38 +231,200 @@ impl Builder {
         }
 
         // Determine target
-        let target_triple = if let Some(target) = &options.target {
-            target.clone()
-        } else if let Some(default_target) = self.config.targets.default.first() {
-            helpers::info(format!(
-                "Using default target from config: {default_target}"
-            ));
-            default_target.clone()
-        } else {
-            let host = Target::detect_host()?;
-            helpers::info(format!("No target specified, using host: {}", host.triple));
-            host.triple
-        };
+        let target = self.select_target(options)?;
+        if options.target.is_none() {
+            if self.config.targets.default.is_empty() {
+                helpers::info(format!(
+                    "No target specified, using host: {}",
+                    target.triple
+                ));
+            } else {
+                helpers::info(format!(
+                    "Using default target from config: {}",
+                    target.triple
+                ));
+            }
+        }
+        // Everything logged while building carries the target
+        let _span = tracing::info_span!("target", triple = %target.triple).entered();
+
+        // Fold this target's output in CI logs; closed on every return path
+        let _log_group = ci::LogGroup::start(format!(
+            "xcargo {} {}",
+            options.operation.as_str(),
+            target.triple
+        ));
 
-        // Parse target
-        let target = Target::from_triple(&target_triple)?;
         helpers::progress(format!(
             "{} for target: {}",
             options.operation.description(),
             target.triple
         ));
 
-        // Check if we should use container build
-        let should_use_container =
-            options.use_container || self.should_use_container_for_target(&target)?;
+        // Cleaning needs no toolchain, linker or container
+        if !options.operation.compiles() {
+            return self.clean(&target, options);
+        }
+
+        // Offline builds stop here when they would need the network
+        if self.is_offline(options) {
+            self.check_offline(std::slice::from_ref(&target.triple), options)?;
+        }
+
+        // Plugins may prepare or veto the build, and hear how it went
+        let ctx = Self::plugin_context(&target, options);
+        self.plugins.execute_hook(PluginHook::PreBuild, &ctx)?;
+        let result = self.build_target(&target, options);
+        match &result {
+            Ok(()) => self.plugins.execute_hook(PluginHook::PostBuild, &ctx)?,
+            Err(e) => {
+                let message = e.to_string();
+                if let Err(hook_error) =
+                    self.plugins
+                        .execute_hook_with_error(PluginHook::BuildFailed, &ctx, &message)
+                {
+                    helpers::warning(format!("Build-failed plugin hook failed: {hook_error}"));
+                }
+            }
+        }
+        result
+    }
+
+    /// Context passed to plugin hooks for building `target`
+    fn plugin_context(target: &Target, options: &BuildOptions) -> PluginContext {
+        PluginContext::new(target.triple.clone())
+            .with_release(options.release)
+            .with_project_root(
+                Self::find_cargo_toml()
+                    .and_then(|manifest| manifest.parent().map(Path::to_path_buf))
+                    .unwrap_or_default(),
+            )
+            .with_cargo_args(options.cargo_args.clone())
+            .with_toolchain(options.toolchain.clone())
+            .with_container(options.use_container)
+            .with_zig(options.use_zig == Some(true))
+    }
+
+    /// Build `target` once it is selected and the plugins' pre-build hooks
+    /// ran, and add how it went to the build history
+    fn build_target(&self, target: &Target, options: &BuildOptions) -> Result<()> {
+        let stopwatch = Stopwatch::start();
+        let started = Instant::now();
+
+        // Build or test only the kinds of cargo targets configured for it
+        let options = &self.with_target_kinds(target, options);
+
+        let (result, strategy) = match self.prepare_target(target, options) {
+            Ok((native, selection)) => (
+                self.build_selected(target, options, native, &selection, stopwatch),
+                Some(selection.strategy),
+            ),
+            Err(e) => (Err(e), None),
+        };
+
+        let mut run = Run::new(
+            &target.triple,
+            options,
+            Outcome::of(&result),
+            started.elapsed(),
+        );
+        if let Some(strategy) = strategy {
+            run = run.with_strategy(strategy.as_str());
+        }
+        runs::record(&run);
+        result
+    }
+
+    /// Whether `target` is the host, and how to build it
+    fn prepare_target(&self, target: &Target, options: &BuildOptions) -> Result<(bool, Selection)> {
+        if options.coverage.is_some() && !coverage::supports(target) {
+            return Err(Error::Config(format!(
+                "Coverage cannot be collected for {}: WebAssembly has no profiler runtime",
+                target.triple
+            )));
+        }
+
+        // Building for the host runs plain cargo: no Zig, container or
+        // linker, unless one of them is asked for
+        let native = target.is_host()?;
+        let selection = self.select_strategy(target, options)?;
+        if native && selection.strategy == Strategy::Native {
+            helpers::info(format!("Native build: {} is the host", target.triple));
+        } else {
+            Self::report_strategy(target, &selection, options);
+        }
+        Ok((native, selection))
+    }
+
+    /// Build `target` with the strategy in `selection`
+    fn build_selected(
+        &self,
+        target: &Target,
+        options: &BuildOptions,
+        native: bool,
+        selection: &Selection,
+        mut stopwatch: Stopwatch,
+    ) -> Result<()> {
+        if selection.strategy == Strategy::Container {
+            if options.timings {
+                helpers::warning("Compile timings are not collected for container builds");
+            }
+            if options.operation == CargoOperation::Bench {
+                helpers::warning("Benchmarks are not run in container builds");
+            }
+            stopwatch.lap(Phase::Toolchain);
+            self.build_with_container(target, options)?;
+            // The container's setup and post-processing are not told apart
+            stopwatch.lap(Phase::Compile);
+            Self::save_phases(target, options, stopwatch);
+            return Ok(());
+        }
+
+        // Plugin strategies take over the command, and say where cargo runs
+        let plugin_strategy = match &selection.strategy {
+            Strategy::Plugin(name) => Some(name.as_str()),
+            _ => None,
+        };
+        // A Nix shell brings its own cargo, linker and target libraries
+        let nix_shell = if selection.strategy == Strategy::Nix {
+            Some(Self::nix_shell().ok_or_else(|| {
+                Error::Config(format!(
+                    "The nix strategy was chosen for {} but the project has no flake.nix or shell.nix",
+                    target.triple
+                ))
+            })?)
+        } else {
+            None
+        };
+        let own_toolchain = plugin_strategy.is_some() || nix_shell.is_some();
 
-        if should_use_container {
-            return self.build_with_container(&target, options);
+        // Container images bring their own cargo; check the local one
+        if !own_toolchain {
+            self.check_cargo_capabilities(options)?;
+        }
+        if options.timings {
+            timings::require_nightly(options.toolchain.as_deref())?;
         }
 
-        // Check if Zig can handle this cross-compilation
-        let zig_env = self.try_zig_cross_compilation(&target, options)?;
+        let zig_env = if selection.strategy == Strategy::Zig {
+            Some(self.zig_environment(target, options)?)
+        } else {
+            if !native {
+                self.suggest_zig(target)?;
+            }
+            None
+        };
         let using_zig = zig_env.is_some();
+        if !using_zig && options.glibc_version.is_some() {
+            helpers::warning("--glibc only applies to Zig builds, ignoring it");
+        }
+        let static_link = self.links_statically(target, options);
+        if static_link && !static_link::is_supported(target) {
+            helpers::warning(format!(
+                "{} has no static C runtime, linking dynamically",
+                target.triple
+            ));
+        }
 
         // Determine toolchain
         let toolchain = if let Some(tc) = &options.toolchain {
@@ -152,14 +434,23 @@ impl Builder {
         };
 
         // Ensure target is installed
-        helpers::progress("Checking toolchain and target...".to_string());
-        self.toolchain_manager.prepare_target(&toolchain, &target)?;
-        helpers::success("Toolchain and target ready");
+        if !own_toolchain {
+            helpers::progress("Checking toolchain and target...".to_string());
+            self.prepare_toolchain(&toolchain, target, options)?;
+            helpers::success("Toolchain and target ready");
+        }
 
         // Show tips based on target
         if target.os != Target::detect_host()?.os {
             if using_zig {
                 helpers::tip("Cross-compiling using Zig toolchain");
+            } else if let Some(strategy) = plugin_strategy {
+                helpers::tip(format!("Cross-compiling using the {strategy} strategy"));
+            } else if let Some(shell) = &nix_shell {
+                helpers::tip(format!(
+                    "Cross-compiling in the Nix shell of {}",
+                    shell.file().display()
+                ));
             } else {
                 helpers::tip("Cross-compiling to a different OS");
                 if self.config.container.use_when == "target.os != host.os" {
@@ -171,21 +462,64 @@ impl Builder {
         // Get target-specific configuration
         let target_config = self.config.get_target_config(&target.triple);
 
+        // Cargo's own configuration applies too, under xcargo.toml
+        let cargo_config = CargoTargetConfig::find(&target.triple)?;
+        for setting in self.config.target_settings(&target.triple, &cargo_config) {
+            if let Some(conflict) = setting.conflict(&target.triple) {
+                helpers::warning(conflict);
+            }
+        }
+        let cargo_linker = cargo_config.linker.as_ref().map(|linker| &linker.value);
+        if let Some(linker) = cargo_config.linker.as_ref().filter(|_| using_zig) {
+            helpers::warning(format!(
+                "Zig links {}, replacing linker {} from {}",
+                target.triple,
+                linker.value,
+                linker.path.display()
+            ));
+        }
+
+        // Only operations that link need the target's linker to exist, and
+        // only when it runs here
+        let checks_linker = !using_zig && !own_toolchain && options.operation.links();
+
+        // Static musl builds compile C with a musl compiler unless Zig does
+        let musl_cc = if static_link && checks_linker && static_link::is_musl(target) {
+            Some(Self::musl_compiler(target)?)
+        } else {
+            None
+        };
+
         // Check linker configuration and availability (skip if using Zig)
         let linker = if using_zig {
             None // Zig provides its own linker
+        } else if nix_shell.is_some() {
+            // The shell sets up its linker unless one is configured
+            target_config.and_then(|config| config.linker.clone())
         } else if let Some(config) = target_config {
-            config.linker.clone()
+            config
+                .linker
+                .clone()
+                .or_else(|| cargo_linker.cloned())
+                .or_else(|| musl_cc.clone())
+        } else if let Some(linker) = cargo_linker {
+            Some(linker.clone())
+        } else if native {
+            None // The toolchain's default linker already targets the host
+        } else if musl_cc.is_some() {
+            musl_cc.clone()
+        } else if let Some(provisioned) = self.provision_linker(target, options, checks_linker)? {
+            Some(provisioned)
         } else {
             let requirements = target.get_requirements();
             requirements.linker
         };
 
         // Verify linker exists if specified (and not using Zig)
-        if !using_zig {
+        if checks_linker {
             if let Some(ref linker_path) = linker {
                 if let Ok(path) = which::which(linker_path) {
-                    if options.verbose {
+                    if options.verbose > 0 {
                         helpers::info(format!(
                             "Using linker: {} ({})",
                             linker_path,
@@ -204,7 +538,7 @@ impl Builder {
 
                     // Suggest platform-specific installation
                     let host = Target::detect_host()?;
-                    self.suggest_linker_installation(&host, &target);
+                    self.suggest_linker_installation(&host, target);
 
                     helpers::tip("The build may fail if the linker is not available");
                 }
@@ -214,14 +548,14 @@ impl Builder {
                 if let Some(suggested_linker) = requirements.linker {
                     // Check if the suggested linker is available
                     if which::which(&suggested_linker).is_ok() {
-                        if options.verbose {
+                        if options.verbose > 0 {
                             helpers::info(format!("Using default linker: {suggested_linker}"));
                         }
                     } else {
                         helpers::hint(format!("Recommended linker '{suggested_linker}' not found"));
 
                         let host = Target::detect_host()?;
-                        self.suggest_linker_installation(&host, &target);
+                        self.suggest_linker_installation(&host, target);
 
                         helpers::tip(format!(
                             "Configure in xcargo.toml: [targets.\"{}\"] linker = \"{}\"",
@@ -232,22 +566,41 @@ impl Builder {
             }
         }
 
+        // Resolve native dependencies (OpenSSL, etc.)
+        stopwatch.lap(Phase::Toolchain);
+        let dep_resolutions = self.resolve_native_deps(target)?;
+        stopwatch.lap(Phase::Dependencies);
+
         // Build cargo command with progress tracking
         use crate::output::progress::BuildProgress;
 
         let progress = match options.operation {
-            super::options::CargoOperation::Build => BuildProgress::compiling(&target.triple),
-            super::options::CargoOperation::Check => BuildProgress::checking(&target.triple),
-            super::options::CargoOperation::Test => BuildProgress::testing(&target.triple),
+            CargoOperation::Build => BuildProgress::compiling(&target.triple),
+            CargoOperation::Check | CargoOperation::Clippy => {
+                BuildProgress::checking(&target.triple)
+            }
+            CargoOperation::Test => BuildProgress::testing(&target.triple),
+            other => BuildProgress::new(&target.triple, other.description()),
         };
+        if let Some(estimate) = history::load().estimate(&history::key(&target.triple, options)) {
+            progress.show_estimate(&estimate.describe());
+        }
 
         let mut cmd = Command::new("cargo");
 
+        // Import the Visual Studio environment for MSVC targets on Windows
+        if let Some(env) = self.msvc_environment(target)? {
+            if options.verbose > 0 {
+                helpers::info(format!("Imported {} MSVC environment variables", env.len()));
+            }
+            cmd.envs(env);
+        }
+
         // Apply Zig environment if using Zig for cross-compilation
         if let Some(ref env) = zig_env {
             for (key, value) in env {
                 cmd.env(key, value);
-                if options.verbose {
+                if options.verbose > 0 {
                     helpers::info(format!("Setting {}={}", key, value.display()));
                 }
             }
@@ -255,7 +608,8 @@ impl Builder {
 
         // Set environment variables for linker and custom env vars (only if not using Zig)
         if !using_zig {
-            if let Some(ref linker_path) = linker {
+            // Cargo already uses the linker its configuration names
+            if let Some(linker_path) = linker.as_ref().filter(|l| Some(*l) != cargo_linker) {
                 // Convert target triple to CARGO env var format
                 // e.g., x86_64-pc-windows-gnu -> CARGO_TARGET_X86_64_PC_WINDOWS_GNU_LINKER
                 let env_var = format!(
@@ -264,27 +618,112 @@ impl Builder {
                 );
                 cmd.env(&env_var, linker_path);
 
-                if options.verbose {
+                if options.verbose > 0 {
                     helpers::info(format!("Setting {env_var}={linker_path}"));
                 }
             }
+
+            // C code in build scripts needs the musl compiler too
+            if let Some(ref compiler) = musl_cc {
+                let env_var = format!("CC_{}", target.triple.replace('-', "_"));
+                cmd.env(&env_var, compiler);
+                if options.verbose > 0 {
+                    helpers::info(format!("Setting {env_var}={compiler}"));
+                }
+            }
         }
 
-        // Add custom environment variables from target config
+        // Add custom environment variables from [build] and the profile,
+        // then from target config so it wins
+        for (key, value) in &self.config.build.env {
+            cmd.env(key, value);
+            if options.verbose > 0 {
+                helpers::info(format!("Setting {key}={value}"));
+            }
+        }
         if let Some(config) = target_config {
             for (key, value) in &config.env {
                 cmd.env(key, value);
-                if options.verbose {
+                if options.verbose > 0 {
+                    helpers::info(format!("Setting {key}={value}"));
+                }
+            }
+        }
+
+        // Add custom rustflags if specified, and crt-static for static builds
+        if let Some(rustflags) = self.rustflags(target, options, &cargo_config) {
+            let rustflags_str = rustflags.args().join(" ");
+            cmd.env("RUSTFLAGS", &rustflags_str);
+            if options.verbose > 0 {
+                helpers::info(format!("Setting RUSTFLAGS={rustflags_str}"));
+                Self::report_rustflags(&rustflags);
+            }
+        }
+
+        // Run WASI test binaries in wasmtime or wasmer, and those of other
+        // Linux architectures under QEMU
+        if let Some((key, value)) = self.binary_runner(target, options, &cargo_config)? {
+            if options.verbose > 0 {
+                helpers::info(format!("Setting {key}={value}"));
+            }
+            cmd.env(key, value);
+        }
+
+        // Build instrumented tests apart and have them write raw profiles
+        let coverage = match options.coverage {
+            Some(_) => Some(self.prepare_coverage(target, native, &cargo_config)?),
+            None => None,
+        };
+        if let Some(coverage) = &coverage {
+            cmd.env("CARGO_TARGET_DIR", coverage.cargo_target_dir());
+            cmd.env("LLVM_PROFILE_FILE", coverage.profile_file());
+            // simctl spawn passes on the variables with this prefix
+            if apple::is_simulator(target) {
+                cmd.env("SIMCTL_CHILD_LLVM_PROFILE_FILE", coverage.profile_file());
+            }
+            if options.verbose > 0 {
+                helpers::info(format!(
+                    "Writing coverage data to {}",
+                    coverage.profraw_dir().display()
+                ));
+            }
+        }
+
+        // Point iOS, iOS simulator and Mac Catalyst builds at their Xcode SDK
+        for (key, value) in self.apple_environment(target, options)? {
+            cmd.env(&key, &value);
+            if options.verbose > 0 {
+                helpers::info(format!("Setting {key}={value}"));
+            }
+        }
+
+        // Apply native dependency resolutions, keeping any env set above
+        let explicit_env: Vec<String> = cmd
+            .get_envs()
+            .map(|(key, _)| key.to_string_lossy().into_owned())
+            .collect();
+        let mut dep_features = Vec::new();
+        for resolution in &dep_resolutions {
+            for (key, value) in &resolution.env {
+                if explicit_env.contains(key) {
+                    continue;
+                }
+                cmd.env(key, value);
+                if options.verbose > 0 {
                     helpers::info(format!("Setting {key}={value}"));
                 }
             }
+            dep_features.extend(resolution.features.iter().cloned());
+        }
 
-            // Add custom rustflags if specified
-            if let Some(ref rustflags) = config.rustflags {
-                let rustflags_str = rustflags.join(" ");
-                cmd.env("RUSTFLAGS", &rustflags_str);
-                if options.verbose {
-                    helpers::info(format!("Setting RUSTFLAGS={rustflags_str}"));
+        // Route compilation through the compiler cache, if any
+        if let Some(wrapper) = self.compiler_wrapper() {
+            if options.verbose > 0 {
+                helpers::info(format!("Using {} as compiler wrapper", wrapper.name));
+            }
+            for (key, value) in wrapper.native_env() {
+                if !explicit_env.contains(&key) {
+                    cmd.env(key, value);
                 }
             }
         }
@@ -300,12 +739,77 @@ impl Builder {
         cmd.arg("--target").arg(&target.triple);
 
         // Add release flag
-        if options.release {
+        if options.release && options.operation.accepts_release() {
             cmd.arg("--release");
         }
 
+        // Add feature selection (CLI, per-target config, native deps)
+        cmd.args(self.feature_args(target, options, &dep_features));
+
+        // Add binary, example and library selection
+        cmd.args(options.selection.cargo_args());
+
+        // Report every crate that fails to compile, not only the first
+        cmd.args(self.keep_going_arg(options));
+
+        // Keep cargo off the network too
+        if self.passes_offline(options) {
+            cmd.arg("--offline");
+        }
+
+        // Read diagnostics as JSON to annotate them for CI and summarize
+        // them across targets, unless the user picked a message format
+        let own_format = options
+            .cargo_args
+            .iter()
+            .chain(&self.config.build.cargo_flags)
+            .any(|arg| arg.starts_with("--message-format"));
+        let mut handling = OutputHandling::default();
+        if !own_format {
+            handling.annotations = ci::Annotations::detect();
+            if self.diagnostics.is_collecting() {
+                // A retry replaces what the failed attempt reported
+                self.diagnostics.clear_target(&target.triple);
+                handling.diagnostics = Some((self.diagnostics.clone(), target.triple.clone()));
+            }
+        }
+        if handling.is_json() {
+            cmd.arg(ci::JSON_MESSAGE_FORMAT);
+        }
+
+        // Look for known failures in cargo's output to explain them
+        let explainer = explain::Matcher::new();
+        handling.explain = Some(explainer.clone());
+
+        // Have cargo print each unit's compile time
+        let timings = options.timings.then(timings::Collector::new);
+        if timings.is_some() {
+            cmd.args(timings::CARGO_ARGS);
+        }
+        handling.timings.clone_from(&timings);
+
+        // Read benchmark results from the bench binaries' output
+        let bench = (options.operation == CargoOperation::Bench).then(bench::Collector::new);
+        handling.bench.clone_from(&bench);
+        if bench.is_some() && !options.cargo_args.iter().any(|arg| arg == "--no-run") {
+            let host = Target::detect_host()?;
+            // Rosetta runs x86_64 binaries on Apple silicon
+            let runs = native || (target.os == "macos" && host.os == "macos");
+            if !runs && self.test_runner(target, &host, &cargo_config).is_none() {
+                helpers::warning(format!(
+                    "Nothing can run {} binaries here; only building its benchmarks",
+                    target.triple
+                ));
+                helpers::hint(format!(
+                    "Install QEMU user mode (qemu-user) or set CARGO_TARGET_{}_RUNNER",
+                    target.triple.to_uppercase().replace('-', "_")
+                ));
+                cmd.arg("--no-run");
+            }
+        }
+
         // Add verbose flag
-        if options.verbose
+        if options.cargo_verbose()
             || self
                 .config
                 .build
@@ -317,7 +821,7 @@ impl Builder {
 
         // Add additional cargo flags from config
         for flag in &self.config.build.cargo_flags {
-            if flag != "--verbose" || !options.verbose {
+            if flag != "--verbose" || !options.cargo_verbose() {
                 cmd.arg(flag);
             }
         }
@@ -327,20 +831,58 @@ impl Builder {
             cmd.arg(arg);
         }
 
-        if options.verbose {
-            helpers::info(format!("Executing: {cmd:?}"));
+        if let Some(strategy) = plugin_strategy {
+            cmd = self.plugin_command(strategy, target, options, &cmd)?;
+        } else if let Some(shell) = &nix_shell {
+            cmd = shell.wrap(&cmd);
         }
 
+        print_command(&cmd, options);
+
         // Execute build
-        let status = cmd
-            .status()
-            .map_err(|e| Error::Build(format!("Failed to execute cargo: {e}")))?;
+        let status = match self.run_cargo(&mut cmd, options, handling) {
+            Ok(status) => status,
+            Err(e) => {
+                progress.finish_error(match e {
+                    Error::Cancelled => "cancelled",
+                    Error::TimedOut(_) => "timed out",
+                    _ => "build failed",
+                });
+                return Err(e);
+            }
+        };
 
         if status.success() {
+            stopwatch.lap(Phase::Compile);
             progress.finish_success();
 
-            // Show helpful tips (only for build/test, not check)
-            if options.operation != CargoOperation::Check {
+            if let Some(timings) = &timings {
+                Self::save_timings(target, options, timings);
+            }
+            if let Some(bench) = &bench {
+                Self::save_bench(target, bench);
+            }
+            if let (Some(coverage), Some(format)) = (&coverage, options.coverage) {
+                Self::report_coverage(coverage, options, format, None)?;
+            }
+
+            if options.operation == CargoOperation::Build {
+                self.collect_artifacts(target, options)?;
+                self.export_android(target, options)?;
+                self.export_wasm(target, options)?;
+                self.export_xcframeworks(target, options)?;
+                self.write_firmware_images(target, options)?;
+                self.verify_artifacts(target, options)?;
+            }
+            stopwatch.lap(Phase::PostProcess);
+            Self::save_phases(target, options, stopwatch);
+
+            if options.operation == CargoOperation::Doc {
+                helpers::tip(format!("Documentation is in target/{}/doc/", target.triple));
+            }
+
+            // Show helpful tips (only for operations that produce binaries)
+            if options.operation.links() {
                 if options.release {
                     helpers::tip(format!(
                         "Release build artifacts are in target/{}/release/",
@@ -371,9 +913,18 @@ impl Builder {
             Ok(())
         } else {
             progress.finish_error("build failed");
-
-            // Provide helpful error context
-            if linker.is_none() {
+            support::record_failure(&target.triple, &cmd, status.code());
+
+            // Provide helpful error context, preferring what cargo's output
+            // says went wrong. Nothing is linked for check, clippy and doc, so
+            // skip linker hints for them.
+            let explanations = explainer.explanations();
+            if let Some(first) = explanations.first() {
+                for explanation in &explanations {
+                    explanation.print_short(&target.triple);
+                }
+                helpers::tip(format!("Run 'xcargo explain {}' for details", first.code));
+            } else if checks_linker && linker.is_none() {
                 let requirements = target.get_requirements();
                 if let Some(suggested_linker) = requirements.linker {
                     println!();
@@ -435,7 +986,7 @@ impl Builder {
                         }
                     }
                 }
-            } else if let Some(ref linker_path) = linker {
+            } else if let Some(linker_path) = linker.as_ref().filter(|_| checks_linker) {
                 if which::which(linker_path).is_err() {
                     println!();
                     helpers::hint(format!(
@@ -456,29 +1007,121 @@ impl Builder {
         }
     }
 
+    /// `options` limited to `[targets.<triple>] kinds` (build, check and
+    /// clippy) or `test_kinds` (test and bench), unless the command line
+    /// selects cargo targets itself
+    fn with_target_kinds(&self, target: &Target, options: &BuildOptions) -> BuildOptions {
+        let Some(config) = self.config.get_target_config(&target.triple) else {
+            return options.clone();
+        };
+        let (key, kinds) = match options.operation {
+            CargoOperation::Build | CargoOperation::Check | CargoOperation::Clippy => {
+                ("kinds", &config.kinds)
+            }
+            CargoOperation::Test | CargoOperation::Bench => ("test_kinds", &config.test_kinds),
+            CargoOperation::Doc | CargoOperation::Clean => return options.clone(),
+        };
+        let selects = |arg: &String| {
+            SELECTION_ARGS
+                .iter()
+                .any(|flag| arg == flag || arg.starts_with(&format!("{flag}=")))
+        };
+        if kinds.is_empty()
+            || !options.selection.is_empty()
+            || options.cargo_args.iter().any(selects)
+        {
+            return options.clone();
+        }
+
+        helpers::info(format!(
+            "Only {} ([targets.\"{}\"] {key})",
+            kinds.join(", "),
+            target.triple
+        ));
+        BuildOptions {
+            selection: TargetSelection::of_kinds(kinds),
+            ..options.clone()
+        }
+    }
+
+    /// Split targets into those to build and those excluded on this host by
+    /// `only_on_host`/`skip_on_ci`, with the reason each was skipped
+    pub(super) fn exclude_targets(
+        &self,
+        targets: &[String],
+    ) -> Result<(Vec<String>, Vec<SkippedTarget>)> {
+        let host = Target::detect_host()?;
+        let on_ci = ci::is_ci(|key| std::env::var(key).ok());
+
+        let mut included = Vec::new();
+        let mut skipped = Vec::new();
+        for target in targets {
+            let reason = self
+                .config
+                .get_target_config(target)
+                .and_then(|config| config.exclusion_reason(&host.os, on_ci));
+            match reason {
+                Some(reason) => skipped.push((target.clone(), reason)),
+                None => included.push(target.clone()),
+            }
+        }
+        Ok((included, skipped))
+    }
+
     /// Build for multiple targets (sequential)
     pub fn build_all(&self, targets: &[String], options: &BuildOptions) -> Result<()> {
+        let started = Instant::now();
         helpers::section(format!(
             "xcargo {} (multiple targets)",
             options.operation.as_str()
         ));
+        let (targets, mut skipped) = self.exclude_targets(targets)?;
+        let source_hash = Self::source_hash();
+        let targets = self.resume_targets(targets, options, source_hash, &mut skipped)?;
+        let targets = targets.as_slice();
         helpers::info(format!(
             "{} for {} targets",
             options.operation.description(),
             targets.len()
         ));
 
+        if self.is_offline(options) {
+            self.check_offline(targets, options)?;
+        }
+        if options.operation.compiles() {
+            self.install_missing_targets(targets, options)?;
+        }
+
+        let estimates = Self::estimates(targets, options);
+        history::print_queue(targets, &estimates, false);
+
         let mut successes = Vec::new();
         let mut failures = Vec::new();
+        self.diagnostics.start(true);
 
         for (idx, target) in targets.iter().enumerate() {
-            println!("\n[{}/{}] Target: {}", idx + 1, targets.len(), target);
-            println!("{}", "─".repeat(50));
+            if self.cancel.is_cancelled() {
+                let reason = "not built, cancelled".to_string();
+                skipped.extend(targets[idx..].iter().map(|t| (t.clone(), reason.clone())));
+                break;
+            }
+
+            let left = history::remaining(&estimates[idx..], false, Duration::ZERO)
+                .map(|left| format!(" ({} left)", history::approximate(left)))
+                .unwrap_or_default();
+            println!("\n[{}/{}] Target: {}{left}", idx + 1, targets.len(), target);
+            helpers::separator();
 
             let mut target_options = options.clone();
             target_options.target = Some(target.clone());
 
-            match self.build(&target_options) {
+            let result = self.build_with_retries(&target_options);
+            if matches!(result, Err(Error::Cancelled)) {
+                skipped.push((target.clone(), "cancelled while building".to_string()));
+                continue;
+            }
+            self.record_results(&[(target.clone(), result.is_ok())], options, source_hash);
+            match result {
                 Ok(()) => successes.push(target.clone()),
                 Err(e) => {
                     helpers::error(format!("Failed to build {target}: {e}"));
@@ -487,103 +1130,1987 @@ impl Builder {
             }
         }
 
+        self.diagnostics.finish();
         println!("\n");
         helpers::section("Build Summary");
         helpers::success(format!("{} target(s) built successfully", successes.len()));
-
+        print_skipped(&skipped);
+
+        // Results of the targets that finished are recorded either way
+        let cancelled = self.cancel.is_cancelled();
+        self.finish_builds(
+            options,
+            &BuildsSummary {
+                operation: options.operation.as_str().to_string(),
+                succeeded: successes.clone(),
+                failed: failures.clone(),
+                skipped: skipped.len(),
+                cancelled,
+                elapsed: started.elapsed(),
+            },
+        );
         if !failures.is_empty() {
             helpers::error(format!("{} target(s) failed", failures.len()));
             for target in &failures {
                 helpers::error(format!("  - {target}"));
             }
+            print_resume_hint(options);
+            if cancelled {
+                return Err(Error::Cancelled);
+            }
             return Err(Error::Build("Some targets failed to build".to_string()));
         }
+        if cancelled {
+            print_resume_hint(options);
+            return Err(Error::Cancelled);
+        }
 
         helpers::tip(tips::PARALLEL_BUILDS);
         Ok(())
     }
 
-    /// Try to use Zig for cross-compilation if available and supported
+    /// Build one target of a multi-target build, retrying failures
     ///
-    /// Returns Some(env) if Zig can handle this cross-compilation, None otherwise.
-    /// Respects the `use_zig` option: None = auto, Some(true) = force, Some(false) = disable
-    fn try_zig_cross_compilation(
-        &self,
-        target: &Target,
-        options: &BuildOptions,
-    ) -> Result<Option<HashMap<String, PathBuf>>> {
-        // Check if Zig is explicitly disabled
-        if options.use_zig == Some(false) {
-            if options.verbose {
-                helpers::info("Zig disabled via --no-zig flag");
+    /// Retries come from `--retries` or `build.retries`; the wait before
+    /// each starts at `build.retry_delay` seconds and doubles.
+    pub(super) fn build_with_retries(&self, options: &BuildOptions) -> Result<()> {
+        let retries = options.retries.unwrap_or(self.config.build.retries);
+        let mut delay = Duration::from_secs(self.config.build.retry_delay);
+        let mut attempt = 0;
+        loop {
+            match self.build(options) {
+                Err(e) if attempt < retries && !self.cancel.is_cancelled() => {
+                    attempt += 1;
+                    helpers::warning(format!(
+                        "{e}; retrying in {}s (attempt {} of {})",
+                        delay.as_secs(),
+                        attempt + 1,
+                        retries + 1
+                    ));
+                    thread::sleep(delay);
+                    if self.cancel.is_cancelled() {
+                        return Err(Error::Cancelled);
+                    }
+                    delay = delay.saturating_mul(2);
+                }
+                result => return result,
             }
-            return Ok(None);
         }
+    }
 
-        // Check if Zig is explicitly forced
-        let force_zig = options.use_zig == Some(true);
-
-        // Determine if we're cross-compiling to a different OS
-        let host = Target::detect_host()?;
-        let is_cross_os = target.os != host.os;
+    /// Expected build time of each of `targets` from the build history
+    pub(super) fn estimates(
+        targets: &[String],
+        options: &BuildOptions,
+    ) -> Vec<Option<history::Estimate>> {
+        let history = history::load();
+        targets
+            .iter()
+            .map(|target| history.estimate(&history::key(target, options)))
+            .collect()
+    }
 
-        // For auto mode, only attempt Zig for cross-compilation (different OS)
-        if !force_zig && !is_cross_os {
-            return Ok(None);
+    /// Leave out targets `--resume` finds already built, adding them to
+    /// `skipped`
+    ///
+    /// A target counts as built when the build cache records a success with
+    /// the same settings and sources; the history records it as cached.
+    pub(super) fn resume_targets(
+        &self,
+        targets: Vec<String>,
+        options: &BuildOptions,
+        source_hash: Option<u64>,
+        skipped: &mut Vec<SkippedTarget>,
+    ) -> Result<Vec<String>> {
+        if !options.resume {
+            return Ok(targets);
         }
+        let Some(source_hash) = source_hash.filter(|_| self.records_results(options)) else {
+            helpers::warning("--resume needs the build cache ([build] cache = true) and a build");
+            return Ok(targets);
+        };
 
-        // Check if Zig is available and supports this target
-        if let Some(ref zig) = self.zig_toolchain {
-            if zig.supports_target(target) {
-                helpers::info(format!(
-                    "Zig {} detected, using for cross-compilation",
-                    zig.version()
-                ));
-                let env = zig.environment_for_target(target)?;
-                return Ok(Some(env));
-            } else if force_zig {
-                return Err(Error::Toolchain(format!(
-                    "Zig does not support target '{}'. Supported targets: x86_64-linux-gnu, aarch64-linux-gnu, armv7-linux-gnueabihf",
-                    target.triple
-                )));
-            }
-            // Zig available but doesn't support this target - not an error in auto mode
-            if options.verbose {
-                helpers::info(format!(
-                    "Zig doesn't support target '{}', falling back to native toolchain",
-                    target.triple
-                ));
-            }
-        } else {
-            // Zig not available
-            if force_zig {
-                return Err(Error::Toolchain(
-                    "Zig not found. Install Zig to use --zig flag: brew install zig (macOS) or scoop install zig (Windows)".to_string()
-                ));
-            } else if is_cross_os && ZigToolchain::supports_target_name(&target.triple) {
-                // Graceful degradation: Zig could help but isn't available
-                helpers::hint("Zig is not installed but could simplify this cross-compilation");
-                let install_hint = match host.os.as_str() {
-                    "macos" => "Install with: brew install zig",
-                    "linux" => "Install with: snap install zig --classic --beta",
-                    "windows" => "Install with: scoop install zig",
-                    _ => "Install Zig: https://ziglang.org/download/",
-                };
-                helpers::tip(format!("{install_hint} (then use --zig flag)"));
+        let cache = BuildCache::new()?;
+        let mut built = Vec::new();
+        let mut remaining = Vec::new();
+        for target in targets {
+            if cache.needs_rebuild(&self.cache_key(&target, options)?, source_hash) {
+                remaining.push(target);
+            } else {
+                built.push(target);
             }
         }
-
-        Ok(None)
+        for target in &built {
+            runs::record(&Run::cached(target, options));
+        }
+        skipped.extend(
+            built
+                .into_iter()
+                .map(|target| (target, "already built, --resume".to_string())),
+        );
+        Ok(remaining)
     }
 
-    /// Suggest platform-specific installation instructions for a linker
-    fn suggest_linker_installation(&self, host: &Target, target: &Target) {
-        let host_os = host.os.as_str();
-        let target_os = target.os.as_str();
+    /// Run the plugins' builds-finished hooks once a multi-target build is
+    /// over, e.g. to notify the user
+    ///
+    /// The build is over either way, so a failing hook only warns.
+    pub(super) fn finish_builds(&self, options: &BuildOptions, summary: &BuildsSummary) {
+        let ctx = summary.to_context(
+            PluginContext::default()
+                .with_release(options.release)
+                .with_project_root(
+                    Self::find_cargo_toml()
+                        .and_then(|manifest| manifest.parent().map(Path::to_path_buf))
+                        .unwrap_or_default(),
+                ),
+        );
+        if let Err(e) = self.plugins.execute_hook(PluginHook::BuildsFinished, &ctx) {
+            helpers::warning(format!("Builds-finished plugin hook failed: {e}"));
+        }
+    }
 
-        match (host_os, target_os) {
-            ("macos", "linux") => {
-                helpers::tip(
+    /// Remember which targets built successfully, for `--resume`
+    pub(super) fn record_results(
+        &self,
+        results: &[(String, bool)],
+        options: &BuildOptions,
+        source_hash: Option<u64>,
+    ) {
+        let Some(source_hash) = source_hash.filter(|_| self.records_results(options)) else {
+            return;
+        };
+        let saved = BuildCache::new().and_then(|mut cache| {
+            for (target, success) in results {
+                cache.update(self.cache_key(target, options)?, source_hash, *success);
+            }
+            cache.save()
+        });
+        if let Err(e) = saved {
+            helpers::warning(format!("Could not update the build cache: {e}"));
+        }
+    }
+
+    /// Key a build of `triple` with `options` is cached under, with the
+    /// rustflags rustc gets and the arguments the build passes to cargo
+    fn cache_key(&self, triple: &str, options: &BuildOptions) -> Result<CacheKey> {
+        let target = Target::from_known_triple(triple)?;
+        let cargo_config = CargoTargetConfig::find(&target.triple)?;
+        Ok(CacheKey::for_build(
+            triple,
+            options,
+            &self.effective_rustflags(&target, options, &cargo_config),
+            &self.cache_key_args(&target, options),
+        ))
+    }
+
+    /// Rustflags rustc gets for `target`: what xcargo sets, or else
+    /// cargo's own choice of `RUSTFLAGS` over `.cargo/config.toml`
+    fn effective_rustflags(
+        &self,
+        target: &Target,
+        options: &BuildOptions,
+        cargo_config: &CargoTargetConfig,
+    ) -> Vec<String> {
+        if let Some(rustflags) = self.rustflags(target, options, cargo_config) {
+            return rustflags.args();
+        }
+        match std::env::var("RUSTFLAGS") {
+            Ok(caller) => caller.split_whitespace().map(str::to_string).collect(),
+            Err(_) => cargo_config
+                .rustflags
+                .as_ref()
+                .map(|flags| flags.value.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Arguments the build passes to cargo after the target, in the same
+    /// order, leaving out the ones that only change how cargo reports
+    /// (`--offline`, `--message-format`, `--timings`, `--verbose` added
+    /// by xcargo)
+    fn cache_key_args(&self, target: &Target, options: &BuildOptions) -> Vec<String> {
+        let mut args = Vec::new();
+        if options.release && options.operation.accepts_release() {
+            args.push("--release".to_string());
+        }
+        args.extend(self.feature_args(target, options, &[]));
+        args.extend(options.selection.cargo_args());
+        args.extend(self.keep_going_arg(options).map(ToString::to_string));
+        args.extend(self.config.build.cargo_flags.iter().cloned());
+        args.extend(options.cargo_args.iter().cloned());
+        args
+    }
+
+    /// Whether build results go to the build cache
+    fn records_results(&self, options: &BuildOptions) -> bool {
+        self.config.build.cache && options.operation == CargoOperation::Build
+    }
+
+    /// Hash of the project's files, which a cached build must match
+    pub(super) fn source_hash() -> Option<u64> {
+        let manifest = Self::find_cargo_toml()?;
+        cache::hash_tree(manifest.parent()?)
+    }
+
+    /// Whether a Zig toolchain was found
+    fn has_zig(&self) -> bool {
+        #[cfg(feature = "zig")]
+        return self.zig_toolchain.is_some();
+        #[cfg(not(feature = "zig"))]
+        false
+    }
+
+    /// Environment that makes cargo link and compile C with Zig
+    #[cfg(feature = "zig")]
+    fn zig_environment(
+        &self,
+        target: &Target,
+        options: &BuildOptions,
+    ) -> Result<HashMap<String, PathBuf>> {
+        let Some(ref zig) = self.zig_toolchain else {
+            return Err(Error::Toolchain(
+                "Zig not found. Install Zig to use --zig flag: brew install zig (macOS) or scoop install zig (Windows)".to_string()
+            ));
+        };
+        if !zig.supports_target(target) {
+            return Err(Error::Toolchain(format!(
+                "Zig does not support target '{}'. Supported targets: x86_64-linux-gnu, aarch64-linux-gnu, armv7-linux-gnueabihf, x86_64-windows-gnu, and macOS with an SDK in SDKROOT",
+                target.triple
+            )));
+        }
+
+        helpers::info(format!(
+            "Zig {} detected, using for cross-compilation",
+            zig.version()
+        ));
+        let glibc = self.glibc_version(target, options)?;
+        if let Some(version) = glibc {
+            helpers::info(format!("Linking against glibc {version}"));
+        }
+        zig.environment_for_target(target, glibc)
+    }
+
+    /// Zig builds (fallback when feature not enabled)
+    #[cfg(not(feature = "zig"))]
+    fn zig_environment(
+        &self,
+        _target: &Target,
+        _options: &BuildOptions,
+    ) -> Result<HashMap<String, PathBuf>> {
+        helpers::hint("Rebuild xcargo with: cargo install xcargo --features zig");
+        Err(Error::Toolchain("Zig support not enabled".to_string()))
+    }
+
+    /// Point out Zig when it is missing but could build a cross-OS target
+    fn suggest_zig(&self, target: &Target) -> Result<()> {
+        let host = Target::detect_host()?;
+        if self.has_zig() || target.os == host.os || !Self::zig_supports(&target.triple) {
+            return Ok(());
+        }
+
+        // Graceful degradation: Zig could help but isn't available
+        helpers::hint("Zig is not installed but could simplify this cross-compilation");
+        let install_hint = match host.os.as_str() {
+            "macos" => "Install with: brew install zig",
+            "linux" => "Install with: snap install zig --classic --beta",
+            "windows" => "Install with: scoop install zig",
+            _ => "Install Zig: https://ziglang.org/download/",
+        };
+        helpers::tip(format!("{install_hint} (then use --zig flag)"));
+        Ok(())
+    }
+
+    /// Compute cargo feature flags for a target
+    ///
+    /// Features from the CLI, `[targets.<triple>] features`, and `extra`
+    /// (e.g., native dependency resolutions) are merged and deduplicated.
+    /// `--all-features` only covers the package's own features, so
+    /// dependency features (`openssl/vendored`) are still passed with it.
+    fn feature_args(
+        &self,
+        target: &Target,
+        options: &BuildOptions,
+        extra: &[String],
+    ) -> Vec<String> {
+        let target_config = self.config.get_target_config(&target.triple);
+        let configured = target_config
+            .map(|c| c.features.as_slice())
+            .unwrap_or_default();
+
+        let mut features: Vec<&str> = Vec::new();
+        for list in options.features.iter().chain(configured).chain(extra) {
+            for feature in list.split([',', ' ']).filter(|f| !f.is_empty()) {
+                if !features.contains(&feature) {
+                    features.push(feature);
+                }
+            }
+        }
+
+        let mut args = Vec::new();
+        if options.all_features {
+            args.push("--all-features".to_string());
+            features.retain(|feature| feature.contains('/'));
+        }
+        if !features.is_empty() {
+            args.push("--features".to_string());
+            args.push(features.join(","));
+        }
+
+        if options.no_default_features || target_config.is_some_and(|c| c.no_default_features) {
+            args.push("--no-default-features".to_string());
+        }
+
+        args
+    }
+
+    /// Cargo's `--keep-going` for builds with `--keep-going`, unless the
+    /// cargo arguments already have it
+    fn keep_going_arg(&self, options: &BuildOptions) -> Option<&'static str> {
+        let passed = options
+            .cargo_args
+            .iter()
+            .chain(&self.config.build.cargo_flags)
+            .any(|arg| arg == "--keep-going");
+        (options.keep_going && options.operation == CargoOperation::Build && !passed)
+            .then_some("--keep-going")
+    }
+
+    /// Target from options, the first configured default, or the host
+    fn select_target(&self, options: &BuildOptions) -> Result<Target> {
+        let target_triple = if let Some(target) = &options.target {
+            target.clone()
+        } else if let Some(default_target) = self.config.targets.default.first() {
+            default_target.clone()
+        } else {
+            return Target::detect_host();
+        };
+
+        let target_triple =
+            Target::resolve_alias_with(&target_triple, &self.config.resolved_aliases())?;
+        Target::from_known_triple(&target_triple)
+    }
+
+    /// Work out how a build would be carried out, without running it
+    pub fn plan(&self, options: &BuildOptions) -> Result<BuildPlan> {
+        let target = self.select_target(options)?;
+        let host = Target::detect_host()?;
+
+        let strategy = strategy::select(&self.strategy_facts(&target, &host, options));
+        let native = target.triple == host.triple && strategy.strategy == Strategy::Native;
+        let mut warnings = Vec::new();
+        if options.glibc_version.is_some() && strategy.strategy != Strategy::Zig {
+            warnings.push("--glibc only applies to Zig builds, ignoring it".to_string());
+        }
+
+        let lockfile = Lockfile::discover()?;
+        let dependencies = match &lockfile {
+            Some(lockfile) => {
+                deps::resolve_for_target(&self.config.deps, lockfile, &target, &host)?
+            }
+            None => Vec::new(),
+        };
+
+        let cargo_config = CargoTargetConfig::find(&target.triple)?;
+        let runner = self.test_runner(&target, &host, &cargo_config);
+        Ok(BuildPlan {
+            feature_args: self.feature_args(&target, options, &[]),
+            image: self.container_image(&target.triple),
+            zig: Self::zig_supports(&target.triple),
+            target: target.triple,
+            host: host.triple,
+            path: strategy.strategy.to_string(),
+            strategy,
+            native,
+            warnings,
+            dependencies,
+            has_lockfile: lockfile.is_some(),
+            runner,
+        })
+    }
+
+    /// Image a container build for `triple` would run in
+    #[cfg(feature = "container")]
+    pub(super) fn container_image(&self, triple: &str) -> Option<String> {
+        use crate::container::ImageSelector;
+
+        if let Some(image) = self.config.container.images.get(triple) {
+            return Some(image.clone());
+        }
+        self.config
+            .container
+            .registry
+            .clone()
+            .map_or_else(ImageSelector::new, ImageSelector::with_registry)
+            .select_for_target(triple)
+            .ok()
+            .map(|image| image.full_name())
+    }
+
+    #[cfg(not(feature = "container"))]
+    pub(super) fn container_image(&self, _triple: &str) -> Option<String> {
+        None
+    }
+
+    /// Whether Zig can cross-compile to `triple`
+    fn zig_supports(triple: &str) -> bool {
+        #[cfg(feature = "zig")]
+        return ZigToolchain::supports_target_name(triple);
+        #[cfg(not(feature = "zig"))]
+        {
+            let _ = triple;
+            false
+        }
+    }
+
+    /// What runs test binaries for `target` on `host`: a configured or
+    /// environment `CARGO_TARGET_<TRIPLE>_RUNNER`, cargo's configured
+    /// runner, the host itself, a WASI runtime, QEMU or an iOS simulator
+    fn test_runner(
+        &self,
+        target: &Target,
+        host: &Target,
+        cargo_config: &CargoTargetConfig,
+    ) -> Option<String> {
+        let runner_var = format!(
+            "CARGO_TARGET_{}_RUNNER",
+            target.triple.to_uppercase().replace('-', "_")
+        );
+        let configured = self
+            .config
+            .get_target_config(&target.triple)
+            .and_then(|config| config.env.get(&runner_var).cloned());
+        if let Some(runner) = configured.or_else(|| std::env::var(&runner_var).ok()) {
+            return Some(runner);
+        }
+        if let Some(runner) = &cargo_config.runner {
+            return Some(runner.value.join(" "));
+        }
+
+        let on_mac = host.os == "macos";
+        if target.triple == host.triple || (on_mac && apple::is_mac_catalyst(target)) {
+            Some("native".to_string())
+        } else if wasm::is_wasi(target) {
+            wasm::wasi_runner().map(|runner| format!("{runner} (WASI)"))
+        } else if let Some(runner) = qemu::runner(target, host) {
+            Some(format!("{runner} (QEMU)"))
+        } else if on_mac && apple::is_simulator(target) {
+            let device = self
+                .config
+                .get_target_config(&target.triple)
+                .and_then(|config| config.simulator.clone())
+                .unwrap_or_else(|| "newest available".to_string());
+            Some(format!("xcrun simctl spawn (iOS simulator: {device})"))
+        } else {
+            None
+        }
+    }
+
+    /// Whether builds with `options` may not use the network
+    pub(super) fn is_offline(&self, options: &BuildOptions) -> bool {
+        options.offline || self.config.build.offline
+    }
+
+    /// Whether xcargo adds `--offline` to cargo's arguments, unless they
+    /// already have it
+    fn passes_offline(&self, options: &BuildOptions) -> bool {
+        self.is_offline(options)
+            && !options
+                .cargo_args
+                .iter()
+                .chain(&self.config.build.cargo_flags)
+                .any(|arg| arg == "--offline")
+    }
+
+    /// Whether `target` builds in a container rather than with the local
+    /// toolchain
+    fn uses_container(&self, target: &Target, options: &BuildOptions) -> Result<bool> {
+        Ok(self.select_strategy(target, options)?.strategy == Strategy::Container)
+    }
+
+    /// Choose how to build `target`, see [`strategy`]
+    pub(super) fn select_strategy(
+        &self,
+        target: &Target,
+        options: &BuildOptions,
+    ) -> Result<Selection> {
+        let host = Target::detect_host()?;
+        Ok(strategy::select(
+            &self.strategy_facts(target, &host, options),
+        ))
+    }
+
+    /// Print and log why `selection` was made for `target`
+    fn report_strategy(target: &Target, selection: &Selection, options: &BuildOptions) {
+        tracing::info!(
+            strategy = %selection.strategy,
+            reason = %selection.reason,
+            "selected build strategy"
+        );
+        if options.verbose > 0 {
+            helpers::info(format!(
+                "Strategy for {}: {} ({})",
+                target.triple, selection.strategy, selection.reason
+            ));
+        }
+    }
+
+    /// What the strategy for `target` is chosen from
+    fn strategy_facts(&self, target: &Target, host: &Target, options: &BuildOptions) -> Facts {
+        let is_host = target.triple == host.triple;
+        let target_config = self.config.get_target_config(&target.triple);
+        let section = format!("[targets.\"{}\"]", target.triple);
+        let use_when = self.config.container.use_when.as_str();
+        // Xcode's SDKs exist on macOS only, never in a container
+        let xcode = host.os == "macos" && apple::AppleSdk::for_target(target).is_some();
+
+        let mut excluded = Vec::new();
+        if options.use_zig == Some(false) {
+            excluded.push((Strategy::Zig, "--no-zig".to_string()));
+        }
+        if xcode {
+            excluded.push((
+                Strategy::Container,
+                "Xcode, which only runs on macOS".to_string(),
+            ));
+        } else if use_when == "never" {
+            excluded.push((
+                Strategy::Container,
+                "[container] use_when = \"never\"".to_string(),
+            ));
+        }
+
+        let forced = if options.use_container {
+            Some((Strategy::Container, "--container".to_string()))
+        } else if options.use_zig == Some(true) {
+            Some((Strategy::Zig, "--zig".to_string()))
+        } else if let Some(strategy) = target_config.and_then(|config| config.strategy.clone()) {
+            Some((strategy, format!("{section} strategy")))
+        } else if target_config.and_then(|config| config.force_container) == Some(true) {
+            Some((Strategy::Container, format!("{section} force_container")))
+        } else if is_host {
+            // Project-wide defaults are for cross-compiling
+            None
+        } else if let Some(strategy) = self.config.build.strategy.clone() {
+            let source = match &self.config.profile {
+                Some(profile) => format!("profile '{profile}'"),
+                None => "[build] strategy".to_string(),
+            };
+            Some((strategy, source))
+        } else if !xcode
+            && cfg!(feature = "container")
+            && (use_when == "always"
+                // WebAssembly has no OS to need a container for
+                || (use_when == "target.os != host.os"
+                    && target.os != host.os
+                    && !wasm::is_wasm(target)))
+        {
+            Some((
+                Strategy::Container,
+                format!("[container] use_when = \"{use_when}\""),
+            ))
+        } else {
+            None
+        };
+
+        Facts {
+            is_host,
+            forced,
+            excluded,
+            prefer_zig: self.zig_preference(target, options),
+            native: self.native_availability(target, host, options),
+            zig: self.zig_availability(target),
+            container: self.container_availability(target),
+            nix: self.nix_strategy(target),
+            plugins: self.plugin_strategies(target, options),
+        }
+    }
+
+    /// The project's Nix shell, from the current directory up
+    fn nix_shell() -> Option<NixShell> {
+        std::env::current_dir()
+            .ok()
+            .and_then(|dir| NixShell::find(&dir))
+    }
+
+    /// The Nix strategy's priority and whether it can build `target`: on
+    /// with a `flake.nix` or `shell.nix`, or `[strategies.nix] enable`
+    fn nix_strategy(&self, target: &Target) -> Option<(i32, Availability)> {
+        let config = self
+            .config
+            .strategies
+            .get(Strategy::Nix.as_str())
+            .cloned()
+            .unwrap_or_default();
+        let shell = Self::nix_shell();
+        if config.enable == Some(false) || (shell.is_none() && config.enable != Some(true)) {
+            return None;
+        }
+        let applies = config.targets.is_empty()
+            || config
+                .targets
+                .iter()
+                .any(|pattern| deps::rules::matches_target(pattern, &target.triple));
+        if !applies {
+            return None;
+        }
+
+        let available = match shell {
+            None => Err("the project has no flake.nix or shell.nix".to_string()),
+            Some(shell) if which::which(shell.program()).is_err() => {
+                Err(format!("{} is not installed", shell.program()))
+            }
+            Some(shell) => Ok(format!(
+                "{} provides the toolchain",
+                shell
+                    .file()
+                    .file_name()
+                    .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+            )),
+        };
+        Some((
+            config.priority.unwrap_or_else(|| Strategy::Nix.priority()),
+            available,
+        ))
+    }
+
+    /// Plugin strategies that apply to `target`, and whether they can build it
+    fn plugin_strategies(&self, target: &Target, options: &BuildOptions) -> Vec<PluginFacts> {
+        let ctx = Self::plugin_context(target, options);
+        self.plugins
+            .strategies()
+            .into_iter()
+            .filter_map(|(name, plugin)| {
+                let config = self
+                    .config
+                    .strategies
+                    .get(&name)
+                    .cloned()
+                    .unwrap_or_default();
+                let applies = config.targets.is_empty()
+                    || config
+                        .targets
+                        .iter()
+                        .any(|pattern| deps::rules::matches_target(pattern, &target.triple));
+                applies.then(|| PluginFacts {
+                    available: plugin
+                        .strategy_available(&name, &ctx)
+                        .map_err(|e| e.to_string()),
+                    name,
+                    priority: config.priority.unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+
+    /// The command a plugin strategy runs in place of cargo's `cmd`
+    fn plugin_command(
+        &self,
+        strategy: &str,
+        target: &Target,
+        options: &BuildOptions,
+        cmd: &Command,
+    ) -> Result<Command> {
+        let Some(plugin) = self.plugins.strategy_provider(strategy) else {
+            return Err(Error::Config(format!(
+                "No plugin provides the strategy '{strategy}' chosen for {}",
+                target.triple
+            )));
+        };
+        let ctx = Self::plugin_context(target, options);
+        let command = plugin.strategy_command(strategy, &ctx, BuildCommand::from_command(cmd))?;
+        if options.verbose > 0 {
+            helpers::info(format!(
+                "Plugin {} runs the {strategy} build",
+                plugin.name()
+            ));
+        }
+        Ok(command.to_command())
+    }
+
+    /// Why only Zig does what was asked for `target`, if so
+    fn zig_preference(&self, target: &Target, options: &BuildOptions) -> Option<String> {
+        let target_config = self.config.get_target_config(&target.triple);
+        let glibc = options
+            .glibc_version
+            .as_deref()
+            .or_else(|| target_config.and_then(|config| config.glibc_version.as_deref()));
+        if let Some(version) = glibc.filter(|_| glibc::is_glibc_target(target)) {
+            return Some(format!("only Zig links against glibc {version}"));
+        }
+        if self.links_statically(target, options) && static_link::is_musl(target) {
+            return Some("Zig is also a musl C compiler".to_string());
+        }
+        None
+    }
+
+    /// Whether the local toolchain has the linker and tools `target` needs
+    fn native_availability(
+        &self,
+        target: &Target,
+        host: &Target,
+        options: &BuildOptions,
+    ) -> Availability {
+        if host.os == "macos" && apple::AppleSdk::for_target(target).is_some() {
+            return Ok("Xcode builds Apple targets".to_string());
+        }
+        let configured = self
+            .config
+            .get_target_config(&target.triple)
+            .and_then(|config| config.linker.as_deref());
+        if let Some(linker) = configured {
+            return if which::which(linker).is_ok() {
+                Ok(format!("configured linker {linker} is installed"))
+            } else {
+                Err(format!("configured linker {linker} is not installed"))
+            };
+        }
+        if self.links_statically(target, options) && static_link::is_musl(target) {
+            return static_link::find_musl_compiler(target, host)
+                .map(|compiler| format!("musl C compiler {compiler} is installed"))
+                .ok_or_else(|| "no musl C compiler is installed".to_string());
+        }
+
+        if let Some(found) = provision::find_linker(&target.triple) {
+            return Ok(format!("linker {found} is installed"));
+        }
+        let requirements = target.get_requirements();
+        if let Some(linker) = requirements.linker {
+            return match which::which(&linker).is_ok().then_some(linker.clone()) {
+                Some(found) => Ok(format!("linker {found} is installed")),
+                None => Err(format!("linker {linker} is not installed")),
+            };
+        }
+        let missing: Vec<&str> = requirements
+            .tools
+            .iter()
+            .map(String::as_str)
+            .filter(|tool| which::which(tool).is_err())
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!("{} not installed", missing.join(", ")));
+        }
+        let needs_os_linker =
+            !matches!(target.os.as_str(), "none" | "unknown") && !target.arch.starts_with("wasm");
+        if target.os != host.os && needs_os_linker {
+            return Err(format!(
+                "no linker for {} targets on a {} host",
+                target.os, host.os
+            ));
+        }
+        Ok("needs no cross linker".to_string())
+    }
+
+    /// Whether Zig is installed and supports `target`
+    fn zig_availability(&self, target: &Target) -> Availability {
+        if !self.has_zig() {
+            return Err("Zig is not installed".to_string());
+        }
+        if !Self::zig_supports(&target.triple) {
+            return Err(format!("Zig does not support {}", target.triple));
+        }
+        Ok(format!("Zig supports {}", target.triple))
+    }
+
+    /// Whether a container runtime and an image for `target` are available
+    fn container_availability(&self, target: &Target) -> Availability {
+        if !cfg!(feature = "container") {
+            return Err("container support is not compiled in".to_string());
+        }
+        let Some(image) = self.container_image(&target.triple) else {
+            return Err(format!("no container image for {}", target.triple));
+        };
+        let runtimes: &[&str] = match self.config.container.runtime.as_str() {
+            "docker" => &["docker"],
+            "podman" => &["podman"],
+            "nerdctl" | "containerd" => &["nerdctl"],
+            _ => &["docker", "podman", "nerdctl"],
+        };
+        match runtimes
+            .iter()
+            .find(|runtime| which::which(runtime).is_ok())
+        {
+            Some(runtime) => Ok(format!("{runtime} can run {image}")),
+            None => Err(format!("{} is not installed", runtimes.join(" or "))),
+        }
+    }
+
+    /// Install the toolchain, target and components the build needs, unless
+    /// `--no-install` forbids it
+    fn prepare_toolchain(
+        &self,
+        toolchain: &str,
+        target: &Target,
+        options: &BuildOptions,
+    ) -> Result<()> {
+        let manager = &self.toolchain_manager;
+        if !manager.is_toolchain_installed(toolchain)? {
+            Self::allow_install(
+                options,
+                &format!("Toolchain {toolchain} is not installed"),
+                &format!("rustup toolchain install {toolchain}"),
+            )?;
+            manager.install_toolchain(toolchain)?;
+        }
+
+        self.install_missing_targets(std::slice::from_ref(&target.triple), options)?;
+
+        for component in self.required_components(options) {
+            if !manager.is_component_installed(toolchain, component)? {
+                Self::allow_install(
+                    options,
+                    &format!("Component {component} is not installed for toolchain {toolchain}"),
+                    &format!("rustup component add {component} --toolchain {toolchain}"),
+                )?;
+                manager.install_component(toolchain, component)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fail with `problem` and the `command` that fixes it under
+    /// `--no-install`
+    fn allow_install(options: &BuildOptions, problem: &str, command: &str) -> Result<()> {
+        if !options.no_install {
+            return Ok(());
+        }
+        helpers::hint(format!("Install it with: {command}"));
+        Err(Error::Toolchain(format!(
+            "{problem}, and --no-install forbids installing it"
+        )))
+    }
+
+    /// Add the rustup targets of `targets` that are missing, asking first
+    /// unless `toolchain.auto_install_targets` is set
+    ///
+    /// Targets built in containers need none. Multi-target builds call this
+    /// before starting, so there is one question for all targets.
+    pub(super) fn install_missing_targets(
+        &self,
+        targets: &[String],
+        options: &BuildOptions,
+    ) -> Result<()> {
+        let toolchain = options.toolchain.as_deref().unwrap_or("stable");
+        // A missing toolchain is installed, with its targets, per build
+        if !self.toolchain_manager.is_toolchain_installed(toolchain)? {
+            return Ok(());
+        }
+
+        let installed = self.toolchain_manager.list_targets(toolchain)?;
+        let mut missing = Vec::new();
+        for triple in targets {
+            let Ok(target) = Target::from_triple(triple) else {
+                continue;
+            };
+            if !installed.contains(triple) && !self.uses_container(&target, options)? {
+                missing.push(triple.as_str());
+            }
+        }
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let command = format!(
+            "rustup target add {} --toolchain {toolchain}",
+            missing.join(" ")
+        );
+        Self::allow_install(
+            options,
+            &format!("Target {} is not installed", missing.join(", ")),
+            &command,
+        )?;
+        if !self.config.toolchain.auto_install_targets && !confirm_install(&missing, toolchain)? {
+            helpers::hint(format!("Install it with: {command}"));
+            helpers::tip("Set [toolchain] auto_install_targets = true to install without asking");
+            return Err(Error::Cancelled);
+        }
+
+        for triple in missing {
+            self.toolchain_manager.install_target(toolchain, triple)?;
+        }
+        Ok(())
+    }
+
+    /// A cross GCC for `target` from [`provision`], offering to install one
+    ///
+    /// Only asked at a terminal, and never with `--no-install` or offline;
+    /// otherwise the usual hints about the missing linker follow.
+    fn provision_linker(
+        &self,
+        target: &Target,
+        options: &BuildOptions,
+        checks_linker: bool,
+    ) -> Result<Option<String>> {
+        if let Some(linker) = provision::find_linker(&target.triple) {
+            return Ok(Some(linker));
+        }
+        if !checks_linker || options.no_install || self.is_offline(options) {
+            return Ok(None);
+        }
+        let Some(provision) = Provision::plan(&target.triple, false) else {
+            return Ok(None);
+        };
+        if !confirm_provision(&provision)? {
+            helpers::tip(format!(
+                "Install it later with: xcargo toolchain provision {}",
+                target.triple
+            ));
+            return Ok(None);
+        }
+        provision.run(&self.config.network).map(Some)
+    }
+
+    /// Whether binaries for `target` are linked statically
+    fn links_statically(&self, target: &Target, options: &BuildOptions) -> bool {
+        options.static_link
+            || self
+                .config
+                .get_target_config(&target.triple)
+                .is_some_and(|config| config.static_link)
+    }
+
+    /// The musl C compiler a static build of `target` uses without Zig
+    fn musl_compiler(target: &Target) -> Result<String> {
+        let host = Target::detect_host()?;
+        if let Some(compiler) = static_link::find_musl_compiler(target, &host) {
+            return Ok(compiler);
+        }
+
+        let compilers = static_link::musl_compilers(target, &host);
+        helpers::hint(
+            "Install Zig, or a musl C compiler (musl-tools on Debian/Ubuntu for musl-gcc)",
+        );
+        helpers::tip(format!(
+            "Or build in a container: xcargo build --target {} --static --container",
+            target.triple
+        ));
+        Err(Error::Toolchain(format!(
+            "Static builds for {} need Zig or a musl C compiler ({})",
+            target.triple,
+            compilers.join(" or ")
+        )))
+    }
+
+    /// `RUSTFLAGS` for `target`, when xcargo adds to what cargo would use
+    /// anyway: `[targets.<triple>] rustflags`, crt-static when it is linked
+    /// statically and `--rustflags`, merged over cargo's configured ones
+    /// and the caller's, see [`RustFlags`]
+    fn rustflags(
+        &self,
+        target: &Target,
+        options: &BuildOptions,
+        cargo_config: &CargoTargetConfig,
+    ) -> Option<RustFlags> {
+        let configured = self
+            .config
+            .get_target_config(&target.triple)
+            .and_then(|config| config.rustflags.clone())
+            .unwrap_or_default();
+        let static_flags = if self.links_statically(target, options) {
+            static_link::with_crt_static(target, Vec::new())
+        } else {
+            Vec::new()
+        };
+        let coverage_flags: Vec<String> = match options.coverage {
+            Some(_) => coverage::RUSTFLAGS
+                .iter()
+                .map(|flag| (*flag).to_string())
+                .collect(),
+            None => Vec::new(),
+        };
+        if configured.is_empty()
+            && static_flags.is_empty()
+            && coverage_flags.is_empty()
+            && options.rustflags.is_empty()
+        {
+            return None;
+        }
+
+        let mut flags = RustFlags::default();
+        if let Some(inherited) = &cargo_config.rustflags {
+            let source = FlagSource::CargoConfig(inherited.path.clone());
+            flags.add(&source, &inherited.value);
+        }
+        if let Ok(caller) = std::env::var("RUSTFLAGS") {
+            let caller: Vec<String> = caller.split_whitespace().map(str::to_string).collect();
+            flags.add(&FlagSource::Environment, &caller);
+        }
+        flags.add(&FlagSource::Config, &configured);
+        flags.add(&FlagSource::Static, &static_flags);
+        flags.add(&FlagSource::Coverage, &coverage_flags);
+        flags.add(&FlagSource::CommandLine, &options.rustflags);
+        Some(flags)
+    }
+
+    /// Print where each rustflag comes from and which ones were dropped
+    fn report_rustflags(rustflags: &RustFlags) {
+        for (flag, source) in rustflags.sources() {
+            helpers::hint(format!("{flag} ({source})"));
+        }
+        for dropped in rustflags.overrides() {
+            helpers::info(format!("Rustflag {dropped}"));
+        }
+    }
+
+    /// Toolchain components the build needs besides the target: `rust-src`
+    /// for `-Zbuild-std`, and `llvm-tools` for gates running cargo-binutils
+    /// and for coverage reports
+    fn required_components(&self, options: &BuildOptions) -> Vec<&'static str> {
+        let mut components = Vec::new();
+        if toolchain::uses_build_std(
+            options
+                .cargo_args
+                .iter()
+                .chain(&self.config.build.cargo_flags),
+        ) {
+            components.push(toolchain::RUST_SRC);
+        }
+        let gates_use_llvm_tools = options.operation == CargoOperation::Build
+            && self
+                .config
+                .artifacts
+                .gates
+                .iter()
+                .any(|gate| toolchain::uses_llvm_tools(&gate.command));
+        if gates_use_llvm_tools || options.coverage.is_some() {
+            components.push(toolchain::LLVM_TOOLS);
+        }
+        components
+    }
+
+    /// Fail, listing all that is missing, when building `targets` offline
+    /// would need the network
+    pub(super) fn check_offline(&self, targets: &[String], options: &BuildOptions) -> Result<()> {
+        let mut missing = Vec::new();
+        for target in targets {
+            let mut target_options = options.clone();
+            target_options.target = Some(target.clone());
+            missing.extend(self.missing_offline(&target_options)?);
+        }
+        offline::report(&missing)
+    }
+
+    /// What building `options.target` would download
+    fn missing_offline(&self, options: &BuildOptions) -> Result<Vec<Missing>> {
+        let plan = self.plan(options)?;
+        let target = plan.target.as_str();
+
+        // Container images bring their own toolchain and native libraries
+        if plan.path == "container" {
+            return Ok(self.missing_image(target).into_iter().collect());
+        }
+
+        let mut missing = Vec::new();
+        let toolchain = options.toolchain.as_deref().unwrap_or("stable");
+        let components = self.required_components(options);
+        if self.toolchain_manager.is_toolchain_installed(toolchain)? {
+            if !self
+                .toolchain_manager
+                .is_target_installed(toolchain, target)?
+            {
+                missing.push(Missing::new(
+                    target,
+                    format!("Rust target for toolchain {toolchain}"),
+                    format!("rustup target add {target} --toolchain {toolchain}"),
+                ));
+            }
+            for component in components {
+                if !self
+                    .toolchain_manager
+                    .is_component_installed(toolchain, component)?
+                {
+                    missing.push(Missing::new(
+                        target,
+                        format!("component {component} for toolchain {toolchain}"),
+                        format!("rustup component add {component} --toolchain {toolchain}"),
+                    ));
+                }
+            }
+        } else {
+            let mut install = format!("rustup toolchain install {toolchain} --target {target}");
+            if !components.is_empty() {
+                install = format!("{install} --component {}", components.join(","));
+            }
+            missing.push(Missing::new(
+                target,
+                format!("toolchain {toolchain}"),
+                install,
+            ));
+        }
+
+        if !plan.native && options.use_zig == Some(true) && !self.has_zig() {
+            missing.push(Missing::new(
+                target,
+                "Zig, requested with --zig",
+                "Install Zig from https://ziglang.org/download/",
+            ));
+        }
+
+        for resolution in &plan.dependencies {
+            if let Some(fetch) = resolution.fetch.as_ref().filter(|f| !f.is_complete()) {
+                missing.push(Missing::new(
+                    target,
+                    format!("{} download from {}", resolution.name, fetch.url),
+                    format!("xcargo build --target {target}"),
+                ));
+            }
+        }
+        Ok(missing)
+    }
+
+    /// The container image for `target`, if it is not pulled
+    #[cfg(feature = "container")]
+    fn missing_image(&self, target: &str) -> Option<Missing> {
+        use crate::container::{ContainerBuilder, RuntimeType};
+
+        let runtime_type =
+            RuntimeType::from_str(&self.config.container.runtime).unwrap_or(RuntimeType::Auto);
+        // Without a runtime or image the build reports why on its own
+        let container_builder = ContainerBuilder::new(runtime_type).ok()?;
+        let image = self.container_image(target)?;
+        (!container_builder.has_image(&image)).then(|| {
+            Missing::new(
+                target,
+                format!("container image {image}"),
+                format!("{} pull {image}", container_builder.runtime_name()),
+            )
+        })
+    }
+
+    #[cfg(not(feature = "container"))]
+    fn missing_image(&self, _target: &str) -> Option<Missing> {
+        None
+    }
+
+    /// Explain how a build would be set up without running it
+    ///
+    /// Shows the build path and every native dependency decision, including
+    /// the env vars and features xcargo would pass to cargo.
+    pub fn explain(&self, options: &BuildOptions) -> Result<()> {
+        helpers::section("xcargo explain");
+
+        let plan = self.plan(options)?;
+        println!("  Target: {}", plan.target);
+        println!("  Host:   {}", plan.host);
+        println!(
+            "  Build:  {} ({})",
+            plan.path_description(),
+            plan.strategy.reason
+        );
+        for warning in &plan.warnings {
+            helpers::warning(warning);
+        }
+
+        if !plan.has_lockfile {
+            helpers::hint(
+                "No Cargo.lock found; run 'cargo generate-lockfile' to detect native dependencies",
+            );
+        }
+        if plan.dependencies.is_empty() {
+            helpers::info("No native dependencies need special handling");
+        }
+        for resolution in &plan.dependencies {
+            println!();
+            helpers::info(format!(
+                "{}: {} ({})",
+                resolution.name, resolution.strategy, resolution.reason
+            ));
+            for note in &resolution.notes {
+                println!("    {note}");
+            }
+            for (key, value) in &resolution.env {
+                println!("    env {key}={value}");
+            }
+            for feature in &resolution.features {
+                println!("    feature {feature}");
+            }
+            if let Some(fetch) = &resolution.fetch {
+                println!("    download {}", fetch.url);
+            }
+        }
+
+        if !plan.feature_args.is_empty() {
+            println!();
+            helpers::info(format!(
+                "Cargo feature flags: {}",
+                plan.feature_args.join(" ")
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Print how the build strategy is chosen, without building
+    pub fn explain_strategy(&self, options: &BuildOptions) -> Result<()> {
+        let target = self.select_target(options)?;
+        let selection = self.select_strategy(&target, options)?;
+        helpers::info(format!(
+            "{}: {} ({})",
+            target.triple, selection.strategy, selection.reason
+        ));
+        for step in &selection.steps {
+            let mark = if step.available { "✓" } else { "✗" };
+            println!("    {mark} {:<10} {}", step.strategy.as_str(), step.reason);
+        }
+        Ok(())
+    }
+
+    /// Whether each of `triples` is installed and could be built here, and
+    /// with what, see [`super::matrix`]
+    pub fn matrix(&self, triples: &[String], options: &BuildOptions) -> Result<Vec<MatrixRow>> {
+        let host = Target::detect_host()?;
+        // Without rustup nothing counts as installed, the rest still applies
+        let installed = Target::detect_installed().unwrap_or_default();
+        triples
+            .iter()
+            .map(|triple| {
+                let target = Target::from_triple(triple)?;
+                let facts = self.strategy_facts(&target, &host, options);
+                let selection = strategy::select(&facts);
+                let buildable = facts.is_host
+                    || selection
+                        .steps
+                        .iter()
+                        .any(|step| step.strategy == selection.strategy && step.available);
+                Ok(MatrixRow {
+                    target: target.triple.clone(),
+                    installed: installed.iter().any(|t| t.triple == target.triple),
+                    native: facts.native.into(),
+                    zig: facts.zig.into(),
+                    container: facts.container.into(),
+                    strategy: selection.strategy,
+                    buildable,
+                })
+            })
+            .collect()
+    }
+
+    /// Run `cargo clean` for one target
+    fn clean(&self, target: &Target, options: &BuildOptions) -> Result<()> {
+        let mut cmd = Command::new("cargo");
+        if let Some(toolchain) = &options.toolchain {
+            cmd.arg(format!("+{toolchain}"));
+        }
+        cmd.arg("clean").arg("--target").arg(&target.triple);
+        if options.release {
+            cmd.arg("--release");
+        }
+        if options.cargo_verbose() {
+            cmd.arg("--verbose");
+        }
+        cmd.args(&options.cargo_args);
+
+        print_command(&cmd, options);
+        let status = self.run_cargo(&mut cmd, options, OutputHandling::default())?;
+
+        if status.success() {
+            helpers::success(format!("Cleaned {}", target.triple));
+            Ok(())
+        } else {
+            Err(Error::Build(format!(
+                "{} failed for target {}",
+                options.operation.description(),
+                target.triple
+            )))
+        }
+    }
+
+    /// Run cargo, stopping it on cancellation or after the build timeout
+    ///
+    /// When cargo's output is logged (e.g., to `--log-file`), it is captured
+    /// line by line within the current target's span. See
+    /// [`OutputHandling`] for what else is done with cargo's output.
+    fn run_cargo(
+        &self,
+        cmd: &mut Command,
+        options: &BuildOptions,
+        handling: OutputHandling,
+    ) -> Result<ExitStatus> {
+        // Stopping cargo stops rustc, linkers and build scripts too
+        let limits = RunLimits {
+            process_group: true,
+            ..self.run_limits(options)?
+        };
+        tracing::debug!(command = ?cmd, "running cargo");
+
+        let logged = tracing::enabled!(target: output::CARGO_OUTPUT_TARGET, tracing::Level::INFO);
+        let result = if logged || handling.is_captured() {
+            let span = tracing::Span::current();
+            let json = handling.is_json();
+            let live = handling
+                .diagnostics
+                .as_ref()
+                .map_or(true, |(diagnostics, _)| diagnostics.is_live());
+            // Rendered diagnostics keep their colors only where they show
+            let colored = handling.annotations.is_some()
+                || (!output::is_plain() && std::io::stderr().is_terminal());
+            // Cargo only colors its output on a terminal
+            if colored && std::env::var_os("CARGO_TERM_COLOR").is_none() {
+                cmd.env("CARGO_TERM_COLOR", "always");
+            }
+            cancel::run_with_output(cmd, &limits, move |stream, line| {
+                if stream == Stream::Stdout {
+                    if let Some(bench) = &handling.bench {
+                        bench.observe(line);
+                    }
+                }
+                let timing = stream == Stream::Stdout
+                    && handling
+                        .timings
+                        .as_ref()
+                        .is_some_and(|timings| timings.accept(line));
+                if timing {
+                    return;
+                }
+                let shown = if json && stream == Stream::Stdout {
+                    if let Some((diagnostics, target)) = &handling.diagnostics {
+                        diagnostics.record_line(target, line);
+                    }
+                    let mut shown = ci::translate(line, handling.annotations);
+                    if let Some(explain) = &handling.explain {
+                        for (_, text) in &shown {
+                            explain.accept(text);
+                        }
+                    }
+                    // Parallel builds show compiler messages in the summary
+                    if !live {
+                        shown.retain(|(stream, _)| *stream == Stream::Stdout);
+                    }
+                    if !colored {
+                        for (_, text) in &mut shown {
+                            *text = output::strip_ansi(text);
+                        }
+                    }
+                    shown
+                } else {
+                    if let Some(explain) = &handling.explain {
+                        explain.accept(line);
+                    }
+                    vec![(stream, line.to_string())]
+                };
+                for (stream, text) in shown {
+                    cancel::echo(stream, &text);
+                    if logged {
+                        span.in_scope(|| {
+                            tracing::info!(
+                                target: output::CARGO_OUTPUT_TARGET,
+                                stream = stream.as_str(),
+                                "{}",
+                                output::strip_ansi(&text)
+                            );
+                        });
+                    }
+                }
+            })
+        } else {
+            cancel::run(cmd, &limits)
+        };
+        let status = result.map_err(|e| match e {
+            Error::Io(e) => Error::Build(format!("Failed to execute cargo: {e}")),
+            e => e,
+        })?;
+        tracing::debug!(code = status.code(), "cargo finished");
+        trace::record_command(cmd, status.code());
+        Ok(status)
+    }
+
+    /// Keep the compile times of `target`'s build for the `--timings` report
+    fn save_timings(target: &Target, options: &BuildOptions, collector: &timings::Collector) {
+        let profile = if options.release { "release" } else { "debug" };
+        let recorded = TargetTimings::new(&target.triple, profile, collector.units());
+        if let Err(e) = timings::timings_dir().and_then(|dir| timings::save(&dir, &recorded)) {
+            helpers::warning(format!("Could not save compile timings: {e}"));
+        }
+    }
+
+    /// Coverage files of `target`, emptied of the last run's profiles
+    ///
+    /// Fails when nothing here can run the target's tests.
+    fn prepare_coverage(
+        &self,
+        target: &Target,
+        native: bool,
+        cargo_config: &CargoTargetConfig,
+    ) -> Result<TargetCoverage> {
+        let host = Target::detect_host()?;
+        // Rosetta runs x86_64 binaries on Apple silicon
+        let runs = native || (target.os == "macos" && host.os == "macos");
+        if !runs && self.test_runner(target, &host, cargo_config).is_none() {
+            helpers::hint(format!(
+                "Install QEMU user mode (qemu-user), set CARGO_TARGET_{}_RUNNER or build in a container",
+                target.triple.to_uppercase().replace('-', "_")
+            ));
+            return Err(Error::Toolchain(format!(
+                "Nothing can run {} tests here, so there is no coverage to collect",
+                target.triple
+            )));
+        }
+        let coverage = TargetCoverage::for_target(&target.triple)?;
+        coverage.prepare()?;
+        Ok(coverage)
+    }
+
+    /// Merge the profiles of `coverage`'s target and print the report
+    fn report_coverage(
+        coverage: &TargetCoverage,
+        options: &BuildOptions,
+        format: coverage::Format,
+        remapped: Option<(&str, &Path)>,
+    ) -> Result<()> {
+        let profile = if options.release { "release" } else { "debug" };
+        helpers::progress("Merging coverage data...");
+        let summary = coverage.report(profile, format, options.toolchain.as_deref(), remapped)?;
+        coverage.print_summary(&summary, format);
+        Ok(())
+    }
+
+    /// Keep the phase times of `target`'s build for `xcargo timings`
+    fn save_phases(target: &Target, options: &BuildOptions, stopwatch: Stopwatch) {
+        let profile = if options.release { "release" } else { "debug" };
+        let recorded = stopwatch.finish(&target.triple, options.operation.as_str(), profile);
+        if let Err(e) = phases::phases_dir().and_then(|dir| phases::save(&dir, &recorded)) {
+            tracing::debug!(error = %e, "could not record the build phases");
+        }
+    }
+
+    /// Keep the benchmark results of `target` for the cross-target report
+    fn save_bench(target: &Target, collector: &bench::Collector) {
+        let results = collector.results();
+        if results.is_empty() {
+            return;
+        }
+        let recorded = TargetBench::new(&target.triple, results);
+        if let Err(e) = bench::bench_dir().and_then(|dir| bench::save(&dir, &recorded)) {
+            helpers::warning(format!("Could not save benchmark results: {e}"));
+        }
+    }
+
+    /// Cancellation and timeout for one target's build
+    ///
+    /// `--timeout` takes precedence over `build.timeout`.
+    pub(super) fn run_limits(&self, options: &BuildOptions) -> Result<RunLimits> {
+        let timeout = match (options.timeout, &self.config.build.timeout) {
+            (Some(timeout), _) => Some(timeout),
+            (None, Some(timeout)) => Some(cancel::parse_duration(timeout).ok_or_else(|| {
+                Error::Config(format!("build.timeout '{timeout}' is not a duration"))
+            })?),
+            (None, None) => None,
+        };
+        Ok(RunLimits {
+            cancel: self.cancel.clone(),
+            timeout,
+            process_group: false,
+        })
+    }
+
+    /// Place final binaries in the configured `[artifacts] dir` and run
+    /// their pre-publish gates
+    fn collect_artifacts(&self, target: &Target, options: &BuildOptions) -> Result<()> {
+        let Some(dir) = &self.config.artifacts.dir else {
+            return Ok(());
+        };
+
+        let profile = if options.release { "release" } else { "debug" };
+        let (target_dir, mut products) = artifacts::workspace_products()?;
+        products.retain(|product| options.selection.includes(product));
+        let collector = ArtifactCollector::new(dir, &self.config.artifacts.mode);
+        let mut placed = collector.collect(target, profile, &target_dir, &products)?;
+
+        if placed.is_empty() {
+            helpers::warning(format!(
+                "No binaries or C libraries found in {}",
+                target_dir.join(&target.triple).join(profile).display()
+            ));
+        } else {
+            helpers::success(format!(
+                "{} artifact(s) placed in {}",
+                placed.len(),
+                collector.dir().join(&target.triple).join(profile).display()
+            ));
+            if options.verbose > 0 {
+                for artifact in &placed {
+                    helpers::info(format!("{} ({} bytes)", artifact.path, artifact.size));
+                }
+            }
+
+            let gates = Gates::new(
+                &self.config.artifacts.gates,
+                &self.plugins,
+                self.run_limits(options)?,
+            );
+            if !gates.is_empty() {
+                helpers::progress("Running artifact gates...".to_string());
+                gates.check_all(collector.dir(), &target.triple, profile, &mut placed)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the modules of a WebAssembly target through wasm-bindgen and
+    /// wasm-opt into the configured `[artifacts.wasm] dir`
+    fn export_wasm(&self, target: &Target, options: &BuildOptions) -> Result<()> {
+        let Some(export) = WasmExport::new(&self.config.artifacts.wasm) else {
+            return Ok(());
+        };
+        if !wasm::is_wasm(target) {
+            return Ok(());
+        }
+
+        if export.uses_bindgen(target) {
+            let locked = Lockfile::discover()
+                .ok()
+                .flatten()
+                .and_then(|lockfile| lockfile.get("wasm-bindgen").map(|p| p.version.clone()));
+            let installed = Command::new("wasm-bindgen")
+                .arg("--version")
+                .output()
+                .ok()
+                .map(|output| String::from_utf8_lossy(&output.stdout).into_owned());
+            if let (Some(installed), Some(locked)) = (installed, locked) {
+                if let Some(warning) = wasm::bindgen_mismatch(&installed, &locked) {
+                    helpers::warning(warning);
+                }
+            }
+        }
+
+        let profile = if options.release { "release" } else { "debug" };
+        let (target_dir, mut products) = artifacts::workspace_products()?;
+        products.retain(|product| options.selection.includes(product));
+        let exported = export.export(target, profile, &target_dir, &products)?;
+
+        if exported.is_empty() {
+            helpers::warning(format!(
+                "No WebAssembly modules to export for {} (add crate-type = [\"cdylib\"] to [lib])",
+                target.triple
+            ));
+        } else {
+            helpers::success(format!(
+                "{} WebAssembly module{} {} to {}",
+                exported.len(),
+                if exported.len() == 1 { "" } else { "s" },
+                if export.optimizes(profile) {
+                    "optimized and exported"
+                } else {
+                    "exported"
+                },
+                export.dir().display()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Package the static libraries of an iOS or macOS target as
+    /// xcframeworks in the configured `[artifacts.apple] dir`
+    fn export_xcframeworks(&self, target: &Target, options: &BuildOptions) -> Result<()> {
+        let Some(export) = AppleExport::new(&self.config.artifacts.apple) else {
+            return Ok(());
+        };
+        if Slice::for_target(target).is_none() {
+            return Ok(());
+        }
+
+        let profile = if options.release { "release" } else { "debug" };
+        let (target_dir, mut products) = artifacts::workspace_products()?;
+        products.retain(|product| options.selection.includes(product));
+        let written = export.export(target, profile, &target_dir, &products)?;
+
+        if written.is_empty() {
+            helpers::warning(format!(
+                "No static libraries to package for {} (add crate-type = [\"staticlib\"] to [lib])",
+                target.triple
+            ));
+        }
+        for framework in &written {
+            helpers::success(format!("Packaged {}", framework.display()));
+        }
+        Ok(())
+    }
+
+    /// Write the `[embedded] formats` images of an embedded target's
+    /// binaries next to them
+    fn write_firmware_images(&self, target: &Target, options: &BuildOptions) -> Result<()> {
+        let embedded = &self.config.embedded;
+        if embedded.formats.is_empty() || !embedded::is_embedded(target) {
+            return Ok(());
+        }
+        let formats: Vec<ImageFormat> = embedded
+            .formats
+            .iter()
+            .filter_map(|name| ImageFormat::from_name(name))
+            .collect();
+        let family = embedded
+            .uf2_family
+            .as_deref()
+            .map(embedded::uf2_family)
+            .transpose()?;
+        if formats.contains(&ImageFormat::Uf2) && family.is_none() {
+            helpers::warning("No [embedded] uf2_family set; UF2 bootloaders may reject the image");
+        }
+
+        let profile = if options.release { "release" } else { "debug" };
+        let binaries = artifacts::built_binaries(target, profile)?;
+        if binaries.is_empty() {
+            return Ok(());
+        }
+        let objcopy = embedded::find_objcopy()?;
+        for elf in &binaries {
+            let images = formats
+                .iter()
+                .map(|format| embedded::write_image(&objcopy, elf, *format, family))
+                .collect::<Result<Vec<_>>>()?;
+            helpers::success(format!(
+                "Wrote {}",
+                images
+                    .iter()
+                    .map(|image| image.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    /// `CARGO_TARGET_<TRIPLE>_RUNNER` running WASI test binaries in wasmtime
+    /// or wasmer, and those of another Linux architecture under QEMU if it
+    /// is installed, unless a runner is already set
+    fn binary_runner(
+        &self,
+        target: &Target,
+        options: &BuildOptions,
+        cargo_config: &CargoTargetConfig,
+    ) -> Result<Option<(String, String)>> {
+        let runs_binaries = matches!(
+            options.operation,
+            CargoOperation::Test | CargoOperation::Bench
+        );
+        if !runs_binaries || target.is_host()? {
+            return Ok(None);
+        }
+        let runner_var = format!(
+            "CARGO_TARGET_{}_RUNNER",
+            target.triple.to_uppercase().replace('-', "_")
+        );
+        let configured = self
+            .config
+            .get_target_config(&target.triple)
+            .is_some_and(|config| config.env.contains_key(&runner_var));
+        if configured || cargo_config.runner.is_some() || std::env::var_os(&runner_var).is_some() {
+            return Ok(None);
+        }
+        if !wasm::is_wasi(target) {
+            let host = Target::detect_host()?;
+            return Ok(qemu::runner(target, &host).map(|runner| (runner_var, runner)));
+        }
+        match wasm::wasi_runner() {
+            Some(runner) => Ok(Some((runner_var, runner.to_string()))),
+            None => Err(wasm::missing_runtime(target)),
+        }
+    }
+
+    /// Lay out the cdylibs of an Android target in the configured
+    /// `[artifacts.android] dir` for Gradle and `CMakeLists.txt` consumers
+    fn export_android(&self, target: &Target, options: &BuildOptions) -> Result<()> {
+        let Some(export) = AndroidExport::new(&self.config.artifacts.android) else {
+            return Ok(());
+        };
+        let Some(abi) = android::abi(target) else {
+            return Ok(());
+        };
+
+        let profile = if options.release { "release" } else { "debug" };
+        let (target_dir, mut products) = artifacts::workspace_products()?;
+        products.retain(|product| options.selection.includes(product));
+        let exported = export.export(target, profile, &target_dir, &products)?;
+
+        let misaligned = AndroidExport::misaligned(target, &exported)?;
+        for (library, alignment) in &misaligned {
+            helpers::warning(format!(
+                "{} is aligned to {alignment}-byte pages, not 16 KB; it won't load on Android 15 devices with 16 KB pages",
+                library.display()
+            ));
+        }
+        if !misaligned.is_empty() {
+            helpers::hint(format!(
+                "Link with 16 KB pages: [targets.\"{}\"] rustflags = [\"-C\", \"link-arg=-Wl,-z,max-page-size=16384\"] (NDK r28 does by default)",
+                target.triple
+            ));
+            if self.config.artifacts.android.require_16k_pages {
+                return Err(Error::Build(format!(
+                    "{} Android librar{} not aligned to 16 KB pages (artifacts.android.require_16k_pages)",
+                    misaligned.len(),
+                    if misaligned.len() == 1 { "y is" } else { "ies are" }
+                )));
+            }
+        }
+
+        if exported.is_empty() {
+            helpers::warning(format!(
+                "No cdylibs to export for {} (add crate-type = [\"cdylib\"] to [lib])",
+                target.triple
+            ));
+        } else {
+            helpers::success(format!(
+                "{} Android librar{} exported to {}",
+                exported.len(),
+                if exported.len() == 1 { "y" } else { "ies" },
+                export.dir().join(android::JNI_LIBS_DIR).join(abi).display()
+            ));
+        }
+        Ok(())
+    }
+
+    /// glibc version to link `target` against with Zig
+    ///
+    /// `--glibc` overrides the target's `glibc_version`, which defaults to
+    /// its `min_glibc`. Targets without glibc get `None`.
+    #[cfg(feature = "zig")]
+    fn glibc_version(
+        &self,
+        target: &Target,
+        options: &BuildOptions,
+    ) -> Result<Option<GlibcVersion>> {
+        if !glibc::is_glibc_target(target) {
+            if options.glibc_version.is_some() {
+                helpers::warning(format!(
+                    "--glibc has no effect on {}, which does not use glibc",
+                    target.triple
+                ));
+            }
+            return Ok(None);
+        }
+        let target_config = self.config.get_target_config(&target.triple);
+        let Some(version) = options.glibc_version.as_deref().or_else(|| {
+            target_config.and_then(|config| {
+                config
+                    .glibc_version
+                    .as_deref()
+                    .or(config.min_glibc.as_deref())
+            })
+        }) else {
+            return Ok(None);
+        };
+
+        GlibcVersion::parse(version).map(Some).ok_or_else(|| {
+            Error::Config(format!(
+                "Invalid glibc version \"{version}\": expected a version like \"2.17\""
+            ))
+        })
+    }
+
+    /// Check built binaries: everything `xcargo verify` checks with
+    /// `[verify] after_build`, otherwise only the `min_glibc` limit
+    fn verify_artifacts(&self, target: &Target, options: &BuildOptions) -> Result<()> {
+        if self.links_statically(target, options) {
+            self.verify_static(target, options)?;
+        }
+        if !self.config.verify.after_build {
+            return self.verify_glibc(target, options);
+        }
+
+        let profile = if options.release { "release" } else { "debug" };
+        let verifications = verify::verify_target(target, profile, &self.config)?;
+        let failed = verify::print_report(&verifications, options.verbose > 0);
+        if failed > 0 {
+            return Err(Error::Build(format!(
+                "{failed} binary(ies) built for {} failed verification",
+                target.triple
+            )));
+        }
+        Ok(())
+    }
+
+    /// Fail when a static build produced executables with a dynamic loader
+    fn verify_static(&self, target: &Target, options: &BuildOptions) -> Result<()> {
+        let profile = if options.release { "release" } else { "debug" };
+        let binaries = artifacts::built_binaries(target, profile)?;
+        let dynamic = static_link::dynamic_binaries(&binaries)?;
+        if dynamic.is_empty() {
+            if options.verbose > 0 && !binaries.is_empty() {
+                helpers::success("Binaries are statically linked");
+            }
+            return Ok(());
+        }
+
+        for (path, info) in &dynamic {
+            helpers::error(format!("{}: {}", path.display(), info.summary()));
+            if !info.libraries.is_empty() {
+                helpers::hint(format!("Links {}", info.libraries.join(", ")));
+            }
+        }
+        Err(Error::Build(format!(
+            "{} binary(ies) built for {} are dynamically linked, but a static build was asked for",
+            dynamic.len(),
+            target.triple
+        )))
+    }
+
+    /// Warn when built binaries need a newer glibc than `min_glibc` allows
+    fn verify_glibc(&self, target: &Target, options: &BuildOptions) -> Result<()> {
+        let Some(min) = self
+            .config
+            .get_target_config(&target.triple)
+            .and_then(|config| config.min_glibc.as_deref())
+            .and_then(GlibcVersion::parse)
+        else {
+            return Ok(());
+        };
+        if !glibc::is_glibc_target(target) {
+            return Ok(());
+        }
+
+        let profile = if options.release { "release" } else { "debug" };
+        let binaries = artifacts::built_binaries(target, profile)?;
+        let violations = glibc::check(&binaries, min)?;
+        if violations.is_empty() {
+            if options.verbose > 0 {
+                helpers::success(format!("Binaries run on glibc {min} and newer"));
+            }
+            return Ok(());
+        }
+
+        for violation in &violations {
+            helpers::warning(format!(
+                "{} requires glibc {} (min_glibc is {min})",
+                violation.path.display(),
+                violation.required
+            ));
+        }
+        for tip in glibc::alternatives(target, min) {
+            helpers::tip(tip);
+        }
+        Ok(())
+    }
+
+    /// Visual Studio environment for an MSVC target on a Windows host
+    ///
+    /// Returns `None` when not needed or already active (Developer Command Prompt).
+    fn msvc_environment(&self, target: &Target) -> Result<Option<HashMap<String, String>>> {
+        if target.env.as_deref() != Some("msvc") || msvc::environment_active() {
+            return Ok(None);
+        }
+        let host = Target::detect_host()?;
+        if host.os != "windows" {
+            return Ok(None);
+        }
+
+        let Some(install) = msvc::find_installation(&msvc::required_components(target))? else {
+            helpers::warning(format!(
+                "No Visual Studio installation with C++ tools for {} found",
+                target.triple
+            ));
+            helpers::hint(
+                "Install Visual Studio Build Tools with the 'Desktop development with C++' workload",
+            );
+            return Ok(None);
+        };
+
+        helpers::info(format!(
+            "Using MSVC from {} {}",
+            install.display_name, install.installation_version
+        ));
+        install.environment(&host, target).map(Some)
+    }
+
+    /// Xcode SDK variables for an iOS target on a macOS host, and for tests
+    /// on simulator targets a runner starting them in a simulator
+    ///
+    /// Variables set in `[targets.<triple>] env` are left alone, and flags
+    /// there or in the environment are kept after the SDK's.
+    fn apple_environment(
+        &self,
+        target: &Target,
+        options: &BuildOptions,
+    ) -> Result<Vec<(String, String)>> {
+        let Some(sdk) = apple::AppleSdk::for_target(target) else {
+            return Ok(Vec::new());
+        };
+        if Target::detect_host()?.os != "macos" {
+            return Ok(Vec::new());
+        }
+
+        let target_config = self.config.get_target_config(&target.triple);
+        let mut env: HashMap<String, String> = std::env::vars().collect();
+        if let Some(config) = target_config {
+            env.extend(config.env.clone());
+        }
+        let configured = |key: &str| target_config.is_some_and(|c| c.env.contains_key(key));
+
+        let sdk_path = sdk.path()?;
+        helpers::info(format!(
+            "Using the {} SDK at {}",
+            sdk.name(),
+            sdk_path.display()
+        ));
+        let mut vars: Vec<(String, String)> = apple::environment(target, &sdk_path, &env)
+            .into_iter()
+            .filter(|(key, _)| key != "SDKROOT" || !configured(key))
+            .collect();
+
+        let runner_var = format!(
+            "CARGO_TARGET_{}_RUNNER",
+            target.triple.to_uppercase().replace('-', "_")
+        );
+        let runs_binaries = matches!(
+            options.operation,
+            CargoOperation::Test | CargoOperation::Bench
+        );
+        if runs_binaries && apple::is_simulator(target) && !env.contains_key(&runner_var) {
+            let preferred = target_config.and_then(|c| c.simulator.as_deref());
+            let devices = apple::list_simulators()?;
+            let Some(device) = apple::choose_simulator(&devices, preferred) else {
+                return Err(Error::Toolchain(match preferred {
+                    Some(name) => format!("No available iOS simulator named {name}"),
+                    None => "No iOS simulators are available".to_string(),
+                }));
+            };
+            if !device.is_booted() {
+                helpers::progress(format!("Booting simulator {}", device.name));
+                device.boot()?;
+            }
+            helpers::info(format!("Running tests on simulator {}", device.name));
+            vars.push((runner_var, device.runner()));
+        }
+
+        Ok(vars)
+    }
+
+    /// Compiler wrapper to apply, unless the user already set `RUSTC_WRAPPER`
+    fn compiler_wrapper(&self) -> Option<CompilerWrapper> {
+        if cache::wrapper_from_env().is_some() {
+            return None;
+        }
+        CompilerWrapper::detect(&self.config.cache)
+    }
+
+    /// Resolve native dependencies found in Cargo.lock and explain each choice
+    fn resolve_native_deps(&self, target: &Target) -> Result<Vec<DepResolution>> {
+        let lockfile = match Lockfile::discover() {
+            Ok(Some(lockfile)) => lockfile,
+            _ => return Ok(Vec::new()),
+        };
+
+        let host = Target::detect_host()?;
+        let resolutions = deps::resolve_for_target(&self.config.deps, &lockfile, target, &host)?;
+
+        for resolution in &resolutions {
+            helpers::info(format!(
+                "{}: {} ({})",
+                resolution.name, resolution.strategy, resolution.reason
+            ));
+            resolution.prepare(&self.config.network)?;
+        }
+
+        Ok(resolutions)
+    }
+
+    /// Suggest platform-specific installation instructions for a linker
+    fn suggest_linker_installation(&self, host: &Target, target: &Target) {
+        let host_os = host.os.as_str();
+        let target_os = target.os.as_str();
+
+        match (host_os, target_os) {
+            ("macos", "linux") => {
+                helpers::tip(
                     "For Linux cross-compilation on macOS, consider using Zig: brew install zig",
                 );
                 helpers::tip(format!(
@@ -614,32 +3141,13 @@ impl Builder {
         }
     }
 
-    /// Determine if a container build should be used for this target
-    fn should_use_container_for_target(&self, target: &Target) -> Result<bool> {
-        #[cfg(not(feature = "container"))]
-        {
-            let _ = target; // Suppress unused warning
-            return Ok(false);
-        }
-
-        #[cfg(feature = "container")]
-        {
-            let host = Target::detect_host()?;
-
-            // Check config's use_when condition
-            match self.config.container.use_when.as_str() {
-                "always" => Ok(true),
-                "never" => Ok(false),
-                "target.os != host.os" => Ok(target.os != host.os),
-                _ => Ok(false),
-            }
-        }
-    }
-
     /// Build using a container
     #[cfg(feature = "container")]
     fn build_with_container(&self, target: &Target, options: &BuildOptions) -> Result<()> {
-        use crate::container::{ContainerBuilder, ContainerConfig, RuntimeType};
+        use crate::container::{
+            ContainerBuilder, ContainerConfig, HostUser, PullPolicy, RuntimeType,
+        };
+        use crate::network;
 
         helpers::section("xcargo container build");
         helpers::info(format!("Building {} using container", target.triple));
@@ -650,6 +3158,7 @@ impl Builder {
 
         // Create container builder
         let container_builder = ContainerBuilder::new(runtime_type)
+            .map(|builder| builder.with_registry(self.config.container.registry.as_deref()))
             .map_err(|e| {
                 helpers::error(format!("Failed to initialize container runtime: {e}"));
                 helpers::hint("Make sure Docker or Podman is installed and running");
@@ -692,57 +3201,179 @@ impl Builder {
             container_builder.runtime_name()
         ));
 
-        // Select appropriate image
-        let image = container_builder
-            .select_image(&target.triple)
-            .map_err(|e| {
-                helpers::error(format!("Failed to select container image: {e}"));
-
-                // Suggest alternatives based on the error
-                if target.os == "macos" {
-                    helpers::hint("macOS cross-compilation requires osxcross or building on macOS");
-                    helpers::tip("Consider using GitHub Actions macOS runners for macOS builds");
-                } else if target.triple.starts_with("wasm") {
-                    helpers::hint("WebAssembly doesn't require containers - use native build");
-                    helpers::tip("Run without --container flag");
-                } else {
-                    helpers::hint("This target may not have a pre-built container image");
-                    helpers::tip("You can specify a custom image in xcargo.toml");
-                }
+        // Select appropriate image, unless [container] images names one
+        let image = match self.config.container.images.get(&target.triple) {
+            Some(image) => image.clone(),
+            None => container_builder
+                .select_image(&target.triple)
+                .map_err(|e| {
+                    helpers::error(format!("Failed to select container image: {e}"));
+
+                    // Suggest alternatives based on the error
+                    if target.os == "macos" {
+                        helpers::hint(
+                            "macOS cross-compilation requires osxcross or building on macOS",
+                        );
+                        helpers::tip(
+                            "Consider using GitHub Actions macOS runners for macOS builds",
+                        );
+                    } else if target.triple.starts_with("wasm") {
+                        helpers::hint("WebAssembly doesn't require containers - use native build");
+                        helpers::tip("Run without --container flag");
+                    } else {
+                        helpers::hint("This target may not have a pre-built container image");
+                        helpers::tip("Set one for the target in [container.images] in xcargo.toml");
+                    }
 
-                e
-            })?;
+                    e
+                })?
+                .full_name(),
+        };
 
-        helpers::info(format!("Using image: {}", image.full_name()));
+        helpers::info(format!("Using image: {image}"));
 
         // Build container config
         let mut container_config = ContainerConfig::default();
         container_config.runtime = runtime_type;
-        container_config.image = image.full_name();
+        container_config.image = image;
+        container_config.pull_policy = PullPolicy::from_str(&self.config.container.pull_policy)?;
+        if self.config.container.run_as_user {
+            container_config.user = HostUser::current();
+        }
+        container_config.limits = self.run_limits(options)?;
+        container_config.offline = self.is_offline(options);
 
-        // Add custom environment variables from target config
-        if let Some(target_config) = self.config.get_target_config(&target.triple) {
+        // Add custom environment variables from [build] and the profile,
+        // then from target config
+        container_config.env.extend(
+            self.config
+                .build
+                .env
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+        let target_config = self.config.get_target_config(&target.triple);
+        if let Some(target_config) = target_config {
             for (key, value) in &target_config.env {
                 container_config.env.push((key.clone(), value.clone()));
             }
         }
 
+        // Pass host variables through, and run the pre-build commands
+        let passthrough = self
+            .config
+            .container
+            .passthrough
+            .iter()
+            .chain(target_config.into_iter().flat_map(|c| &c.passthrough));
+        for name in passthrough {
+            if let Ok(value) = std::env::var(name) {
+                container_config.env.push((name.clone(), value));
+            }
+        }
+        network::apply_to_container(&self.config.network, &mut container_config, |name| {
+            std::env::var(name).ok()
+        })?;
+        container_config.pre_build = match target_config {
+            Some(c) if !c.pre_build.is_empty() => c.pre_build.clone(),
+            _ => self.config.container.pre_build.clone(),
+        };
+
+        let cargo_config = CargoTargetConfig::find(&target.triple)?;
+        if let Some(rustflags) = self.rustflags(target, options, &cargo_config) {
+            if options.verbose > 0 {
+                Self::report_rustflags(&rustflags);
+            }
+            container_config
+                .env
+                .push(("RUSTFLAGS".to_string(), rustflags.args().join(" ")));
+        }
+
+        // Run the instrumented tests in the container, which sees the
+        // project (and so the coverage files in its target directory) at
+        // the container's working directory
+        let project = crate::paths::simplify(&std::env::current_dir()?);
+        let coverage = match options.coverage {
+            Some(_) => {
+                let coverage = TargetCoverage::for_target(&target.triple)?;
+                coverage.prepare()?;
+                let workdir = container_config.workdir.clone();
+                let in_container = |path: &Path| {
+                    let relative = path.strip_prefix(&project).map_err(|_| {
+                        Error::Container(format!(
+                            "Coverage in container builds needs the target directory inside the project, not in {}",
+                            coverage.dir().display()
+                        ))
+                    })?;
+                    Ok::<_, Error>(format!(
+                        "{workdir}/{}",
+                        relative.to_string_lossy().replace('\\', "/")
+                    ))
+                };
+                container_config.cargo_command = CargoOperation::Test.as_str().to_string();
+                container_config.env.push((
+                    "CARGO_TARGET_DIR".to_string(),
+                    in_container(&coverage.cargo_target_dir())?,
+                ));
+                container_config.env.push((
+                    "LLVM_PROFILE_FILE".to_string(),
+                    in_container(&coverage.profile_file())?,
+                ));
+                Some(coverage)
+            }
+            None => None,
+        };
+
+        // Share the host's compiler cache with the container
+        if let Some(wrapper) = self.compiler_wrapper() {
+            let host_os = Target::detect_host().map(|h| h.os).unwrap_or_default();
+            match wrapper.container_setup(&host_os) {
+                Some(setup) => {
+                    container_config.env.extend(setup.env);
+                    container_config.volumes.extend(setup.volumes);
+                }
+                None => helpers::warning(format!(
+                    "{} can only be shared with containers from Linux hosts",
+                    wrapper.name
+                )),
+            }
+        }
+
         // Execute container build
         helpers::progress("Pulling container image...");
 
-        let mut cargo_args = options.cargo_args.clone();
+        let mut cargo_args = self.feature_args(target, options, &[]);
+        cargo_args.extend(options.selection.cargo_args());
+        cargo_args.extend(options.cargo_args.iter().cloned());
         if options.release {
             cargo_args.insert(0, "--release".to_string());
         }
-        if options.verbose {
+        if options.cargo_verbose() {
             cargo_args.insert(0, "--verbose".to_string());
         }
+        if self.passes_offline(options) {
+            cargo_args.push("--offline".to_string());
+        }
 
         container_builder.build(&target.triple, &cargo_args, &container_config)?;
 
         println!(); // Empty line for spacing
         helpers::success(format!("Container build completed for {}", target.triple));
 
+        if let (Some(coverage), Some(format)) = (&coverage, options.coverage) {
+            let workdir = container_config.workdir.as_str();
+            Self::report_coverage(coverage, options, format, Some((workdir, &project)))?;
+        }
+
+        if options.operation == CargoOperation::Build {
+            self.collect_artifacts(target, options)?;
+            self.export_android(target, options)?;
+            self.export_wasm(target, options)?;
+            self.export_xcframeworks(target, options)?;
+            self.write_firmware_images(target, options)?;
+            self.verify_artifacts(target, options)?;
+        }
+
         // Show helpful tips
         if options.release {
             helpers::tip(format!(
@@ -771,6 +3402,139 @@ impl Builder {
     }
 }
 
+/// Cargo arguments that select which cargo targets to compile
+const SELECTION_ARGS: &[&str] = &[
+    "--lib",
+    "--bin",
+    "--bins",
+    "--example",
+    "--examples",
+    "--test",
+    "--tests",
+    "--bench",
+    "--benches",
+    "--doc",
+    "--all-targets",
+];
+
+/// What [`Builder::run_cargo`] does with cargo's output besides printing it
+#[derive(Default)]
+struct OutputHandling {
+    /// Annotate diagnostics for CI
+    annotations: Option<ci::Annotations>,
+    /// Collect timing messages instead of printing them
+    timings: Option<timings::Collector>,
+    /// Record diagnostics for the target in the multi-target summary
+    diagnostics: Option<(DiagnosticSummary, String)>,
+    /// Look for failures to explain
+    explain: Option<explain::Matcher>,
+    /// Read benchmark results, still printing the output
+    bench: Option<bench::Collector>,
+}
+
+impl OutputHandling {
+    /// Whether cargo runs with [`ci::JSON_MESSAGE_FORMAT`]
+    fn is_json(&self) -> bool {
+        self.annotations.is_some() || self.diagnostics.is_some()
+    }
+
+    /// Whether cargo's output is read rather than passed through
+    fn is_captured(&self) -> bool {
+        self.is_json() || self.timings.is_some() || self.explain.is_some() || self.bench.is_some()
+    }
+}
+
+/// Ask whether to add `targets` to `toolchain`
+///
+/// Without a terminal there is no one to ask, so they are added, as they
+/// always were; `--no-install` is the way to forbid it.
+#[cfg(feature = "tui")]
+fn confirm_install(targets: &[&str], toolchain: &str) -> Result<bool> {
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        return Ok(true);
+    }
+    inquire::Confirm::new(&format!(
+        "Target {} is not installed for {toolchain}. Install it with rustup?",
+        targets.join(", ")
+    ))
+    .with_default(true)
+    .prompt()
+    .map_err(|e| Error::Prompt(e.to_string()))
+}
+
+// Matches the prompting version, so callers don't depend on the feature
+#[cfg(not(feature = "tui"))]
+#[allow(clippy::unnecessary_wraps)]
+fn confirm_install(_targets: &[&str], _toolchain: &str) -> Result<bool> {
+    Ok(true)
+}
+
+/// Ask whether to install a cross GCC as `provision` describes
+///
+/// Package managers need root and downloads are large, so nothing is
+/// installed unless someone at a terminal agrees.
+#[cfg(feature = "tui")]
+fn confirm_provision(provision: &Provision) -> Result<bool> {
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        return Ok(false);
+    }
+    inquire::Confirm::new(&format!(
+        "No cross GCC for {} is installed. Install it ({})?",
+        provision.target,
+        provision.describe()
+    ))
+    .with_default(true)
+    .prompt()
+    .map_err(|e| Error::Prompt(e.to_string()))
+}
+
+// Matches the prompting version, so callers don't depend on the feature
+#[cfg(not(feature = "tui"))]
+#[allow(clippy::unnecessary_wraps)]
+fn confirm_provision(_provision: &Provision) -> Result<bool> {
+    Ok(false)
+}
+
+/// A target left out of a multi-target build, and why
+pub(super) type SkippedTarget = (String, String);
+
+/// List targets left out of a multi-target build in its summary
+pub(super) fn print_skipped(skipped: &[SkippedTarget]) {
+    if skipped.is_empty() {
+        return;
+    }
+    helpers::info(format!("{} target(s) skipped", skipped.len()));
+    for (target, reason) in skipped {
+        helpers::info(format!("  - {target} ({reason})"));
+    }
+}
+
+/// Point to `--resume` after a failed multi-target build
+pub(super) fn print_resume_hint(options: &BuildOptions) {
+    if options.operation == CargoOperation::Build && !options.resume {
+        helpers::tip("Rerun with --resume to build only the targets that did not succeed");
+    }
+}
+
+/// Show a cargo command about to run with `-v`, and with `-vvv` also its
+/// working directory and full environment (secrets redacted)
+fn print_command(cmd: &Command, options: &BuildOptions) {
+    if options.verbose == 0 {
+        return;
+    }
+    let traced = trace::TracedCommand::from_command(cmd, None);
+    helpers::info(format!("Executing: {}", traced.display()));
+    if !options.dumps_commands() {
+        return;
+    }
+    if let Some(cwd) = &traced.cwd {
+        println!("  in {}", cwd.display());
+    }
+    for (key, value) in traced.full_env() {
+        println!("  {key}={value}");
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -785,4 +3549,152 @@ mod tests {
         }
         assert!(builder.is_ok());
     }
+
+    #[test]
+    fn test_run_limits() {
+        let mut config = Config::default();
+        config.build.timeout = Some("15m".to_string());
+        let Ok(builder) = Builder::with_config(config) else {
+            return;
+        };
+        let token = CancellationToken::new();
+        let builder = builder.with_cancellation(token.clone());
+
+        let limits = builder.run_limits(&BuildOptions::default()).unwrap();
+        assert_eq!(limits.timeout, Some(Duration::from_secs(900)));
+        token.cancel();
+        assert!(limits.cancel.is_cancelled());
+
+        let options = BuildOptions {
+            timeout: Some(Duration::from_secs(30)),
+            ..Default::default()
+        };
+        let limits = builder.run_limits(&options).unwrap();
+        assert_eq!(limits.timeout, Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_feature_args_merges_target_config() {
+        use crate::config::TargetCustomConfig;
+
+        let mut config = Config::default();
+        config.targets.custom.insert(
+            "wasm32-unknown-unknown".to_string(),
+            TargetCustomConfig {
+                features: vec!["web".to_string()],
+                no_default_features: true,
+                ..Default::default()
+            },
+        );
+        let Ok(builder) = Builder::with_config(config) else {
+            return;
+        };
+
+        let options = BuildOptions {
+            features: vec!["logging,web".to_string()],
+            ..Default::default()
+        };
+
+        let wasm = Target::from_triple("wasm32-unknown-unknown").unwrap();
+        assert_eq!(
+            builder.feature_args(&wasm, &options, &["openssl/vendored".to_string()]),
+            vec![
+                "--features",
+                "logging,web,openssl/vendored",
+                "--no-default-features"
+            ]
+        );
+
+        let linux = Target::from_triple("x86_64-unknown-linux-gnu").unwrap();
+        assert_eq!(
+            builder.feature_args(&linux, &options, &[]),
+            vec!["--features", "logging,web"]
+        );
+        // Dependency features still need naming with --all-features
+        let options = BuildOptions {
+            all_features: true,
+            ..options
+        };
+        assert_eq!(
+            builder.feature_args(&wasm, &options, &["openssl/vendored".to_string()]),
+            vec![
+                "--all-features",
+                "--features",
+                "openssl/vendored",
+                "--no-default-features"
+            ]
+        );
+        assert_eq!(
+            builder.feature_args(&linux, &options, &[]),
+            vec!["--all-features"]
+        );
+    }
+
+    #[test]
+    fn test_plugin_strategy() {
+        use crate::config::StrategyConfig;
+        use crate::plugin::Plugin;
+
+        struct Remote;
+
+        impl Plugin for Remote {
+            fn name(&self) -> &'static str {
+                "remote"
+            }
+
+            fn strategies(&self) -> Vec<String> {
+                vec!["remote-builder".to_string()]
+            }
+
+            fn strategy_command(
+                &self,
+                _strategy: &str,
+                _ctx: &PluginContext,
+                command: BuildCommand,
+            ) -> Result<BuildCommand> {
+                let mut args = vec!["builder".to_string(), command.program];
+                args.extend(command.args);
+                Ok(BuildCommand {
+                    program: "ssh".to_string(),
+                    args,
+                    ..command
+                })
+            }
+        }
+
+        let mut config = Config::default();
+        config.strategies.insert(
+            "remote-builder".to_string(),
+            StrategyConfig {
+                priority: Some(40),
+                targets: vec!["aarch64-*".to_string()],
+                ..StrategyConfig::default()
+            },
+        );
+        let Ok(builder) = Builder::with_config(config) else {
+            return;
+        };
+        let mut plugins = PluginRegistry::new();
+        plugins.register(Box::new(Remote)).unwrap();
+        let builder = builder.with_plugins(Arc::new(plugins));
+        let options = BuildOptions::default();
+
+        let arm = Target::from_triple("aarch64-unknown-linux-gnu").unwrap();
+        let facts = builder.plugin_strategies(&arm, &options);
+        assert_eq!(facts.len(), 1);
+        assert_eq!(facts[0].priority, 40);
+        let riscv = Target::from_triple("riscv64gc-unknown-linux-gnu").unwrap();
+        assert!(builder.plugin_strategies(&riscv, &options).is_empty());
+
+        let mut cargo = Command::new("cargo");
+        cargo.args(["build", "--target", "aarch64-unknown-linux-gnu"]);
+        let cmd = builder
+            .plugin_command("remote-builder", &arm, &options, &cargo)
+            .unwrap();
+        assert_eq!(cmd.get_program(), "ssh");
+        assert_eq!(cmd.get_args().count(), 5);
+        assert!(builder
+            .plugin_command("nix-shell", &arm, &options, &cargo)
+            .is_err());
+    }
 }
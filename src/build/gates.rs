@@ -0,0 +1,352 @@
+//! Pre-publish gates for artifacts
+//!
+//! Gates are checks every artifact must pass before it may be published,
+//! such as a malware scan or an internal compliance checker. They are
+//! commands from `[[artifacts.gates]]` and plugins' `on_pre_publish`
+//! hooks, and run on each file placed in the artifact directory. Whether
+//! each gate passed is recorded with the artifact in `artifacts.json`, and
+//! a build whose artifacts fail a gate fails.
+//!
+//! ```toml
+//! [[artifacts.gates]]
+//! name = "malware-scan"
+//! command = ["clamscan", "--no-summary", "{artifact}"]
+//! ```
+//!
+//! Anything that uploads artifacts calls [`require_passed`] on the
+//! manifest first, so nothing is published unless every artifact passed
+//! every configured gate.
+
+use crate::cancel::{self, RunLimits};
+use crate::config::GateConfig;
+use crate::error::{Error, Result};
+use crate::output::helpers;
+use crate::plugin::{PluginContext, PluginHook, PluginRegistry};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+use super::artifacts::{Artifact, ArtifactManifest};
+
+/// Outcome of one gate for one artifact
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GateResult {
+    /// Gate name (the plugin's name for plugin hooks)
+    pub name: String,
+    /// Whether the artifact passed
+    pub passed: bool,
+    /// Why it did not pass
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl GateResult {
+    fn pass(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            passed: true,
+            message: None,
+        }
+    }
+
+    fn fail(name: &str, message: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            passed: false,
+            message: Some(message.into()),
+        }
+    }
+}
+
+/// The gates configured for a build
+pub struct Gates<'a> {
+    commands: &'a [GateConfig],
+    plugins: &'a PluginRegistry,
+    limits: RunLimits,
+}
+
+impl<'a> Gates<'a> {
+    /// Gates from `commands` and every plugin in `plugins`, stopped by
+    /// `limits`
+    #[must_use]
+    pub fn new(commands: &'a [GateConfig], plugins: &'a PluginRegistry, limits: RunLimits) -> Self {
+        Self {
+            commands,
+            plugins,
+            limits,
+        }
+    }
+
+    /// Whether there is nothing to check
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty() && self.plugins.count() == 0
+    }
+
+    /// Run every gate on `artifact`, stored in the artifact directory `dir`
+    ///
+    /// All gates run even after one fails, so the manifest shows every
+    /// problem. Fails only if the build is cancelled or a gate times out.
+    pub fn check(&self, dir: &Path, artifact: &Artifact) -> Result<Vec<GateResult>> {
+        let path = dir.join(&artifact.path);
+        let mut results = Vec::new();
+
+        for gate in self.commands {
+            results.push(self.run_command(gate, &path, artifact)?);
+        }
+
+        for name in self.plugins.list() {
+            let Some(plugin) = self.plugins.get(&name) else {
+                continue;
+            };
+            let mut ctx = PluginContext::new(artifact.target.clone())
+                .with_release(artifact.profile == "release");
+            ctx.set_metadata("artifact".to_string(), path.display().to_string());
+            results.push(
+                match PluginHook::PrePublish.execute(plugin.as_ref(), &ctx) {
+                    Ok(()) => GateResult::pass(&name),
+                    Err(e) => GateResult::fail(&name, e.to_string()),
+                },
+            );
+        }
+        Ok(results)
+    }
+
+    /// Check each of `artifacts`, built for `target` in `profile`, and
+    /// record the results in the manifest in `dir`
+    ///
+    /// Fails if any artifact did not pass.
+    pub fn check_all(
+        &self,
+        dir: &Path,
+        target: &str,
+        profile: &str,
+        artifacts: &mut [Artifact],
+    ) -> Result<()> {
+        for artifact in artifacts.iter_mut() {
+            artifact.gates = self.check(dir, artifact)?;
+        }
+
+        let mut manifest = ArtifactManifest::load(dir);
+        manifest.replace(target, profile, artifacts.to_vec());
+        manifest.save(dir)?;
+
+        let mut failed = 0;
+        for artifact in artifacts.iter() {
+            for gate in artifact.gates.iter().filter(|gate| !gate.passed) {
+                failed += 1;
+                helpers::error(format!(
+                    "{} failed gate '{}': {}",
+                    artifact.path,
+                    gate.name,
+                    gate.message.as_deref().unwrap_or("rejected")
+                ));
+            }
+        }
+        if failed > 0 {
+            return Err(Error::Build(format!(
+                "{failed} artifact gate check(s) failed"
+            )));
+        }
+        helpers::success(format!("{} artifact(s) passed all gates", artifacts.len()));
+        Ok(())
+    }
+
+    fn run_command(
+        &self,
+        gate: &GateConfig,
+        path: &Path,
+        artifact: &Artifact,
+    ) -> Result<GateResult> {
+        let args: Vec<String> = gate
+            .command
+            .iter()
+            .map(|arg| {
+                arg.replace("{artifact}", &path.display().to_string())
+                    .replace("{target}", &artifact.target)
+                    .replace("{profile}", &artifact.profile)
+            })
+            .collect();
+        let Some((program, args)) = args.split_first() else {
+            return Ok(GateResult::fail(&gate.name, "no command configured"));
+        };
+
+        let mut cmd = Command::new(program);
+        cmd.args(args)
+            .env("XCARGO_ARTIFACT", path)
+            .env("XCARGO_TARGET", &artifact.target)
+            .env("XCARGO_PROFILE", &artifact.profile);
+        match cancel::run(&mut cmd, &self.limits) {
+            Ok(status) if status.success() => Ok(GateResult::pass(&gate.name)),
+            Ok(status) => Ok(GateResult::fail(
+                &gate.name,
+                match status.code() {
+                    Some(code) => format!("{program} exited with code {code}"),
+                    None => format!("{program} was terminated"),
+                },
+            )),
+            Err(e @ (Error::Cancelled | Error::TimedOut(_))) => Err(e),
+            Err(e) => Ok(GateResult::fail(
+                &gate.name,
+                format!("could not run {program}: {e}"),
+            )),
+        }
+    }
+}
+
+/// Fail unless every artifact in `manifest` passed all of `required` and
+/// every other gate that ran on it
+///
+/// Call before publishing anything from the artifact directory.
+pub fn require_passed(manifest: &ArtifactManifest, required: &[GateConfig]) -> Result<()> {
+    for artifact in &manifest.artifacts {
+        if let Some(gate) = artifact.gates.iter().find(|gate| !gate.passed) {
+            return Err(Error::Build(format!(
+                "{} failed gate '{}', not publishing",
+                artifact.path, gate.name
+            )));
+        }
+        if let Some(gate) = required
+            .iter()
+            .find(|gate| !artifact.gates.iter().any(|result| result.name == gate.name))
+        {
+            return Err(Error::Build(format!(
+                "{} was not checked by gate '{}', not publishing",
+                artifact.path, gate.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build::artifacts::ArtifactKind;
+    use crate::plugin::Plugin;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn artifact(path: &str) -> Artifact {
+        Artifact {
+            target: "x86_64-unknown-linux-gnu".to_string(),
+            profile: "release".to_string(),
+            package: "app".to_string(),
+            name: "app".to_string(),
+            kind: ArtifactKind::Bin,
+            path: path.to_string(),
+            source: PathBuf::from("target/x86_64-unknown-linux-gnu/release/app"),
+            size: 6,
+            gates: Vec::new(),
+        }
+    }
+
+    fn gate(name: &str, command: &[&str]) -> GateConfig {
+        GateConfig {
+            name: name.to_string(),
+            command: command.iter().map(|arg| (*arg).to_string()).collect(),
+        }
+    }
+
+    struct Blocklist;
+
+    impl Plugin for Blocklist {
+        fn name(&self) -> &'static str {
+            "blocklist"
+        }
+
+        fn on_pre_publish(&self, ctx: &PluginContext) -> Result<()> {
+            match ctx.get_metadata("artifact") {
+                Some(path) if path.ends_with("blocked") => {
+                    Err(Error::Build("blocked by policy".to_string()))
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_gates() {
+        let temp = TempDir::new().unwrap();
+        let commands = [
+            gate("exists", &["test", "-f", "{artifact}"]),
+            gate("profile", &["test", "{profile}", "=", "debug"]),
+        ];
+        let plugins = PluginRegistry::new();
+        let gates = Gates::new(&commands, &plugins, RunLimits::default());
+        assert!(!gates.is_empty());
+
+        std::fs::write(temp.path().join("app"), b"binary").unwrap();
+        let results = gates.check(temp.path(), &artifact("app")).unwrap();
+        assert_eq!(results[0], GateResult::pass("exists"));
+        assert!(!results[1].passed);
+        assert_eq!(
+            results[1].message.as_deref(),
+            Some("test exited with code 1")
+        );
+
+        let missing = gate("missing", &["xcargo-no-such-scanner"]);
+        let gates = Gates::new(
+            std::slice::from_ref(&missing),
+            &plugins,
+            RunLimits::default(),
+        );
+        let results = gates.check(temp.path(), &artifact("app")).unwrap();
+        assert!(results[0]
+            .message
+            .as_deref()
+            .unwrap()
+            .starts_with("could not run"));
+    }
+
+    #[test]
+    fn test_plugin_gates_recorded_in_manifest() {
+        let temp = TempDir::new().unwrap();
+        let mut plugins = PluginRegistry::new();
+        plugins.register(Box::new(Blocklist)).unwrap();
+        let gates = Gates::new(&[], &plugins, RunLimits::default());
+
+        let mut artifacts = vec![artifact("ok"), artifact("blocked")];
+        let result = gates.check_all(
+            temp.path(),
+            "x86_64-unknown-linux-gnu",
+            "release",
+            &mut artifacts,
+        );
+        assert!(result.is_err());
+
+        let manifest = ArtifactManifest::load(temp.path());
+        assert_eq!(manifest.artifacts.len(), 2);
+        let blocked = &manifest.artifacts[0];
+        assert_eq!(blocked.path, "blocked");
+        assert_eq!(
+            blocked.gates,
+            [GateResult::fail(
+                "blocklist",
+                "Build failed: blocked by policy"
+            )]
+        );
+        assert!(manifest.artifacts[1].passed_gates());
+        assert!(require_passed(&manifest, &[]).is_err());
+    }
+
+    #[test]
+    fn test_require_passed() {
+        let required = [gate("scan", &["scan"])];
+        let mut checked = artifact("app");
+        checked.gates = vec![GateResult::pass("scan")];
+        let manifest = ArtifactManifest {
+            artifacts: vec![checked],
+            ..ArtifactManifest::default()
+        };
+        assert!(require_passed(&manifest, &required).is_ok());
+
+        let unchecked = ArtifactManifest {
+            artifacts: vec![artifact("app")],
+            ..ArtifactManifest::default()
+        };
+        assert!(require_passed(&unchecked, &[]).is_ok());
+        assert!(require_passed(&unchecked, &required).is_err());
+    }
+}
@@ -0,0 +1,322 @@
+//! glibc symbol version checks for `*-linux-gnu` binaries
+//!
+//! A binary linked against a newer glibc than the one on the machine it
+//! runs on fails at startup (`version 'GLIBC_2.34' not found`). The
+//! versions a binary needs are recorded in its ELF `.gnu.version_r`
+//! section; they are compared against `[targets.<triple>] min_glibc`.
+
+use crate::error::{Error, Result};
+use crate::target::Target;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// `SHT_GNU_verneed`: version needs section
+const SHT_GNU_VERNEED: u32 = 0x6fff_fffe;
+
+/// A glibc release version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GlibcVersion {
+    /// Major version
+    pub major: u32,
+    /// Minor version
+    pub minor: u32,
+    /// Patch version
+    pub patch: u32,
+}
+
+impl GlibcVersion {
+    /// Parse "2.17" or "2.3.4"
+    #[must_use]
+    pub fn parse(version: &str) -> Option<Self> {
+        let mut parts = version.trim().split('.').map(str::parse::<u32>);
+        let major = parts.next()?.ok()?;
+        let minor = parts.next()?.ok()?;
+        let patch = match parts.next() {
+            Some(patch) => patch.ok()?,
+            None => 0,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
+impl fmt::Display for GlibcVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.patch == 0 {
+            write!(f, "{}.{}", self.major, self.minor)
+        } else {
+            write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+        }
+    }
+}
+
+/// A binary that needs a newer glibc than allowed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlibcViolation {
+    /// Binary path
+    pub path: PathBuf,
+    /// Newest glibc version it requires
+    pub required: GlibcVersion,
+}
+
+/// Check if `target` links against glibc
+#[must_use]
+pub fn is_glibc_target(target: &Target) -> bool {
+    target.os == "linux"
+        && target
+            .env
+            .as_deref()
+            .is_some_and(|env| env.starts_with("gnu"))
+}
+
+/// glibc versions an ELF file requires, oldest first
+///
+/// Statically linked files require none; non-ELF files are an error.
+pub fn required_versions(data: &[u8]) -> Result<Vec<GlibcVersion>> {
+    let elf = Elf::parse(data).ok_or_else(|| Error::Build("Not an ELF file".to_string()))?;
+    let mut versions: Vec<GlibcVersion> = elf
+        .needed_versions()
+        .ok_or_else(|| Error::Build("Malformed ELF version section".to_string()))?
+        .iter()
+        .filter_map(|name| name.strip_prefix("GLIBC_"))
+        .filter_map(GlibcVersion::parse)
+        .collect();
+    versions.sort_unstable();
+    versions.dedup();
+    Ok(versions)
+}
+
+/// Newest glibc version the binary at `path` requires
+pub fn max_required(path: &Path) -> Result<Option<GlibcVersion>> {
+    let data = std::fs::read(path)?;
+    Ok(required_versions(&data)?.pop())
+}
+
+/// Binaries among `paths` that need a glibc newer than `min`
+pub fn check(paths: &[PathBuf], min: GlibcVersion) -> Result<Vec<GlibcViolation>> {
+    let mut violations = Vec::new();
+    for path in paths {
+        if let Some(required) = max_required(path)? {
+            if required > min {
+                violations.push(GlibcViolation {
+                    path: path.clone(),
+                    required,
+                });
+            }
+        }
+    }
+    Ok(violations)
+}
+
+/// Ways to build for an older glibc
+#[must_use]
+pub fn alternatives(target: &Target, min: GlibcVersion) -> Vec<String> {
+    let mut tips = vec![format!(
+        "Link against glibc {min} with Zig: xcargo build --target {} --zig --glibc {min}",
+        target.triple
+    )];
+    let musl = target.triple.replace("-gnu", "-musl");
+    if musl != target.triple {
+        tips.push(format!(
+            "Or build a static binary without glibc: xcargo build --target {musl}"
+        ));
+    }
+    tips
+}
+
+/// The parts of an ELF file needed to read version requirements
+struct Elf<'a> {
+    data: &'a [u8],
+    is_64: bool,
+    little_endian: bool,
+}
+
+impl<'a> Elf<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        if data.get(..4)? != b"\x7fELF" {
+            return None;
+        }
+        let is_64 = match data.get(4)? {
+            1 => false,
+            2 => true,
+            _ => return None,
+        };
+        let little_endian = match data.get(5)? {
+            1 => true,
+            2 => false,
+            _ => return None,
+        };
+        Some(Self {
+            data,
+            is_64,
+            little_endian,
+        })
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self
+            .data
+            .get(offset..offset.checked_add(2)?)?
+            .try_into()
+            .ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self
+            .data
+            .get(offset..offset.checked_add(4)?)?
+            .try_into()
+            .ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    /// `offset` if it lies within the file, so small field offsets can be
+    /// added to it without overflowing
+    fn offset(&self, offset: usize) -> Option<usize> {
+        (offset < self.data.len()).then_some(offset)
+    }
+
+    /// Address-sized field
+    fn word(&self, offset: usize) -> Option<usize> {
+        if self.is_64 {
+            let bytes: [u8; 8] = self
+                .data
+                .get(offset..offset.checked_add(8)?)?
+                .try_into()
+                .ok()?;
+            let value = if self.little_endian {
+                u64::from_le_bytes(bytes)
+            } else {
+                u64::from_be_bytes(bytes)
+            };
+            usize::try_from(value).ok()
+        } else {
+            usize::try_from(self.u32(offset)?).ok()
+        }
+    }
+
+    /// (type, link, offset, size) of every section header
+    fn sections(&self) -> Option<Vec<(u32, u32, usize, usize)>> {
+        let (shoff, shentsize, shnum) = if self.is_64 {
+            (self.word(0x28)?, self.u16(0x3a)?, self.u16(0x3c)?)
+        } else {
+            (self.word(0x20)?, self.u16(0x2e)?, self.u16(0x30)?)
+        };
+
+        (0..usize::from(shnum))
+            .map(|index| {
+                let header = self.offset(shoff.checked_add(index * usize::from(shentsize))?)?;
+                let (offset, size) = if self.is_64 {
+                    (self.word(header + 0x18)?, self.word(header + 0x20)?)
+                } else {
+                    (self.word(header + 0x10)?, self.word(header + 0x14)?)
+                };
+                Some((
+                    self.u32(header + 4)?,
+                    self.u32(header + if self.is_64 { 0x28 } else { 0x18 })?,
+                    offset,
+                    size,
+                ))
+            })
+            .collect()
+    }
+
+    fn string(&self, offset: usize) -> Option<&'a str> {
+        let bytes = self.data.get(offset..)?;
+        let end = bytes.iter().position(|&b| b == 0)?;
+        std::str::from_utf8(&bytes[..end]).ok()
+    }
+
+    /// Version names from every `Vernaux` entry (e.g., `GLIBC_2.17`)
+    fn needed_versions(&self) -> Option<Vec<&'a str>> {
+        let sections = self.sections()?;
+        let mut names = Vec::new();
+
+        for &(kind, link, offset, size) in &sections {
+            if kind != SHT_GNU_VERNEED {
+                continue;
+            }
+            let strtab = sections.get(usize::try_from(link).ok()?)?.2;
+
+            // Verneed: vn_version, vn_cnt, vn_file, vn_aux, vn_next
+            let mut entry = offset;
+            let end = offset.checked_add(size)?;
+            while entry < end {
+                entry = self.offset(entry)?;
+                let count = self.u16(entry + 2)?;
+                let mut aux = entry.checked_add(usize::try_from(self.u32(entry + 8)?).ok()?)?;
+                // Vernaux: vna_hash, vna_flags, vna_other, vna_name, vna_next
+                for _ in 0..count {
+                    aux = self.offset(aux)?;
+                    let name = usize::try_from(self.u32(aux + 8)?).ok()?;
+                    names.push(self.string(strtab.checked_add(name)?)?);
+                    aux = aux.checked_add(usize::try_from(self.u32(aux + 12)?).ok()?)?;
+                }
+
+                let next = usize::try_from(self.u32(entry + 12)?).ok()?;
+                if next == 0 {
+                    break;
+                }
+                entry = entry.checked_add(next)?;
+            }
+        }
+
+        Some(names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        let v217 = GlibcVersion::parse("2.17").unwrap();
+        assert_eq!(v217.to_string(), "2.17");
+        assert_eq!(GlibcVersion::parse("2.3.4").unwrap().to_string(), "2.3.4");
+        assert!(GlibcVersion::parse("2.34").unwrap() > v217);
+        assert!(GlibcVersion::parse("PRIVATE").is_none());
+    }
+
+    #[test]
+    fn test_not_elf() {
+        assert!(required_versions(b"MZ\x90\x00").is_err());
+    }
+
+    #[test]
+    fn test_host_binary() {
+        // The test binary itself is dynamically linked against glibc on gnu hosts
+        let Ok(host) = Target::detect_host() else {
+            return;
+        };
+        if !is_glibc_target(&host) {
+            return;
+        }
+        let exe = std::env::current_exe().unwrap();
+        let required = max_required(&exe).unwrap().unwrap();
+        assert!(required >= GlibcVersion::parse("2.2.5").unwrap());
+    }
+
+    #[test]
+    fn test_alternatives() {
+        let target = Target::from_triple("x86_64-unknown-linux-gnu").unwrap();
+        let tips = alternatives(&target, GlibcVersion::parse("2.17").unwrap());
+        assert!(tips[0].contains("--glibc 2.17"));
+        assert!(tips[1].contains("x86_64-unknown-linux-musl"));
+    }
+}
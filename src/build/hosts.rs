@@ -0,0 +1,554 @@
+//! Distributing multi-target builds across a pool of hosts
+//!
+//! With `[[hosts]]` configured, parallel `--all` and `--group` builds spread
+//! their targets over the pool instead of building every one here. A host
+//! builds:
+//!
+//! - `local`: with this machine's strategy chain
+//! - `container`: in a container on this machine, for targets with an image
+//! - `ssh`: by running xcargo in a checkout of the project on another
+//!   machine, then copying `target/<triple>/<profile>` back
+//!
+//! `targets` patterns limit a host to some targets. Each target goes to the
+//! host that can build it and has the least work queued per job slot,
+//! longest builds first, counting the estimated time of earlier builds (see
+//! [`history`](crate::history)). Results are reported per host once every
+//! target finished.
+
+use crate::cancel::{self, CancellationToken, RunLimits};
+use crate::config::HostConfig;
+use crate::deps::rules::matches_target;
+use crate::error::{Error, Result};
+use crate::history;
+use crate::output::helpers;
+use crate::plugin::BuildsSummary;
+use crate::shell::quote;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task;
+
+use super::executor::{print_resume_hint, print_skipped, Builder};
+use super::options::{BuildOptions, CargoOperation};
+
+/// Assumed length of a build that has no estimate yet
+pub const DEFAULT_COST: Duration = Duration::from_secs(60);
+
+/// Where a host runs its builds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HostKind {
+    /// This machine, with its strategy chain
+    #[default]
+    Local,
+    /// Another machine, reached over SSH
+    Ssh,
+    /// A container on this machine
+    Container,
+}
+
+impl fmt::Display for HostKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Local => "local",
+            Self::Ssh => "ssh",
+            Self::Container => "container",
+        })
+    }
+}
+
+/// Whether `host` builds `triple`; `has_image` tells if a container image
+/// exists for it
+#[must_use]
+pub fn can_build(host: &HostConfig, triple: &str, has_image: bool) -> bool {
+    let matches = host.targets.is_empty()
+        || host
+            .targets
+            .iter()
+            .any(|pattern| matches_target(pattern, triple));
+    matches && (host.kind != HostKind::Container || has_image)
+}
+
+/// The host each target is built on, by index into `hosts`
+///
+/// Targets are placed longest first (by `costs`), each on the host that can
+/// build it and would have the least queued work per job.
+pub fn assign(
+    targets: &[String],
+    hosts: &[HostConfig],
+    costs: &[Duration],
+    has_image: impl Fn(&str) -> bool,
+) -> Result<Vec<usize>> {
+    let mut order: Vec<usize> = (0..targets.len()).collect();
+    order.sort_by_key(|&idx| std::cmp::Reverse(costs[idx]));
+
+    let mut load = vec![Duration::ZERO; hosts.len()];
+    let mut assignment = vec![0; targets.len()];
+    for idx in order {
+        let triple = &targets[idx];
+        let image = has_image(triple);
+        let slots = |host: usize| u32::try_from(hosts[host].jobs.max(1)).unwrap_or(u32::MAX);
+        let host = (0..hosts.len())
+            .filter(|&host| can_build(&hosts[host], triple, image))
+            .min_by_key(|&host| (load[host] + costs[idx]) / slots(host))
+            .ok_or_else(|| Error::Config(format!("No host in [[hosts]] can build {triple}")))?;
+        load[host] += costs[idx];
+        assignment[idx] = host;
+    }
+    Ok(assignment)
+}
+
+/// xcargo's arguments for building `target` like `options` on another host
+#[must_use]
+pub fn xcargo_args(target: &str, options: &BuildOptions) -> Vec<String> {
+    let mut args = vec![
+        options.operation.as_str().to_string(),
+        "--target".to_string(),
+        target.to_string(),
+    ];
+    if options.release && options.operation.accepts_release() {
+        args.push("--release".to_string());
+    }
+    if let Some(toolchain) = &options.toolchain {
+        args.extend(["--toolchain".to_string(), toolchain.clone()]);
+    }
+    if !options.features.is_empty() {
+        args.extend(["--features".to_string(), options.features.join(",")]);
+    }
+    if options.all_features {
+        args.push("--all-features".to_string());
+    }
+    if options.no_default_features {
+        args.push("--no-default-features".to_string());
+    }
+    if options.static_link {
+        args.push("--static".to_string());
+    }
+    if !options.rustflags.is_empty() {
+        args.extend(["--rustflags".to_string(), options.rustflags.join(" ")]);
+    }
+    args.extend(options.selection.cargo_args());
+    if !options.cargo_args.is_empty() {
+        args.push("--".to_string());
+        args.extend(options.cargo_args.iter().cloned());
+    }
+    args
+}
+
+/// Directory of the project checkout on an SSH host
+fn remote_dir(host: &HostConfig) -> Result<String> {
+    match &host.dir {
+        Some(dir) => Ok(dir.clone()),
+        None => Ok(std::env::current_dir()?.display().to_string()),
+    }
+}
+
+/// The `ssh` command building `target` on `host`
+pub fn ssh_command(host: &HostConfig, target: &str, options: &BuildOptions) -> Result<Command> {
+    let Some(address) = &host.address else {
+        return Err(Error::Config(format!(
+            "Host '{}' needs an address to be reached over SSH",
+            host.name
+        )));
+    };
+    let xcargo: Vec<String> = xcargo_args(target, options)
+        .iter()
+        .map(|arg| quote(arg))
+        .collect();
+    let script = format!(
+        "cd {} && xcargo {}",
+        quote(&remote_dir(host)?),
+        xcargo.join(" ")
+    );
+
+    let mut cmd = Command::new("ssh");
+    cmd.args(["-o", "BatchMode=yes"]).arg(address).arg(script);
+    Ok(cmd)
+}
+
+/// The `scp` command copying `target`'s build output from `host` into
+/// `target/<triple>` here
+pub fn fetch_command(host: &HostConfig, target: &str, release: bool) -> Result<Command> {
+    let address = host.address.as_deref().unwrap_or_default();
+    let profile = if release { "release" } else { "debug" };
+    let source = format!("{address}:{}/target/{target}/{profile}", remote_dir(host)?);
+
+    let mut cmd = Command::new("scp");
+    cmd.args(["-q", "-r", "-o", "BatchMode=yes"])
+        .arg(source)
+        .arg(Path::new("target").join(target));
+    Ok(cmd)
+}
+
+/// Build `target` on the SSH `host` and copy its binaries back
+fn build_over_ssh(
+    host: &HostConfig,
+    target: &str,
+    options: &BuildOptions,
+    limits: &RunLimits,
+) -> Result<()> {
+    let mut cmd = ssh_command(host, target, options)?;
+    let prefix = format!("[{}]", host.name);
+    let status = cancel::run_with_output(&mut cmd, limits, move |stream, line| {
+        cancel::echo(stream, &format!("{prefix} {line}"));
+    })?;
+    if !status.success() {
+        return Err(Error::Build(format!(
+            "xcargo {} failed on host {} ({status})",
+            options.operation.as_str(),
+            host.name
+        )));
+    }
+
+    if options.operation == CargoOperation::Build {
+        std::fs::create_dir_all(Path::new("target").join(target))?;
+        let mut fetch = fetch_command(host, target, options.release)?;
+        let status = cancel::run(&mut fetch, limits)?;
+        if !status.success() {
+            return Err(Error::Build(format!(
+                "Could not copy the {target} build output from host {} ({status})",
+                host.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// How one target went on its host
+struct Outcome {
+    host: usize,
+    target: String,
+    result: std::result::Result<Duration, String>,
+}
+
+impl Builder {
+    /// Build `targets` across the hosts of `[[hosts]]`
+    pub async fn build_distributed(
+        &self,
+        targets: &[String],
+        options: &BuildOptions,
+    ) -> Result<()> {
+        let started = Instant::now();
+        let hosts = self.config().hosts.clone();
+        helpers::section(format!(
+            "xcargo {} ({} hosts)",
+            options.operation.as_str(),
+            hosts.len()
+        ));
+        let (targets, mut skipped) = self.exclude_targets(targets)?;
+        let source_hash = Self::source_hash();
+        let targets = self.resume_targets(targets, options, source_hash, &mut skipped)?;
+
+        let estimates = Self::estimates(&targets, options);
+        let costs: Vec<Duration> = estimates
+            .iter()
+            .map(|estimate| estimate.map_or(DEFAULT_COST, |estimate| estimate.duration))
+            .collect();
+        let assignment = assign(&targets, &hosts, &costs, |triple| {
+            self.container_image(triple).is_some()
+        })?;
+
+        let local: Vec<String> = targets
+            .iter()
+            .zip(&assignment)
+            .filter(|(_, &host)| hosts[host].kind != HostKind::Ssh)
+            .map(|(target, _)| target.clone())
+            .collect();
+        if self.is_offline(options) {
+            self.check_offline(&local, options)?;
+        }
+        if options.operation.compiles() {
+            self.install_missing_targets(&local, options)?;
+        }
+
+        let mut queues = vec![VecDeque::new(); hosts.len()];
+        for (target, &host) in targets.iter().zip(&assignment) {
+            queues[host].push_back(target.clone());
+        }
+        for (host, queue) in hosts.iter().zip(&queues) {
+            if !queue.is_empty() {
+                let queue: Vec<&str> = queue.iter().map(String::as_str).collect();
+                helpers::info(format!(
+                    "{} ({}): {}",
+                    host.name,
+                    host.kind,
+                    queue.join(", ")
+                ));
+            }
+        }
+
+        let limits = self.run_limits(options)?;
+        let hosts = Arc::new(hosts);
+        let outcomes = Arc::new(Mutex::new(Vec::new()));
+        let cancelled = Arc::new(Mutex::new(Vec::new()));
+        let diagnostics = self.diagnostics().clone();
+        diagnostics.start(false);
+
+        let mut handles = Vec::new();
+        for (host, queue) in queues.into_iter().enumerate() {
+            let queue = Arc::new(Mutex::new(queue));
+            for _ in 0..hosts[host].jobs.max(1) {
+                let worker = Worker {
+                    host,
+                    hosts: Arc::clone(&hosts),
+                    queue: Arc::clone(&queue),
+                    options: options.clone(),
+                    limits: limits.clone(),
+                    token: self.cancellation_token().clone(),
+                    plugins: Arc::clone(self.plugins()),
+                    diagnostics: diagnostics.clone(),
+                    outcomes: Arc::clone(&outcomes),
+                    cancelled: Arc::clone(&cancelled),
+                };
+                handles.push(task::spawn_blocking(move || worker.run()));
+            }
+        }
+        for handle in handles {
+            handle
+                .await
+                .map_err(|e| Error::Build(format!("Task join error: {e}")))?;
+        }
+
+        let outcomes = outcomes.lock().unwrap();
+        let results: Vec<(String, bool)> = outcomes
+            .iter()
+            .map(|outcome| (outcome.target.clone(), outcome.result.is_ok()))
+            .collect();
+        self.record_results(&results, options, source_hash);
+        diagnostics.finish();
+
+        print_hosts(&hosts, &outcomes);
+        let failures: Vec<&str> = outcomes
+            .iter()
+            .filter(|outcome| outcome.result.is_err())
+            .map(|outcome| outcome.target.as_str())
+            .collect();
+        skipped.extend(
+            cancelled
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|target| (target.clone(), "cancelled".to_string())),
+        );
+        print_skipped(&skipped);
+
+        let was_cancelled = self.cancellation_token().is_cancelled();
+        self.finish_builds(
+            options,
+            &BuildsSummary {
+                operation: options.operation.as_str().to_string(),
+                succeeded: outcomes
+                    .iter()
+                    .filter(|outcome| outcome.result.is_ok())
+                    .map(|outcome| outcome.target.clone())
+                    .collect(),
+                failed: failures.iter().map(ToString::to_string).collect(),
+                skipped: skipped.len(),
+                cancelled: was_cancelled,
+                elapsed: started.elapsed(),
+            },
+        );
+        if !failures.is_empty() {
+            print_resume_hint(options);
+            if was_cancelled {
+                return Err(Error::Cancelled);
+            }
+            return Err(Error::Build("Some targets failed to build".to_string()));
+        }
+        if was_cancelled {
+            print_resume_hint(options);
+            return Err(Error::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+/// One job slot of a host, building targets from the host's queue
+struct Worker {
+    host: usize,
+    hosts: Arc<Vec<HostConfig>>,
+    queue: Arc<Mutex<VecDeque<String>>>,
+    options: BuildOptions,
+    limits: RunLimits,
+    token: CancellationToken,
+    plugins: Arc<crate::plugin::PluginRegistry>,
+    diagnostics: super::diagnostics::DiagnosticSummary,
+    outcomes: Arc<Mutex<Vec<Outcome>>>,
+    cancelled: Arc<Mutex<Vec<String>>>,
+}
+
+impl Worker {
+    fn run(self) {
+        loop {
+            let Some(target) = self.queue.lock().unwrap().pop_front() else {
+                return;
+            };
+            if self.token.is_cancelled() {
+                self.cancelled.lock().unwrap().push(target);
+                continue;
+            }
+
+            let host = &self.hosts[self.host];
+            println!();
+            helpers::info(format!("[{}] Starting build for: {target}", host.name));
+            helpers::separator();
+
+            let started = Instant::now();
+            match self.build(host, &target) {
+                Ok(()) => self.outcomes.lock().unwrap().push(Outcome {
+                    host: self.host,
+                    target,
+                    result: Ok(started.elapsed()),
+                }),
+                Err(Error::Cancelled) => self.cancelled.lock().unwrap().push(target),
+                Err(e) => {
+                    helpers::error(format!("Failed to build {target} on {}: {e}", host.name));
+                    self.outcomes.lock().unwrap().push(Outcome {
+                        host: self.host,
+                        target,
+                        result: Err(e.to_string()),
+                    });
+                }
+            }
+        }
+    }
+
+    fn build(&self, host: &HostConfig, target: &str) -> Result<()> {
+        let mut options = self.options.clone();
+        options.target = Some(target.to_string());
+        if host.kind == HostKind::Ssh {
+            return build_over_ssh(host, target, &options, &self.limits);
+        }
+        options.use_container = host.kind == HostKind::Container;
+        Builder::new()?
+            .with_cancellation(self.token.clone())
+            .with_plugins(Arc::clone(&self.plugins))
+            .with_diagnostics(self.diagnostics.clone())
+            .build_with_retries(&options)
+    }
+}
+
+/// Report what each host built, and how long it was busy
+fn print_hosts(hosts: &[HostConfig], outcomes: &[Outcome]) {
+    println!();
+    helpers::section("Build Summary by Host");
+    for (idx, host) in hosts.iter().enumerate() {
+        let done: Vec<&Outcome> = outcomes
+            .iter()
+            .filter(|outcome| outcome.host == idx)
+            .collect();
+        if done.is_empty() {
+            continue;
+        }
+        let busy: Duration = done
+            .iter()
+            .filter_map(|outcome| outcome.result.as_ref().ok())
+            .sum();
+        let built = done.iter().filter(|outcome| outcome.result.is_ok()).count();
+        println!(
+            "{} ({}): {built} of {} built, {} building",
+            host.name,
+            host.kind,
+            done.len(),
+            history::approximate(busy)
+        );
+        for outcome in done {
+            match &outcome.result {
+                Ok(took) => println!("  ✓ {} ({})", outcome.target, history::approximate(*took)),
+                Err(e) => println!("  ✗ {}: {e}", outcome.target),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(name: &str, kind: HostKind, targets: &[&str], jobs: usize) -> HostConfig {
+        HostConfig {
+            name: name.to_string(),
+            kind,
+            address: Some(format!("ci@{name}")),
+            dir: Some("/src/app".to_string()),
+            targets: targets.iter().map(ToString::to_string).collect(),
+            jobs,
+        }
+    }
+
+    fn triples(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_assign_by_capability() {
+        let hosts = [
+            host("here", HostKind::Local, &["*-linux-*"], 1),
+            host("mac", HostKind::Ssh, &["*-apple-*"], 1),
+            host("docker", HostKind::Container, &[], 1),
+        ];
+        let targets = triples(&[
+            "aarch64-apple-darwin",
+            "x86_64-unknown-linux-gnu",
+            "x86_64-pc-windows-gnu",
+        ]);
+        let costs = [DEFAULT_COST; 3];
+
+        let assignment = assign(&targets, &hosts, &costs, |_| true).unwrap();
+        assert_eq!(assignment, [1, 0, 2]);
+
+        // Without an image, nothing builds Windows
+        let error = assign(&targets, &hosts, &costs, |_| false).unwrap_err();
+        assert!(error.to_string().contains("x86_64-pc-windows-gnu"));
+    }
+
+    #[test]
+    fn test_assign_balances_load() {
+        let hosts = [
+            host("small", HostKind::Local, &[], 1),
+            host("big", HostKind::Ssh, &[], 2),
+        ];
+        let targets = triples(&["a", "b", "c", "d"]);
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+        let costs = [minutes(1), minutes(10), minutes(1), minutes(4)];
+
+        // The 10 minute build goes to the big host, which has room left
+        // for a second build before it is as busy as the small one
+        let assignment = assign(&targets, &hosts, &costs, |_| true).unwrap();
+        assert_eq!(assignment[1], 1);
+        assert_eq!(assignment[3], 0);
+        assert_eq!(assignment.iter().filter(|&&host| host == 1).count(), 2);
+    }
+
+    #[test]
+    fn test_ssh_command() {
+        let host = host("mac", HostKind::Ssh, &[], 1);
+        let options = BuildOptions {
+            release: true,
+            features: vec!["tls".to_string()],
+            cargo_args: vec!["--locked".to_string()],
+            ..BuildOptions::default()
+        };
+        let cmd = ssh_command(&host, "aarch64-apple-darwin", &options).unwrap();
+        let args: Vec<String> = cmd
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(args[..3], ["-o", "BatchMode=yes", "ci@mac"]);
+        assert_eq!(
+            args[3],
+            "cd '/src/app' && xcargo 'build' '--target' 'aarch64-apple-darwin' '--release' \
+             '--features' 'tls' '--' '--locked'"
+        );
+
+        let fetch = fetch_command(&host, "aarch64-apple-darwin", true).unwrap();
+        assert!(fetch
+            .get_args()
+            .any(|arg| arg == "ci@mac:/src/app/target/aarch64-apple-darwin/release"));
+    }
+}
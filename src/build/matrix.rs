@@ -0,0 +1,98 @@
+//! Buildability matrix: what `xcargo build --all` would do on this host
+//!
+//! For each target, whether its standard library is installed and whether
+//! the native toolchain, Zig and a container could build it, with the
+//! strategy a build would pick.
+
+use super::strategy::{Availability, Strategy};
+use crate::output::helpers;
+use serde::Serialize;
+
+/// Whether a strategy could build a target here, and why
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Check {
+    /// Whether it could
+    pub available: bool,
+    /// Why it could, or what is missing
+    pub reason: String,
+}
+
+impl From<Availability> for Check {
+    fn from(availability: Availability) -> Self {
+        match availability {
+            Ok(reason) => Self {
+                available: true,
+                reason,
+            },
+            Err(reason) => Self {
+                available: false,
+                reason,
+            },
+        }
+    }
+}
+
+/// One target of the matrix
+#[derive(Debug, Clone, Serialize)]
+pub struct MatrixRow {
+    /// Target triple
+    pub target: String,
+    /// Whether the target's standard library is installed with rustup
+    pub installed: bool,
+    /// Whether the native toolchain could build it
+    pub native: Check,
+    /// Whether Zig could build it
+    pub zig: Check,
+    /// Whether a container could build it
+    pub container: Check,
+    /// Strategy a build would use
+    pub strategy: Strategy,
+    /// Whether that strategy can actually build it
+    pub buildable: bool,
+}
+
+/// Print `rows` as a table, then what is missing for targets that cannot
+/// be built
+pub fn print(rows: &[MatrixRow]) {
+    let width = rows
+        .iter()
+        .map(|row| row.target.len())
+        .chain(["TARGET".len()])
+        .max()
+        .unwrap_or(0);
+    println!(
+        "{:<width$}  {:<9}  {:<6}  {:<4}  {:<9}  BUILDS WITH",
+        "TARGET", "INSTALLED", "NATIVE", "ZIG", "CONTAINER"
+    );
+    for row in rows {
+        let builds_with = if row.buildable {
+            row.strategy.to_string()
+        } else {
+            format!("{} (fails)", row.strategy)
+        };
+        println!(
+            "{:<width$}  {:<9}  {:<6}  {:<4}  {:<9}  {builds_with}",
+            row.target,
+            helpers::status_marker(row.installed),
+            helpers::status_marker(row.native.available),
+            helpers::status_marker(row.zig.available),
+            helpers::status_marker(row.container.available),
+        );
+    }
+
+    let unbuildable: Vec<&MatrixRow> = rows.iter().filter(|row| !row.buildable).collect();
+    if unbuildable.is_empty() {
+        return;
+    }
+    println!();
+    for row in unbuildable {
+        helpers::warning(format!("{} cannot be built here", row.target));
+        for (name, check) in [
+            ("native", &row.native),
+            ("zig", &row.zig),
+            ("container", &row.container),
+        ] {
+            helpers::hint(format!("{name}: {}", check.reason));
+        }
+    }
+}
@@ -3,10 +3,29 @@
 //! This module handles the actual build process, including invoking cargo
 //! with the appropriate flags for cross-compilation.
 
+pub mod android;
+pub mod artifacts;
+pub mod clean;
+pub mod diagnostics;
+pub mod embedded;
 mod executor;
+pub mod gates;
+pub mod glibc;
+pub mod hosts;
+pub mod matrix;
+pub mod nix;
+mod offline;
 mod options;
 mod parallel;
+mod plan;
+pub mod qemu;
+pub mod rustflags;
+pub mod static_link;
+pub mod strategy;
+pub mod wasm;
+pub mod xcframework;
 
 // Re-export public types
 pub use executor::Builder;
-pub use options::{BuildOptions, CargoOperation};
+pub use options::{BuildOptions, CargoOperation, TargetSelection};
+pub use plan::BuildPlan;
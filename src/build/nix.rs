@@ -0,0 +1,161 @@
+//! Nix shells as a build strategy
+//!
+//! A project with a `flake.nix` or `shell.nix` (or `[strategies.nix]
+//! enable = true`) can get its cross toolchain from Nix instead of the host
+//! or a container: cargo runs inside `nix develop --command`, or
+//! `nix-shell --run` for a `shell.nix`, so the linker, C compiler and
+//! system libraries are the ones the shell pins. The shell's own
+//! environment wins over what xcargo sets.
+
+use crate::shell::quote;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A Nix development shell for the project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NixShell {
+    /// A flake's default dev shell, by the flake's directory
+    Flake(PathBuf),
+    /// A `shell.nix` file
+    Shell(PathBuf),
+}
+
+impl NixShell {
+    /// The shell for the project `start` is in: the first directory from
+    /// `start` up to the repository root with a `flake.nix` or `shell.nix`
+    #[must_use]
+    pub fn find(start: &Path) -> Option<Self> {
+        for dir in start.ancestors() {
+            if dir.join("flake.nix").is_file() {
+                return Some(Self::Flake(dir.to_path_buf()));
+            }
+            let shell = dir.join("shell.nix");
+            if shell.is_file() {
+                return Some(Self::Shell(shell));
+            }
+            if dir.join(".git").exists() {
+                break;
+            }
+        }
+        None
+    }
+
+    /// The file defining the shell
+    #[must_use]
+    pub fn file(&self) -> PathBuf {
+        match self {
+            Self::Flake(dir) => dir.join("flake.nix"),
+            Self::Shell(file) => file.clone(),
+        }
+    }
+
+    /// Program entering the shell
+    #[must_use]
+    pub fn program(&self) -> &'static str {
+        match self {
+            Self::Flake(_) => "nix",
+            Self::Shell(_) => "nix-shell",
+        }
+    }
+
+    /// `cmd` run inside the shell, with the environment and directory it
+    /// was given
+    #[must_use]
+    pub fn wrap(&self, cmd: &Command) -> Command {
+        let inner: Vec<String> = std::iter::once(cmd.get_program())
+            .chain(cmd.get_args())
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+
+        let mut wrapped = Command::new(self.program());
+        match self {
+            Self::Flake(dir) => {
+                wrapped
+                    .arg("develop")
+                    .arg(dir)
+                    .arg("--command")
+                    .args(&inner);
+            }
+            Self::Shell(file) => {
+                let script: Vec<String> = inner.iter().map(|arg| quote(arg)).collect();
+                wrapped.arg(file).arg("--run").arg(script.join(" "));
+            }
+        }
+        for (key, value) in cmd.get_envs() {
+            match value {
+                Some(value) => wrapped.env(key, value),
+                None => wrapped.env_remove(key),
+            };
+        }
+        if let Some(dir) = cmd.get_current_dir() {
+            wrapped.current_dir(dir);
+        }
+        wrapped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(cmd: &Command) -> Vec<String> {
+        cmd.get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_find() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let crate_dir = dir.path().join("crates/app");
+        std::fs::create_dir_all(&crate_dir).unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        assert_eq!(NixShell::find(&crate_dir), None);
+
+        std::fs::write(dir.path().join("shell.nix"), "").unwrap();
+        assert_eq!(
+            NixShell::find(&crate_dir),
+            Some(NixShell::Shell(dir.path().join("shell.nix")))
+        );
+        std::fs::write(dir.path().join("flake.nix"), "").unwrap();
+        assert_eq!(
+            NixShell::find(&crate_dir),
+            Some(NixShell::Flake(dir.path().to_path_buf()))
+        );
+    }
+
+    #[test]
+    fn test_wrap() {
+        let mut cargo = Command::new("cargo");
+        cargo
+            .args(["build", "--target", "aarch64-unknown-linux-gnu"])
+            .env("RUSTFLAGS", "-Dwarnings");
+
+        let flake = NixShell::Flake(PathBuf::from("/src/app")).wrap(&cargo);
+        assert_eq!(flake.get_program(), "nix");
+        assert_eq!(
+            args(&flake),
+            [
+                "develop",
+                "/src/app",
+                "--command",
+                "cargo",
+                "build",
+                "--target",
+                "aarch64-unknown-linux-gnu"
+            ]
+        );
+        assert_eq!(flake.get_envs().count(), 1);
+
+        let shell = NixShell::Shell(PathBuf::from("/src/app/shell.nix")).wrap(&cargo);
+        assert_eq!(shell.get_program(), "nix-shell");
+        assert_eq!(
+            args(&shell),
+            [
+                "/src/app/shell.nix",
+                "--run",
+                "'cargo' 'build' '--target' 'aarch64-unknown-linux-gnu'"
+            ]
+        );
+    }
+}
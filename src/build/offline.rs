@@ -0,0 +1,69 @@
+//! Offline builds
+//!
+//! With `--offline` or `build.offline = true`, a build may not reach the
+//! network: rustup does not install toolchains or targets, container images
+//! are not pulled and native dependency archives are not downloaded. Instead
+//! of failing halfway when one of them is needed, everything the build needs
+//! is checked before it starts and whatever is missing is listed at once.
+//! Zig is never downloaded by xcargo, so a build forced onto it only needs
+//! it installed.
+
+use crate::error::{Error, Result};
+use crate::output::helpers;
+
+/// Something an offline build needs that is not available locally
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Missing {
+    /// Target that needs it
+    pub target: String,
+    /// What is missing (e.g., "container image ghcr.io/...")
+    pub what: String,
+    /// How to get it while online
+    pub fix: String,
+}
+
+impl Missing {
+    /// Something `target` needs
+    pub fn new(target: &str, what: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            target: target.to_string(),
+            what: what.into(),
+            fix: fix.into(),
+        }
+    }
+}
+
+/// Fail listing everything in `missing`, if anything
+pub fn report(missing: &[Missing]) -> Result<()> {
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    helpers::error("The build is offline, but needs what is not available locally:");
+    for item in missing {
+        println!("  {} {}: {}", helpers::bullet(), item.target, item.what);
+        println!("      {}", item.fix);
+    }
+    helpers::hint("Run the commands above while online, or build without --offline");
+    Err(Error::Build(format!(
+        "Offline build is missing {} item(s)",
+        missing.len()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        assert!(report(&[]).is_ok());
+
+        let missing = [
+            Missing::new("aarch64-unknown-linux-gnu", "target", "rustup target add"),
+            Missing::new("x86_64-pc-windows-gnu", "image", "docker pull"),
+        ];
+        let error = report(&missing).unwrap_err();
+        assert!(error.to_string().contains("missing 2 item(s)"));
+    }
+}
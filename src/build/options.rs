@@ -1,5 +1,9 @@
 //! Build options and cargo operations
 
+use super::artifacts::{ArtifactKind, Product};
+use crate::coverage;
+use std::time::Duration;
+
 /// Cargo operation type
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum CargoOperation {
@@ -10,9 +14,28 @@ pub enum CargoOperation {
     Check,
     /// cargo test
     Test,
+    /// cargo clippy
+    Clippy,
+    /// cargo doc
+    Doc,
+    /// cargo bench
+    Bench,
+    /// cargo clean
+    Clean,
 }
 
 impl CargoOperation {
+    /// Every operation, in the order of `xcargo --help`
+    pub const ALL: [CargoOperation; 7] = [
+        CargoOperation::Build,
+        CargoOperation::Check,
+        CargoOperation::Test,
+        CargoOperation::Clippy,
+        CargoOperation::Doc,
+        CargoOperation::Bench,
+        CargoOperation::Clean,
+    ];
+
     /// Get the cargo subcommand name
     #[must_use]
     pub fn as_str(&self) -> &'static str {
@@ -20,9 +43,19 @@ impl CargoOperation {
             CargoOperation::Build => "build",
             CargoOperation::Check => "check",
             CargoOperation::Test => "test",
+            CargoOperation::Clippy => "clippy",
+            CargoOperation::Doc => "doc",
+            CargoOperation::Bench => "bench",
+            CargoOperation::Clean => "clean",
         }
     }
 
+    /// Operation for a cargo subcommand name
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|op| op.as_str() == name)
+    }
+
     /// Get a human-readable description
     #[must_use]
     pub fn description(&self) -> &'static str {
@@ -30,12 +63,120 @@ impl CargoOperation {
             CargoOperation::Build => "Building",
             CargoOperation::Check => "Checking",
             CargoOperation::Test => "Testing",
+            CargoOperation::Clippy => "Linting",
+            CargoOperation::Doc => "Documenting",
+            CargoOperation::Bench => "Benchmarking",
+            CargoOperation::Clean => "Cleaning",
         }
     }
+
+    /// Whether the operation links binaries for the target, and so needs
+    /// its linker (check, clippy and doc only compile metadata)
+    #[must_use]
+    pub fn links(&self) -> bool {
+        matches!(
+            self,
+            CargoOperation::Build | CargoOperation::Test | CargoOperation::Bench
+        )
+    }
+
+    /// Whether the operation compiles, and so needs the target's standard
+    /// library installed (everything but clean)
+    #[must_use]
+    pub fn compiles(&self) -> bool {
+        *self != CargoOperation::Clean
+    }
+
+    /// Whether cargo accepts `--release` (bench always uses the bench
+    /// profile)
+    #[must_use]
+    pub fn accepts_release(&self) -> bool {
+        *self != CargoOperation::Bench
+    }
+}
+
+/// Cargo targets a build is limited to (`--bin`, `--example`, `--lib`)
+///
+/// An empty selection builds what cargo builds by default: the library and
+/// every binary, but no examples.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetSelection {
+    /// Binaries to build (`--bin`)
+    pub bins: Vec<String>,
+    /// Examples to build (`--example`)
+    pub examples: Vec<String>,
+    /// Build the library (`--lib`)
+    pub lib: bool,
+    /// Every cargo target of these kinds (`lib`, `bin`, `example`, `test`,
+    /// `bench`, `doc`), e.g. from `[targets.<triple>] kinds`
+    pub kinds: Vec<String>,
+}
+
+impl TargetSelection {
+    /// Selection of every cargo target of `kinds`
+    #[must_use]
+    pub fn of_kinds(kinds: &[String]) -> Self {
+        Self {
+            kinds: kinds.to_vec(),
+            ..Self::default()
+        }
+    }
+
+    /// Check if nothing was selected, so cargo's defaults apply
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.bins.is_empty() && self.examples.is_empty() && !self.lib && self.kinds.is_empty()
+    }
+
+    /// Arguments selecting these targets on the cargo command line
+    #[must_use]
+    pub fn cargo_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.lib || self.has_kind("lib") {
+            args.push("--lib".to_string());
+        }
+        for kind in &self.kinds {
+            match kind.as_str() {
+                "lib" => {}
+                "doc" => args.push("--doc".to_string()),
+                "bench" => args.push("--benches".to_string()),
+                kind => args.push(format!("--{kind}s")),
+            }
+        }
+        for bin in &self.bins {
+            args.push("--bin".to_string());
+            args.push(bin.clone());
+        }
+        for example in &self.examples {
+            args.push("--example".to_string());
+            args.push(example.clone());
+        }
+        args
+    }
+
+    /// Check if a build with this selection produces `product`
+    #[must_use]
+    pub fn includes(&self, product: &Product) -> bool {
+        if self.is_empty() {
+            return product.kind != ArtifactKind::Example;
+        }
+        match product.kind {
+            ArtifactKind::Bin => self.has_kind("bin") || self.bins.contains(&product.name),
+            ArtifactKind::Example => {
+                self.has_kind("example") || self.examples.contains(&product.name)
+            }
+            ArtifactKind::Cdylib | ArtifactKind::Staticlib => self.lib || self.has_kind("lib"),
+        }
+    }
+
+    fn has_kind(&self, kind: &str) -> bool {
+        self.kinds.iter().any(|k| k == kind)
+    }
 }
 
 /// Build options and configuration
 #[derive(Debug, Clone)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct BuildOptions {
     /// Target triple to build for
     pub target: Option<String>,
@@ -49,8 +190,11 @@ pub struct BuildOptions {
     /// Toolchain to use (defaults to active)
     pub toolchain: Option<String>,
 
-    /// Verbose output
-    pub verbose: bool,
+    /// Verbosity: 1 (`-v`) reports xcargo's decisions (strategy, linker,
+    /// environment set) and the commands it runs, 2 (`-vv`) also runs cargo
+    /// with `--verbose`, 3 (`-vvv`) also prints the full environment of each
+    /// cargo command with secrets redacted
+    pub verbose: u8,
 
     /// Use container for build
     pub use_container: bool,
@@ -58,8 +202,78 @@ pub struct BuildOptions {
     /// Zig preference: None = auto, Some(true) = force, Some(false) = disable
     pub use_zig: Option<bool>,
 
-    /// Cargo operation (build, check, test)
+    /// glibc version Zig links `*-linux-gnu` targets against (e.g., "2.17")
+    pub glibc_version: Option<String>,
+
+    /// Cargo operation (build, check, test, clippy, doc, bench, clean)
     pub operation: CargoOperation,
+
+    /// Binaries, examples and library to build (everything cargo builds
+    /// by default when empty)
+    pub selection: TargetSelection,
+
+    /// Cargo features to enable (merged with per-target config)
+    pub features: Vec<String>,
+
+    /// Disable default features
+    pub no_default_features: bool,
+
+    /// Enable all features
+    pub all_features: bool,
+
+    /// Times to retry a failed target in multi-target builds (overrides
+    /// `build.retries`)
+    pub retries: Option<u32>,
+
+    /// Have cargo build every crate it can after one fails to compile, with
+    /// cargo's `--keep-going`; only [`CargoOperation::Build`] passes it on.
+    /// Multi-target builds build every target either way
+    pub keep_going: bool,
+
+    /// Skip targets whose last build with the same settings and sources
+    /// succeeded, according to the build cache
+    pub resume: bool,
+
+    /// Longest a single target may build (overrides `build.timeout`)
+    pub timeout: Option<Duration>,
+
+    /// Record each crate's compile time with cargo's `--timings=json`
+    /// (nightly only), see [`crate::timings`]
+    pub timings: bool,
+
+    /// Instrument the tests and report their coverage in this format,
+    /// see [`crate::coverage`]
+    pub coverage: Option<coverage::Format>,
+
+    /// Never use the network: no rustup installs, image pulls or downloads,
+    /// and cargo runs with `--offline` (see also `build.offline`)
+    pub offline: bool,
+
+    /// Never install toolchains, targets or components with rustup; fail
+    /// instead when one is missing
+    pub no_install: bool,
+
+    /// Link binaries statically (see also `[targets.<triple>] static`),
+    /// see [`super::static_link`]
+    pub static_link: bool,
+
+    /// Rustflags for this build (`--rustflags`), merged over every
+    /// configured one, see [`super::rustflags`]
+    pub rustflags: Vec<String>,
+}
+
+impl BuildOptions {
+    /// Whether cargo runs with `--verbose` (`-vv`)
+    #[must_use]
+    pub fn cargo_verbose(&self) -> bool {
+        self.verbose >= 2
+    }
+
+    /// Whether the full environment of cargo commands is printed (`-vvv`)
+    #[must_use]
+    pub fn dumps_commands(&self) -> bool {
+        self.verbose >= 3
+    }
 }
 
 impl Default for BuildOptions {
@@ -69,10 +283,25 @@ impl Default for BuildOptions {
             release: false,
             cargo_args: Vec::new(),
             toolchain: None,
-            verbose: false,
+            verbose: 0,
             use_container: false,
             use_zig: None,
+            glibc_version: None,
             operation: CargoOperation::Build,
+            selection: TargetSelection::default(),
+            features: Vec::new(),
+            no_default_features: false,
+            all_features: false,
+            retries: None,
+            keep_going: false,
+            resume: false,
+            timeout: None,
+            timings: false,
+            coverage: None,
+            offline: false,
+            no_install: false,
+            static_link: false,
+            rustflags: Vec::new(),
         }
     }
 }
@@ -86,6 +315,10 @@ mod tests {
         assert_eq!(CargoOperation::Build.as_str(), "build");
         assert_eq!(CargoOperation::Check.as_str(), "check");
         assert_eq!(CargoOperation::Test.as_str(), "test");
+        assert_eq!(CargoOperation::Clippy.as_str(), "clippy");
+        assert_eq!(CargoOperation::Doc.as_str(), "doc");
+        assert_eq!(CargoOperation::Bench.as_str(), "bench");
+        assert_eq!(CargoOperation::Clean.as_str(), "clean");
     }
 
     #[test]
@@ -93,6 +326,66 @@ mod tests {
         assert_eq!(CargoOperation::Build.description(), "Building");
         assert_eq!(CargoOperation::Check.description(), "Checking");
         assert_eq!(CargoOperation::Test.description(), "Testing");
+        assert_eq!(CargoOperation::Doc.description(), "Documenting");
+    }
+
+    #[test]
+    fn test_cargo_operation_from_name() {
+        for op in CargoOperation::ALL {
+            assert_eq!(CargoOperation::from_name(op.as_str()), Some(op));
+        }
+        assert_eq!(CargoOperation::from_name("publish"), None);
+    }
+
+    #[test]
+    fn test_cargo_operation_requirements() {
+        assert!(CargoOperation::Bench.links());
+        assert!(!CargoOperation::Doc.links());
+        assert!(!CargoOperation::Clippy.links());
+        assert!(CargoOperation::Doc.compiles());
+        assert!(!CargoOperation::Clean.compiles());
+        assert!(!CargoOperation::Bench.accepts_release());
+        assert!(CargoOperation::Clippy.accepts_release());
+    }
+
+    #[test]
+    fn test_target_selection() {
+        let product = |name: &str, kind| Product {
+            package: "app".to_string(),
+            name: name.to_string(),
+            kind,
+        };
+        let all = TargetSelection::default();
+        assert!(all.is_empty());
+        assert!(all.cargo_args().is_empty());
+        assert!(all.includes(&product("app", ArtifactKind::Bin)));
+        assert!(!all.includes(&product("demo", ArtifactKind::Example)));
+
+        let selection = TargetSelection {
+            bins: vec!["app".to_string()],
+            examples: vec!["demo".to_string()],
+            lib: false,
+            kinds: Vec::new(),
+        };
+        assert_eq!(
+            selection.cargo_args(),
+            vec!["--bin", "app", "--example", "demo"]
+        );
+        assert!(selection.includes(&product("app", ArtifactKind::Bin)));
+        assert!(!selection.includes(&product("helper", ArtifactKind::Bin)));
+        assert!(selection.includes(&product("demo", ArtifactKind::Example)));
+        assert!(!selection.includes(&product("ffi", ArtifactKind::Cdylib)));
+
+        let kinds = ["lib", "example", "test", "bench", "doc"].map(String::from);
+        let selection = TargetSelection::of_kinds(&kinds);
+        assert!(!selection.is_empty());
+        assert_eq!(
+            selection.cargo_args(),
+            vec!["--lib", "--examples", "--tests", "--benches", "--doc"]
+        );
+        assert!(selection.includes(&product("demo", ArtifactKind::Example)));
+        assert!(selection.includes(&product("ffi", ArtifactKind::Cdylib)));
+        assert!(!selection.includes(&product("app", ArtifactKind::Bin)));
     }
 
     #[test]
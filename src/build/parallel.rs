@@ -1,15 +1,19 @@
 //! Parallel build execution
 
 use crate::error::{Error, Result};
+use crate::history;
 use crate::output::helpers;
+use crate::plugin::BuildsSummary;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::task;
 
-use super::executor::Builder;
+use super::executor::{print_resume_hint, print_skipped, Builder};
 use super::options::BuildOptions;
 
 impl Builder {
-    /// Build multiple targets in parallel using tokio tasks
+    /// Build multiple targets in parallel using tokio tasks, or across the
+    /// hosts of `[[hosts]]` if there are any
     pub async fn build_all_parallel(
         &self,
         targets: &[String],
@@ -17,16 +21,40 @@ impl Builder {
     ) -> Result<()> {
         use crate::output::progress::MultiTargetProgress;
 
+        if !self.config().hosts.is_empty() {
+            return self.build_distributed(targets, options).await;
+        }
+
         helpers::section(format!("xcargo {} (parallel)", options.operation.as_str()));
+        let (targets, mut skipped) = self.exclude_targets(targets)?;
+        let source_hash = Self::source_hash();
+        let targets = self.resume_targets(targets, options, source_hash, &mut skipped)?;
         helpers::info(format!(
             "{} for {} targets in parallel",
             options.operation.description(),
             targets.len()
         ));
 
+        if self.is_offline(options) {
+            self.check_offline(&targets, options)?;
+        }
+        if options.operation.compiles() {
+            self.install_missing_targets(&targets, options)?;
+        }
+
+        let estimates = Arc::new(Self::estimates(&targets, options));
+        history::print_queue(&targets, &estimates, true);
+
         let multi_progress = MultiTargetProgress::new();
         let successes = Arc::new(Mutex::new(Vec::new()));
         let failures = Arc::new(Mutex::new(Vec::new()));
+        let cancelled = Arc::new(Mutex::new(Vec::new()));
+
+        let diagnostics = self.diagnostics().clone();
+        diagnostics.start(false);
+        // Targets still building, for the time left
+        let unfinished = Arc::new(Mutex::new(vec![true; targets.len()]));
+        let started = Instant::now();
 
         let mut handles = Vec::new();
 
@@ -37,18 +65,34 @@ impl Builder {
 
             let successes = Arc::clone(&successes);
             let failures = Arc::clone(&failures);
+            let cancelled = Arc::clone(&cancelled);
+            let token = self.cancellation_token().clone();
+            let plugins = Arc::clone(self.plugins());
+            let diagnostics = diagnostics.clone();
+            let estimates = Arc::clone(&estimates);
+            let unfinished = Arc::clone(&unfinished);
 
             let handle = task::spawn_blocking(move || {
                 use crate::output::helpers;
 
+                if token.is_cancelled() {
+                    unfinished.lock().unwrap()[idx] = false;
+                    cancelled.lock().unwrap().push(target);
+                    return;
+                }
+
                 println!();
                 helpers::info(format!("[{}] Starting build for: {}", idx + 1, target));
-                println!("{}", "─".repeat(50));
+                helpers::separator();
 
                 // Create a new builder for this task
                 let builder = match Builder::new() {
-                    Ok(b) => b,
+                    Ok(b) => b
+                        .with_cancellation(token)
+                        .with_plugins(plugins)
+                        .with_diagnostics(diagnostics),
                     Err(e) => {
+                        unfinished.lock().unwrap()[idx] = false;
                         let mut failures = failures.lock().unwrap();
                         failures.push(target.clone());
                         helpers::error(format!("Failed to create builder for {target}: {e}"));
@@ -56,11 +100,14 @@ impl Builder {
                     }
                 };
 
-                match builder.build(&target_options) {
+                let result = builder.build_with_retries(&target_options);
+                print_time_left(&estimates, &unfinished, idx, started.elapsed());
+                match result {
                     Ok(()) => {
                         let mut successes = successes.lock().unwrap();
                         successes.push(target.clone());
                     }
+                    Err(Error::Cancelled) => cancelled.lock().unwrap().push(target),
                     Err(e) => {
                         let mut failures = failures.lock().unwrap();
                         failures.push(target.clone());
@@ -81,19 +128,80 @@ impl Builder {
 
         let successes = successes.lock().unwrap();
         let failures = failures.lock().unwrap();
+        let cancelled = cancelled.lock().unwrap();
+
+        let results: Vec<(String, bool)> = successes
+            .iter()
+            .map(|target| (target.clone(), true))
+            .chain(failures.iter().map(|target| (target.clone(), false)))
+            .collect();
+        self.record_results(&results, options, source_hash);
+        diagnostics.finish();
 
         // Show summary with elapsed time
         multi_progress.finish_summary(successes.len(), failures.len());
-
+        skipped.extend(
+            cancelled
+                .iter()
+                .map(|target| (target.clone(), "cancelled".to_string())),
+        );
+        print_skipped(&skipped);
+
+        let was_cancelled = self.cancellation_token().is_cancelled();
+        self.finish_builds(
+            options,
+            &BuildsSummary {
+                operation: options.operation.as_str().to_string(),
+                succeeded: successes.clone(),
+                failed: failures.clone(),
+                skipped: skipped.len(),
+                cancelled: was_cancelled,
+                elapsed: started.elapsed(),
+            },
+        );
         if !failures.is_empty() {
             println!();
             helpers::error("Failed targets:");
             for target in failures.iter() {
                 helpers::error(format!("  - {target}"));
             }
+            print_resume_hint(options);
+            if was_cancelled {
+                return Err(Error::Cancelled);
+            }
             return Err(Error::Build("Some targets failed to build".to_string()));
         }
+        if was_cancelled {
+            print_resume_hint(options);
+            return Err(Error::Cancelled);
+        }
 
         Ok(())
     }
 }
+
+/// After target `idx` finished, print the expected time until the targets
+/// still building or queued are done
+fn print_time_left(
+    estimates: &[Option<history::Estimate>],
+    unfinished: &Mutex<Vec<bool>>,
+    idx: usize,
+    elapsed: Duration,
+) {
+    let mut unfinished = unfinished.lock().unwrap();
+    unfinished[idx] = false;
+    let building: Vec<Option<history::Estimate>> = estimates
+        .iter()
+        .zip(unfinished.iter())
+        .filter(|(_, unfinished)| **unfinished)
+        .map(|(estimate, _)| *estimate)
+        .collect();
+    if let Some(left) = history::remaining(&building, true, elapsed) {
+        helpers::info(format!(
+            "{} of {} targets left ({} to go)",
+            building.len(),
+            estimates.len(),
+            history::approximate(left)
+        ));
+    }
+}
@@ -0,0 +1,53 @@
+//! Build plans: what a build would do, without running it
+
+use super::strategy::Selection;
+use crate::deps::DepResolution;
+use serde::Serialize;
+
+/// How a build for one target would be carried out
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildPlan {
+    /// Target triple
+    pub target: String,
+    /// Host triple
+    pub host: String,
+    /// Build path: "native", "zig", "container" or a plugin strategy
+    pub path: String,
+    /// How the build path was chosen
+    pub strategy: Selection,
+    /// Whether the target is the host and builds without cross-compilation
+    /// setup
+    pub native: bool,
+    /// Problems with the requested options (e.g., flags that are ignored)
+    pub warnings: Vec<String>,
+    /// Native dependency decisions
+    pub dependencies: Vec<DepResolution>,
+    /// Feature flags passed to cargo
+    pub feature_args: Vec<String>,
+    /// Whether a Cargo.lock was found to inspect
+    pub has_lockfile: bool,
+    /// Container image a container build would use, if there is one
+    pub image: Option<String>,
+    /// Whether Zig can cross-compile to the target
+    pub zig: bool,
+    /// What runs test and bench binaries on this host: "native", or the
+    /// runner command; `None` when they can only be built
+    pub runner: Option<String>,
+}
+
+impl BuildPlan {
+    /// Human-readable description of the build path
+    #[must_use]
+    pub fn path_description(&self) -> String {
+        if self.native {
+            return "native build (target is the host)".to_string();
+        }
+        match self.path.as_str() {
+            "container" => "container".to_string(),
+            "zig" => "native toolchain via Zig".to_string(),
+            "native" => "native toolchain".to_string(),
+            "nix" => "Nix shell".to_string(),
+            plugin => format!("{plugin} strategy (plugin)"),
+        }
+    }
+}
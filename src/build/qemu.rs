@@ -0,0 +1,78 @@
+//! QEMU user mode as the runner of Linux test and benchmark binaries
+//!
+//! On a Linux host, binaries of another Linux architecture run under
+//! `qemu-<arch>` (from the `qemu-user` package) when it is installed.
+//! Dynamically linked glibc binaries also need the target's libraries,
+//! which distro cross GCC packages put in `/usr/<gnu triple>`.
+
+use crate::deps::gnu_triple;
+use crate::target::Target;
+use std::path::Path;
+
+/// The QEMU user-mode program for `target`'s architecture
+#[must_use]
+pub fn program(target: &Target) -> Option<String> {
+    let arch = match target.arch.as_str() {
+        "x86" => "i386",
+        "powerpc" => "ppc",
+        "powerpc64" if target.triple.starts_with("powerpc64le") => "ppc64le",
+        "powerpc64" => "ppc64",
+        arch @ ("x86_64" | "aarch64" | "arm" | "riscv32" | "riscv64" | "s390x" | "loongarch64"
+        | "sparc64" | "mips" | "mips64") => arch,
+        _ => return None,
+    };
+    Some(format!("qemu-{arch}"))
+}
+
+/// Runner for `target`'s binaries on `host`, if both are Linux, the host
+/// cannot run them itself and QEMU for the target's architecture is
+/// installed
+#[must_use]
+pub fn runner(target: &Target, host: &Target) -> Option<String> {
+    let native = target.arch == host.arch || (host.arch == "x86_64" && target.arch == "x86");
+    if host.os != "linux" || target.os != "linux" || native {
+        return None;
+    }
+    let program = program(target)?;
+    which::which(&program).ok()?;
+    let sysroot = gnu_triple(target)
+        .filter(|_| {
+            target
+                .env
+                .as_deref()
+                .is_some_and(|env| env.starts_with("gnu"))
+        })
+        .map(|triple| Path::new("/usr").join(triple))
+        .filter(|dir| dir.is_dir());
+    Some(match sysroot {
+        Some(dir) => format!("{program} -L {}", dir.display()),
+        None => program,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_program() {
+        let program = |triple| program(&Target::from_triple(triple).unwrap());
+        assert_eq!(
+            program("aarch64-unknown-linux-gnu").as_deref(),
+            Some("qemu-aarch64")
+        );
+        assert_eq!(
+            program("armv7-unknown-linux-musleabihf").as_deref(),
+            Some("qemu-arm")
+        );
+        assert_eq!(
+            program("i686-unknown-linux-gnu").as_deref(),
+            Some("qemu-i386")
+        );
+        assert_eq!(
+            program("powerpc64le-unknown-linux-gnu").as_deref(),
+            Some("qemu-ppc64le")
+        );
+        assert_eq!(program("wasm32-unknown-unknown"), None);
+    }
+}
@@ -0,0 +1,349 @@
+//! Rustflags merged from every place that sets them
+//!
+//! Cargo takes rustflags from one place only: `RUSTFLAGS` when it is set,
+//! otherwise `.cargo/config.toml`. Once xcargo sets `RUSTFLAGS` itself the
+//! others would be lost, so they are merged instead, lowest precedence
+//! first: cargo's configuration, the caller's `RUSTFLAGS`, `[targets.<triple>]
+//! rustflags`, `--static` and `--rustflags`. Codegen options set twice keep
+//! the later value, target features are merged feature by feature, and
+//! repeated flags are kept once.
+
+use std::fmt;
+use std::path::PathBuf;
+
+/// Flags that take their value as the next argument
+const FLAGS_WITH_VALUE: &[&str] = &[
+    "-C",
+    "--codegen",
+    "--cfg",
+    "--check-cfg",
+    "-L",
+    "-l",
+    "-Z",
+    "-A",
+    "-W",
+    "-D",
+    "-F",
+    "--cap-lints",
+    "--emit",
+    "--print",
+];
+
+/// Codegen options that may be given more than once, each adding to the
+/// others
+const REPEATABLE_CODEGEN: &[&str] = &["link-arg", "link-args", "llvm-args", "pre-link-arg"];
+
+/// Where rustflags come from, lowest precedence first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FlagSource {
+    /// `rustflags` in a cargo configuration file
+    CargoConfig(PathBuf),
+    /// The caller's `RUSTFLAGS`
+    Environment,
+    /// `[targets.<triple>] rustflags` in xcargo.toml
+    Config,
+    /// `-C target-feature=+crt-static` for static builds
+    Static,
+    /// `-C instrument-coverage` for `xcargo coverage`
+    Coverage,
+    /// `--rustflags` on the command line
+    CommandLine,
+}
+
+impl fmt::Display for FlagSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CargoConfig(path) => write!(f, "{}", path.display()),
+            Self::Environment => write!(f, "RUSTFLAGS"),
+            Self::Config => write!(f, "xcargo.toml"),
+            Self::Static => write!(f, "--static"),
+            Self::Coverage => write!(f, "xcargo coverage"),
+            Self::CommandLine => write!(f, "--rustflags"),
+        }
+    }
+}
+
+/// One flag with its value, if it takes one
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Flag {
+    name: String,
+    value: Option<String>,
+}
+
+impl Flag {
+    /// Split `args` into flags, spelling each with its value as the next
+    /// argument (`-C key=value` for `-Ckey=value` or `--codegen=key=value`)
+    fn parse(args: &[String]) -> Vec<Self> {
+        let mut flags = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let (name, value) = if FLAGS_WITH_VALUE.contains(&arg.as_str()) {
+                (arg.as_str(), args.next().cloned())
+            } else if let Some((name, value)) = Self::attached(arg) {
+                (name, Some(value.to_string()))
+            } else {
+                (arg.as_str(), None)
+            };
+            let name = if name == "--codegen" { "-C" } else { name };
+            flags.push(Self {
+                name: name.to_string(),
+                value,
+            });
+        }
+        flags
+    }
+
+    /// A flag and its value given as one argument: `-Dwarnings`,
+    /// `--cfg=unix`
+    fn attached(arg: &str) -> Option<(&'static str, &str)> {
+        FLAGS_WITH_VALUE.iter().find_map(|flag| {
+            let rest = arg.strip_prefix(flag)?;
+            let value = if flag.starts_with("--") {
+                rest.strip_prefix('=')?
+            } else {
+                rest
+            };
+            (!value.is_empty()).then_some((*flag, value))
+        })
+    }
+
+    /// The codegen option's key and value, for `-C key=value`
+    fn codegen(&self) -> Option<(&str, &str)> {
+        let value = self.value.as_deref().filter(|_| self.name == "-C")?;
+        Some(value.split_once('=').unwrap_or((value, "")))
+    }
+
+    fn args(&self) -> Vec<String> {
+        std::iter::once(self.name.clone())
+            .chain(self.value.clone())
+            .collect()
+    }
+}
+
+impl fmt::Display for Flag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{} {value}", self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// A flag dropped because a later one contradicts it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Override {
+    /// The flag kept
+    pub kept: String,
+    /// Where it comes from
+    pub kept_from: FlagSource,
+    /// The flag dropped
+    pub dropped: String,
+    /// Where that came from
+    pub dropped_from: FlagSource,
+}
+
+impl fmt::Display for Override {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}) overrides {} ({})",
+            self.kept, self.kept_from, self.dropped, self.dropped_from
+        )
+    }
+}
+
+/// Rustflags for one build, merged from their sources
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RustFlags {
+    flags: Vec<(Flag, FlagSource)>,
+    overrides: Vec<Override>,
+}
+
+impl RustFlags {
+    /// Add `args` from `source`, over the flags added before
+    pub fn add(&mut self, source: &FlagSource, args: &[String]) {
+        for flag in Flag::parse(args) {
+            self.push(flag, source);
+        }
+    }
+
+    fn push(&mut self, flag: Flag, source: &FlagSource) {
+        let codegen = flag
+            .codegen()
+            .map(|(key, value)| (key.to_string(), value.to_string()));
+        match codegen {
+            Some((key, features)) if key == "target-feature" => {
+                self.add_features(&features, source);
+            }
+            Some((key, _)) if !REPEATABLE_CODEGEN.contains(&key.as_str()) => {
+                let earlier = self
+                    .flags
+                    .iter()
+                    .position(|(other, _)| other.codegen().is_some_and(|(k, _)| k == key));
+                match earlier {
+                    Some(index) if self.flags[index].0 == flag => {}
+                    Some(index) => {
+                        let (dropped, dropped_from) =
+                            std::mem::replace(&mut self.flags[index], (flag, source.clone()));
+                        self.overridden(index, &dropped, dropped_from);
+                    }
+                    None => self.flags.push((flag, source.clone())),
+                }
+            }
+            _ if self.flags.iter().any(|(other, _)| *other == flag) => {}
+            _ => self.flags.push((flag, source.clone())),
+        }
+    }
+
+    /// Merge `+feature,-feature` lists, the later sign of a feature winning
+    fn add_features(&mut self, features: &str, source: &FlagSource) {
+        let index = self.flags.iter().position(|(flag, _)| {
+            flag.codegen()
+                .is_some_and(|(key, _)| key == "target-feature")
+        });
+        let Some(index) = index else {
+            let flag = Flag {
+                name: "-C".to_string(),
+                value: Some(format!("target-feature={features}")),
+            };
+            self.flags.push((flag, source.clone()));
+            return;
+        };
+
+        let (earlier, earlier_from) = self.flags[index].clone();
+        let mut merged: Vec<String> = earlier
+            .codegen()
+            .map(|(_, list)| list.split(',').map(str::to_string).collect())
+            .unwrap_or_default();
+        let mut changed = false;
+        for feature in features.split(',').filter(|feature| !feature.is_empty()) {
+            let name = feature.trim_start_matches(['+', '-']);
+            match merged
+                .iter()
+                .position(|other| other.trim_start_matches(['+', '-']) == name)
+            {
+                Some(i) if merged[i] == feature => {}
+                Some(i) => {
+                    self.overrides.push(Override {
+                        kept: format!("-C target-feature={feature}"),
+                        kept_from: source.clone(),
+                        dropped: format!("-C target-feature={}", merged[i]),
+                        dropped_from: earlier_from.clone(),
+                    });
+                    merged[i] = feature.to_string();
+                    changed = true;
+                }
+                None => {
+                    merged.push(feature.to_string());
+                    changed = true;
+                }
+            }
+        }
+        if changed {
+            self.flags[index] = (
+                Flag {
+                    name: "-C".to_string(),
+                    value: Some(format!("target-feature={}", merged.join(","))),
+                },
+                source.clone(),
+            );
+        }
+    }
+
+    fn overridden(&mut self, index: usize, dropped: &Flag, dropped_from: FlagSource) {
+        let (kept, kept_from) = &self.flags[index];
+        self.overrides.push(Override {
+            kept: kept.to_string(),
+            kept_from: kept_from.clone(),
+            dropped: dropped.to_string(),
+            dropped_from,
+        });
+    }
+
+    /// Whether no flags are set
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.flags.is_empty()
+    }
+
+    /// The merged flags, as arguments
+    #[must_use]
+    pub fn args(&self) -> Vec<String> {
+        self.flags
+            .iter()
+            .flat_map(|(flag, _)| flag.args())
+            .collect()
+    }
+
+    /// Each merged flag with where it comes from
+    #[must_use]
+    pub fn sources(&self) -> Vec<(String, &FlagSource)> {
+        self.flags
+            .iter()
+            .map(|(flag, source)| (flag.to_string(), source))
+            .collect()
+    }
+
+    /// Flags dropped because later ones contradict them
+    #[must_use]
+    pub fn overrides(&self) -> &[Override] {
+        &self.overrides
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(flags: &str) -> Vec<String> {
+        flags.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_merge_rustflags() {
+        let cargo = FlagSource::CargoConfig(PathBuf::from(".cargo/config.toml"));
+        let mut flags = RustFlags::default();
+        flags.add(
+            &cargo,
+            &args("-Copt-level=2 --cfg tokio_unstable -C link-arg=-s -C target-feature=+avx2,-crt-static"),
+        );
+        flags.add(
+            &FlagSource::Environment,
+            &args("--cfg tokio_unstable -Dwarnings"),
+        );
+        flags.add(
+            &FlagSource::Config,
+            &args("--codegen opt-level=3 -C link-arg=-Wl,--gc-sections"),
+        );
+        flags.add(&FlagSource::Static, &args("-C target-feature=+crt-static"));
+
+        assert_eq!(
+            flags.args().join(" "),
+            "-C opt-level=3 --cfg tokio_unstable -C link-arg=-s -C target-feature=+avx2,+crt-static -D warnings -C link-arg=-Wl,--gc-sections"
+        );
+        let overrides: Vec<String> = flags.overrides().iter().map(ToString::to_string).collect();
+        assert_eq!(
+            overrides,
+            [
+                "-C opt-level=3 (xcargo.toml) overrides -C opt-level=2 (.cargo/config.toml)",
+                "-C target-feature=+crt-static (--static) overrides -C target-feature=-crt-static (.cargo/config.toml)",
+            ]
+        );
+        let sources = flags.sources();
+        assert_eq!(
+            sources[0],
+            ("-C opt-level=3".to_string(), &FlagSource::Config)
+        );
+        assert_eq!(
+            sources[4],
+            ("-D warnings".to_string(), &FlagSource::Environment)
+        );
+
+        // The same flag twice is no contradiction
+        let mut flags = RustFlags::default();
+        flags.add(&FlagSource::Config, &args("-C target-cpu=native"));
+        flags.add(&FlagSource::CommandLine, &args("-Ctarget-cpu=native"));
+        assert_eq!(flags.args(), ["-C", "target-cpu=native"]);
+        assert!(flags.overrides().is_empty());
+    }
+}
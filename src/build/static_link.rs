@@ -0,0 +1,124 @@
+//! Static linking (`--static` or `[targets.<triple>] static = true`)
+//!
+//! musl targets link statically by default, but C code built for them
+//! needs a musl C compiler: Zig, `musl-gcc` (for the host architecture) or
+//! an `<arch>-linux-musl-gcc` cross compiler. glibc and Windows targets get
+//! a static C runtime with `-C target-feature=+crt-static`; Apple
+//! platforms and WebAssembly have none to link. After the build, ELF
+//! executables are checked for a dynamic interpreter or shared libraries.
+
+use crate::error::Result;
+use crate::target::Target;
+use crate::verify::{BinaryFormat, BinaryInfo, Linkage};
+use std::path::{Path, PathBuf};
+
+/// Rustflag linking the C runtime statically
+pub const CRT_STATIC: &str = "-C target-feature=+crt-static";
+
+/// Check if `target` uses musl, which links statically by default
+#[must_use]
+pub fn is_musl(target: &Target) -> bool {
+    target
+        .env
+        .as_deref()
+        .is_some_and(|env| env.starts_with("musl"))
+}
+
+/// Check if `target` can link its C runtime statically at all
+#[must_use]
+pub fn is_supported(target: &Target) -> bool {
+    !matches!(
+        target.os.as_str(),
+        "macos" | "ios" | "tvos" | "watchos" | "visionos"
+    ) && !target.arch.starts_with("wasm")
+}
+
+/// `flags` with [`CRT_STATIC`] added where `target` needs it
+///
+/// musl targets and flags that already set `+crt-static` are left alone.
+#[must_use]
+pub fn with_crt_static(target: &Target, mut flags: Vec<String>) -> Vec<String> {
+    let present = flags.iter().any(|flag| flag.contains("+crt-static"));
+    if is_supported(target) && !is_musl(target) && !present {
+        flags.extend(CRT_STATIC.split(' ').map(str::to_string));
+    }
+    flags
+}
+
+/// C compilers that build for a musl `target`, most specific first
+///
+/// `musl-gcc` wraps the host's GCC, so it only fits the host architecture.
+#[must_use]
+pub fn musl_compilers(target: &Target, host: &Target) -> Vec<String> {
+    let mut compilers = vec![format!("{}-linux-musl-gcc", target.triple_arch())];
+    if target.arch == host.arch {
+        compilers.push("musl-gcc".to_string());
+    }
+    compilers
+}
+
+/// The first of [`musl_compilers`] on `PATH`
+#[must_use]
+pub fn find_musl_compiler(target: &Target, host: &Target) -> Option<String> {
+    musl_compilers(target, host)
+        .into_iter()
+        .find(|compiler| which::which(compiler).is_ok())
+}
+
+/// The ELF executables among `binaries` that are linked dynamically
+///
+/// Shared libraries are skipped, since they are loaded dynamically anyway.
+pub fn dynamic_binaries(binaries: &[PathBuf]) -> Result<Vec<(PathBuf, BinaryInfo)>> {
+    let mut dynamic = Vec::new();
+    for path in binaries.iter().filter(|path| !is_shared_library(path)) {
+        let info = BinaryInfo::parse(&std::fs::read(path)?)?;
+        if info.format == BinaryFormat::Elf && info.linkage == Linkage::Dynamic {
+            dynamic.push((path.clone(), info));
+        }
+    }
+    Ok(dynamic)
+}
+
+fn is_shared_library(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == "so" || ext == "dll" || ext == "dylib")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(triple: &str) -> Target {
+        Target::from_triple(triple).unwrap()
+    }
+
+    #[test]
+    fn test_with_crt_static() {
+        let flags = with_crt_static(
+            &target("x86_64-unknown-linux-gnu"),
+            vec!["-Dwarnings".into()],
+        );
+        assert_eq!(flags, ["-Dwarnings", "-C", "target-feature=+crt-static"]);
+
+        // Added once, and never for musl or Apple targets
+        assert_eq!(
+            with_crt_static(&target("x86_64-pc-windows-msvc"), flags.clone()),
+            flags
+        );
+        assert!(with_crt_static(&target("x86_64-unknown-linux-musl"), Vec::new()).is_empty());
+        assert!(with_crt_static(&target("aarch64-apple-darwin"), Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_musl_compilers() {
+        let host = target("x86_64-unknown-linux-gnu");
+        assert_eq!(
+            musl_compilers(&target("x86_64-unknown-linux-musl"), &host),
+            ["x86_64-linux-musl-gcc", "musl-gcc"]
+        );
+        assert_eq!(
+            musl_compilers(&target("aarch64-unknown-linux-musl"), &host),
+            ["aarch64-linux-musl-gcc"]
+        );
+    }
+}
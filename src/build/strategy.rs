@@ -0,0 +1,462 @@
+//! Build strategy selection
+//!
+//! A cross build runs with the native toolchain, through Zig, or in a
+//! container. Unless one is chosen explicitly, the strategy for a target
+//! is the first of the chain native → zig → container that can build it on
+//! this host: native needs the target's linker and tools, Zig needs to be
+//! installed and to support the target, and a container needs Docker or
+//! Podman and an image for the target. Zig goes first when only it can do
+//! what was asked (an older glibc, a static musl build).
+//!
+//! Projects with a Nix shell also get a `nix` strategy, tried before native
+//! (priority 35), which runs cargo inside the shell; see
+//! [`nix`](super::nix).
+//!
+//! Plugins can add strategies of their own (e.g., a remote builder), see
+//! [`Plugin::strategies`](crate::plugin::Plugin::strategies). They join the
+//! chain by `[strategies.<name>] priority`: native, Zig and container have
+//! 30, 20 and 10, so a plugin strategy without a priority is the last
+//! resort, and one above 30 is tried first.
+//!
+//! Explicit choices skip the chain, in this order: `--container` or
+//! `--zig`, `[targets.<triple>] strategy` (or `force_container`), and
+//! `[container] use_when`. Every step's verdict is kept, so
+//! `xcargo build --explain-strategy` can show why a strategy won.
+//!
+//! ```
+//! use xcargo::build::strategy::{self, Facts, Strategy};
+//!
+//! let facts = Facts {
+//!     native: Err("linker aarch64-linux-gnu-gcc is not installed".to_string()),
+//!     zig: Ok("Zig supports aarch64-unknown-linux-gnu".to_string()),
+//!     ..Facts::default()
+//! };
+//! let selection = strategy::select(&facts);
+//! assert_eq!(selection.strategy, Strategy::Zig);
+//! ```
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Metadata, Schema, SchemaObject};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How a target is built
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Strategy {
+    /// The local Rust toolchain and the target's linker
+    Native,
+    /// The local Rust toolchain with `zig cc` as linker and C compiler
+    Zig,
+    /// cargo inside a Docker or Podman image for the target
+    Container,
+    /// cargo inside the project's Nix shell
+    Nix,
+    /// A strategy provided by a plugin, by name
+    Plugin(String),
+}
+
+impl Strategy {
+    /// The built-in chain, in the order strategies are tried
+    pub const CHAIN: [Self; 3] = [Self::Native, Self::Zig, Self::Container];
+
+    /// Name used in configuration and output
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Native => "native",
+            Self::Zig => "zig",
+            Self::Container => "container",
+            Self::Nix => "nix",
+            Self::Plugin(name) => name,
+        }
+    }
+
+    /// Place of a built-in strategy in the chain; higher goes first
+    #[must_use]
+    pub fn priority(&self) -> i32 {
+        match self {
+            Self::Nix => 35,
+            Self::Native => 30,
+            Self::Zig => 20,
+            Self::Container => 10,
+            Self::Plugin(_) => 0,
+        }
+    }
+
+    /// Check if this is one of native, zig, container and nix
+    #[must_use]
+    pub fn is_builtin(&self) -> bool {
+        !matches!(self, Self::Plugin(_))
+    }
+}
+
+impl From<String> for Strategy {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "native" => Self::Native,
+            "zig" => Self::Zig,
+            "container" => Self::Container,
+            "nix" => Self::Nix,
+            _ => Self::Plugin(name),
+        }
+    }
+}
+
+impl From<Strategy> for String {
+    fn from(strategy: Strategy) -> Self {
+        strategy.as_str().to_string()
+    }
+}
+
+impl JsonSchema for Strategy {
+    fn schema_name() -> String {
+        "Strategy".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            metadata: Some(Box::new(Metadata {
+                description: Some(
+                    "A built-in strategy (native, zig, container, nix) or a plugin strategy's name"
+                        .to_string(),
+                ),
+                examples: ["native", "zig", "container", "nix"]
+                    .into_iter()
+                    .map(Into::into)
+                    .collect(),
+                ..Metadata::default()
+            })),
+            ..SchemaObject::default()
+        }
+        .into()
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Whether a strategy can build the target here: why it can, or what it
+/// is missing
+pub type Availability = std::result::Result<String, String>;
+
+/// What a selection is based on
+#[derive(Debug, Clone)]
+pub struct Facts {
+    /// The target is the host, so there is nothing to cross-compile
+    pub is_host: bool,
+    /// Strategy chosen explicitly, and what chose it (e.g., "--container")
+    pub forced: Option<(Strategy, String)>,
+    /// Strategies ruled out, and what ruled them out (e.g., "--no-zig")
+    pub excluded: Vec<(Strategy, String)>,
+    /// Why Zig is tried first, if it is
+    pub prefer_zig: Option<String>,
+    /// Whether the native toolchain can build the target
+    pub native: Availability,
+    /// Whether Zig can build the target
+    pub zig: Availability,
+    /// Whether a container can build the target
+    pub container: Availability,
+    /// Priority of the project's Nix shell and whether it can build the
+    /// target; `None` without a shell
+    pub nix: Option<(i32, Availability)>,
+    /// Strategies of plugins that apply to the target
+    pub plugins: Vec<PluginFacts>,
+}
+
+/// A plugin strategy that applies to the target
+#[derive(Debug, Clone)]
+pub struct PluginFacts {
+    /// Strategy name
+    pub name: String,
+    /// Place in the chain, from `[strategies.<name>] priority`
+    pub priority: i32,
+    /// Whether the plugin can build the target
+    pub available: Availability,
+}
+
+impl Default for Facts {
+    fn default() -> Self {
+        Self {
+            is_host: false,
+            forced: None,
+            excluded: Vec::new(),
+            prefer_zig: None,
+            native: Ok(String::new()),
+            zig: Err("not installed".to_string()),
+            container: Err("no container runtime".to_string()),
+            nix: None,
+            plugins: Vec::new(),
+        }
+    }
+}
+
+impl Facts {
+    fn availability(&self, strategy: &Strategy) -> Availability {
+        match strategy {
+            Strategy::Native => self.native.clone(),
+            Strategy::Zig => self.zig.clone(),
+            Strategy::Container => self.container.clone(),
+            Strategy::Nix => self.nix.as_ref().map_or_else(
+                || Err("the project has no flake.nix or shell.nix".to_string()),
+                |(_, available)| available.clone(),
+            ),
+            Strategy::Plugin(name) => self
+                .plugins
+                .iter()
+                .find(|plugin| plugin.name == *name)
+                .map_or_else(
+                    || Err("no plugin provides it".to_string()),
+                    |plugin| plugin.available.clone(),
+                ),
+        }
+    }
+
+    fn exclusion(&self, strategy: &Strategy) -> Option<&str> {
+        self.excluded
+            .iter()
+            .find(|(excluded, _)| excluded == strategy)
+            .map(|(_, by)| by.as_str())
+    }
+
+    /// Strategies in the order they are tried: by priority, built-in ones
+    /// first among equals, and Zig ahead of native when it is preferred
+    fn order(&self) -> Vec<Strategy> {
+        let mut order: Vec<(Strategy, i32)> = Strategy::CHAIN
+            .into_iter()
+            .map(|strategy| {
+                let priority = match strategy {
+                    Strategy::Zig if self.prefer_zig.is_some() => Strategy::Native.priority() + 1,
+                    _ => strategy.priority(),
+                };
+                (strategy, priority)
+            })
+            .chain(
+                self.nix
+                    .as_ref()
+                    .map(|(priority, _)| (Strategy::Nix, *priority)),
+            )
+            .chain(
+                self.plugins
+                    .iter()
+                    .map(|plugin| (Strategy::Plugin(plugin.name.clone()), plugin.priority)),
+            )
+            .collect();
+        order.sort_by_key(|(_, priority)| std::cmp::Reverse(*priority));
+        order.into_iter().map(|(strategy, _)| strategy).collect()
+    }
+}
+
+/// One strategy's verdict
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Step {
+    /// Strategy considered
+    pub strategy: Strategy,
+    /// Whether it can build the target
+    pub available: bool,
+    /// Why it can or cannot
+    pub reason: String,
+}
+
+/// The chosen strategy and how it was chosen
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Selection {
+    /// Strategy the build uses
+    pub strategy: Strategy,
+    /// Why it was chosen
+    pub reason: String,
+    /// Verdicts of the chain, in the order they were considered
+    pub steps: Vec<Step>,
+}
+
+/// Pick the strategy for a target from `facts`
+///
+/// When nothing in the chain is available the native toolchain is used
+/// anyway, so the build fails with its hints about what to install.
+#[must_use]
+pub fn select(facts: &Facts) -> Selection {
+    // The host needs no cross-compilation setup unless it is asked for
+    if facts.is_host {
+        let (strategy, reason) = match &facts.forced {
+            Some((strategy, by)) => (strategy.clone(), format!("chosen by {by}")),
+            None => (Strategy::Native, "the target is the host".to_string()),
+        };
+        return Selection {
+            strategy,
+            reason,
+            steps: Vec::new(),
+        };
+    }
+
+    let steps: Vec<Step> = facts
+        .order()
+        .into_iter()
+        .map(|strategy| {
+            let (available, reason) =
+                match (facts.exclusion(&strategy), facts.availability(&strategy)) {
+                    (Some(by), _) => (false, format!("ruled out by {by}")),
+                    (None, Ok(why)) => (true, why),
+                    (None, Err(missing)) => (false, missing),
+                };
+            Step {
+                strategy,
+                available,
+                reason,
+            }
+        })
+        .collect();
+
+    if let Some((strategy, by)) = &facts.forced {
+        return Selection {
+            strategy: strategy.clone(),
+            reason: format!("chosen by {by}"),
+            steps,
+        };
+    }
+
+    let (strategy, reason) = match steps.iter().find(|step| step.available) {
+        Some(step) => {
+            let reason = match (&facts.prefer_zig, &step.strategy) {
+                (Some(why), Strategy::Zig) => format!("{}; {why}", step.reason),
+                _ => step.reason.clone(),
+            };
+            (step.strategy.clone(), reason)
+        }
+        None => (
+            Strategy::Native,
+            "nothing in the chain is available, trying the native toolchain".to_string(),
+        ),
+    };
+    Selection {
+        strategy,
+        reason,
+        steps,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn missing(what: &str) -> Availability {
+        Err(what.to_string())
+    }
+
+    #[test]
+    fn test_chain_order() {
+        let mut facts = Facts {
+            zig: Ok("zig".to_string()),
+            container: Ok("docker".to_string()),
+            ..Facts::default()
+        };
+        assert_eq!(select(&facts).strategy, Strategy::Native);
+
+        facts.native = missing("no linker");
+        let selection = select(&facts);
+        assert_eq!(selection.strategy, Strategy::Zig);
+        assert_eq!(selection.steps.len(), 3);
+        assert!(!selection.steps[0].available);
+
+        facts.excluded.push((Strategy::Zig, "--no-zig".to_string()));
+        let selection = select(&facts);
+        assert_eq!(selection.strategy, Strategy::Container);
+        assert_eq!(selection.steps[1].reason, "ruled out by --no-zig");
+
+        facts.container = missing("no image");
+        let selection = select(&facts);
+        assert_eq!(selection.strategy, Strategy::Native);
+        assert!(selection.reason.contains("nothing in the chain"));
+    }
+
+    #[test]
+    fn test_forced_and_preferred() {
+        let mut facts = Facts {
+            zig: Ok("zig".to_string()),
+            prefer_zig: Some("only Zig links against glibc 2.17".to_string()),
+            ..Facts::default()
+        };
+        let selection = select(&facts);
+        assert_eq!(selection.strategy, Strategy::Zig);
+        assert!(selection
+            .reason
+            .ends_with("only Zig links against glibc 2.17"));
+
+        facts.forced = Some((Strategy::Container, "--container".to_string()));
+        let selection = select(&facts);
+        assert_eq!(selection.strategy, Strategy::Container);
+        assert_eq!(selection.reason, "chosen by --container");
+
+        facts.is_host = true;
+        assert_eq!(select(&facts).strategy, Strategy::Container);
+        facts.forced = None;
+        assert_eq!(select(&facts).strategy, Strategy::Native);
+    }
+
+    #[test]
+    fn test_plugin_priority() {
+        let mut facts = Facts {
+            native: missing("no linker"),
+            plugins: vec![PluginFacts {
+                name: "remote-builder".to_string(),
+                priority: 0,
+                available: Ok("build host is reachable".to_string()),
+            }],
+            ..Facts::default()
+        };
+        // Without a priority, plugin strategies come last
+        let selection = select(&facts);
+        assert_eq!(
+            selection.strategy,
+            Strategy::Plugin("remote-builder".to_string())
+        );
+        assert_eq!(selection.steps.len(), 4);
+
+        facts.native = Ok("linker is installed".to_string());
+        assert_eq!(select(&facts).strategy, Strategy::Native);
+
+        facts.plugins[0].priority = 100;
+        let selection = select(&facts);
+        assert_eq!(selection.steps[0].strategy.as_str(), "remote-builder");
+        assert_eq!(selection.reason, "build host is reachable");
+
+        facts.forced = Some((
+            Strategy::Plugin("nix-shell".to_string()),
+            "config".to_string(),
+        ));
+        let selection = select(&facts);
+        assert_eq!(selection.strategy.as_str(), "nix-shell");
+    }
+
+    #[test]
+    fn test_nix_goes_first() {
+        let mut facts = Facts::default();
+        assert_eq!(select(&facts).steps.len(), 3);
+
+        facts.nix = Some((
+            Strategy::Nix.priority(),
+            Ok("flake.nix provides the toolchain".to_string()),
+        ));
+        let selection = select(&facts);
+        assert_eq!(selection.strategy, Strategy::Nix);
+        assert_eq!(selection.steps.len(), 4);
+
+        facts.nix = Some((0, Ok("flake.nix provides the toolchain".to_string())));
+        assert_eq!(select(&facts).strategy, Strategy::Native);
+    }
+
+    #[test]
+    fn test_strategy_names() {
+        for strategy in Strategy::CHAIN {
+            let json = serde_json::to_string(&strategy).unwrap();
+            assert_eq!(json, format!("\"{strategy}\""));
+            assert!(strategy.is_builtin());
+        }
+        let strategy: Strategy = serde_json::from_str("\"nix-shell\"").unwrap();
+        assert_eq!(strategy, Strategy::Plugin("nix-shell".to_string()));
+    }
+}
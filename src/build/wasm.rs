@@ -0,0 +1,262 @@
+//! WebAssembly builds for the web and for WASI
+//!
+//! With `[artifacts.wasm] dir = "pkg"`, each build of a `wasm32-*` target
+//! runs its binaries and cdylibs through a post-build pipeline that leaves
+//! them in `pkg/`, ready to serve or publish:
+//!
+//! - for `wasm32-unknown-unknown`, wasm-bindgen generates the JavaScript
+//!   bindings (`<name>.js`, `<name>_bg.wasm` and TypeScript declarations)
+//!   for the configured `bindgen_target`
+//! - WASI modules, and everything with `bindgen = false`, are copied as-is
+//! - release builds are then optimized in place with wasm-opt
+//!
+//! ```toml
+//! [artifacts.wasm]
+//! dir = "pkg"
+//! bindgen_target = "bundler"
+//! opt = ["-Oz"]
+//! ```
+//!
+//! WASI test binaries run under wasmtime or wasmer, whichever is installed.
+
+use crate::config::WasmExportConfig;
+use crate::error::{Error, Result};
+use crate::target::Target;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::artifacts::{ArtifactKind, Product};
+
+/// WASI runtimes able to run test binaries, in order of preference, with
+/// the runner cargo is given for them
+const WASI_RUNTIMES: &[(&str, &str)] = &[
+    ("wasmtime", "wasmtime run --dir=."),
+    ("wasmer", "wasmer run --dir=."),
+];
+
+/// Whether `target` is WebAssembly
+#[must_use]
+pub fn is_wasm(target: &Target) -> bool {
+    target.arch.starts_with("wasm")
+}
+
+/// Whether `target` is WebAssembly with WASI, e.g. `wasm32-wasip1`
+#[must_use]
+pub fn is_wasi(target: &Target) -> bool {
+    is_wasm(target) && target.os.starts_with("wasi")
+}
+
+/// Runner for WASI test binaries: the first of wasmtime and wasmer that is
+/// installed
+#[must_use]
+pub fn wasi_runner() -> Option<&'static str> {
+    WASI_RUNTIMES
+        .iter()
+        .find(|(program, _)| which::which(program).is_ok())
+        .map(|(_, runner)| *runner)
+}
+
+/// Error for WASI tests with no runtime to run them
+#[must_use]
+pub fn missing_runtime(target: &Target) -> Error {
+    Error::Toolchain(format!(
+        "Running tests for {} needs a WASI runtime: install wasmtime (https://wasmtime.dev) or wasmer (https://wasmer.io)",
+        target.triple
+    ))
+}
+
+/// A warning when the installed wasm-bindgen (`wasm-bindgen --version`
+/// output) is not the version of the `wasm-bindgen` crate in Cargo.lock;
+/// the two must match exactly
+#[must_use]
+pub fn bindgen_mismatch(installed: &str, locked: &str) -> Option<String> {
+    let version = installed.split_whitespace().nth(1)?;
+    (version != locked).then(|| {
+        format!(
+            "wasm-bindgen {version} is installed but the project uses wasm-bindgen {locked} \
+             (cargo install wasm-bindgen-cli --version {locked})"
+        )
+    })
+}
+
+/// Output directory of the WebAssembly pipeline
+pub struct WasmExport<'a> {
+    dir: PathBuf,
+    config: &'a WasmExportConfig,
+}
+
+impl<'a> WasmExport<'a> {
+    /// Export configured by `config`, `None` if it sets no directory
+    #[must_use]
+    pub fn new(config: &'a WasmExportConfig) -> Option<Self> {
+        let dir = PathBuf::from(config.dir.as_ref()?);
+        Some(Self { dir, config })
+    }
+
+    /// Output directory
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Whether `target`'s modules go through wasm-bindgen
+    #[must_use]
+    pub fn uses_bindgen(&self, target: &Target) -> bool {
+        self.config.bindgen && is_wasm(target) && !is_wasi(target)
+    }
+
+    /// Whether wasm-opt runs for a build in `profile`
+    #[must_use]
+    pub fn optimizes(&self, profile: &str) -> bool {
+        profile == "release" && !self.config.opt.is_empty()
+    }
+
+    /// Run the binaries and cdylibs built in `target_dir/<triple>/<profile>/`
+    /// through wasm-bindgen or copy them, then through wasm-opt
+    ///
+    /// Returns the `.wasm` files written; products that were not built are
+    /// skipped, and targets other than WebAssembly export nothing.
+    pub fn export(
+        &self,
+        target: &Target,
+        profile: &str,
+        target_dir: &Path,
+        products: &[Product],
+    ) -> Result<Vec<PathBuf>> {
+        if !is_wasm(target) {
+            return Ok(Vec::new());
+        }
+        let build_dir = target_dir.join(&target.triple).join(profile);
+
+        let mut exported = Vec::new();
+        for product in products {
+            if !matches!(product.kind, ArtifactKind::Bin | ArtifactKind::Cdylib) {
+                continue;
+            }
+            let source = build_dir.join(product.file_name(target));
+            if !source.is_file() {
+                continue;
+            }
+            fs::create_dir_all(&self.dir)?;
+            let module = if self.uses_bindgen(target) {
+                run(&mut self.bindgen_command(&source))?;
+                self.dir.join(bindgen_module(&source))
+            } else {
+                let dest = self.dir.join(source.file_name().unwrap_or_default());
+                fs::copy(&source, &dest)?;
+                dest
+            };
+            if self.optimizes(profile) {
+                self.optimize(&module)?;
+            }
+            exported.push(module);
+        }
+        Ok(exported)
+    }
+
+    /// wasm-bindgen generating the bindings for `module` in the output
+    /// directory
+    fn bindgen_command(&self, module: &Path) -> Command {
+        let mut cmd = Command::new("wasm-bindgen");
+        cmd.arg("--target")
+            .arg(&self.config.bindgen_target)
+            .arg("--out-dir")
+            .arg(&self.dir)
+            .args(&self.config.bindgen_args)
+            .arg(module);
+        cmd
+    }
+
+    /// Optimize `module` in place with wasm-opt
+    fn optimize(&self, module: &Path) -> Result<()> {
+        let optimized = module.with_extension("wasm.opt");
+        run(Command::new("wasm-opt")
+            .args(&self.config.opt)
+            .arg(module)
+            .arg("-o")
+            .arg(&optimized))?;
+        fs::rename(&optimized, module)?;
+        Ok(())
+    }
+}
+
+/// File name of the module wasm-bindgen writes for `module`
+fn bindgen_module(module: &Path) -> String {
+    let stem = module
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    format!("{stem}_bg.wasm")
+}
+
+/// Run a pipeline tool, failing with its error output
+fn run(cmd: &mut Command) -> Result<()> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let output = cmd.output().map_err(|e| {
+        Error::Toolchain(format!(
+            "Failed to run {program}: {e} ({})",
+            match program.as_str() {
+                "wasm-bindgen" => "cargo install wasm-bindgen-cli",
+                _ => "install binaryen",
+            }
+        ))
+    })?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(Error::Build(format!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(triple: &str) -> Target {
+        Target::from_triple(triple).unwrap()
+    }
+
+    #[test]
+    fn test_wasm_targets() {
+        assert!(is_wasm(&target("wasm32-unknown-unknown")));
+        assert!(!is_wasi(&target("wasm32-unknown-unknown")));
+        assert!(is_wasi(&target("wasm32-wasip1")));
+        assert!(is_wasi(&target("wasm32-wasip2")));
+        assert!(!is_wasm(&target("x86_64-unknown-linux-gnu")));
+    }
+
+    #[test]
+    fn test_bindgen_mismatch() {
+        assert_eq!(bindgen_mismatch("wasm-bindgen 0.2.92\n", "0.2.92"), None);
+        assert!(bindgen_mismatch("wasm-bindgen 0.2.90", "0.2.92")
+            .unwrap()
+            .contains("--version 0.2.92"));
+    }
+
+    #[test]
+    fn test_export_plan() {
+        let config = WasmExportConfig {
+            dir: Some("pkg".to_string()),
+            ..WasmExportConfig::default()
+        };
+        let export = WasmExport::new(&config).unwrap();
+        assert!(export.uses_bindgen(&target("wasm32-unknown-unknown")));
+        assert!(!export.uses_bindgen(&target("wasm32-wasip1")));
+        assert!(export.optimizes("release"));
+        assert!(!export.optimizes("debug"));
+
+        let cmd = export.bindgen_command(Path::new("target/app.wasm"));
+        let args: Vec<_> = cmd.get_args().collect();
+        assert_eq!(
+            args,
+            ["--target", "web", "--out-dir", "pkg", "target/app.wasm"]
+        );
+        assert_eq!(bindgen_module(Path::new("target/app.wasm")), "app_bg.wasm");
+
+        assert!(WasmExport::new(&WasmExportConfig::default()).is_none());
+    }
+}
@@ -0,0 +1,525 @@
+//! Xcode xcframeworks of static libraries
+//!
+//! With `[artifacts.apple] dir = "apple"`, each build of an iOS, iOS
+//! simulator, Mac Catalyst or macOS target copies the workspace's static
+//! libraries into `apple/slices/<triple>/`. Targets built separately add up,
+//! and after every build xcargo rewrites `<Name>.xcframework` for each
+//! library from the slices there, ready to drag into an Xcode project:
+//!
+//! - one library per platform: `ios-arm64`, `ios-arm64_x86_64-simulator`,
+//!   `ios-arm64_x86_64-maccatalyst` and `macos-arm64_x86_64`, with the
+//!   architectures of a platform combined into a universal archive
+//! - `Headers/<Name>/`, the C headers from `headers` (e.g., cbindgen's
+//!   output) and a `module.modulemap`, so Swift can `import <Name>`
+//! - the `Info.plist` Xcode reads the libraries from
+//!
+//! ```toml
+//! [artifacts.apple]
+//! dir = "apple"
+//! headers = "include"
+//! ```
+
+use crate::config::AppleExportConfig;
+use crate::error::{Error, Result};
+use crate::target::Target;
+use crate::toolchain::apple;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::artifacts::{ArtifactKind, Product};
+
+/// Directory the static libraries are kept in, by target, inside the
+/// export directory
+pub const SLICES_DIR: &str = "slices";
+
+/// Alignment of the archives in a universal file, as a power of two
+const FAT_ALIGN: u32 = 14;
+
+/// Where a target's libraries go in an xcframework
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Slice {
+    /// `SupportedPlatform`: ios or macos
+    pub platform: &'static str,
+    /// `SupportedPlatformVariant`: simulator or maccatalyst
+    pub variant: Option<&'static str>,
+    /// Architecture, as Apple names it
+    pub arch: &'static str,
+}
+
+impl Slice {
+    /// Slice of `target`, `None` for targets other than iOS and macOS
+    #[must_use]
+    pub fn for_target(target: &Target) -> Option<Self> {
+        let arch = match target.arch.as_str() {
+            "aarch64" => "arm64",
+            "x86_64" => "x86_64",
+            _ => return None,
+        };
+        let (platform, variant) = match target.os.as_str() {
+            "macos" => ("macos", None),
+            "ios" if apple::is_mac_catalyst(target) => ("ios", Some("maccatalyst")),
+            "ios" if apple::is_simulator(target) => ("ios", Some("simulator")),
+            "ios" => ("ios", None),
+            _ => return None,
+        };
+        Some(Self {
+            platform,
+            variant,
+            arch,
+        })
+    }
+
+    /// Mach-O CPU type and subtype of the architecture
+    fn cpu(&self) -> (u32, u32) {
+        match self.arch {
+            "arm64" => (0x0100_000c, 0),
+            _ => (0x0100_0007, 3),
+        }
+    }
+}
+
+/// Platform and variant the slices of one xcframework library share
+type Platform = (&'static str, Option<&'static str>);
+
+/// Export directory for Xcode consumers
+pub struct AppleExport<'a> {
+    dir: PathBuf,
+    config: &'a AppleExportConfig,
+}
+
+impl<'a> AppleExport<'a> {
+    /// Export configured by `config`, `None` if it sets no directory
+    #[must_use]
+    pub fn new(config: &'a AppleExportConfig) -> Option<Self> {
+        let dir = PathBuf::from(config.dir.as_ref()?);
+        Some(Self { dir, config })
+    }
+
+    /// Export directory
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Copy the static libraries built in `target_dir/<triple>/<profile>/`
+    /// into `slices/<triple>/` and rewrite the xcframeworks
+    ///
+    /// Returns the xcframeworks written; products that were not built are
+    /// skipped, and targets other than Apple's export nothing.
+    pub fn export(
+        &self,
+        target: &Target,
+        profile: &str,
+        target_dir: &Path,
+        products: &[Product],
+    ) -> Result<Vec<PathBuf>> {
+        if Slice::for_target(target).is_none() {
+            return Ok(Vec::new());
+        }
+        let build_dir = target_dir.join(&target.triple).join(profile);
+        let slice_dir = self.dir.join(SLICES_DIR).join(&target.triple);
+
+        let mut copied = false;
+        for product in products {
+            if product.kind != ArtifactKind::Staticlib {
+                continue;
+            }
+            let file = product.file_name(target);
+            let source = build_dir.join(&file);
+            if !source.is_file() {
+                continue;
+            }
+            fs::create_dir_all(&slice_dir)?;
+            fs::copy(&source, slice_dir.join(&file))?;
+            copied = true;
+        }
+
+        if copied {
+            self.write_frameworks()
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    /// Libraries in `slices/`, with the targets each was built for
+    pub fn contents(&self) -> Result<BTreeMap<String, Vec<String>>> {
+        let mut contents: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let Ok(targets) = fs::read_dir(self.dir.join(SLICES_DIR)) else {
+            return Ok(contents);
+        };
+        for entry in targets.flatten() {
+            let triple = entry.file_name().to_string_lossy().into_owned();
+            for library in fs::read_dir(entry.path())?.flatten() {
+                let name = library.file_name().to_string_lossy().into_owned();
+                let archive = Path::new(&name).extension().is_some_and(|ext| ext == "a");
+                if name.starts_with("lib") && archive {
+                    contents.entry(name).or_default().push(triple.clone());
+                }
+            }
+        }
+        for triples in contents.values_mut() {
+            triples.sort();
+        }
+        Ok(contents)
+    }
+
+    /// Rewrite an xcframework per library from what is in `slices/`
+    fn write_frameworks(&self) -> Result<Vec<PathBuf>> {
+        let contents = self.contents()?;
+        if self.config.name.is_some() && contents.len() > 1 {
+            return Err(Error::Config(format!(
+                "artifacts.apple.name can only name one library, but {} were built ({})",
+                contents.len(),
+                contents.keys().cloned().collect::<Vec<_>>().join(", ")
+            )));
+        }
+
+        let mut written = Vec::new();
+        for (library, triples) in &contents {
+            let name = self
+                .config
+                .name
+                .clone()
+                .unwrap_or_else(|| module_name(library));
+            written.push(self.write_framework(&name, library, triples)?);
+        }
+        Ok(written)
+    }
+
+    /// Write `<name>.xcframework` from `library` as built for `triples`
+    fn write_framework(&self, name: &str, library: &str, triples: &[String]) -> Result<PathBuf> {
+        let mut groups: BTreeMap<Platform, Vec<(Slice, PathBuf)>> = BTreeMap::new();
+        for triple in triples {
+            let Some(slice) = Slice::for_target(&Target::from_triple(triple)?) else {
+                continue;
+            };
+            let path = self.dir.join(SLICES_DIR).join(triple).join(library);
+            groups
+                .entry((slice.platform, slice.variant))
+                .or_default()
+                .push((slice, path));
+        }
+
+        let path = self.dir.join(format!("{name}.xcframework"));
+        let partial = self.dir.join(format!("{name}.xcframework.partial"));
+        if partial.exists() {
+            fs::remove_dir_all(&partial)?;
+        }
+
+        let mut libraries = Vec::new();
+        for ((platform, variant), mut slices) in groups {
+            slices.sort();
+            let archs: Vec<&str> = slices.iter().map(|(slice, _)| slice.arch).collect();
+            let identifier = match variant {
+                Some(variant) => format!("{platform}-{}-{variant}", archs.join("_")),
+                None => format!("{platform}-{}", archs.join("_")),
+            };
+            let dir = partial.join(&identifier);
+            fs::create_dir_all(&dir)?;
+
+            if let [(_, single)] = slices.as_slice() {
+                fs::copy(single, dir.join(library))?;
+            } else {
+                let archives = slices
+                    .iter()
+                    .map(|(slice, path)| Ok((slice.cpu(), fs::read(path)?)))
+                    .collect::<Result<Vec<_>>>()?;
+                fs::write(dir.join(library), universal(&archives)?)?;
+            }
+            self.write_headers(&dir.join("Headers").join(name), name)?;
+
+            libraries.push(PlistLibrary {
+                identifier,
+                library: library.to_string(),
+                archs: archs.iter().map(ToString::to_string).collect(),
+                platform,
+                variant,
+            });
+        }
+
+        fs::write(partial.join("Info.plist"), info_plist(&libraries))?;
+        if path.exists() {
+            fs::remove_dir_all(&path)?;
+        }
+        fs::rename(&partial, &path)?;
+        Ok(path)
+    }
+
+    /// Copy the configured headers into `dir` next to a module map
+    fn write_headers(&self, dir: &Path, name: &str) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        let mut headers = Vec::new();
+        if let Some(source) = &self.config.headers {
+            let entries = fs::read_dir(source).map_err(|e| {
+                Error::Config(format!("Cannot read artifacts.apple.headers {source}: {e}"))
+            })?;
+            for entry in entries.flatten() {
+                let file = entry.file_name().to_string_lossy().into_owned();
+                if Path::new(&file).extension().is_some_and(|ext| ext == "h") {
+                    fs::copy(entry.path(), dir.join(&file))?;
+                    headers.push(file);
+                }
+            }
+        }
+        headers.sort();
+        fs::write(dir.join("module.modulemap"), module_map(name, &headers))?;
+        Ok(())
+    }
+}
+
+/// A library entry of the xcframework's `Info.plist`
+struct PlistLibrary {
+    identifier: String,
+    library: String,
+    archs: Vec<String>,
+    platform: &'static str,
+    variant: Option<&'static str>,
+}
+
+/// Module name for a library file: `libcore_ffi.a` is `CoreFfi`
+#[must_use]
+pub fn module_name(library: &str) -> String {
+    library
+        .trim_start_matches("lib")
+        .trim_end_matches(".a")
+        .split(['_', '-'])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first.to_uppercase().chain(chars).collect()
+            })
+        })
+        .collect()
+}
+
+/// `module.modulemap` for module `name` with `headers`
+fn module_map(name: &str, headers: &[String]) -> String {
+    let mut map = format!("module {name} {{\n");
+    for header in headers {
+        let _ = writeln!(map, "    header \"{header}\"");
+    }
+    map.push_str("    export *\n}\n");
+    map
+}
+
+/// The xcframework's `Info.plist`
+fn info_plist(libraries: &[PlistLibrary]) -> String {
+    let mut plist = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n\
+         <dict>\n\
+         \t<key>AvailableLibraries</key>\n\
+         \t<array>\n",
+    );
+    for library in libraries {
+        let _ = write!(
+            plist,
+            "\t\t<dict>\n\
+             \t\t\t<key>HeadersPath</key>\n\
+             \t\t\t<string>Headers</string>\n\
+             \t\t\t<key>LibraryIdentifier</key>\n\
+             \t\t\t<string>{}</string>\n\
+             \t\t\t<key>LibraryPath</key>\n\
+             \t\t\t<string>{}</string>\n\
+             \t\t\t<key>SupportedArchitectures</key>\n\
+             \t\t\t<array>\n",
+            library.identifier, library.library
+        );
+        for arch in &library.archs {
+            let _ = writeln!(plist, "\t\t\t\t<string>{arch}</string>");
+        }
+        let _ = write!(
+            plist,
+            "\t\t\t</array>\n\
+             \t\t\t<key>SupportedPlatform</key>\n\
+             \t\t\t<string>{}</string>\n",
+            library.platform
+        );
+        if let Some(variant) = library.variant {
+            let _ = write!(
+                plist,
+                "\t\t\t<key>SupportedPlatformVariant</key>\n\
+                 \t\t\t<string>{variant}</string>\n"
+            );
+        }
+        plist.push_str("\t\t</dict>\n");
+    }
+    plist.push_str(
+        "\t</array>\n\
+         \t<key>CFBundlePackageType</key>\n\
+         \t<string>XFWK</string>\n\
+         \t<key>XCFrameworkFormatVersion</key>\n\
+         \t<string>1.0</string>\n\
+         </dict>\n\
+         </plist>\n",
+    );
+    plist
+}
+
+/// A universal (fat) file holding `archives`, each with its Mach-O CPU
+/// type and subtype, as `lipo -create` writes it
+fn universal(archives: &[((u32, u32), Vec<u8>)]) -> Result<Vec<u8>> {
+    let too_large = || Error::Build("Static library too large for a universal archive".to_string());
+    let align = 1usize << FAT_ALIGN;
+    let count = u32::try_from(archives.len()).map_err(|_| too_large())?;
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&0xcafe_babe_u32.to_be_bytes());
+    header.extend_from_slice(&count.to_be_bytes());
+
+    let aligned = |offset: usize| (offset + align - 1) / align * align;
+    let mut offset = aligned(8 + 20 * archives.len());
+    let mut placed = Vec::new();
+    for ((cpu, subtype), data) in archives {
+        for field in [
+            *cpu,
+            *subtype,
+            u32::try_from(offset).map_err(|_| too_large())?,
+            u32::try_from(data.len()).map_err(|_| too_large())?,
+            FAT_ALIGN,
+        ] {
+            header.extend_from_slice(&field.to_be_bytes());
+        }
+        placed.push((offset, data));
+        offset = aligned(offset + data.len());
+    }
+
+    let mut file = header;
+    for (offset, data) in placed {
+        file.resize(offset, 0);
+        file.extend_from_slice(data);
+    }
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn staticlib(name: &str) -> Product {
+        Product {
+            package: name.to_string(),
+            name: name.to_string(),
+            kind: ArtifactKind::Staticlib,
+        }
+    }
+
+    fn build(target_dir: &Path, triple: &str, file: &str) {
+        let dir = target_dir.join(triple).join("release");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(file), triple).unwrap();
+    }
+
+    #[test]
+    fn test_slice() {
+        let slice = |triple: &str| Slice::for_target(&Target::from_triple(triple).unwrap());
+        let ios = slice("aarch64-apple-ios").unwrap();
+        assert_eq!(
+            (ios.platform, ios.variant, ios.arch),
+            ("ios", None, "arm64")
+        );
+        assert_eq!(
+            slice("aarch64-apple-ios-sim").unwrap().variant,
+            Some("simulator")
+        );
+        assert_eq!(
+            slice("x86_64-apple-ios").unwrap().variant,
+            Some("simulator")
+        );
+        assert_eq!(
+            slice("aarch64-apple-ios-macabi").unwrap().variant,
+            Some("maccatalyst")
+        );
+        assert_eq!(slice("x86_64-apple-darwin").unwrap().platform, "macos");
+        assert_eq!(slice("aarch64-linux-android"), None);
+    }
+
+    #[test]
+    fn test_module_name() {
+        assert_eq!(module_name("libcore_ffi.a"), "CoreFfi");
+        assert_eq!(module_name("libapp.a"), "App");
+    }
+
+    #[test]
+    fn test_universal() {
+        let file = universal(&[
+            ((0x0100_000c, 0), vec![1; 10]),
+            ((0x0100_0007, 3), vec![2; 5]),
+        ])
+        .unwrap();
+        let word = |at: usize| u32::from_be_bytes(file[at..at + 4].try_into().unwrap());
+        assert_eq!(word(0), 0xcafe_babe);
+        assert_eq!(word(4), 2);
+        assert_eq!(word(8), 0x0100_000c);
+        assert_eq!(word(16), 16384);
+        assert_eq!(word(20), 10);
+        assert_eq!(word(28), 0x0100_0007);
+        assert_eq!(word(36), 32768);
+        assert_eq!(file[16384..16394], [1; 10]);
+        assert_eq!(file[32768..], [2; 5]);
+    }
+
+    #[test]
+    fn test_export_adds_up_across_targets() {
+        let dir = TempDir::new().unwrap();
+        let target_dir = dir.path().join("target");
+        let include = dir.path().join("include");
+        fs::create_dir(&include).unwrap();
+        fs::write(include.join("core_ffi.h"), "int add(int, int);\n").unwrap();
+        let triples = [
+            "aarch64-apple-ios",
+            "aarch64-apple-ios-sim",
+            "x86_64-apple-ios",
+        ];
+        for triple in triples {
+            build(&target_dir, triple, "libcore_ffi.a");
+        }
+        let config = AppleExportConfig {
+            dir: Some(dir.path().join("apple").display().to_string()),
+            headers: Some(include.display().to_string()),
+            ..Default::default()
+        };
+        let export = AppleExport::new(&config).unwrap();
+        let products = [staticlib("core-ffi"), staticlib("missing")];
+
+        for triple in triples {
+            let target = Target::from_triple(triple).unwrap();
+            let written = export
+                .export(&target, "release", &target_dir, &products)
+                .unwrap();
+            assert_eq!(written, [export.dir().join("CoreFfi.xcframework")]);
+        }
+        let linux = Target::from_triple("x86_64-unknown-linux-gnu").unwrap();
+        assert!(export
+            .export(&linux, "release", &target_dir, &products)
+            .unwrap()
+            .is_empty());
+
+        let framework = export.dir().join("CoreFfi.xcframework");
+        assert_eq!(
+            fs::read_to_string(framework.join("ios-arm64/libcore_ffi.a")).unwrap(),
+            "aarch64-apple-ios"
+        );
+        let simulator =
+            fs::read(framework.join("ios-arm64_x86_64-simulator/libcore_ffi.a")).unwrap();
+        assert_eq!(simulator[..4], [0xca, 0xfe, 0xba, 0xbe]);
+        let headers = framework.join("ios-arm64/Headers/CoreFfi");
+        assert!(headers.join("core_ffi.h").is_file());
+        assert_eq!(
+            fs::read_to_string(headers.join("module.modulemap")).unwrap(),
+            "module CoreFfi {\n    header \"core_ffi.h\"\n    export *\n}\n"
+        );
+
+        let plist = fs::read_to_string(framework.join("Info.plist")).unwrap();
+        assert!(plist.contains("<string>ios-arm64_x86_64-simulator</string>"));
+        assert!(
+            plist.contains("<key>SupportedPlatformVariant</key>\n\t\t\t<string>simulator</string>")
+        );
+        assert!(plist.contains("<string>XFWK</string>"));
+    }
+}
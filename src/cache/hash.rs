@@ -34,7 +34,7 @@ pub fn hash_file(path: &Path) -> Option<u64> {
 
 /// Hash a string to u64
 #[must_use]
-fn hash_str(s: &str) -> u64 {
+pub(crate) fn hash_str(s: &str) -> u64 {
     // Simple DJB2 hash algorithm
     let mut hash: u64 = 5381;
     for byte in s.bytes() {
@@ -45,7 +45,7 @@ fn hash_str(s: &str) -> u64 {
 
 /// Combine multiple u64 values into a single hash
 #[must_use]
-fn hash_combine(values: &[u64]) -> u64 {
+pub(crate) fn hash_combine(values: &[u64]) -> u64 {
     let mut hash: u64 = 0;
     for &value in values {
         hash ^= value.wrapping_add(0x9e3779b9)
@@ -68,6 +68,53 @@ pub fn hash_files(paths: &[&Path]) -> Option<u64> {
     Some(hash_combine(&hashes))
 }
 
+/// Files of a package that cargo builds from, next to its `Cargo.toml`
+const PACKAGE_FILES: &[&str] = &["Cargo.toml", "Cargo.lock", "build.rs"];
+
+/// Directories of a package that cargo builds from
+const PACKAGE_DIRS: &[&str] = &["src", "benches", "examples", "tests"];
+
+/// Hash the sources of every package under `root`
+///
+/// Only what cargo builds from counts: each package's manifest, lock file,
+/// build script and `src`, `benches`, `examples` and `tests` directories.
+/// Build output, artifact directories and reports elsewhere in the project,
+/// and hidden files and directories (`.git`, `.xcargo`, ...) do not change
+/// the hash.
+#[must_use]
+pub fn hash_tree(root: &Path) -> Option<u64> {
+    let mut files = Vec::new();
+    // Directories to look at, and whether all of their files are sources
+    let mut dirs = vec![(root.to_path_buf(), false)];
+    while let Some((dir, sources)) = dirs.pop() {
+        let package = !sources && dir.join("Cargo.toml").is_file();
+        for entry in fs::read_dir(&dir).ok()?.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') || (dir == root && name == "target") {
+                continue;
+            }
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() => {
+                    let sources = sources || (package && PACKAGE_DIRS.contains(&name.as_ref()));
+                    dirs.push((entry.path(), sources));
+                }
+                Ok(kind)
+                    if kind.is_file()
+                        && (sources || (package && PACKAGE_FILES.contains(&name.as_ref()))) =>
+                {
+                    files.push(entry.path());
+                }
+                _ => {}
+            }
+        }
+    }
+    files.sort();
+
+    let hashes: Vec<u64> = files.iter().filter_map(|path| hash_file(path)).collect();
+    Some(hash_combine(&hashes))
+}
+
 /// Check if a file has changed by comparing hashes
 pub fn has_file_changed(path: &Path, previous_hash: u64) -> Result<bool> {
     let current_hash = hash_file(path)
@@ -156,6 +203,53 @@ mod tests {
         assert_eq!(hash, hash2);
     }
 
+    #[test]
+    fn test_hash_tree() {
+        let temp = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("src")).unwrap();
+        fs::write(temp.path().join("Cargo.toml"), "[package]\n").unwrap();
+        fs::write(temp.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        let hash = hash_tree(temp.path()).unwrap();
+
+        // Build output and hidden directories do not count
+        fs::create_dir_all(temp.path().join("target/debug")).unwrap();
+        fs::write(temp.path().join("target/debug/app"), "binary").unwrap();
+        fs::create_dir_all(temp.path().join(".git")).unwrap();
+        fs::write(temp.path().join(".git/HEAD"), "ref").unwrap();
+        assert_eq!(hash_tree(temp.path()), Some(hash));
+
+        fs::write(temp.path().join("src/lib.rs"), "pub fn f() {}\n").unwrap();
+        assert_ne!(hash_tree(temp.path()), Some(hash));
+    }
+
+    #[test]
+    fn test_hash_tree_skips_output_in_the_project() {
+        let temp = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("src")).unwrap();
+        fs::write(temp.path().join("Cargo.toml"), "[workspace]\n").unwrap();
+        fs::write(temp.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::create_dir_all(temp.path().join("crates/core/src")).unwrap();
+        fs::write(temp.path().join("crates/core/Cargo.toml"), "[package]\n").unwrap();
+        let hash = hash_tree(temp.path()).unwrap();
+
+        // An [artifacts] dir, --out-dir and reports written into the project
+        fs::create_dir_all(temp.path().join("dist/x86_64-unknown-linux-gnu/debug")).unwrap();
+        fs::write(
+            temp.path().join("dist/x86_64-unknown-linux-gnu/debug/app"),
+            "binary",
+        )
+        .unwrap();
+        fs::write(temp.path().join("dist/manifest.json"), "{}").unwrap();
+        fs::create_dir_all(temp.path().join("coverage")).unwrap();
+        fs::write(temp.path().join("coverage/index.html"), "<html>").unwrap();
+        fs::write(temp.path().join("timings.html"), "<html>").unwrap();
+        assert_eq!(hash_tree(temp.path()), Some(hash));
+
+        // Workspace members' sources count
+        fs::write(temp.path().join("crates/core/src/lib.rs"), "pub fn f() {}\n").unwrap();
+        assert_ne!(hash_tree(temp.path()), Some(hash));
+    }
+
     #[test]
     fn test_has_file_changed() {
         let mut temp_file = NamedTempFile::new().unwrap();
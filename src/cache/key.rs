@@ -0,0 +1,303 @@
+//! Cache keys covering everything that affects build output
+
+use super::hash::{hash_combine, hash_str};
+use crate::build::{BuildOptions, TargetSelection};
+#[cfg(feature = "zig")]
+use crate::toolchain::zig::ZigToolchain;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+/// Installed Zig version, if any
+#[cfg(feature = "zig")]
+fn zig_version() -> Option<String> {
+    ZigToolchain::detect()
+        .ok()
+        .flatten()
+        .map(|zig| zig.version().to_string())
+}
+
+#[cfg(not(feature = "zig"))]
+fn zig_version() -> Option<String> {
+    None
+}
+
+/// Inputs besides source files that decide whether a cached build is valid
+///
+/// Switching toolchain, profile or features must not reuse a build made
+/// with different settings, so all of them are part of the key.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheKey {
+    /// Target triple
+    pub target: String,
+    /// Toolchain the build uses (e.g., "stable", "nightly"), as asked for
+    /// or as rustup picks it for the project
+    #[serde(default)]
+    pub toolchain: String,
+    /// Output of `rustc --version` for the toolchain
+    #[serde(default)]
+    pub rustc_version: Option<String>,
+    /// Build profile ("debug" or "release")
+    #[serde(default)]
+    pub profile: String,
+    /// Feature flags passed to cargo, normalized
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// Zig version when Zig may be used for the build
+    #[serde(default)]
+    pub zig_version: Option<String>,
+    /// Selected binaries, examples and library (e.g., "bin:app"), sorted;
+    /// empty for cargo's default targets
+    #[serde(default)]
+    pub selection: Vec<String>,
+    /// Rustflags rustc gets, from wherever cargo or xcargo takes them, in
+    /// order
+    #[serde(default)]
+    pub rustflags: Vec<String>,
+    /// Arguments passed to cargo after the operation and target, in order
+    #[serde(default)]
+    pub cargo_args: Vec<String>,
+}
+
+impl CacheKey {
+    /// Key for a target with default settings (stable, debug, no features)
+    pub fn new(target: impl Into<String>) -> Self {
+        Self {
+            target: target.into(),
+            toolchain: "stable".to_string(),
+            profile: "debug".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Set the toolchain channel
+    #[must_use]
+    pub fn with_toolchain(mut self, toolchain: impl Into<String>) -> Self {
+        self.toolchain = toolchain.into();
+        self
+    }
+
+    /// Set the rustc version string
+    #[must_use]
+    pub fn with_rustc_version(mut self, version: Option<String>) -> Self {
+        self.rustc_version = version;
+        self
+    }
+
+    /// Set the profile from a release flag
+    #[must_use]
+    pub fn with_release(mut self, release: bool) -> Self {
+        self.profile = if release { "release" } else { "debug" }.to_string();
+        self
+    }
+
+    /// Set the feature flags; order and duplicates do not matter
+    #[must_use]
+    pub fn with_features(mut self, mut features: Vec<String>) -> Self {
+        features.sort();
+        features.dedup();
+        self.features = features;
+        self
+    }
+
+    /// Set the Zig version
+    #[must_use]
+    pub fn with_zig_version(mut self, version: Option<String>) -> Self {
+        self.zig_version = version;
+        self
+    }
+
+    /// Set the selected cargo targets; order and duplicates do not matter
+    #[must_use]
+    pub fn with_selection(mut self, selection: &TargetSelection) -> Self {
+        let bins = selection.bins.iter().map(|bin| format!("bin:{bin}"));
+        let examples = selection.examples.iter().map(|e| format!("example:{e}"));
+        let kinds = selection.kinds.iter().map(|kind| format!("kind:{kind}"));
+        let mut names: Vec<String> = bins.chain(examples).chain(kinds).collect();
+        if selection.lib {
+            names.push("lib".to_string());
+        }
+        names.sort();
+        names.dedup();
+        self.selection = names;
+        self
+    }
+
+    /// Set the effective rustflags; order and repeats are kept, as rustc
+    /// sees them
+    #[must_use]
+    pub fn with_rustflags(mut self, rustflags: Vec<String>) -> Self {
+        self.rustflags = rustflags;
+        self
+    }
+
+    /// Set the cargo arguments; order and repeats are kept, as cargo sees
+    /// them
+    #[must_use]
+    pub fn with_cargo_args(mut self, args: Vec<String>) -> Self {
+        self.cargo_args = args;
+        self
+    }
+
+    /// Build the key for `target` from build options, the effective
+    /// rustflags and the arguments the build passes to cargo, querying
+    /// rustup, rustc and Zig
+    #[must_use]
+    pub fn for_build(
+        target: &str,
+        options: &BuildOptions,
+        rustflags: &[String],
+        cargo_args: &[String],
+    ) -> Self {
+        let toolchain = options.toolchain.as_deref();
+
+        let zig_version = if options.use_zig == Some(false) {
+            None
+        } else {
+            zig_version()
+        };
+
+        Self::new(target)
+            .with_toolchain(active_toolchain(toolchain))
+            .with_rustc_version(rustc_version(toolchain))
+            .with_release(options.release)
+            .with_features(options.features.clone())
+            .with_zig_version(zig_version)
+            .with_selection(&options.selection)
+            .with_rustflags(rustflags.to_vec())
+            .with_cargo_args(cargo_args.to_vec())
+    }
+
+    /// Hash of all key fields
+    #[must_use]
+    pub fn hash(&self) -> u64 {
+        let mut parts = vec![
+            hash_str(&self.target),
+            hash_str(&self.toolchain),
+            hash_str(self.rustc_version.as_deref().unwrap_or("")),
+            hash_str(&self.profile),
+            hash_str(self.zig_version.as_deref().unwrap_or("")),
+        ];
+        parts.extend(self.features.iter().map(|f| hash_str(f)));
+        parts.extend(self.selection.iter().map(|s| hash_str(s)));
+        // Lengths first, so a flag can't move between the lists unnoticed
+        for list in [&self.rustflags, &self.cargo_args] {
+            parts.push(list.len() as u64);
+            parts.extend(list.iter().map(|arg| hash_str(arg)));
+        }
+        hash_combine(&parts)
+    }
+}
+
+/// The toolchain cargo builds with: `requested`, or the one rustup picks
+/// here (`RUSTUP_TOOLCHAIN`, a directory override, `rust-toolchain.toml`
+/// or the default)
+fn active_toolchain(requested: Option<&str>) -> String {
+    if let Some(toolchain) = requested {
+        return toolchain.to_string();
+    }
+    // e.g. "stable-x86_64-unknown-linux-gnu (overridden by '...')"
+    Command::new("rustup")
+        .args(["show", "active-toolchain"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .split_whitespace()
+                .next()
+                .map(ToString::to_string)
+        })
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// `rustc --version` for a rustup toolchain, or the active one
+fn rustc_version(toolchain: Option<&str>) -> Option<String> {
+    let mut rustc = Command::new("rustc");
+    if let Some(toolchain) = toolchain {
+        rustc.arg(format!("+{toolchain}"));
+    }
+    let output = rustc.arg("--version").output().ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_changes_with_inputs() {
+        let base = CacheKey::new("x86_64-unknown-linux-gnu")
+            .with_rustc_version(Some("rustc 1.80.0".to_string()));
+
+        let nightly = base.clone().with_toolchain("nightly");
+        let release = base.clone().with_release(true);
+        let featured = base.clone().with_features(vec!["serde".to_string()]);
+        let zig = base.clone().with_zig_version(Some("0.13.0".to_string()));
+        let selected = base.clone().with_selection(&TargetSelection {
+            bins: vec!["app".to_string()],
+            ..Default::default()
+        });
+        let kinds = base
+            .clone()
+            .with_selection(&TargetSelection::of_kinds(&["example".to_string()]));
+        let bumped = base
+            .clone()
+            .with_rustc_version(Some("rustc 1.81.0".to_string()));
+
+        for other in [
+            &nightly, &release, &featured, &zig, &selected, &kinds, &bumped,
+        ] {
+            assert_ne!(base.hash(), other.hash());
+        }
+    }
+
+    #[test]
+    fn test_feature_order_does_not_matter() {
+        let a = CacheKey::new("t").with_features(vec!["b".to_string(), "a".to_string()]);
+        let b = CacheKey::new("t").with_features(vec![
+            "a".to_string(),
+            "b".to_string(),
+            "a".to_string(),
+        ]);
+        assert_eq!(a, b);
+        assert_eq!(a.hash(), b.hash());
+    }
+
+    #[test]
+    fn test_for_build_uses_rustflags_and_cargo_args() {
+        let options = BuildOptions {
+            toolchain: Some("stable".to_string()),
+            use_zig: Some(false),
+            ..Default::default()
+        };
+        let args =
+            |list: &[&str]| -> Vec<String> { list.iter().map(ToString::to_string).collect() };
+        let key = |rustflags: &[&str], cargo_args: &[&str]| {
+            CacheKey::for_build("t", &options, &args(rustflags), &args(cargo_args)).hash()
+        };
+
+        let plain = CacheKey::for_build("t", &options, &[], &[]);
+        assert_eq!(plain.toolchain, "stable");
+        let keys = [
+            plain.hash(),
+            key(&[], &["--no-default-features"]),
+            key(&["-C", "opt-level=1"], &[]),
+            key(&["-C", "opt-level=1", "-C", "opt-level=2"], &[]),
+            key(&["-C", "opt-level=2", "-C", "opt-level=1"], &[]),
+            key(&[], &["--features", "a", "--features", "a"]),
+            key(&[], &["--features", "a"]),
+            // The same words, split differently between the lists
+            key(&["-C"], &["opt-level=1"]),
+            key(&["-C", "opt-level=1"], &["-C"]),
+        ];
+        for (i, a) in keys.iter().enumerate() {
+            for b in &keys[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+}
@@ -4,20 +4,30 @@
 //! by detecting when source files haven't changed.
 
 mod hash;
+mod key;
+mod wrapper;
 
-pub use hash::{hash_file, hash_files, has_file_changed};
+pub use hash::{hash_file, hash_files, hash_tree, has_file_changed};
+pub use key::CacheKey;
+pub use wrapper::{wrapper_from_env, CompilerWrapper, ContainerWrapperSetup};
 
 use crate::error::{Error, Result};
+use crate::paths;
+use crate::state::{self, State};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::hash::BuildHasher;
+use std::path::{Path, PathBuf};
 
 /// Cache entry for a build target
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheEntry {
     /// Target triple
     pub target: String,
+    /// Toolchain, profile and feature settings the build used
+    #[serde(default)]
+    pub key: CacheKey,
     /// Hash of source files
     pub source_hash: u64,
     /// Timestamp when build completed
@@ -26,6 +36,11 @@ pub struct CacheEntry {
     pub success: bool,
 }
 
+impl<S: BuildHasher + Default> State for HashMap<String, CacheEntry, S> {
+    const SCHEMA: u32 = 1;
+    const DESCRIPTION: &'static str = "build cache";
+}
+
 /// Build cache manager
 #[derive(Debug, Default)]
 pub struct BuildCache {
@@ -42,14 +57,14 @@ impl BuildCache {
     /// Returns error if cache directory cannot be created
     pub fn new() -> Result<Self> {
         let cache_dir = Self::default_cache_dir()?;
-        fs::create_dir_all(&cache_dir)?;
+        fs::create_dir_all(paths::long_path(&cache_dir))?;
 
         let mut cache = Self {
             cache_dir,
             entries: HashMap::new(),
         };
 
-        cache.load()?;
+        cache.load();
         Ok(cache)
     }
 
@@ -58,14 +73,14 @@ impl BuildCache {
     /// # Errors
     /// Returns error if cache directory cannot be created
     pub fn with_cache_dir(cache_dir: PathBuf) -> Result<Self> {
-        fs::create_dir_all(&cache_dir)?;
+        fs::create_dir_all(paths::long_path(&cache_dir))?;
 
         let mut cache = Self {
             cache_dir,
             entries: HashMap::new(),
         };
 
-        cache.load()?;
+        cache.load();
         Ok(cache)
     }
 
@@ -81,27 +96,23 @@ impl BuildCache {
         Ok(home.join(".xcargo").join("cache"))
     }
 
+    /// Get the cache directory
+    #[must_use]
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
     /// Get cache file path
-    fn cache_file_path(&self) -> PathBuf {
+    #[must_use]
+    pub fn cache_file_path(&self) -> PathBuf {
         self.cache_dir.join("build-cache.json")
     }
 
     /// Load cache from disk
     ///
-    /// # Errors
-    /// Returns error if cache file cannot be read or parsed
-    fn load(&mut self) -> Result<()> {
-        let cache_file = self.cache_file_path();
-
-        if !cache_file.exists() {
-            return Ok(());
-        }
-
-        let contents = fs::read_to_string(&cache_file)?;
-        self.entries = serde_json::from_str(&contents)
-            .map_err(|e| Error::Config(format!("Failed to parse cache: {e}")))?;
-
-        Ok(())
+    /// An unreadable cache file is set aside and the cache starts empty.
+    fn load(&mut self) {
+        self.entries = state::load(&self.cache_file_path());
     }
 
     /// Save cache to disk
@@ -109,12 +120,7 @@ impl BuildCache {
     /// # Errors
     /// Returns error if cache file cannot be written
     pub fn save(&self) -> Result<()> {
-        let cache_file = self.cache_file_path();
-        let contents = serde_json::to_string_pretty(&self.entries)
-            .map_err(|e| Error::Config(format!("Failed to serialize cache: {e}")))?;
-
-        fs::write(&cache_file, contents)?;
-        Ok(())
+        state::save(&self.cache_file_path(), &self.entries)
     }
 
     /// Check if target needs rebuild
@@ -122,14 +128,13 @@ impl BuildCache {
     /// Returns true if:
     /// - No cache entry exists
     /// - Previous build failed
+    /// - Toolchain, profile, features or Zig version differ
     /// - Source files have changed
     #[must_use]
-    pub fn needs_rebuild(&self, target: &str, source_hash: u64) -> bool {
-        match self.entries.get(target) {
+    pub fn needs_rebuild(&self, key: &CacheKey, source_hash: u64) -> bool {
+        match self.entries.get(&key.target) {
             None => true, // No cache entry
-            Some(entry) => {
-                !entry.success || entry.source_hash != source_hash
-            }
+            Some(entry) => !entry.success || entry.key != *key || entry.source_hash != source_hash,
         }
     }
 
@@ -139,17 +144,26 @@ impl BuildCache {
         self.entries.get(target)
     }
 
+    /// All cache entries, sorted by target
+    #[must_use]
+    pub fn entries(&self) -> Vec<&CacheEntry> {
+        let mut entries: Vec<_> = self.entries.values().collect();
+        entries.sort_by(|a, b| a.target.cmp(&b.target));
+        entries
+    }
+
     /// Update cache entry
-    pub fn update(&mut self, target: String, source_hash: u64, success: bool) {
+    pub fn update(&mut self, key: CacheKey, source_hash: u64, success: bool) {
         let timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .map(|d| d.as_secs())
             .unwrap_or(0);
 
         self.entries.insert(
-            target.clone(),
+            key.target.clone(),
             CacheEntry {
-                target,
+                target: key.target.clone(),
+                key,
                 source_hash,
                 timestamp,
                 success,
@@ -163,8 +177,10 @@ impl BuildCache {
     }
 
     /// Clear cache entry for specific target
-    pub fn clear_target(&mut self, target: &str) {
-        self.entries.remove(target);
+    ///
+    /// Returns false if there was no entry for the target.
+    pub fn clear_target(&mut self, target: &str) -> bool {
+        self.entries.remove(target).is_some()
     }
 
     /// Get cache statistics
@@ -183,7 +199,7 @@ impl BuildCache {
 }
 
 /// Cache statistics
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CacheStats {
     /// Total cache entries
     pub total_entries: usize,
@@ -211,7 +227,7 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let mut cache = BuildCache::with_cache_dir(temp_dir.path().to_path_buf()).unwrap();
 
-        cache.update("x86_64-unknown-linux-gnu".to_string(), 12345, true);
+        cache.update(CacheKey::new("x86_64-unknown-linux-gnu"), 12345, true);
 
         let entry = cache.get("x86_64-unknown-linux-gnu").unwrap();
         assert_eq!(entry.target, "x86_64-unknown-linux-gnu");
@@ -225,20 +241,40 @@ mod tests {
         let mut cache = BuildCache::with_cache_dir(temp_dir.path().to_path_buf()).unwrap();
 
         // No cache entry - needs rebuild
-        assert!(cache.needs_rebuild("x86_64-unknown-linux-gnu", 12345));
+        assert!(cache.needs_rebuild(&CacheKey::new("x86_64-unknown-linux-gnu"), 12345));
 
         // Add successful build
-        cache.update("x86_64-unknown-linux-gnu".to_string(), 12345, true);
+        cache.update(CacheKey::new("x86_64-unknown-linux-gnu"), 12345, true);
 
         // Same hash - no rebuild needed
-        assert!(!cache.needs_rebuild("x86_64-unknown-linux-gnu", 12345));
+        assert!(!cache.needs_rebuild(&CacheKey::new("x86_64-unknown-linux-gnu"), 12345));
 
         // Different hash - needs rebuild
-        assert!(cache.needs_rebuild("x86_64-unknown-linux-gnu", 67890));
+        assert!(cache.needs_rebuild(&CacheKey::new("x86_64-unknown-linux-gnu"), 67890));
 
         // Failed build - needs rebuild
-        cache.update("x86_64-pc-windows-gnu".to_string(), 11111, false);
-        assert!(cache.needs_rebuild("x86_64-pc-windows-gnu", 11111));
+        cache.update(CacheKey::new("x86_64-pc-windows-gnu"), 11111, false);
+        assert!(cache.needs_rebuild(&CacheKey::new("x86_64-pc-windows-gnu"), 11111));
+    }
+
+    #[test]
+    fn test_needs_rebuild_on_toolchain_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut cache = BuildCache::with_cache_dir(temp_dir.path().to_path_buf()).unwrap();
+
+        let stable = CacheKey::new("x86_64-unknown-linux-gnu")
+            .with_rustc_version(Some("rustc 1.80.0".to_string()));
+        cache.update(stable.clone(), 12345, true);
+        assert!(!cache.needs_rebuild(&stable, 12345));
+
+        let nightly = stable.clone().with_toolchain("nightly");
+        assert!(cache.needs_rebuild(&nightly, 12345));
+
+        let release = stable.clone().with_release(true);
+        assert!(cache.needs_rebuild(&release, 12345));
+
+        let zig = stable.with_zig_version(Some("0.13.0".to_string()));
+        assert!(cache.needs_rebuild(&zig, 12345));
     }
 
     #[test]
@@ -247,7 +283,7 @@ mod tests {
 
         {
             let mut cache = BuildCache::with_cache_dir(temp_dir.path().to_path_buf()).unwrap();
-            cache.update("x86_64-unknown-linux-gnu".to_string(), 12345, true);
+            cache.update(CacheKey::new("x86_64-unknown-linux-gnu"), 12345, true);
             cache.save().unwrap();
         }
 
@@ -257,13 +293,27 @@ mod tests {
         assert_eq!(entry.source_hash, 12345);
     }
 
+    #[test]
+    fn test_cache_from_truncated_file() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("build-cache.json"),
+            r#"{"schema": 1, "data": {"x86_64"#,
+        )
+        .unwrap();
+
+        let cache = BuildCache::with_cache_dir(temp_dir.path().to_path_buf()).unwrap();
+        assert!(cache.entries().is_empty());
+        assert!(temp_dir.path().join("build-cache.json.bak").exists());
+    }
+
     #[test]
     fn test_cache_clear() {
         let temp_dir = TempDir::new().unwrap();
         let mut cache = BuildCache::with_cache_dir(temp_dir.path().to_path_buf()).unwrap();
 
-        cache.update("target1".to_string(), 111, true);
-        cache.update("target2".to_string(), 222, true);
+        cache.update(CacheKey::new("target1"), 111, true);
+        cache.update(CacheKey::new("target2"), 222, true);
 
         assert_eq!(cache.entries.len(), 2);
 
@@ -276,10 +326,11 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let mut cache = BuildCache::with_cache_dir(temp_dir.path().to_path_buf()).unwrap();
 
-        cache.update("target1".to_string(), 111, true);
-        cache.update("target2".to_string(), 222, true);
+        cache.update(CacheKey::new("target1"), 111, true);
+        cache.update(CacheKey::new("target2"), 222, true);
 
-        cache.clear_target("target1");
+        assert!(cache.clear_target("target1"));
+        assert!(!cache.clear_target("missing"));
 
         assert!(cache.get("target1").is_none());
         assert!(cache.get("target2").is_some());
@@ -290,9 +341,9 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let mut cache = BuildCache::with_cache_dir(temp_dir.path().to_path_buf()).unwrap();
 
-        cache.update("target1".to_string(), 111, true);
-        cache.update("target2".to_string(), 222, true);
-        cache.update("target3".to_string(), 333, false);
+        cache.update(CacheKey::new("target1"), 111, true);
+        cache.update(CacheKey::new("target2"), 222, true);
+        cache.update(CacheKey::new("target3"), 333, false);
 
         let stats = cache.stats();
         assert_eq!(stats.total_entries, 3);
@@ -0,0 +1,176 @@
+//! Compiler cache (`RUSTC_WRAPPER`) integration
+//!
+//! Detects sccache, or uses `[cache] compiler_wrapper`, and produces the
+//! environment needed to route rustc and C compilers through it in both
+//! native and container builds.
+
+use crate::config::CacheConfig;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Directory the compiler cache is mounted at inside build containers
+const CONTAINER_CACHE_DIR: &str = "/root/.cache/xcargo-compiler-cache";
+
+/// A resolved compiler wrapper
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompilerWrapper {
+    /// Wrapper name (e.g., "sccache")
+    pub name: String,
+    /// Absolute path to the wrapper binary
+    pub path: PathBuf,
+}
+
+/// Env and mounts needed to use the wrapper inside a container
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContainerWrapperSetup {
+    /// Environment variables for the container
+    pub env: Vec<(String, String)>,
+    /// Volumes as `(host, container)` pairs
+    pub volumes: Vec<(String, String)>,
+}
+
+impl CompilerWrapper {
+    /// Resolve the wrapper from config, falling back to sccache in `PATH`
+    ///
+    /// Returns `None` when `compiler_wrapper = "none"` or nothing is found.
+    #[must_use]
+    pub fn detect(config: &CacheConfig) -> Option<Self> {
+        let name = match config.compiler_wrapper.as_deref() {
+            Some("none") => return None,
+            Some(name) => name,
+            None => "sccache",
+        };
+
+        let path = which::which(name).ok()?;
+        let name = path
+            .file_stem()
+            .map_or_else(|| name.to_string(), |s| s.to_string_lossy().into_owned());
+        Some(Self { name, path })
+    }
+
+    /// Check if this wrapper is sccache
+    #[must_use]
+    pub fn is_sccache(&self) -> bool {
+        self.name == "sccache"
+    }
+
+    /// Environment for a native build
+    #[must_use]
+    pub fn native_env(&self) -> Vec<(String, String)> {
+        let mut env = vec![("RUSTC_WRAPPER".to_string(), self.path.display().to_string())];
+        if self.is_sccache() {
+            // sccache cannot cache incremental builds
+            env.push(("CARGO_INCREMENTAL".to_string(), "0".to_string()));
+        }
+        env
+    }
+
+    /// Env and mounts for a container build
+    ///
+    /// The host binary is mounted into the container, so this only works
+    /// from Linux hosts; `None` is returned elsewhere.
+    #[must_use]
+    pub fn container_setup(&self, host_os: &str) -> Option<ContainerWrapperSetup> {
+        if host_os != "linux" {
+            return None;
+        }
+
+        let binary = format!("/usr/local/bin/{}", self.name);
+        let mut setup = ContainerWrapperSetup {
+            env: vec![("RUSTC_WRAPPER".to_string(), binary.clone())],
+            volumes: vec![(self.path.display().to_string(), binary)],
+        };
+
+        if self.is_sccache() {
+            if let Some(dir) = sccache_dir() {
+                setup
+                    .volumes
+                    .push((dir.display().to_string(), CONTAINER_CACHE_DIR.to_string()));
+                setup
+                    .env
+                    .push(("SCCACHE_DIR".to_string(), CONTAINER_CACHE_DIR.to_string()));
+            }
+            setup
+                .env
+                .push(("CARGO_INCREMENTAL".to_string(), "0".to_string()));
+        }
+
+        Some(setup)
+    }
+
+    /// Run `rustc` through the wrapper for `target`, as cargo would
+    pub fn verify(&self, target: &str) -> std::result::Result<(), String> {
+        let output = Command::new(&self.path)
+            .args(["rustc", "--print", "cfg", "--target", target])
+            .output()
+            .map_err(|e| format!("failed to run {}: {e}", self.path.display()))?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(stderr.lines().next().unwrap_or("unknown error").to_string())
+        }
+    }
+}
+
+/// Local sccache cache directory (`SCCACHE_DIR` or the platform default)
+fn sccache_dir() -> Option<PathBuf> {
+    if let Ok(dir) = std::env::var("SCCACHE_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    dirs::cache_dir().map(|dir| dir.join("sccache"))
+}
+
+/// Check if `RUSTC_WRAPPER` is already set by the user
+#[must_use]
+pub fn wrapper_from_env() -> Option<PathBuf> {
+    std::env::var_os("RUSTC_WRAPPER")
+        .filter(|value| !value.is_empty())
+        .map(|value| Path::new(&value).to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sccache() -> CompilerWrapper {
+        CompilerWrapper {
+            name: "sccache".to_string(),
+            path: PathBuf::from("/usr/bin/sccache"),
+        }
+    }
+
+    #[test]
+    fn test_detect_disabled() {
+        let config = CacheConfig {
+            compiler_wrapper: Some("none".to_string()),
+        };
+        assert!(CompilerWrapper::detect(&config).is_none());
+    }
+
+    #[test]
+    fn test_native_env() {
+        let env = sccache().native_env();
+        assert_eq!(
+            env[0],
+            ("RUSTC_WRAPPER".to_string(), "/usr/bin/sccache".to_string())
+        );
+        assert!(env.contains(&("CARGO_INCREMENTAL".to_string(), "0".to_string())));
+    }
+
+    #[test]
+    fn test_container_setup() {
+        assert!(sccache().container_setup("macos").is_none());
+
+        let setup = sccache().container_setup("linux").unwrap();
+        assert!(setup.env.contains(&(
+            "RUSTC_WRAPPER".to_string(),
+            "/usr/local/bin/sccache".to_string()
+        )));
+        assert!(setup.volumes.contains(&(
+            "/usr/bin/sccache".to_string(),
+            "/usr/local/bin/sccache".to_string()
+        )));
+    }
+}
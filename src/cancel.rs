@@ -0,0 +1,477 @@
+//! Build timeouts and cooperative cancellation
+//!
+//! Child processes (cargo, container runs) are started with [`run`], which
+//! waits for them while watching a [`CancellationToken`] and an optional
+//! timeout. When either fires, the child is interrupted (`SIGINT` on Unix),
+//! then terminated (`SIGTERM`), given [`GRACE_PERIOD`] to exit after each,
+//! and only then killed, so cargo can release its locks and the container
+//! runtime can remove the container. Cargo runs in a process group of its
+//! own (see [`RunLimits::process_group`]) that is signalled as a whole, so
+//! rustc, linkers and build scripts stop with it.
+//!
+//! The CLI's Ctrl-C handler cancels [`CancellationToken::global`], which
+//! every [`Builder`](crate::build::Builder) watches unless given its own
+//! token. A multi-target build then stops starting targets, records the
+//! ones that finished and reports the rest as not built.
+//!
+//! ```no_run
+//! use xcargo::build::{BuildOptions, Builder};
+//! use xcargo::cancel::CancellationToken;
+//!
+//! # fn example() -> xcargo::Result<()> {
+//! let token = CancellationToken::new();
+//! let builder = Builder::new()?.with_cancellation(token.clone());
+//!
+//! // From another thread, e.g. when the user closes a window:
+//! // token.cancel();
+//! builder.build(&BuildOptions::default())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::output::progress::format_duration;
+use std::io::{BufRead, BufReader, Read, Write};
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Time a child gets to exit after each signal before the next, stronger
+/// one is sent
+pub const GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// How often a running child is checked
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Children currently waited on by [`run`]
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// Process groups led by children currently waited on by [`run`]
+static GROUPS: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+/// Process-wide token, cancelled by the CLI on Ctrl-C
+static GLOBAL: OnceLock<CancellationToken> = OnceLock::new();
+
+/// A flag shared between a build and whoever may cancel it
+///
+/// Clones share the flag; once cancelled, a token stays cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// A new token that is not cancelled
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The process-wide token cancelled on interrupt
+    #[must_use]
+    pub fn global() -> &'static CancellationToken {
+        GLOBAL.get_or_init(CancellationToken::new)
+    }
+
+    /// Ask everything watching this token to stop
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether [`Self::cancel`] was called
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// When to stop a child process early
+#[derive(Debug, Clone, Default)]
+pub struct RunLimits {
+    /// Stop when this token is cancelled
+    pub cancel: CancellationToken,
+    /// Stop after this long
+    pub timeout: Option<Duration>,
+    /// Run the child in a process group of its own and signal the whole
+    /// group, so the processes it starts stop with it; not for children
+    /// that use the terminal, like `docker run -it`
+    pub process_group: bool,
+}
+
+/// Signals a stopped child receives, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Signal {
+    Interrupt,
+    Terminate,
+    Kill,
+}
+
+/// Send `signal` to `child`, or with `group` to the process group it leads
+#[cfg(unix)]
+pub(crate) fn send(child: &mut Child, signal: Signal, group: bool) -> bool {
+    let name = match signal {
+        Signal::Interrupt => "-INT",
+        Signal::Terminate => "-TERM",
+        Signal::Kill => "-KILL",
+    };
+    let pid = if group {
+        format!("-{}", child.id())
+    } else {
+        child.id().to_string()
+    };
+    kill(name, &pid)
+}
+
+/// Run `kill <signal> -- <pid>`, where a negative `pid` names a group
+#[cfg(unix)]
+fn kill(signal: &str, pid: &str) -> bool {
+    Command::new("kill")
+        .args([signal, "--", pid])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Without signals a child can only be killed outright
+#[cfg(not(unix))]
+pub(crate) fn send(child: &mut Child, _signal: Signal, _group: bool) -> bool {
+    child.kill().is_ok()
+}
+
+/// Stream a line of child output was printed on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    /// Standard output
+    Stdout,
+    /// Standard error
+    Stderr,
+}
+
+impl Stream {
+    /// "stdout" or "stderr"
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Stdout => "stdout",
+            Self::Stderr => "stderr",
+        }
+    }
+}
+
+/// Number of child processes currently running under [`run`]
+#[must_use]
+pub fn running() -> usize {
+    RUNNING.load(Ordering::SeqCst)
+}
+
+/// Kill the process groups of the children running under [`run`], for
+/// exiting without waiting for them
+pub fn kill_groups() {
+    let groups = GROUPS.lock().unwrap_or_else(PoisonError::into_inner);
+    #[cfg(unix)]
+    for pid in groups.iter() {
+        kill("-KILL", &format!("-{pid}"));
+    }
+    drop(groups);
+}
+
+/// Run `cmd` to completion unless `limits` stop it first
+///
+/// Returns [`Error::Cancelled`] or [`Error::TimedOut`] when the child was
+/// stopped, after it has exited.
+pub fn run(cmd: &mut Command, limits: &RunLimits) -> Result<ExitStatus> {
+    if limits.cancel.is_cancelled() {
+        return Err(Error::Cancelled);
+    }
+
+    let mut child = spawn(cmd, limits)?;
+    watch(&mut child, limits)
+}
+
+/// Like [`run`], but with the child's output passed to `on_line` line by
+/// line instead of going straight to the terminal
+///
+/// Lines are given without their line ending. `on_line` decides what is
+/// printed, e.g. with [`echo`].
+pub fn run_with_output<F>(cmd: &mut Command, limits: &RunLimits, on_line: F) -> Result<ExitStatus>
+where
+    F: Fn(Stream, &str) + Send + Sync + 'static,
+{
+    if limits.cancel.is_cancelled() {
+        return Err(Error::Cancelled);
+    }
+
+    let mut child = spawn(cmd.stdout(Stdio::piped()).stderr(Stdio::piped()), limits)?;
+    let on_line = Arc::new(on_line);
+    let readers = [
+        child
+            .stdout
+            .take()
+            .map(|out| forward(out, Stream::Stdout, Arc::clone(&on_line))),
+        child
+            .stderr
+            .take()
+            .map(|err| forward(err, Stream::Stderr, Arc::clone(&on_line))),
+    ];
+
+    let result = watch(&mut child, limits);
+
+    for reader in readers.into_iter().flatten() {
+        let _ = reader.join();
+    }
+    result
+}
+
+/// Start `cmd`, in a process group of its own if `limits` ask for one
+fn spawn(cmd: &mut Command, limits: &RunLimits) -> std::io::Result<Child> {
+    #[cfg(unix)]
+    if limits.process_group {
+        cmd.process_group(0);
+    }
+    cmd.spawn()
+}
+
+/// Print `line` on xcargo's own `stream`
+pub fn echo(stream: Stream, line: &str) {
+    let _ = match stream {
+        Stream::Stdout => writeln!(std::io::stdout(), "{line}"),
+        Stream::Stderr => writeln!(std::io::stderr(), "{line}"),
+    };
+}
+
+/// Read `pipe` line by line, handing each line to `on_line`
+fn forward<R, F>(pipe: R, stream: Stream, on_line: Arc<F>) -> JoinHandle<()>
+where
+    R: Read + Send + 'static,
+    F: Fn(Stream, &str) + Send + Sync + 'static,
+{
+    thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        let mut line = Vec::new();
+        while reader
+            .read_until(b'\n', &mut line)
+            .is_ok_and(|read| read > 0)
+        {
+            let text = String::from_utf8_lossy(&line);
+            on_line(stream, text.trim_end_matches(['\r', '\n']));
+            line.clear();
+        }
+    })
+}
+
+/// [`wait`] for `child`, counted in [`running`] and with its process group
+/// known to [`kill_groups`]
+fn watch(child: &mut Child, limits: &RunLimits) -> Result<ExitStatus> {
+    let pid = child.id();
+    RUNNING.fetch_add(1, Ordering::SeqCst);
+    if limits.process_group {
+        GROUPS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(pid);
+    }
+    let result = wait(child, limits);
+    if limits.process_group {
+        GROUPS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|group| *group != pid);
+    }
+    RUNNING.fetch_sub(1, Ordering::SeqCst);
+    result
+}
+
+fn wait(child: &mut Child, limits: &RunLimits) -> Result<ExitStatus> {
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            // A child that exited because of the interrupt is a cancellation
+            if !status.success() && limits.cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            return Ok(status);
+        }
+
+        if limits.cancel.is_cancelled() {
+            terminate(child, limits.process_group)?;
+            return Err(Error::Cancelled);
+        }
+        if let Some(timeout) = limits.timeout.filter(|t| started.elapsed() >= *t) {
+            terminate(child, limits.process_group)?;
+            return Err(Error::TimedOut(format_duration(timeout)));
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Interrupt `child`, or with `group` its process group, then terminate
+/// and finally kill it, each after [`GRACE_PERIOD`]
+fn terminate(child: &mut Child, group: bool) -> Result<()> {
+    tracing::debug!(pid = child.id(), group, "stopping child process");
+    for signal in [Signal::Interrupt, Signal::Terminate] {
+        if !cfg!(unix) || !send(child, signal, group) {
+            break;
+        }
+        let deadline = Instant::now() + GRACE_PERIOD;
+        while Instant::now() < deadline {
+            if child.try_wait()?.is_some() {
+                // Nothing the child started may outlive it
+                if group {
+                    send(child, Signal::Kill, true);
+                }
+                return Ok(());
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    if group {
+        send(child, Signal::Kill, true);
+    }
+    // Already exited is fine
+    let _ = child.kill();
+    child.wait()?;
+    Ok(())
+}
+
+/// Parse a duration such as `90`, `90s`, `15m`, `2h` or `1h30m`
+///
+/// A bare number is seconds. Zero is not a duration.
+#[must_use]
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    if let Ok(secs) = text.parse::<u64>() {
+        return (secs > 0).then(|| Duration::from_secs(secs));
+    }
+
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            _ => return None,
+        };
+        let value: u64 = number.parse().ok()?;
+        total = total.checked_add(value.checked_mul(unit)?)?;
+        number.clear();
+    }
+
+    (number.is_empty() && total > 0).then(|| Duration::from_secs(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("45s"), Some(Duration::from_secs(45)));
+        assert_eq!(parse_duration("15m"), Some(Duration::from_secs(900)));
+        assert_eq!(parse_duration("1h30m"), Some(Duration::from_secs(5400)));
+
+        for invalid in ["", "0", "0s", "m", "1.5h", "15min", "10d"] {
+            assert_eq!(parse_duration(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_token_is_shared_by_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+        token.cancel();
+        assert!(clone.is_cancelled());
+        assert!(!CancellationToken::new().is_cancelled());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_limits() {
+        let status = run(&mut Command::new("true"), &RunLimits::default()).unwrap();
+        assert!(status.success());
+
+        let limits = RunLimits {
+            timeout: Some(Duration::from_millis(200)),
+            ..RunLimits::default()
+        };
+        let started = Instant::now();
+        let mut sleep = Command::new("sleep");
+        sleep.arg("30");
+        assert!(matches!(run(&mut sleep, &limits), Err(Error::TimedOut(_))));
+        assert!(started.elapsed() < GRACE_PERIOD);
+
+        let lines = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = Arc::clone(&lines);
+        let mut echo = Command::new("sh");
+        echo.args(["-c", "echo out; echo err >&2"]);
+        let status = run_with_output(&mut echo, &RunLimits::default(), move |stream, line| {
+            seen.lock().unwrap().push((stream, line.to_string()));
+        })
+        .unwrap();
+        assert!(status.success());
+        let mut lines = lines.lock().unwrap().clone();
+        lines.sort_by_key(|(stream, _)| stream.as_str());
+        assert_eq!(
+            lines,
+            [
+                (Stream::Stderr, "err".to_string()),
+                (Stream::Stdout, "out".to_string())
+            ]
+        );
+
+        let limits = RunLimits::default();
+        limits.cancel.cancel();
+        assert!(matches!(
+            run(&mut Command::new("true"), &limits),
+            Err(Error::Cancelled)
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timeout_stops_the_process_group() {
+        let temp = tempfile::tempdir().unwrap();
+        let pid_file = temp.path().join("pid");
+        let limits = RunLimits {
+            timeout: Some(Duration::from_millis(500)),
+            process_group: true,
+            ..RunLimits::default()
+        };
+        // A shell's background jobs ignore SIGINT, so the sleep outlives
+        // the shell unless the whole group is stopped
+        let mut shell = Command::new("sh");
+        shell
+            .arg("-c")
+            .arg(format!("sleep 30 & echo $! > {}; wait", pid_file.display()));
+        let started = Instant::now();
+        assert!(matches!(run(&mut shell, &limits), Err(Error::TimedOut(_))));
+        assert!(started.elapsed() < GRACE_PERIOD);
+
+        // Killed, though perhaps not reaped where nothing reaps orphans
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        let state = Command::new("ps")
+            .args(["-o", "stat=", "-p", pid.trim()])
+            .output()
+            .unwrap();
+        let state = String::from_utf8_lossy(&state.stdout);
+        assert!(
+            state.trim().is_empty() || state.starts_with('Z'),
+            "sleep {} outlived its shell: {state}",
+            pid.trim()
+        );
+    }
+}
@@ -0,0 +1,405 @@
+//! Cargo's own configuration
+//!
+//! Many projects already set a linker, rustflags or a runner for a target in
+//! `[target.<triple>]` of `.cargo/config.toml`. Builds keep them: xcargo
+//! leaves a linker cargo is configured with alone, carries the rustflags
+//! over when it sets `RUSTFLAGS` (which replaces them), and tests run with
+//! the configured runner. Where xcargo.toml sets something else it wins,
+//! with a warning rather than silently.
+
+use super::{line_and_column, Config, ConfigProblem};
+use crate::build::rustflags::{FlagSource, RustFlags};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Names of cargo's configuration file in a `.cargo` directory; cargo reads
+/// the first when both exist
+pub const CARGO_CONFIG_FILE_NAMES: &[&str] = &["config", "config.toml"];
+
+/// A setting from a cargo configuration file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CargoSetting<T> {
+    /// The value
+    pub value: T,
+    /// The file it is set in; the last one for merged lists
+    pub path: PathBuf,
+}
+
+/// What cargo's configuration sets for one target
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CargoTargetConfig {
+    /// `linker`, resolved the way cargo resolves relative paths
+    pub linker: Option<CargoSetting<String>>,
+    /// `runner`, program first
+    pub runner: Option<CargoSetting<Vec<String>>>,
+    /// `rustflags`, or `[build] rustflags` when the target has none
+    pub rustflags: Option<CargoSetting<Vec<String>>>,
+}
+
+impl CargoTargetConfig {
+    /// What the configuration files cargo reads from the current directory
+    /// set for `triple`
+    pub fn find(triple: &str) -> std::result::Result<Self, ConfigProblem> {
+        match std::env::current_dir() {
+            Ok(cwd) => Self::find_from(&cwd, triple),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// What the configuration files cargo reads from `start` set for
+    /// `triple`
+    pub fn find_from(start: &Path, triple: &str) -> std::result::Result<Self, ConfigProblem> {
+        let mut config = Self::default();
+        let mut build_rustflags: Option<CargoSetting<Vec<String>>> = None;
+        // Lists merge across files and values in nearer files win, so
+        // read the farthest first
+        for path in files(start).iter().rev() {
+            let file = CargoConfigFile::read(path)?;
+            if let Some(flags) = file.build.rustflags.clone() {
+                append(&mut build_rustflags, flags.into_args(), path);
+            }
+            let Some(table) = file.target(triple) else {
+                continue;
+            };
+            if let Some(linker) = &table.linker {
+                config.linker = Some(CargoSetting {
+                    value: resolve(linker, path),
+                    path: path.clone(),
+                });
+            }
+            if let Some(runner) = table.runner {
+                let mut runner = runner.into_args();
+                if let Some(program) = runner.first_mut() {
+                    *program = resolve(program, path);
+                }
+                config.runner = Some(CargoSetting {
+                    value: runner,
+                    path: path.clone(),
+                });
+            }
+            if let Some(flags) = table.rustflags {
+                append(&mut config.rustflags, flags.into_args(), path);
+            }
+        }
+        config.rustflags = config.rustflags.or(build_rustflags);
+        Ok(config)
+    }
+}
+
+/// Cargo configuration files that apply in `start`, nearest first: those
+/// in `.cargo` of it and its ancestors, then the one in the cargo home
+fn files(start: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = start.ancestors().map(|dir| dir.join(".cargo")).collect();
+    let cargo_home = std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")));
+    if let Some(cargo_home) = cargo_home.filter(|home| !dirs.contains(home)) {
+        dirs.push(cargo_home);
+    }
+    dirs.iter()
+        .filter_map(|dir| {
+            CARGO_CONFIG_FILE_NAMES
+                .iter()
+                .map(|name| dir.join(name))
+                .find(|path| path.is_file())
+        })
+        .collect()
+}
+
+/// Extend the list `setting` with `values` from `path`
+fn append(setting: &mut Option<CargoSetting<Vec<String>>>, values: Vec<String>, path: &Path) {
+    let setting = setting.get_or_insert_with(|| CargoSetting {
+        value: Vec::new(),
+        path: path.to_path_buf(),
+    });
+    setting.value.extend(values);
+    setting.path = path.to_path_buf();
+}
+
+/// A program named in the cargo configuration file at `path`: a bare name
+/// is looked up in `PATH`, a relative path is relative to the directory
+/// holding `.cargo`
+fn resolve(program: &str, path: &Path) -> String {
+    let relative = Path::new(program);
+    if relative.is_absolute() || relative.components().count() < 2 {
+        return program.to_string();
+    }
+    match path.parent().and_then(Path::parent) {
+        Some(root) => root.join(relative).display().to_string(),
+        None => program.to_string(),
+    }
+}
+
+/// `path`, relative to the current directory when it is inside it
+fn shown(path: &Path) -> String {
+    std::env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| path.to_path_buf())
+        .display()
+        .to_string()
+}
+
+/// The parts of a cargo configuration file xcargo reads
+#[derive(Deserialize, Default)]
+struct CargoConfigFile {
+    #[serde(default)]
+    build: CargoBuild,
+    // `cfg(...)` keys hold tables of their own shape, so targets are
+    // picked out before deserializing
+    #[serde(default)]
+    target: BTreeMap<String, toml::Value>,
+}
+
+impl CargoConfigFile {
+    /// Read the file at `path`
+    fn read(path: &Path) -> std::result::Result<Self, ConfigProblem> {
+        let problem = |message: String, offset: Option<(usize, usize)>| ConfigProblem {
+            path: path.to_path_buf(),
+            line: offset.map(|(line, _)| line),
+            column: offset.map(|(_, column)| column),
+            message,
+        };
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| problem(format!("Failed to read cargo configuration: {e}"), None))?;
+        toml::from_str(&contents).map_err(|e| {
+            let offset = e.span().map(|span| line_and_column(&contents, span.start));
+            let message = e.message().trim().replace('\n', ", ");
+            problem(format!("Failed to parse TOML: {message}"), offset)
+        })
+    }
+
+    /// `[target.<triple>]`
+    fn target(&self, triple: &str) -> Option<CargoTarget> {
+        self.target
+            .get(triple)
+            .and_then(|table| table.clone().try_into().ok())
+    }
+}
+
+/// `[build]`
+#[derive(Deserialize, Default)]
+struct CargoBuild {
+    rustflags: Option<Args>,
+}
+
+/// `[target.<triple>]`
+#[derive(Deserialize)]
+struct CargoTarget {
+    linker: Option<String>,
+    runner: Option<Args>,
+    rustflags: Option<Args>,
+}
+
+/// Arguments as a list or a space-separated string
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+enum Args {
+    List(Vec<String>),
+    Line(String),
+}
+
+impl Args {
+    fn into_args(self) -> Vec<String> {
+        match self {
+            Self::List(args) => args,
+            Self::Line(line) => line.split_whitespace().map(str::to_string).collect(),
+        }
+    }
+}
+
+/// A linker, runner or rustflags setting in effect for a target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetSetting {
+    /// `linker`, `runner` or `rustflags`
+    pub key: &'static str,
+    /// The value in effect
+    pub value: String,
+    /// Where it is set: xcargo.toml, a cargo configuration file, or both
+    /// for rustflags carried over
+    pub origin: String,
+    /// What a cargo configuration file sets instead, when xcargo's
+    /// configuration overrides it
+    pub overrides: Option<CargoSetting<String>>,
+}
+
+impl TargetSetting {
+    /// A warning about xcargo's configuration overriding cargo's for
+    /// `triple`
+    #[must_use]
+    pub fn conflict(&self, triple: &str) -> Option<String> {
+        self.overrides.as_ref().map(|cargo| {
+            format!(
+                "xcargo.toml sets {} = {} for {triple}, overriding {} from {}",
+                self.key,
+                self.value,
+                cargo.value,
+                shown(&cargo.path)
+            )
+        })
+    }
+}
+
+impl Config {
+    /// The linker, runner and rustflags in effect for `triple`, from this
+    /// configuration over `cargo`'s
+    #[must_use]
+    pub fn target_settings(&self, triple: &str, cargo: &CargoTargetConfig) -> Vec<TargetSetting> {
+        let target_config = self.get_target_config(triple);
+        let prefix = format!("CARGO_TARGET_{}_", triple.to_uppercase().replace('-', "_"));
+        // Target variables are set after [build] ones, so they win
+        let env = |name: &str| {
+            target_config
+                .and_then(|config| config.env.get(name))
+                .or_else(|| self.build.env.get(name))
+                .cloned()
+        };
+        let cargo_value = |setting: Option<&CargoSetting<Vec<String>>>| {
+            setting.map(|setting| CargoSetting {
+                value: setting.value.join(" "),
+                path: setting.path.clone(),
+            })
+        };
+        let mut settings = Vec::new();
+        let mut push = |key, xcargo: Option<String>, cargo: Option<CargoSetting<String>>| match (
+            xcargo, cargo,
+        ) {
+            (Some(value), cargo) => settings.push(TargetSetting {
+                key,
+                overrides: cargo.filter(|cargo| cargo.value != value),
+                value,
+                origin: "xcargo.toml".to_string(),
+            }),
+            (None, Some(cargo)) => settings.push(TargetSetting {
+                key,
+                value: cargo.value,
+                origin: shown(&cargo.path),
+                overrides: None,
+            }),
+            (None, None) => {}
+        };
+
+        let linker = target_config
+            .and_then(|config| config.linker.clone())
+            .or_else(|| env(&format!("{prefix}LINKER")));
+        push("linker", linker, cargo.linker.clone());
+        push(
+            "runner",
+            env(&format!("{prefix}RUNNER")),
+            cargo_value(cargo.runner.as_ref()),
+        );
+
+        // `rustflags` are merged over cargo's, a RUSTFLAGS variable replaces
+        // them
+        let rustflags = target_config.and_then(|config| config.rustflags.as_ref());
+        match (env("RUSTFLAGS"), rustflags, &cargo.rustflags) {
+            (Some(flags), _, cargo) => push("rustflags", Some(flags), cargo_value(cargo.as_ref())),
+            (None, Some(flags), Some(cargo)) => {
+                let mut merged = RustFlags::default();
+                merged.add(&FlagSource::CargoConfig(cargo.path.clone()), &cargo.value);
+                merged.add(&FlagSource::Config, flags);
+                settings.push(TargetSetting {
+                    key: "rustflags",
+                    value: merged.args().join(" "),
+                    origin: format!("{} + xcargo.toml", shown(&cargo.path)),
+                    overrides: None,
+                });
+            }
+            (None, flags, cargo) => push(
+                "rustflags",
+                flags.map(|flags| flags.join(" ")),
+                cargo_value(cargo.as_ref()),
+            ),
+        }
+        settings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_target_config() {
+        let temp = tempfile::tempdir().unwrap();
+        let member = temp.path().join("crates").join("app");
+        std::fs::create_dir_all(member.join(".cargo")).unwrap();
+        std::fs::create_dir_all(temp.path().join(".cargo")).unwrap();
+        std::fs::write(
+            temp.path().join(".cargo").join("config.toml"),
+            "[build]\nrustflags = [\"-Dwarnings\"]\n\n[target.xcargo-test-linux-gnu]\nlinker = \"tools/ld\"\nrunner = \"qemu-aarch64 -L /usr\"\nrustflags = [\"-Ctarget-cpu=native\"]\n\n[target.'cfg(unix)']\nrunner = \"ignored\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            member.join(".cargo").join("config"),
+            "[target.xcargo-test-linux-gnu]\nlinker = \"aarch64-linux-gnu-gcc\"\nrustflags = \"-Clink-arg=-s\"\n",
+        )
+        .unwrap();
+
+        let config = CargoTargetConfig::find_from(&member, "xcargo-test-linux-gnu").unwrap();
+        let linker = config.linker.unwrap();
+        assert_eq!(linker.value, "aarch64-linux-gnu-gcc");
+        assert_eq!(linker.path, member.join(".cargo").join("config"));
+        assert_eq!(config.runner.unwrap().value, ["qemu-aarch64", "-L", "/usr"]);
+        assert_eq!(
+            config.rustflags.unwrap().value,
+            ["-Ctarget-cpu=native", "-Clink-arg=-s"]
+        );
+
+        // Relative paths are relative to the directory holding .cargo
+        let config = CargoTargetConfig::find_from(temp.path(), "xcargo-test-linux-gnu").unwrap();
+        let linker = temp.path().join("tools").join("ld");
+        assert_eq!(config.linker.unwrap().value, linker.display().to_string());
+
+        // Without target rustflags, [build] ones apply
+        let config = CargoTargetConfig::find_from(temp.path(), "xcargo-test-none").unwrap();
+        assert_eq!(config.rustflags.unwrap().value, ["-Dwarnings"]);
+        assert!(config.linker.is_none());
+    }
+
+    #[test]
+    fn test_target_settings_conflicts() {
+        let path = PathBuf::from(".cargo/config.toml");
+        let cargo = CargoTargetConfig {
+            linker: Some(CargoSetting {
+                value: "clang".to_string(),
+                path: path.clone(),
+            }),
+            runner: Some(CargoSetting {
+                value: vec!["qemu-aarch64".to_string()],
+                path: path.clone(),
+            }),
+            rustflags: Some(CargoSetting {
+                value: vec!["-Ctarget-cpu=native".to_string()],
+                path,
+            }),
+        };
+        let config: Config = toml::from_str(
+            "[targets.aarch64-unknown-linux-gnu]\nlinker = \"aarch64-linux-gnu-gcc\"\nrustflags = [\"-Clink-arg=-s\"]\n",
+        )
+        .unwrap();
+
+        let settings = config.target_settings("aarch64-unknown-linux-gnu", &cargo);
+        assert_eq!(settings.len(), 3);
+        assert_eq!(settings[0].value, "aarch64-linux-gnu-gcc");
+        assert_eq!(
+            settings[0].conflict("aarch64-unknown-linux-gnu").unwrap(),
+            "xcargo.toml sets linker = aarch64-linux-gnu-gcc for aarch64-unknown-linux-gnu, overriding clang from .cargo/config.toml"
+        );
+        assert_eq!(settings[1].origin, ".cargo/config.toml");
+        assert!(settings[1].overrides.is_none());
+        assert_eq!(settings[2].value, "-C target-cpu=native -C link-arg=-s");
+        assert!(settings[2].overrides.is_none());
+
+        // A RUSTFLAGS variable replaces cargo's rustflags
+        let config: Config = toml::from_str(
+            "[targets.aarch64-unknown-linux-gnu.env]\nRUSTFLAGS = \"-Copt-level=s\"\nCARGO_TARGET_AARCH64_UNKNOWN_LINUX_GNU_RUNNER = \"qemu-aarch64\"\n",
+        )
+        .unwrap();
+        let settings = config.target_settings("aarch64-unknown-linux-gnu", &cargo);
+        assert_eq!(settings[0].origin, ".cargo/config.toml");
+        assert!(settings[1].overrides.is_none());
+        assert!(settings[2].overrides.is_some());
+    }
+}
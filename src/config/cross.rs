@@ -0,0 +1,317 @@
+//! Cross.toml import
+//!
+//! Projects moving over from cross keep working: a Cross.toml next to the
+//! project's Cargo.toml is read as configuration, under everything else,
+//! and `xcargo migrate cross` turns it into an xcargo.toml. Images, host
+//! variables passed through and pre-build hooks carry over; settings
+//! xcargo has no counterpart for are listed rather than dropped silently.
+
+use super::{line_and_column, Config, ConfigProblem};
+use crate::error::{Error, Result};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Name of cross's configuration file
+pub const CROSS_FILE_NAME: &str = "Cross.toml";
+
+/// Environment variable pointing cross at another configuration file
+pub const ENV_CROSS_CONFIG: &str = "CROSS_CONFIG";
+
+/// A Cross.toml, as xcargo configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossImport {
+    /// The settings it makes, as a value tree of xcargo keys
+    pub value: Value,
+    /// Settings that were not carried over, and why
+    pub skipped: Vec<String>,
+}
+
+impl CrossImport {
+    /// Read and convert a Cross.toml
+    pub fn read(path: &Path) -> std::result::Result<Self, ConfigProblem> {
+        let problem = |message: String, offset: Option<(usize, usize)>| ConfigProblem {
+            path: path.to_path_buf(),
+            line: offset.map(|(line, _)| line),
+            column: offset.map(|(_, column)| column),
+            message,
+        };
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| problem(format!("Failed to read {CROSS_FILE_NAME}: {e}"), None))?;
+        let cross: CrossToml = toml::from_str(&contents).map_err(|e| {
+            let offset = e.span().map(|span| line_and_column(&contents, span.start));
+            let message = e.message().trim().replace('\n', ", ");
+            problem(format!("Failed to parse TOML: {message}"), offset)
+        })?;
+        Ok(Self::from_cross(cross))
+    }
+
+    /// Convert a parsed Cross.toml
+    fn from_cross(cross: CrossToml) -> Self {
+        let mut value = json!({});
+        let mut skipped = Vec::new();
+        let mut set = |key: &[&str], setting: Value| {
+            let mut table = &mut value;
+            for segment in key {
+                table = table
+                    .as_object_mut()
+                    .expect("tables are objects")
+                    .entry(*segment)
+                    .or_insert_with(|| Value::Object(Map::new()));
+            }
+            *table = setting;
+        };
+
+        let build = cross.build;
+        if let Some(target) = build.default_target {
+            set(&["targets", "default"], json!([target]));
+        }
+        if !build.env.passthrough.is_empty() {
+            set(&["container", "passthrough"], json!(build.env.passthrough));
+        }
+        let mut hooks = false;
+        if let Some(pre_build) = build.pre_build {
+            set(&["container", "pre_build"], json!(pre_build.commands()));
+            hooks = true;
+        }
+        if build.build_std == Some(toml::Value::Boolean(true)) {
+            set(&["build", "cargo_flags"], json!(["-Zbuild-std"]));
+        } else if let Some(build_std) = build.build_std {
+            skipped.push(format!(
+                "build.build-std = {build_std}: set -Zbuild-std=<crates> in [build] cargo_flags"
+            ));
+        }
+        skipped.extend(Self::unsupported("build", &build.env.volumes, build.other));
+
+        for (triple, target) in cross.target {
+            let key = |name| ["targets", triple.as_str(), name];
+            if let Some(image) = target.image {
+                set(&["container", "images", &triple], json!(image.name()));
+            }
+            if !target.env.passthrough.is_empty() {
+                set(&key("passthrough"), json!(target.env.passthrough));
+            }
+            if let Some(pre_build) = target.pre_build {
+                set(&key("pre_build"), json!(pre_build.commands()));
+                hooks = true;
+            }
+            match target.zig {
+                Some(toml::Value::Boolean(true)) => set(&key("strategy"), json!("zig")),
+                Some(toml::Value::String(version)) => {
+                    set(&key("strategy"), json!("zig"));
+                    set(&key("glibc_version"), json!(version));
+                }
+                Some(toml::Value::Boolean(false)) | None => {}
+                Some(zig) => skipped.push(format!(
+                    "target.{triple}.zig = {zig}: set strategy = \"zig\" and glibc_version in [targets.{triple}]"
+                )),
+            }
+            let section = format!("target.{triple}");
+            skipped.extend(Self::unsupported(
+                &section,
+                &target.env.volumes,
+                target.other,
+            ));
+        }
+
+        // cross runs pre-build hooks as root, while building its image
+        if hooks {
+            set(&["container", "run_as_user"], json!(false));
+        }
+        Self { value, skipped }
+    }
+
+    /// Notes for settings of `section` xcargo doesn't carry over
+    fn unsupported(
+        section: &str,
+        volumes: &[String],
+        other: BTreeMap<String, toml::Value>,
+    ) -> Vec<String> {
+        let volumes = (!volumes.is_empty()).then(|| {
+            format!(
+                "{section}.env.volumes = {volumes:?}: container builds mount only the project and the cargo home"
+            )
+        });
+        volumes
+            .into_iter()
+            .chain(other.into_iter().map(|(key, value)| {
+                let why = match key.as_str() {
+                    "dockerfile" => "build the image yourself and name it in [container.images]",
+                    "runner" => "xcargo picks test runners itself",
+                    "xargo" => "xargo is not supported",
+                    _ => "xcargo has no such setting",
+                };
+                format!("{section}.{key} = {value}: {why}")
+            }))
+            .collect()
+    }
+
+    /// The configuration it makes
+    pub fn config(&self) -> std::result::Result<Config, String> {
+        serde_json::from_value(self.value.clone()).map_err(|e| e.to_string())
+    }
+
+    /// The settings it makes, as the contents of an xcargo.toml
+    pub fn to_toml(&self) -> Result<String> {
+        let toml = toml::to_string_pretty(&self.value)
+            .map_err(|e| Error::Config(format!("Failed to serialize to TOML: {e}")))?;
+        Ok(format!(
+            "# Migrated from {CROSS_FILE_NAME} by `xcargo migrate cross`\n\n{toml}"
+        ))
+    }
+}
+
+/// Cross.toml
+#[derive(Deserialize)]
+struct CrossToml {
+    #[serde(default)]
+    build: CrossBuild,
+    #[serde(default)]
+    target: BTreeMap<String, CrossTarget>,
+}
+
+/// `[build]`, which applies to every target
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+struct CrossBuild {
+    default_target: Option<String>,
+    #[serde(default)]
+    env: CrossEnv,
+    pre_build: Option<PreBuild>,
+    build_std: Option<toml::Value>,
+    #[serde(flatten)]
+    other: BTreeMap<String, toml::Value>,
+}
+
+/// `[target.<triple>]`
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct CrossTarget {
+    image: Option<CrossImage>,
+    #[serde(default)]
+    env: CrossEnv,
+    pre_build: Option<PreBuild>,
+    zig: Option<toml::Value>,
+    #[serde(flatten)]
+    other: BTreeMap<String, toml::Value>,
+}
+
+/// `[build.env]` or `[target.<triple>.env]`
+#[derive(Deserialize, Default)]
+struct CrossEnv {
+    #[serde(default)]
+    passthrough: Vec<String>,
+    #[serde(default)]
+    volumes: Vec<String>,
+}
+
+/// `image = "name"` or `image = { name = "...", ... }`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CrossImage {
+    Name(String),
+    Table { name: String },
+}
+
+impl CrossImage {
+    fn name(self) -> String {
+        match self {
+            Self::Name(name) | Self::Table { name } => name,
+        }
+    }
+}
+
+/// Commands, or the path of a script, run before the build
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PreBuild {
+    Commands(Vec<String>),
+    Script(String),
+}
+
+impl PreBuild {
+    fn commands(self) -> Vec<String> {
+        match self {
+            Self::Commands(commands) => commands,
+            Self::Script(path) => vec![format!("sh {path}")],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(contents: &str) -> CrossImport {
+        CrossImport::from_cross(toml::from_str(contents).unwrap())
+    }
+
+    #[test]
+    fn test_import_cross_toml() {
+        let import = import(
+            r#"
+[build]
+default-target = "aarch64-unknown-linux-gnu"
+pre-build = ["dpkg --add-architecture arm64", "apt-get update"]
+
+[build.env]
+passthrough = ["GITHUB_TOKEN"]
+volumes = ["SDK_DIR"]
+
+[target.aarch64-unknown-linux-gnu]
+image = "ghcr.io/example/aarch64:main"
+runner = "qemu-user"
+
+[target.aarch64-unknown-linux-gnu.env]
+passthrough = ["OPENSSL_DIR"]
+
+[target.armv7-unknown-linux-gnueabihf]
+image = { name = "example/armv7:1", toolchain = ["x86_64-unknown-linux-gnu"] }
+pre-build = "./scripts/armv7.sh"
+zig = "2.17"
+"#,
+        );
+        let config = import.config().unwrap();
+        assert_eq!(config.targets.default, ["aarch64-unknown-linux-gnu"]);
+        assert_eq!(config.container.passthrough, ["GITHUB_TOKEN"]);
+        assert_eq!(config.container.pre_build.len(), 2);
+        assert!(!config.container.run_as_user);
+        assert_eq!(
+            config.container.images["aarch64-unknown-linux-gnu"],
+            "ghcr.io/example/aarch64:main"
+        );
+        assert_eq!(
+            config.container.images["armv7-unknown-linux-gnueabihf"],
+            "example/armv7:1"
+        );
+
+        let aarch64 = &config.targets.custom["aarch64-unknown-linux-gnu"];
+        assert_eq!(aarch64.passthrough, ["OPENSSL_DIR"]);
+        let armv7 = &config.targets.custom["armv7-unknown-linux-gnueabihf"];
+        assert_eq!(armv7.pre_build, ["sh ./scripts/armv7.sh"]);
+        assert_eq!(armv7.strategy, Some(crate::build::strategy::Strategy::Zig));
+        assert_eq!(armv7.glibc_version.as_deref(), Some("2.17"));
+
+        assert_eq!(import.skipped.len(), 2, "{:?}", import.skipped);
+        assert!(import.skipped[0].starts_with("build.env.volumes"));
+        assert!(import.skipped[1].starts_with("target.aarch64-unknown-linux-gnu.runner"));
+
+        let toml = import.to_toml().unwrap();
+        let config: Config = toml::from_str(&toml).unwrap();
+        assert_eq!(config.targets.default, ["aarch64-unknown-linux-gnu"]);
+    }
+
+    #[test]
+    fn test_read_locates_errors() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join(CROSS_FILE_NAME);
+        std::fs::write(&path, "[build.env]\npassthrough = \"TOKEN\"\n").unwrap();
+        let problem = CrossImport::read(&path).unwrap_err();
+        assert_eq!(problem.line, Some(2));
+
+        std::fs::write(&path, "").unwrap();
+        let import = CrossImport::read(&path).unwrap();
+        assert_eq!(import.config().unwrap(), Config::default());
+    }
+}
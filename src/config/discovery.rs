@@ -1,10 +1,24 @@
 //! Configuration file discovery
 //!
-//! This module handles finding xcargo.toml files in the filesystem
+//! This module handles finding xcargo.toml files in the filesystem. The
+//! YAML (`xcargo.yaml`, `xcargo.yml`) and JSON (`xcargo.json`) variants
+//! are found too; when several exist in one directory, TOML wins.
+//!
+//! Configuration in Cargo.toml is found too. From lowest precedence to
+//! highest, a project's sources are a Cross.toml, `[workspace.metadata.xcargo]`
+//! in the workspace root, `[package.metadata.xcargo]` in the package's
+//! manifest, and xcargo.toml; all of them merge over the global
+//! configuration.
 
+use super::cross::{CROSS_FILE_NAME, ENV_CROSS_CONFIG};
+use super::source::{manifest_tables, ConfigSource, MANIFEST_FILE_NAME};
 use crate::error::Result;
+use crate::paths;
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Configuration file names, in order of precedence
+pub const CONFIG_FILE_NAMES: &[&str] = &["xcargo.toml", "xcargo.yaml", "xcargo.yml", "xcargo.json"];
 
 /// Configuration file discovery utility
 pub struct ConfigDiscovery;
@@ -28,7 +42,7 @@ impl ConfigDiscovery {
     /// # }
     /// ```
     pub fn find() -> Result<Option<PathBuf>> {
-        Self::find_from(env::current_dir()?)
+        Self::find_from(paths::simplify(&env::current_dir()?))
     }
 
     /// Find xcargo.toml starting from a specific directory
@@ -36,9 +50,7 @@ impl ConfigDiscovery {
         let mut current = start;
 
         loop {
-            let config_path = current.join("xcargo.toml");
-
-            if config_path.exists() && config_path.is_file() {
+            if let Some(config_path) = Self::find_in(&current) {
                 return Ok(Some(config_path));
             }
 
@@ -52,10 +64,69 @@ impl ConfigDiscovery {
         Ok(None)
     }
 
-    /// Check if xcargo.toml exists in the current directory
+    /// Find a configuration file directly in `dir`
+    fn find_in(dir: &Path) -> Option<PathBuf> {
+        CONFIG_FILE_NAMES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file())
+    }
+
+    /// Find every source of the project's configuration from the current
+    /// directory, lowest precedence first
+    pub fn find_sources() -> Result<Vec<ConfigSource>> {
+        Self::find_sources_from(paths::simplify(&env::current_dir()?))
+    }
+
+    /// Find every source of the project's configuration from a specific
+    /// directory, lowest precedence first
+    ///
+    /// The package is the nearest Cargo.toml with a `[package]` table, and
+    /// the workspace root the nearest one from there with `[workspace]`, as
+    /// cargo finds them; manifests that don't parse are passed over. The
+    /// Cross.toml is the one `CROSS_CONFIG` names, or else the one next to
+    /// the workspace root's manifest, as cross finds it.
+    pub fn find_sources_from(start: PathBuf) -> Result<Vec<ConfigSource>> {
+        let mut package = None;
+        let mut workspace = None;
+        let mut root = None;
+        for dir in start.ancestors() {
+            let manifest = dir.join(MANIFEST_FILE_NAME);
+            let Some([(is_package, package_config), (is_workspace, workspace_config)]) =
+                manifest_tables(&manifest)
+            else {
+                continue;
+            };
+            if is_package && package.is_none() {
+                package = Some(package_config.then(|| ConfigSource::Package(manifest.clone())));
+                root = Some(dir.to_path_buf());
+            }
+            if is_workspace {
+                root = Some(dir.to_path_buf());
+                workspace = workspace_config.then_some(ConfigSource::Workspace(manifest));
+                break;
+            }
+        }
+
+        let cross = match env::var_os(ENV_CROSS_CONFIG) {
+            Some(path) => Some(PathBuf::from(path)),
+            None => root.map(|root| root.join(CROSS_FILE_NAME)),
+        };
+        let mut sources: Vec<ConfigSource> = cross
+            .filter(|path| path.is_file())
+            .map(ConfigSource::Cross)
+            .into_iter()
+            .chain(workspace)
+            .chain(package.flatten())
+            .collect();
+        sources.extend(Self::find_from(start)?.map(ConfigSource::File));
+        Ok(sources)
+    }
+
+    /// Check if a configuration file exists in the current directory
     pub fn exists_in_current() -> Result<bool> {
         let current = env::current_dir()?;
-        Ok(current.join("xcargo.toml").exists())
+        Ok(Self::find_in(&current).is_some())
     }
 
     /// Get the default config file path (current directory)
@@ -114,6 +185,76 @@ mod tests {
         assert!(found.is_none());
     }
 
+    #[test]
+    fn test_find_yaml_and_json() {
+        let temp = TempDir::new().unwrap();
+        let yaml_path = temp.path().join("xcargo.yaml");
+        fs::write(&yaml_path, "targets: {}\n").unwrap();
+
+        let found = ConfigDiscovery::find_from(temp.path().to_path_buf()).unwrap();
+        assert_eq!(found.unwrap(), yaml_path);
+
+        // TOML takes precedence over other formats in the same directory
+        let toml_path = temp.path().join("xcargo.toml");
+        fs::write(&toml_path, "[targets]\n").unwrap();
+        fs::write(temp.path().join("xcargo.json"), "{}").unwrap();
+
+        let found = ConfigDiscovery::find_from(temp.path().to_path_buf()).unwrap();
+        assert_eq!(found.unwrap(), toml_path);
+    }
+
+    #[test]
+    fn test_find_sources_in_workspace() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join(MANIFEST_FILE_NAME);
+        fs::write(
+            &root,
+            "[workspace]\nmembers = [\"app\"]\n\n[workspace.metadata.xcargo.build]\nparallel = false\n\n[package]\nname = \"root\"\n\n[package.metadata.xcargo]\naliases = {}\n",
+        )
+        .unwrap();
+        let app = temp.path().join("app");
+        fs::create_dir_all(app.join("src")).unwrap();
+        let member = app.join(MANIFEST_FILE_NAME);
+        fs::write(&member, "[package]\nname = \"app\"\n").unwrap();
+
+        // The root package's own table doesn't apply to members
+        let found = ConfigDiscovery::find_sources_from(app.join("src")).unwrap();
+        assert_eq!(found, [ConfigSource::Workspace(root.clone())]);
+
+        fs::write(
+            &member,
+            "[package]\nname = \"app\"\n\n[package.metadata.xcargo]\naliases = {}\n",
+        )
+        .unwrap();
+        let config = app.join("xcargo.toml");
+        fs::write(&config, "[targets]\n").unwrap();
+        let found = ConfigDiscovery::find_sources_from(app.clone()).unwrap();
+        assert_eq!(
+            found,
+            [
+                ConfigSource::Workspace(root.clone()),
+                ConfigSource::Package(member),
+                ConfigSource::File(config),
+            ]
+        );
+
+        let found = ConfigDiscovery::find_sources_from(temp.path().to_path_buf()).unwrap();
+        assert_eq!(
+            found,
+            [
+                ConfigSource::Workspace(root.clone()),
+                ConfigSource::Package(root),
+            ]
+        );
+
+        // A Cross.toml next to the workspace root goes under everything
+        let cross = temp.path().join(CROSS_FILE_NAME);
+        fs::write(&cross, "[build]\n").unwrap();
+        let found = ConfigDiscovery::find_sources_from(app).unwrap();
+        assert_eq!(found.first(), Some(&ConfigSource::Cross(cross)));
+        assert_eq!(found.len(), 4);
+    }
+
     #[test]
     fn test_default_path() {
         let path = ConfigDiscovery::default_path().unwrap();
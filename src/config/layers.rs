@@ -0,0 +1,320 @@
+//! Layered configuration
+//!
+//! Machine-specific settings (SDK paths, the container runtime, the cache
+//! directory) go in the global configuration, `~/.xcargo/config.toml`, which
+//! sits under the project's xcargo.toml. The files are merged table by
+//! table: whatever the project sets wins, and everything else comes from
+//! the global file. Configuration in Cargo.toml metadata layers the same
+//! way, between the global file and xcargo.toml.
+
+use super::{Config, ConfigDiscovery, ConfigProblem, ConfigSource, KEY_ALIASES};
+use crate::error::Result;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Where an effective setting came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    /// Built-in default
+    Default,
+    /// The global configuration
+    Global(PathBuf),
+    /// The project's configuration file
+    Project(PathBuf),
+    /// `[package.metadata.xcargo]` in the package's Cargo.toml
+    Package(PathBuf),
+    /// `[workspace.metadata.xcargo]` in the workspace root's Cargo.toml
+    Workspace(PathBuf),
+    /// The project's Cross.toml
+    Cross(PathBuf),
+    /// A `[profiles.<name>]` section applied with `--profile`
+    Profile(String),
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::Global(_) => write!(f, "global"),
+            Self::Project(_) => write!(f, "project"),
+            Self::Package(_) => write!(f, "package"),
+            Self::Workspace(_) => write!(f, "workspace"),
+            Self::Cross(_) => write!(f, "cross"),
+            Self::Profile(name) => write!(f, "profile {name}"),
+        }
+    }
+}
+
+/// One effective setting, as `xcargo config --show-origin` lists it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting {
+    /// Dotted key (e.g., `container.runtime`)
+    pub key: String,
+    /// Value, as JSON
+    pub value: String,
+    /// Where the value came from
+    pub origin: Origin,
+}
+
+/// Parse a configuration source into a value tree with canonical key names
+fn read(source: &ConfigSource) -> std::result::Result<Value, ConfigProblem> {
+    let (config, value) = Config::load_with_value(source)?;
+    let parsed = serde_json::to_value(config).map_err(|e| ConfigProblem {
+        path: source.path().to_path_buf(),
+        line: None,
+        column: None,
+        message: format!("Failed to serialize config: {e}"),
+    })?;
+    Ok(canonical(&value, Some(&parsed)))
+}
+
+/// Rename the keys of `value` that serde reads as aliases (see
+/// [`KEY_ALIASES`]) to the names `parsed`, the layer's configuration
+/// serialized, has for them
+///
+/// An alias is only renamed where its field is: `strategy` names the
+/// strategies at the top level, and a target's strategy under `targets`.
+fn canonical(value: &Value, parsed: Option<&Value>) -> Value {
+    let Value::Object(map) = value else {
+        return value.clone();
+    };
+    let fields = parsed.and_then(Value::as_object);
+    map.iter()
+        .map(|(key, child)| {
+            let name = match fields {
+                Some(fields) if !fields.contains_key(key) => KEY_ALIASES
+                    .iter()
+                    .find(|(alias, name)| {
+                        alias == key && fields.contains_key(*name) && !map.contains_key(*name)
+                    })
+                    .map_or(key.as_str(), |(_, name)| name),
+                _ => key,
+            };
+            let child = canonical(child, fields.and_then(|f| f.get(name)));
+            (name.to_string(), child)
+        })
+        .collect::<Map<_, _>>()
+        .into()
+}
+
+/// Merge `over` into `base`, table by table, with `over` winning
+fn merge(base: &mut Value, over: Value) {
+    match (base, over) {
+        (Value::Object(base), Value::Object(over)) => {
+            for (key, value) in over {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, over) => *base = over,
+    }
+}
+
+/// Load configuration sources, each merged over the ones before it
+pub(super) fn load(sources: &[ConfigSource]) -> std::result::Result<Config, ConfigProblem> {
+    let (last, below) = match sources {
+        [] => return Ok(Config::default()),
+        [source] => return Config::load_source(source),
+        [below @ .., last] => (last, below),
+    };
+    let mut value = Value::Object(Map::new());
+    for source in sources {
+        merge(&mut value, read(source)?);
+    }
+    serde_json::from_value(value).map_err(|e| {
+        let below: Vec<String> = below.iter().map(ToString::to_string).collect();
+        ConfigProblem {
+            path: last.path().to_path_buf(),
+            line: None,
+            column: None,
+            message: format!("Failed to merge with {}: {e}", below.join(", ")),
+        }
+    })
+}
+
+/// Dotted keys and values of every scalar and array in `value`
+fn leaves(value: &Value, prefix: &str, out: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let segment = if key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                {
+                    key.clone()
+                } else {
+                    format!("\"{key}\"")
+                };
+                let key = if prefix.is_empty() {
+                    segment
+                } else {
+                    format!("{prefix}.{segment}")
+                };
+                leaves(value, &key, out);
+            }
+        }
+        Value::Null => {}
+        value => out.push((prefix.to_string(), value.clone())),
+    }
+}
+
+/// Keys a configuration source sets
+fn keys(source: &ConfigSource) -> Result<Vec<String>> {
+    let mut out = Vec::new();
+    leaves(&read(source)?, "", &mut out);
+    Ok(out.into_iter().map(|(key, _)| key).collect())
+}
+
+/// Every effective setting, and whether it came from the defaults, the
+/// global configuration, one of the project's sources, or the profile in
+/// `XCARGO_PROFILE`
+pub fn effective_settings() -> Result<Vec<Setting>> {
+    let global = Config::global_file();
+    let sources: Vec<ConfigSource> = global
+        .clone()
+        .map(ConfigSource::File)
+        .into_iter()
+        .chain(ConfigDiscovery::find_sources()?)
+        .collect();
+    let base = load(&sources)?;
+    let mut config = base.clone();
+    config.apply_env_profile()?;
+
+    let serialize = |config: &Config| -> Result<Vec<(String, Value)>> {
+        let value = serde_json::to_value(config)
+            .map_err(|e| crate::error::Error::Config(format!("Failed to serialize config: {e}")))?;
+        let mut out = Vec::new();
+        leaves(&value, "", &mut out);
+        Ok(out)
+    };
+    let before: HashMap<String, Value> = serialize(&base)?.into_iter().collect();
+    // Highest precedence first, so the first layer that sets a key is its origin
+    let mut layers = Vec::new();
+    for source in sources.iter().rev() {
+        let origin = match source {
+            ConfigSource::File(path) if Some(path) == global.as_ref() => {
+                Origin::Global(path.clone())
+            }
+            ConfigSource::File(path) => Origin::Project(path.clone()),
+            ConfigSource::Package(path) => Origin::Package(path.clone()),
+            ConfigSource::Workspace(path) => Origin::Workspace(path.clone()),
+            ConfigSource::Cross(path) => Origin::Cross(path.clone()),
+        };
+        layers.push((keys(source)?, origin));
+    }
+
+    Ok(serialize(&config)?
+        .into_iter()
+        .map(|(key, value)| {
+            let origin = match &config.profile {
+                Some(profile) if before.get(&key) != Some(&value) => {
+                    Origin::Profile(profile.clone())
+                }
+                _ => layers
+                    .iter()
+                    .find(|(keys, _)| keys.contains(&key))
+                    .map_or(Origin::Default, |(_, origin)| origin.clone()),
+            };
+            Setting {
+                key,
+                value: value.to_string(),
+                origin,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge() {
+        let mut base = json!({
+            "container": { "runtime": "podman", "registry": "mirror.example" },
+            "targets": { "default": ["a", "b"] },
+        });
+        merge(
+            &mut base,
+            json!({
+                "container": { "runtime": "docker" },
+                "targets": { "default": ["c"] },
+            }),
+        );
+        assert_eq!(
+            base,
+            json!({
+                "container": { "runtime": "docker", "registry": "mirror.example" },
+                "targets": { "default": ["c"] },
+            })
+        );
+    }
+
+    #[test]
+    fn test_canonical_keys() {
+        let layer = json!({
+            "strategy": { "nix": { "enable": false } },
+            "targets": { "x86_64-unknown-linux-gnu": { "min-glibc": "2.17", "strategy": "zig" } },
+            "build": { "cache": true },
+            "notifications": { "min-duration": 300, "on-success": false },
+        });
+        let config: Config = serde_json::from_value(layer.clone()).unwrap();
+        let parsed = serde_json::to_value(config).unwrap();
+        let value = canonical(&layer, Some(&parsed));
+        assert_eq!(value["strategies"]["nix"]["enable"], json!(false));
+        let target = &value["targets"]["x86_64-unknown-linux-gnu"];
+        assert_eq!(target["min_glibc"], json!("2.17"));
+        assert_eq!(target["strategy"], json!("zig"));
+        assert_eq!(value["build"], json!({ "cache": true }));
+        assert_eq!(
+            value["notifications"],
+            json!({ "min_duration": 300, "on_success": false })
+        );
+    }
+
+    #[test]
+    fn test_load_layers_project_over_global() {
+        let temp = tempfile::tempdir().unwrap();
+        let global = temp.path().join("config.toml");
+        std::fs::write(
+            &global,
+            "[container]\nruntime = \"podman\"\n\n[build]\ncache = false\n\n[artifacts.android]\nmin-sdk = 21\ndir = \"jni\"\n",
+        )
+        .unwrap();
+        let project = temp.path().join("xcargo.yaml");
+        std::fs::write(
+            &project,
+            "targets:\n  default: [aarch64-linux-android]\nartifacts:\n  android:\n    min_sdk: 24\nbuild:\n  cache: true\n",
+        )
+        .unwrap();
+
+        let sources = [
+            ConfigSource::File(global.clone()),
+            ConfigSource::File(project),
+        ];
+        let config = load(&sources).unwrap();
+        assert_eq!(config.container.runtime, "podman");
+        assert!(config.build.cache);
+        assert_eq!(config.targets.default, ["aarch64-linux-android"]);
+        assert_eq!(config.artifacts.android.min_sdk, 24);
+        assert_eq!(config.artifacts.android.dir.as_deref(), Some("jni"));
+
+        let keys = keys(&sources[0]).unwrap();
+        assert!(
+            keys.contains(&"artifacts.android.min_sdk".to_string()),
+            "{keys:?}"
+        );
+
+        std::fs::write(&global, "[build]\ncache = \"no\"\n").unwrap();
+        let problem = load(&sources).unwrap_err();
+        assert_eq!(problem.path, global);
+        assert_eq!(problem.line, Some(2));
+    }
+}
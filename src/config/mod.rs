@@ -2,17 +2,170 @@
 //!
 //! This module handles parsing and managing xcargo.toml configuration files.
 
+use crate::build::embedded;
+use crate::build::glibc::GlibcVersion;
+use crate::build::hosts::HostKind;
+use crate::build::strategy::Strategy;
+use crate::cancel;
 use crate::error::{Error, Result};
+use crate::plugin::notify::WebhookFormat;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
+mod cargo;
+mod cross;
 mod discovery;
+mod layers;
+mod source;
+mod validate;
+
+pub use cargo::{CargoSetting, CargoTargetConfig, TargetSetting, CARGO_CONFIG_FILE_NAMES};
+pub use cross::{CrossImport, CROSS_FILE_NAME, ENV_CROSS_CONFIG};
+pub use discovery::{ConfigDiscovery, CONFIG_FILE_NAMES};
+pub use layers::{effective_settings, Origin, Setting};
+pub use source::{ConfigSource, MANIFEST_FILE_NAME};
+pub use validate::{check, Violation};
+
+/// Configuration file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// TOML (`xcargo.toml`), the default
+    Toml,
+    /// YAML (`xcargo.yaml` or `xcargo.yml`)
+    Yaml,
+    /// JSON (`xcargo.json`)
+    Json,
+}
+
+impl ConfigFormat {
+    /// Format for a file, by extension; anything unknown is TOML
+    #[must_use]
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml" | "yml") => Self::Yaml,
+            Some("json") => Self::Json,
+            _ => Self::Toml,
+        }
+    }
+
+    /// Default file name for this format
+    #[must_use]
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            Self::Toml => "xcargo.toml",
+            Self::Yaml => "xcargo.yaml",
+            Self::Json => "xcargo.json",
+        }
+    }
+
+    /// Parse a format name ("toml", "yaml", "yml" or "json")
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A configuration file that could not be loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigProblem {
+    /// The file
+    pub path: PathBuf,
+    /// Line of the error (1-based), when the parser reports it
+    pub line: Option<usize>,
+    /// Column of the error (1-based), when the parser reports it
+    pub column: Option<usize>,
+    /// What is wrong
+    pub message: String,
+}
+
+impl ConfigProblem {
+    /// `path:line:column`, as far as known
+    #[must_use]
+    pub fn location(&self) -> String {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => format!("{}:{line}:{column}", self.path.display()),
+            (Some(line), None) => format!("{}:{line}", self.path.display()),
+            _ => self.path.display().to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.location(), self.message)
+    }
+}
+
+impl From<ConfigProblem> for Error {
+    fn from(problem: ConfigProblem) -> Self {
+        Error::Config(problem.to_string())
+    }
+}
+
+/// 1-based line and column of byte `offset` in `text`
+fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
 
-pub use discovery::ConfigDiscovery;
+/// Parse configuration file contents into a value tree, without
+/// interpreting it
+fn parse_value(
+    contents: &str,
+    format: ConfigFormat,
+) -> std::result::Result<serde_json::Value, String> {
+    match format {
+        ConfigFormat::Toml => toml::from_str(contents).map_err(|e| e.to_string()),
+        ConfigFormat::Yaml if contents.trim().is_empty() => {
+            Ok(serde_json::Value::Object(serde_json::Map::new()))
+        }
+        ConfigFormat::Yaml => serde_yaml::from_str(contents).map_err(|e| e.to_string()),
+        ConfigFormat::Json => serde_json::from_str(contents).map_err(|e| e.to_string()),
+    }
+}
+
+/// Other names serde reads fields under, from the `alias` attributes
+/// below, each with the field's name
+///
+/// Configuration layers rename the aliases so that a setting has one key
+/// whichever way a layer spells it.
+pub(crate) const KEY_ALIASES: &[(&str, &str)] = &[
+    ("strategy", "strategies"),
+    ("no-default-features", "no_default_features"),
+    ("only-on-host", "only_on_host"),
+    ("skip-on-ci", "skip_on_ci"),
+    ("min-glibc", "min_glibc"),
+    ("glibc-version", "glibc_version"),
+    ("test-kinds", "test_kinds"),
+    ("pre-build", "pre_build"),
+    ("min-sdk", "min_sdk"),
+    ("require-16k-pages", "require_16k_pages"),
+    ("uf2-family", "uf2_family"),
+    ("bindgen-target", "bindgen_target"),
+    ("bindgen-args", "bindgen_args"),
+    ("auto-install-targets", "auto_install_targets"),
+    ("catalog-url", "catalog_url"),
+    ("ca-bundle", "ca_bundle"),
+    ("min-duration", "min_duration"),
+    ("on-success", "on_success"),
+    ("on-failure", "on_failure"),
+    ("after-build", "after_build"),
+    ("allowed-libraries", "allowed_libraries"),
+];
 
 /// Main configuration structure for xcargo.toml
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 #[serde(deny_unknown_fields)]
 #[derive(Default)]
 pub struct Config {
@@ -31,10 +184,71 @@ pub struct Config {
     /// Custom profiles for different build scenarios
     #[serde(default)]
     pub profiles: HashMap<String, ProfileConfig>,
+
+    /// Native dependency resolution settings
+    #[serde(default)]
+    pub deps: DepsConfig,
+
+    /// Compiler cache settings
+    #[serde(default)]
+    pub cache: CacheConfig,
+
+    /// Artifact output directory settings
+    #[serde(default)]
+    pub artifacts: ArtifactsConfig,
+
+    /// Signing and notarization settings
+    #[serde(default)]
+    pub sign: SignConfig,
+
+    /// Post-build binary verification settings
+    #[serde(default)]
+    pub verify: VerifyConfig,
+
+    /// Rustup toolchain settings
+    #[serde(default)]
+    pub toolchain: ToolchainConfig,
+
+    /// Proxy and CA settings for xcargo's downloads and container builds
+    #[serde(default)]
+    pub network: NetworkConfig,
+
+    /// Desktop and webhook notifications when multi-target builds finish
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+
+    /// Firmware images and flashing for embedded targets
+    #[serde(default)]
+    pub embedded: EmbeddedConfig,
+
+    /// Container images made by `xcargo docker build`
+    #[serde(default)]
+    pub docker: DockerConfig,
+
+    /// Where plugin strategies and Nix go in the fallback chain
+    /// (`[strategies.<name>]`)
+    #[serde(default, alias = "strategy")]
+    pub strategies: HashMap<String, StrategyConfig>,
+
+    /// Hosts parallel multi-target builds are spread across (`[[hosts]]`)
+    #[serde(default)]
+    pub hosts: Vec<HostConfig>,
+
+    /// Named target groups for `--group` (e.g., `mobile = ["aarch64-linux-android", ...]`)
+    #[serde(default, rename = "target-groups")]
+    pub target_groups: HashMap<String, Vec<String>>,
+
+    /// User-defined target aliases (e.g., `rpi = "aarch64-unknown-linux-gnu"`)
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+
+    /// Name of the profile applied with [`Config::apply_profile`]
+    #[serde(skip)]
+    pub profile: Option<String>,
 }
 
 /// Target configuration section
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
 pub struct TargetsConfig {
     /// Default targets to build when no target is specified
     #[serde(default)]
@@ -45,8 +259,17 @@ pub struct TargetsConfig {
     pub custom: HashMap<String, TargetCustomConfig>,
 }
 
+/// Environment variable naming the profile to apply, as `--profile` does
+pub const ENV_PROFILE: &str = "XCARGO_PROFILE";
+
+/// Kinds of cargo targets `[targets.<triple>] kinds` may list
+pub const TARGET_KINDS: &[&str] = &["lib", "bin", "example", "test", "bench"];
+
+/// Kinds of cargo targets `[targets.<triple>] test_kinds` may list
+pub const TEST_KINDS: &[&str] = &["lib", "bin", "example", "test", "bench", "doc"];
+
 /// Custom configuration for a specific target
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
 pub struct TargetCustomConfig {
     /// Custom linker to use for this target
     pub linker: Option<String>,
@@ -54,16 +277,111 @@ pub struct TargetCustomConfig {
     /// Force container build for this target
     pub force_container: Option<bool>,
 
+    /// Build strategy, skipping the native → zig → container fallback chain
+    #[serde(default)]
+    pub strategy: Option<Strategy>,
+
     /// Additional environment variables
     #[serde(default)]
     pub env: HashMap<String, String>,
 
     /// Additional rustflags
     pub rustflags: Option<Vec<String>>,
+
+    /// Cargo features to enable for this target
+    #[serde(default)]
+    pub features: Vec<String>,
+
+    /// Disable default features for this target
+    #[serde(default, alias = "no-default-features")]
+    pub no_default_features: bool,
+
+    /// Only build with `--all`/`--group` on these host OSes (e.g., `["macos"]`)
+    #[serde(default, alias = "only-on-host")]
+    pub only_on_host: Vec<String>,
+
+    /// Skip with `--all`/`--group` when running on CI
+    #[serde(default, alias = "skip-on-ci")]
+    pub skip_on_ci: bool,
+
+    /// Newest glibc the binaries may require (e.g., "2.17"), for `*-linux-gnu`
+    #[serde(default, alias = "min-glibc")]
+    pub min_glibc: Option<String>,
+
+    /// glibc version Zig links against (e.g., "2.17"); defaults to `min_glibc`
+    #[serde(default, alias = "glibc-version")]
+    pub glibc_version: Option<String>,
+
+    /// Kinds of cargo targets build, check and clippy compile for this
+    /// target (e.g., `["bin", "example"]`); cargo's defaults if empty
+    #[serde(default)]
+    pub kinds: Vec<String>,
+
+    /// Kinds of cargo targets test and bench run for this target (e.g.,
+    /// `["lib"]` for smoke tests under emulation); everything if empty
+    #[serde(default, alias = "test-kinds")]
+    pub test_kinds: Vec<String>,
+
+    /// iOS simulator (name or UDID) tests run on, for `*-apple-ios-sim`
+    /// and `x86_64-apple-ios`; a booted one or an iPhone if unset
+    #[serde(default)]
+    pub simulator: Option<String>,
+
+    /// Link binaries statically, like `--static`
+    #[serde(default, rename = "static")]
+    pub static_link: bool,
+
+    /// Chip `xcargo flash` passes as `{chip}` (e.g., `STM32F407VGTx`)
+    #[serde(default)]
+    pub chip: Option<String>,
+
+    /// Host environment variables container builds of this target get too,
+    /// besides `[container] passthrough`
+    #[serde(default)]
+    pub passthrough: Vec<String>,
+
+    /// Shell commands container builds of this target run before cargo,
+    /// instead of `[container] pre_build`
+    #[serde(default, alias = "pre-build")]
+    pub pre_build: Vec<String>,
+}
+
+impl TargetCustomConfig {
+    /// Why this target is excluded from multi-target builds on `host_os`, if it is
+    #[must_use]
+    pub fn exclusion_reason(&self, host_os: &str, on_ci: bool) -> Option<String> {
+        if self.skip_on_ci && on_ci {
+            return Some("skip_on_ci is set".to_string());
+        }
+
+        let host_os = normalize_os(host_os);
+        if !self.only_on_host.is_empty()
+            && !self
+                .only_on_host
+                .iter()
+                .any(|os| normalize_os(os) == host_os)
+        {
+            return Some(format!(
+                "only built on {} hosts",
+                self.only_on_host.join(", ")
+            ));
+        }
+
+        None
+    }
+}
+
+/// Treat `macos`, `darwin` and `osx` as the same host OS
+fn normalize_os(os: &str) -> String {
+    match os.to_lowercase().as_str() {
+        "macos" | "darwin" | "osx" => "darwin".to_string(),
+        other => other.to_string(),
+    }
 }
 
 /// Build configuration section
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct BuildConfig {
     /// Enable parallel builds for multiple targets
     #[serde(default = "default_true")]
@@ -83,12 +401,36 @@ pub struct BuildConfig {
     /// Additional cargo flags
     #[serde(default)]
     pub cargo_flags: Vec<String>,
+
+    /// Times to retry a failed target in multi-target builds
+    #[serde(default)]
+    pub retries: u32,
+
+    /// Seconds to wait before the first retry, doubled for each one after
+    #[serde(default = "default_retry_delay")]
+    pub retry_delay: u64,
+
+    /// Longest a single target may build (e.g., "15m", "1h30m"); unlimited if unset
+    #[serde(default)]
+    pub timeout: Option<String>,
+
+    /// Build without network access, as with `--offline`
+    #[serde(default)]
+    pub offline: bool,
+
+    /// Build strategy for targets without their own, skipping the fallback chain
+    #[serde(default)]
+    pub strategy: Option<Strategy>,
+
+    /// Environment variables set for every target, before per-target ones
+    #[serde(default)]
+    pub env: HashMap<String, String>,
 }
 
 /// Container runtime configuration
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub struct ContainerConfig {
-    /// Container runtime to use: auto, youki, docker, podman
+    /// Container runtime to use: auto, youki, docker, podman, nerdctl
     #[serde(default = "default_runtime")]
     pub runtime: String,
 
@@ -97,23 +439,447 @@ pub struct ContainerConfig {
     #[serde(default = "default_use_when")]
     pub use_when: String,
 
-    /// Custom container image registry
+    /// Registry mirror build images come from instead of ghcr.io/cross-rs,
+    /// as `host[/path]` (e.g., "registry.corp.example/cross-rs")
     pub registry: Option<String>,
 
     /// Image pull policy: always, never, if-not-present
     #[serde(default = "default_pull_policy")]
     pub pull_policy: String,
+
+    /// Run builds as the invoking user rather than root, so `target/` and
+    /// the cargo home don't fill with root-owned files
+    #[serde(default = "default_true")]
+    pub run_as_user: bool,
+
+    /// Build image per target triple, replacing the cross-rs default; pin
+    /// one with a digest (`<image>:<tag>@sha256:<digest>`), as
+    /// `xcargo container update-images` records them
+    #[serde(default)]
+    pub images: HashMap<String, String>,
+
+    /// Host environment variables passed on to container builds, by name
+    /// (e.g., `["GITHUB_TOKEN"]`); unset ones are skipped
+    #[serde(default)]
+    pub passthrough: Vec<String>,
+
+    /// Shell commands run in the container before cargo (e.g.,
+    /// `["apt-get update", "apt-get install -y libssl-dev:arm64"]`), as the
+    /// build user; set `run_as_user = false` for ones that need root
+    #[serde(default, alias = "pre-build")]
+    pub pre_build: Vec<String>,
+}
+
+/// Compiler cache configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct CacheConfig {
+    /// `RUSTC_WRAPPER` to use (e.g., "sccache"); "none" disables detection
+    pub compiler_wrapper: Option<String>,
+}
+
+/// Artifact output directory configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct ArtifactsConfig {
+    /// Directory final binaries are placed in, as `<dir>/<triple>/<profile>/`
+    pub dir: Option<String>,
+
+    /// How files are placed: "copy" or "hardlink"
+    #[serde(default = "default_artifact_mode")]
+    pub mode: String,
+
+    /// Checks every artifact must pass before it is published
+    #[serde(default)]
+    pub gates: Vec<GateConfig>,
+
+    /// Export of Android libraries for Gradle and `CMakeLists.txt` consumers
+    #[serde(default)]
+    pub android: AndroidExportConfig,
+
+    /// wasm-bindgen and wasm-opt run on WebAssembly builds
+    #[serde(default)]
+    pub wasm: WasmExportConfig,
+
+    /// Xcode xcframeworks of static libraries for Xcode projects
+    #[serde(default)]
+    pub apple: AppleExportConfig,
+}
+
+/// Android consumer export (`[artifacts.android]`)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct AndroidExportConfig {
+    /// Directory cdylibs built for Android are laid out in as
+    /// `jniLibs/<abi>/lib*.so`; no export if unset
+    pub dir: Option<String>,
+
+    /// Also package the libraries as an `.aar` (android-aar feature)
+    #[serde(default)]
+    pub aar: bool,
+
+    /// Java package named in the AAR's `AndroidManifest.xml`
+    pub package: Option<String>,
+
+    /// Lowest Android API level the libraries support (`minSdkVersion`)
+    #[serde(default = "default_min_sdk", alias = "min-sdk")]
+    pub min_sdk: u32,
+
+    /// Fail the build when a 64-bit library is not aligned to 16 KB pages,
+    /// instead of warning
+    #[serde(default, alias = "require-16k-pages")]
+    pub require_16k_pages: bool,
+}
+
+/// Xcode consumer export (`[artifacts.apple]`)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct AppleExportConfig {
+    /// Directory static libraries built for iOS and macOS are packaged
+    /// into `<Name>.xcframework` in; no export if unset
+    pub dir: Option<String>,
+
+    /// Directory of C headers for the library (e.g., cbindgen's output)
+    pub headers: Option<String>,
+
+    /// Module name Swift imports; from the library name if unset
+    pub name: Option<String>,
+}
+
+/// Firmware images and flashing for embedded targets (`[embedded]`)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct EmbeddedConfig {
+    /// Images written next to each binary of a `thumbv*`/`riscv*` build:
+    /// "bin", "hex" and "uf2"
+    #[serde(default)]
+    pub formats: Vec<String>,
+
+    /// UF2 family: a name (rp2040, samd21, ...) or an ID like "0xe48bff56"
+    #[serde(default, alias = "uf2-family")]
+    pub uf2_family: Option<String>,
+
+    /// Command `xcargo flash` runs; `{elf}`, `{bin}`, `{hex}`, `{uf2}`,
+    /// `{chip}` and `{target}` are replaced
+    #[serde(default = "default_flash")]
+    pub flash: Vec<String>,
+}
+
+/// Container images of built binaries (`[docker]`)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct DockerConfig {
+    /// Image the binary is copied onto, e.g.
+    /// "gcr.io/distroless/cc-debian12" for binaries linked against glibc
+    #[serde(default = "default_docker_base")]
+    pub base: String,
+
+    /// Tags the image gets; `{name}`, `{version}` and `{git_sha}` are
+    /// replaced
+    #[serde(default = "default_docker_tags")]
+    pub tags: Vec<String>,
+
+    /// Binary to package, when the workspace builds several
+    #[serde(default)]
+    pub bin: Option<String>,
+}
+
+/// WebAssembly post-build pipeline (`[artifacts.wasm]`)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct WasmExportConfig {
+    /// Directory `.wasm` files and their JavaScript bindings are written
+    /// to; no pipeline if unset
+    pub dir: Option<String>,
+
+    /// Generate JavaScript bindings with wasm-bindgen (wasm32-unknown-unknown)
+    #[serde(default = "default_true")]
+    pub bindgen: bool,
+
+    /// What the bindings are for, as wasm-bindgen's `--target`: web,
+    /// bundler, nodejs, no-modules or deno
+    #[serde(default = "default_bindgen_target", alias = "bindgen-target")]
+    pub bindgen_target: String,
+
+    /// Further wasm-bindgen arguments, e.g. `["--no-typescript"]`
+    #[serde(default, alias = "bindgen-args")]
+    pub bindgen_args: Vec<String>,
+
+    /// wasm-opt arguments for release builds; empty skips wasm-opt
+    #[serde(default = "default_wasm_opt")]
+    pub opt: Vec<String>,
 }
 
-/// Profile configuration for different build scenarios
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// A command an artifact must pass before it is published
+/// (`[[artifacts.gates]]`), e.g. a malware scanner
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct GateConfig {
+    /// Name the result is recorded under in the artifact manifest
+    pub name: String,
+
+    /// Program and arguments; `{artifact}`, `{target}` and `{profile}` are
+    /// replaced with the artifact's path, target triple and profile
+    pub command: Vec<String>,
+}
+
+/// Signing configuration section
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct SignConfig {
+    /// Apple notarization settings
+    #[serde(default)]
+    pub notarize: NotarizeConfig,
+}
+
+/// Toolchain configuration section
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct ToolchainConfig {
+    /// Add missing rustup targets without asking first
+    #[serde(default, alias = "auto-install-targets")]
+    pub auto_install_targets: bool,
+
+    /// Where `xcargo toolchain provision` fetches its catalog of prebuilt
+    /// cross toolchains from (default: the copy in xcargo's repository)
+    #[serde(default, alias = "catalog-url")]
+    pub catalog_url: Option<String>,
+}
+
+/// Network configuration section
+///
+/// Applies to what xcargo downloads itself (standalone toolchains, the
+/// toolchain catalog, prebuilt dependencies) and to container builds.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct NetworkConfig {
+    /// Proxy URL (e.g., `http://proxy.corp:3128`), instead of the one in
+    /// `HTTPS_PROXY` and friends
+    pub proxy: Option<String>,
+
+    /// PEM file of CA certificates trusted instead of the system's, for
+    /// proxies that intercept TLS; it needs the public roots too
+    #[serde(alias = "ca-bundle")]
+    pub ca_bundle: Option<PathBuf>,
+
+    /// Host environment variables passed on to container builds, by name;
+    /// the proxy variables unless set
+    #[serde(default = "default_network_passthrough")]
+    pub passthrough: Vec<String>,
+}
+
+/// Notifications when a multi-target build finishes (`[notifications]`)
+///
+/// Off unless `desktop` is set or a webhook is listed.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct NotificationsConfig {
+    /// Show a desktop notification (notify-send on Linux, Notification
+    /// Center on macOS, a tray balloon on Windows)
+    #[serde(default)]
+    pub desktop: bool,
+
+    /// Webhooks to post the result to (`[[notifications.webhooks]]`)
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+
+    /// Only notify about builds that took at least this many seconds
+    #[serde(default = "default_notify_after", alias = "min-duration")]
+    pub min_duration: u64,
+
+    /// Notify when every target succeeded
+    #[serde(default = "default_true", alias = "on-success")]
+    pub on_success: bool,
+
+    /// Notify when a target failed or the build was cancelled
+    #[serde(default = "default_true", alias = "on-failure")]
+    pub on_failure: bool,
+}
+
+impl NotificationsConfig {
+    /// Whether anything is notified
+    #[must_use]
+    pub fn enabled(&self) -> bool {
+        self.desktop || !self.webhooks.is_empty()
+    }
+}
+
+/// A webhook notified when builds finish
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct WebhookConfig {
+    /// URL the result is posted to
+    pub url: String,
+
+    /// Payload: slack, discord or json; guessed from the URL when unset
+    pub format: Option<WebhookFormat>,
+}
+
+/// A plugin strategy's or Nix's place in the chain (`[strategies.<name>]`)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct StrategyConfig {
+    /// Higher is tried first; nix, native, zig and container have 35, 30,
+    /// 20 and 10, plugin strategies 0
+    pub priority: Option<i32>,
+
+    /// Use the strategy even without its usual trigger (e.g., `nix` without
+    /// a `flake.nix`), or never with `false`
+    pub enable: Option<bool>,
+
+    /// Target patterns the strategy applies to (`*` wildcards, empty = all)
+    #[serde(default)]
+    pub targets: Vec<String>,
+}
+
+/// A build host in the pool (`[[hosts]]`)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct HostConfig {
+    /// Name shown in progress and reports
+    pub name: String,
+
+    /// Where builds run: local, ssh or container
+    #[serde(default)]
+    pub kind: HostKind,
+
+    /// SSH destination, e.g. "ci@mac-mini.local" (ssh hosts)
+    pub address: Option<String>,
+
+    /// Project checkout on an ssh host (defaults to this project's path)
+    pub dir: Option<String>,
+
+    /// Target patterns the host builds (`*` wildcards, empty = all)
+    #[serde(default)]
+    pub targets: Vec<String>,
+
+    /// Builds the host runs at once
+    #[serde(default = "default_host_jobs")]
+    pub jobs: usize,
+}
+
+fn default_host_jobs() -> usize {
+    1
+}
+
+/// Binary verification configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct VerifyConfig {
+    /// Verify binaries after every build (as `xcargo verify` does)
+    #[serde(default, alias = "after-build")]
+    pub after_build: bool,
+
+    /// Non-system shared libraries binaries may depend on, matched by file
+    /// name prefix (e.g., "libssl")
+    #[serde(default, alias = "allowed-libraries")]
+    pub allowed_libraries: Vec<String>,
+}
+
+/// Apple notarization configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct NotarizeConfig {
+    /// Keychain profile from `xcrun notarytool store-credentials`
+    /// (None = use credentials from the environment)
+    pub keychain_profile: Option<String>,
+
+    /// Seconds to wait for Apple's verdict
+    #[serde(default = "default_notarize_timeout")]
+    pub timeout: u64,
+
+    /// Seconds between status checks
+    #[serde(default = "default_notarize_poll_interval")]
+    pub poll_interval: u64,
+
+    /// Staple the ticket to `.app`, `.pkg` and `.dmg` artifacts
+    #[serde(default = "default_true")]
+    pub staple: bool,
+}
+
+/// Native dependency resolution configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct DepsConfig {
+    /// OpenSSL resolution settings
+    #[serde(default)]
+    pub openssl: OpenSslConfig,
+
+    /// bindgen / libclang settings
+    #[serde(default)]
+    pub bindgen: BindgenConfig,
+
+    /// Apply built-in presets for other native dependencies
+    #[serde(default = "default_true")]
+    pub presets: bool,
+
+    /// Presets to skip even when their dependency is detected
+    #[serde(default)]
+    pub skip: Vec<String>,
+
+    /// User-defined rules applied when a crate is in the dependency graph
+    #[serde(default)]
+    pub rules: Vec<DepRuleConfig>,
+}
+
+/// A user-defined dependency rule (`[[deps.rules]]`)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct DepRuleConfig {
+    /// Crate whose presence in Cargo.lock triggers the rule
+    #[serde(rename = "crate")]
+    pub krate: String,
+
+    /// Target patterns the rule applies to (`*` wildcards, empty = all)
+    #[serde(default)]
+    pub targets: Vec<String>,
+
+    /// Environment variables to set
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+
+    /// Cargo features to enable
+    #[serde(default)]
+    pub features: Vec<String>,
+
+    /// Explanation shown when the rule applies
+    pub reason: Option<String>,
+}
+
+/// OpenSSL resolution configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct OpenSslConfig {
+    /// Strategy: auto, vendored, sysroot, precompiled
+    #[serde(default = "default_auto")]
+    pub strategy: String,
+
+    /// OpenSSL installation directory used for sysroot builds
+    pub dir: Option<String>,
+
+    /// URL of a precompiled bundle (`{target}` is replaced with the triple)
+    pub precompiled_url: Option<String>,
+
+    /// Per-target strategy overrides
+    #[serde(default)]
+    pub targets: HashMap<String, String>,
+}
+
+/// bindgen configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct BindgenConfig {
+    /// Sysroot passed to clang for cross targets
+    pub sysroot: Option<String>,
+
+    /// Extra clang arguments for all cross targets
+    #[serde(default)]
+    pub clang_args: Vec<String>,
+}
+
+/// Profile configuration for different build scenarios (`[profiles.<name>]`),
+/// selected with `--profile` or `XCARGO_PROFILE`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
 pub struct ProfileConfig {
-    /// Targets to build in this profile
+    /// Profile whose settings this one starts from
+    pub inherits: Option<String>,
+
+    /// Targets to build in this profile, replacing `[targets] default`
+    #[serde(default)]
     pub targets: Vec<String>,
 
-    /// Build configuration overrides
-    #[serde(flatten)]
-    pub build: Option<BuildConfig>,
+    /// Build strategy for targets without their own
+    pub strategy: Option<Strategy>,
+
+    /// Enable parallel builds for multiple targets
+    pub parallel: Option<bool>,
+
+    /// Number of parallel jobs
+    pub jobs: Option<usize>,
+
+    /// Environment variables set for every target
+    #[serde(default)]
+    pub env: HashMap<String, String>,
 }
 
 impl Default for BuildConfig {
@@ -124,6 +890,103 @@ impl Default for BuildConfig {
             cache: true,
             force_container: false,
             cargo_flags: Vec::new(),
+            retries: 0,
+            retry_delay: default_retry_delay(),
+            timeout: None,
+            offline: false,
+            strategy: None,
+            env: HashMap::new(),
+        }
+    }
+}
+
+impl Default for DepsConfig {
+    fn default() -> Self {
+        Self {
+            openssl: OpenSslConfig::default(),
+            bindgen: BindgenConfig::default(),
+            presets: true,
+            skip: Vec::new(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl Default for ArtifactsConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            mode: default_artifact_mode(),
+            gates: Vec::new(),
+            android: AndroidExportConfig::default(),
+            wasm: WasmExportConfig::default(),
+            apple: AppleExportConfig::default(),
+        }
+    }
+}
+
+impl Default for EmbeddedConfig {
+    fn default() -> Self {
+        Self {
+            formats: Vec::new(),
+            uf2_family: None,
+            flash: default_flash(),
+        }
+    }
+}
+
+impl Default for DockerConfig {
+    fn default() -> Self {
+        Self {
+            base: default_docker_base(),
+            tags: default_docker_tags(),
+            bin: None,
+        }
+    }
+}
+
+impl Default for WasmExportConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            bindgen: true,
+            bindgen_target: default_bindgen_target(),
+            bindgen_args: Vec::new(),
+            opt: default_wasm_opt(),
+        }
+    }
+}
+
+impl Default for AndroidExportConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            aar: false,
+            package: None,
+            min_sdk: default_min_sdk(),
+            require_16k_pages: false,
+        }
+    }
+}
+
+impl Default for NotarizeConfig {
+    fn default() -> Self {
+        Self {
+            keychain_profile: None,
+            timeout: default_notarize_timeout(),
+            poll_interval: default_notarize_poll_interval(),
+            staple: true,
+        }
+    }
+}
+
+impl Default for OpenSslConfig {
+    fn default() -> Self {
+        Self {
+            strategy: default_auto(),
+            dir: None,
+            precompiled_url: None,
+            targets: HashMap::new(),
         }
     }
 }
@@ -135,6 +998,32 @@ impl Default for ContainerConfig {
             use_when: default_use_when(),
             registry: None,
             pull_policy: default_pull_policy(),
+            run_as_user: true,
+            images: HashMap::new(),
+            passthrough: Vec::new(),
+            pre_build: Vec::new(),
+        }
+    }
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            ca_bundle: None,
+            passthrough: default_network_passthrough(),
+        }
+    }
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            desktop: false,
+            webhooks: Vec::new(),
+            min_duration: default_notify_after(),
+            on_success: true,
+            on_failure: true,
         }
     }
 }
@@ -144,6 +1033,30 @@ fn default_true() -> bool {
     true
 }
 
+fn default_notify_after() -> u64 {
+    60
+}
+
+fn default_network_passthrough() -> Vec<String> {
+    [
+        "HTTPS_PROXY",
+        "https_proxy",
+        "HTTP_PROXY",
+        "http_proxy",
+        "ALL_PROXY",
+        "all_proxy",
+        "NO_PROXY",
+        "no_proxy",
+    ]
+    .iter()
+    .map(ToString::to_string)
+    .collect()
+}
+
+fn default_auto() -> String {
+    "auto".to_string()
+}
+
 fn default_runtime() -> String {
     "auto".to_string()
 }
@@ -156,6 +1069,52 @@ fn default_pull_policy() -> String {
     "if-not-present".to_string()
 }
 
+/// Upper bound for `build.retries`
+pub const MAX_RETRIES: u32 = 10;
+
+fn default_retry_delay() -> u64 {
+    5
+}
+
+fn default_artifact_mode() -> String {
+    "copy".to_string()
+}
+
+fn default_min_sdk() -> u32 {
+    21
+}
+
+fn default_docker_base() -> String {
+    "scratch".to_string()
+}
+
+fn default_docker_tags() -> Vec<String> {
+    vec!["{name}:{version}".to_string()]
+}
+
+fn default_flash() -> Vec<String> {
+    embedded::DEFAULT_FLASH
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn default_bindgen_target() -> String {
+    "web".to_string()
+}
+
+fn default_wasm_opt() -> Vec<String> {
+    vec!["-O".to_string()]
+}
+
+fn default_notarize_timeout() -> u64 {
+    1800
+}
+
+fn default_notarize_poll_interval() -> u64 {
+    30
+}
+
 impl Config {
     /// Load configuration from a TOML file
     ///
@@ -171,10 +1130,67 @@ impl Config {
     /// # }
     /// ```
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let contents = std::fs::read_to_string(path.as_ref())
-            .map_err(|e| Error::Config(format!("Failed to read config file: {e}")))?;
+        Self::load(path.as_ref()).map_err(Error::from)
+    }
+
+    /// Load configuration from a file, locating what is wrong if it cannot
+    /// be read or parsed, or sets keys nothing reads
+    pub fn load(path: &Path) -> std::result::Result<Self, ConfigProblem> {
+        Self::load_source(&ConfigSource::File(path.to_path_buf()))
+    }
+
+    /// Load configuration from a source, like [`Config::load`]
+    pub fn load_source(source: &ConfigSource) -> std::result::Result<Self, ConfigProblem> {
+        Self::load_with_value(source).map(|(config, _)| config)
+    }
+
+    /// Load configuration like [`Config::load_source`], also as a value tree
+    fn load_with_value(
+        source: &ConfigSource,
+    ) -> std::result::Result<(Self, serde_json::Value), ConfigProblem> {
+        let (config, value) = source.parse()?;
+        match validate::unknown_keys(&value).first() {
+            Some(violation) => Err(validate::locate_violation(
+                std::slice::from_ref(source),
+                violation,
+            )),
+            None => Ok((config, value)),
+        }
+    }
 
-        Self::from_str(&contents)
+    /// Parse a configuration file, also as a value tree
+    fn parse_file(path: &Path) -> std::result::Result<(Self, serde_json::Value), ConfigProblem> {
+        let problem = |message: String, offset: Option<(usize, usize)>| ConfigProblem {
+            path: path.to_path_buf(),
+            line: offset.map(|(line, _)| line),
+            column: offset.map(|(_, column)| column),
+            message,
+        };
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| problem(format!("Failed to read config file: {e}"), None))?;
+
+        let format = ConfigFormat::from_path(path);
+        let config = match format {
+            ConfigFormat::Toml => toml::from_str(&contents).map_err(|e| {
+                let offset = e.span().map(|span| line_and_column(&contents, span.start));
+                let message = e.message().trim().replace('\n', ", ");
+                problem(format!("Failed to parse TOML: {message}"), offset)
+            }),
+            // An empty YAML document is null rather than an empty mapping
+            ConfigFormat::Yaml if contents.trim().is_empty() => Ok(Self::default()),
+            ConfigFormat::Yaml => serde_yaml::from_str(&contents).map_err(|e| {
+                let offset = e
+                    .location()
+                    .map(|location| (location.line(), location.column()));
+                problem(format!("Failed to parse YAML: {e}"), offset)
+            }),
+            ConfigFormat::Json => serde_json::from_str(&contents).map_err(|e| {
+                let offset = (e.line() > 0).then(|| (e.line(), e.column()));
+                problem(format!("Failed to parse JSON: {e}"), offset)
+            }),
+        }?;
+        let value = parse_value(&contents, format).map_err(|message| problem(message, None))?;
+        Ok((config, value))
     }
 
     /// Parse configuration from a TOML string
@@ -182,103 +1198,565 @@ impl Config {
         toml::from_str(toml).map_err(|e| Error::Config(format!("Failed to parse TOML: {e}")))
     }
 
+    /// Parse configuration in the given format
+    pub fn parse(contents: &str, format: ConfigFormat) -> Result<Self> {
+        match format {
+            ConfigFormat::Toml => Self::from_str(contents),
+            // An empty YAML document is null rather than an empty mapping
+            ConfigFormat::Yaml if contents.trim().is_empty() => Ok(Self::default()),
+            ConfigFormat::Yaml => serde_yaml::from_str(contents)
+                .map_err(|e| Error::Config(format!("Failed to parse YAML: {e}"))),
+            ConfigFormat::Json => serde_json::from_str(contents)
+                .map_err(|e| Error::Config(format!("Failed to parse JSON: {e}"))),
+        }
+    }
+
     /// Discover and load configuration from the current directory
     ///
-    /// Searches for xcargo.toml in current directory and parent directories
+    /// Searches for xcargo.toml in current directory and parent directories,
+    /// and for xcargo tables in Cargo.toml, merges them over the global
+    /// configuration and applies the profile named in `XCARGO_PROFILE`.
+    /// The path is that of the source with the highest precedence.
     pub fn discover() -> Result<Option<(Self, PathBuf)>> {
-        if let Some(path) = ConfigDiscovery::find()? {
-            let config = Self::from_file(&path)?;
-            Ok(Some((config, path)))
-        } else {
-            Ok(None)
+        let sources = ConfigDiscovery::find_sources()?;
+        let Some(source) = sources.last() else {
+            return Ok(None);
+        };
+        let mut config = Self::load_layered(&sources)?;
+        config.apply_env_profile()?;
+        Ok(Some((config, source.path().to_path_buf())))
+    }
+
+    /// The configuration in effect here: the project's over the global
+    /// one, either alone, or the defaults
+    pub fn effective() -> Result<Self> {
+        if let Some((config, _)) = Self::discover()? {
+            return Ok(config);
+        }
+        let mut config = Self::load_layered(&[])?;
+        config.apply_env_profile()?;
+        Ok(config)
+    }
+
+    /// Discover configuration like [`Config::discover`], but fall back to
+    /// the defaults when the file is broken
+    ///
+    /// For commands that can run on command-line settings alone; the caller
+    /// reports the problem returned alongside.
+    pub fn discover_or_default() -> Result<(Self, Option<ConfigProblem>)> {
+        let sources = ConfigDiscovery::find_sources()?;
+        if sources.is_empty() {
+            return Ok((Self::effective()?, None));
+        }
+        match Self::load_layered(&sources) {
+            Ok(mut config) => {
+                config.apply_env_profile()?;
+                Ok((config, None))
+            }
+            // Keep the project's or the global configuration if it still loads
+            Err(problem) => {
+                let config = layers::load(&sources)
+                    .ok()
+                    .or_else(|| Self::load_global().ok().flatten())
+                    .unwrap_or_default();
+                Ok((config, Some(problem)))
+            }
+        }
+    }
+
+    /// Load a project's configuration sources merged over the global
+    /// configuration, failing with the first setting that breaks a rule
+    fn load_layered(sources: &[ConfigSource]) -> std::result::Result<Self, ConfigProblem> {
+        let sources: Vec<ConfigSource> = Self::global_file()
+            .map(ConfigSource::File)
+            .into_iter()
+            .chain(sources.iter().cloned())
+            .collect();
+        Self::checked(layers::load(&sources)?, &sources)
+    }
+
+    /// `config`, unless a setting breaks a rule; that is located in the
+    /// last of `sources` that sets it
+    fn checked(self, sources: &[ConfigSource]) -> std::result::Result<Self, ConfigProblem> {
+        match self.violations().first() {
+            Some(violation) => Err(validate::locate_violation(sources, violation)),
+            None => Ok(self),
+        }
+    }
+
+    /// Apply the profile named in `XCARGO_PROFILE`, if set
+    fn apply_env_profile(&mut self) -> Result<()> {
+        match std::env::var(ENV_PROFILE) {
+            Ok(name) if !name.is_empty() => self.apply_profile(&name),
+            _ => Ok(()),
         }
     }
 
+    /// Path of the global configuration, `~/.xcargo/config.toml`
+    #[must_use]
+    pub fn global_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".xcargo").join("config.toml"))
+    }
+
+    /// Path of the global configuration, if it exists
+    fn global_file() -> Option<PathBuf> {
+        Self::global_path().filter(|path| path.is_file())
+    }
+
+    /// Load the global configuration, if it exists
+    pub fn load_global() -> Result<Option<Self>> {
+        Self::global_file().map(Self::from_file).transpose()
+    }
+
+    /// Target aliases from the global configuration, overridden by this one's
+    #[must_use]
+    pub fn resolved_aliases(&self) -> HashMap<String, String> {
+        let mut aliases = Self::load_global()
+            .ok()
+            .flatten()
+            .map(|global| global.aliases)
+            .unwrap_or_default();
+        aliases.extend(self.aliases.clone());
+        aliases
+    }
+
     /// Get the default configuration
     #[must_use]
     pub fn default_config() -> Self {
         Self::default()
     }
 
-    /// Merge this configuration with another, with other taking precedence
-    pub fn merge(&mut self, other: &Config) {
-        // Merge targets
-        if !other.targets.default.is_empty() {
-            self.targets.default = other.targets.default.clone();
+    /// Get configuration for a specific target
+    #[must_use]
+    pub fn get_target_config(&self, target: &str) -> Option<&TargetCustomConfig> {
+        self.targets.custom.get(target)
+    }
+
+    /// Get the targets of a group from `[target-groups]`
+    pub fn target_group(&self, name: &str) -> Result<&[String]> {
+        match self.target_groups.get(name) {
+            Some(targets) if targets.is_empty() => Err(Error::Config(format!(
+                "Target group '{name}' has no targets"
+            ))),
+            Some(targets) => Ok(targets),
+            None => {
+                let mut names: Vec<&str> = self.target_groups.keys().map(String::as_str).collect();
+                names.sort_unstable();
+                let available = if names.is_empty() {
+                    "define groups in xcargo.toml under [target-groups]".to_string()
+                } else {
+                    format!("available: {}", names.join(", "))
+                };
+                Err(Error::Config(format!(
+                    "Unknown target group '{name}' ({available})"
+                )))
+            }
+        }
+    }
+
+    /// Get a profile by name
+    #[must_use]
+    pub fn get_profile(&self, name: &str) -> Option<&ProfileConfig> {
+        self.profiles.get(name)
+    }
+
+    /// Get a profile with the settings of the profiles it inherits from
+    /// filled in, the nearest taking precedence
+    pub fn resolve_profile(&self, name: &str) -> Result<ProfileConfig> {
+        let mut chain: Vec<&str> = Vec::new();
+        let mut next = Some(name);
+        while let Some(current) = next {
+            if chain.contains(&current) {
+                chain.push(current);
+                return Err(Error::Config(format!(
+                    "Profile inheritance cycle: {}",
+                    chain.join(" -> ")
+                )));
+            }
+            let Some(profile) = self.profiles.get(current) else {
+                let mut names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+                names.sort_unstable();
+                let available = if names.is_empty() {
+                    "define profiles in xcargo.toml under [profiles.<name>]".to_string()
+                } else {
+                    format!("available: {}", names.join(", "))
+                };
+                return Err(Error::Config(match chain.last() {
+                    Some(child) => format!(
+                        "Profile '{child}' inherits unknown profile '{current}' ({available})"
+                    ),
+                    None => format!("Unknown profile '{current}' ({available})"),
+                }));
+            };
+            chain.push(current);
+            next = profile.inherits.as_deref();
+        }
+
+        let mut resolved = ProfileConfig::default();
+        for profile in chain.iter().rev().map(|name| &self.profiles[*name]) {
+            if !profile.targets.is_empty() {
+                resolved.targets.clone_from(&profile.targets);
+            }
+            if profile.strategy.is_some() {
+                resolved.strategy.clone_from(&profile.strategy);
+            }
+            resolved.parallel = profile.parallel.or(resolved.parallel);
+            resolved.jobs = profile.jobs.or(resolved.jobs);
+            resolved.env.extend(profile.env.clone());
+        }
+        resolved.inherits = None;
+        Ok(resolved)
+    }
+
+    /// Override the configuration with a profile's settings
+    pub fn apply_profile(&mut self, name: &str) -> Result<()> {
+        let profile = self.resolve_profile(name)?;
+        if !profile.targets.is_empty() {
+            self.targets.default = profile.targets;
+        }
+        if profile.strategy.is_some() {
+            self.build.strategy = profile.strategy;
+        }
+        if let Some(parallel) = profile.parallel {
+            self.build.parallel = parallel;
+        }
+        if profile.jobs.is_some() {
+            self.build.jobs = profile.jobs;
+        }
+        self.build.env.extend(profile.env);
+        self.profile = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Validate the configuration, failing with the first setting that
+    /// breaks a rule
+    pub fn validate(&self) -> Result<()> {
+        match self.violations().into_iter().next() {
+            Some(violation) => Err(Error::Config(violation.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// JSON Schema of the configuration format, for editors
+    #[must_use]
+    pub fn schema() -> schemars::schema::RootSchema {
+        let mut generator = schemars::gen::SchemaSettings::draft07().into_generator();
+        let custom = generator.subschema_for::<TargetCustomConfig>();
+        let mut schema = generator.into_root_schema_for::<Self>();
+        schema.schema.metadata().title = Some("xcargo configuration".to_string());
+        // `[targets.<triple>]` tables are a flattened map, which the derive leaves out
+        if let Some(schemars::schema::Schema::Object(targets)) =
+            schema.definitions.get_mut("TargetsConfig")
+        {
+            targets.object().additional_properties = Some(Box::new(custom));
+        }
+        schema
+    }
+
+    /// Every setting that breaks a rule, by key
+    #[must_use]
+    pub fn violations(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let mut violation = |key: &[&str], message: String| {
+            violations.push(Violation::new(key, message));
+        };
+        let message = |e: Error| match e {
+            Error::Config(message) => message,
+            e => e.to_string(),
+        };
+
+        // Validate runtime
+        let valid_runtimes = ["auto", "youki", "docker", "podman", "nerdctl", "containerd"];
+        if !valid_runtimes.contains(&self.container.runtime.as_str()) {
+            violation(
+                &["container", "runtime"],
+                format!(
+                    "Invalid container runtime: {}. Must be one of: {}",
+                    self.container.runtime,
+                    valid_runtimes.join(", ")
+                ),
+            );
+        }
+
+        // Validate pull policy
+        let valid_policies = ["always", "never", "if-not-present"];
+        if !valid_policies.contains(&self.container.pull_policy.as_str()) {
+            violation(
+                &["container", "pull_policy"],
+                format!(
+                    "Invalid pull policy: {}. Must be one of: {}",
+                    self.container.pull_policy,
+                    valid_policies.join(", ")
+                ),
+            );
+        }
+        if let Some(registry) = &self.container.registry {
+            if registry.is_empty() || registry.contains("://") || registry.ends_with('/') {
+                violation(
+                    &["container", "registry"],
+                    format!("'{registry}' must be a host with an optional path, like \"registry.corp.example/cross-rs\""),
+                );
+            }
+        }
+        for (triple, image) in &self.container.images {
+            let pinned = image.split_once('@').map(|(_, digest)| digest);
+            let valid = pinned.map_or(true, |digest| {
+                digest.strip_prefix("sha256:").is_some_and(|hex| {
+                    hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit())
+                })
+            });
+            if !valid {
+                violation(
+                    &["container", "images", triple],
+                    format!("'{image}' must be pinned as <image>@sha256:<64 hex digits>"),
+                );
+            }
+        }
+
+        // Validate OpenSSL strategies
+        let valid_strategies = ["auto", "vendored", "sysroot", "precompiled"];
+        let openssl = &self.deps.openssl;
+        let strategies = std::iter::once((None, &openssl.strategy)).chain(
+            openssl
+                .targets
+                .iter()
+                .map(|(triple, strategy)| (Some(triple.as_str()), strategy)),
+        );
+        for (triple, strategy) in strategies {
+            if !valid_strategies.contains(&strategy.as_str()) {
+                let key = match triple {
+                    Some(triple) => vec!["deps", "openssl", "targets", triple],
+                    None => vec!["deps", "openssl", "strategy"],
+                };
+                violation(
+                    &key,
+                    format!(
+                        "Invalid OpenSSL strategy: {}. Must be one of: {}",
+                        strategy,
+                        valid_strategies.join(", ")
+                    ),
+                );
+            }
+        }
+
+        // Validate artifact mode
+        let valid_modes = ["copy", "hardlink"];
+        if !valid_modes.contains(&self.artifacts.mode.as_str()) {
+            violation(
+                &["artifacts", "mode"],
+                format!(
+                    "Invalid artifacts mode: {}. Must be one of: {}",
+                    self.artifacts.mode,
+                    valid_modes.join(", ")
+                ),
+            );
+        }
+
+        // Gates are told apart by name in the manifest
+        let mut gate_names = HashSet::new();
+        for (index, gate) in self.artifacts.gates.iter().enumerate() {
+            let index = index.to_string();
+            if gate.name.is_empty() || gate.command.is_empty() {
+                violation(
+                    &["artifacts", "gates", &index],
+                    "Gates need a name and a command".to_string(),
+                );
+            } else if !gate_names.insert(gate.name.as_str()) {
+                violation(
+                    &["artifacts", "gates", &index, "name"],
+                    format!("More than one gate is named '{}'", gate.name),
+                );
+            }
+        }
+
+        // Hosts are told apart by name, and SSH hosts need an address
+        let mut host_names = HashSet::new();
+        for (index, host) in self.hosts.iter().enumerate() {
+            let index = index.to_string();
+            if !host_names.insert(host.name.as_str()) {
+                violation(
+                    &["hosts", &index, "name"],
+                    format!("More than one host is named '{}'", host.name),
+                );
+            }
+            if host.kind == HostKind::Ssh && host.address.is_none() {
+                violation(
+                    &["hosts", &index],
+                    format!("Host '{}' is an ssh host without an address", host.name),
+                );
+            }
+            if host.jobs == 0 {
+                violation(
+                    &["hosts", &index, "jobs"],
+                    format!("Host '{}' needs at least 1 job", host.name),
+                );
+            }
+        }
+
+        // The AAR manifest needs a package name
+        if self.artifacts.android.aar && self.artifacts.android.package.is_none() {
+            violation(
+                &["artifacts", "android", "aar"],
+                "Needs artifacts.android.package (e.g., \"com.example.rust\")".to_string(),
+            );
+        }
+
+        for (index, format) in self.embedded.formats.iter().enumerate() {
+            if embedded::ImageFormat::from_name(format).is_none() {
+                violation(
+                    &["embedded", "formats", &index.to_string()],
+                    format!("Invalid embedded format: {format}. Must be one of: bin, hex, uf2"),
+                );
+            }
+        }
+        if let Some(family) = &self.embedded.uf2_family {
+            if let Err(e) = embedded::uf2_family(family) {
+                violation(&["embedded", "uf2_family"], message(e));
+            }
         }
-        for (key, value) in &other.targets.custom {
-            self.targets.custom.insert(key.clone(), value.clone());
+        if self.embedded.flash.is_empty() {
+            violation(&["embedded", "flash"], "Must name a command".to_string());
         }
 
-        // Merge build config (other overrides self)
-        self.build.parallel = other.build.parallel;
-        if other.build.jobs.is_some() {
-            self.build.jobs = other.build.jobs;
+        if self.docker.tags.is_empty() {
+            violation(&["docker", "tags"], "Must not be empty".to_string());
         }
-        self.build.cache = other.build.cache;
-        self.build.force_container = other.build.force_container;
-        if !other.build.cargo_flags.is_empty() {
-            self.build.cargo_flags = other.build.cargo_flags.clone();
+
+        let bindgen_targets = ["web", "bundler", "nodejs", "no-modules", "deno"];
+        if !bindgen_targets.contains(&self.artifacts.wasm.bindgen_target.as_str()) {
+            violation(
+                &["artifacts", "wasm", "bindgen_target"],
+                format!(
+                    "Invalid wasm-bindgen target: {}. Must be one of: {}",
+                    self.artifacts.wasm.bindgen_target,
+                    bindgen_targets.join(", ")
+                ),
+            );
         }
 
-        // Merge container config
-        self.container.runtime = other.container.runtime.clone();
-        self.container.use_when = other.container.use_when.clone();
-        if other.container.registry.is_some() {
-            self.container.registry = other.container.registry.clone();
+        // Validate notarization polling
+        if self.sign.notarize.poll_interval == 0 {
+            violation(
+                &["sign", "notarize", "poll_interval"],
+                "Must be at least 1 second".to_string(),
+            );
         }
-        self.container.pull_policy = other.container.pull_policy.clone();
 
-        // Merge profiles
-        for (key, value) in &other.profiles {
-            self.profiles.insert(key.clone(), value.clone());
+        for (triple, target) in &self.targets.custom {
+            // Validate glibc versions
+            let versions = [
+                ("min_glibc", &target.min_glibc),
+                ("glibc_version", &target.glibc_version),
+            ];
+            for (key, version) in versions {
+                if let Some(version) = version {
+                    if GlibcVersion::parse(version).is_none() {
+                        violation(
+                            &["targets", triple, key],
+                            format!("Must be a version like \"2.17\", got \"{version}\""),
+                        );
+                    }
+                }
+            }
+
+            // Validate cargo target kinds
+            let kinds = [
+                ("kinds", &target.kinds, TARGET_KINDS),
+                ("test_kinds", &target.test_kinds, TEST_KINDS),
+            ];
+            for (key, kinds, valid) in kinds {
+                if let Some((index, kind)) = kinds
+                    .iter()
+                    .enumerate()
+                    .find(|(_, kind)| !valid.contains(&kind.as_str()))
+                {
+                    violation(
+                        &["targets", triple, key, &index.to_string()],
+                        format!("Invalid kind: {kind}. Must be one of: {}", valid.join(", ")),
+                    );
+                }
+            }
         }
-    }
 
-    /// Get configuration for a specific target
-    #[must_use]
-    pub fn get_target_config(&self, target: &str) -> Option<&TargetCustomConfig> {
-        self.targets.custom.get(target)
-    }
+        // An empty prefix would allow every library
+        if let Some(index) = self
+            .verify
+            .allowed_libraries
+            .iter()
+            .position(String::is_empty)
+        {
+            violation(
+                &["verify", "allowed_libraries", &index.to_string()],
+                "Entries must not be empty".to_string(),
+            );
+        }
 
-    /// Get a profile by name
-    #[must_use]
-    pub fn get_profile(&self, name: &str) -> Option<&ProfileConfig> {
-        self.profiles.get(name)
-    }
+        // Validate target groups
+        for (name, targets) in &self.target_groups {
+            if targets.is_empty() {
+                violation(
+                    &["target-groups", name],
+                    "Must list at least one target".to_string(),
+                );
+            }
+        }
 
-    /// Validate the configuration
-    pub fn validate(&self) -> Result<()> {
-        // Validate runtime
-        let valid_runtimes = ["auto", "youki", "docker", "podman"];
-        if !valid_runtimes.contains(&self.container.runtime.as_str()) {
-            return Err(Error::Config(format!(
-                "Invalid container runtime: {}. Must be one of: {}",
-                self.container.runtime,
-                valid_runtimes.join(", ")
-            )));
+        // Validate profile inheritance
+        for name in self.profiles.keys() {
+            if let Err(e) = self.resolve_profile(name) {
+                violation(&["profiles", name, "inherits"], message(e));
+            }
         }
 
-        // Validate pull policy
-        let valid_policies = ["always", "never", "if-not-present"];
-        if !valid_policies.contains(&self.container.pull_policy.as_str()) {
-            return Err(Error::Config(format!(
-                "Invalid pull policy: {}. Must be one of: {}",
-                self.container.pull_policy,
-                valid_policies.join(", ")
-            )));
+        // Validate aliases
+        for (name, triple) in &self.aliases {
+            if name.is_empty() || triple.is_empty() {
+                violation(
+                    &["aliases", name],
+                    "Must name a non-empty target triple".to_string(),
+                );
+            }
+        }
+
+        // Validate dependency rules
+        if let Some(index) = self
+            .deps
+            .rules
+            .iter()
+            .position(|rule| rule.krate.is_empty())
+        {
+            violation(
+                &["deps", "rules", &index.to_string()],
+                "Rules must name a crate".to_string(),
+            );
         }
 
         // Validate jobs count
-        if let Some(jobs) = self.build.jobs {
-            if jobs == 0 {
-                return Err(Error::Config(
-                    "build.jobs must be greater than 0".to_string(),
-                ));
+        if self.build.jobs == Some(0) {
+            violation(&["build", "jobs"], "Must be greater than 0".to_string());
+        }
+
+        // Validate retries; the delay doubles with each one
+        if self.build.retries > MAX_RETRIES {
+            violation(
+                &["build", "retries"],
+                format!("Must be at most {MAX_RETRIES}"),
+            );
+        }
+
+        if let Some(timeout) = &self.build.timeout {
+            if cancel::parse_duration(timeout).is_none() {
+                violation(
+                    &["build", "timeout"],
+                    format!("'{timeout}' is not a duration (e.g., \"15m\")"),
+                );
             }
         }
 
-        Ok(())
+        violations.extend(validate::strategy_conflicts(self));
+        violations.extend(validate::unknown_targets(self, &validate::known_triples()));
+        violations.sort_by(|a, b| a.key.cmp(&b.key));
+        violations
     }
 
     /// Convert configuration to TOML string
@@ -287,12 +1765,22 @@ impl Config {
             .map_err(|e| Error::Config(format!("Failed to serialize to TOML: {e}")))
     }
 
-    /// Save configuration to a file
+    /// Serialize configuration in the given format
+    pub fn to_format(&self, format: ConfigFormat) -> Result<String> {
+        match format {
+            ConfigFormat::Toml => self.to_toml(),
+            ConfigFormat::Yaml => serde_yaml::to_string(self)
+                .map_err(|e| Error::Config(format!("Failed to serialize to YAML: {e}"))),
+            ConfigFormat::Json => serde_json::to_string_pretty(self)
+                .map_err(|e| Error::Config(format!("Failed to serialize to JSON: {e}"))),
+        }
+    }
+
+    /// Save configuration to a file, in the format its extension implies
     pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        let toml = self.to_toml()?;
-        std::fs::write(path.as_ref(), toml)
-            .map_err(|e| Error::Config(format!("Failed to write config file: {e}")))?;
-        Ok(())
+        let contents = self.to_format(ConfigFormat::from_path(path.as_ref()))?;
+        crate::state::write_atomic(path.as_ref(), contents)
+            .map_err(|e| Error::Config(format!("Failed to write config file: {e}")))
     }
 }
 
@@ -300,6 +1788,46 @@ impl Config {
 mod tests {
     use super::*;
 
+    /// Configurations in xcargo.toml form, each layered over the ones
+    /// before it as the global and project files are
+    fn layered(layers: &[&str]) -> Config {
+        let temp = tempfile::tempdir().unwrap();
+        let sources: Vec<ConfigSource> = layers
+            .iter()
+            .enumerate()
+            .map(|(i, contents)| {
+                let path = temp.path().join(format!("layer{i}.toml"));
+                std::fs::write(&path, contents).unwrap();
+                ConfigSource::File(path)
+            })
+            .collect();
+        layers::load(&sources).unwrap()
+    }
+
+    #[test]
+    fn test_key_aliases_match_the_serde_attributes() {
+        let source = include_str!("mod.rs");
+        let mut lines = source.lines().map(str::trim);
+        let mut attributes = Vec::new();
+        while let Some(line) = lines.next() {
+            if !line.starts_with("#[serde(") {
+                continue;
+            }
+            let Some((_, rest)) = line.split_once("alias = \"") else {
+                continue;
+            };
+            let alias = rest.split('"').next().unwrap();
+            let field = lines.find(|line| line.starts_with("pub ")).unwrap();
+            let name = field["pub ".len()..].split(':').next().unwrap();
+            attributes.push((alias, name));
+        }
+        attributes.sort_unstable();
+        attributes.dedup();
+        let mut table = KEY_ALIASES.to_vec();
+        table.sort_unstable();
+        assert_eq!(attributes, table);
+    }
+
     #[test]
     fn test_default_config() {
         let config = Config::default();
@@ -351,6 +1879,78 @@ mod tests {
         assert!(config.profiles.contains_key("release-all"));
     }
 
+    #[test]
+    fn test_schema_covers_config() {
+        let schema = serde_json::to_value(Config::schema()).unwrap();
+        let serialized = serde_json::to_value(Config::default()).unwrap();
+        for key in serialized.as_object().unwrap().keys() {
+            assert!(schema["properties"].get(key).is_some(), "{key} missing");
+        }
+        assert_eq!(schema["additionalProperties"], false);
+        assert_eq!(
+            schema["definitions"]["TargetsConfig"]["additionalProperties"]["$ref"],
+            "#/definitions/TargetCustomConfig"
+        );
+        assert!(schema["definitions"]["TargetCustomConfig"]["properties"]
+            .get("min_glibc")
+            .is_some());
+    }
+
+    #[test]
+    fn test_profiles() {
+        let toml = r#"
+            [targets]
+            default = ["x86_64-unknown-linux-gnu"]
+
+            [build.env]
+            RUST_LOG = "info"
+
+            [profiles.release]
+            targets = ["x86_64-unknown-linux-gnu", "aarch64-unknown-linux-gnu"]
+            env = { RUST_LOG = "warn", SHARED = "1" }
+
+            [profiles.ci]
+            inherits = "release"
+            strategy = "container"
+            parallel = false
+            env = { RUST_LOG = "error" }
+        "#;
+
+        let mut config = Config::from_str(toml).unwrap();
+        config.validate().unwrap();
+
+        let ci = config.resolve_profile("ci").unwrap();
+        assert_eq!(ci.targets.len(), 2);
+        assert_eq!(ci.strategy, Some(Strategy::Container));
+        assert_eq!(ci.env["RUST_LOG"], "error");
+        assert_eq!(ci.env["SHARED"], "1");
+
+        config.apply_profile("ci").unwrap();
+        assert_eq!(config.profile.as_deref(), Some("ci"));
+        assert_eq!(config.targets.default.len(), 2);
+        assert_eq!(config.build.strategy, Some(Strategy::Container));
+        assert!(!config.build.parallel);
+        assert_eq!(config.build.env["RUST_LOG"], "error");
+
+        let err = config.apply_profile("nightly").unwrap_err().to_string();
+        assert!(err.contains("available: ci, release"), "{err}");
+
+        let cycle =
+            Config::from_str("[profiles.a]\ninherits = \"b\"\n[profiles.b]\ninherits = \"a\"")
+                .unwrap();
+        let err = cycle.validate().unwrap_err().to_string();
+        assert!(err.contains("a -> b -> a"), "{err}");
+
+        let dangling = Config::from_str("[profiles.ci]\ninherits = \"base\"").unwrap();
+        let err = dangling.validate().unwrap_err().to_string();
+        assert!(
+            err.contains("'ci' inherits unknown profile 'base'"),
+            "{err}"
+        );
+
+        assert!(Config::from_str("[profiles.ci]\ntarget = []").is_err());
+    }
+
     #[test]
     fn test_custom_target_config() {
         let toml = r#"
@@ -360,6 +1960,7 @@ mod tests {
             [targets."x86_64-pc-windows-gnu"]
             linker = "x86_64-w64-mingw32-gcc"
             force_container = false
+            strategy = "zig"
 
             [targets."x86_64-pc-windows-gnu".env]
             CC = "x86_64-w64-mingw32-gcc"
@@ -372,12 +1973,516 @@ mod tests {
             Some("x86_64-w64-mingw32-gcc".to_string())
         );
         assert_eq!(target_config.force_container, Some(false));
+        assert_eq!(target_config.strategy, Some(Strategy::Zig));
         assert_eq!(
             target_config.env.get("CC"),
             Some(&"x86_64-w64-mingw32-gcc".to_string())
         );
     }
 
+    #[test]
+    fn test_target_feature_config() {
+        let toml = r#"
+            [targets."wasm32-unknown-unknown"]
+            features = ["web"]
+            no-default-features = true
+        "#;
+
+        let config = Config::from_str(toml).unwrap();
+        let target_config = config.get_target_config("wasm32-unknown-unknown").unwrap();
+        assert_eq!(target_config.features, vec!["web"]);
+        assert!(target_config.no_default_features);
+    }
+
+    #[test]
+    fn test_target_exclusion_config() {
+        let toml = r#"
+            [targets."aarch64-apple-darwin"]
+            only_on_host = ["macos"]
+
+            [targets."x86_64-pc-windows-msvc"]
+            skip_on_ci = true
+        "#;
+
+        let config = Config::from_str(toml).unwrap();
+        let darwin = config.get_target_config("aarch64-apple-darwin").unwrap();
+        assert!(darwin.exclusion_reason("darwin", true).is_none());
+        assert_eq!(
+            darwin.exclusion_reason("linux", false).as_deref(),
+            Some("only built on macos hosts")
+        );
+
+        let msvc = config.get_target_config("x86_64-pc-windows-msvc").unwrap();
+        assert!(msvc.exclusion_reason("linux", false).is_none());
+        assert!(msvc.exclusion_reason("linux", true).is_some());
+    }
+
+    #[test]
+    fn test_min_glibc_config() {
+        let toml = "[targets.\"x86_64-unknown-linux-gnu\"]\nmin_glibc = \"2.17\"";
+        let config = Config::from_str(toml).unwrap();
+        let target = config
+            .get_target_config("x86_64-unknown-linux-gnu")
+            .unwrap();
+        assert_eq!(target.min_glibc.as_deref(), Some("2.17"));
+        assert!(config.validate().is_ok());
+
+        let config = Config::from_str(&toml.replace("2.17", "latest")).unwrap();
+        assert!(config.validate().is_err());
+
+        let config = Config::from_str(&toml.replace("min_glibc", "glibc-version")).unwrap();
+        let target = config
+            .get_target_config("x86_64-unknown-linux-gnu")
+            .unwrap();
+        assert_eq!(target.glibc_version.as_deref(), Some("2.17"));
+        let invalid = "[targets.\"x86_64-unknown-linux-gnu\"]\nglibc_version = \"2\"";
+        let config = Config::from_str(invalid).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_target_kinds_config() {
+        let toml = r#"
+            [targets."aarch64-unknown-linux-gnu"]
+            kinds = ["bin", "example"]
+            test-kinds = ["lib", "doc"]
+        "#;
+        let config = Config::from_str(toml).unwrap();
+        let target = config
+            .get_target_config("aarch64-unknown-linux-gnu")
+            .unwrap();
+        assert_eq!(target.kinds, ["bin", "example"]);
+        assert_eq!(target.test_kinds, ["lib", "doc"]);
+        assert!(config.validate().is_ok());
+
+        // Nothing is documented when building
+        let config = Config::from_str(&toml.replace("\"example\"", "\"doc\"")).unwrap();
+        assert!(config.validate().is_err());
+        let config = Config::from_str(&toml.replace("\"lib\"", "\"libs\"")).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_dep_rules_config() {
+        let toml = r#"
+            [[deps.rules]]
+            crate = "reqwest"
+            targets = ["*-musl"]
+            features = ["reqwest/rustls-tls"]
+
+            [[deps.rules]]
+            crate = "bindgen"
+            env = { LIBCLANG_PATH = "/usr/lib/llvm-17/lib" }
+        "#;
+
+        let config = Config::from_str(toml).unwrap();
+        assert_eq!(config.deps.rules.len(), 2);
+        assert_eq!(config.deps.rules[0].krate, "reqwest");
+        assert_eq!(config.deps.rules[0].targets, vec!["*-musl"]);
+        assert!(config.deps.rules[1].targets.is_empty());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_artifacts_config() {
+        let config = Config::from_str("[artifacts]\ndir = \"dist/\"").unwrap();
+        assert_eq!(config.artifacts.dir.as_deref(), Some("dist/"));
+        assert_eq!(config.artifacts.mode, "copy");
+
+        let config = Config::from_str("[artifacts]\nmode = \"symlink\"").unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_artifact_gates_config() {
+        let config = Config::from_str(
+            r#"
+[[artifacts.gates]]
+name = "malware-scan"
+command = ["clamscan", "--no-summary", "{artifact}"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.artifacts.gates[0].name, "malware-scan");
+        assert_eq!(config.artifacts.gates[0].command[2], "{artifact}");
+        assert!(config.validate().is_ok());
+
+        let duplicate = r#"
+[[artifacts.gates]]
+name = "scan"
+command = ["a"]
+
+[[artifacts.gates]]
+name = "scan"
+command = ["b"]
+"#;
+        assert!(Config::from_str(duplicate).unwrap().validate().is_err());
+
+        let empty = "[[artifacts.gates]]\nname = \"scan\"\ncommand = []";
+        assert!(Config::from_str(empty).unwrap().validate().is_err());
+    }
+
+    #[test]
+    fn test_toolchain_config() {
+        assert!(!Config::default().toolchain.auto_install_targets);
+        let config = Config::from_str("[toolchain]\nauto-install-targets = true\n").unwrap();
+        assert!(config.toolchain.auto_install_targets);
+        assert_eq!(config.toolchain.catalog_url, None);
+
+        let config = layered(&[
+            "[toolchain]\nauto-install-targets = true\n",
+            "[toolchain]\ncatalog-url = \"https://mirror.example/catalog.json\"\n",
+        ]);
+        assert!(config.toolchain.auto_install_targets);
+        assert_eq!(
+            config.toolchain.catalog_url.as_deref(),
+            Some("https://mirror.example/catalog.json")
+        );
+    }
+
+    #[test]
+    fn test_notifications_config() {
+        assert!(!Config::default().notifications.enabled());
+
+        let toml = r#"
+[notifications]
+desktop = true
+min-duration = 300
+on-success = false
+
+[[notifications.webhooks]]
+url = "https://hooks.slack.com/services/T000/B000/XXXX"
+
+[[notifications.webhooks]]
+url = "https://ci.example/hooks/xcargo"
+format = "json"
+"#;
+        let config = Config::from_str(toml).unwrap();
+        let notifications = &config.notifications;
+        assert!(notifications.enabled());
+        assert_eq!(notifications.min_duration, 300);
+        assert!(!notifications.on_success && notifications.on_failure);
+        assert_eq!(notifications.webhooks[0].format, None);
+        assert_eq!(notifications.webhooks[1].format, Some(WebhookFormat::Json));
+
+        assert_eq!(layered(&[toml, toml]).notifications, config.notifications);
+    }
+
+    #[test]
+    fn test_network_config() {
+        let config = Config::default();
+        assert_eq!(config.network.proxy, None);
+        assert!(config.network.passthrough.contains(&"NO_PROXY".to_string()));
+
+        let toml = r#"
+[network]
+proxy = "http://proxy.corp:3128"
+ca-bundle = "/etc/corp/ca.pem"
+passthrough = ["PIP_INDEX_URL"]
+"#;
+        let config = Config::from_str(toml).unwrap();
+        assert_eq!(
+            config.network.ca_bundle.as_deref(),
+            Some(Path::new("/etc/corp/ca.pem"))
+        );
+        assert_eq!(config.network.passthrough, ["PIP_INDEX_URL"]);
+
+        // A project's passthrough list replaces the global one
+        let layered = layered(&[toml, "[network]\npassthrough = [\"NPM_TOKEN\"]\n"]);
+        assert_eq!(
+            layered.network.proxy.as_deref(),
+            Some("http://proxy.corp:3128")
+        );
+        assert_eq!(layered.network.passthrough, ["NPM_TOKEN"]);
+    }
+
+    #[test]
+    fn test_hosts_config() {
+        let toml = r#"
+            [[hosts]]
+            name = "here"
+
+            [[hosts]]
+            name = "mac-mini"
+            kind = "ssh"
+            address = "ci@mac-mini.local"
+            targets = ["*-apple-*"]
+            jobs = 2
+        "#;
+        let config = Config::from_str(toml).unwrap();
+        assert_eq!(config.hosts.len(), 2);
+        assert_eq!(config.hosts[0].kind, HostKind::Local);
+        assert_eq!(config.hosts[0].jobs, 1);
+        assert_eq!(config.hosts[1].kind, HostKind::Ssh);
+        config.validate().unwrap();
+
+        let no_address = "[[hosts]]\nname = \"mac\"\nkind = \"ssh\"\n";
+        assert!(Config::from_str(no_address).unwrap().validate().is_err());
+    }
+
+    #[test]
+    fn test_strategies_config() {
+        let toml = r#"
+            [strategies.remote-builder]
+            priority = 40
+            targets = ["aarch64-*"]
+
+            [targets."aarch64-unknown-linux-gnu"]
+            strategy = "nix-shell"
+        "#;
+        let config = Config::from_str(toml).unwrap();
+        let remote = &config.strategies["remote-builder"];
+        assert_eq!(remote.priority, Some(40));
+        assert_eq!(remote.targets, vec!["aarch64-*"]);
+        assert_eq!(
+            config
+                .get_target_config("aarch64-unknown-linux-gnu")
+                .unwrap()
+                .strategy,
+            Some(Strategy::Plugin("nix-shell".to_string()))
+        );
+
+        let config = Config::from_str("[strategy.nix]\nenable = true\n").unwrap();
+        assert_eq!(config.strategies["nix"].enable, Some(true));
+    }
+
+    #[test]
+    fn test_load_locates_errors() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let write = |name: &str, contents: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, contents).unwrap();
+            path
+        };
+
+        let path = write("xcargo.toml", "[targets]\ndefault = [\"a\"\n[build]\n");
+        let problem = Config::load(&path).unwrap_err();
+        assert_eq!((problem.line, problem.column), (Some(3), Some(1)));
+        assert!(problem.message.starts_with("Failed to parse TOML"));
+        assert!(!problem.message.contains('\n'));
+        assert_eq!(problem.location(), format!("{}:3:1", path.display()));
+
+        let path = write("xcargo.yaml", "build:\n  parallel: maybe\n");
+        let problem = Config::load(&path).unwrap_err();
+        assert_eq!(problem.line, Some(2));
+
+        let path = write("xcargo.json", "{\n  \"build\": {\n}");
+        let problem = Config::load(&path).unwrap_err();
+        assert_eq!(problem.line, Some(3));
+        assert!(Error::from(problem).to_string().contains("xcargo.json:3"));
+
+        let problem = Config::load(&dir.path().join("missing.toml")).unwrap_err();
+        assert_eq!(problem.line, None);
+        assert!(problem.message.starts_with("Failed to read config file"));
+    }
+
+    #[test]
+    fn test_line_and_column() {
+        assert_eq!(line_and_column("abc", 0), (1, 1));
+        assert_eq!(line_and_column("ab\ncd", 4), (2, 2));
+        assert_eq!(line_and_column("ab", 10), (1, 3));
+    }
+
+    #[test]
+    fn test_android_export_config() {
+        let config = Config::from_str("[artifacts.android]\ndir = \"android\"").unwrap();
+        assert_eq!(config.artifacts.android.dir.as_deref(), Some("android"));
+        assert_eq!(config.artifacts.android.min_sdk, 21);
+        assert!(!config.artifacts.android.aar);
+        assert!(config.validate().is_ok());
+
+        let config = Config::from_str("[artifacts.android]\naar = true").unwrap();
+        assert!(config.validate().is_err());
+        let config = Config::from_str(
+            "[artifacts.android]\naar = true\npackage = \"com.example.rust\"\nmin-sdk = 24",
+        )
+        .unwrap();
+        assert_eq!(config.artifacts.android.min_sdk, 24);
+        assert!(!config.artifacts.android.require_16k_pages);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_docker_config() {
+        let config = Config::default();
+        assert_eq!(config.docker.base, "scratch");
+        assert_eq!(config.docker.tags, vec!["{name}:{version}"]);
+
+        let config = Config::from_str(
+            "[docker]\nbase = \"gcr.io/distroless/cc-debian12\"\n\
+             tags = [\"ghcr.io/acme/{name}:{git_sha}\"]\nbin = \"server\"",
+        )
+        .unwrap();
+        assert_eq!(config.docker.base, "gcr.io/distroless/cc-debian12");
+        assert_eq!(config.docker.bin.as_deref(), Some("server"));
+        assert!(config.validate().is_ok());
+
+        let config = Config::from_str("[docker]\ntags = []").unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_container_images() {
+        let image = "ghcr.io/cross-rs/aarch64-unknown-linux-gnu:main";
+        let pinned = format!("{image}@sha256:{}", "0f".repeat(32));
+        let mut config = Config::from_str(&format!(
+            "[container]\npull_policy = \"never\"\n\n[container.images]\n\
+             aarch64-unknown-linux-gnu = \"{pinned}\"\n"
+        ))
+        .unwrap();
+        assert_eq!(config.container.images["aarch64-unknown-linux-gnu"], pinned);
+        assert!(config.validate().is_ok());
+
+        config
+            .container
+            .images
+            .insert("x86_64-pc-windows-gnu".to_string(), image.to_string());
+        assert!(config.validate().is_ok());
+        config.container.images.insert(
+            "x86_64-pc-windows-gnu".to_string(),
+            format!("{image}@sha256:1234"),
+        );
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_container_registry() {
+        let mut config = Config::default();
+        assert!(config.container.run_as_user);
+        config.container.registry = Some("registry.corp.example/cross-rs".to_string());
+        assert!(config.validate().is_ok());
+        config.container.registry = Some("https://registry.corp.example".to_string());
+        assert!(config.validate().is_err());
+        config.container.registry = Some(String::new());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_embedded_config() {
+        let config = Config::from_str("").unwrap();
+        assert_eq!(config.embedded.flash[0], "probe-rs");
+        assert!(config.embedded.formats.is_empty());
+
+        let config = Config::from_str(
+            "[embedded]\nformats = [\"bin\", \"uf2\"]\nuf2-family = \"rp2040\"\n\n\
+             [targets.\"thumbv6m-none-eabi\"]\nchip = \"RP2040\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.embedded.formats, vec!["bin", "uf2"]);
+        assert_eq!(
+            config
+                .get_target_config("thumbv6m-none-eabi")
+                .unwrap()
+                .chip
+                .as_deref(),
+            Some("RP2040")
+        );
+        assert!(config.validate().is_ok());
+
+        let config = Config::from_str("[embedded]\nformats = [\"srec\"]").unwrap();
+        assert!(config.validate().is_err());
+        let config = Config::from_str("[embedded]\nuf2_family = \"esp8266\"").unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_apple_export_config() {
+        let config =
+            Config::from_str("[artifacts.apple]\ndir = \"apple\"\nheaders = \"include\"").unwrap();
+        assert_eq!(config.artifacts.apple.dir.as_deref(), Some("apple"));
+        assert_eq!(config.artifacts.apple.headers.as_deref(), Some("include"));
+        assert_eq!(config.artifacts.apple.name, None);
+    }
+
+    #[test]
+    fn test_wasm_export_config() {
+        let config = Config::from_str("[artifacts.wasm]\ndir = \"pkg\"").unwrap();
+        assert_eq!(config.artifacts.wasm.dir.as_deref(), Some("pkg"));
+        assert!(config.artifacts.wasm.bindgen);
+        assert_eq!(config.artifacts.wasm.bindgen_target, "web");
+        assert_eq!(config.artifacts.wasm.opt, vec!["-O"]);
+        assert!(config.validate().is_ok());
+
+        let config = Config::from_str(
+            "[artifacts.wasm]\nbindgen-target = \"nodejs\"\nopt = [\"-Oz\", \"--strip-debug\"]",
+        )
+        .unwrap();
+        assert_eq!(config.artifacts.wasm.bindgen_target, "nodejs");
+        assert_eq!(config.artifacts.wasm.opt, vec!["-Oz", "--strip-debug"]);
+        let config = Config::from_str("[artifacts.wasm]\nbindgen_target = \"browser\"").unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_notarize_config() {
+        let config = Config::from_str(
+            "[sign.notarize]\nkeychain_profile = \"xcargo-notary\"\nstaple = false",
+        )
+        .unwrap();
+        let notarize = &config.sign.notarize;
+        assert_eq!(notarize.keychain_profile.as_deref(), Some("xcargo-notary"));
+        assert_eq!(notarize.timeout, 1800);
+        assert!(!notarize.staple);
+
+        let config = Config::from_str("[sign.notarize]\npoll_interval = 0").unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_verify_config() {
+        let config =
+            Config::from_str("[verify]\nafter-build = true\nallowed_libraries = [\"libssl\"]")
+                .unwrap();
+        assert!(config.verify.after_build);
+        assert_eq!(config.verify.allowed_libraries, vec!["libssl"]);
+        assert!(!Config::default().verify.after_build);
+
+        let config = Config::from_str("[verify]\nallowed_libraries = [\"\"]").unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_target_groups_config() {
+        let config = Config::from_str(
+            r#"
+            [target-groups]
+            desktop = ["x86_64-unknown-linux-gnu", "x86_64-pc-windows-gnu"]
+            embedded = ["thumbv7em-none-eabihf"]
+        "#,
+        )
+        .unwrap();
+        assert_eq!(config.target_group("desktop").unwrap().len(), 2);
+        assert!(config.validate().is_ok());
+
+        let err = config.target_group("mobile").unwrap_err().to_string();
+        assert!(err.contains("available: desktop, embedded"));
+
+        let config = Config::from_str("[target-groups]\nempty = []").unwrap();
+        assert!(config.target_group("empty").is_err());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_aliases_config() {
+        let mut config =
+            Config::from_str("[aliases]\nrpi = \"aarch64-unknown-linux-gnu\"").unwrap();
+        assert_eq!(
+            config.aliases.get("rpi").map(String::as_str),
+            Some("aarch64-unknown-linux-gnu")
+        );
+        assert!(config.validate().is_ok());
+
+        let layered = layered(&[
+            "[aliases]\nrpi = \"aarch64-unknown-linux-gnu\"\npi0 = \"arm-unknown-linux-gnueabihf\"",
+            "[aliases]\nrpi = \"armv7-unknown-linux-gnueabihf\"",
+        ]);
+        assert_eq!(layered.aliases["rpi"], "armv7-unknown-linux-gnueabihf");
+        assert_eq!(layered.aliases["pi0"], "arm-unknown-linux-gnueabihf");
+
+        config.aliases.insert("empty".to_string(), String::new());
+        assert!(config.validate().is_err());
+    }
+
     #[test]
     fn test_config_validation() {
         let mut config = Config::default();
@@ -396,25 +2501,70 @@ mod tests {
         config.container.pull_policy = "always".to_string();
         config.build.jobs = Some(0);
         assert!(config.validate().is_err());
+
+        // Fix jobs, test too many retries
+        config.build.jobs = None;
+        config.build.retries = MAX_RETRIES + 1;
+        assert!(config.validate().is_err());
+        config.build.retries = 3;
+        assert!(config.validate().is_ok());
+
+        config.build.timeout = Some("15 minutes".to_string());
+        assert!(config.validate().is_err());
+        config.build.timeout = Some("15m".to_string());
+        assert!(config.validate().is_ok());
     }
 
     #[test]
     fn test_config_merge() {
-        let mut base = Config::default();
-        base.targets.default = vec!["linux".to_string()];
-        base.build.parallel = false;
+        let base = "[targets]\ndefault = [\"linux\"]\n\n[build]\nparallel = false\n";
+        let override_config = "[targets]\ndefault = [\"windows\"]\n\n\
+                               [build]\njobs = 8\nretries = 2\ntimeout = \"1h\"\n";
+
+        let merged = layered(&[base, override_config]);
+
+        assert_eq!(merged.targets.default, vec!["windows"]);
+        assert_eq!(merged.build.jobs, Some(8));
+        assert_eq!(merged.build.retries, 2);
+        assert_eq!(merged.build.timeout.as_deref(), Some("1h"));
+        assert!(!merged.build.parallel); // Not set by the override
+    }
 
-        let mut override_config = Config::default();
-        override_config.targets.default = vec!["windows".to_string()];
-        override_config.build.jobs = Some(8);
-        // Override's default is true, so it will override base's false
-        assert!(override_config.build.parallel);
+    #[test]
+    fn test_yaml_and_json_formats() {
+        let yaml = r"
+targets:
+  default: [x86_64-pc-windows-gnu]
+  x86_64-pc-windows-gnu:
+    linker: x86_64-w64-mingw32-gcc
+build:
+  jobs: 4
+";
+        let from_yaml = Config::parse(yaml, ConfigFormat::Yaml).unwrap();
+        assert_eq!(from_yaml.targets.default, vec!["x86_64-pc-windows-gnu"]);
+        assert_eq!(from_yaml.build.jobs, Some(4));
+        assert_eq!(
+            from_yaml
+                .get_target_config("x86_64-pc-windows-gnu")
+                .unwrap()
+                .linker
+                .as_deref(),
+            Some("x86_64-w64-mingw32-gcc")
+        );
 
-        base.merge(&override_config);
+        // Round-trip through JSON gives the same configuration
+        let json = from_yaml.to_format(ConfigFormat::Json).unwrap();
+        assert_eq!(Config::parse(&json, ConfigFormat::Json).unwrap(), from_yaml);
 
-        assert_eq!(base.targets.default, vec!["windows"]);
-        assert_eq!(base.build.jobs, Some(8));
-        assert!(base.build.parallel); // Merged with other's value (default true)
+        assert_eq!(
+            Config::parse("", ConfigFormat::Yaml).unwrap(),
+            Config::default()
+        );
+        assert!(Config::parse("unknown: 1", ConfigFormat::Yaml).is_err());
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("xcargo.yml")),
+            ConfigFormat::Yaml
+        );
     }
 
     #[test]
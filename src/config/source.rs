@@ -0,0 +1,213 @@
+//! Configuration sources
+//!
+//! Besides xcargo.toml, a project's configuration can live in Cargo.toml:
+//! under `[package.metadata.xcargo]` for one package, or under
+//! `[workspace.metadata.xcargo]` in the workspace root for all of its
+//! members. The tables take the same keys as xcargo.toml. A Cross.toml is
+//! read too, converted to the settings it corresponds to.
+
+use super::cross::{CrossImport, CROSS_FILE_NAME};
+use super::{line_and_column, parse_value, Config, ConfigFormat, ConfigProblem};
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Name of cargo's manifest
+pub const MANIFEST_FILE_NAME: &str = "Cargo.toml";
+
+/// Where configuration is read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// A configuration file: xcargo.toml, its YAML and JSON variants, or
+    /// the global configuration
+    File(PathBuf),
+    /// `[package.metadata.xcargo]` in a package's Cargo.toml
+    Package(PathBuf),
+    /// `[workspace.metadata.xcargo]` in a workspace root's Cargo.toml
+    Workspace(PathBuf),
+    /// A Cross.toml, for projects moving over from cross
+    Cross(PathBuf),
+}
+
+impl ConfigSource {
+    /// The sources in a file, lowest precedence first: both metadata tables
+    /// of a Cargo.toml, a Cross.toml, or the file itself
+    #[must_use]
+    pub fn in_file(path: &Path) -> Vec<Self> {
+        let name = path.file_name().and_then(|name| name.to_str());
+        if name == Some(MANIFEST_FILE_NAME) {
+            vec![
+                Self::Workspace(path.to_path_buf()),
+                Self::Package(path.to_path_buf()),
+            ]
+        } else if name == Some(CROSS_FILE_NAME) {
+            vec![Self::Cross(path.to_path_buf())]
+        } else {
+            vec![Self::File(path.to_path_buf())]
+        }
+    }
+
+    /// The file
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::File(path) | Self::Package(path) | Self::Workspace(path) | Self::Cross(path) => {
+                path
+            }
+        }
+    }
+
+    /// Keys of the table holding the configuration; empty for a file
+    #[must_use]
+    pub fn table(&self) -> &'static [&'static str] {
+        match self {
+            Self::File(_) | Self::Cross(_) => &[],
+            Self::Package(_) => &["package", "metadata", "xcargo"],
+            Self::Workspace(_) => &["workspace", "metadata", "xcargo"],
+        }
+    }
+
+    /// Parse the configuration, also as a value tree
+    pub(super) fn parse(&self) -> std::result::Result<(Config, Value), ConfigProblem> {
+        match self {
+            Self::File(path) => Config::parse_file(path),
+            Self::Package(path) | Self::Workspace(path) => self.parse_manifest(path),
+            Self::Cross(path) => {
+                let import = CrossImport::read(path)?;
+                let config = import.config().map_err(|message| ConfigProblem {
+                    path: path.clone(),
+                    line: None,
+                    column: None,
+                    message,
+                })?;
+                Ok((config, import.value))
+            }
+        }
+    }
+
+    /// Parse the metadata table of a Cargo.toml; a manifest without one
+    /// holds the defaults
+    fn parse_manifest(&self, path: &Path) -> std::result::Result<(Config, Value), ConfigProblem> {
+        let problem = |message: String, offset: Option<(usize, usize)>| ConfigProblem {
+            path: path.to_path_buf(),
+            line: offset.map(|(line, _)| line),
+            column: offset.map(|(_, column)| column),
+            message,
+        };
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| problem(format!("Failed to read {MANIFEST_FILE_NAME}: {e}"), None))?;
+
+        // Deserializing the whole manifest keeps the spans of errors
+        let manifest: Manifest = toml::from_str(&contents).map_err(|e| {
+            let offset = e.span().map(|span| line_and_column(&contents, span.start));
+            let message = e.message().trim().replace('\n', ", ");
+            problem(format!("Failed to parse TOML: {message}"), offset)
+        })?;
+        let section = match self {
+            Self::Workspace(_) => manifest.workspace,
+            _ => manifest.package,
+        };
+        let config = section
+            .and_then(|section| section.metadata.xcargo)
+            .unwrap_or_default();
+
+        let value =
+            parse_value(&contents, ConfigFormat::Toml).map_err(|message| problem(message, None))?;
+        let value = self
+            .table()
+            .iter()
+            .try_fold(&value, |value, key| value.get(key))
+            .cloned()
+            .unwrap_or_else(|| Value::Object(serde_json::Map::new()));
+        Ok((config, value))
+    }
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) | Self::Cross(path) => write!(f, "{}", path.display()),
+            Self::Package(path) | Self::Workspace(path) => {
+                write!(f, "{} [{}]", path.display(), self.table().join("."))
+            }
+        }
+    }
+}
+
+/// The parts of a Cargo.toml configuration is read from
+#[derive(Deserialize)]
+struct Manifest {
+    package: Option<ManifestSection>,
+    workspace: Option<ManifestSection>,
+}
+
+/// `[package]` or `[workspace]`
+#[derive(Deserialize)]
+struct ManifestSection {
+    #[serde(default)]
+    metadata: ManifestMetadata,
+}
+
+/// `[package.metadata]` or `[workspace.metadata]`
+#[derive(Deserialize, Default)]
+struct ManifestMetadata {
+    xcargo: Option<Config>,
+}
+
+/// Whether the Cargo.toml at `path` has `[package]` and `[workspace]`
+/// tables, and whether each has `metadata.xcargo`
+pub(super) fn manifest_tables(path: &Path) -> Option<[(bool, bool); 2]> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let manifest: toml::Table = toml::from_str(&contents).ok()?;
+    Some(["package", "workspace"].map(|name| {
+        let section = manifest.get(name);
+        let configured = section
+            .and_then(|section| section.get("metadata"))
+            .and_then(|metadata| metadata.get("xcargo"))
+            .is_some();
+        (section.is_some(), configured)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join(MANIFEST_FILE_NAME);
+        std::fs::write(
+            &path,
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[package.metadata.xcargo.build]\nretries = 2\n\n[workspace.metadata.xcargo.targets]\ndefault = [\"wasm32-unknown-unknown\"]\n\n[package.metadata.docs.rs]\nall-features = true\n",
+        )
+        .unwrap();
+
+        let [workspace, package] = ConfigSource::in_file(&path).try_into().unwrap();
+        let (config, value) = package.parse().unwrap();
+        assert_eq!(config.build.retries, 2);
+        assert!(config.targets.default.is_empty());
+        assert_eq!(value["build"]["retries"], 2);
+        let (config, _) = workspace.parse().unwrap();
+        assert_eq!(config.targets.default, ["wasm32-unknown-unknown"]);
+        assert_eq!(
+            package.to_string(),
+            format!("{} [package.metadata.xcargo]", path.display())
+        );
+        assert_eq!(manifest_tables(&path), Some([(true, true), (true, true)]));
+
+        std::fs::write(
+            &path,
+            "[package]\nname = \"app\"\n\n[package.metadata.xcargo.build]\nretries = \"two\"\n",
+        )
+        .unwrap();
+        let problem = package.parse().unwrap_err();
+        assert_eq!(problem.line, Some(5));
+
+        // A manifest without the table holds the defaults
+        std::fs::write(&path, "[package]\nname = \"app\"\n").unwrap();
+        let (config, _) = package.parse().unwrap();
+        assert_eq!(config, Config::default());
+    }
+}
@@ -0,0 +1,459 @@
+//! Configuration validation
+//!
+//! Parsing catches malformed files and unknown top-level sections; this
+//! module catches the rest: keys inside sections that nothing reads, target
+//! triples rustc doesn't know, and strategy settings that contradict each
+//! other. Every problem is tied to a key path, which is located in the file
+//! that sets it, by line and column for TOML.
+
+use super::{
+    line_and_column, parse_value, Config, ConfigFormat, ConfigProblem, ConfigSource,
+    TargetCustomConfig,
+};
+use crate::build::strategy::Strategy;
+use crate::target::{suggest, Target};
+use serde_json::Value;
+use std::fmt;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// A setting that breaks a rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Key path, e.g. `["targets", "default", "1"]`; numbers index arrays
+    pub key: Vec<String>,
+    /// What is wrong
+    pub message: String,
+}
+
+impl Violation {
+    /// A violation of the setting at `key`
+    #[must_use]
+    pub fn new(key: &[&str], message: impl Into<String>) -> Self {
+        Self {
+            key: key.iter().map(ToString::to_string).collect(),
+            message: message.into(),
+        }
+    }
+
+    /// The key as written in TOML (e.g., `targets.default[1]`)
+    #[must_use]
+    pub fn dotted_key(&self) -> String {
+        let mut dotted = String::new();
+        for segment in &self.key {
+            if !dotted.is_empty() && segment.bytes().all(|b| b.is_ascii_digit()) {
+                let _ = write!(dotted, "[{segment}]");
+                continue;
+            }
+            if !dotted.is_empty() {
+                dotted.push('.');
+            }
+            if segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                dotted.push_str(segment);
+            } else {
+                let _ = write!(dotted, "\"{segment}\"");
+            }
+        }
+        dotted
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.dotted_key(), self.message)
+    }
+}
+
+/// Key path of a setting serde skipped over
+fn segments(path: &serde_ignored::Path<'_>, out: &mut Vec<String>) {
+    match path {
+        serde_ignored::Path::Root => {}
+        serde_ignored::Path::Seq { parent, index } => {
+            segments(parent, out);
+            out.push(index.to_string());
+        }
+        serde_ignored::Path::Map { parent, key } => {
+            segments(parent, out);
+            out.push(key.clone());
+        }
+        serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => segments(parent, out),
+    }
+}
+
+/// Keys in a parsed file that no setting reads, with the closest known
+/// key as a suggestion
+pub(super) fn unknown_keys(value: &Value) -> Vec<Violation> {
+    let mut unknown: Vec<Vec<String>> = Vec::new();
+    let config: Option<Config> = serde_ignored::deserialize(value.clone(), |path| {
+        let mut key = Vec::new();
+        segments(&path, &mut key);
+        unknown.push(key);
+    })
+    .ok();
+
+    // serde buffers the flattened per-target tables, out of sight of the
+    // pass above, so they get one of their own
+    if let Some(targets) = value.get("targets").and_then(Value::as_object) {
+        for (triple, table) in targets.iter().filter(|(_, table)| table.is_object()) {
+            let _: Option<TargetCustomConfig> = serde_ignored::deserialize(table.clone(), |path| {
+                let mut key = vec!["targets".to_string(), triple.clone()];
+                segments(&path, &mut key);
+                unknown.push(key);
+            })
+            .ok();
+        }
+    }
+
+    let known = config.and_then(|config| serde_json::to_value(config).ok());
+    unknown
+        .into_iter()
+        .filter_map(|key| {
+            let (name, parent) = key.split_last()?;
+            let siblings: Vec<&String> = known
+                .as_ref()
+                .and_then(|known| lookup(known, parent))
+                .and_then(Value::as_object)
+                .map(|table| table.keys().collect())
+                .unwrap_or_default();
+            let message = match suggest::suggest(name, &siblings).first() {
+                Some(closest) => format!("Unknown key (did you mean `{closest}`?)"),
+                None => "Unknown key".to_string(),
+            };
+            Some(Violation { key, message })
+        })
+        .collect()
+}
+
+/// Targets rustc can compile for, asked once per process
+#[must_use]
+pub fn known_triples() -> Vec<String> {
+    static KNOWN: OnceLock<Vec<String>> = OnceLock::new();
+    KNOWN.get_or_init(Target::known_triples).clone()
+}
+
+/// Target entries that are neither a triple in `known`, an alias nor a
+/// custom target spec; nothing is checked if `known` is empty
+pub(super) fn unknown_targets(config: &Config, known: &[String]) -> Vec<Violation> {
+    if known.is_empty() {
+        return Vec::new();
+    }
+    let aliases = config.resolved_aliases();
+    let mut entries: Vec<(Vec<String>, String)> = Vec::new();
+    let mut add = |key: Vec<&str>, entry: &str| {
+        entries.push((
+            key.iter().map(ToString::to_string).collect(),
+            entry.to_string(),
+        ));
+    };
+
+    for (index, triple) in config.targets.default.iter().enumerate() {
+        add(vec!["targets", "default", &index.to_string()], triple);
+    }
+    for triple in config.targets.custom.keys() {
+        add(vec!["targets", triple], triple);
+    }
+    for (name, triples) in &config.target_groups {
+        for (index, triple) in triples.iter().enumerate() {
+            add(vec!["target-groups", name, &index.to_string()], triple);
+        }
+    }
+    for (name, profile) in &config.profiles {
+        for (index, triple) in profile.targets.iter().enumerate() {
+            add(
+                vec!["profiles", name, "targets", &index.to_string()],
+                triple,
+            );
+        }
+    }
+    for (name, triple) in &config.aliases {
+        add(vec!["aliases", name], triple);
+    }
+    for triple in config.container.images.keys() {
+        add(vec!["container", "images", triple], triple);
+    }
+
+    entries
+        .into_iter()
+        .filter_map(|(key, entry)| {
+            let triple = Target::resolve_alias_with(&entry, &aliases).unwrap_or_default();
+            let is_spec = Path::new(&triple)
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+            if is_spec || triple.is_empty() || known.contains(&triple) {
+                return None;
+            }
+            let message = match suggest::suggest(&triple, known).as_slice() {
+                [] => format!("Unknown target '{entry}'"),
+                suggestions => format!(
+                    "Unknown target '{entry}' (did you mean {}?)",
+                    suggestions.join(", ")
+                ),
+            };
+            Some(Violation { key, message })
+        })
+        .collect()
+}
+
+/// Strategy settings that contradict each other
+pub(super) fn strategy_conflicts(config: &Config) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let no_containers = config.container.use_when == "never";
+    let needs_container = |key: Vec<&str>| {
+        Violation::new(
+            &key,
+            "Needs a container, but [container] use_when = \"never\" rules them out",
+        )
+    };
+
+    for (triple, target) in &config.targets.custom {
+        let forced = target.force_container == Some(true);
+        match &target.strategy {
+            Some(strategy) if *strategy != Strategy::Container && forced => {
+                violations.push(Violation::new(
+                    &["targets", triple, "force_container"],
+                    format!("Conflicts with strategy = \"{strategy}\"; remove one of them"),
+                ));
+            }
+            Some(Strategy::Container) if no_containers => {
+                violations.push(needs_container(vec!["targets", triple, "strategy"]));
+            }
+            None if forced && no_containers => {
+                violations.push(needs_container(vec!["targets", triple, "force_container"]));
+            }
+            _ => {}
+        }
+    }
+    if no_containers && config.build.strategy == Some(Strategy::Container) {
+        violations.push(needs_container(vec!["build", "strategy"]));
+    }
+    for (name, profile) in &config.profiles {
+        if no_containers && profile.strategy == Some(Strategy::Container) {
+            violations.push(needs_container(vec!["profiles", name, "strategy"]));
+        }
+    }
+    violations
+}
+
+/// Line and column (1-based) of the setting at `key` in TOML `contents`
+fn locate(contents: &str, key: &[String]) -> Option<(usize, usize)> {
+    let document = toml_edit::ImDocument::parse(contents).ok()?;
+    let mut item = document.as_item().clone();
+    let mut span = None;
+    for segment in key {
+        if let Some(table) = item.as_table_like() {
+            let (name, value) = table.get_key_value(segment)?;
+            span = name.span();
+            item = value.clone();
+        } else if let Some(tables) = item.as_array_of_tables() {
+            let table = tables.get(segment.parse().ok()?)?;
+            span = table.span();
+            item = toml_edit::Item::Table(table.clone());
+        } else {
+            let value = item.as_array()?.get(segment.parse().ok()?)?;
+            span = value.span();
+            item = toml_edit::Item::Value(value.clone());
+        }
+    }
+    span.map(|span| line_and_column(contents, span.start))
+}
+
+/// The part of a parsed file at `key`
+fn lookup<'a>(value: &'a Value, key: &[String]) -> Option<&'a Value> {
+    key.iter().try_fold(value, |value, segment| match value {
+        Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+        value => value.get(segment),
+    })
+}
+
+/// Tie a violation to the last of `sources` that sets its key, by line and
+/// column if it is TOML, or to the last source if none does
+pub(super) fn locate_violation(sources: &[ConfigSource], violation: &Violation) -> ConfigProblem {
+    // The key as written in the file, under the source's table
+    let key_in = |source: &ConfigSource| -> Vec<String> {
+        source
+            .table()
+            .iter()
+            .map(ToString::to_string)
+            .chain(violation.key.iter().cloned())
+            .collect()
+    };
+    let found = sources.iter().rev().find_map(|source| {
+        let contents = std::fs::read_to_string(source.path()).ok()?;
+        let format = ConfigFormat::from_path(source.path());
+        let value = parse_value(&contents, format).ok()?;
+        let key = key_in(source);
+        lookup(&value, &key).map(|_| {
+            let location = match format {
+                ConfigFormat::Toml => locate(&contents, &key),
+                _ => None,
+            };
+            (source.path(), key, location)
+        })
+    });
+    let (path, key, location) = found.unwrap_or_else(|| match sources.last() {
+        Some(source) => (source.path(), key_in(source), None),
+        None => (Path::new(""), violation.key.clone(), None),
+    });
+    ConfigProblem {
+        path: path.to_path_buf(),
+        line: location.map(|(line, _)| line),
+        column: location.map(|(_, column)| column),
+        message: Violation {
+            key,
+            message: violation.message.clone(),
+        }
+        .to_string(),
+    }
+}
+
+/// Every problem with the configuration merged from `sources` (the global
+/// file first), each located in the source that causes it
+#[must_use]
+pub fn check(sources: &[ConfigSource]) -> Vec<ConfigProblem> {
+    let mut problems = Vec::new();
+    for source in sources {
+        match source.parse() {
+            Ok((_, value)) => problems.extend(
+                unknown_keys(&value)
+                    .iter()
+                    .map(|violation| locate_violation(std::slice::from_ref(source), violation)),
+            ),
+            Err(problem) => problems.push(problem),
+        }
+    }
+    if !problems.is_empty() {
+        return problems;
+    }
+
+    match super::layers::load(sources) {
+        Ok(config) => config
+            .violations()
+            .iter()
+            .map(|violation| locate_violation(sources, violation))
+            .collect(),
+        Err(problem) => vec![problem],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_dotted_key() {
+        let violation = Violation::new(&["targets", "default", "1"], "Unknown target 'x'");
+        assert_eq!(violation.dotted_key(), "targets.default[1]");
+        let violation = Violation::new(&["container", "images", "my.target"], "");
+        assert_eq!(violation.dotted_key(), "container.images.\"my.target\"");
+        assert_eq!(
+            Violation::new(&["build", "jobs"], "Must be greater than 0").to_string(),
+            "build.jobs: Must be greater than 0"
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let value = json!({
+            "build": { "paralel": false },
+            "targets": {
+                "default": ["x86_64-unknown-linux-gnu"],
+                "x86_64-unknown-linux-gnu": { "linkr": "cc" },
+            },
+            "hosts": [{ "name": "local", "kind": "local", "jobz": 2 }],
+        });
+        let mut violations = unknown_keys(&value);
+        violations.sort_by(|a, b| a.key.cmp(&b.key));
+        let found: Vec<String> = violations.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            found,
+            [
+                "build.paralel: Unknown key (did you mean `parallel`?)",
+                "hosts[0].jobz: Unknown key (did you mean `jobs`?)",
+                "targets.x86_64-unknown-linux-gnu.linkr: Unknown key (did you mean `linker`?)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_targets() {
+        let known = [
+            "aarch64-unknown-linux-gnu".to_string(),
+            "x86_64-unknown-linux-gnu".to_string(),
+        ];
+        let config = Config::from_str(
+            r#"
+            [targets]
+            default = ["x86_64-unknown-linux-gnu", "x86_64-linux-gnu", "rpi", "custom.json"]
+
+            [target-groups]
+            arm = ["aarch64-unknwon-linux-gnu"]
+
+            [aliases]
+            rpi = "aarch64-unknown-linux-gnu"
+        "#,
+        )
+        .unwrap();
+        let mut violations = unknown_targets(&config, &known);
+        violations.sort_by(|a, b| a.key.cmp(&b.key));
+        let found: Vec<String> = violations.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            found,
+            [
+                "target-groups.arm[0]: Unknown target 'aarch64-unknwon-linux-gnu' (did you mean aarch64-unknown-linux-gnu?)",
+                "targets.default[1]: Unknown target 'x86_64-linux-gnu' (did you mean x86_64-unknown-linux-gnu?)",
+            ]
+        );
+        assert!(unknown_targets(&config, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_strategy_conflicts() {
+        let config = Config::from_str(
+            r#"
+            [container]
+            use_when = "never"
+
+            [targets."x86_64-pc-windows-gnu"]
+            strategy = "zig"
+            force_container = true
+
+            [targets."x86_64-unknown-freebsd"]
+            strategy = "container"
+        "#,
+        )
+        .unwrap();
+        let mut violations = strategy_conflicts(&config);
+        violations.sort_by(|a, b| a.key.cmp(&b.key));
+        let keys: Vec<String> = violations.iter().map(Violation::dotted_key).collect();
+        assert_eq!(
+            keys,
+            [
+                "targets.x86_64-pc-windows-gnu.force_container",
+                "targets.x86_64-unknown-freebsd.strategy",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_locate() {
+        let contents = "[build]\njobs = 0\n\n[[hosts]]\nname = \"a\"\n\n[[hosts]]\nname = \"a\"\n\n[targets]\ndefault = [\"x\", \"y\"]\n";
+        let key = |key: &[&str]| -> Vec<String> { key.iter().map(ToString::to_string).collect() };
+        assert_eq!(locate(contents, &key(&["build", "jobs"])), Some((2, 1)));
+        assert_eq!(
+            locate(contents, &key(&["hosts", "1", "name"])),
+            Some((8, 1))
+        );
+        assert_eq!(
+            locate(contents, &key(&["targets", "default", "1"])),
+            Some((11, 17))
+        );
+        assert_eq!(locate(contents, &key(&["build", "cache"])), None);
+    }
+}
@@ -23,6 +23,27 @@ impl CrossImage {
     }
 }
 
+/// Split `image` into the reference it names and the digest it is pinned
+/// by, if any (`repo:tag@sha256:...`)
+#[must_use]
+pub fn split_digest(image: &str) -> (&str, Option<&str>) {
+    match image.split_once('@') {
+        Some((reference, digest)) => (reference, Some(digest)),
+        None => (image, None),
+    }
+}
+
+/// The repository of `image`, without its tag or digest
+#[must_use]
+pub fn repository(image: &str) -> &str {
+    let reference = split_digest(image).0;
+    match reference.rsplit_once(':') {
+        // A colon before the last slash separates a registry's port
+        Some((repository, tag)) if !tag.contains('/') => repository,
+        _ => reference,
+    }
+}
+
 /// Image selector for choosing appropriate images
 pub struct ImageSelector {
     /// Image registry (default: ghcr.io/cross-rs)
@@ -44,6 +65,14 @@ impl ImageSelector {
         Self { registry }
     }
 
+    /// Check if `image` comes from this selector's registry
+    #[must_use]
+    pub fn owns(&self, image: &str) -> bool {
+        image
+            .strip_prefix(&self.registry)
+            .is_some_and(|rest| rest.starts_with('/'))
+    }
+
     /// Select appropriate image for a target
     pub fn select_for_target(&self, target: &str) -> Result<CrossImage> {
         let (image_name, tag) = match target {
@@ -167,4 +196,36 @@ mod tests {
         let selector = ImageSelector::new();
         assert!(!selector.supported_targets().is_empty());
     }
+
+    #[test]
+    fn test_split_digest() {
+        let digest = format!("sha256:{}", "ab".repeat(32));
+        let pinned = format!("ghcr.io/cross-rs/aarch64-unknown-linux-gnu:main@{digest}");
+        assert_eq!(
+            split_digest(&pinned),
+            (
+                "ghcr.io/cross-rs/aarch64-unknown-linux-gnu:main",
+                Some(digest.as_str())
+            )
+        );
+        assert_eq!(split_digest("rust:latest"), ("rust:latest", None));
+
+        assert_eq!(
+            repository(&pinned),
+            "ghcr.io/cross-rs/aarch64-unknown-linux-gnu"
+        );
+        assert_eq!(repository("localhost:5000/cross"), "localhost:5000/cross");
+        assert_eq!(
+            repository("localhost:5000/cross:v1"),
+            "localhost:5000/cross"
+        );
+    }
+
+    #[test]
+    fn test_owns_image() {
+        let selector = ImageSelector::new();
+        assert!(selector.owns("ghcr.io/cross-rs/x86_64-pc-windows-gnu:latest"));
+        assert!(!selector.owns("ghcr.io/cross-rs-fork/app:latest"));
+        assert!(!selector.owns("rust:latest"));
+    }
 }
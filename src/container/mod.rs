@@ -3,18 +3,28 @@
 //! This module provides support for building in containers when native
 //! cross-compilation toolchains are not available or practical.
 
+use crate::cancel::RunLimits;
 use crate::error::{Error, Result};
+use crate::installs::{self, Installed};
+use crate::paths;
+use crate::shell::quote;
 
 mod images;
+pub mod publish;
+pub mod registry;
 mod runtime;
 
 pub use images::{CrossImage, ImageSelector};
-pub use runtime::{ContainerRuntime, RuntimeType};
+pub use runtime::{create_runtime, ContainerRuntime, ImageOutput, RuntimeType};
+
+/// Where the host's cargo home is mounted when the build runs as the host
+/// user, who can't reach `/root`
+const USER_CARGO_HOME: &str = "/cargo";
 
 /// Container build configuration
 #[derive(Debug, Clone)]
 pub struct ContainerConfig {
-    /// Runtime to use (docker, podman, auto)
+    /// Runtime to use (docker, podman, nerdctl, auto)
     pub runtime: RuntimeType,
 
     /// Container image to use
@@ -28,6 +38,25 @@ pub struct ContainerConfig {
 
     /// Working directory inside container
     pub workdir: String,
+
+    /// Cancellation and timeout for the container run
+    pub limits: RunLimits,
+
+    /// When to pull the image
+    pub pull_policy: PullPolicy,
+
+    /// Host user to run the build as, so the files it writes belong to
+    /// them; root when unset
+    pub user: Option<HostUser>,
+
+    /// Use the image only if it is already pulled, never pulling it
+    pub offline: bool,
+
+    /// Shell commands run before cargo, in the same container
+    pub pre_build: Vec<String>,
+
+    /// Cargo subcommand to run (`build` unless tests run in the container)
+    pub cargo_command: String,
 }
 
 impl Default for ContainerConfig {
@@ -38,14 +67,86 @@ impl Default for ContainerConfig {
             volumes: Vec::new(),
             env: Vec::new(),
             workdir: "/project".to_string(),
+            limits: RunLimits::default(),
+            pull_policy: PullPolicy::IfNotPresent,
+            user: None,
+            offline: false,
+            pre_build: Vec::new(),
+            cargo_command: "build".to_string(),
+        }
+    }
+}
+
+/// When a container build pulls its image (`[container] pull_policy`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PullPolicy {
+    /// Pull before every build, picking up a moved tag
+    Always,
+    /// Pull only when the image is missing
+    IfNotPresent,
+    /// Never pull; a missing image fails the build
+    Never,
+}
+
+impl PullPolicy {
+    /// Parse from string
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "always" => Ok(Self::Always),
+            "if-not-present" => Ok(Self::IfNotPresent),
+            "never" => Ok(Self::Never),
+            _ => Err(Error::Config(format!("Unknown pull policy: {s}"))),
+        }
+    }
+
+    /// Whether to pull an image that is `present` or not
+    #[must_use]
+    pub fn pulls(self, present: bool) -> bool {
+        match self {
+            Self::Always => true,
+            Self::IfNotPresent => !present,
+            Self::Never => false,
         }
     }
 }
 
+/// A user on the host, by numeric ids
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostUser {
+    /// User id
+    pub uid: u32,
+    /// Primary group id
+    pub gid: u32,
+}
+
+impl HostUser {
+    /// The user running xcargo, from `id`
+    ///
+    /// `None` for root, whose builds need no mapping, and where `id` is
+    /// missing, as on Windows.
+    #[must_use]
+    pub fn current() -> Option<Self> {
+        let id = |flag| {
+            let output = std::process::Command::new("id").arg(flag).output().ok()?;
+            if !output.status.success() {
+                return None;
+            }
+            String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+        };
+        let user = Self {
+            uid: id("-u")?,
+            gid: id("-g")?,
+        };
+        (user.uid != 0).then_some(user)
+    }
+}
+
 /// Container builder for executing builds in containers
 pub struct ContainerBuilder {
     runtime: Box<dyn ContainerRuntime>,
     image_selector: ImageSelector,
+    /// Host of the `[container] registry` mirror, which may need logging in to
+    mirror: Option<String>,
 }
 
 impl ContainerBuilder {
@@ -57,9 +158,21 @@ impl ContainerBuilder {
         Ok(Self {
             runtime,
             image_selector,
+            mirror: None,
         })
     }
 
+    /// Take images from `registry` (`host[/path]`), a mirror of
+    /// ghcr.io/cross-rs, when it is set
+    #[must_use]
+    pub fn with_registry(mut self, registry: Option<&str>) -> Self {
+        if let Some(registry) = registry {
+            self.image_selector = ImageSelector::with_registry(registry.to_string());
+            self.mirror = registry.split('/').next().map(str::to_string);
+        }
+        self
+    }
+
     /// Check if the container runtime is available
     #[must_use]
     pub fn is_available(&self) -> bool {
@@ -72,6 +185,66 @@ impl ContainerBuilder {
         self.runtime.name()
     }
 
+    /// Remove the cross-compilation images pulled for container builds
+    ///
+    /// Returns each removed image with the space it used.
+    pub fn remove_images(&self) -> Result<Vec<(String, u64)>> {
+        let mut removed = Vec::new();
+        for image in self.runtime.list_images()? {
+            if !self.image_selector.owns(&image) {
+                continue;
+            }
+            let size = self.runtime.image_size(&image).unwrap_or(0);
+            self.runtime.remove_image(&image)?;
+            removed.push((image, size));
+        }
+        Ok(removed)
+    }
+
+    /// Remove one local image
+    pub fn remove_image(&self, image: &str) -> Result<()> {
+        self.runtime.remove_image(image)
+    }
+
+    /// Whether `image` is already pulled
+    #[must_use]
+    pub fn has_image(&self, image: &str) -> bool {
+        if images::split_digest(image).1.is_some() {
+            return self.runtime.has_image(image);
+        }
+        self.runtime
+            .list_images()
+            .is_ok_and(|images| images.iter().any(|name| name == image))
+    }
+
+    /// Pull the tag `image` names and return it pinned by the digest pulled
+    pub fn pin(&self, image: &str) -> Result<String> {
+        let reference = images::split_digest(image).0;
+        self.pull(reference)?;
+        let digest = self.runtime.repo_digest(reference)?;
+        Ok(format!("{reference}@{digest}"))
+    }
+
+    /// Pull `image`, logging in to the mirror first when it comes from there
+    fn pull(&self, image: &str) -> Result<()> {
+        let host = registry::host(image);
+        if self.mirror.as_deref() != Some(host) {
+            return self.runtime.pull_image(image);
+        }
+        let authenticated = registry::ensure_login(self.runtime.as_ref(), host)?;
+        self.runtime.pull_image(image).map_err(|e| {
+            if authenticated {
+                return e;
+            }
+            Error::Container(format!(
+                "{e}\n{host} may need credentials: run `{} login {host}` or set {} and {}",
+                self.runtime.name(),
+                registry::ENV_REGISTRY_USER,
+                registry::ENV_REGISTRY_PASSWORD
+            ))
+        })
+    }
+
     /// Select appropriate image for target
     pub fn select_image(&self, target: &str) -> Result<CrossImage> {
         self.image_selector.select_for_target(target)
@@ -93,40 +266,90 @@ impl ContainerBuilder {
         }
 
         // Select image if not specified
-        let image = if config.image.is_empty() {
-            self.select_image(target)?.full_name()
-        } else {
-            config.image.clone()
-        };
+        let mut config = config.clone();
+        if config.image.is_empty() {
+            config.image = self.select_image(target)?.full_name();
+        }
+        let image = config.image.clone();
 
-        // Pull image if needed
-        self.runtime.pull_image(&image)?;
+        // Pull image as the policy says, recording it for `xcargo uninstall`
+        // when it is new
+        let present = self.has_image(&image);
+        if !present && config.offline {
+            return Err(Error::Container(format!(
+                "Image {image} is not pulled and the build is offline"
+            )));
+        }
+        if !present && config.pull_policy == PullPolicy::Never {
+            return Err(Error::Container(format!(
+                "Image {image} is not pulled and [container] pull_policy is \"never\""
+            )));
+        }
+        if !config.offline && config.pull_policy.pulls(present) {
+            self.pull(&image)?;
+            if !present {
+                installs::record(Installed::image(&image, target));
+            }
+        }
 
         // Build the container command
-        let mut volumes = config.volumes.clone();
 
         // Add current directory as volume
         let current_dir = std::env::current_dir()
             .map_err(|e| Error::Container(format!("Failed to get current directory: {e}")))?;
-        let current_dir_str = current_dir.to_string_lossy().to_string();
-        volumes.push((current_dir_str.clone(), config.workdir.clone()));
+        let current_dir_str = paths::simplify(&current_dir).to_string_lossy().to_string();
+        config
+            .volumes
+            .push((current_dir_str.clone(), config.workdir.clone()));
 
-        // Add cargo cache volume for faster builds
-        if let Ok(home) = std::env::var("HOME") {
-            let cargo_cache = format!("{home}/.cargo");
-            volumes.push((cargo_cache, "/root/.cargo".to_string()));
+        // Add cargo cache volume for faster builds (HOME is usually unset on Windows)
+        let cargo_home = std::env::var_os("CARGO_HOME")
+            .map(std::path::PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")));
+        if let Some(cargo_cache) = cargo_home {
+            let mount = if config.user.is_some() {
+                USER_CARGO_HOME
+            } else {
+                "/root/.cargo"
+            };
+            config
+                .volumes
+                .push((cargo_cache.to_string_lossy().to_string(), mount.to_string()));
+        }
+        // A user the image doesn't know has no home; give cargo and the
+        // tools it runs writable ones
+        if config.user.is_some() {
+            config
+                .env
+                .push(("CARGO_HOME".to_string(), USER_CARGO_HOME.to_string()));
+            config.env.push(("HOME".to_string(), "/tmp".to_string()));
         }
 
         // Build cargo command
-        let mut cmd = vec!["cargo".to_string(), "build".to_string()];
+        let mut cmd = vec!["cargo".to_string(), config.cargo_command.clone()];
         cmd.push("--target".to_string());
         cmd.push(target.to_string());
         cmd.extend_from_slice(cargo_args);
 
         // Run in container
         self.runtime
-            .run(&image, &cmd, &volumes, &config.env, &config.workdir)
+            .run(&with_pre_build(cmd, &config.pre_build), &config)
+    }
+}
+
+/// `cmd`, run by a shell after the `pre_build` commands, if there are any
+fn with_pre_build(cmd: Vec<String>, pre_build: &[String]) -> Vec<String> {
+    if pre_build.is_empty() {
+        return cmd;
     }
+    let cargo: Vec<String> = cmd.iter().map(|arg| quote(arg)).collect();
+    let script = pre_build
+        .iter()
+        .cloned()
+        .chain([format!("exec {}", cargo.join(" "))])
+        .collect::<Vec<_>>()
+        .join(" && ");
+    vec!["sh".to_string(), "-c".to_string(), script]
 }
 
 #[cfg(test)]
@@ -138,6 +361,37 @@ mod tests {
         let config = ContainerConfig::default();
         assert_eq!(config.runtime, RuntimeType::Auto);
         assert_eq!(config.workdir, "/project");
+        assert_eq!(config.pull_policy, PullPolicy::IfNotPresent);
+    }
+
+    #[test]
+    fn test_pull_policy() {
+        assert!(PullPolicy::from_str("always").unwrap().pulls(true));
+        assert!(PullPolicy::from_str("if-not-present").unwrap().pulls(false));
+        assert!(!PullPolicy::IfNotPresent.pulls(true));
+        assert!(!PullPolicy::from_str("never").unwrap().pulls(false));
+        assert!(PullPolicy::from_str("sometimes").is_err());
+    }
+
+    #[test]
+    fn test_with_pre_build() {
+        let cmd = vec![
+            "cargo".to_string(),
+            "build".to_string(),
+            "--features=a b".to_string(),
+        ];
+        assert_eq!(with_pre_build(cmd.clone(), &[]), cmd);
+        assert_eq!(
+            with_pre_build(
+                cmd,
+                &["apt-get update".to_string(), "./setup.sh".to_string()]
+            ),
+            [
+                "sh",
+                "-c",
+                "apt-get update && ./setup.sh && exec 'cargo' 'build' '--features=a b'"
+            ]
+        );
     }
 
     #[test]
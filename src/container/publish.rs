@@ -0,0 +1,434 @@
+//! Container images from built binaries
+//!
+//! `xcargo docker build` packages a binary built for one or more Linux
+//! targets into a minimal image: the binary alone on top of `[docker] base`
+//! (`scratch` by default, or a distroless image for binaries that need
+//! libc). With several targets, the container runtime builds one image per
+//! platform under a multi-arch manifest.
+//!
+//! ```toml
+//! [docker]
+//! base = "gcr.io/distroless/static-debian12"
+//! tags = ["ghcr.io/acme/{name}:{version}", "ghcr.io/acme/{name}:latest"]
+//! ```
+//!
+//! Tags may use `{name}` (the binary), `{version}` (its package's version)
+//! and `{git_sha}` (the short commit hash).
+//!
+//! `xcargo docker push --multi-arch` pushes one image per target instead,
+//! tagged `<tag>-<arch>`, then combines them under the tags in a manifest
+//! list annotated with the xcargo and rustc versions that built them.
+//! Pushing logs in as [`registry`](super::registry) describes.
+
+use crate::build::artifacts::{self, ArtifactKind};
+use crate::error::{Error, Result};
+use crate::target::Target;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::{ContainerRuntime, ImageOutput};
+
+/// The empty base image, with no C library for binaries to link against
+pub const SCRATCH: &str = "scratch";
+
+/// Path of the binary inside the image
+const ENTRYPOINT_DIR: &str = "/usr/local/bin";
+
+/// Manifest list annotation recording the xcargo version
+pub const ANNOTATION_XCARGO: &str = "io.github.xcargo.version";
+/// Manifest list annotation recording the rustc version
+pub const ANNOTATION_RUSTC: &str = "io.github.xcargo.rustc";
+
+/// Tag of the `platform` image a manifest list under `tag` points at, e.g.
+/// `app:1.0-arm64` for `app:1.0` and `linux/arm64`
+#[must_use]
+pub fn arch_tag(tag: &str, platform: &str) -> String {
+    let suffix = platform
+        .strip_prefix("linux/")
+        .unwrap_or(platform)
+        .replace('/', "-");
+    let name_start = tag.rfind('/').map_or(0, |slash| slash + 1);
+    if tag[name_start..].contains(':') {
+        format!("{tag}-{suffix}")
+    } else {
+        format!("{tag}:latest-{suffix}")
+    }
+}
+
+/// Container platform of a Linux target, as `os/arch[/variant]`
+pub fn platform(target: &Target) -> Result<String> {
+    if target.os != "linux" {
+        return Err(Error::Config(format!(
+            "Container images need a Linux target, not {}",
+            target.triple
+        )));
+    }
+    let arch = match target.triple_arch() {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "armv7" | "thumbv7neon" => "arm/v7",
+        "arm" => "arm/v6",
+        "i686" | "i586" => "386",
+        "riscv64gc" | "riscv64" => "riscv64",
+        "powerpc64le" => "ppc64le",
+        "s390x" => "s390x",
+        "loongarch64" => "loong64",
+        arch => {
+            return Err(Error::Config(format!(
+                "No container platform for the {arch} architecture ({})",
+                target.triple
+            )))
+        }
+    };
+    Ok(format!("linux/{arch}"))
+}
+
+/// Whether binaries for `target` run on a base image without libc
+#[must_use]
+pub fn runs_on_scratch(target: &Target) -> bool {
+    target.env.as_deref() == Some("musl")
+}
+
+/// A binary built for `target`, and the package it belongs to
+///
+/// `bin` picks one when the workspace builds several.
+pub fn find_binary(target: &Target, profile: &str, bin: Option<&str>) -> Result<(PathBuf, String)> {
+    let (target_dir, products) = artifacts::workspace_products()?;
+    let build_dir = target_dir.join(&target.triple).join(profile);
+    let mut built: Vec<(PathBuf, String)> = products
+        .iter()
+        .filter(|product| product.kind == ArtifactKind::Bin)
+        .filter(|product| bin.map_or(true, |bin| product.name == bin))
+        .map(|product| {
+            (
+                build_dir.join(product.file_name(target)),
+                product.package.clone(),
+            )
+        })
+        .filter(|(path, _)| path.is_file())
+        .collect();
+
+    match built.len() {
+        0 => Err(Error::Build(format!(
+            "No {}binary found in target/{}/{profile}/",
+            bin.map(|bin| format!("{bin} ")).unwrap_or_default(),
+            target.triple
+        ))),
+        1 => Ok(built.remove(0)),
+        _ => {
+            let names: Vec<String> = built
+                .iter()
+                .filter_map(|(path, _)| path.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .collect();
+            Err(Error::Config(format!(
+                "Several binaries were built ({}); choose one with --bin",
+                names.join(", ")
+            )))
+        }
+    }
+}
+
+/// Short hash of the current commit
+fn git_sha() -> Result<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .map_err(|e| Error::Config(format!("Failed to run git for {{git_sha}}: {e}")))?;
+    if !output.status.success() {
+        return Err(Error::Config(
+            "{git_sha} is only available in a git repository with commits".to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// `templates` with `{name}`, `{version}` and `{git_sha}` replaced
+pub fn tags(templates: &[String], name: &str, version: &str) -> Result<Vec<String>> {
+    let sha = if templates.iter().any(|tag| tag.contains("{git_sha}")) {
+        git_sha()?
+    } else {
+        String::new()
+    };
+    Ok(templates
+        .iter()
+        .map(|tag| {
+            tag.replace("{name}", name)
+                .replace("{version}", version)
+                .replace("{git_sha}", &sha)
+        })
+        .collect())
+}
+
+/// An image to build from one binary per platform
+#[derive(Debug, Clone)]
+pub struct ImageBuild {
+    /// Base image
+    pub base: String,
+    /// Name of the binary in the image
+    pub name: String,
+    /// Version recorded in the image's labels
+    pub version: String,
+    /// Binaries, by platform
+    pub binaries: Vec<(String, PathBuf)>,
+    /// Tags the image gets
+    pub tags: Vec<String>,
+    /// Push the image instead of loading it locally
+    pub push: bool,
+}
+
+impl ImageBuild {
+    /// Whether the image covers several platforms
+    #[must_use]
+    pub fn is_multi_arch(&self) -> bool {
+        self.binaries.len() > 1
+    }
+
+    /// The Dockerfile, copying each platform's binary from
+    /// `<platform>/<name>` in the build context
+    #[must_use]
+    pub fn dockerfile(&self) -> String {
+        let source = if self.is_multi_arch() {
+            format!("${{TARGETPLATFORM}}/{}", self.name)
+        } else {
+            format!(
+                "{}/{}",
+                self.binaries.first().map_or("", |(platform, _)| platform),
+                self.name
+            )
+        };
+        let mut dockerfile = format!("FROM {}\n", self.base);
+        if self.is_multi_arch() {
+            dockerfile.push_str("ARG TARGETPLATFORM\n");
+        }
+        let _ = write!(
+            dockerfile,
+            "LABEL org.opencontainers.image.title=\"{name}\" org.opencontainers.image.version=\"{version}\"\n\
+             COPY --chmod=755 {source} {ENTRYPOINT_DIR}/{name}\n\
+             ENTRYPOINT [\"{ENTRYPOINT_DIR}/{name}\"]\n",
+            name = self.name,
+            version = self.version,
+        );
+        dockerfile
+    }
+
+    /// One image per platform, pushed under its [`arch_tag`]s, for a
+    /// manifest list under this image's tags
+    #[must_use]
+    pub fn per_arch(&self) -> Vec<ImageBuild> {
+        self.binaries
+            .iter()
+            .map(|(platform, binary)| ImageBuild {
+                binaries: vec![(platform.clone(), binary.clone())],
+                tags: self
+                    .tags
+                    .iter()
+                    .map(|tag| arch_tag(tag, platform))
+                    .collect(),
+                push: true,
+                ..self.clone()
+            })
+            .collect()
+    }
+
+    /// Write the build context to `dir`: the Dockerfile and the binaries
+    pub fn stage(&self, dir: &Path) -> Result<()> {
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+        for (platform, binary) in &self.binaries {
+            let platform_dir = dir.join(platform);
+            fs::create_dir_all(&platform_dir)?;
+            fs::copy(binary, platform_dir.join(&self.name))?;
+        }
+        fs::write(dir.join("Dockerfile"), self.dockerfile())?;
+        Ok(())
+    }
+
+    /// Where the image goes: pushed, or else loaded into the local image
+    /// store, except for a multi-arch image, which the store may not hold
+    /// and is written to `archive` instead
+    #[must_use]
+    pub fn output(&self, archive: &Path) -> ImageOutput {
+        if self.push {
+            ImageOutput::Push
+        } else if self.is_multi_arch() {
+            ImageOutput::Archive(archive.to_path_buf())
+        } else {
+            ImageOutput::Load
+        }
+    }
+
+    /// Platforms the image is built for
+    #[must_use]
+    pub fn platforms(&self) -> Vec<String> {
+        self.binaries
+            .iter()
+            .map(|(platform, _)| platform.clone())
+            .collect()
+    }
+
+    /// Stage the image in `context` and build it with `runtime`
+    pub fn build(
+        &self,
+        runtime: &dyn ContainerRuntime,
+        context: &Path,
+        archive: &Path,
+    ) -> Result<()> {
+        self.stage(context)?;
+        runtime.build_image(
+            context,
+            &self.platforms(),
+            &self.tags,
+            &self.output(archive),
+        )
+    }
+}
+
+/// `rustc --version` of the toolchain in use
+#[must_use]
+pub fn rustc_version() -> Option<String> {
+    let output = Command::new("rustc").arg("--version").output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Annotations for a manifest list of an image at `version`, recording
+/// the xcargo and rustc versions that built it
+#[must_use]
+pub fn annotations(version: &str, rustc: Option<&str>) -> Vec<(String, String)> {
+    let mut annotations = vec![
+        (
+            "org.opencontainers.image.version".to_string(),
+            version.to_string(),
+        ),
+        (
+            ANNOTATION_XCARGO.to_string(),
+            env!("CARGO_PKG_VERSION").to_string(),
+        ),
+    ];
+    if let Some(rustc) = rustc {
+        annotations.push((ANNOTATION_RUSTC.to_string(), rustc.to_string()));
+    }
+    annotations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(platforms: &[&str]) -> ImageBuild {
+        ImageBuild {
+            base: SCRATCH.to_string(),
+            name: "app".to_string(),
+            version: "1.2.0".to_string(),
+            binaries: platforms
+                .iter()
+                .map(|platform| ((*platform).to_string(), PathBuf::from("app")))
+                .collect(),
+            tags: vec!["acme/app:1.2.0".to_string()],
+            push: false,
+        }
+    }
+
+    #[test]
+    fn test_platform() {
+        let platform = |triple| platform(&Target::from_triple(triple).unwrap());
+        assert_eq!(
+            platform("x86_64-unknown-linux-musl").unwrap(),
+            "linux/amd64"
+        );
+        assert_eq!(
+            platform("aarch64-unknown-linux-gnu").unwrap(),
+            "linux/arm64"
+        );
+        assert_eq!(
+            platform("armv7-unknown-linux-musleabihf").unwrap(),
+            "linux/arm/v7"
+        );
+        assert!(platform("x86_64-pc-windows-gnu").is_err());
+    }
+
+    #[test]
+    fn test_tags() {
+        let templates = vec!["{name}:{version}".to_string(), "acme/{name}".to_string()];
+        assert_eq!(
+            tags(&templates, "app", "1.2.0").unwrap(),
+            ["app:1.2.0", "acme/app"]
+        );
+    }
+
+    #[test]
+    fn test_dockerfile() {
+        let single = image(&["linux/amd64"]).dockerfile();
+        assert!(single.starts_with("FROM scratch\n"));
+        assert!(single.contains("COPY --chmod=755 linux/amd64/app /usr/local/bin/app\n"));
+        assert!(!single.contains("TARGETPLATFORM"));
+
+        let multi = image(&["linux/amd64", "linux/arm64"]).dockerfile();
+        assert!(multi.contains("ARG TARGETPLATFORM\n"));
+        assert!(multi.contains("COPY --chmod=755 ${TARGETPLATFORM}/app /usr/local/bin/app\n"));
+        assert!(multi.contains("org.opencontainers.image.version=\"1.2.0\""));
+    }
+
+    #[test]
+    fn test_output() {
+        let archive = Path::new("app.tar");
+        assert_eq!(image(&["linux/amd64"]).output(archive), ImageOutput::Load);
+
+        let mut multi = image(&["linux/amd64", "linux/arm64"]);
+        assert_eq!(
+            multi.output(archive),
+            ImageOutput::Archive(archive.to_path_buf())
+        );
+        assert_eq!(multi.platforms(), ["linux/amd64", "linux/arm64"]);
+        multi.push = true;
+        assert_eq!(multi.output(archive), ImageOutput::Push);
+    }
+
+    #[test]
+    fn test_arch_tag() {
+        assert_eq!(arch_tag("app:1.0", "linux/arm64"), "app:1.0-arm64");
+        assert_eq!(
+            arch_tag("localhost:5000/app", "linux/arm/v7"),
+            "localhost:5000/app:latest-arm-v7"
+        );
+    }
+
+    #[test]
+    fn test_per_arch() {
+        let mut image = image(&["linux/amd64", "linux/arm64"]);
+        image.push = false;
+        let per_arch = image.per_arch();
+        assert_eq!(per_arch.len(), 2);
+        assert_eq!(per_arch[1].tags, ["acme/app:1.2.0-arm64"]);
+        assert!(per_arch[1].push && !per_arch[1].is_multi_arch());
+
+        let annotations = annotations("1.2.0", Some("rustc 1.80.0"));
+        assert_eq!(annotations[0].1, "1.2.0");
+        assert_eq!(
+            annotations[2],
+            (ANNOTATION_RUSTC.to_string(), "rustc 1.80.0".to_string())
+        );
+    }
+
+    #[test]
+    fn test_stage() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let binary = dir.path().join("built");
+        fs::write(&binary, "binary").unwrap();
+        let mut image = image(&["linux/arm64"]);
+        image.binaries[0].1 = binary;
+
+        let context = dir.path().join("context");
+        image.stage(&context).unwrap();
+        assert!(context.join("linux/arm64/app").is_file());
+        assert!(fs::read_to_string(context.join("Dockerfile"))
+            .unwrap()
+            .contains("linux/arm64/app"));
+    }
+}
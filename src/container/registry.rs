@@ -0,0 +1,194 @@
+//! Container registries and the credentials for them
+//!
+//! Build images come from ghcr.io/cross-rs unless `[container] registry`
+//! names a mirror, as networks that block public registries need:
+//!
+//! ```toml
+//! [container]
+//! registry = "registry.corp.example/cross-rs"
+//! ```
+//!
+//! xcargo reuses the credentials the runtime already has: `docker login`
+//! entries, credential helpers (`credHelpers`) and stores (`credsStore`) in
+//! `~/.docker/config.json` (or `$DOCKER_CONFIG/config.json`), and Podman's
+//! `auth.json`. When `XCARGO_REGISTRY_USER` and `XCARGO_REGISTRY_PASSWORD`
+//! are set (or `GITHUB_ACTOR` and `GITHUB_TOKEN` for ghcr.io), it logs in
+//! with them before pulling from the mirror or pushing.
+
+use crate::error::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use super::ContainerRuntime;
+
+/// Registry user to log in as before pulling from a mirror or pushing
+pub const ENV_REGISTRY_USER: &str = "XCARGO_REGISTRY_USER";
+/// Password or token for [`ENV_REGISTRY_USER`]
+pub const ENV_REGISTRY_PASSWORD: &str = "XCARGO_REGISTRY_PASSWORD";
+
+/// Registry host an image reference points at, `docker.io` when it names
+/// none
+#[must_use]
+pub fn host(image: &str) -> &str {
+    match image.split_once('/') {
+        Some((host, _)) if host.contains(['.', ':']) || host == "localhost" => host,
+        _ => "docker.io",
+    }
+}
+
+/// Credentials to log in to a registry with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryLogin {
+    /// Registry host
+    pub registry: String,
+    /// User name
+    pub user: String,
+    /// Password or token
+    pub password: String,
+}
+
+impl RegistryLogin {
+    /// Credentials for `registry` from the environment
+    ///
+    /// [`ENV_REGISTRY_USER`] and [`ENV_REGISTRY_PASSWORD`], or for ghcr.io
+    /// the `GITHUB_ACTOR` and `GITHUB_TOKEN` of a GitHub Actions job.
+    pub fn resolve(registry: &str, lookup: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let (user, password) = match (lookup(ENV_REGISTRY_USER), lookup(ENV_REGISTRY_PASSWORD)) {
+            (Some(user), Some(password)) => (user, password),
+            _ if registry == "ghcr.io" => (lookup("GITHUB_ACTOR")?, lookup("GITHUB_TOKEN")?),
+            _ => return None,
+        };
+        Some(Self {
+            registry: registry.to_string(),
+            user,
+            password,
+        })
+    }
+}
+
+/// Whether the runtime has credentials for `registry`, in its auth file
+/// `config` (see [`auth_files`])
+///
+/// A credential store holds them for every registry, so its presence
+/// counts as having them.
+#[must_use]
+pub fn has_credentials(config: &str, registry: &str) -> bool {
+    let Ok(config) = serde_json::from_str::<serde_json::Value>(config) else {
+        return false;
+    };
+    if config.get("credsStore").is_some() {
+        return true;
+    }
+    let host = if registry == "docker.io" {
+        "index.docker.io"
+    } else {
+        registry
+    };
+    ["auths", "credHelpers"].iter().any(|key| {
+        config
+            .get(key)
+            .and_then(serde_json::Value::as_object)
+            .is_some_and(|entries| entries.keys().any(|entry| entry.contains(host)))
+    })
+}
+
+/// Files `runtime` reads registry credentials from, in order: Podman's
+/// `auth.json` files, then the `config.json` docker and nerdctl share and
+/// Podman falls back to
+#[must_use]
+pub fn auth_files(runtime: &str) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    if runtime == "podman" {
+        if let Some(file) = std::env::var_os("REGISTRY_AUTH_FILE") {
+            files.push(PathBuf::from(file));
+        } else {
+            if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR") {
+                files.push(PathBuf::from(dir).join("containers").join("auth.json"));
+            }
+            if let Some(dir) = dirs::config_dir() {
+                files.push(dir.join("containers").join("auth.json"));
+            }
+        }
+    }
+    match std::env::var_os("DOCKER_CONFIG") {
+        Some(dir) => files.push(PathBuf::from(dir).join("config.json")),
+        None => files.extend(dirs::home_dir().map(|home| home.join(".docker").join("config.json"))),
+    }
+    files
+}
+
+/// Whether `runtime` has credentials for `registry` in one of its
+/// [`auth_files`]
+#[must_use]
+pub fn has_stored_credentials(runtime: &str, registry: &str) -> bool {
+    auth_files(runtime)
+        .iter()
+        .filter_map(|file| fs::read_to_string(file).ok())
+        .any(|config| has_credentials(&config, registry))
+}
+
+/// Log `runtime` in to `registry` with credentials from the environment
+/// (see [`RegistryLogin::resolve`]), if they are set
+///
+/// Returns whether the runtime has credentials for the registry, from the
+/// environment or stored.
+pub fn ensure_login(runtime: &dyn ContainerRuntime, registry: &str) -> Result<bool> {
+    match RegistryLogin::resolve(registry, |key| std::env::var(key).ok()) {
+        Some(login) => {
+            runtime.login(registry, &login.user, &login.password)?;
+            Ok(true)
+        }
+        None => Ok(has_stored_credentials(runtime.name(), registry)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host() {
+        assert_eq!(host("ghcr.io/acme/app:1.0"), "ghcr.io");
+        assert_eq!(host("localhost:5000/app"), "localhost:5000");
+        assert_eq!(host("acme/app"), "docker.io");
+        assert_eq!(host("app:1.0"), "docker.io");
+    }
+
+    #[test]
+    fn test_registry_login() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |key: &str| {
+                vars.iter()
+                    .find(|(name, _)| *name == key)
+                    .map(|(_, value)| (*value).to_string())
+            }
+        };
+        let login = RegistryLogin::resolve(
+            "quay.io",
+            env(&[
+                (ENV_REGISTRY_USER, "bot"),
+                (ENV_REGISTRY_PASSWORD, "secret"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(login.user, "bot");
+        assert_eq!(login.registry, "quay.io");
+
+        let github = env(&[("GITHUB_ACTOR", "octocat"), ("GITHUB_TOKEN", "token")]);
+        assert_eq!(
+            RegistryLogin::resolve("ghcr.io", github).unwrap().user,
+            "octocat"
+        );
+        assert!(RegistryLogin::resolve("quay.io", github).is_none());
+    }
+
+    #[test]
+    fn test_has_credentials() {
+        let config = r#"{"auths":{"https://index.docker.io/v1/":{},"ghcr.io":{"auth":"x"}}}"#;
+        assert!(has_credentials(config, "docker.io"));
+        assert!(has_credentials(config, "ghcr.io"));
+        assert!(!has_credentials(config, "quay.io"));
+        assert!(has_credentials(r#"{"credsStore":"desktop"}"#, "quay.io"));
+        assert!(!has_credentials("", "ghcr.io"));
+    }
+}
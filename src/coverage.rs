@@ -0,0 +1,431 @@
+//! Code coverage of tests, on the host and cross-compiled
+//!
+//! `xcargo coverage` runs `cargo test` with `-C instrument-coverage` in a
+//! target directory of its own, so instrumented builds never replace the
+//! regular ones. The tests run wherever the target's tests run: natively,
+//! under a configured runner or QEMU, in the iOS simulator or in a
+//! container image. They all write raw profiles (`.profraw`) to a directory
+//! the host can read, which llvm-profdata merges and llvm-cov turns into an
+//! lcov report and, optionally, HTML. Everything for a target is kept in
+//! `target/xcargo/coverage/<triple>`:
+//!
+//! ```text
+//! target/xcargo/coverage/aarch64-unknown-linux-gnu/
+//! ├── target/            instrumented build
+//! ├── profraw/           raw profiles of the last run
+//! ├── coverage.profdata  merged profile
+//! ├── lcov.info
+//! └── html/index.html    with --html
+//! ```
+//!
+//! The LLVM tools come from the toolchain's `llvm-tools` component, whose
+//! LLVM matches the one that wrote the profiles.
+
+use crate::build::artifacts;
+use crate::error::{Error, Result};
+use crate::output::helpers;
+use crate::target::Target;
+use crate::trace;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Rustflags instrumenting a build for coverage
+pub const RUSTFLAGS: &[&str] = &["-C", "instrument-coverage"];
+
+/// Coverage files inside cargo's target directory
+const COVERAGE_DIR: &str = "xcargo/coverage";
+
+/// File name pattern of raw profiles: one per process and binary
+const PROFILE_FILE: &str = "xcargo-%p-%m.profraw";
+
+/// Sources left out of reports: dependencies and the standard library
+const IGNORE_FILENAMES: &str =
+    r"[/\\]\.cargo[/\\](registry|git)[/\\]|[/\\]rustc[/\\][0-9a-f]+[/\\]";
+
+/// Kind of report to write besides lcov
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// `lcov.info` only
+    #[default]
+    Lcov,
+    /// `lcov.info` and an HTML report
+    Html,
+}
+
+/// Whether coverage can be collected for `target` at all
+///
+/// WebAssembly has no profiler runtime to write the profiles.
+#[must_use]
+pub fn supports(target: &Target) -> bool {
+    !target.arch.starts_with("wasm")
+}
+
+/// Coverage files of one target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetCoverage {
+    triple: String,
+    dir: PathBuf,
+}
+
+impl TargetCoverage {
+    /// Coverage of `triple` under cargo's `target_dir`
+    #[must_use]
+    pub fn new(target_dir: &Path, triple: &str) -> Self {
+        Self {
+            triple: triple.to_string(),
+            dir: target_dir.join(COVERAGE_DIR).join(triple),
+        }
+    }
+
+    /// Coverage of `triple` in the current workspace
+    pub fn for_target(triple: &str) -> Result<Self> {
+        let (target_dir, _) = artifacts::workspace_products()?;
+        Ok(Self::new(&target_dir, triple))
+    }
+
+    /// Directory with everything of this target
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Target directory of the instrumented build
+    #[must_use]
+    pub fn cargo_target_dir(&self) -> PathBuf {
+        self.dir.join("target")
+    }
+
+    /// Directory the tests write their raw profiles to
+    #[must_use]
+    pub fn profraw_dir(&self) -> PathBuf {
+        self.dir.join("profraw")
+    }
+
+    /// `LLVM_PROFILE_FILE` for the tests
+    #[must_use]
+    pub fn profile_file(&self) -> PathBuf {
+        self.profraw_dir().join(PROFILE_FILE)
+    }
+
+    /// The lcov report
+    #[must_use]
+    pub fn lcov(&self) -> PathBuf {
+        self.dir.join("lcov.info")
+    }
+
+    /// Directory of the HTML report
+    #[must_use]
+    pub fn html_dir(&self) -> PathBuf {
+        self.dir.join("html")
+    }
+
+    /// Remove the profiles of the last run, so reports only count this one
+    pub fn prepare(&self) -> Result<()> {
+        let profraw = self.profraw_dir();
+        if profraw.exists() {
+            fs::remove_dir_all(&profraw)?;
+        }
+        fs::create_dir_all(&profraw)?;
+        Ok(())
+    }
+
+    /// Raw profiles the tests wrote
+    #[must_use]
+    pub fn profraws(&self) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(self.profraw_dir()) else {
+            return Vec::new();
+        };
+        let mut profraws: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "profraw"))
+            .collect();
+        profraws.sort();
+        profraws
+    }
+
+    /// Executables of the instrumented build in `profile`: test harnesses
+    /// and the binaries they test
+    #[must_use]
+    pub fn objects(&self, profile: &str) -> Vec<PathBuf> {
+        let deps = self
+            .cargo_target_dir()
+            .join(&self.triple)
+            .join(profile)
+            .join("deps");
+        let Ok(entries) = fs::read_dir(deps) else {
+            return Vec::new();
+        };
+        let mut objects: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && is_executable(path))
+            .collect();
+        objects.sort();
+        objects
+    }
+
+    /// Merge the raw profiles and write the reports
+    ///
+    /// Paths the build saw under `remapped.0` (e.g., the project mounted in
+    /// a container) are read from `remapped.1` on the host.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tests wrote no profiles, the LLVM tools are
+    /// missing or fail.
+    pub fn report(
+        &self,
+        profile: &str,
+        format: Format,
+        toolchain: Option<&str>,
+        remapped: Option<(&str, &Path)>,
+    ) -> Result<Summary> {
+        let profraws = self.profraws();
+        if profraws.is_empty() {
+            return Err(Error::Build(format!(
+                "The tests for {} wrote no coverage data to {}",
+                self.triple,
+                self.profraw_dir().display()
+            )));
+        }
+        let objects = self.objects(profile);
+        let Some((first, others)) = objects.split_first() else {
+            return Err(Error::Build(format!(
+                "No instrumented test binaries for {} were found",
+                self.triple
+            )));
+        };
+
+        let profdata = self.dir.join("coverage.profdata");
+        let mut merge = Command::new(llvm_tool("profdata", toolchain)?);
+        merge
+            .args(["merge", "-sparse"])
+            .args(&profraws)
+            .arg("-o")
+            .arg(&profdata);
+        run(&mut merge)?;
+
+        let llvm_cov = llvm_tool("cov", toolchain)?;
+        let cov = |subcommand: &str| {
+            let mut cmd = Command::new(&llvm_cov);
+            cmd.arg(subcommand)
+                .arg(format!("-instr-profile={}", profdata.display()))
+                .arg(format!("-ignore-filename-regex={IGNORE_FILENAMES}"));
+            if let Some((build, host)) = remapped {
+                cmd.arg(format!("-path-equivalence={build},{}", host.display()));
+            }
+            cmd.arg(first);
+            for object in others {
+                cmd.arg("-object").arg(object);
+            }
+            cmd
+        };
+
+        let lcov = run(cov("export").arg("-format=lcov"))?;
+        fs::write(self.lcov(), &lcov)?;
+        if format == Format::Html {
+            run(cov("show")
+                .args(["-format=html", "-show-line-counts-or-regions"])
+                .arg(format!("-output-dir={}", self.html_dir().display())))?;
+        }
+        Ok(Summary::from_lcov(&lcov))
+    }
+
+    /// Print how much of the code the tests covered and where the reports
+    /// are
+    pub fn print_summary(&self, summary: &Summary, format: Format) {
+        match summary.percent() {
+            Some(percent) => helpers::success(format!(
+                "{}: {percent:.1}% of lines covered ({} of {})",
+                self.triple, summary.covered, summary.lines
+            )),
+            None => helpers::warning(format!("{}: no instrumented lines", self.triple)),
+        }
+        helpers::info(format!("lcov: {}", self.lcov().display()));
+        if format == Format::Html {
+            helpers::info(format!(
+                "HTML: {}",
+                self.html_dir().join("index.html").display()
+            ));
+        }
+    }
+}
+
+/// Lines found and covered, as an lcov report counts them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Summary {
+    /// Instrumented lines
+    pub lines: u64,
+    /// Lines run at least once
+    pub covered: u64,
+}
+
+impl Summary {
+    /// Sum the `LF` and `LH` records of an lcov report
+    #[must_use]
+    pub fn from_lcov(lcov: &str) -> Self {
+        let mut summary = Self::default();
+        for line in lcov.lines() {
+            if let Some(found) = line.strip_prefix("LF:") {
+                summary.lines += found.trim().parse::<u64>().unwrap_or(0);
+            } else if let Some(hit) = line.strip_prefix("LH:") {
+                summary.covered += hit.trim().parse::<u64>().unwrap_or(0);
+            }
+        }
+        summary
+    }
+
+    /// Share of the lines covered, in percent
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn percent(&self) -> Option<f64> {
+        (self.lines > 0).then(|| self.covered as f64 * 100.0 / self.lines as f64)
+    }
+}
+
+/// Whether `path` in a `deps` directory is a program rather than a library,
+/// dependency list or debug info
+fn is_executable(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => ext.eq_ignore_ascii_case("exe"),
+        None => {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::metadata(path).is_ok_and(|meta| meta.permissions().mode() & 0o111 != 0)
+            }
+            #[cfg(not(unix))]
+            {
+                true
+            }
+        }
+    }
+}
+
+/// `llvm-<name>` from the `llvm-tools` component of `toolchain` (the
+/// default one if `None`), or else from `PATH`, also as cargo-binutils'
+/// `rust-<name>`
+pub fn llvm_tool(name: &str, toolchain: Option<&str>) -> Result<PathBuf> {
+    let mut rustc = Command::new("rustc");
+    if let Some(toolchain) = toolchain {
+        rustc.arg(format!("+{toolchain}"));
+    }
+    let sysroot = rustc
+        .args(["--print", "sysroot"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+    if let (Some(sysroot), Ok(host)) = (sysroot, Target::detect_host()) {
+        let path = Path::new(&sysroot)
+            .join("lib/rustlib")
+            .join(&host.triple)
+            .join("bin")
+            .join(format!("llvm-{name}{}", std::env::consts::EXE_SUFFIX));
+        if path.is_file() {
+            return Ok(path);
+        }
+    }
+    for program in [format!("llvm-{name}"), format!("rust-{name}")] {
+        if let Ok(path) = which::which(program) {
+            return Ok(path);
+        }
+    }
+    Err(Error::Toolchain(format!(
+        "llvm-{name} not found: rustup component add llvm-tools"
+    )))
+}
+
+/// Run an LLVM tool, returning its output
+fn run(cmd: &mut Command) -> Result<String> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let output = cmd
+        .output()
+        .map_err(|e| Error::Build(format!("Failed to run {program}: {e}")))?;
+    trace::record_command(cmd, output.status.code());
+    if !output.status.success() {
+        return Err(Error::Build(format!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_layout() {
+        let coverage = TargetCoverage::new(Path::new("/work/target"), "aarch64-unknown-linux-gnu");
+        let dir = Path::new("/work/target/xcargo/coverage/aarch64-unknown-linux-gnu");
+        assert_eq!(coverage.dir(), dir);
+        assert_eq!(coverage.cargo_target_dir(), dir.join("target"));
+        assert_eq!(
+            coverage.profile_file(),
+            dir.join("profraw/xcargo-%p-%m.profraw")
+        );
+        assert_eq!(coverage.lcov(), dir.join("lcov.info"));
+    }
+
+    #[test]
+    fn test_prepare_removes_last_run() {
+        let temp = TempDir::new().unwrap();
+        let coverage = TargetCoverage::new(temp.path(), "x86_64-unknown-linux-gnu");
+        coverage.prepare().unwrap();
+        fs::write(coverage.profraw_dir().join("xcargo-1-2.profraw"), "").unwrap();
+        fs::write(coverage.profraw_dir().join("notes.txt"), "").unwrap();
+        assert_eq!(coverage.profraws().len(), 1);
+
+        coverage.prepare().unwrap();
+        assert!(coverage.profraws().is_empty());
+        assert!(coverage.profraw_dir().is_dir());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_objects() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir::new().unwrap();
+        let coverage = TargetCoverage::new(temp.path(), "x86_64-unknown-linux-gnu");
+        let deps = coverage
+            .cargo_target_dir()
+            .join("x86_64-unknown-linux-gnu/debug/deps");
+        fs::create_dir_all(&deps).unwrap();
+        for name in ["app-1a2b", "libserde-3c4d.rlib", "app-1a2b.d", "notes"] {
+            fs::write(deps.join(name), "").unwrap();
+        }
+        fs::set_permissions(deps.join("app-1a2b"), fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert_eq!(coverage.objects("debug"), [deps.join("app-1a2b")]);
+        assert!(coverage.objects("release").is_empty());
+    }
+
+    #[test]
+    fn test_summary_from_lcov() {
+        let lcov = "SF:src/lib.rs\nDA:1,1\nLF:10\nLH:7\nend_of_record\n\
+                    SF:src/main.rs\nLF:10\nLH:2\nend_of_record\n";
+        let summary = Summary::from_lcov(lcov);
+        assert_eq!(
+            summary,
+            Summary {
+                lines: 20,
+                covered: 9
+            }
+        );
+        assert!((summary.percent().unwrap() - 45.0).abs() < 1e-9);
+        assert_eq!(Summary::default().percent(), None);
+    }
+
+    #[test]
+    fn test_supports() {
+        let target = |triple| Target::from_triple(triple).unwrap();
+        assert!(supports(&target("aarch64-unknown-linux-musl")));
+        assert!(!supports(&target("wasm32-wasip1")));
+    }
+}
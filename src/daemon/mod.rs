@@ -0,0 +1,785 @@
+//! Long-running build server for editors
+//!
+//! `xcargo daemon` speaks JSON-RPC 2.0 over stdio (or a Unix socket) so
+//! editor extensions can drive cross builds without re-spawning xcargo for
+//! every query. Messages are newline-delimited JSON.
+//!
+//! Methods:
+//!
+//! - `plan` - how a build would run (target, build path, native deps)
+//! - `build` - start a build in the background, returns a `buildId`
+//! - `cancel` - stop a running build by `buildId`
+//! - `subscribeEvents` - start receiving `build/output` and
+//!   `build/finished` notifications
+//! - `logs` - a target's recent output, or everything after an `offset`
+//! - `shutdown` - cancel running builds and exit
+//!
+//! Builds run as child `xcargo` processes so their output can be streamed
+//! line by line and cancelled without affecting the server. On Unix each
+//! build leads its own process group: cancelling interrupts the whole
+//! group, as Ctrl+C would, and terminates then kills it if it is still
+//! running after [`CANCEL_GRACE`]. Output is also
+//! written to the target's [`LogRing`] on disk rather than kept in memory;
+//! `build/output` events carry the ring position after each line, so a
+//! client that subscribes late can catch up with `logs`.
+
+pub mod protocol;
+
+use crate::build::{BuildOptions, Builder, CargoOperation};
+use crate::cancel::{self, Signal};
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::logs::{self, LogRing};
+use crate::target::Target;
+use protocol::{
+    Notification, Request, Response, RpcError, INVALID_PARAMS, METHOD_NOT_FOUND, PARSE_ERROR,
+    SERVER_ERROR,
+};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Parameters for `plan` and `build`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+#[allow(clippy::struct_excessive_bools)]
+pub struct BuildParams {
+    /// Target triple (defaults to the configured default or host)
+    pub target: Option<String>,
+    /// Cargo operation: build, check, test, clippy, doc, bench or clean
+    pub operation: Option<String>,
+    /// Release mode
+    pub release: bool,
+    /// Features to enable
+    pub features: Vec<String>,
+    /// Disable default features
+    pub no_default_features: bool,
+    /// Enable all features
+    pub all_features: bool,
+    /// Use a container
+    pub container: bool,
+    /// Zig preference: None = auto
+    pub zig: Option<bool>,
+}
+
+impl BuildParams {
+    fn operation(&self) -> std::result::Result<CargoOperation, RpcError> {
+        let name = self.operation.as_deref().unwrap_or("build");
+        CargoOperation::from_name(name)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("Unknown operation: {name}")))
+    }
+
+    /// Convert to build options
+    pub fn to_options(&self) -> std::result::Result<BuildOptions, RpcError> {
+        Ok(BuildOptions {
+            target: self.target.clone(),
+            release: self.release,
+            use_container: self.container,
+            use_zig: self.zig,
+            operation: self.operation()?,
+            features: self.features.clone(),
+            no_default_features: self.no_default_features,
+            all_features: self.all_features,
+            ..Default::default()
+        })
+    }
+
+    /// Command-line arguments for an equivalent `xcargo` invocation
+    pub fn to_args(&self) -> std::result::Result<Vec<String>, RpcError> {
+        let operation = self.operation()?;
+        let mut args = vec![operation.as_str().to_string()];
+        if let Some(target) = &self.target {
+            args.push("--target".to_string());
+            args.push(target.clone());
+        }
+        // `xcargo clean` takes only targets
+        if operation == CargoOperation::Clean {
+            return Ok(args);
+        }
+        // `xcargo check` and `bench` have no --release and only `build`
+        // supports --container
+        if self.release && operation.accepts_release() && operation != CargoOperation::Check {
+            args.push("--release".to_string());
+        }
+        if !self.features.is_empty() {
+            args.push("--features".to_string());
+            args.push(self.features.join(","));
+        }
+        if self.no_default_features {
+            args.push("--no-default-features".to_string());
+        }
+        if self.all_features {
+            args.push("--all-features".to_string());
+        }
+        if self.container && args[0] == "build" {
+            args.push("--container".to_string());
+        }
+        match self.zig {
+            Some(true) => args.push("--zig".to_string()),
+            Some(false) => args.push("--no-zig".to_string()),
+            None => {}
+        }
+        Ok(args)
+    }
+}
+
+type SharedWriter = Arc<Mutex<Box<dyn Write + Send>>>;
+
+/// Open log rings, by target triple
+type SharedLogs = Arc<Mutex<HashMap<String, LogRing>>>;
+
+/// Parameters for `logs`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct LogParams {
+    /// Target triple (defaults to the configured default or host)
+    target: Option<String>,
+    /// Return the lines after this position instead of the last ones
+    offset: Option<u64>,
+    /// Number of lines to return when no offset is given
+    tail: Option<usize>,
+}
+
+/// JSON-RPC build server
+pub struct Server {
+    out: SharedWriter,
+    builds: Arc<Mutex<HashMap<u64, Child>>>,
+    cancelled: Arc<Mutex<HashSet<u64>>>,
+    subscribed: Arc<AtomicBool>,
+    logs: SharedLogs,
+    shutdown: AtomicBool,
+    next_build: AtomicU64,
+    exe: PathBuf,
+}
+
+impl Server {
+    /// Create a server writing responses and events to `out`
+    pub fn new(out: Box<dyn Write + Send>) -> Result<Self> {
+        Ok(Self {
+            out: Arc::new(Mutex::new(out)),
+            builds: Arc::new(Mutex::new(HashMap::new())),
+            cancelled: Arc::new(Mutex::new(HashSet::new())),
+            subscribed: Arc::new(AtomicBool::new(false)),
+            logs: Arc::new(Mutex::new(HashMap::new())),
+            shutdown: AtomicBool::new(false),
+            next_build: AtomicU64::new(1),
+            exe: std::env::current_exe()?,
+        })
+    }
+
+    /// Serve requests from `input` until EOF or `shutdown`
+    pub fn serve<R: BufRead>(&self, input: R) -> Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if let Some(response) = self.handle(&line) {
+                send(&self.out, &response);
+            }
+            if self.is_shutdown() {
+                break;
+            }
+        }
+
+        self.cancel_all();
+        Ok(())
+    }
+
+    /// Check if a client requested `shutdown`
+    #[must_use]
+    pub fn is_shutdown(&self) -> bool {
+        self.shutdown.load(Ordering::SeqCst)
+    }
+
+    /// Handle one message, returning the response (none for notifications)
+    pub fn handle(&self, line: &str) -> Option<Response> {
+        let request: Request = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => {
+                return Some(Response::err(
+                    Value::Null,
+                    RpcError::new(PARSE_ERROR, e.to_string()),
+                ))
+            }
+        };
+
+        let result = self.dispatch(&request.method, request.params);
+        let id = request.id?;
+        Some(match result {
+            Ok(value) => Response::ok(id, value),
+            Err(error) => Response::err(id, error),
+        })
+    }
+
+    fn dispatch(&self, method: &str, params: Value) -> std::result::Result<Value, RpcError> {
+        match method {
+            "plan" => {
+                let params = parse_params::<Option<BuildParams>>(params)?.unwrap_or_default();
+                let options = params.to_options()?;
+                let plan = Builder::new()
+                    .and_then(|builder| builder.plan(&options))
+                    .map_err(|e| server_error(&e))?;
+                serde_json::to_value(plan).map_err(|e| RpcError::new(SERVER_ERROR, e.to_string()))
+            }
+            "build" => {
+                let params = parse_params::<Option<BuildParams>>(params)?.unwrap_or_default();
+                let target = log_target(params.target.as_deref())?;
+                let id = self.start_build(&params.to_args()?, &target)?;
+                Ok(json!({ "buildId": id, "target": target }))
+            }
+            "cancel" => {
+                #[derive(Deserialize)]
+                #[serde(rename_all = "camelCase")]
+                struct CancelParams {
+                    build_id: u64,
+                }
+                let params = parse_params::<CancelParams>(params)?;
+                Ok(json!({ "cancelled": self.cancel(params.build_id) }))
+            }
+            "logs" => {
+                let params = parse_params::<Option<LogParams>>(params)?.unwrap_or_default();
+                let target = log_target(params.target.as_deref())?;
+                let path = logs::log_path(&target).map_err(|e| server_error(&e))?;
+                if !path.exists() {
+                    return Ok(json!({ "target": target, "lines": [], "offset": 0 }));
+                }
+
+                let ring = LogRing::open(&path).map_err(|e| server_error(&e))?;
+                let chunk = match params.offset {
+                    Some(offset) => ring.read_from(offset),
+                    None => ring.read_from(0).map(|mut chunk| {
+                        let tail = params.tail.unwrap_or(DEFAULT_TAIL);
+                        chunk.lines.drain(..chunk.lines.len().saturating_sub(tail));
+                        chunk
+                    }),
+                }
+                .map_err(|e| server_error(&e))?;
+                Ok(json!({ "target": target, "lines": chunk.lines, "offset": chunk.next }))
+            }
+            "subscribeEvents" => {
+                self.subscribed.store(true, Ordering::SeqCst);
+                Ok(json!({ "subscribed": true }))
+            }
+            "shutdown" => {
+                self.shutdown.store(true, Ordering::SeqCst);
+                Ok(Value::Null)
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method: {method}"),
+            )),
+        }
+    }
+
+    /// Spawn `xcargo <args>` and stream its output as events and to the
+    /// log of `target`
+    fn start_build(&self, args: &[String], target: &str) -> std::result::Result<u64, RpcError> {
+        let id = self.next_build.fetch_add(1, Ordering::SeqCst);
+        self.open_log(target)?;
+        append_log(
+            &self.logs,
+            target,
+            &format!("--- build {id}: xcargo {} ---", args.join(" ")),
+        );
+
+        let mut command = Command::new(&self.exe);
+        command
+            .args(args)
+            .env("NO_COLOR", "1")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            // Cancelling signals the group, reaching cargo, rustc and any
+            // container or emulator the build started
+            command.process_group(0);
+        }
+        let mut child = command.spawn().map_err(|e| server_error(&e.into()))?;
+
+        let readers: Vec<_> = [
+            (
+                "stdout",
+                child
+                    .stdout
+                    .take()
+                    .map(|s| Box::new(s) as Box<dyn Read + Send>),
+            ),
+            (
+                "stderr",
+                child
+                    .stderr
+                    .take()
+                    .map(|s| Box::new(s) as Box<dyn Read + Send>),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(stream, reader)| reader.map(|reader| (stream, reader)))
+        .map(|(stream, reader)| {
+            let out = Arc::clone(&self.out);
+            let subscribed = Arc::clone(&self.subscribed);
+            let logs = Arc::clone(&self.logs);
+            let target = target.to_string();
+            thread::spawn(move || {
+                for line in BufReader::new(reader)
+                    .lines()
+                    .map_while(std::result::Result::ok)
+                {
+                    let line = strip_ansi(&line);
+                    let offset = append_log(&logs, &target, &line);
+                    if subscribed.load(Ordering::SeqCst) {
+                        send(
+                            &out,
+                            &Notification::new(
+                                "build/output",
+                                json!({
+                                    "buildId": id,
+                                    "target": target,
+                                    "stream": stream,
+                                    "line": line,
+                                    "offset": offset,
+                                }),
+                            ),
+                        );
+                    }
+                }
+            })
+        })
+        .collect();
+
+        self.builds
+            .lock()
+            .map_err(|_| RpcError::new(SERVER_ERROR, "build table poisoned"))?
+            .insert(id, child);
+
+        let out = Arc::clone(&self.out);
+        let builds = Arc::clone(&self.builds);
+        let cancelled = Arc::clone(&self.cancelled);
+        let subscribed = Arc::clone(&self.subscribed);
+        thread::spawn(move || {
+            for reader in readers {
+                let _ = reader.join();
+            }
+
+            let child = builds.lock().ok().and_then(|mut builds| builds.remove(&id));
+            let status = child.and_then(|mut child| child.wait().ok());
+            let was_cancelled = cancelled
+                .lock()
+                .is_ok_and(|mut cancelled| cancelled.remove(&id));
+
+            if subscribed.load(Ordering::SeqCst) {
+                send(
+                    &out,
+                    &Notification::new(
+                        "build/finished",
+                        json!({
+                            "buildId": id,
+                            "success": status.is_some_and(|s| s.success()),
+                            "exitCode": status.and_then(|s| s.code()),
+                            "cancelled": was_cancelled,
+                        }),
+                    ),
+                );
+            }
+        });
+
+        Ok(id)
+    }
+
+    /// Open (or reuse) the log ring for `target`
+    fn open_log(&self, target: &str) -> std::result::Result<(), RpcError> {
+        let mut rings = self
+            .logs
+            .lock()
+            .map_err(|_| RpcError::new(SERVER_ERROR, "log table poisoned"))?;
+        if !rings.contains_key(target) {
+            let path = logs::log_path(target).map_err(|e| server_error(&e))?;
+            let ring =
+                LogRing::create(&path, logs::DEFAULT_CAPACITY).map_err(|e| server_error(&e))?;
+            rings.insert(target.to_string(), ring);
+        }
+        Ok(())
+    }
+
+    /// Interrupt a running build, escalating to SIGTERM and then SIGKILL
+    /// while it keeps running
+    fn cancel(&self, id: u64) -> bool {
+        let Ok(mut builds) = self.builds.lock() else {
+            return false;
+        };
+        let Some(child) = builds.get_mut(&id) else {
+            return false;
+        };
+        if let Ok(mut cancelled) = self.cancelled.lock() {
+            cancelled.insert(id);
+        }
+        if !cancel::send(child, Signal::Interrupt, true) {
+            return false;
+        }
+
+        // The build stays in the table until its output is drained and it
+        // has been reaped
+        let builds = Arc::clone(&self.builds);
+        thread::spawn(move || {
+            for signal in [Signal::Terminate, Signal::Kill] {
+                thread::sleep(CANCEL_GRACE);
+                let Ok(mut builds) = builds.lock() else {
+                    return;
+                };
+                match builds.get_mut(&id) {
+                    Some(child) => cancel::send(child, signal, true),
+                    None => return,
+                };
+            }
+        });
+        true
+    }
+
+    /// Cancel every running build and wait for them to exit
+    fn cancel_all(&self) {
+        let ids: Vec<u64> = self
+            .builds
+            .lock()
+            .map(|builds| builds.keys().copied().collect())
+            .unwrap_or_default();
+        for id in &ids {
+            self.cancel(*id);
+        }
+
+        // Give the escalation time to run before the server exits
+        let deadline = Instant::now() + CANCEL_GRACE * 3;
+        while !ids.is_empty()
+            && Instant::now() < deadline
+            && self.builds.lock().is_ok_and(|builds| !builds.is_empty())
+        {
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+/// How long a cancelled build gets to exit after each signal before the
+/// next, stronger one is sent
+pub const CANCEL_GRACE: Duration = Duration::from_secs(5);
+
+/// Run the server on stdin/stdout
+pub fn run_stdio() -> Result<()> {
+    let server = Server::new(Box::new(std::io::stdout()))?;
+    server.serve(std::io::stdin().lock())
+}
+
+/// Run the server on a Unix socket, one client at a time
+///
+/// A socket left behind by a daemon that exited is replaced. Fails if
+/// `path` is something other than a socket, or if another daemon is
+/// still listening on it.
+#[cfg(unix)]
+pub fn run_socket(path: &std::path::Path) -> Result<()> {
+    use std::os::unix::net::UnixListener;
+
+    remove_stale_socket(path)?;
+    let listener = UnixListener::bind(path)?;
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let server = Server::new(Box::new(stream.try_clone()?))?;
+        server.serve(BufReader::new(stream))?;
+        if server.is_shutdown() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove the socket at `path` if no daemon is listening on it
+#[cfg(unix)]
+fn remove_stale_socket(path: &std::path::Path) -> Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixStream;
+
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return Ok(());
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(Error::Config(format!(
+            "{} exists and is not a socket",
+            path.display()
+        )));
+    }
+    if UnixStream::connect(path).is_ok() {
+        return Err(Error::Config(format!(
+            "Another xcargo daemon is already listening on {}",
+            path.display()
+        )));
+    }
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+/// Lines `logs` returns when no offset or tail is given
+const DEFAULT_TAIL: usize = 200;
+
+/// Target whose log a build writes to: `target` (resolving aliases), the
+/// configured default or the host, as the build itself picks it
+fn log_target(target: Option<&str>) -> std::result::Result<String, RpcError> {
+    let config = Config::effective().unwrap_or_default();
+    let triple = match target.or(config.targets.default.first().map(String::as_str)) {
+        Some(triple) => Target::resolve_alias_with(triple, &config.resolved_aliases()),
+        None => Target::detect_host().map(|host| host.triple),
+    };
+    triple.map_err(|e| RpcError::from_error(INVALID_PARAMS, &e))
+}
+
+/// Append a line to the log of `target`, returning the position after it
+fn append_log(logs: &SharedLogs, target: &str, line: &str) -> Option<u64> {
+    let mut rings = logs.lock().ok()?;
+    let ring = rings.get_mut(target)?;
+    ring.append(line).ok()?;
+    ring.window().ok().map(|(_, end)| end)
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> std::result::Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn server_error(error: &Error) -> RpcError {
+    RpcError::from_error(SERVER_ERROR, error)
+}
+
+/// Remove ANSI escape sequences (colors) from a line of output
+fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // CSI sequences end with a byte in '@'..='~'
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Write one message as a line of JSON
+fn send<T: serde::Serialize>(out: &SharedWriter, message: &T) {
+    if let (Ok(mut out), Ok(json)) = (out.lock(), serde_json::to_string(message)) {
+        let _ = writeln!(out, "{json}");
+        let _ = out.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server() -> Server {
+        Server::new(Box::new(std::io::sink())).unwrap()
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let response = server().handle("{not json").unwrap();
+        assert_eq!(response.error.unwrap().code, PARSE_ERROR);
+    }
+
+    #[test]
+    fn test_unknown_method() {
+        let response = server()
+            .handle(r#"{"jsonrpc":"2.0","id":1,"method":"frobnicate"}"#)
+            .unwrap();
+        assert_eq!(response.id, json!(1));
+        assert_eq!(response.error.unwrap().code, METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_subscribe_and_cancel_unknown() {
+        let server = server();
+        let response = server
+            .handle(r#"{"jsonrpc":"2.0","id":"a","method":"subscribeEvents"}"#)
+            .unwrap();
+        assert_eq!(response.result, Some(json!({ "subscribed": true })));
+
+        let response = server
+            .handle(r#"{"jsonrpc":"2.0","id":2,"method":"cancel","params":{"buildId":42}}"#)
+            .unwrap();
+        assert_eq!(response.result, Some(json!({ "cancelled": false })));
+    }
+
+    #[test]
+    fn test_xcargo_errors_carry_error_code() {
+        let error = server_error(&Error::Config("no targets".to_string()));
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({
+                "code": SERVER_ERROR,
+                "message": "Configuration error: no targets",
+                "data": { "error_code": "X0002", "exit_code": 2 },
+            })
+        );
+        let parse = serde_json::to_value(RpcError::new(PARSE_ERROR, "bad")).unwrap();
+        assert!(parse.get("data").is_none());
+    }
+
+    #[test]
+    fn test_notification_has_no_response() {
+        assert!(server()
+            .handle(r#"{"jsonrpc":"2.0","method":"subscribeEvents"}"#)
+            .is_none());
+    }
+
+    #[test]
+    fn test_build_params_to_args() {
+        let params: BuildParams = serde_json::from_value(json!({
+            "target": "aarch64-unknown-linux-gnu",
+            "operation": "test",
+            "release": true,
+            "container": true,
+            "features": ["a", "b"],
+            "zig": false,
+        }))
+        .unwrap();
+
+        assert_eq!(
+            params.to_args().unwrap(),
+            vec![
+                "test",
+                "--target",
+                "aarch64-unknown-linux-gnu",
+                "--release",
+                "--features",
+                "a,b",
+                "--no-zig",
+            ]
+        );
+
+        let bench = BuildParams {
+            operation: Some("bench".to_string()),
+            release: true,
+            features: vec!["a".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(bench.to_args().unwrap(), vec!["bench", "--features", "a"]);
+
+        let clean = BuildParams {
+            target: Some("aarch64-unknown-linux-gnu".to_string()),
+            operation: Some("clean".to_string()),
+            release: true,
+            zig: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(
+            clean.to_args().unwrap(),
+            vec!["clean", "--target", "aarch64-unknown-linux-gnu"]
+        );
+
+        let bad = BuildParams {
+            operation: Some("publish".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(bad.to_args().unwrap_err().code, INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(
+            strip_ansi("\u{1b}[1m\u{1b}[31m✗ \u{1b}[0mfailed"),
+            "✗ failed"
+        );
+        assert_eq!(strip_ansi("plain"), "plain");
+    }
+
+    #[test]
+    fn test_append_log_offsets() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let server = server();
+        let target = "x86_64-unknown-linux-gnu";
+        assert_eq!(append_log(&server.logs, target, "unopened"), None);
+
+        let ring = LogRing::create(&temp.path().join("ring.log"), 1024).unwrap();
+        server.logs.lock().unwrap().insert(target.to_string(), ring);
+        let first = append_log(&server.logs, target, "   Compiling app").unwrap();
+        let second = append_log(&server.logs, target, "    Finished").unwrap();
+        assert_eq!(first, 17);
+        assert_eq!(second, 30);
+
+        let rings = server.logs.lock().unwrap();
+        let chunk = rings[target].read_from(first).unwrap();
+        assert_eq!(chunk.lines, vec!["    Finished"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stale_socket_is_replaced() {
+        use std::os::unix::net::UnixListener;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let file = temp.path().join("notes.txt");
+        std::fs::write(&file, "keep me").unwrap();
+        let err = remove_stale_socket(&file).unwrap_err();
+        assert!(err.to_string().contains("not a socket"), "{err}");
+        assert!(file.exists());
+
+        let socket = temp.path().join("xcargo.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+        let err = remove_stale_socket(&socket).unwrap_err();
+        assert!(err.to_string().contains("already listening"), "{err}");
+
+        drop(listener);
+        remove_stale_socket(&socket).unwrap();
+        assert!(!socket.exists());
+        remove_stale_socket(&socket).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cancel_signals_the_process_group() {
+        use std::os::unix::process::CommandExt;
+
+        // A shell that ignores SIGINT, with a child of its own; it says so
+        // once the trap is set, so the signal can't beat it
+        let mut child = Command::new("sh")
+            .args(["-c", "trap '' INT; echo ready; sleep 30 & wait"])
+            .process_group(0)
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut ready = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut ready)
+            .unwrap();
+        assert_eq!(ready.trim(), "ready");
+        assert!(cancel::send(&mut child, Signal::Interrupt, true));
+        thread::sleep(Duration::from_millis(200));
+        assert!(child.try_wait().unwrap().is_none());
+
+        assert!(cancel::send(&mut child, Signal::Terminate, true));
+        assert!(child.wait().unwrap().code().is_none());
+    }
+
+    #[test]
+    fn test_shutdown_stops_serving() {
+        let server = server();
+        let input = "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"shutdown\"}\n{\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"subscribeEvents\"}\n";
+        server.serve(input.as_bytes()).unwrap();
+        assert!(server.is_shutdown());
+        assert!(!server.subscribed.load(Ordering::SeqCst));
+    }
+}
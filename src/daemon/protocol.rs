@@ -0,0 +1,129 @@
+//! JSON-RPC 2.0 message types
+//!
+//! Messages are exchanged as newline-delimited JSON: one request,
+//! response or notification per line.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Invalid JSON was received
+pub const PARSE_ERROR: i64 = -32700;
+/// The method does not exist
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// Invalid method parameters
+pub const INVALID_PARAMS: i64 = -32602;
+/// The request failed while being handled
+pub const SERVER_ERROR: i64 = -32000;
+
+/// A request (or notification, when `id` is absent) from the client
+#[derive(Debug, Clone, Deserialize)]
+pub struct Request {
+    /// Request id, echoed in the response
+    #[serde(default)]
+    pub id: Option<Value>,
+    /// Method name
+    pub method: String,
+    /// Method parameters
+    #[serde(default)]
+    pub params: Value,
+}
+
+/// Error object in a response
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct RpcError {
+    /// Error code
+    pub code: i64,
+    /// Error message
+    pub message: String,
+    /// xcargo error code and exit code, for errors from xcargo itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    /// Create an error with a code and message
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// Create an error for an xcargo `error`, with its [`ErrorCode`] and
+    /// exit code in `data`
+    ///
+    /// [`ErrorCode`]: crate::error::ErrorCode
+    #[must_use]
+    pub fn from_error(code: i64, error: &crate::error::Error) -> Self {
+        Self {
+            code,
+            message: error.to_string(),
+            data: Some(serde_json::json!({
+                "error_code": error.code(),
+                "exit_code": error.exit_code(),
+            })),
+        }
+    }
+}
+
+/// Response to a request
+#[derive(Debug, Clone, Serialize)]
+pub struct Response {
+    /// Protocol version, always "2.0"
+    pub jsonrpc: &'static str,
+    /// Id of the request being answered
+    pub id: Value,
+    /// Result on success
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    /// Error on failure
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl Response {
+    /// Successful response
+    #[must_use]
+    pub fn ok(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    /// Error response
+    #[must_use]
+    pub fn err(id: Value, error: RpcError) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Server-to-client notification
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    /// Protocol version, always "2.0"
+    pub jsonrpc: &'static str,
+    /// Event name (e.g., "build/output")
+    pub method: String,
+    /// Event payload
+    pub params: Value,
+}
+
+impl Notification {
+    /// Create a notification
+    pub fn new(method: impl Into<String>, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            method: method.into(),
+            params,
+        }
+    }
+}
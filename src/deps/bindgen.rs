@@ -0,0 +1,231 @@
+//! bindgen / libclang coordination for cross builds
+//!
+//! bindgen parses C headers with libclang at build time. When
+//! cross-compiling it needs the *target's* headers, so xcargo points it at
+//! the same sysroot used for linking via `BINDGEN_EXTRA_CLANG_ARGS_<triple>`:
+//!
+//! 1. `[deps.bindgen] sysroot` from xcargo.toml, if set
+//! 2. a cross sysroot at `/usr/<gnu-triple>` (Linux and MinGW targets)
+//! 3. for Apple targets, the Xcode SDK of an iOS target's variant (device,
+//!    simulator or Mac Catalyst), otherwise `SDKROOT`
+//!
+//! libclang itself is located via `LIBCLANG_PATH` or common LLVM install
+//! directories.
+
+use super::{gnu_triple, DepResolution};
+use crate::config::BindgenConfig;
+use crate::target::Target;
+use crate::toolchain::apple::AppleSdk;
+use std::path::{Path, PathBuf};
+
+/// Directories searched for libclang when `LIBCLANG_PATH` is unset
+const LIBCLANG_DIRS: &[&str] = &[
+    "/usr/lib",
+    "/usr/lib64",
+    "/usr/local/lib",
+    "/usr/lib/x86_64-linux-gnu",
+    "/usr/lib/aarch64-linux-gnu",
+    "/opt/homebrew/opt/llvm/lib",
+    "/usr/local/opt/llvm/lib",
+    "/Library/Developer/CommandLineTools/usr/lib",
+    "C:\\Program Files\\LLVM\\bin",
+];
+
+/// Resolves libclang and clang arguments for bindgen
+pub struct BindgenResolver<'a> {
+    config: &'a BindgenConfig,
+    /// Filesystem root searched for cross sysroots
+    root: PathBuf,
+}
+
+impl<'a> BindgenResolver<'a> {
+    /// Create a resolver using the default search locations
+    #[must_use]
+    pub fn new(config: &'a BindgenConfig) -> Self {
+        Self {
+            config,
+            root: PathBuf::from("/"),
+        }
+    }
+
+    /// Override the filesystem root searched for cross sysroots
+    #[must_use]
+    pub fn with_root(mut self, root: PathBuf) -> Self {
+        self.root = root;
+        self
+    }
+
+    /// Resolve bindgen settings for `target`
+    #[must_use]
+    pub fn resolve(&self, target: &Target, host: &Target) -> DepResolution {
+        let libclang = find_libclang();
+        let sysroot = if target.triple == host.triple {
+            None
+        } else {
+            self.sysroot(target)
+        };
+
+        let mut args: Vec<String> = Vec::new();
+        if let Some((dir, _)) = &sysroot {
+            if target.vendor == "apple" {
+                args.push("-isysroot".to_string());
+                args.push(dir.display().to_string());
+            } else {
+                args.push(format!("--sysroot={}", dir.display()));
+            }
+        }
+        args.extend(self.config.clang_args.iter().cloned());
+
+        let strategy = if sysroot.is_some() { "sysroot" } else { "host" };
+        let reason = match (&sysroot, target.triple == host.triple) {
+            (Some((dir, _)), _) => format!("parsing headers from {}", dir.display()),
+            (None, true) => "native build, using host headers".to_string(),
+            (None, false) => "no target sysroot found, bindgen will see host headers".to_string(),
+        };
+        let mut resolution = DepResolution::new("bindgen", strategy, reason);
+
+        resolution
+            .notes
+            .push("bindgen runs libclang at build time to parse C headers".to_string());
+        match &libclang {
+            Some(path) => {
+                resolution
+                    .notes
+                    .push(format!("libclang: {}", path.display()));
+                if std::env::var_os("LIBCLANG_PATH").is_none() {
+                    if let Some(dir) = path.parent() {
+                        resolution
+                            .env
+                            .push(("LIBCLANG_PATH".to_string(), dir.display().to_string()));
+                    }
+                }
+            }
+            None => resolution
+                .notes
+                .push("libclang: not found, install clang/LLVM or set LIBCLANG_PATH".to_string()),
+        }
+        if let Some((_, source)) = &sysroot {
+            resolution.notes.push(format!("sysroot source: {source}"));
+        }
+
+        if !args.is_empty() {
+            let key = format!(
+                "BINDGEN_EXTRA_CLANG_ARGS_{}",
+                target.triple.replace('-', "_")
+            );
+            resolution
+                .notes
+                .push(format!("clang receives `{}` via {key}", args.join(" ")));
+            resolution.env.push((key, args.join(" ")));
+        }
+
+        resolution
+    }
+
+    /// Find the sysroot to hand to clang, with a description of its source
+    fn sysroot(&self, target: &Target) -> Option<(PathBuf, &'static str)> {
+        if let Some(dir) = &self.config.sysroot {
+            return Some((PathBuf::from(dir), "[deps.bindgen] sysroot"));
+        }
+
+        if target.vendor == "apple" {
+            if let Some(path) = AppleSdk::for_target(target).and_then(|sdk| sdk.path().ok()) {
+                return Some((path, "Xcode SDK for the target"));
+            }
+            return std::env::var_os("SDKROOT").map(|sdk| (PathBuf::from(sdk), "SDKROOT"));
+        }
+
+        let dir = self.root.join("usr").join(gnu_triple(target)?);
+        dir.join("include")
+            .is_dir()
+            .then_some((dir, "cross sysroot for the target"))
+    }
+}
+
+/// Locate the libclang shared library
+#[must_use]
+pub fn find_libclang() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("LIBCLANG_PATH") {
+        return find_libclang_in(Path::new(&dir));
+    }
+
+    let mut dirs: Vec<PathBuf> = LIBCLANG_DIRS.iter().map(PathBuf::from).collect();
+
+    // Versioned Debian/Ubuntu installs: /usr/lib/llvm-<N>/lib, newest first
+    if let Ok(entries) = std::fs::read_dir("/usr/lib") {
+        let mut llvm: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("llvm-"))
+            })
+            .map(|path| path.join("lib"))
+            .collect();
+        llvm.sort();
+        llvm.reverse();
+        dirs.splice(0..0, llvm);
+    }
+
+    dirs.iter().find_map(|dir| find_libclang_in(dir))
+}
+
+/// Find a libclang library file directly inside `dir`
+fn find_libclang_in(dir: &Path) -> Option<PathBuf> {
+    let entries = std::fs::read_dir(dir).ok()?;
+    entries.flatten().map(|entry| entry.path()).find(|path| {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| {
+                name.starts_with("libclang.so")
+                    || name == "libclang.dylib"
+                    || name == "libclang.dll"
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_cross_sysroot_args() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("usr/aarch64-linux-gnu/include")).unwrap();
+
+        let config = BindgenConfig::default();
+        let resolver = BindgenResolver::new(&config).with_root(temp.path().to_path_buf());
+        let target = Target::from_triple("aarch64-unknown-linux-gnu").unwrap();
+        let host = Target::from_triple("x86_64-unknown-linux-gnu").unwrap();
+
+        let resolution = resolver.resolve(&target, &host);
+        assert_eq!(resolution.strategy, "sysroot");
+        let (_, args) = resolution
+            .env
+            .iter()
+            .find(|(key, _)| key == "BINDGEN_EXTRA_CLANG_ARGS_aarch64_unknown_linux_gnu")
+            .unwrap();
+        assert!(args.starts_with("--sysroot="));
+        assert!(args.ends_with("usr/aarch64-linux-gnu"));
+    }
+
+    #[test]
+    fn test_configured_sysroot_and_args() {
+        let config = BindgenConfig {
+            sysroot: Some("/opt/sysroots/rpi".to_string()),
+            clang_args: vec!["-DNDEBUG".to_string()],
+        };
+        let resolver = BindgenResolver::new(&config);
+        let target = Target::from_triple("armv7-unknown-linux-gnueabihf").unwrap();
+        let host = Target::from_triple("x86_64-unknown-linux-gnu").unwrap();
+
+        let resolution = resolver.resolve(&target, &host);
+        assert!(resolution.env.contains(&(
+            "BINDGEN_EXTRA_CLANG_ARGS_armv7_unknown_linux_gnueabihf".to_string(),
+            "--sysroot=/opt/sysroots/rpi -DNDEBUG".to_string()
+        )));
+    }
+}
@@ -0,0 +1,433 @@
+//! Crypto crates with assembly and C code: `ring` and `aws-lc`
+//!
+//! Both compile C and assembly from their build scripts, so they are the
+//! usual reason a pure-Rust TLS stack fails to cross-compile:
+//!
+//! - **ring** needs a C compiler for the target, and on targets without an
+//!   OS random source a feature selecting another one
+//! - **aws-lc-sys** (rustls' default provider) additionally needs `CMake`
+//!   and libclang on targets without pregenerated bindings, NASM for
+//!   `x86_64` Windows, and does not build at all for some architectures,
+//!   where switching rustls to ring is the way out
+//! - **aws-lc-fips-sys** always needs `CMake`, Go and Perl
+//!
+//! The presets here are registered in [`super::presets::PRESETS`];
+//! [`required_tools`] backs the `xcargo doctor` check.
+
+use super::presets::{enable_feature, PresetContext};
+use super::{DepResolution, Lockfile};
+use crate::target::Target;
+
+/// Crypto crates with native code that xcargo knows about
+pub const CRYPTO_CRATES: &[&str] = &["ring", "aws-lc-sys", "aws-lc-fips-sys"];
+
+/// Targets aws-lc-sys ships pregenerated bindings for
+const AWS_LC_PREGENERATED: &[&str] = &[
+    "aarch64-apple-darwin",
+    "aarch64-apple-ios",
+    "aarch64-linux-android",
+    "aarch64-pc-windows-msvc",
+    "aarch64-unknown-linux-gnu",
+    "aarch64-unknown-linux-musl",
+    "i686-pc-windows-msvc",
+    "i686-unknown-linux-gnu",
+    "powerpc64le-unknown-linux-gnu",
+    "riscv64gc-unknown-linux-gnu",
+    "x86_64-apple-darwin",
+    "x86_64-pc-windows-gnu",
+    "x86_64-pc-windows-msvc",
+    "x86_64-unknown-linux-gnu",
+    "x86_64-unknown-linux-musl",
+];
+
+/// Cargo.toml snippet that makes rustls use ring instead of aws-lc
+const RUSTLS_RING: &str = r#"rustls = { version = "0.23", default-features = false, features = ["ring", "std", "tls12", "logging"] }"#;
+
+/// A build tool a crypto crate needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tool {
+    /// Executable name
+    pub name: &'static str,
+    /// Why it is needed
+    pub reason: &'static str,
+}
+
+/// Crypto crates from [`CRYPTO_CRATES`] present in the lockfile
+#[must_use]
+pub fn detect(lockfile: &Lockfile) -> Vec<&'static str> {
+    CRYPTO_CRATES
+        .iter()
+        .copied()
+        .filter(|krate| lockfile.contains(krate))
+        .collect()
+}
+
+/// Check if aws-lc can be built for `target` at all
+#[must_use]
+pub fn aws_lc_supported(target: &Target) -> bool {
+    let arch = target.triple_arch();
+    !(arch.starts_with("wasm")
+        || arch.starts_with("mips")
+        || arch.starts_with("thumb")
+        || arch.starts_with("riscv32")
+        || arch == "powerpc"
+        || arch == "sparc64"
+        || is_bare_metal(target))
+}
+
+/// Tools `krate` needs to build for `target`
+#[must_use]
+pub fn required_tools(krate: &str, target: &Target, lockfile: &Lockfile) -> Vec<Tool> {
+    let windows_x64 = target.os == "windows" && target.arch == "x86_64";
+    let mut tools = Vec::new();
+
+    match krate {
+        "ring" => {
+            let from_git = lockfile.packages.iter().any(|p| {
+                p.name == "ring" && p.source.as_deref().is_some_and(|s| s.starts_with("git+"))
+            });
+            if from_git {
+                tools.push(Tool {
+                    name: "perl",
+                    reason: "ring built from git generates its assembly with Perl",
+                });
+            }
+            if target.arch.starts_with("wasm") || target.triple == "aarch64-pc-windows-msvc" {
+                tools.push(Tool {
+                    name: "clang",
+                    reason: "ring's C code needs clang for this target",
+                });
+            }
+        }
+        "aws-lc-sys" => {
+            if !AWS_LC_PREGENERATED.contains(&target.triple.as_str()) {
+                tools.push(Tool {
+                    name: "cmake",
+                    reason: "aws-lc-sys builds with CMake on targets without pregenerated bindings",
+                });
+                tools.push(Tool {
+                    name: "clang",
+                    reason: "aws-lc-sys generates bindings with libclang for this target",
+                });
+            }
+            if windows_x64 && std::env::var_os("AWS_LC_SYS_PREBUILT_NASM").is_none() {
+                tools.push(Tool {
+                    name: "nasm",
+                    reason: "aws-lc-sys assembles x86_64 Windows code with NASM",
+                });
+            }
+        }
+        "aws-lc-fips-sys" => {
+            tools.push(Tool {
+                name: "cmake",
+                reason: "aws-lc-fips-sys always builds with CMake",
+            });
+            tools.push(Tool {
+                name: "go",
+                reason: "the FIPS module build runs Go tooling",
+            });
+            tools.push(Tool {
+                name: "perl",
+                reason: "the FIPS module generates its assembly with Perl",
+            });
+            if windows_x64 {
+                tools.push(Tool {
+                    name: "nasm",
+                    reason: "aws-lc-fips-sys assembles x86_64 Windows code with NASM",
+                });
+            }
+        }
+        _ => {}
+    }
+
+    tools
+}
+
+/// Tools from [`required_tools`] that are not in `PATH`
+#[must_use]
+pub fn missing_tools(krate: &str, target: &Target, lockfile: &Lockfile) -> Vec<Tool> {
+    required_tools(krate, target, lockfile)
+        .into_iter()
+        .filter(|tool| which::which(tool.name).is_err())
+        .collect()
+}
+
+/// ring feature that supplies randomness on targets without an OS source
+#[must_use]
+pub fn ring_getrandom_feature(target: &Target) -> Option<(&'static str, &'static str)> {
+    if target.triple == "wasm32-unknown-unknown" {
+        Some((
+            "wasm32_unknown_unknown_js",
+            "wasm32-unknown-unknown has no OS randomness; ring takes it from JavaScript",
+        ))
+    } else if target.os == "espidf" {
+        Some((
+            "less-safe-getrandom-espidf",
+            "ESP-IDF randomness is only safe once the RF subsystem is running",
+        ))
+    } else if is_bare_metal(target) {
+        Some((
+            "less-safe-getrandom-custom-or-rdrand",
+            "bare-metal target: register a getrandom custom backend (or use RDRAND on x86)",
+        ))
+    } else {
+        None
+    }
+}
+
+/// Preset for `ring`
+pub(super) fn ring(ctx: &PresetContext<'_>) -> Option<DepResolution> {
+    let target = ctx.target;
+    if !ctx.is_cross() || target.env.as_deref() == Some("msvc") {
+        return None;
+    }
+
+    let suffix = target.triple.replace('-', "_");
+    let mut resolution = if target.arch.starts_with("wasm") {
+        let mut resolution = DepResolution::new(
+            "ring",
+            "clang",
+            "compiling ring's C code for WebAssembly with clang",
+        );
+        resolution
+            .env
+            .push((format!("CC_{suffix}"), "clang".to_string()));
+        resolution
+            .env
+            .push((format!("AR_{suffix}"), "llvm-ar".to_string()));
+        resolution
+    } else if let Some(cc) = &ctx.cross_cc {
+        let mut resolution = DepResolution::new(
+            "ring",
+            "cross-cc",
+            format!("compiling ring's C code with {cc}"),
+        );
+        resolution.env.extend(cross_cc_env(&suffix, cc));
+        resolution
+    } else {
+        DepResolution::new(
+            "ring",
+            "needs-cc",
+            format!(
+                "no C cross-compiler found for {}; ring's build script needs one (try Zig or --container)",
+                target.triple
+            ),
+        )
+    };
+
+    if let Some((feature, note)) = ring_getrandom_feature(target) {
+        enable_feature(&mut resolution, ctx.lockfile, &[("ring", feature)]);
+        resolution.notes.push(note.to_string());
+    }
+    add_missing_tools(&mut resolution, "ring", ctx);
+    Some(resolution)
+}
+
+/// Preset for `aws-lc-sys`
+pub(super) fn aws_lc(ctx: &PresetContext<'_>) -> Option<DepResolution> {
+    let target = ctx.target;
+    if !ctx.is_cross() {
+        return None;
+    }
+
+    if !aws_lc_supported(target) {
+        let mut resolution = DepResolution::new(
+            "aws-lc",
+            "switch-backend",
+            format!(
+                "aws-lc does not build for {}; switch rustls to the ring provider",
+                target.triple
+            ),
+        );
+        resolution
+            .notes
+            .push(format!("In Cargo.toml: {RUSTLS_RING}"));
+        resolution.notes.push(
+            "Crates depending on rustls (reqwest, hyper-rustls, tokio-rustls) usually offer a ring feature too"
+                .to_string(),
+        );
+        return Some(resolution);
+    }
+
+    let suffix = target.triple.replace('-', "_");
+    let mut resolution = match &ctx.cross_cc {
+        Some(cc) => {
+            let mut resolution =
+                DepResolution::new("aws-lc", "cross-cc", format!("compiling aws-lc with {cc}"));
+            resolution.env.extend(cross_cc_env(&suffix, cc));
+            resolution
+        }
+        None => DepResolution::new(
+            "aws-lc",
+            "default",
+            "using aws-lc-sys's own compiler detection",
+        ),
+    };
+
+    if target.os == "windows" && target.arch == "x86_64" && which::which("nasm").is_err() {
+        resolution.strategy = "prebuilt-nasm".to_string();
+        resolution
+            .env
+            .push(("AWS_LC_SYS_PREBUILT_NASM".to_string(), "1".to_string()));
+        resolution
+            .notes
+            .push("NASM not found, using aws-lc-sys's prebuilt NASM objects".to_string());
+    }
+    if !AWS_LC_PREGENERATED.contains(&target.triple.as_str()) {
+        resolution.notes.push(format!(
+            "no pregenerated bindings for {}: aws-lc-sys needs CMake, libclang and its `bindgen` feature",
+            target.triple
+        ));
+    }
+    add_missing_tools(&mut resolution, "aws-lc-sys", ctx);
+    Some(resolution)
+}
+
+/// Preset for `aws-lc-fips-sys`
+pub(super) fn aws_lc_fips(ctx: &PresetContext<'_>) -> Option<DepResolution> {
+    if !ctx.is_cross() {
+        return None;
+    }
+
+    let mut resolution = DepResolution::new(
+        "aws-lc-fips",
+        "cmake",
+        "the FIPS module always builds with CMake, Go and Perl",
+    );
+    if let Some(cc) = &ctx.cross_cc {
+        let suffix = ctx.target.triple.replace('-', "_");
+        resolution.env.extend(cross_cc_env(&suffix, cc));
+    }
+    add_missing_tools(&mut resolution, "aws-lc-fips-sys", ctx);
+    Some(resolution)
+}
+
+/// `CC_<triple>` and, for GNU toolchains, `AR_<triple>`
+fn cross_cc_env(suffix: &str, cc: &str) -> Vec<(String, String)> {
+    let mut env = vec![(format!("CC_{suffix}"), cc.to_string())];
+    if let Some(prefix) = cc.strip_suffix("-gcc") {
+        env.push((format!("AR_{suffix}"), format!("{prefix}-ar")));
+    }
+    env
+}
+
+fn add_missing_tools(resolution: &mut DepResolution, krate: &str, ctx: &PresetContext<'_>) {
+    for tool in missing_tools(krate, ctx.target, ctx.lockfile) {
+        resolution
+            .notes
+            .push(format!("{} not found in PATH: {}", tool.name, tool.reason));
+    }
+}
+
+fn is_bare_metal(target: &Target) -> bool {
+    target.triple.contains("-none")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCK: &str = r#"
+[[package]]
+name = "my-app"
+version = "0.1.0"
+dependencies = ["ring", "rustls"]
+
+[[package]]
+name = "rustls"
+version = "0.23.12"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["aws-lc-rs", "ring"]
+
+[[package]]
+name = "aws-lc-sys"
+version = "0.21.1"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "ring"
+version = "0.17.8"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    fn target(triple: &str) -> Target {
+        Target::from_triple(triple).unwrap()
+    }
+
+    #[test]
+    fn test_detect() {
+        let lockfile = Lockfile::from_str(LOCK).unwrap();
+        assert_eq!(detect(&lockfile), vec!["ring", "aws-lc-sys"]);
+    }
+
+    #[test]
+    fn test_required_tools() {
+        let lockfile = Lockfile::from_str(LOCK).unwrap();
+        let names = |krate: &str, triple: &str| -> Vec<&str> {
+            required_tools(krate, &target(triple), &lockfile)
+                .iter()
+                .map(|tool| tool.name)
+                .collect()
+        };
+
+        assert!(names("aws-lc-sys", "aarch64-unknown-linux-gnu").is_empty());
+        assert_eq!(
+            names("aws-lc-sys", "armv7-unknown-linux-gnueabihf"),
+            vec!["cmake", "clang"]
+        );
+        assert_eq!(
+            names("aws-lc-fips-sys", "x86_64-unknown-linux-gnu"),
+            vec!["cmake", "go", "perl"]
+        );
+        assert!(names("ring", "aarch64-unknown-linux-gnu").is_empty());
+        assert_eq!(names("ring", "wasm32-unknown-unknown"), vec!["clang"]);
+    }
+
+    #[test]
+    fn test_ring_getrandom_feature() {
+        assert_eq!(
+            ring_getrandom_feature(&target("wasm32-unknown-unknown")).map(|(f, _)| f),
+            Some("wasm32_unknown_unknown_js")
+        );
+        assert_eq!(
+            ring_getrandom_feature(&target("thumbv7em-none-eabihf")).map(|(f, _)| f),
+            Some("less-safe-getrandom-custom-or-rdrand")
+        );
+        assert!(ring_getrandom_feature(&target("aarch64-unknown-linux-gnu")).is_none());
+    }
+
+    #[test]
+    fn test_aws_lc_switches_backend_on_unsupported_targets() {
+        let lockfile = Lockfile::from_str(LOCK).unwrap();
+        let host = target("x86_64-unknown-linux-gnu");
+        let mips = target("mips-unknown-linux-gnu");
+        let ctx = PresetContext {
+            target: &mips,
+            host: &host,
+            lockfile: &lockfile,
+            cross_cc: None,
+        };
+
+        let resolution = aws_lc(&ctx).unwrap();
+        assert_eq!(resolution.strategy, "switch-backend");
+        assert!(resolution.notes[0].contains("\"ring\""));
+        assert!(aws_lc_supported(&target("aarch64-unknown-linux-gnu")));
+    }
+
+    #[test]
+    fn test_ring_wasm() {
+        let lockfile = Lockfile::from_str(LOCK).unwrap();
+        let host = target("x86_64-unknown-linux-gnu");
+        let wasm = target("wasm32-unknown-unknown");
+        let ctx = PresetContext {
+            target: &wasm,
+            host: &host,
+            lockfile: &lockfile,
+            cross_cc: None,
+        };
+
+        let resolution = ring(&ctx).unwrap();
+        assert_eq!(resolution.strategy, "clang");
+        assert_eq!(resolution.features, vec!["ring/wasm32_unknown_unknown_js"]);
+    }
+}
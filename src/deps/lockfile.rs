@@ -0,0 +1,145 @@
+//! Cargo.lock inspection for native dependency detection
+
+use crate::error::{Error, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// A single `[[package]]` entry from Cargo.lock
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct LockedPackage {
+    /// Package name
+    pub name: String,
+    /// Locked version
+    pub version: String,
+    /// Package source (`None` for workspace members)
+    pub source: Option<String>,
+    /// Dependencies of this package (`name` or `name version`)
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+impl LockedPackage {
+    /// Check if this package is a member of the local workspace
+    #[must_use]
+    pub fn is_workspace_member(&self) -> bool {
+        self.source.is_none()
+    }
+
+    /// Check if this package depends directly on `name`
+    #[must_use]
+    pub fn depends_on(&self, name: &str) -> bool {
+        self.dependencies
+            .iter()
+            .any(|dep| dep.split_whitespace().next() == Some(name))
+    }
+}
+
+/// Parsed Cargo.lock
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Lockfile {
+    /// All locked packages
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+impl Lockfile {
+    /// Parse a Cargo.lock from a string
+    pub fn from_str(contents: &str) -> Result<Self> {
+        toml::from_str(contents)
+            .map_err(|e| Error::Config(format!("Failed to parse Cargo.lock: {e}")))
+    }
+
+    /// Load a Cargo.lock from disk
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let contents = std::fs::read_to_string(path.as_ref())?;
+        Self::from_str(&contents)
+    }
+
+    /// Find Cargo.lock starting from a directory and walking up to the root
+    #[must_use]
+    pub fn find_from(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join("Cargo.lock"))
+            .find(|path| path.is_file())
+    }
+
+    /// Discover and load the Cargo.lock for the current directory
+    ///
+    /// Returns `Ok(None)` when the project has no lockfile yet.
+    pub fn discover() -> Result<Option<Self>> {
+        let cwd = std::env::current_dir()?;
+        match Self::find_from(&cwd) {
+            Some(path) => Self::from_file(path).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Check if a package appears anywhere in the dependency graph
+    #[must_use]
+    pub fn contains(&self, name: &str) -> bool {
+        self.packages.iter().any(|p| p.name == name)
+    }
+
+    /// Get a locked package by name (first match)
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|p| p.name == name)
+    }
+
+    /// Check if any workspace member depends directly on `name`
+    #[must_use]
+    pub fn is_direct_dependency(&self, name: &str) -> bool {
+        self.packages
+            .iter()
+            .filter(|p| p.is_workspace_member())
+            .any(|p| p.depends_on(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCK: &str = r#"
+version = 3
+
+[[package]]
+name = "my-app"
+version = "0.1.0"
+dependencies = [
+ "openssl",
+ "serde 1.0.200",
+]
+
+[[package]]
+name = "openssl"
+version = "0.10.64"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = [
+ "openssl-sys",
+]
+
+[[package]]
+name = "openssl-sys"
+version = "0.9.102"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    #[test]
+    fn test_parse_lockfile() {
+        let lock = Lockfile::from_str(LOCK).unwrap();
+        assert_eq!(lock.packages.len(), 3);
+        assert!(lock.contains("openssl-sys"));
+        assert!(!lock.contains("libz-sys"));
+        assert_eq!(lock.get("openssl").unwrap().version, "0.10.64");
+    }
+
+    #[test]
+    fn test_direct_dependency() {
+        let lock = Lockfile::from_str(LOCK).unwrap();
+        assert!(lock.is_direct_dependency("openssl"));
+        assert!(lock.is_direct_dependency("serde"));
+        assert!(!lock.is_direct_dependency("openssl-sys"));
+    }
+}
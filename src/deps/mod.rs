@@ -0,0 +1,406 @@
+//! Native dependency resolution for cross builds
+//!
+//! Crates that bind to C libraries (`openssl-sys` and friends) need extra
+//! environment or feature tweaks when cross-compiling. This module inspects
+//! the project's Cargo.lock and decides, per target, how each native
+//! dependency should be provided.
+//!
+//! Resolutions come from four sources, applied in order: the OpenSSL
+//! resolver, bindgen coordination, built-in presets, and user rules from
+//! `[[deps.rules]]`.
+
+pub mod bindgen;
+pub mod crypto;
+mod lockfile;
+pub mod openssl;
+pub mod presets;
+pub mod rules;
+
+pub use bindgen::BindgenResolver;
+pub use lockfile::{LockedPackage, Lockfile};
+pub use openssl::{OpenSslResolver, OpenSslStrategy};
+pub use presets::{Preset, PresetContext, PRESETS};
+
+use crate::config::{DepsConfig, NetworkConfig};
+use crate::error::{Error, Result};
+use crate::installs::{self, Installed};
+use crate::network;
+use crate::scratch::ScratchDir;
+use crate::target::Target;
+use crate::trace;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// File written into a fetched directory once it is fully extracted
+const COMPLETE_MARKER: &str = ".xcargo-complete";
+
+/// A download required before a resolution can be used
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Fetch {
+    /// URL of a `.tar.gz` or `.tar.xz` archive
+    pub url: String,
+    /// Directory the archive is extracted into
+    pub dest: PathBuf,
+    /// Target the archive is for, recorded for `xcargo uninstall`
+    pub target: String,
+    /// Expected SHA-256 of the archive, hex encoded; unverified without
+    pub sha256: Option<String>,
+}
+
+impl Fetch {
+    /// Whether `dest` holds a fully extracted archive
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.dest.join(COMPLETE_MARKER).is_file()
+    }
+
+    /// Download and extract the archive unless `dest` already has it
+    ///
+    /// The archive is downloaded to the build's scratch directory and
+    /// extracted next to `dest`, marked complete and only then moved into
+    /// place. A `dest` without the marker, left by an interrupted or failed
+    /// fetch, is removed and fetched again. curl uses the proxy and CA
+    /// bundle of `network`.
+    pub fn ensure(&self, network: &NetworkConfig) -> Result<()> {
+        if self.is_complete() {
+            return Ok(());
+        }
+        if self.dest.exists() {
+            std::fs::remove_dir_all(&self.dest)?;
+        }
+
+        let parent = self.dest.parent().unwrap_or(Path::new("."));
+        std::fs::create_dir_all(parent)?;
+        let download = ScratchDir::for_build("fetch")?;
+        let staging = ScratchDir::create_in(&parent.join(".partial"), "fetch")?;
+        let archive = download.join("archive.tar.gz");
+
+        let mut curl = network::curl(network)?;
+        curl.arg("-o").arg(&archive).arg(&self.url);
+        let status = curl
+            .status()
+            .map_err(|e| Error::Toolchain(format!("Failed to run curl: {e}")))?;
+        trace::record_command(&curl, status.code());
+        if !status.success() {
+            return Err(Error::Toolchain(format!("Failed to download {}", self.url)));
+        }
+
+        trace::record_download(&self.url, &archive);
+        if let Some(expected) = &self.sha256 {
+            Self::verify(&archive, expected, &self.url)?;
+        }
+
+        let mut tar = Command::new("tar");
+        tar.args(["-xf"])
+            .arg(&archive)
+            .arg("-C")
+            .arg(staging.path())
+            .arg("--strip-components=1");
+        let status = tar
+            .status()
+            .map_err(|e| Error::Toolchain(format!("Failed to run tar: {e}")))?;
+        trace::record_command(&tar, status.code());
+        if !status.success() {
+            return Err(Error::Toolchain(format!("Failed to extract {}", self.url)));
+        }
+
+        std::fs::write(staging.path().join(COMPLETE_MARKER), &self.url)?;
+        if let Err(e) = std::fs::rename(staging.path(), &self.dest) {
+            // Another build may have fetched it in the meantime
+            if !self.is_complete() {
+                return Err(e.into());
+            }
+        }
+        installs::record(Installed::download(&self.dest, &self.target));
+        Ok(())
+    }
+
+    /// Check that the archive at `path` has the SHA-256 `expected`
+    fn verify(path: &Path, expected: &str, url: &str) -> Result<()> {
+        let actual = trace::sha256_file(path)?;
+        if !actual.eq_ignore_ascii_case(expected.trim()) {
+            return Err(Error::Toolchain(format!(
+                "Checksum mismatch for {url}: expected sha256 {expected}, got {actual}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// How a native dependency will be provided for one target
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DepResolution {
+    /// Dependency name (e.g., "openssl")
+    pub name: String,
+    /// Chosen strategy (e.g., "vendored", "sysroot")
+    pub strategy: String,
+    /// Human-readable explanation of the choice
+    pub reason: String,
+    /// Environment variables to set for cargo
+    pub env: Vec<(String, String)>,
+    /// Cargo features to enable
+    pub features: Vec<String>,
+    /// Download needed before building
+    pub fetch: Option<Fetch>,
+    /// Extra detail shown by `--explain`
+    pub notes: Vec<String>,
+}
+
+impl DepResolution {
+    /// Create a resolution with no env or features
+    pub fn new(
+        name: impl Into<String>,
+        strategy: impl Into<String>,
+        reason: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            strategy: strategy.into(),
+            reason: reason.into(),
+            env: Vec::new(),
+            features: Vec::new(),
+            fetch: None,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Perform any pending download
+    pub fn prepare(&self, network: &NetworkConfig) -> Result<()> {
+        match &self.fetch {
+            Some(fetch) => fetch.ensure(network),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Resolve all detected native dependencies for a target
+///
+/// Returns an empty list when the project has no native dependencies
+/// xcargo knows about.
+pub fn resolve_for_target(
+    config: &DepsConfig,
+    lockfile: &Lockfile,
+    target: &Target,
+    host: &Target,
+) -> Result<Vec<DepResolution>> {
+    let mut resolutions = Vec::new();
+
+    if lockfile.contains("openssl-sys") {
+        let resolver =
+            OpenSslResolver::new(&config.openssl, lockfile.is_direct_dependency("openssl"));
+        resolutions.push(resolver.resolve(target, host)?);
+    }
+
+    if lockfile.contains("bindgen") {
+        resolutions.push(BindgenResolver::new(&config.bindgen).resolve(target, host));
+    }
+
+    if config.presets {
+        let ctx = PresetContext {
+            target,
+            host,
+            lockfile,
+            cross_cc: find_cross_cc(target),
+        };
+        resolutions.extend(
+            PRESETS
+                .iter()
+                .filter(|preset| !config.skip.iter().any(|name| preset.matches(name)))
+                .filter_map(|preset| preset.apply(&ctx)),
+        );
+    }
+
+    // User rules come last so their env wins over built-in choices
+    resolutions.extend(rules::apply_rules(&config.rules, lockfile, target));
+
+    Ok(resolutions)
+}
+
+/// Find a GNU cross C compiler for the target in `PATH`
+fn find_cross_cc(target: &Target) -> Option<String> {
+    let cc = format!("{}-gcc", gnu_triple(target)?);
+    which::which(&cc).ok().map(|_| cc)
+}
+
+/// GNU-style triple distro cross toolchains for a Rust target are named
+/// after: the compiler prefix (`i686-linux-gnu-gcc`) and the sysroot
+/// (`/usr/i686-linux-gnu`)
+pub(crate) fn gnu_triple(target: &Target) -> Option<String> {
+    let arch = match target.triple_arch() {
+        "i686" | "i586" => "i686",
+        arch if arch.starts_with("riscv64") => "riscv64",
+        arch if arch.starts_with("arm") || arch.starts_with("thumb") => "arm",
+        arch => arch,
+    };
+
+    match (target.os.as_str(), target.env.as_deref()) {
+        // The triple's own environment, e.g. "gnueabihf" where rustc's
+        // env is "gnu"
+        ("linux", Some(_)) => {
+            let (_, env) = target.triple.split_once("-linux-")?;
+            Some(format!("{arch}-linux-{env}"))
+        }
+        ("windows", Some("gnu")) => Some(format!("{}-w64-mingw32", target.triple_arch())),
+        _ => None,
+    }
+}
+
+/// Debian multiarch triple of a Linux target, which its libraries are
+/// installed under (`/usr/lib/i386-linux-gnu`)
+///
+/// The same as [`gnu_triple`] except for 32-bit x86, which multiarch calls
+/// i386.
+pub(crate) fn multiarch_triple(target: &Target) -> Option<String> {
+    if target.os != "linux" {
+        return None;
+    }
+    let triple = gnu_triple(target)?;
+    Some(match triple.strip_prefix("i686-") {
+        Some(rest) => format!("i386-{rest}"),
+        None => triple,
+    })
+}
+
+/// Environment variable prefix `*-sys` build scripts use for per-target overrides
+///
+/// e.g., `aarch64-unknown-linux-gnu` -> `AARCH64_UNKNOWN_LINUX_GNU`
+#[must_use]
+pub fn target_env_prefix(target: &Target) -> String {
+    target.triple.to_uppercase().replace('-', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_native_deps() {
+        let lockfile = Lockfile::default();
+        let target = Target::from_triple("aarch64-unknown-linux-gnu").unwrap();
+        let host = Target::from_triple("x86_64-unknown-linux-gnu").unwrap();
+
+        let resolutions =
+            resolve_for_target(&DepsConfig::default(), &lockfile, &target, &host).unwrap();
+        assert!(resolutions.is_empty());
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let temp = tempfile::tempdir().unwrap();
+        let archive = temp.path().join("archive.tar.gz");
+        std::fs::write(&archive, "xcargo").unwrap();
+        let sha256 = trace::sha256_file(&archive).unwrap();
+
+        assert!(Fetch::verify(&archive, &sha256.to_uppercase(), "https://example.com/a").is_ok());
+        let err = Fetch::verify(&archive, &"0".repeat(64), "https://example.com/a").unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch"), "{err}");
+    }
+
+    #[test]
+    fn test_partial_fetch_is_not_complete() {
+        let temp = tempfile::tempdir().unwrap();
+        let fetch = Fetch {
+            url: "https://example.com/openssl.tar.gz".to_string(),
+            dest: temp.path().join("openssl"),
+            target: "aarch64-unknown-linux-gnu".to_string(),
+            sha256: None,
+        };
+        assert!(!fetch.is_complete());
+
+        std::fs::create_dir_all(fetch.dest.join("include")).unwrap();
+        assert!(!fetch.is_complete());
+
+        std::fs::write(fetch.dest.join(COMPLETE_MARKER), &fetch.url).unwrap();
+        assert!(fetch.is_complete());
+    }
+
+    #[test]
+    fn test_target_env_prefix() {
+        let target = Target::from_triple("armv7-unknown-linux-gnueabihf").unwrap();
+        assert_eq!(target_env_prefix(&target), "ARMV7_UNKNOWN_LINUX_GNUEABIHF");
+    }
+
+    #[test]
+    fn test_gnu_triple() {
+        let triple = |t: &str| gnu_triple(&Target::from_triple(t).unwrap());
+        assert_eq!(
+            triple("armv7-unknown-linux-gnueabihf").as_deref(),
+            Some("arm-linux-gnueabihf")
+        );
+        assert_eq!(
+            triple("x86_64-pc-windows-gnu").as_deref(),
+            Some("x86_64-w64-mingw32")
+        );
+        assert_eq!(
+            triple("i686-unknown-linux-gnu").as_deref(),
+            Some("i686-linux-gnu")
+        );
+        assert_eq!(
+            triple("i586-unknown-linux-gnu").as_deref(),
+            Some("i686-linux-gnu")
+        );
+        assert_eq!(
+            triple("riscv64gc-unknown-linux-gnu").as_deref(),
+            Some("riscv64-linux-gnu")
+        );
+        assert_eq!(
+            triple("thumbv7neon-unknown-linux-gnueabihf").as_deref(),
+            Some("arm-linux-gnueabihf")
+        );
+        assert_eq!(
+            triple("i686-pc-windows-gnu").as_deref(),
+            Some("i686-w64-mingw32")
+        );
+        assert_eq!(triple("aarch64-apple-darwin"), None);
+    }
+
+    #[test]
+    fn test_multiarch_triple() {
+        let triple = |t: &str| multiarch_triple(&Target::from_triple(t).unwrap());
+        assert_eq!(
+            triple("i686-unknown-linux-gnu").as_deref(),
+            Some("i386-linux-gnu")
+        );
+        assert_eq!(
+            triple("riscv64gc-unknown-linux-gnu").as_deref(),
+            Some("riscv64-linux-gnu")
+        );
+        assert_eq!(
+            triple("armv7-unknown-linux-gnueabihf").as_deref(),
+            Some("arm-linux-gnueabihf")
+        );
+        assert_eq!(triple("x86_64-pc-windows-gnu"), None);
+    }
+
+    #[test]
+    fn test_presets_opt_out() {
+        let lockfile = Lockfile::from_str(
+            r#"
+[[package]]
+name = "libz-sys"
+version = "1.1.16"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#,
+        )
+        .unwrap();
+        let target = Target::from_triple("aarch64-unknown-linux-gnu").unwrap();
+        let host = Target::from_triple("x86_64-unknown-linux-gnu").unwrap();
+
+        let mut config = DepsConfig::default();
+        let resolutions = resolve_for_target(&config, &lockfile, &target, &host).unwrap();
+        assert_eq!(resolutions.len(), 1);
+        assert_eq!(resolutions[0].name, "zlib");
+
+        config.skip = vec!["libz-sys".to_string()];
+        assert!(resolve_for_target(&config, &lockfile, &target, &host)
+            .unwrap()
+            .is_empty());
+
+        config.skip.clear();
+        config.presets = false;
+        assert!(resolve_for_target(&config, &lockfile, &target, &host)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,422 @@
+//! OpenSSL resolution for cross builds
+//!
+//! `openssl-sys` is the most common reason a cross build fails. For each
+//! target this module picks one of three strategies:
+//!
+//! - **sysroot**: link against OpenSSL already installed for the target
+//!   (multiarch packages, a cross sysroot, or `[deps.openssl] dir`)
+//! - **precompiled**: use a prebuilt bundle cached in `~/.xcargo/deps/openssl`
+//! - **vendored**: build OpenSSL from source via the `vendored` feature
+
+use super::{gnu_triple, multiarch_triple, target_env_prefix, DepResolution, Fetch};
+use crate::config::OpenSslConfig;
+use crate::error::{Error, Result};
+use crate::target::Target;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// How OpenSSL is provided for a target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenSslStrategy {
+    /// Build OpenSSL from source (`openssl/vendored`)
+    Vendored,
+    /// Use libraries installed in the host's sysroot for the target
+    Sysroot,
+    /// Use a downloaded precompiled bundle
+    Precompiled,
+}
+
+impl OpenSslStrategy {
+    /// Parse a strategy name, returning `None` for `auto`
+    pub fn from_str(s: &str) -> Result<Option<Self>> {
+        match s {
+            "auto" => Ok(None),
+            "vendored" => Ok(Some(Self::Vendored)),
+            "sysroot" => Ok(Some(Self::Sysroot)),
+            "precompiled" => Ok(Some(Self::Precompiled)),
+            _ => Err(Error::Config(format!("Unknown OpenSSL strategy: {s}"))),
+        }
+    }
+
+    /// Get the strategy name
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Vendored => "vendored",
+            Self::Sysroot => "sysroot",
+            Self::Precompiled => "precompiled",
+        }
+    }
+}
+
+impl fmt::Display for OpenSslStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// OpenSSL locations found for a target
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SysrootLayout {
+    /// Prefix with `lib/` and `include/` below it
+    Prefix(PathBuf),
+    /// Split multiarch layout (Debian/Ubuntu `libssl-dev:<arch>`)
+    Split { lib: PathBuf, include: PathBuf },
+}
+
+/// Resolves the OpenSSL strategy for a target
+pub struct OpenSslResolver<'a> {
+    config: &'a OpenSslConfig,
+    /// Filesystem root searched for sysroot installs
+    root: PathBuf,
+    /// Cache directory for precompiled bundles
+    cache_dir: PathBuf,
+    /// Whether `openssl` is a direct dependency of the workspace
+    direct_dependency: bool,
+}
+
+impl<'a> OpenSslResolver<'a> {
+    /// Create a resolver using the default search locations
+    #[must_use]
+    pub fn new(config: &'a OpenSslConfig, direct_dependency: bool) -> Self {
+        let cache_dir = dirs::home_dir()
+            .unwrap_or_default()
+            .join(".xcargo")
+            .join("deps")
+            .join("openssl");
+
+        Self {
+            config,
+            root: PathBuf::from("/"),
+            cache_dir,
+            direct_dependency,
+        }
+    }
+
+    /// Override the filesystem root searched for sysroot installs
+    #[must_use]
+    pub fn with_root(mut self, root: PathBuf) -> Self {
+        self.root = root;
+        self
+    }
+
+    /// Override the precompiled bundle cache directory
+    #[must_use]
+    pub fn with_cache_dir(mut self, cache_dir: PathBuf) -> Self {
+        self.cache_dir = cache_dir;
+        self
+    }
+
+    /// Resolve how OpenSSL should be provided for `target`
+    pub fn resolve(&self, target: &Target, host: &Target) -> Result<DepResolution> {
+        let requested = self
+            .config
+            .targets
+            .get(&target.triple)
+            .unwrap_or(&self.config.strategy);
+
+        match OpenSslStrategy::from_str(requested)? {
+            Some(OpenSslStrategy::Vendored) => Ok(self.vendored(target, "requested in xcargo.toml")),
+            Some(OpenSslStrategy::Sysroot) => self.sysroot(target).ok_or_else(|| {
+                Error::Config(format!(
+                    "OpenSSL strategy 'sysroot' requested for {} but no OpenSSL libraries were found for it. \
+                     Install them or set [deps.openssl] dir",
+                    target.triple
+                ))
+            }),
+            Some(OpenSslStrategy::Precompiled) => self.precompiled(target).ok_or_else(|| {
+                Error::Config(format!(
+                    "OpenSSL strategy 'precompiled' requested for {} but no bundle is cached and \
+                     [deps.openssl] precompiled_url is not set",
+                    target.triple
+                ))
+            }),
+            None => Ok(self.auto(target, host)),
+        }
+    }
+
+    /// Pick the best available strategy
+    fn auto(&self, target: &Target, host: &Target) -> DepResolution {
+        if target.triple == host.triple && self.config.dir.is_none() {
+            return DepResolution::new(
+                "openssl",
+                OpenSslStrategy::Sysroot.as_str(),
+                "native build, openssl-sys locates host OpenSSL via pkg-config",
+            );
+        }
+
+        self.sysroot(target)
+            .or_else(|| self.precompiled(target))
+            .unwrap_or_else(|| self.vendored(target, "no target OpenSSL found on this host"))
+    }
+
+    fn vendored(&self, target: &Target, why: &str) -> DepResolution {
+        let mut resolution = DepResolution::new("openssl", OpenSslStrategy::Vendored.as_str(), why);
+
+        if self.direct_dependency {
+            resolution.features.push("openssl/vendored".to_string());
+        } else {
+            resolution.reason.push_str(
+                "; enable openssl's `vendored` feature in Cargo.toml to build it from source",
+            );
+        }
+
+        // A stale OPENSSL_NO_VENDOR would silently disable the vendored build
+        resolution.env.push((
+            format!("{}_OPENSSL_NO_VENDOR", target_env_prefix(target)),
+            "0".to_string(),
+        ));
+        resolution
+    }
+
+    fn sysroot(&self, target: &Target) -> Option<DepResolution> {
+        let prefix = target_env_prefix(target);
+        let (layout, why) = if let Some(dir) = &self.config.dir {
+            (
+                SysrootLayout::Prefix(PathBuf::from(dir)),
+                "using [deps.openssl] dir".to_string(),
+            )
+        } else {
+            let layout = self.find_sysroot(target)?;
+            let why = match &layout {
+                SysrootLayout::Prefix(dir) => format!("found target OpenSSL in {}", dir.display()),
+                SysrootLayout::Split { lib, .. } => {
+                    format!("found multiarch OpenSSL in {}", lib.display())
+                }
+            };
+            (layout, why)
+        };
+
+        let mut resolution = DepResolution::new("openssl", OpenSslStrategy::Sysroot.as_str(), why);
+        match layout {
+            SysrootLayout::Prefix(dir) => {
+                resolution
+                    .env
+                    .push((format!("{prefix}_OPENSSL_DIR"), dir.display().to_string()));
+            }
+            SysrootLayout::Split { lib, include } => {
+                resolution.env.push((
+                    format!("{prefix}_OPENSSL_LIB_DIR"),
+                    lib.display().to_string(),
+                ));
+                resolution.env.push((
+                    format!("{prefix}_OPENSSL_INCLUDE_DIR"),
+                    include.display().to_string(),
+                ));
+            }
+        }
+        Some(resolution)
+    }
+
+    fn precompiled(&self, target: &Target) -> Option<DepResolution> {
+        let bundle = self.cache_dir.join(&target.triple);
+        let prefix = target_env_prefix(target);
+
+        let mut resolution = if has_openssl_prefix(&bundle) {
+            DepResolution::new(
+                "openssl",
+                OpenSslStrategy::Precompiled.as_str(),
+                format!("using cached bundle in {}", bundle.display()),
+            )
+        } else {
+            let url = self
+                .config
+                .precompiled_url
+                .as_ref()?
+                .replace("{target}", &target.triple);
+            let mut resolution = DepResolution::new(
+                "openssl",
+                OpenSslStrategy::Precompiled.as_str(),
+                format!("downloading bundle from {url}"),
+            );
+            resolution.fetch = Some(Fetch {
+                url,
+                dest: bundle.clone(),
+                target: target.triple.clone(),
+                sha256: None,
+            });
+            resolution
+        };
+
+        resolution.env.push((
+            format!("{prefix}_OPENSSL_DIR"),
+            bundle.display().to_string(),
+        ));
+        resolution
+            .env
+            .push((format!("{prefix}_OPENSSL_STATIC"), "1".to_string()));
+        Some(resolution)
+    }
+
+    /// Search well-known cross sysroot locations for the target's OpenSSL
+    fn find_sysroot(&self, target: &Target) -> Option<SysrootLayout> {
+        // Cross sysroot: /usr/<gnu-triple>/{lib,include}
+        let prefix = self.root.join("usr").join(gnu_triple(target)?);
+        if has_openssl_prefix(&prefix) {
+            return Some(SysrootLayout::Prefix(prefix));
+        }
+
+        // Debian multiarch: /usr/lib/<multiarch-triple> + /usr/include
+        let lib = self
+            .root
+            .join("usr")
+            .join("lib")
+            .join(multiarch_triple(target)?);
+        let include = self.root.join("usr").join("include");
+        if has_libssl(&lib) && include.join("openssl").is_dir() {
+            return Some(SysrootLayout::Split { lib, include });
+        }
+
+        None
+    }
+}
+
+/// Check if `dir` looks like an OpenSSL install prefix
+fn has_openssl_prefix(dir: &Path) -> bool {
+    has_libssl(&dir.join("lib")) && dir.join("include").join("openssl").is_dir()
+}
+
+/// Check if `dir` contains libssl in any of the usual forms
+fn has_libssl(dir: &Path) -> bool {
+    ["libssl.so", "libssl.a", "libssl.dylib", "libssl.dll.a"]
+        .iter()
+        .any(|name| dir.join(name).exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn target(triple: &str) -> Target {
+        Target::from_triple(triple).unwrap()
+    }
+
+    fn install_prefix(dir: &Path) {
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        fs::create_dir_all(dir.join("include").join("openssl")).unwrap();
+        fs::write(dir.join("lib").join("libssl.a"), "").unwrap();
+    }
+
+    #[test]
+    fn test_native_build_uses_host_openssl() {
+        let config = OpenSslConfig::default();
+        let resolver = OpenSslResolver::new(&config, true);
+        let host = target("x86_64-unknown-linux-gnu");
+
+        let resolution = resolver.resolve(&host, &host).unwrap();
+        assert_eq!(resolution.strategy, "sysroot");
+        assert!(resolution.env.is_empty());
+    }
+
+    #[test]
+    fn test_auto_finds_cross_sysroot() {
+        let temp = TempDir::new().unwrap();
+        install_prefix(&temp.path().join("usr").join("aarch64-linux-gnu"));
+
+        let config = OpenSslConfig::default();
+        let resolver = OpenSslResolver::new(&config, true)
+            .with_root(temp.path().to_path_buf())
+            .with_cache_dir(temp.path().join("cache"));
+
+        let resolution = resolver
+            .resolve(
+                &target("aarch64-unknown-linux-gnu"),
+                &target("x86_64-unknown-linux-gnu"),
+            )
+            .unwrap();
+        assert_eq!(resolution.strategy, "sysroot");
+        assert_eq!(resolution.env[0].0, "AARCH64_UNKNOWN_LINUX_GNU_OPENSSL_DIR");
+    }
+
+    #[test]
+    fn test_sysroot_layouts_of_32_bit_x86() {
+        let i686 = target("i686-unknown-linux-gnu");
+        let host = target("x86_64-unknown-linux-gnu");
+        let config = OpenSslConfig::default();
+
+        // The cross sysroot is named like the compiler
+        let temp = TempDir::new().unwrap();
+        install_prefix(&temp.path().join("usr").join("i686-linux-gnu"));
+        let resolver = OpenSslResolver::new(&config, true).with_root(temp.path().to_path_buf());
+        assert!(matches!(
+            resolver.find_sysroot(&i686),
+            Some(SysrootLayout::Prefix(_))
+        ));
+
+        // Multiarch packages use i386
+        let temp = TempDir::new().unwrap();
+        let lib = temp.path().join("usr").join("lib").join("i386-linux-gnu");
+        fs::create_dir_all(&lib).unwrap();
+        fs::write(lib.join("libssl.so"), "").unwrap();
+        fs::create_dir_all(temp.path().join("usr").join("include").join("openssl")).unwrap();
+        let resolver = OpenSslResolver::new(&config, true)
+            .with_root(temp.path().to_path_buf())
+            .with_cache_dir(temp.path().join("cache"));
+        assert!(matches!(
+            resolver.find_sysroot(&i686),
+            Some(SysrootLayout::Split { .. })
+        ));
+        let resolution = resolver.resolve(&i686, &host).unwrap();
+        assert_eq!(resolution.strategy, "sysroot");
+    }
+
+    #[test]
+    fn test_auto_falls_back_to_vendored() {
+        let temp = TempDir::new().unwrap();
+        let config = OpenSslConfig::default();
+        let resolver = OpenSslResolver::new(&config, true)
+            .with_root(temp.path().to_path_buf())
+            .with_cache_dir(temp.path().join("cache"));
+
+        let resolution = resolver
+            .resolve(
+                &target("aarch64-unknown-linux-gnu"),
+                &target("x86_64-unknown-linux-gnu"),
+            )
+            .unwrap();
+        assert_eq!(resolution.strategy, "vendored");
+        assert_eq!(resolution.features, vec!["openssl/vendored"]);
+    }
+
+    #[test]
+    fn test_precompiled_download_planned() {
+        let temp = TempDir::new().unwrap();
+        let mut config = OpenSslConfig::default();
+        config.precompiled_url = Some("https://example.com/openssl-{target}.tar.gz".to_string());
+        let resolver = OpenSslResolver::new(&config, false)
+            .with_root(temp.path().to_path_buf())
+            .with_cache_dir(temp.path().join("cache"));
+
+        let resolution = resolver
+            .resolve(
+                &target("x86_64-pc-windows-gnu"),
+                &target("x86_64-unknown-linux-gnu"),
+            )
+            .unwrap();
+        assert_eq!(resolution.strategy, "precompiled");
+        let fetch = resolution.fetch.unwrap();
+        assert_eq!(
+            fetch.url,
+            "https://example.com/openssl-x86_64-pc-windows-gnu.tar.gz"
+        );
+    }
+
+    #[test]
+    fn test_explicit_sysroot_missing_is_error() {
+        let temp = TempDir::new().unwrap();
+        let mut config = OpenSslConfig::default();
+        config.targets.insert(
+            "aarch64-unknown-linux-gnu".to_string(),
+            "sysroot".to_string(),
+        );
+        let resolver = OpenSslResolver::new(&config, true).with_root(temp.path().to_path_buf());
+
+        let result = resolver.resolve(
+            &target("aarch64-unknown-linux-gnu"),
+            &target("x86_64-unknown-linux-gnu"),
+        );
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,261 @@
+//! Built-in presets for common native dependencies
+//!
+//! Each preset recognises one `*-sys` crate in Cargo.lock and encodes the
+//! env vars and features that usually make it cross-compile:
+//!
+//! - **sqlite** (`libsqlite3-sys`): build the bundled amalgamation
+//! - **zlib** (`libz-sys`): build zlib statically from source
+//! - **libgit2** (`libgit2-sys`): build the vendored libgit2
+//! - **ring** (`ring`): point the `cc` crate at a target C compiler
+//! - **aws-lc** (`aws-lc-sys`), **aws-lc-fips** (`aws-lc-fips-sys`): see
+//!   [`super::crypto`]
+//!
+//! Presets only apply to cross builds; native builds keep cargo's defaults.
+
+use super::{crypto, DepResolution, Lockfile};
+use crate::target::Target;
+use std::fmt::Write;
+
+/// Inputs available to a preset
+pub struct PresetContext<'a> {
+    /// Target being built
+    pub target: &'a Target,
+    /// Host machine
+    pub host: &'a Target,
+    /// Project lockfile
+    pub lockfile: &'a Lockfile,
+    /// GNU cross C compiler for the target found in `PATH`, if any
+    pub cross_cc: Option<String>,
+}
+
+impl PresetContext<'_> {
+    pub(super) fn is_cross(&self) -> bool {
+        self.target.triple != self.host.triple
+    }
+}
+
+/// A native dependency preset
+pub struct Preset {
+    /// Preset name, used in `[deps] skip`
+    pub name: &'static str,
+    /// Crate whose presence in Cargo.lock enables the preset
+    pub crate_name: &'static str,
+    apply: fn(&PresetContext<'_>) -> Option<DepResolution>,
+}
+
+impl Preset {
+    /// Check if this preset matches a `[deps] skip` entry (name or crate)
+    #[must_use]
+    pub fn matches(&self, name: &str) -> bool {
+        self.name == name || self.crate_name == name
+    }
+
+    /// Apply the preset, returning `None` when nothing needs to change
+    #[must_use]
+    pub fn apply(&self, ctx: &PresetContext<'_>) -> Option<DepResolution> {
+        if !ctx.lockfile.contains(self.crate_name) {
+            return None;
+        }
+        (self.apply)(ctx)
+    }
+}
+
+/// All built-in presets
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "sqlite",
+        crate_name: "libsqlite3-sys",
+        apply: sqlite,
+    },
+    Preset {
+        name: "zlib",
+        crate_name: "libz-sys",
+        apply: zlib,
+    },
+    Preset {
+        name: "libgit2",
+        crate_name: "libgit2-sys",
+        apply: libgit2,
+    },
+    Preset {
+        name: "ring",
+        crate_name: "ring",
+        apply: crypto::ring,
+    },
+    Preset {
+        name: "aws-lc",
+        crate_name: "aws-lc-sys",
+        apply: crypto::aws_lc,
+    },
+    Preset {
+        name: "aws-lc-fips",
+        crate_name: "aws-lc-fips-sys",
+        apply: crypto::aws_lc_fips,
+    },
+];
+
+fn sqlite(ctx: &PresetContext<'_>) -> Option<DepResolution> {
+    if !ctx.is_cross() {
+        return None;
+    }
+    let mut resolution = DepResolution::new(
+        "sqlite",
+        "bundled",
+        "cross build, compiling the bundled SQLite",
+    );
+    enable_feature(
+        &mut resolution,
+        ctx.lockfile,
+        &[("rusqlite", "bundled"), ("libsqlite3-sys", "bundled")],
+    );
+    Some(resolution)
+}
+
+fn zlib(ctx: &PresetContext<'_>) -> Option<DepResolution> {
+    if !ctx.is_cross() {
+        return None;
+    }
+    let mut resolution =
+        DepResolution::new("zlib", "static", "cross build, compiling zlib from source");
+    resolution
+        .env
+        .push(("LIBZ_SYS_STATIC".to_string(), "1".to_string()));
+    enable_feature(&mut resolution, ctx.lockfile, &[("libz-sys", "static")]);
+    Some(resolution)
+}
+
+fn libgit2(ctx: &PresetContext<'_>) -> Option<DepResolution> {
+    if !ctx.is_cross() {
+        return None;
+    }
+    let mut resolution = DepResolution::new(
+        "libgit2",
+        "vendored",
+        "cross build, compiling the vendored libgit2",
+    );
+    resolution
+        .env
+        .push(("LIBGIT2_NO_VENDOR".to_string(), "0".to_string()));
+    enable_feature(
+        &mut resolution,
+        ctx.lockfile,
+        &[("git2", "vendored-libgit2"), ("libgit2-sys", "vendored")],
+    );
+    Some(resolution)
+}
+
+/// Enable the first `(crate, feature)` pair that is a direct dependency
+///
+/// When none is, the reason is extended with a hint since cargo only
+/// accepts `crate/feature` for direct dependencies.
+pub(super) fn enable_feature(
+    resolution: &mut DepResolution,
+    lockfile: &Lockfile,
+    candidates: &[(&str, &str)],
+) {
+    if let Some((krate, feature)) = candidates
+        .iter()
+        .find(|(krate, _)| lockfile.is_direct_dependency(krate))
+    {
+        resolution.features.push(format!("{krate}/{feature}"));
+    } else if let Some((krate, feature)) = candidates.first() {
+        let _ = write!(
+            resolution.reason,
+            "; enable {krate}'s `{feature}` feature in Cargo.toml if the build fails"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCK: &str = r#"
+[[package]]
+name = "my-app"
+version = "0.1.0"
+dependencies = [
+ "rusqlite",
+ "flate2",
+ "ring",
+]
+
+[[package]]
+name = "rusqlite"
+version = "0.31.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["libsqlite3-sys"]
+
+[[package]]
+name = "libsqlite3-sys"
+version = "0.28.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "flate2"
+version = "1.0.30"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["libz-sys"]
+
+[[package]]
+name = "libz-sys"
+version = "1.1.16"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "ring"
+version = "0.17.8"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    fn apply(name: &str, ctx: &PresetContext<'_>) -> Option<DepResolution> {
+        PRESETS.iter().find(|p| p.name == name).unwrap().apply(ctx)
+    }
+
+    #[test]
+    fn test_presets_cross_build() {
+        let lockfile = Lockfile::from_str(LOCK).unwrap();
+        let target = Target::from_triple("aarch64-unknown-linux-gnu").unwrap();
+        let host = Target::from_triple("x86_64-unknown-linux-gnu").unwrap();
+        let ctx = PresetContext {
+            target: &target,
+            host: &host,
+            lockfile: &lockfile,
+            cross_cc: Some("aarch64-linux-gnu-gcc".to_string()),
+        };
+
+        let sqlite = apply("sqlite", &ctx).unwrap();
+        assert_eq!(sqlite.features, vec!["rusqlite/bundled"]);
+
+        let zlib = apply("zlib", &ctx).unwrap();
+        assert!(zlib.features.is_empty());
+        assert_eq!(
+            zlib.env,
+            vec![("LIBZ_SYS_STATIC".to_string(), "1".to_string())]
+        );
+
+        let ring = apply("ring", &ctx).unwrap();
+        assert_eq!(ring.strategy, "cross-cc");
+        assert!(ring.env.contains(&(
+            "AR_aarch64_unknown_linux_gnu".to_string(),
+            "aarch64-linux-gnu-ar".to_string()
+        )));
+
+        // libgit2-sys is not in the lockfile
+        assert!(apply("libgit2", &ctx).is_none());
+    }
+
+    #[test]
+    fn test_presets_skip_native_build() {
+        let lockfile = Lockfile::from_str(LOCK).unwrap();
+        let host = Target::from_triple("x86_64-unknown-linux-gnu").unwrap();
+        let ctx = PresetContext {
+            target: &host,
+            host: &host,
+            lockfile: &lockfile,
+            cross_cc: None,
+        };
+
+        assert!(PRESETS.iter().all(|p| p.apply(&ctx).is_none()));
+    }
+}
@@ -0,0 +1,131 @@
+//! User-defined dependency rules (`[[deps.rules]]`)
+//!
+//! Rules let a team encode fixes for their own native dependencies, e.g.:
+//!
+//! ```toml
+//! [[deps.rules]]
+//! crate = "reqwest"
+//! targets = ["*-musl"]
+//! features = ["reqwest/rustls-tls"]
+//! reason = "no OpenSSL on musl images"
+//! ```
+
+use super::{DepResolution, Lockfile};
+use crate::config::DepRuleConfig;
+use crate::target::Target;
+
+/// Apply every rule whose crate is locked and whose targets match
+#[must_use]
+pub fn apply_rules(
+    rules: &[DepRuleConfig],
+    lockfile: &Lockfile,
+    target: &Target,
+) -> Vec<DepResolution> {
+    rules
+        .iter()
+        .filter(|rule| lockfile.contains(&rule.krate))
+        .filter(|rule| {
+            rule.targets.is_empty()
+                || rule
+                    .targets
+                    .iter()
+                    .any(|pattern| matches_target(pattern, &target.triple))
+        })
+        .map(|rule| {
+            let reason = rule
+                .reason
+                .clone()
+                .unwrap_or_else(|| "matched [[deps.rules]] in xcargo.toml".to_string());
+            let mut resolution = DepResolution::new(&rule.krate, "rule", reason);
+
+            let mut env: Vec<_> = rule.env.iter().collect();
+            env.sort();
+            resolution.env = env
+                .into_iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+            resolution.features = rule.features.clone();
+            resolution
+        })
+        .collect()
+}
+
+/// Match a target triple against a pattern where `*` matches any substring
+#[must_use]
+pub fn matches_target(pattern: &str, triple: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = triple.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard: exact match
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_target() {
+        assert!(matches_target("*-musl", "x86_64-unknown-linux-musl"));
+        assert!(matches_target("*", "aarch64-apple-darwin"));
+        assert!(matches_target(
+            "aarch64-*-linux-*",
+            "aarch64-unknown-linux-gnu"
+        ));
+        assert!(matches_target(
+            "x86_64-pc-windows-gnu",
+            "x86_64-pc-windows-gnu"
+        ));
+        assert!(!matches_target("*-musl", "x86_64-unknown-linux-gnu"));
+        assert!(!matches_target("x86_64-*", "aarch64-unknown-linux-gnu"));
+        assert!(!matches_target("x86_64", "x86_64-pc-windows-gnu"));
+    }
+
+    #[test]
+    fn test_apply_rules() {
+        let lockfile = Lockfile::from_str(
+            r#"
+[[package]]
+name = "reqwest"
+version = "0.12.4"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#,
+        )
+        .unwrap();
+        let rules = vec![
+            DepRuleConfig {
+                krate: "reqwest".to_string(),
+                targets: vec!["*-musl".to_string()],
+                features: vec!["reqwest/rustls-tls".to_string()],
+                ..Default::default()
+            },
+            DepRuleConfig {
+                krate: "bindgen".to_string(),
+                env: [("LIBCLANG_PATH".to_string(), "/opt/llvm/lib".to_string())].into(),
+                ..Default::default()
+            },
+        ];
+
+        let musl = Target::from_triple("x86_64-unknown-linux-musl").unwrap();
+        let resolutions = apply_rules(&rules, &lockfile, &musl);
+        assert_eq!(resolutions.len(), 1);
+        assert_eq!(resolutions[0].features, vec!["reqwest/rustls-tls"]);
+
+        let gnu = Target::from_triple("x86_64-unknown-linux-gnu").unwrap();
+        assert!(apply_rules(&rules, &lockfile, &gnu).is_empty());
+    }
+}
@@ -1,12 +1,24 @@
 //! Individual diagnostic checks
 
-use crate::config::ConfigDiscovery;
+use crate::build::artifacts;
+use crate::build::glibc::{self, GlibcVersion};
+use crate::cache::{wrapper_from_env, CompilerWrapper};
+use crate::config::{Config, ConfigDiscovery};
+use crate::deps::bindgen::find_libclang;
+use crate::deps::crypto;
+use crate::deps::Lockfile;
+use crate::i18n::{self, Catalog};
+use crate::target::Target;
+use crate::toolchain::msvc;
 use crate::toolchain::ToolchainManager;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use which::which;
 
 /// Status of a diagnostic check
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum CheckStatus {
     /// Check passed successfully
     Pass,
@@ -19,7 +31,7 @@ pub enum CheckStatus {
 }
 
 /// Result of a diagnostic check
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CheckResult {
     /// Name of the check
     pub name: String,
@@ -28,7 +40,11 @@ pub struct CheckResult {
     /// Detailed message
     pub message: String,
     /// Optional suggestion for fixing issues
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub suggestion: Option<String>,
+    /// Version of the tool found, if the check reports one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
 }
 
 impl CheckResult {
@@ -39,6 +55,7 @@ impl CheckResult {
             status: CheckStatus::Pass,
             message: message.into(),
             suggestion: None,
+            version: None,
         }
     }
 
@@ -53,6 +70,7 @@ impl CheckResult {
             status: CheckStatus::Warning,
             message: message.into(),
             suggestion: Some(suggestion.into()),
+            version: None,
         }
     }
 
@@ -67,6 +85,7 @@ impl CheckResult {
             status: CheckStatus::Fail,
             message: message.into(),
             suggestion: Some(suggestion.into()),
+            version: None,
         }
     }
 
@@ -81,8 +100,16 @@ impl CheckResult {
             status: CheckStatus::Critical,
             message: message.into(),
             suggestion: Some(suggestion.into()),
+            version: None,
         }
     }
+
+    /// Record the version of the tool found
+    #[must_use]
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
 }
 
 /// Trait for diagnostic checks
@@ -99,10 +126,8 @@ pub fn check_rustup() -> CheckResult {
             if let Ok(output) = Command::new("rustup").arg("--version").output() {
                 let version = String::from_utf8_lossy(&output.stdout);
                 let version_line = version.lines().next().unwrap_or("unknown");
-                CheckResult::pass(
-                    "rustup",
-                    format!("Found at {:?}: {}", path, version_line),
-                )
+                CheckResult::pass("rustup", format!("Found at {:?}: {}", path, version_line))
+                    .with_version(version_line)
             } else {
                 CheckResult::pass("rustup", format!("Found at {:?}", path))
             }
@@ -110,7 +135,7 @@ pub fn check_rustup() -> CheckResult {
         Err(_) => CheckResult::critical(
             "rustup",
             "rustup not found in PATH",
-            "Install rustup from https://rustup.rs/",
+            i18n::tr("doctor.install_rustup"),
         ),
     }
 }
@@ -123,6 +148,7 @@ pub fn check_cargo() -> CheckResult {
                 let version = String::from_utf8_lossy(&output.stdout);
                 let version_line = version.lines().next().unwrap_or("unknown");
                 CheckResult::pass("cargo", format!("Found at {:?}: {}", path, version_line))
+                    .with_version(version_line)
             } else {
                 CheckResult::pass("cargo", format!("Found at {:?}", path))
             }
@@ -130,7 +156,7 @@ pub fn check_cargo() -> CheckResult {
         Err(_) => CheckResult::critical(
             "cargo",
             "cargo not found in PATH",
-            "Install Rust toolchain from https://rustup.rs/",
+            i18n::tr("doctor.install_rust"),
         ),
     }
 }
@@ -143,7 +169,7 @@ pub fn check_default_toolchain() -> CheckResult {
             return CheckResult::fail(
                 "default toolchain",
                 "Could not initialize toolchain manager",
-                "Ensure rustup is properly installed",
+                i18n::tr("doctor.check_rustup_install"),
             )
         }
     };
@@ -156,12 +182,12 @@ pub fn check_default_toolchain() -> CheckResult {
         Ok(None) => CheckResult::warning(
             "default toolchain",
             "No default toolchain set",
-            "Run: rustup default stable",
+            i18n::tr("doctor.set_default_toolchain"),
         ),
         Err(_) => CheckResult::warning(
             "default toolchain",
             "Could not determine default toolchain",
-            "Run: rustup default stable",
+            i18n::tr("doctor.set_default_toolchain"),
         ),
     }
 }
@@ -174,7 +200,7 @@ pub fn check_installed_targets() -> CheckResult {
             return CheckResult::fail(
                 "installed targets",
                 "Could not check installed targets",
-                "Ensure rustup is properly installed",
+                i18n::tr("doctor.check_rustup_install"),
             )
         }
     };
@@ -187,7 +213,7 @@ pub fn check_installed_targets() -> CheckResult {
             return CheckResult::fail(
                 "installed targets",
                 "Could not determine toolchain",
-                "Run: rustup default stable",
+                i18n::tr("doctor.set_default_toolchain"),
             )
         }
     };
@@ -201,7 +227,7 @@ pub fn check_installed_targets() -> CheckResult {
                 CheckResult::warning(
                     "installed targets",
                     "No additional targets installed (only host target)",
-                    "Install targets with: rustup target add <target>",
+                    i18n::tr("doctor.add_targets"),
                 )
             } else {
                 CheckResult::pass(
@@ -213,7 +239,7 @@ pub fn check_installed_targets() -> CheckResult {
         Err(_) => CheckResult::fail(
             "installed targets",
             "Could not list installed targets",
-            "Check rustup installation",
+            i18n::tr("doctor.check_rustup"),
         ),
     }
 }
@@ -224,10 +250,8 @@ pub fn check_zig() -> CheckResult {
         Ok(path) => {
             if let Ok(output) = Command::new("zig").arg("version").output() {
                 let version = String::from_utf8_lossy(&output.stdout);
-                CheckResult::pass(
-                    "zig",
-                    format!("Found at {:?}: v{}", path, version.trim()),
-                )
+                CheckResult::pass("zig", format!("Found at {:?}: v{}", path, version.trim()))
+                    .with_version(version.trim())
             } else {
                 CheckResult::pass("zig", format!("Found at {:?}", path))
             }
@@ -235,7 +259,7 @@ pub fn check_zig() -> CheckResult {
         Err(_) => CheckResult::warning(
             "zig",
             "Zig not found (optional)",
-            "Install Zig for easy Linux cross-compilation: https://ziglang.org/download/",
+            i18n::tr("doctor.install_zig"),
         ),
     }
 }
@@ -252,21 +276,21 @@ pub fn check_docker() -> CheckResult {
                     CheckResult::warning(
                         "docker",
                         format!("Found at {:?} but daemon not running", path),
-                        "Start Docker daemon",
+                        i18n::tr("doctor.start_docker"),
                     )
                 }
             } else {
                 CheckResult::warning(
                     "docker",
                     format!("Found at {:?} but status unknown", path),
-                    "Verify Docker installation",
+                    i18n::tr("doctor.verify_docker"),
                 )
             }
         }
         Err(_) => CheckResult::warning(
             "docker",
             "Docker not found (optional)",
-            "Install Docker for container-based builds: https://docker.com/",
+            i18n::tr("doctor.install_docker"),
         ),
     }
 }
@@ -279,6 +303,7 @@ pub fn check_podman() -> CheckResult {
                 let version = String::from_utf8_lossy(&output.stdout);
                 let version_line = version.lines().next().unwrap_or("unknown");
                 CheckResult::pass("podman", format!("Found at {:?}: {}", path, version_line))
+                    .with_version(version_line)
             } else {
                 CheckResult::pass("podman", format!("Found at {:?}", path))
             }
@@ -286,7 +311,31 @@ pub fn check_podman() -> CheckResult {
         Err(_) => CheckResult::warning(
             "podman",
             "Podman not found (optional)",
-            "Install Podman as Docker alternative: https://podman.io/",
+            i18n::tr("doctor.install_podman"),
+        ),
+    }
+}
+
+/// Check if nerdctl, the containerd CLI, is available
+pub fn check_nerdctl() -> CheckResult {
+    match which("nerdctl") {
+        Ok(path) => {
+            if let Ok(output) = Command::new("nerdctl").arg("--version").output() {
+                let version = String::from_utf8_lossy(&output.stdout);
+                let version_line = version.lines().next().unwrap_or("unknown");
+                CheckResult::pass(
+                    "nerdctl",
+                    format!("Found at {}: {version_line}", path.display()),
+                )
+                .with_version(version_line)
+            } else {
+                CheckResult::pass("nerdctl", format!("Found at {}", path.display()))
+            }
+        }
+        Err(_) => CheckResult::warning(
+            "nerdctl",
+            "nerdctl not found (optional)",
+            i18n::tr("doctor.install_nerdctl"),
         ),
     }
 }
@@ -315,7 +364,7 @@ pub fn check_common_linkers() -> CheckResult {
         CheckResult::warning(
             "common linkers",
             "No common cross-compilation linkers found",
-            "Install build tools for your platform (build-essential, mingw-w64, etc.)",
+            i18n::tr("doctor.install_build_tools"),
         )
     } else {
         let message = format!("Found {} linker(s): {}", found.len(), found.join(", "));
@@ -323,39 +372,419 @@ pub fn check_common_linkers() -> CheckResult {
         if missing.is_empty() {
             CheckResult::pass("common linkers", message)
         } else {
-            let suggestion = format!(
-                "Missing: {}. Install as needed for your targets.",
-                missing
-                    .iter()
-                    .map(|(_, desc)| *desc)
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            );
+            let linkers = missing
+                .iter()
+                .map(|(_, desc)| *desc)
+                .collect::<Vec<_>>()
+                .join(", ");
+            let suggestion = i18n::tr_args("doctor.missing_linkers", &[("linkers", &linkers)]);
             CheckResult::warning("common linkers", message, suggestion)
         }
     }
 }
 
-/// Check for xcargo configuration file
-pub fn check_config_file() -> CheckResult {
-    match ConfigDiscovery::find() {
-        Ok(Some(path)) => CheckResult::pass(
-            "xcargo.toml",
-            format!("Found configuration at: {}", path.display()),
+/// Check that the compiler wrapper (sccache) works for configured targets
+pub fn check_compiler_wrapper() -> CheckResult {
+    let config = Config::effective().unwrap_or_default();
+
+    if let Some(path) = wrapper_from_env() {
+        return CheckResult::pass(
+            "compiler cache",
+            format!("RUSTC_WRAPPER set by environment: {}", path.display()),
+        );
+    }
+
+    let Some(wrapper) = CompilerWrapper::detect(&config.cache) else {
+        return match config.cache.compiler_wrapper.as_deref() {
+            None | Some("none") => {
+                CheckResult::pass("compiler cache", "No compiler wrapper in use")
+            }
+            Some(name) => CheckResult::fail(
+                "compiler cache",
+                format!("Compiler wrapper '{name}' not found in PATH"),
+                i18n::tr_args("doctor.install_wrapper", &[("name", name)]),
+            ),
+        };
+    };
+
+    let mut targets = config.targets.default.clone();
+    if targets.is_empty() {
+        if let Ok(host) = Target::detect_host() {
+            targets.push(host.triple);
+        }
+    }
+
+    let failures: Vec<String> = targets
+        .iter()
+        .filter_map(|target| {
+            wrapper
+                .verify(target)
+                .err()
+                .map(|e| format!("{target}: {e}"))
+        })
+        .collect();
+
+    if failures.is_empty() {
+        CheckResult::pass(
+            "compiler cache",
+            format!(
+                "{} at {} works for {} target(s)",
+                wrapper.name,
+                wrapper.path.display(),
+                targets.len()
+            ),
+        )
+    } else {
+        CheckResult::warning(
+            "compiler cache",
+            format!("{} failed for {}", wrapper.name, failures.join("; ")),
+            i18n::tr("doctor.wrapper_targets"),
+        )
+    }
+}
+
+/// Check that libclang is available when the project uses bindgen
+pub fn check_libclang() -> CheckResult {
+    let uses_bindgen = Lockfile::discover()
+        .ok()
+        .flatten()
+        .is_some_and(|lockfile| lockfile.contains("bindgen"));
+
+    match find_libclang() {
+        Some(path) => CheckResult::pass("libclang", format!("Found at {}", path.display())),
+        None if uses_bindgen => CheckResult::fail(
+            "libclang",
+            "Project uses bindgen but libclang was not found",
+            i18n::tr("doctor.install_libclang"),
         ),
+        None => CheckResult::pass("libclang", "Not found (only needed for bindgen)"),
+    }
+}
+
+/// Check build tools needed by `ring`/`aws-lc` for the configured targets
+pub fn check_crypto_tools() -> CheckResult {
+    let Some(lockfile) = Lockfile::discover().ok().flatten() else {
+        return CheckResult::pass("crypto crates", "No Cargo.lock found");
+    };
+    let crates = crypto::detect(&lockfile);
+    if crates.is_empty() {
+        return CheckResult::pass("crypto crates", "Project does not use ring or aws-lc");
+    }
+
+    let config = Config::effective().unwrap_or_default();
+    let targets: Vec<Target> = if config.targets.default.is_empty() {
+        Target::detect_host().into_iter().collect()
+    } else {
+        config
+            .targets
+            .default
+            .iter()
+            .filter_map(|triple| Target::from_triple(triple).ok())
+            .collect()
+    };
+
+    let mut missing: Vec<String> = Vec::new();
+    for target in &targets {
+        for krate in &crates {
+            if *krate != "ring" && !crypto::aws_lc_supported(target) {
+                missing.push(format!(
+                    "{krate} does not build for {} (switch rustls to ring)",
+                    target.triple
+                ));
+                continue;
+            }
+            for tool in crypto::missing_tools(krate, target, &lockfile) {
+                let entry = format!("{} ({krate}, {})", tool.name, target.triple);
+                if !missing.contains(&entry) {
+                    missing.push(entry);
+                }
+            }
+        }
+    }
+
+    if missing.is_empty() {
+        CheckResult::pass(
+            "crypto crates",
+            format!("Build tools found for {}", crates.join(", ")),
+        )
+    } else {
+        CheckResult::warning(
+            "crypto crates",
+            format!("Missing: {}", missing.join("; ")),
+            i18n::tr("doctor.install_crypto_tools"),
+        )
+    }
+}
+
+/// Check built `*-linux-gnu` binaries against `min_glibc`
+pub fn check_glibc() -> CheckResult {
+    let config = Config::effective().unwrap_or_default();
+
+    let mut limits: Vec<(Target, GlibcVersion)> = config
+        .targets
+        .custom
+        .iter()
+        .filter_map(|(triple, target)| {
+            let min = GlibcVersion::parse(target.min_glibc.as_deref()?)?;
+            let target = Target::from_triple(triple).ok()?;
+            glibc::is_glibc_target(&target).then_some((target, min))
+        })
+        .collect();
+    if limits.is_empty() {
+        return CheckResult::pass("glibc", "No min_glibc configured");
+    }
+    limits.sort_by(|a, b| a.0.triple.cmp(&b.0.triple));
+
+    let mut checked = 0;
+    let mut problems = Vec::new();
+    for (target, min) in &limits {
+        for profile in ["release", "debug"] {
+            let Ok(binaries) = artifacts::built_binaries(target, profile) else {
+                continue;
+            };
+            checked += binaries.len();
+            for violation in glibc::check(&binaries, *min).unwrap_or_default() {
+                problems.push(format!(
+                    "{} needs glibc {} (> {min})",
+                    violation.path.display(),
+                    violation.required
+                ));
+            }
+        }
+    }
+
+    if !problems.is_empty() {
+        let (target, min) = &limits[0];
+        return CheckResult::warning(
+            "glibc",
+            problems.join("; "),
+            glibc::alternatives(target, *min).join("; "),
+        );
+    }
+    if checked == 0 {
+        CheckResult::pass("glibc", "min_glibc configured, no binaries built yet")
+    } else {
+        CheckResult::pass(
+            "glibc",
+            format!("{checked} binary(ies) within the configured min_glibc"),
+        )
+    }
+}
+
+/// Check Visual Studio / Build Tools components for MSVC targets (Windows hosts)
+pub fn check_msvc() -> CheckResult {
+    if msvc::find_vswhere().is_none() {
+        return CheckResult::warning(
+            "MSVC",
+            "vswhere not found, Visual Studio is probably not installed",
+            i18n::tr("doctor.install_msvc"),
+        );
+    }
+
+    let install = match msvc::find_installation(&[msvc::COMPONENT_VC_TOOLS]) {
+        Ok(Some(install)) => install,
+        Ok(None) => {
+            return CheckResult::fail(
+                "MSVC",
+                "No Visual Studio installation with the x64/x86 C++ build tools",
+                i18n::tr_args(
+                    "doctor.add_msvc_component",
+                    &[("component", msvc::COMPONENT_VC_TOOLS)],
+                ),
+            )
+        }
+        Err(e) => {
+            return CheckResult::fail(
+                "MSVC",
+                format!("vswhere failed: {e}"),
+                i18n::tr("doctor.reinstall_vs_installer"),
+            )
+        }
+    };
+
+    let message = format!("{} {}", install.display_name, install.installation_version);
+    let mut missing = Vec::new();
+    if !matches!(
+        msvc::find_installation(&[msvc::COMPONENT_VC_TOOLS, msvc::COMPONENT_VC_TOOLS_ARM64]),
+        Ok(Some(_))
+    ) {
+        missing.push("ARM64 build tools (for aarch64-pc-windows-msvc)");
+    }
+    if msvc::find_windows_sdk().is_none() {
+        missing.push("Windows SDK");
+    }
+
+    let result = if missing.is_empty() {
+        CheckResult::pass("MSVC", message)
+    } else {
+        CheckResult::warning(
+            "MSVC",
+            format!("{message}; missing: {}", missing.join(", ")),
+            i18n::tr("doctor.add_msvc_components"),
+        )
+    };
+    result.with_version(install.installation_version)
+}
+
+/// Check the message catalog selected with `XCARGO_LANG`
+pub fn check_locale() -> CheckResult {
+    let Ok(lang) = std::env::var(i18n::LANG_VAR) else {
+        return CheckResult::pass("language", "English (set XCARGO_LANG to change)");
+    };
+    let locale = i18n::locale_candidates(&lang)
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| lang.clone());
+
+    match i18n::find(&lang) {
+        Ok(Some((catalog, source))) => {
+            let english = Catalog::english();
+            let missing = catalog.missing_keys(&english).len();
+            if missing == 0 {
+                CheckResult::pass("language", format!("{} ({source})", catalog.locale()))
+            } else {
+                CheckResult::warning(
+                    "language",
+                    format!(
+                        "{} ({source}): {missing} of {} message(s) untranslated",
+                        catalog.locale(),
+                        english.len()
+                    ),
+                    i18n::tr_args(
+                        "doctor.complete_translation",
+                        &[("locale", catalog.locale())],
+                    ),
+                )
+            }
+        }
+        Ok(None) if locale.starts_with("en") => CheckResult::pass("language", "English"),
         Ok(None) => CheckResult::warning(
+            "language",
+            format!("No message catalog for XCARGO_LANG={lang}, using English"),
+            i18n::tr_args("doctor.add_translation", &[("locale", &locale)]),
+        ),
+        Err(e) => CheckResult::fail(
+            "language",
+            e.to_string(),
+            i18n::tr_args("doctor.fix_translation", &[("locale", &locale)]),
+        ),
+    }
+}
+
+/// Directory levels of `target/` searched for files of another owner
+#[cfg(unix)]
+const OWNERSHIP_DEPTH: usize = 4;
+
+/// Check that container builds haven't left files in `target/` that the
+/// project's owner can't overwrite or remove
+#[cfg(unix)]
+pub fn check_target_ownership() -> CheckResult {
+    use std::os::unix::fs::MetadataExt;
+
+    let root = std::env::current_dir().ok().and_then(|cwd| {
+        cwd.ancestors()
+            .find(|dir| dir.join("Cargo.toml").is_file())
+            .map(Path::to_path_buf)
+    });
+    let Some((root, owner)) =
+        root.and_then(|root| std::fs::metadata(&root).ok().map(|owner| (root, owner)))
+    else {
+        return CheckResult::pass("target ownership", "Not in a Cargo project");
+    };
+    let target =
+        std::env::var_os("CARGO_TARGET_DIR").map_or_else(|| root.join("target"), PathBuf::from);
+
+    match foreign_owned(&target, owner.uid()) {
+        0 => CheckResult::pass(
+            "target ownership",
+            format!("{} belongs to the project's owner", target.display()),
+        ),
+        count => CheckResult::warning(
+            "target ownership",
+            format!(
+                "{count} file(s) in {} belong to another user, usually root from a container build",
+                target.display()
+            ),
+            i18n::tr_args(
+                "doctor.fix_ownership",
+                &[
+                    ("owner", &format!("{}:{}", owner.uid(), owner.gid())),
+                    ("dir", &target.display().to_string()),
+                ],
+            ),
+        ),
+    }
+}
+
+/// Number of entries in the first levels of `dir` not owned by `uid`
+#[cfg(unix)]
+fn foreign_owned(dir: &Path, uid: u32) -> usize {
+    use std::os::unix::fs::MetadataExt;
+
+    walkdir::WalkDir::new(dir)
+        .max_depth(OWNERSHIP_DEPTH)
+        .into_iter()
+        .filter_map(std::result::Result::ok)
+        .filter(|entry| entry.metadata().is_ok_and(|meta| meta.uid() != uid))
+        .count()
+}
+
+/// Check for xcargo configuration file
+pub fn check_config_file() -> CheckResult {
+    match ConfigDiscovery::find_sources() {
+        Ok(sources) if !sources.is_empty() => {
+            match sources
+                .iter()
+                .find_map(|source| Config::load_source(source).err())
+            {
+                None => {
+                    let found: Vec<String> =
+                        sources.iter().rev().map(ToString::to_string).collect();
+                    CheckResult::pass(
+                        "xcargo.toml",
+                        format!("Found configuration at: {}", found.join(", ")),
+                    )
+                }
+                Some(problem) => CheckResult::fail(
+                    "xcargo.toml",
+                    format!("Configuration is broken: {problem}"),
+                    i18n::tr_args("doctor.fix_config", &[("location", &problem.location())]),
+                ),
+            }
+        }
+        Ok(_) => CheckResult::warning(
             "xcargo.toml",
             "No xcargo.toml found in current directory or parents",
-            "Run 'xcargo init' to create a configuration file",
+            i18n::tr("doctor.create_config"),
         ),
         Err(e) => CheckResult::fail(
             "xcargo.toml",
             format!("Error checking configuration: {}", e),
-            "Check file permissions",
+            i18n::tr("doctor.check_permissions"),
         ),
     }
 }
 
+/// Check the global configuration, `~/.xcargo/config.toml`, if there is one
+#[must_use]
+pub fn check_global_config_file() -> Option<CheckResult> {
+    let path = Config::global_path().filter(|path| path.is_file())?;
+    Some(match Config::load(&path) {
+        Ok(_) => CheckResult::pass(
+            "Global config",
+            format!("Found global configuration at: {}", path.display()),
+        ),
+        Err(problem) => CheckResult::fail(
+            "Global config",
+            format!("Global configuration is broken: {problem}"),
+            i18n::tr_args(
+                "doctor.fix_global_config",
+                &[("location", &problem.location())],
+            ),
+        ),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -379,6 +808,12 @@ mod tests {
         assert!(critical.suggestion.is_some());
     }
 
+    #[test]
+    fn test_check_locale() {
+        let result = check_locale();
+        assert_eq!(result.name, "language");
+    }
+
     #[test]
     fn test_check_rustup() {
         let result = check_rustup();
@@ -404,9 +839,36 @@ mod tests {
     fn test_check_zig() {
         let result = check_zig();
         // Zig may or may not be installed
-        assert!(
-            matches!(result.status, CheckStatus::Pass | CheckStatus::Warning)
-        );
+        assert!(matches!(
+            result.status,
+            CheckStatus::Pass | CheckStatus::Warning
+        ));
+    }
+
+    #[test]
+    fn test_check_compiler_wrapper() {
+        let result = check_compiler_wrapper();
+        // sccache may or may not be installed
+        assert_eq!(result.name, "compiler cache");
+    }
+
+    #[test]
+    fn test_check_libclang() {
+        let result = check_libclang();
+        // libclang may or may not be installed
+        assert_eq!(result.name, "libclang");
+    }
+
+    #[test]
+    fn test_check_crypto_tools() {
+        let result = check_crypto_tools();
+        assert_eq!(result.name, "crypto crates");
+    }
+
+    #[test]
+    fn test_check_glibc() {
+        let result = check_glibc();
+        assert_eq!(result.name, "glibc");
     }
 
     #[test]
@@ -415,4 +877,18 @@ mod tests {
         // Config may or may not exist, but check should work
         assert!(!result.name.is_empty());
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_foreign_owned() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("debug/deps")).unwrap();
+        std::fs::write(dir.path().join("debug/deps/app"), "binary").unwrap();
+        let uid = std::fs::metadata(dir.path()).unwrap().uid();
+        assert_eq!(foreign_owned(dir.path(), uid), 0);
+        assert_eq!(foreign_owned(dir.path(), uid + 1), 4);
+        assert_eq!(foreign_owned(&dir.path().join("missing"), uid), 0);
+    }
 }
@@ -4,22 +4,34 @@
 //! diagnose and troubleshoot their cross-compilation setup.
 
 mod checks;
+mod network;
 mod report;
 
 pub use checks::{Check, CheckResult, CheckStatus};
-pub use report::DoctorReport;
+pub use network::{offline_from_env, Proxy};
+pub use report::{DoctorReport, ReportFormat};
 
-use crate::error::Result;
+use crate::config::Config;
+use crate::error::{Error, Result};
 use crate::output::helpers;
+use std::path::Path;
 
-/// Run all diagnostic checks and display the report
-pub fn run() -> Result<()> {
-    helpers::section("xcargo doctor - System Diagnostics");
-    println!("Checking your cross-compilation environment...\n");
+/// Run all diagnostic checks
+///
+/// The report also records the host and build-related environment for
+/// `--json` and `--report-file`. Network checks are offline ones when
+/// `CARGO_NET_OFFLINE` is set.
+#[must_use]
+pub fn collect() -> DoctorReport {
+    collect_with(offline_from_env())
+}
 
-    let mut report = DoctorReport::new();
+/// Run all diagnostic checks, with `offline` judging what builds can do
+/// without the network instead of probing it
+#[must_use]
+pub fn collect_with(offline: bool) -> DoctorReport {
+    let mut report = DoctorReport::new().with_host_info();
 
-    // Run all checks
     report.add_check(checks::check_rustup());
     report.add_check(checks::check_cargo());
     report.add_check(checks::check_default_toolchain());
@@ -27,15 +39,74 @@ pub fn run() -> Result<()> {
     report.add_check(checks::check_zig());
     report.add_check(checks::check_docker());
     report.add_check(checks::check_podman());
+    report.add_check(checks::check_nerdctl());
     report.add_check(checks::check_common_linkers());
+    if cfg!(windows) {
+        report.add_check(checks::check_msvc());
+    }
+    report.add_check(checks::check_compiler_wrapper());
+    report.add_check(checks::check_libclang());
+    report.add_check(checks::check_crypto_tools());
+    report.add_check(checks::check_glibc());
+    report.add_check(checks::check_locale());
     report.add_check(checks::check_config_file());
+    if let Some(check) = checks::check_global_config_file() {
+        report.add_check(check);
+    }
+    #[cfg(unix)]
+    report.add_check(checks::check_target_ownership());
+
+    let config = Config::effective().unwrap_or_default();
+    let network = if offline {
+        network::check_offline(&config)
+    } else {
+        network::check_network(&config)
+    };
+    for check in network {
+        report.add_check(check);
+    }
+    report
+}
+
+/// Run all diagnostic checks and display the report
+pub fn run() -> Result<()> {
+    run_with(false, None, offline_from_env())
+}
+
+/// Run all diagnostic checks, printing the report as JSON with `json`
+/// instead of displaying it, and also writing it to `report_file` (YAML
+/// for `.yaml`/`.yml`, JSON otherwise)
+///
+/// With `offline`, nothing is probed over the network; the report says
+/// which build strategies work with what is already on disk.
+///
+/// Fails if a critical check failed, so CI can gate on readiness.
+pub fn run_with(json: bool, report_file: Option<&Path>, offline: bool) -> Result<()> {
+    if !json {
+        helpers::section("xcargo doctor - System Diagnostics");
+        println!("Checking your cross-compilation environment...\n");
+    }
 
-    // Display the report
-    report.display();
+    let report = collect_with(offline);
+
+    if json {
+        println!("{}", report.to_format(ReportFormat::Json)?);
+    } else {
+        report.display();
+    }
+
+    if let Some(path) = report_file {
+        std::fs::write(path, report.to_format(ReportFormat::from_path(path))?).map_err(|e| {
+            Error::Config(format!("Cannot write report to {}: {e}", path.display()))
+        })?;
+        if !json {
+            helpers::info(format!("Report written to {}", path.display()));
+        }
+    }
 
     // Return success/failure based on critical checks
     if report.has_critical_failures() {
-        Err(crate::error::Error::Config(
+        Err(Error::Config(
             "Critical system checks failed. See diagnostics above.".to_string(),
         ))
     } else {
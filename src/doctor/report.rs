@@ -1,21 +1,113 @@
 //! Doctor report formatting and display
 
 use super::{CheckResult, CheckStatus};
+use crate::error::{Error, Result};
+use crate::output;
+use crate::target::Target;
+use crate::trace;
 use colored::Colorize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Environment variables that affect builds, by prefix, listed in reports
+const ENV_PREFIXES: &[&str] = &[
+    "CARGO",
+    "RUST",
+    "XCARGO_",
+    "CC",
+    "CXX",
+    "AR",
+    "CFLAGS",
+    "CXXFLAGS",
+    "LDFLAGS",
+    "PKG_CONFIG",
+    "ZIG",
+    "DOCKER_",
+    "CONTAINER_",
+    "SCCACHE_",
+    "CI",
+];
+
+/// Machine-readable report format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// Pretty-printed JSON
+    Json,
+    /// YAML
+    Yaml,
+}
+
+impl ReportFormat {
+    /// Format for a report file, by extension (JSON unless `.yaml`/`.yml`)
+    #[must_use]
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => Self::Yaml,
+            _ => Self::Json,
+        }
+    }
+}
 
 /// Doctor diagnostic report
 #[derive(Debug, Default)]
 pub struct DoctorReport {
+    /// Host target triple, if rustc could report it
+    host: Option<String>,
+    /// Build-related environment variables, secrets redacted
+    environment: BTreeMap<String, String>,
     /// All check results
     checks: Vec<CheckResult>,
 }
 
+/// Serialized form of a [`DoctorReport`]
+#[derive(Serialize)]
+struct Serialized<'a> {
+    xcargo_version: &'static str,
+    host: Option<&'a str>,
+    os: &'static str,
+    arch: &'static str,
+    ready: bool,
+    summary: ReportSummary,
+    checks: &'a [CheckResult],
+    environment: &'a BTreeMap<String, String>,
+}
+
 impl DoctorReport {
     /// Create a new empty report
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Record the host triple and build-related environment variables
+    /// (secrets redacted) for machine-readable reports
+    #[must_use]
+    pub fn with_host_info(mut self) -> Self {
+        self.host = Target::detect_host().ok().map(|host| host.triple);
+        self.environment = build_environment(std::env::vars());
+        self
+    }
+
+    /// Serialize the report, with its summary, as JSON or YAML
+    pub fn to_format(&self, format: ReportFormat) -> Result<String> {
+        let report = Serialized {
+            xcargo_version: env!("CARGO_PKG_VERSION"),
+            host: self.host.as_deref(),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            ready: !self.has_critical_failures(),
+            summary: self.summary(),
+            checks: &self.checks,
+            environment: &self.environment,
+        };
+        match format {
+            ReportFormat::Json => serde_json::to_string_pretty(&report)
+                .map_err(|e| Error::Config(format!("Cannot serialize doctor report: {e}"))),
+            ReportFormat::Yaml => serde_yaml::to_string(&report)
+                .map_err(|e| Error::Config(format!("Cannot serialize doctor report: {e}"))),
+        }
+    }
+
     /// Add a check result to the report
     pub fn add_check(&mut self, check: CheckResult) {
         self.checks.push(check);
@@ -67,6 +159,16 @@ impl DoctorReport {
                 CheckStatus::Critical => ("✗", "CRIT", |s| s.bright_red().bold()),
             };
 
+        if output::is_plain() {
+            println!("{status_text}: {}", check.name);
+            println!("  {}", check.message);
+            if let Some(suggestion) = &check.suggestion {
+                println!("  Suggestion: {suggestion}");
+            }
+            println!();
+            return;
+        }
+
         println!(
             "{} {} {}",
             icon,
@@ -84,6 +186,10 @@ impl DoctorReport {
 
     fn display_summary(&self) {
         let summary = self.summary();
+        if output::is_plain() {
+            Self::display_plain_summary(&summary);
+            return;
+        }
 
         println!("{}", "=".repeat(60).dimmed());
         println!("{}", "Summary".bold());
@@ -155,10 +261,39 @@ impl DoctorReport {
             );
         }
     }
+
+    /// Summary in words only, for screen readers
+    fn display_plain_summary(summary: &ReportSummary) {
+        println!("Summary");
+        println!("  Total checks: {}", summary.total);
+        println!("  Passed: {}", summary.passed);
+        println!("  Warnings: {}", summary.warnings);
+        println!("  Failed: {}", summary.failed);
+        println!("  Critical: {}", summary.critical);
+        println!();
+
+        let overall = if summary.critical > 0 {
+            "FAIL: Critical issues found. xcargo may not function correctly. Please address the issues above."
+        } else if summary.failed > 0 {
+            "FAIL: Some checks failed. Some features may not work. Review the issues above."
+        } else if summary.warnings > 0 {
+            "PASS: System is functional. Some optional features unavailable."
+        } else {
+            "PASS: All checks passed. Your system is ready for cross-compilation."
+        };
+        println!("{overall}");
+    }
+}
+
+/// Build-related variables among `vars`, secrets redacted
+fn build_environment(vars: impl Iterator<Item = (String, String)>) -> BTreeMap<String, String> {
+    trace::redact_env(
+        vars.filter(|(key, _)| ENV_PREFIXES.iter().any(|prefix| key.starts_with(prefix))),
+    )
 }
 
 /// Summary statistics for the report
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct ReportSummary {
     /// Total number of checks
     pub total: usize,
@@ -221,6 +356,60 @@ mod tests {
         assert_eq!(summary.critical, 1);
     }
 
+    #[test]
+    fn test_report_to_format() {
+        let mut report = DoctorReport::new();
+        report.host = Some("x86_64-unknown-linux-gnu".to_string());
+        report.add_check(CheckResult::pass("cargo", "Found").with_version("cargo 1.80.0"));
+        report.add_check(CheckResult::critical(
+            "rustup",
+            "rustup not found",
+            "Install it",
+        ));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&report.to_format(ReportFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["xcargo_version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(json["host"], "x86_64-unknown-linux-gnu");
+        assert_eq!(json["ready"], false);
+        assert_eq!(json["summary"]["critical"], 1);
+        assert_eq!(json["checks"][0]["status"], "pass");
+        assert_eq!(json["checks"][0]["version"], "cargo 1.80.0");
+        assert!(json["checks"][0].get("suggestion").is_none());
+        assert_eq!(json["checks"][1]["suggestion"], "Install it");
+
+        let yaml = report.to_format(ReportFormat::Yaml).unwrap();
+        assert!(yaml.contains("status: critical"));
+        assert_eq!(
+            ReportFormat::from_path(Path::new("doctor.yml")),
+            ReportFormat::Yaml
+        );
+        assert_eq!(
+            ReportFormat::from_path(Path::new("doctor.json")),
+            ReportFormat::Json
+        );
+    }
+
+    #[test]
+    fn test_build_environment() {
+        let env = build_environment(
+            [
+                ("HOME".to_string(), "/home/me".to_string()),
+                ("RUSTFLAGS".to_string(), "-Dwarnings".to_string()),
+                ("CARGO_REGISTRY_TOKEN".to_string(), "cio_abc".to_string()),
+                (
+                    "CC_aarch64_unknown_linux_gnu".to_string(),
+                    "clang".to_string(),
+                ),
+            ]
+            .into_iter(),
+        );
+        assert!(!env.contains_key("HOME"));
+        assert_eq!(env["RUSTFLAGS"], "-Dwarnings");
+        assert_eq!(env["CARGO_REGISTRY_TOKEN"], trace::REDACTED);
+        assert_eq!(env["CC_aarch64_unknown_linux_gnu"], "clang");
+    }
+
     #[test]
     fn test_report_display() {
         let mut report = DoctorReport::new();
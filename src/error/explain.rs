@@ -0,0 +1,371 @@
+//! Explanations for common cross-compilation failures
+//!
+//! Linker and build script errors rarely say what to do about them when
+//! cross-compiling. Each [`Explanation`] has a code (`XC001`, ...), the
+//! output it is recognized by and xcargo-specific fixes. A failed build
+//! prints the explanations matching cargo's output, and
+//! `xcargo explain <code>` shows one in full.
+//!
+//! Patterns are matched against single lines of output; `*` stands for any
+//! text. The first explanation with a matching pattern claims a line, so
+//! specific explanations come before general ones.
+
+use crate::output::{helpers, strip_ansi};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// A recognized failure and how to fix it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    /// Code for `xcargo explain`
+    pub code: &'static str,
+    /// Short description of the failure
+    pub title: &'static str,
+    /// Output lines it is recognized by
+    pub patterns: &'static [&'static str],
+    /// Why it usually happens
+    pub cause: &'static str,
+    /// What to do, with `{target}` standing for the target triple
+    pub fixes: &'static [&'static str],
+}
+
+/// Every known failure, most specific first
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "XC001",
+        title: "OpenSSL is not available for the target",
+        patterns: &[
+            "cannot find -lssl",
+            "cannot find -lcrypto",
+            "library not found for -lssl",
+            "library not found for -lcrypto",
+            "Could not find directory of OpenSSL installation",
+            "could not find system library 'openssl'",
+        ],
+        cause: "openssl-sys links the system OpenSSL, which is usually only installed for the host.",
+        fixes: &[
+            "Enable the `vendored` feature of the `openssl` crate to build OpenSSL from source for {target}",
+            "Or switch to rustls, e.g. through a `rustls-tls` feature of the crate that needs TLS",
+            "Run `xcargo build --target {target} --explain` to see how native dependencies are resolved",
+        ],
+    },
+    Explanation {
+        code: "XC002",
+        title: "MinGW-w64 is not installed",
+        patterns: &[
+            "linker `*-w64-mingw32-gcc` not found",
+            "*-w64-mingw32-gcc: not found",
+            "*-w64-mingw32-gcc: command not found",
+            "error calling dlltool",
+            "dlltool*not found",
+        ],
+        cause: "Windows GNU targets link with the MinGW-w64 cross toolchain.",
+        fixes: &[
+            "Install MinGW-w64: `sudo apt install mingw-w64` on Debian/Ubuntu, `brew install mingw-w64` on macOS",
+            "Or build in a container with `xcargo build --target {target} --container`",
+        ],
+    },
+    Explanation {
+        code: "XC003",
+        title: "The linker for the target is not installed",
+        patterns: &["linker `*` not found"],
+        cause: "rustc calls the linker configured for the target, and it is not on PATH.",
+        fixes: &[
+            "Use Zig as the linker with `xcargo build --target {target} --zig` (Linux and macOS targets)",
+            "Or install a cross linker and set it with `[targets.\"{target}\"] linker = \"...\"` in xcargo.toml",
+            "Run `xcargo doctor` to see which linkers are missing",
+        ],
+    },
+    Explanation {
+        code: "XC004",
+        title: "A C compiler for the target is not installed",
+        patterns: &["failed to find tool. Is `*` installed?"],
+        cause: "A build script compiles C code with the cc crate, which looks for `<triple>-gcc` or `CC_<triple>`.",
+        fixes: &[
+            "Use `xcargo build --target {target} --zig`, which also provides a C compiler for the target",
+            "Or install a cross GCC (e.g., `sudo apt install gcc-aarch64-linux-gnu`) and set `CC_{target}` in `[targets.\"{target}\".env]`",
+        ],
+    },
+    Explanation {
+        code: "XC005",
+        title: "A native library is not available for the target",
+        patterns: &[
+            "cannot find -l*",
+            "library not found for -l*",
+            "unable to find library -l*",
+            "could not find system library '*'",
+        ],
+        cause: "A `-sys` crate links a system library, which is usually only installed for the host.",
+        fixes: &[
+            "Enable the crate's `vendored`/`bundled` feature, if it has one, to build the library from source",
+            "Or install the library built for {target} (e.g., the Debian `:arm64` package) and point the crate at it in `[targets.\"{target}\".env]`",
+            "Or build in a container whose image has the library: `xcargo build --target {target} --container`",
+        ],
+    },
+    Explanation {
+        code: "XC006",
+        title: "The CPU is unknown for the target",
+        patterns: &[
+            "unknown target CPU",
+            "is not a recognized processor for this target",
+        ],
+        cause: "`-C target-cpu` names a CPU of another architecture, often `native` or a host CPU set in `[build] rustflags` or RUSTFLAGS for every target.",
+        fixes: &[
+            "Move `-C target-cpu=...` to `[target.<host-triple>] rustflags` in .cargo/config.toml",
+            "Or unset RUSTFLAGS when cross-compiling to {target}",
+        ],
+    },
+    Explanation {
+        code: "XC007",
+        title: "The Rust standard library is not installed for the target",
+        patterns: &[
+            "can't find crate for `std`",
+            "can't find crate for `core`",
+        ],
+        cause: "The target's standard library comes from rustup and has not been installed.",
+        fixes: &[
+            "Install it with `xcargo target add {target}`",
+            "Targets without a prebuilt standard library need nightly and `-Zbuild-std`",
+        ],
+    },
+    Explanation {
+        code: "XC008",
+        title: "Object files were built for another architecture",
+        patterns: &[
+            "file in wrong format",
+            "file format not recognized",
+            "is incompatible with *output",
+            "building for * but attempting to link with file built for",
+        ],
+        cause: "C code was compiled by the host compiler, or stale objects from another target were reused.",
+        fixes: &[
+            "Make build scripts use the target's compiler with `--zig` or `CC_{target}` in `[targets.\"{target}\".env]`",
+            "Remove stale output with `xcargo clean --target {target}` and build again",
+        ],
+    },
+    Explanation {
+        code: "XC009",
+        title: "Symbols are missing at link time",
+        patterns: &[
+            "ld: symbol(s) not found",
+            "Undefined symbols for architecture",
+            "undefined reference to `",
+            "unresolved external symbol",
+        ],
+        cause: "A library the code calls into was not linked for the target, or was built without the symbols the target needs.",
+        fixes: &[
+            "Check that the `-sys` crates and build scripts link every library the target needs (e.g., frameworks on macOS, `-lws2_32` on Windows)",
+            "Use `xcargo build --target {target} --zig` so C code and Rust code are built for the same target",
+            "Run with --verbose to see the full linker command",
+        ],
+    },
+    Explanation {
+        code: "XC010",
+        title: "The binary needs a newer glibc",
+        patterns: &["version `GLIBC_*' not found"],
+        cause: "The binary was linked against the build machine's glibc, which is newer than the one where it runs.",
+        fixes: &[
+            "Set `[targets.\"{target}\"] min_glibc = \"2.17\"` and build with `--zig` to link against an older glibc",
+            "Or build for the musl target, whose binaries do not depend on glibc",
+        ],
+    },
+];
+
+impl Explanation {
+    /// The explanation with `code`, ignoring case
+    #[must_use]
+    pub fn find(code: &str) -> Option<&'static Explanation> {
+        EXPLANATIONS
+            .iter()
+            .find(|explanation| explanation.code.eq_ignore_ascii_case(code.trim()))
+    }
+
+    /// The first explanation one of whose patterns matches `line`
+    #[must_use]
+    pub fn for_line(line: &str) -> Option<&'static Explanation> {
+        let line = strip_ansi(line);
+        EXPLANATIONS.iter().find(|explanation| {
+            explanation
+                .patterns
+                .iter()
+                .any(|pattern| matches(pattern, &line))
+        })
+    }
+
+    /// Fixes for `target`, or with `<target>` when there is none
+    #[must_use]
+    pub fn fixes_for(&self, target: Option<&str>) -> Vec<String> {
+        let target = target.unwrap_or("<target>");
+        self.fixes
+            .iter()
+            .map(|fix| fix.replace("{target}", target))
+            .collect()
+    }
+
+    /// Print the title and fixes after a failed build of `target`
+    pub fn print_short(&self, target: &str) {
+        println!();
+        helpers::hint(format!("{} ({})", self.title, self.code));
+        for fix in self.fixes_for(Some(target)) {
+            helpers::tip(fix);
+        }
+    }
+
+    /// Print everything about this failure, for `xcargo explain`
+    pub fn print(&self, target: Option<&str>) {
+        helpers::section(format!("{}: {}", self.code, self.title));
+        println!("{}", self.cause);
+        println!();
+        println!("Recognized by output such as:");
+        for pattern in self.patterns {
+            println!("  {} {pattern}", helpers::bullet());
+        }
+        println!();
+        for fix in self.fixes_for(target) {
+            helpers::tip(fix);
+        }
+    }
+}
+
+/// Explanations for the lines of `text`, each once, in order of appearance
+#[must_use]
+pub fn explain_output(text: &str) -> Vec<&'static Explanation> {
+    let mut found: Vec<&'static Explanation> = Vec::new();
+    for explanation in text.lines().filter_map(Explanation::for_line) {
+        if !found.contains(&explanation) {
+            found.push(explanation);
+        }
+    }
+    found
+}
+
+/// Explanations for the output of a running build, collected line by line
+///
+/// Clones share what was collected.
+#[derive(Debug, Clone, Default)]
+pub struct Matcher {
+    found: Arc<Mutex<Vec<&'static Explanation>>>,
+}
+
+impl Matcher {
+    /// A matcher that has seen nothing
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Look for known failures in one line of output
+    ///
+    /// A line may hold several lines, e.g. a rendered compiler message.
+    pub fn accept(&self, line: &str) {
+        for explanation in explain_output(line) {
+            let mut found = self.found.lock().unwrap_or_else(PoisonError::into_inner);
+            if !found.contains(&explanation) {
+                found.push(explanation);
+            }
+        }
+    }
+
+    /// Explanations for what was seen, in order of appearance
+    #[must_use]
+    pub fn explanations(&self) -> Vec<&'static Explanation> {
+        self.found
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+/// Whether `line` contains `pattern`, where `*` matches any text
+fn matches(pattern: &str, line: &str) -> bool {
+    let mut rest = line;
+    let mut parts = pattern.split('*').filter(|part| !part.is_empty());
+    let Some(first) = parts.next() else {
+        return true;
+    };
+    let Some(start) = rest.find(first) else {
+        return false;
+    };
+    rest = &rest[start + first.len()..];
+    for part in parts {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_codes_are_unique() {
+        let codes: HashSet<&str> = EXPLANATIONS.iter().map(|e| e.code).collect();
+        assert_eq!(codes.len(), EXPLANATIONS.len());
+        assert_eq!(Explanation::find("xc003").unwrap().code, "XC003");
+        assert!(Explanation::find("XC999").is_none());
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("cannot find -l*", "  = note: ld: cannot find -lz"));
+        assert!(matches(
+            "linker `*` not found",
+            "error: linker `cc` not found"
+        ));
+        assert!(!matches(
+            "linker `*` not found",
+            "error: linker `cc` failed"
+        ));
+        assert!(!matches("version `GLIBC_*' not found", "GLIBC_2.34"));
+    }
+
+    #[test]
+    fn test_specific_explanations_win() {
+        let code = |line: &str| Explanation::for_line(line).map(|e| e.code);
+        assert_eq!(
+            code("/usr/bin/ld: cannot find -lssl: No such file or directory"),
+            Some("XC001")
+        );
+        assert_eq!(code("/usr/bin/ld: cannot find -lsqlite3"), Some("XC005"));
+        assert_eq!(
+            code("\x1b[1merror\x1b[0m: linker `x86_64-w64-mingw32-gcc` not found"),
+            Some("XC002")
+        );
+        assert_eq!(
+            code("error: linker `aarch64-linux-gnu-gcc` not found"),
+            Some("XC003")
+        );
+        assert_eq!(
+            code(
+                "'+neoverse-n1' is not a recognized processor for this target (ignoring processor)"
+            ),
+            Some("XC006")
+        );
+        assert_eq!(
+            code("          ld: symbol(s) not found for architecture arm64"),
+            Some("XC009")
+        );
+        assert_eq!(code("Compiling app v0.1.0"), None);
+    }
+
+    #[test]
+    fn test_matcher_collects_each_once() {
+        let matcher = Matcher::new();
+        matcher.accept("error: linking with `cc` failed: exit status: 1\n  = note: /usr/bin/ld: cannot find -lssl\n  /usr/bin/ld: cannot find -lcrypto");
+        matcher.accept("error[E0463]: can't find crate for `std`");
+        matcher.accept("warning: unused variable");
+        let codes: Vec<&str> = matcher.explanations().iter().map(|e| e.code).collect();
+        assert_eq!(codes, ["XC001", "XC007"]);
+
+        let fixes = Explanation::find("XC007")
+            .unwrap()
+            .fixes_for(Some("riscv64gc-unknown-linux-gnu"));
+        assert_eq!(
+            fixes[0],
+            "Install it with `xcargo target add riscv64gc-unknown-linux-gnu`"
+        );
+    }
+}
@@ -1,5 +1,25 @@
 //! Error definitions with structured error codes and suggestions
+//!
+//! Every [`Error`] has a stable [`ErrorCode`], printed with the error
+//! (`[X0101] Linker 'x86_64-w64-mingw32-gcc' not found ...`), included in
+//! `--log-file` JSON logs and daemon error responses, and mapped to the
+//! process [`ExitCode`]. The first two digits give the category:
+//!
+//! | Codes | Category | Exit code |
+//! |-------|----------|-----------|
+//! | `X00xx` | Configuration | 2 |
+//! | `X01xx` | Toolchain and linker | 4 |
+//! | `X02xx` | Container runtime | 6 |
+//! | `X03xx` | Target | 3 |
+//! | `X04xx` | Build | 5 |
+//! | `X05xx` | I/O | 7 |
+//! | `X06xx` | Cancelled by the user | 130 |
+//! | `X07xx` | Plugin | 5 |
+//!
+//! Codes are never reused for a different failure, so scripts can match on
+//! them across releases.
 
+pub mod explain;
 mod suggestions;
 
 use thiserror::Error;
@@ -30,22 +50,139 @@ pub enum ExitCode {
 
 impl From<&Error> for ExitCode {
     fn from(error: &Error) -> Self {
-        match error {
-            Error::Io(_) => ExitCode::IoError,
-            Error::Prompt(_) => ExitCode::UserCancelled,
-            Error::TargetNotFound(_) | Error::InvalidTarget { .. } => ExitCode::TargetError,
-            Error::Toolchain(_)
-            | Error::ToolchainMissing { .. }
-            | Error::LinkerMissing { .. } => ExitCode::ToolchainError,
-            Error::Build(_) | Error::BuildFailed { .. } => ExitCode::BuildError,
-            Error::Config(_) | Error::ConfigParse { .. } => ExitCode::ConfigError,
-            Error::Container(_) | Error::ContainerNotAvailable { .. } => {
-                ExitCode::ContainerError
-            }
+        error.code().exit_code()
+    }
+}
+
+/// Stable code identifying the kind of an [`Error`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ErrorCode {
+    /// X0001: configuration file could not be parsed
+    ConfigParse,
+    /// X0002: configuration is invalid or missing
+    Config,
+    /// X0101: linker for the target not found
+    LinkerMissing,
+    /// X0102: Rust toolchain not installed
+    ToolchainMissing,
+    /// X0103: toolchain could not be used
+    Toolchain,
+    /// X0104: cargo too old for a feature in use
+    CargoTooOld,
+    /// X0201: no container runtime available
+    ContainerNotAvailable,
+    /// X0202: container build or runtime failure
+    Container,
+    /// X0301: target not found
+    TargetNotFound,
+    /// X0302: invalid target triple
+    InvalidTarget,
+    /// X0401: build failed
+    Build,
+    /// X0402: cargo failed for a target
+    BuildFailed,
+    /// X0403: build ran past its timeout
+    TimedOut,
+    /// X0501: file system or process I/O failure
+    Io,
+    /// X0601: interactive prompt failed or was aborted
+    Prompt,
+    /// X0602: build cancelled (e.g., by Ctrl-C)
+    Cancelled,
+    /// X0701: plugin failed to compile, load or run a hook
+    Plugin,
+}
+
+impl ErrorCode {
+    /// Every code, in order
+    pub const ALL: [ErrorCode; 17] = [
+        ErrorCode::ConfigParse,
+        ErrorCode::Config,
+        ErrorCode::LinkerMissing,
+        ErrorCode::ToolchainMissing,
+        ErrorCode::Toolchain,
+        ErrorCode::CargoTooOld,
+        ErrorCode::ContainerNotAvailable,
+        ErrorCode::Container,
+        ErrorCode::TargetNotFound,
+        ErrorCode::InvalidTarget,
+        ErrorCode::Build,
+        ErrorCode::BuildFailed,
+        ErrorCode::TimedOut,
+        ErrorCode::Io,
+        ErrorCode::Prompt,
+        ErrorCode::Cancelled,
+        ErrorCode::Plugin,
+    ];
+
+    /// The code as printed, e.g. "X0101"
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::ConfigParse => "X0001",
+            ErrorCode::Config => "X0002",
+            ErrorCode::LinkerMissing => "X0101",
+            ErrorCode::ToolchainMissing => "X0102",
+            ErrorCode::Toolchain => "X0103",
+            ErrorCode::CargoTooOld => "X0104",
+            ErrorCode::ContainerNotAvailable => "X0201",
+            ErrorCode::Container => "X0202",
+            ErrorCode::TargetNotFound => "X0301",
+            ErrorCode::InvalidTarget => "X0302",
+            ErrorCode::Build => "X0401",
+            ErrorCode::BuildFailed => "X0402",
+            ErrorCode::TimedOut => "X0403",
+            ErrorCode::Io => "X0501",
+            ErrorCode::Prompt => "X0601",
+            ErrorCode::Cancelled => "X0602",
+            ErrorCode::Plugin => "X0701",
+        }
+    }
+
+    /// Look up a printed code (case-insensitive)
+    #[must_use]
+    pub fn parse(code: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|known| known.as_str().eq_ignore_ascii_case(code.trim()))
+    }
+
+    /// Process exit code for errors with this code
+    #[must_use]
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            ErrorCode::ConfigParse | ErrorCode::Config => ExitCode::ConfigError,
+            ErrorCode::LinkerMissing
+            | ErrorCode::ToolchainMissing
+            | ErrorCode::Toolchain
+            | ErrorCode::CargoTooOld => ExitCode::ToolchainError,
+            ErrorCode::ContainerNotAvailable | ErrorCode::Container => ExitCode::ContainerError,
+            ErrorCode::TargetNotFound | ErrorCode::InvalidTarget => ExitCode::TargetError,
+            ErrorCode::Build
+            | ErrorCode::BuildFailed
+            | ErrorCode::TimedOut
+            | ErrorCode::Plugin => ExitCode::BuildError,
+            ErrorCode::Io => ExitCode::IoError,
+            ErrorCode::Prompt | ErrorCode::Cancelled => ExitCode::UserCancelled,
         }
     }
 }
 
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl serde::Serialize for ErrorCode {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
 /// Main error type for xcargo
 #[derive(Error, Debug)]
 pub enum Error {
@@ -94,6 +231,19 @@ pub enum Error {
         install_hint: String,
     },
 
+    /// Active cargo is older than a feature in use requires
+    #[error("{feature} requires cargo >= {required} (found {found})")]
+    CargoTooOld {
+        /// Feature that needs the newer cargo
+        feature: String,
+        /// Minimum cargo version
+        required: String,
+        /// Detected cargo version
+        found: String,
+        /// Upgrade command hint
+        upgrade_hint: String,
+    },
+
     /// Build error (simple)
     #[error("Build failed: {0}")]
     Build(String),
@@ -109,6 +259,14 @@ pub enum Error {
         suggestion: Option<String>,
     },
 
+    /// A child process ran past its timeout and was stopped
+    #[error("Timed out after {0}")]
+    TimedOut(String),
+
+    /// Cancelled (e.g., by Ctrl-C) before finishing
+    #[error("Build cancelled")]
+    Cancelled,
+
     /// Configuration error (simple)
     #[error("Configuration error: {0}")]
     Config(String),
@@ -136,9 +294,42 @@ pub enum Error {
         /// Install hint
         install_hint: String,
     },
+
+    /// A plugin failed to compile, load or run a hook
+    #[error("Plugin '{plugin}' failed: {message}")]
+    Plugin {
+        /// Plugin name
+        plugin: String,
+        /// What went wrong
+        message: String,
+    },
 }
 
 impl Error {
+    /// Stable code for the kind of this error
+    #[must_use]
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::Io(_) => ErrorCode::Io,
+            Error::Prompt(_) => ErrorCode::Prompt,
+            Error::TargetNotFound(_) => ErrorCode::TargetNotFound,
+            Error::InvalidTarget { .. } => ErrorCode::InvalidTarget,
+            Error::Toolchain(_) => ErrorCode::Toolchain,
+            Error::ToolchainMissing { .. } => ErrorCode::ToolchainMissing,
+            Error::LinkerMissing { .. } => ErrorCode::LinkerMissing,
+            Error::CargoTooOld { .. } => ErrorCode::CargoTooOld,
+            Error::Build(_) => ErrorCode::Build,
+            Error::BuildFailed { .. } => ErrorCode::BuildFailed,
+            Error::TimedOut(_) => ErrorCode::TimedOut,
+            Error::Cancelled => ErrorCode::Cancelled,
+            Error::Config(_) => ErrorCode::Config,
+            Error::ConfigParse { .. } => ErrorCode::ConfigParse,
+            Error::Container(_) => ErrorCode::Container,
+            Error::ContainerNotAvailable { .. } => ErrorCode::ContainerNotAvailable,
+            Error::Plugin { .. } => ErrorCode::Plugin,
+        }
+    }
+
     /// Get the exit code for this error
     #[must_use]
     pub fn exit_code(&self) -> i32 {
@@ -171,9 +362,62 @@ mod tests {
         assert_eq!(err.exit_code(), ExitCode::BuildError as i32);
     }
 
+    #[test]
+    fn test_exit_code_cancelled() {
+        assert_eq!(Error::Cancelled.exit_code(), ExitCode::UserCancelled as i32);
+        let err = Error::TimedOut("15m 00s".to_string());
+        assert_eq!(err.exit_code(), ExitCode::BuildError as i32);
+    }
+
     #[test]
     fn test_exit_code_config_error() {
         let err = Error::Config("bad config".to_string());
         assert_eq!(err.exit_code(), ExitCode::ConfigError as i32);
     }
+
+    #[test]
+    fn test_error_codes() {
+        let err = Error::LinkerMissing {
+            linker: "x86_64-w64-mingw32-gcc".to_string(),
+            target: "x86_64-pc-windows-gnu".to_string(),
+            install_hint: String::new(),
+        };
+        assert_eq!(err.code().to_string(), "X0101");
+        assert_eq!(err.exit_code(), ExitCode::ToolchainError as i32);
+
+        let err = Error::ContainerNotAvailable {
+            runtime: "docker".to_string(),
+            install_hint: String::new(),
+        };
+        assert_eq!(err.code().as_str(), "X0201");
+        assert_eq!(err.exit_code(), ExitCode::ContainerError as i32);
+
+        assert_eq!(ErrorCode::parse("x0001"), Some(ErrorCode::ConfigParse));
+        assert_eq!(ErrorCode::parse("X9999"), None);
+        assert_eq!(
+            serde_json::to_string(&ErrorCode::Cancelled).unwrap(),
+            "\"X0602\""
+        );
+    }
+
+    #[test]
+    fn test_error_codes_are_unique_and_match_exit_codes() {
+        let mut seen = std::collections::HashSet::new();
+        for code in ErrorCode::ALL {
+            assert!(seen.insert(code.as_str()), "{code} used twice");
+            assert_eq!(ErrorCode::parse(code.as_str()), Some(code));
+            let category = match &code.as_str()[1..3] {
+                "00" => ExitCode::ConfigError,
+                "01" => ExitCode::ToolchainError,
+                "02" => ExitCode::ContainerError,
+                "03" => ExitCode::TargetError,
+                "04" => ExitCode::BuildError,
+                "05" => ExitCode::IoError,
+                "06" => ExitCode::UserCancelled,
+                "07" => ExitCode::BuildError,
+                other => panic!("unknown category {other}"),
+            };
+            assert_eq!(code.exit_code(), category, "{code}");
+        }
+    }
 }
@@ -1,24 +1,92 @@
 //! Error suggestion and hint generation
 
 use super::Error;
+use crate::i18n::{self, Catalog};
 
 impl Error {
+    /// Error message in the locale selected with `XCARGO_LANG`
+    ///
+    /// Matches the `Display` text for English.
+    #[must_use]
+    pub fn localized(&self) -> String {
+        self.message_in(i18n::catalog())
+    }
+
+    /// Error message from `catalog`
+    #[must_use]
+    pub fn message_in(&self, catalog: &Catalog) -> String {
+        match self {
+            Error::Io(e) => catalog.text("errors.io", &[("error", &e.to_string())]),
+            Error::Prompt(e) => catalog.text("errors.prompt", &[("error", e)]),
+            Error::TargetNotFound(target) => {
+                catalog.text("errors.target_not_found", &[("target", target)])
+            }
+            Error::InvalidTarget { target, .. } => {
+                catalog.text("errors.invalid_target", &[("target", target)])
+            }
+            Error::Toolchain(e) => catalog.text("errors.toolchain", &[("error", e)]),
+            Error::ToolchainMissing { toolchain, .. } => {
+                catalog.text("errors.toolchain_missing", &[("toolchain", toolchain)])
+            }
+            Error::LinkerMissing { linker, target, .. } => catalog.text(
+                "errors.linker_missing",
+                &[("linker", linker), ("target", target)],
+            ),
+            Error::CargoTooOld {
+                feature,
+                required,
+                found,
+                ..
+            } => catalog.text(
+                "errors.cargo_too_old",
+                &[
+                    ("feature", feature),
+                    ("required", required),
+                    ("found", found),
+                ],
+            ),
+            Error::Build(e) => catalog.text("errors.build", &[("error", e)]),
+            Error::BuildFailed { target, .. } => {
+                catalog.text("errors.build_failed", &[("target", target)])
+            }
+            Error::TimedOut(after) => catalog.text("errors.timed_out", &[("after", after)]),
+            Error::Cancelled => catalog.text("errors.cancelled", &[]),
+            Error::Config(e) => catalog.text("errors.config", &[("error", e)]),
+            Error::ConfigParse { .. } => catalog.text("errors.config_parse", &[]),
+            Error::Container(e) => catalog.text("errors.container", &[("error", e)]),
+            Error::ContainerNotAvailable { .. } => {
+                catalog.text("errors.container_not_available", &[])
+            }
+            Error::Plugin { plugin, message } => catalog.text(
+                "errors.plugin",
+                &[("plugin", plugin), ("message", message)],
+            ),
+        }
+    }
+
     /// Get a suggestion for fixing this error
     #[must_use]
     pub fn suggestion(&self) -> Option<String> {
         match self {
             Error::InvalidTarget { suggestions, .. } => {
                 if suggestions.is_empty() {
-                    Some("Run 'xcargo target list' to see available targets".to_string())
+                    Some(i18n::tr("suggestions.list_targets"))
                 } else {
-                    Some(format!("Did you mean: {}?", suggestions.join(", ")))
+                    Some(i18n::tr_args(
+                        "suggestions.did_you_mean",
+                        &[("targets", &suggestions.join(", "))],
+                    ))
                 }
             }
             Error::ToolchainMissing { install_hint, .. } => Some(install_hint.clone()),
             Error::LinkerMissing { install_hint, .. } => Some(install_hint.clone()),
+            Error::CargoTooOld { upgrade_hint, .. } => Some(upgrade_hint.clone()),
             Error::BuildFailed { suggestion, .. } => suggestion.clone(),
             Error::ContainerNotAvailable { install_hint, .. } => Some(install_hint.clone()),
-            Error::ConfigParse { path, .. } => Some(format!("Check {path} for syntax errors")),
+            Error::ConfigParse { path, .. } => Some(i18n::tr_args(
+                "suggestions.check_config_syntax",
+                &[("path", path)],
+            )),
             _ => None,
         }
     }
@@ -28,18 +96,22 @@ impl Error {
     pub fn hint(&self) -> Option<String> {
         match self {
             Error::TargetNotFound(_) | Error::InvalidTarget { .. } => {
-                Some("Use 'xcargo target list' to see available targets".to_string())
+                Some(i18n::tr("hints.list_targets"))
+            }
+            Error::LinkerMissing { target, .. } => {
+                Some(i18n::tr_args("hints.needs_linker", &[("target", target)]))
             }
-            Error::LinkerMissing { target, .. } => Some(format!(
-                "Cross-compiling to {target} requires a compatible linker"
-            )),
             Error::BuildFailed {
                 exit_code: Some(code),
                 ..
-            } => Some(format!("Cargo exited with code {code}")),
-            Error::ContainerNotAvailable { runtime, .. } => {
-                Some(format!("Tried to use {runtime} but it's not running"))
-            }
+            } => Some(i18n::tr_args(
+                "hints.cargo_exit_code",
+                &[("code", &code.to_string())],
+            )),
+            Error::ContainerNotAvailable { runtime, .. } => Some(i18n::tr_args(
+                "hints.runtime_not_running",
+                &[("runtime", runtime)],
+            )),
             _ => None,
         }
     }
@@ -63,7 +135,7 @@ impl Error {
             ("windows", t) if t.contains("linux") => {
                 "Consider using Zig: scoop install zig && xcargo build --zig".to_string()
             }
-            _ => format!("Install a linker that supports {target}"),
+            _ => i18n::tr_args("suggestions.install_linker", &[("target", target)]),
         };
 
         Error::LinkerMissing {
@@ -92,7 +164,10 @@ impl Error {
                  Or Podman: winget install RedHat.Podman"
                     .to_string()
             }
-            _ => format!("Install {runtime} or a compatible container runtime"),
+            _ => i18n::tr_args(
+                "suggestions.install_container_runtime",
+                &[("runtime", runtime)],
+            ),
         };
 
         Error::ContainerNotAvailable {
@@ -139,6 +214,49 @@ mod tests {
         assert!(suggestion.unwrap().contains("Did you mean"));
     }
 
+    #[test]
+    fn test_english_messages_match_display() {
+        let english = Catalog::english();
+        let errors = [
+            Error::Io(std::io::Error::new(std::io::ErrorKind::NotFound, "gone")),
+            Error::Prompt("cancelled".to_string()),
+            Error::TargetNotFound("x86-linux".to_string()),
+            Error::InvalidTarget {
+                target: "x86-linux".to_string(),
+                suggestions: Vec::new(),
+            },
+            Error::Toolchain("broken".to_string()),
+            Error::ToolchainMissing {
+                toolchain: "nightly".to_string(),
+                install_hint: String::new(),
+            },
+            Error::linker_not_found("cc", "x86_64-pc-windows-gnu", "linux"),
+            Error::CargoTooOld {
+                feature: "-Zbuild-std".to_string(),
+                required: "1.70".to_string(),
+                found: "1.60".to_string(),
+                upgrade_hint: String::new(),
+            },
+            Error::Build("failed".to_string()),
+            Error::BuildFailed {
+                target: "x86_64-pc-windows-gnu".to_string(),
+                exit_code: Some(101),
+                suggestion: None,
+            },
+            Error::Config("bad".to_string()),
+            Error::ConfigParse {
+                path: "xcargo.toml".to_string(),
+                line: None,
+                message: String::new(),
+            },
+            Error::Container("failed".to_string()),
+            Error::container_not_found("docker", "linux"),
+        ];
+        for error in errors {
+            assert_eq!(error.message_in(&english), error.to_string());
+        }
+    }
+
     #[test]
     fn test_hint_target_not_found() {
         let err = Error::TargetNotFound("invalid-target".to_string());
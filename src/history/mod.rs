@@ -0,0 +1,305 @@
+//! Build durations of earlier builds, for time estimates
+//!
+//! [`runs`] keeps every build of every project, for `xcargo history`.
+//!
+//! Estimates come from the same log: the successful builds of the nearest
+//! package, per target, operation and profile. The
+//! average of the last few builds estimates the next one: a build shows
+//! "~4m based on last 3 builds" for its target, and multi-target builds list
+//! the queued targets with an estimate of the total, updated as targets
+//! finish.
+//!
+//! ```no_run
+//! use xcargo::build::BuildOptions;
+//! use xcargo::history;
+//!
+//! let options = BuildOptions::default();
+//! let key = history::key("aarch64-unknown-linux-musl", &options);
+//! if let Some(estimate) = history::load().estimate(&key) {
+//!     println!("aarch64-unknown-linux-musl: {}", estimate.describe());
+//! }
+//! ```
+
+use crate::build::BuildOptions;
+use crate::error::{Error, Result};
+use crate::output::helpers;
+use runs::Run;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub mod runs;
+
+/// xcargo's directory inside cargo's target directory
+const XCARGO_DIR: &str = "xcargo";
+
+/// Durations kept per target, operation and profile
+const KEPT: usize = 5;
+
+/// Most recent builds an estimate averages
+const AVERAGED: usize = 3;
+
+/// Durations of earlier successful builds, keyed by
+/// `<triple>/<operation>/<profile>`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildHistory {
+    /// Milliseconds each build took, oldest first
+    builds: BTreeMap<String, Vec<u64>>,
+}
+
+/// Expected duration of a build
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Estimate {
+    /// Average of the builds it is based on
+    pub duration: Duration,
+    /// Number of earlier builds averaged
+    pub builds: usize,
+}
+
+impl Estimate {
+    /// "~4m based on last 3 builds"
+    #[must_use]
+    pub fn describe(&self) -> String {
+        if self.builds == 1 {
+            format!("{} based on the last build", approximate(self.duration))
+        } else {
+            format!(
+                "{} based on last {} builds",
+                approximate(self.duration),
+                self.builds
+            )
+        }
+    }
+}
+
+impl BuildHistory {
+    /// The history of the project in `project` from the recorded `runs`,
+    /// counting the builds that ran and succeeded
+    #[must_use]
+    pub fn from_runs(runs: &[Run], project: &str) -> Self {
+        let mut history = Self::default();
+        for run in runs
+            .iter()
+            .filter(|run| run.project == project && run.timed())
+        {
+            let key = format!("{}/{}/{}", run.target, run.operation, run.profile);
+            history.record(&key, run.duration());
+        }
+        history
+    }
+
+    /// Record that a build of `key` took `duration`
+    pub fn record(&mut self, key: &str, duration: Duration) {
+        let builds = self.builds.entry(key.to_string()).or_default();
+        builds.push(u64::try_from(duration.as_millis()).unwrap_or(u64::MAX));
+        let excess = builds.len().saturating_sub(KEPT);
+        builds.drain(..excess);
+    }
+
+    /// Expected duration of the next build of `key`, if it was built before
+    #[must_use]
+    pub fn estimate(&self, key: &str) -> Option<Estimate> {
+        let builds = self.builds.get(key)?;
+        let recent = &builds[builds.len().saturating_sub(AVERAGED)..];
+        let count = u64::try_from(recent.len()).ok().filter(|n| *n > 0)?;
+        Some(Estimate {
+            duration: Duration::from_millis(recent.iter().sum::<u64>() / count),
+            builds: recent.len(),
+        })
+    }
+}
+
+/// History key of a build of `triple` with `options`
+#[must_use]
+pub fn key(triple: &str, options: &BuildOptions) -> String {
+    let profile = if options.release { "release" } else { "debug" };
+    format!("{triple}/{}/{profile}", options.operation.as_str())
+}
+
+/// xcargo's directory in the current project's target directory
+///
+/// Found without asking cargo, since it is written after every build.
+pub fn xcargo_dir() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("CARGO_TARGET_DIR") {
+        return Ok(PathBuf::from(dir).join(XCARGO_DIR));
+    }
+    Ok(project_dir()?.join("target").join(XCARGO_DIR))
+}
+
+/// Directory of the nearest `Cargo.toml`
+pub fn project_dir() -> Result<PathBuf> {
+    let cwd = std::env::current_dir()?;
+    cwd.ancestors()
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .map(Path::to_path_buf)
+        .ok_or_else(|| Error::Config("No Cargo.toml found".to_string()))
+}
+
+/// The current project's history from the run log, empty if either
+/// cannot be found
+#[must_use]
+pub fn load() -> BuildHistory {
+    let (Ok(project), Ok(path)) = (project_dir(), runs::runs_path()) else {
+        return BuildHistory::default();
+    };
+    BuildHistory::from_runs(&runs::load(&path), &project.to_string_lossy())
+}
+
+/// Expected time left for `estimates`, built one after another or all at
+/// once with `parallel`, after `elapsed`
+///
+/// `None` when no target has an estimate.
+#[must_use]
+pub fn remaining(
+    estimates: &[Option<Estimate>],
+    parallel: bool,
+    elapsed: Duration,
+) -> Option<Duration> {
+    let known = estimates.iter().flatten().map(|estimate| estimate.duration);
+    let total = if parallel {
+        known.max()?
+    } else {
+        let known: Vec<Duration> = known.collect();
+        if known.is_empty() {
+            return None;
+        }
+        known.into_iter().sum()
+    };
+    Some(total.saturating_sub(elapsed))
+}
+
+/// List the queued `targets` of a multi-target build with their estimates
+/// and the expected total
+///
+/// Prints nothing when none of them was built before.
+pub fn print_queue(targets: &[String], estimates: &[Option<Estimate>], parallel: bool) {
+    let Some(total) = remaining(estimates, parallel, Duration::ZERO) else {
+        return;
+    };
+    for (target, estimate) in targets.iter().zip(estimates) {
+        match estimate {
+            Some(estimate) => println!("  {target}: {}", estimate.describe()),
+            None => println!("  {target}: no earlier builds"),
+        }
+    }
+
+    let unknown = estimates.iter().filter(|e| e.is_none()).count();
+    let message = if unknown > 0 {
+        format!(
+            "Estimated total: {}, plus {unknown} target(s) without history",
+            approximate(total)
+        )
+    } else {
+        format!("Estimated total: {}", approximate(total))
+    };
+    helpers::info(message);
+}
+
+/// Rounded duration for estimates: "~45s", "~4m", "~1h 10m"
+#[must_use]
+pub fn approximate(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 60 {
+        format!("~{}s", secs.max(1))
+    } else if secs < 60 * 60 {
+        format!("~{}m", (secs + 30) / 60)
+    } else {
+        let mins = (secs + 30) / 60;
+        format!("~{}h {}m", mins / 60, mins % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build::CargoOperation;
+
+    #[test]
+    fn test_estimate_averages_recent_builds() {
+        let mut history = BuildHistory::default();
+        assert_eq!(history.estimate("t/build/debug"), None);
+
+        history.record("t/build/debug", Duration::from_secs(60));
+        let estimate = history.estimate("t/build/debug").unwrap();
+        assert_eq!(estimate.describe(), "~1m based on the last build");
+
+        for secs in [600, 240, 240, 240] {
+            history.record("t/build/debug", Duration::from_secs(secs));
+        }
+        let estimate = history.estimate("t/build/debug").unwrap();
+        assert_eq!(estimate.duration, Duration::from_secs(240));
+        assert_eq!(estimate.describe(), "~4m based on last 3 builds");
+        assert_eq!(history.builds["t/build/debug"].len(), KEPT);
+    }
+
+    #[test]
+    fn test_history_from_runs() {
+        use runs::Outcome;
+
+        let options = BuildOptions {
+            operation: CargoOperation::Test,
+            release: true,
+            ..BuildOptions::default()
+        };
+        let key = key("aarch64-unknown-linux-musl", &options);
+        assert_eq!(key, "aarch64-unknown-linux-musl/test/release");
+
+        let run = |project: &str, outcome, secs| Run {
+            project: project.to_string(),
+            ..Run::new(
+                "aarch64-unknown-linux-musl",
+                &options,
+                outcome,
+                Duration::from_secs(secs),
+            )
+        };
+        let recorded = [
+            run("/work/app", Outcome::Success, 60),
+            run("/work/app", Outcome::Failed, 5),
+            run("/work/other", Outcome::Success, 600),
+            Run {
+                project: "/work/app".to_string(),
+                ..Run::cached("aarch64-unknown-linux-musl", &options)
+            },
+            run("/work/app", Outcome::Success, 120),
+        ];
+
+        let history = BuildHistory::from_runs(&recorded, "/work/app");
+        let estimate = history.estimate(&key).unwrap();
+        assert_eq!(estimate.builds, 2);
+        assert_eq!(estimate.duration, Duration::from_secs(90));
+        assert_eq!(
+            BuildHistory::from_runs(&recorded, "/work/none"),
+            BuildHistory::default()
+        );
+    }
+
+    #[test]
+    fn test_remaining() {
+        let estimate = |secs| {
+            Some(Estimate {
+                duration: Duration::from_secs(secs),
+                builds: 1,
+            })
+        };
+        let estimates = [estimate(120), None, estimate(300)];
+        let elapsed = Duration::from_secs(60);
+        assert_eq!(
+            remaining(&estimates, false, elapsed),
+            Some(Duration::from_secs(360))
+        );
+        assert_eq!(
+            remaining(&estimates, true, elapsed),
+            Some(Duration::from_secs(240))
+        );
+        assert_eq!(remaining(&[None], false, elapsed), None);
+    }
+
+    #[test]
+    fn test_approximate() {
+        assert_eq!(approximate(Duration::from_millis(300)), "~1s");
+        assert_eq!(approximate(Duration::from_secs(45)), "~45s");
+        assert_eq!(approximate(Duration::from_secs(250)), "~4m");
+        assert_eq!(approximate(Duration::from_secs(4200)), "~1h 10m");
+    }
+}
@@ -0,0 +1,673 @@
+//! Every build of every project, for `xcargo history`
+//!
+//! Each target xcargo builds, tests or checks appends one JSON line to
+//! `~/.xcargo/history.jsonl`: when it ran, the project, target, operation
+//! and profile, how it ended, how long it took, the strategy that built it
+//! and whether `--resume` skipped it because the build cache had it. Lines
+//! are only ever appended, so several xcargo processes can record at once,
+//! and the oldest are dropped once the file grows past a few megabytes.
+//! Each line carries the schema version it was written with; lines from a
+//! newer xcargo are skipped. Time estimates are averaged from the same log
+//! (see [`BuildHistory`](super::BuildHistory)).
+//!
+//! ```no_run
+//! use xcargo::history::runs::{self, Filter};
+//!
+//! # fn example() -> xcargo::Result<()> {
+//! let filter = Filter {
+//!     targets: vec!["aarch64-unknown-linux-gnu".to_string()],
+//!     ..Filter::default()
+//! };
+//! let recorded = filter.apply(runs::load(&runs::runs_path()?));
+//! for trend in runs::trends(&recorded, 5) {
+//!     println!("{}", trend.describe());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::build::BuildOptions;
+use crate::error::{Error, Result};
+use crate::output::{self, helpers, progress::format_duration, progress::format_timestamp};
+use crate::state;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Run log location inside `~/.xcargo`
+const RUNS_FILE: &str = "history.jsonl";
+
+/// Schema version written with each run; bump on incompatible changes
+const SCHEMA: u32 = 1;
+
+/// Size past which the oldest runs are dropped
+const MAX_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Runs kept when the log is trimmed
+const KEPT: usize = 10_000;
+
+/// Held while the log is appended to or trimmed, so parallel builds in this
+/// process do not interleave their lines
+static WRITING: Mutex<()> = Mutex::new(());
+
+/// How a build ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    /// Cargo succeeded
+    Success,
+    /// Cargo or a step around it failed
+    Failed,
+    /// Interrupted with Ctrl-C
+    Cancelled,
+    /// Stopped by `--timeout` or `build.timeout`
+    TimedOut,
+}
+
+impl Outcome {
+    /// Outcome of a build that returned `result`
+    #[must_use]
+    pub fn of<T>(result: &Result<T>) -> Self {
+        match result {
+            Ok(_) => Self::Success,
+            Err(Error::Cancelled) => Self::Cancelled,
+            Err(Error::TimedOut(_)) => Self::TimedOut,
+            Err(_) => Self::Failed,
+        }
+    }
+
+    /// Name used in output and filters
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+            Self::TimedOut => "timed out",
+        }
+    }
+}
+
+/// One recorded build of one target
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Run {
+    /// Schema version the run was written with; 0 for runs recorded before
+    /// versions were
+    #[serde(default)]
+    pub schema: u32,
+    /// Seconds since the Unix epoch when the build ended
+    pub recorded: u64,
+    /// Directory of the project's nearest `Cargo.toml`
+    #[serde(default)]
+    pub project: String,
+    /// Target triple
+    pub target: String,
+    /// Cargo operation (e.g., "build", "test")
+    pub operation: String,
+    /// "debug" or "release"
+    pub profile: String,
+    /// How the build ended
+    pub outcome: Outcome,
+    /// Milliseconds the build took
+    #[serde(default)]
+    pub millis: u64,
+    /// Strategy that built it (e.g., "zig"), if one was chosen
+    #[serde(default)]
+    pub strategy: Option<String>,
+    /// Skipped by `--resume` because the build cache had it
+    #[serde(default)]
+    pub cached: bool,
+}
+
+impl Run {
+    /// A build of `triple` with `options` that ended with `outcome` after
+    /// `duration`, in the current project
+    #[must_use]
+    pub fn new(triple: &str, options: &BuildOptions, outcome: Outcome, duration: Duration) -> Self {
+        Self {
+            schema: SCHEMA,
+            recorded: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            project: super::project_dir()
+                .map(|dir| dir.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            target: triple.to_string(),
+            operation: options.operation.as_str().to_string(),
+            profile: if options.release { "release" } else { "debug" }.to_string(),
+            outcome,
+            millis: u64::try_from(duration.as_millis()).unwrap_or(u64::MAX),
+            strategy: None,
+            cached: false,
+        }
+    }
+
+    /// A build of `triple` that `--resume` skipped as already built
+    #[must_use]
+    pub fn cached(triple: &str, options: &BuildOptions) -> Self {
+        Self {
+            cached: true,
+            ..Self::new(triple, options, Outcome::Success, Duration::ZERO)
+        }
+    }
+
+    /// Set the strategy that built it
+    #[must_use]
+    pub fn with_strategy(mut self, strategy: impl Into<String>) -> Self {
+        self.strategy = Some(strategy.into());
+        self
+    }
+
+    /// How long the build took
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.millis)
+    }
+
+    /// Whether the build ran and succeeded, so its time says something
+    #[must_use]
+    pub fn timed(&self) -> bool {
+        self.outcome == Outcome::Success && !self.cached
+    }
+
+    /// "aarch64-unknown-linux-gnu build (release)"
+    #[must_use]
+    pub fn describe(&self) -> String {
+        format!("{} {} ({})", self.target, self.operation, self.profile)
+    }
+
+    /// Outcome for output, marking cache hits
+    fn result(&self) -> &'static str {
+        if self.cached {
+            "cached"
+        } else {
+            self.outcome.as_str()
+        }
+    }
+}
+
+/// Which runs `xcargo history` shows
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    /// Only these targets; all when empty
+    pub targets: Vec<String>,
+    /// Only this operation
+    pub operation: Option<String>,
+    /// Only runs of the project in this directory
+    pub project: Option<String>,
+    /// Only runs that did not succeed
+    pub failed: bool,
+}
+
+impl Filter {
+    /// Whether `run` is shown
+    #[must_use]
+    pub fn matches(&self, run: &Run) -> bool {
+        (self.targets.is_empty() || self.targets.contains(&run.target))
+            && self
+                .operation
+                .as_ref()
+                .map_or(true, |op| *op == run.operation)
+            && self
+                .project
+                .as_ref()
+                .map_or(true, |dir| *dir == run.project)
+            && (!self.failed || run.outcome != Outcome::Success)
+    }
+
+    /// The runs of `runs` that are shown, in the same order
+    #[must_use]
+    pub fn apply(&self, mut runs: Vec<Run>) -> Vec<Run> {
+        runs.retain(|run| self.matches(run));
+        runs
+    }
+}
+
+/// How the build time of one target, operation and profile develops
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trend {
+    /// Target, operation and profile, as [`Run::describe`]
+    pub build: String,
+    /// Runs in the window
+    pub runs: usize,
+    /// Runs in the window that succeeded, cached or not
+    pub succeeded: usize,
+    /// Runs in the window `--resume` skipped
+    pub cached: usize,
+    /// Average time of the window's timed runs
+    pub average: Option<Duration>,
+    /// Average time of the timed runs in the window before
+    pub previous: Option<Duration>,
+}
+
+impl Trend {
+    /// Change of the average against the window before, in percent;
+    /// positive when builds got slower
+    #[must_use]
+    pub fn change(&self) -> Option<f64> {
+        let (average, previous) = (self.average?, self.previous?);
+        if previous.is_zero() {
+            return None;
+        }
+        Some((average.as_secs_f64() / previous.as_secs_f64() - 1.0) * 100.0)
+    }
+
+    /// "x86_64-pc-windows-gnu build (debug): 1m 20s on average over 5 runs,
+    /// 12% slower than before; 4 of 5 succeeded"
+    #[must_use]
+    pub fn describe(&self) -> String {
+        let average = match self.average {
+            Some(average) => format!(
+                "{} on average over {} run(s)",
+                format_duration(average),
+                self.runs
+            ),
+            None => format!("no timed builds in {} run(s)", self.runs),
+        };
+        let change = self
+            .change()
+            .map(|change| format!(", {}", describe_change(change)))
+            .unwrap_or_default();
+        let cached = if self.cached > 0 {
+            format!(", {} cached", self.cached)
+        } else {
+            String::new()
+        };
+        format!(
+            "{}: {average}{change}; {} of {} succeeded{cached}",
+            self.build, self.succeeded, self.runs
+        )
+    }
+}
+
+/// "12% slower than before", "3% faster than before"
+fn describe_change(change: f64) -> String {
+    if change.abs() < 0.5 {
+        "as fast as before".to_string()
+    } else if change > 0.0 {
+        format!("{change:.0}% slower than before")
+    } else {
+        format!("{:.0}% faster than before", -change)
+    }
+}
+
+/// Path of the run log (`~/.xcargo/history.jsonl`)
+pub fn runs_path() -> Result<PathBuf> {
+    let home = dirs::home_dir()
+        .ok_or_else(|| Error::Config("Could not determine home directory".to_string()))?;
+    Ok(home.join(".xcargo").join(RUNS_FILE))
+}
+
+/// Add `run` to the log at `path`, dropping the oldest runs once it grows
+/// past [`MAX_BYTES`]
+pub fn append(path: &Path, run: &Run) -> Result<()> {
+    let _writing = WRITING.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let line = serde_json::to_string(run)
+        .map_err(|e| Error::Config(format!("Could not serialize the build record: {e}")))?;
+    // One write per line keeps appends from several processes whole
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(format!("{line}\n").as_bytes())?;
+
+    if file.metadata()?.len() > MAX_BYTES {
+        trim(path)?;
+    }
+    Ok(())
+}
+
+/// Keep the last [`KEPT`] lines of the log at `path`
+///
+/// The log is replaced with [`state::write_atomic`], so a crash leaves the
+/// whole old log. Lines other processes append while it is trimmed are
+/// read again just before the replacement and kept.
+fn trim(path: &Path) -> Result<()> {
+    let mut file = fs::File::open(path)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let read = contents.len() as u64;
+
+    let lines: Vec<&str> = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let mut kept = String::new();
+    for line in &lines[lines.len().saturating_sub(KEPT)..] {
+        kept.push_str(line);
+        kept.push('\n');
+    }
+
+    let mut appended = Vec::new();
+    file.seek(SeekFrom::Start(read))?;
+    file.read_to_end(&mut appended)?;
+    let mut kept = kept.into_bytes();
+    kept.extend(appended);
+    state::write_atomic(path, kept)
+}
+
+/// Add `run` to the user's log
+///
+/// Failing to record never fails the build; the run is only missing from
+/// `xcargo history`.
+pub fn record(run: &Run) {
+    if let Err(e) = runs_path().and_then(|path| append(&path, run)) {
+        tracing::debug!(error = %e, "could not record the build in the history");
+    }
+}
+
+/// Runs recorded at `path`, oldest first
+///
+/// Lines that do not parse, e.g. one cut short by a crash, and runs written
+/// by a newer xcargo are skipped.
+#[must_use]
+pub fn load(path: &Path) -> Vec<Run> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str::<Run>(line).ok())
+        .filter(|run| run.schema <= SCHEMA)
+        .collect()
+}
+
+/// Trend of every target, operation and profile in `runs`, over windows of
+/// the `last` runs
+#[must_use]
+pub fn trends(runs: &[Run], last: usize) -> Vec<Trend> {
+    let last = last.max(1);
+    let mut builds: BTreeMap<String, Vec<&Run>> = BTreeMap::new();
+    for run in runs {
+        builds.entry(run.describe()).or_default().push(run);
+    }
+
+    builds
+        .into_iter()
+        .map(|(build, runs)| {
+            let split = runs.len().saturating_sub(last);
+            let (before, window) = runs.split_at(split);
+            let before = &before[before.len().saturating_sub(last)..];
+            Trend {
+                build,
+                runs: window.len(),
+                succeeded: window
+                    .iter()
+                    .filter(|run| run.outcome == Outcome::Success)
+                    .count(),
+                cached: window.iter().filter(|run| run.cached).count(),
+                average: average(window),
+                previous: average(before),
+            }
+        })
+        .collect()
+}
+
+/// Average time of the timed runs among `runs`
+fn average(runs: &[&Run]) -> Option<Duration> {
+    let timed: Vec<u64> = runs
+        .iter()
+        .filter(|run| run.timed())
+        .map(|run| run.millis)
+        .collect();
+    let count = u64::try_from(timed.len()).ok().filter(|n| *n > 0)?;
+    Some(Duration::from_millis(timed.iter().sum::<u64>() / count))
+}
+
+/// List `runs`, oldest first
+pub fn print_runs(runs: &[Run]) {
+    if runs.is_empty() {
+        helpers::warning("No builds were recorded yet; build a target first");
+        return;
+    }
+
+    helpers::section("Build History");
+    if output::is_plain() {
+        for run in runs {
+            println!(
+                "{}: {} via {}, {} in {}",
+                format_timestamp(run.recorded),
+                run.describe(),
+                run.strategy.as_deref().unwrap_or("-"),
+                run.result(),
+                format_duration(run.duration())
+            );
+        }
+        return;
+    }
+
+    let header = ["Finished", "Build", "Strategy", "Time", "Result"].map(String::from);
+    let rows: Vec<[String; 5]> = runs
+        .iter()
+        .map(|run| {
+            [
+                format_timestamp(run.recorded),
+                run.describe(),
+                run.strategy.clone().unwrap_or_else(|| "-".to_string()),
+                format_duration(run.duration()),
+                run.result().to_string(),
+            ]
+        })
+        .collect();
+    let mut widths = header.clone().map(|cell| cell.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    // Times align right, everything else left
+    let line = |cells: &[String; 5]| {
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(column, (cell, width))| {
+                if column == 3 {
+                    format!("{cell:>width$}")
+                } else {
+                    format!("{cell:<width$}")
+                }
+            })
+            .collect();
+        format!("  {}", cells.join("  ").trim_end())
+    };
+    println!("{}", line(&header));
+    for row in &rows {
+        println!("{}", line(row));
+    }
+}
+
+/// Show the trend of every build in `trends`, averaged over the last `last`
+/// runs
+pub fn print_trends(trends: &[Trend], last: usize) {
+    if trends.is_empty() {
+        return;
+    }
+    helpers::section(format!("Trends (last {last} runs)"));
+    for trend in trends {
+        println!("  {}", trend.describe());
+    }
+
+    let slower: Vec<&Trend> = trends
+        .iter()
+        .filter(|trend| trend.change().is_some_and(|change| change >= 25.0))
+        .collect();
+    for trend in slower {
+        helpers::warning(format!(
+            "{} got {}",
+            trend.build,
+            describe_change(trend.change().unwrap_or_default())
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build::CargoOperation;
+    use tempfile::TempDir;
+
+    fn run(target: &str, outcome: Outcome, secs: u64) -> Run {
+        Run {
+            schema: SCHEMA,
+            recorded: 1_700_000_000,
+            project: "/work/app".to_string(),
+            target: target.to_string(),
+            operation: "build".to_string(),
+            profile: "debug".to_string(),
+            outcome,
+            millis: secs * 1000,
+            strategy: Some("native".to_string()),
+            cached: false,
+        }
+    }
+
+    #[test]
+    fn test_outcome_of_result() {
+        assert_eq!(Outcome::of(&Ok(())), Outcome::Success);
+        assert_eq!(
+            Outcome::of::<()>(&Err(Error::Cancelled)),
+            Outcome::Cancelled
+        );
+        assert_eq!(
+            Outcome::of::<()>(&Err(Error::TimedOut("5m".to_string()))),
+            Outcome::TimedOut
+        );
+        assert_eq!(
+            Outcome::of::<()>(&Err(Error::Build("linker failed".to_string()))),
+            Outcome::Failed
+        );
+    }
+
+    #[test]
+    fn test_append_and_load() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join(".xcargo").join(RUNS_FILE);
+        assert!(load(&path).is_empty());
+
+        let options = BuildOptions {
+            operation: CargoOperation::Test,
+            release: true,
+            ..BuildOptions::default()
+        };
+        let first = Run::new(
+            "aarch64-unknown-linux-gnu",
+            &options,
+            Outcome::Failed,
+            Duration::from_millis(2500),
+        )
+        .with_strategy("zig");
+        let second = Run::cached("aarch64-unknown-linux-gnu", &options);
+        append(&path, &first).unwrap();
+        fs::write(
+            &path,
+            format!("{}{{\"recorded\":\n", fs::read_to_string(&path).unwrap()),
+        )
+        .unwrap();
+        append(&path, &second).unwrap();
+
+        let runs = load(&path);
+        assert_eq!(runs, vec![first, second]);
+        assert_eq!(
+            runs[0].describe(),
+            "aarch64-unknown-linux-gnu test (release)"
+        );
+        assert_eq!(runs[1].result(), "cached");
+        assert!(!runs[1].timed());
+    }
+
+    #[test]
+    fn test_filter() {
+        let runs = vec![
+            run("x86_64-pc-windows-gnu", Outcome::Success, 10),
+            run("aarch64-unknown-linux-gnu", Outcome::Failed, 3),
+            run("aarch64-unknown-linux-gnu", Outcome::TimedOut, 600),
+        ];
+        let filter = Filter {
+            targets: vec!["aarch64-unknown-linux-gnu".to_string()],
+            ..Filter::default()
+        };
+        assert_eq!(filter.apply(runs.clone()).len(), 2);
+
+        let failed = Filter {
+            failed: true,
+            ..Filter::default()
+        };
+        assert_eq!(failed.apply(runs.clone()).len(), 2);
+
+        let elsewhere = Filter {
+            project: Some("/work/other".to_string()),
+            ..Filter::default()
+        };
+        assert!(elsewhere.apply(runs).is_empty());
+    }
+
+    #[test]
+    fn test_trends_average_the_last_runs() {
+        let target = "aarch64-unknown-linux-gnu";
+        let mut runs: Vec<Run> = [100, 100, 120, 120]
+            .into_iter()
+            .map(|secs| run(target, Outcome::Success, secs))
+            .collect();
+        runs.push(run(target, Outcome::Failed, 5));
+        runs.push(run("x86_64-pc-windows-gnu", Outcome::Success, 30));
+
+        let trends = trends(&runs, 3);
+        assert_eq!(trends.len(), 2);
+        let trend = &trends[0];
+        assert_eq!(trend.runs, 3);
+        assert_eq!(trend.succeeded, 2);
+        assert_eq!(trend.average, Some(Duration::from_secs(120)));
+        assert_eq!(trend.previous, Some(Duration::from_secs(100)));
+        assert_eq!(
+            trend.describe(),
+            "aarch64-unknown-linux-gnu build (debug): 2m 00s on average over 3 run(s), \
+             20% slower than before; 2 of 3 succeeded"
+        );
+
+        assert_eq!(trends[1].previous, None);
+        assert_eq!(trends[1].change(), None);
+    }
+
+    #[test]
+    fn test_append_trims_old_runs() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join(RUNS_FILE);
+        let line = serde_json::to_string(&run("old", Outcome::Success, 1)).unwrap();
+        let lines = usize::try_from(MAX_BYTES).unwrap() / line.len() + 1;
+        fs::write(&path, format!("{line}\n").repeat(lines)).unwrap();
+
+        append(&path, &run("new", Outcome::Success, 1)).unwrap();
+        let runs = load(&path);
+        assert_eq!(runs.len(), KEPT);
+        assert_eq!(runs[KEPT - 1].target, "new");
+        // No temporary file is left next to it
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_load_skips_newer_schemas() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join(RUNS_FILE);
+        let newer = Run {
+            schema: SCHEMA + 1,
+            ..run("newer", Outcome::Success, 1)
+        };
+        let legacy = r#"{"recorded":1700000000,"target":"legacy","operation":"build","profile":"debug","outcome":"success"}"#;
+        fs::write(
+            &path,
+            format!("{legacy}\n{}\n", serde_json::to_string(&newer).unwrap()),
+        )
+        .unwrap();
+        append(&path, &run("current", Outcome::Success, 1)).unwrap();
+
+        let targets: Vec<String> = load(&path).into_iter().map(|run| run.target).collect();
+        assert_eq!(targets, ["legacy", "current"]);
+    }
+}
@@ -0,0 +1,322 @@
+//! Message catalog and locale selection
+//!
+//! Tips, error messages and doctor suggestions are looked up by key (e.g.,
+//! `tips.config_file`) in the catalog for the locale in `XCARGO_LANG`.
+//! English is built in and fills in every key a translation lacks, so a
+//! partial translation is still usable.
+//!
+//! A translation is a TOML file with the sections and keys of
+//! `locales/en.toml`. Catalogs in the repository's `locales/` directory are
+//! compiled in (see [`BUILTIN`]); `~/.xcargo/locales/<lang>.toml` is read at
+//! runtime and takes precedence, so a translation can be used or tested
+//! without rebuilding xcargo.
+//!
+//! ```no_run
+//! use xcargo::i18n;
+//!
+//! let message = i18n::tr_args("errors.invalid_target", &[("target", "x86-linux")]);
+//! println!("{message}");
+//! ```
+
+use crate::error::{Error, Result};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Environment variable selecting the message locale
+pub const LANG_VAR: &str = "XCARGO_LANG";
+
+/// Built-in English catalog
+const ENGLISH: &str = include_str!("../locales/en.toml");
+
+/// Translations compiled into the binary, as (locale, catalog) pairs
+///
+/// Add community translations here once they are in `locales/`, e.g.
+/// `("pt-BR", include_str!("../locales/pt-BR.toml"))`.
+pub const BUILTIN: &[(&str, &str)] = &[];
+
+/// Catalog used by [`tr`] and [`tr_args`], loaded on first use
+static ACTIVE: OnceLock<Catalog> = OnceLock::new();
+
+/// A message key usable wherever text is expected (e.g., [`crate::output::tips`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageId(pub &'static str);
+
+impl fmt::Display for MessageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&tr(self.0))
+    }
+}
+
+impl From<MessageId> for String {
+    fn from(id: MessageId) -> Self {
+        tr(id.0)
+    }
+}
+
+/// Messages for one locale, keyed by `section.key`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Catalog {
+    locale: String,
+    messages: BTreeMap<String, String>,
+}
+
+impl Catalog {
+    /// Parse a catalog from TOML with one table per section
+    pub fn parse(locale: &str, contents: &str) -> Result<Self> {
+        let table: toml::Table = toml::from_str(contents)
+            .map_err(|e| Error::Config(format!("Invalid {locale} message catalog: {e}")))?;
+
+        let mut messages = BTreeMap::new();
+        for (section, entries) in table {
+            let Some(entries) = entries.as_table() else {
+                return Err(Error::Config(format!(
+                    "Invalid {locale} message catalog: [{section}] is not a table"
+                )));
+            };
+            for (key, value) in entries {
+                let Some(text) = value.as_str() else {
+                    return Err(Error::Config(format!(
+                        "Invalid {locale} message catalog: {section}.{key} is not a string"
+                    )));
+                };
+                messages.insert(format!("{section}.{key}"), text.to_string());
+            }
+        }
+
+        Ok(Self {
+            locale: locale.to_string(),
+            messages,
+        })
+    }
+
+    /// The built-in English catalog
+    ///
+    /// # Panics
+    /// Never in practice: `locales/en.toml` is checked by the tests.
+    #[must_use]
+    pub fn english() -> Self {
+        Self::parse("en", ENGLISH).expect("built-in English catalog is valid TOML")
+    }
+
+    /// Locale of the catalog (e.g., "pt-BR")
+    #[must_use]
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// Number of messages
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Check if the catalog has no messages
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Message for `key`, if the catalog has it
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.messages.get(key).map(String::as_str)
+    }
+
+    /// Keys of `reference` this catalog does not translate
+    #[must_use]
+    pub fn missing_keys<'a>(&self, reference: &'a Catalog) -> Vec<&'a str> {
+        reference
+            .messages
+            .keys()
+            .filter(|key| !self.messages.contains_key(*key))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Use the messages of `translation` over these, keeping the others
+    #[must_use]
+    pub fn overlay(mut self, translation: Catalog) -> Self {
+        self.locale = translation.locale;
+        self.messages.extend(translation.messages);
+        self
+    }
+
+    /// Message for `key` with `{name}` placeholders replaced by `args`
+    ///
+    /// Unknown keys return the key itself so a missing message is visible
+    /// rather than silently empty.
+    #[must_use]
+    pub fn text(&self, key: &str, args: &[(&str, &str)]) -> String {
+        let Some(template) = self.get(key) else {
+            return key.to_string();
+        };
+        args.iter()
+            .fold(template.to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{name}}}"), value)
+            })
+    }
+}
+
+/// Where a translation was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CatalogSource {
+    /// Compiled into xcargo
+    Builtin,
+    /// Read from a file in the user's locale directory
+    File(PathBuf),
+}
+
+impl fmt::Display for CatalogSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Builtin => f.write_str("built in"),
+            Self::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Locales to try for a `XCARGO_LANG` value, most specific first
+///
+/// POSIX-style values are accepted: `pt_BR.UTF-8` gives `["pt-BR", "pt"]`.
+#[must_use]
+pub fn locale_candidates(lang: &str) -> Vec<String> {
+    let lang = lang.split(['.', '@']).next().unwrap_or_default().trim();
+    let mut parts = lang.split(['_', '-']).filter(|part| !part.is_empty());
+    let Some(language) = parts.next().map(str::to_lowercase) else {
+        return Vec::new();
+    };
+
+    let mut candidates = Vec::new();
+    if let Some(region) = parts.next() {
+        candidates.push(format!("{language}-{}", region.to_uppercase()));
+    }
+    candidates.push(language);
+    candidates
+}
+
+/// Directory searched for user-provided translations (`~/.xcargo/locales`)
+#[must_use]
+pub fn user_locale_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".xcargo").join("locales"))
+}
+
+/// Find the translation for `lang`, trying the region-specific locale
+/// before the language alone
+///
+/// Returns `Ok(None)` for English and for locales with no catalog.
+pub fn find(lang: &str) -> Result<Option<(Catalog, CatalogSource)>> {
+    for locale in locale_candidates(lang) {
+        if locale == "en" {
+            return Ok(None);
+        }
+        if let Some(path) = user_locale_dir()
+            .map(|dir| dir.join(format!("{locale}.toml")))
+            .filter(|path| path.is_file())
+        {
+            let catalog = Catalog::parse(&locale, &fs::read_to_string(&path)?)?;
+            return Ok(Some((catalog, CatalogSource::File(path))));
+        }
+        if let Some((_, contents)) = BUILTIN.iter().find(|(builtin, _)| *builtin == locale) {
+            return Ok(Some((
+                Catalog::parse(&locale, contents)?,
+                CatalogSource::Builtin,
+            )));
+        }
+    }
+    Ok(None)
+}
+
+/// The catalog for `XCARGO_LANG` over English
+///
+/// Falls back to English alone when the variable is unset or its catalog
+/// is missing or invalid (`xcargo doctor` reports why).
+pub fn catalog() -> &'static Catalog {
+    ACTIVE.get_or_init(|| {
+        let english = Catalog::english();
+        match std::env::var(LANG_VAR).ok().map(|lang| find(&lang)) {
+            Some(Ok(Some((translation, _)))) => english.overlay(translation),
+            _ => english,
+        }
+    })
+}
+
+/// Message for `key` in the active locale
+#[must_use]
+pub fn tr(key: &str) -> String {
+    catalog().text(key, &[])
+}
+
+/// Message for `key` in the active locale with `{name}` placeholders filled in
+#[must_use]
+pub fn tr_args(key: &str, args: &[(&str, &str)]) -> String {
+    catalog().text(key, args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_english_catalog() {
+        let english = Catalog::english();
+        assert_eq!(english.locale(), "en");
+        assert_eq!(
+            english.text("errors.invalid_target", &[("target", "x86-linux")]),
+            "Invalid target 'x86-linux'"
+        );
+        assert_eq!(english.text("no.such_key", &[]), "no.such_key");
+    }
+
+    #[test]
+    fn test_overlay_falls_back_to_english() {
+        let translation = Catalog::parse(
+            "pt-BR",
+            "[errors]\ninvalid_target = \"Alvo inválido '{target}'\"\n",
+        )
+        .unwrap();
+        let english = Catalog::english();
+        assert!(translation
+            .missing_keys(&english)
+            .contains(&"errors.build_failed"));
+
+        let catalog = english.overlay(translation);
+        assert_eq!(catalog.locale(), "pt-BR");
+        assert_eq!(
+            catalog.text("errors.invalid_target", &[("target", "x")]),
+            "Alvo inválido 'x'"
+        );
+        assert_eq!(
+            catalog.text("errors.build_failed", &[("target", "x")]),
+            "Build failed for target 'x'"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Catalog::parse("es", "[errors]\nbuild = 3\n").is_err());
+        assert!(Catalog::parse("es", "build = \"x\"\n").is_err());
+        assert!(Catalog::parse("es", "[errors\n").is_err());
+    }
+
+    #[test]
+    fn test_locale_candidates() {
+        assert_eq!(locale_candidates("pt_BR.UTF-8"), vec!["pt-BR", "pt"]);
+        assert_eq!(locale_candidates("es"), vec!["es"]);
+        assert!(locale_candidates("").is_empty());
+    }
+
+    #[test]
+    fn test_builtin_catalogs_parse() {
+        let english = Catalog::english();
+        for (locale, contents) in BUILTIN {
+            let catalog = Catalog::parse(locale, contents).unwrap();
+            // Translations may lag behind, but must not invent keys
+            for key in catalog.messages.keys() {
+                assert!(english.get(key).is_some(), "{locale}: unknown key {key}");
+            }
+        }
+    }
+}
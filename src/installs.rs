@@ -0,0 +1,324 @@
+//! Record of what xcargo installed
+//!
+//! Rustup targets and toolchains, pulled container images and downloaded
+//! sysroots are logged in `~/.xcargo/installed.json` as xcargo adds them.
+//! Only things that were missing are logged, so `xcargo uninstall` removes
+//! exactly what xcargo added and leaves anything installed by other means,
+//! which matters on machines shared by several users.
+//!
+//! ```no_run
+//! use xcargo::installs::{InstallLedger, Installed};
+//!
+//! # fn example() -> xcargo::Result<()> {
+//! let ledger = InstallLedger::load(&InstallLedger::default_path()?);
+//! for item in ledger.for_target(Some("x86_64-pc-windows-gnu")) {
+//!     println!("{}", item.describe());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::output::helpers;
+use crate::paths;
+use crate::state::{self, State};
+use crate::toolchain::ToolchainManager;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Ledger location inside `~/.xcargo`
+const LEDGER_FILE: &str = "installed.json";
+
+/// What kind of thing was installed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallKind {
+    /// Rustup target (standard library for a triple)
+    Target,
+    /// Rustup component (e.g., `rust-src`)
+    Component,
+    /// Container image
+    Image,
+    /// Downloaded and extracted archive (e.g., an OpenSSL sysroot)
+    Download,
+    /// Rustup toolchain; removed last, after its targets
+    Toolchain,
+}
+
+/// One thing xcargo installed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Installed {
+    /// Kind of thing
+    pub kind: InstallKind,
+    /// Target triple, toolchain name, image name or directory
+    pub name: String,
+    /// Toolchain a target was added to
+    #[serde(default)]
+    pub toolchain: Option<String>,
+    /// Target the install was made for
+    #[serde(default)]
+    pub target: Option<String>,
+    /// Seconds since the Unix epoch
+    #[serde(default)]
+    pub installed_at: u64,
+}
+
+impl Installed {
+    fn new(kind: InstallKind, name: impl Into<String>, target: Option<&str>) -> Self {
+        Self {
+            kind,
+            name: name.into(),
+            toolchain: None,
+            target: target.map(str::to_string),
+            installed_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        }
+    }
+
+    /// A rustup target added to `toolchain`
+    #[must_use]
+    pub fn target(triple: &str, toolchain: &str) -> Self {
+        let mut item = Self::new(InstallKind::Target, triple, Some(triple));
+        item.toolchain = Some(toolchain.to_string());
+        item
+    }
+
+    /// A rustup component added to `toolchain`
+    #[must_use]
+    pub fn component(name: &str, toolchain: &str) -> Self {
+        let mut item = Self::new(InstallKind::Component, name, None);
+        item.toolchain = Some(toolchain.to_string());
+        item
+    }
+
+    /// A rustup toolchain
+    #[must_use]
+    pub fn toolchain(name: &str) -> Self {
+        Self::new(InstallKind::Toolchain, name, None)
+    }
+
+    /// A container image pulled to build `target`
+    #[must_use]
+    pub fn image(image: &str, target: &str) -> Self {
+        Self::new(InstallKind::Image, image, Some(target))
+    }
+
+    /// An archive extracted into `dir` for `target`
+    #[must_use]
+    pub fn download(dir: &Path, target: &str) -> Self {
+        Self::new(InstallKind::Download, dir.to_string_lossy(), Some(target))
+    }
+
+    /// Human-readable description (e.g., "rustup target x86_64-pc-windows-gnu (stable)")
+    #[must_use]
+    pub fn describe(&self) -> String {
+        match (self.kind, &self.toolchain) {
+            (InstallKind::Target, Some(toolchain)) => {
+                format!("rustup target {} ({toolchain})", self.name)
+            }
+            (InstallKind::Target, None) => format!("rustup target {}", self.name),
+            (InstallKind::Component, Some(toolchain)) => {
+                format!("rustup component {} ({toolchain})", self.name)
+            }
+            (InstallKind::Component, None) => format!("rustup component {}", self.name),
+            (InstallKind::Toolchain, _) => format!("rustup toolchain {}", self.name),
+            (InstallKind::Image, _) => format!("container image {}", self.name),
+            (InstallKind::Download, _) => format!("download {}", self.name),
+        }
+    }
+
+    /// Whether two records are for the same thing
+    fn same_as(&self, other: &Installed) -> bool {
+        self.kind == other.kind && self.name == other.name && self.toolchain == other.toolchain
+    }
+
+    /// Remove the installed thing
+    pub fn uninstall(&self) -> Result<()> {
+        match self.kind {
+            InstallKind::Target => ToolchainManager::new()?
+                .remove_target(self.toolchain.as_deref().unwrap_or("stable"), &self.name),
+            InstallKind::Component => ToolchainManager::new()?
+                .remove_component(self.toolchain.as_deref().unwrap_or("stable"), &self.name),
+            InstallKind::Toolchain => ToolchainManager::new()?.uninstall_toolchain(&self.name),
+            InstallKind::Image => remove_image(&self.name),
+            InstallKind::Download => {
+                let dir = paths::long_path(Path::new(&self.name));
+                if dir.exists() {
+                    fs::remove_dir_all(dir)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Things xcargo installed, oldest first
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstallLedger {
+    #[serde(default)]
+    installed: Vec<Installed>,
+}
+
+impl State for InstallLedger {
+    const SCHEMA: u32 = 1;
+    const DESCRIPTION: &'static str = "install ledger";
+}
+
+impl InstallLedger {
+    /// Ledger in the user's xcargo directory (`~/.xcargo/installed.json`)
+    pub fn default_path() -> Result<PathBuf> {
+        let home = dirs::home_dir()
+            .ok_or_else(|| Error::Config("Could not determine home directory".to_string()))?;
+        Ok(home.join(".xcargo").join(LEDGER_FILE))
+    }
+
+    /// Load the ledger from `path`, or an empty one if there is none or it
+    /// is unreadable
+    #[must_use]
+    pub fn load(path: &Path) -> Self {
+        state::load(path)
+    }
+
+    /// Write the ledger to `path`
+    pub fn save(&self, path: &Path) -> Result<()> {
+        state::save(path, self)
+    }
+
+    /// Everything recorded
+    #[must_use]
+    pub fn entries(&self) -> &[Installed] {
+        &self.installed
+    }
+
+    /// Record `item`, unless the same thing is already recorded
+    pub fn add(&mut self, item: Installed) {
+        if !self.installed.iter().any(|known| known.same_as(&item)) {
+            self.installed.push(item);
+        }
+    }
+
+    /// Forget `item`
+    pub fn remove(&mut self, item: &Installed) {
+        self.installed.retain(|known| !known.same_as(item));
+    }
+
+    /// What to remove for `target` (everything when `None`), in removal
+    /// order: toolchains go last, after the targets added to them
+    ///
+    /// Toolchains belong to no target and are only removed with everything.
+    #[must_use]
+    pub fn for_target(&self, target: Option<&str>) -> Vec<Installed> {
+        let mut items: Vec<Installed> = self
+            .installed
+            .iter()
+            .filter(|item| target.is_none() || item.target.as_deref() == target)
+            .cloned()
+            .collect();
+        items.sort_by_key(|item| item.kind);
+        items
+    }
+}
+
+/// Add `item` to the user's ledger
+///
+/// Failing to record never fails the install itself; it only means
+/// `xcargo uninstall` will not know about the item.
+pub fn record(item: Installed) {
+    let recorded = InstallLedger::default_path().and_then(|path| {
+        let mut ledger = InstallLedger::load(&path);
+        ledger.add(item);
+        ledger.save(&path)
+    });
+    if let Err(e) = recorded {
+        helpers::warning(format!("Could not record installation: {e}"));
+    }
+}
+
+#[cfg(feature = "container")]
+fn remove_image(image: &str) -> Result<()> {
+    use crate::config::Config;
+    use crate::container::{ContainerBuilder, RuntimeType};
+
+    let config = Config::effective()?;
+    let runtime = RuntimeType::from_str(&config.container.runtime).unwrap_or(RuntimeType::Auto);
+    ContainerBuilder::new(runtime)?.remove_image(image)
+}
+
+#[cfg(not(feature = "container"))]
+fn remove_image(image: &str) -> Result<()> {
+    Err(Error::Container(format!(
+        "Container support not enabled, cannot remove {image}"
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_add_deduplicates() {
+        let mut ledger = InstallLedger::default();
+        ledger.add(Installed::target("aarch64-unknown-linux-gnu", "stable"));
+        ledger.add(Installed::target("aarch64-unknown-linux-gnu", "stable"));
+        ledger.add(Installed::target("aarch64-unknown-linux-gnu", "nightly"));
+        assert_eq!(ledger.entries().len(), 2);
+
+        ledger.remove(&Installed::target("aarch64-unknown-linux-gnu", "nightly"));
+        assert_eq!(ledger.entries().len(), 1);
+    }
+
+    #[test]
+    fn test_for_target_orders_toolchains_last() {
+        let mut ledger = InstallLedger::default();
+        ledger.add(Installed::toolchain("nightly"));
+        ledger.add(Installed::target("x86_64-pc-windows-gnu", "nightly"));
+        ledger.add(Installed::image(
+            "ghcr.io/cross-rs/aarch64-unknown-linux-gnu:main",
+            "aarch64-unknown-linux-gnu",
+        ));
+        ledger.add(Installed::component("rust-src", "nightly"));
+
+        let all = ledger.for_target(None);
+        assert_eq!(all.len(), 4);
+        assert_eq!(all[1].describe(), "rustup component rust-src (nightly)");
+        assert_eq!(all[3].kind, InstallKind::Toolchain);
+
+        let windows = ledger.for_target(Some("x86_64-pc-windows-gnu"));
+        assert_eq!(windows.len(), 1);
+        assert_eq!(
+            windows[0].describe(),
+            "rustup target x86_64-pc-windows-gnu (nightly)"
+        );
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join(".xcargo").join(LEDGER_FILE);
+        assert!(InstallLedger::load(&path).entries().is_empty());
+
+        let mut ledger = InstallLedger::default();
+        ledger.add(Installed::download(
+            &temp.path().join("openssl"),
+            "aarch64-unknown-linux-gnu",
+        ));
+        ledger.save(&path).unwrap();
+        assert_eq!(InstallLedger::load(&path), ledger);
+    }
+
+    #[test]
+    fn test_uninstall_download() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path().join("sysroot");
+        fs::create_dir_all(dir.join("lib")).unwrap();
+
+        let item = Installed::download(&dir, "aarch64-unknown-linux-gnu");
+        item.uninstall().unwrap();
+        assert!(!dir.exists());
+        // Already gone is not an error
+        item.uninstall().unwrap();
+    }
+}
@@ -57,6 +57,21 @@
 //! builder.execute()?;
 //! ```
 //!
+//! ### Cargo Features
+//!
+//! Everything is enabled by default for the `xcargo` binary. Crates that
+//! only need target detection and build planning can depend on
+//! `xcargo = { version = "0.3", default-features = false }` and add:
+//!
+//! - `container` - Docker/Podman/nerdctl builds ([`container`])
+//! - `zig` - Cross-compilation through `zig cc`
+//! - `dist` - Apple notarization of release artifacts ([`sign`])
+//! - `tui` - Progress spinners and interactive prompts (plain progress lines otherwise)
+//! - `remote` - JSON-RPC build server ([`daemon`])
+//! - `cli` - The `xcargo` binary itself (implies `tui`)
+//! - `plugins-dynamic` - Project plugins compiled from `.xcargo/plugins` ([`plugin::local`])
+//! - `android-aar` - `.aar` packages of Android exports ([`build::android`])
+//!
 //! ## Configuration
 //!
 //! Create an `xcargo.toml` file to configure cross-compilation targets:
@@ -85,11 +100,30 @@
 //! - [`config`] - Configuration file parsing and management
 //! - [`toolchain`] - Rust toolchain and cross-compiler management
 //! - [`build`] - Build orchestration and execution
-//! - [`container`] - Docker/Podman container runtime integration
+//! - [`container`] - Docker/Podman/nerdctl container runtime integration
 //! - [`plugin`] - Plugin system for extensibility
 //! - [`doctor`] - System diagnostics and health checks
-//! - [`error`] - Error types and handling
+//! - [`error`] - Error types and handling, and explanations for common cross-compilation failures
 //! - [`cache`] - Build caching for faster incremental builds
+//! - [`deps`] - Native dependency resolution (OpenSSL, etc.)
+//! - [`daemon`] - JSON-RPC build server for editor integrations
+//! - [`paths`] - Path handling for Windows long paths and network shares
+//! - [`trace`] - Build input capture and replay
+//! - [`sign`] - Apple notarization of release artifacts
+//! - [`scratch`] - Per-build temporary directories with guaranteed cleanup
+//! - [`verify`] - Post-build checks of binary format, architecture and linkage
+//! - [`size`] - Binary size breakdowns and changes between builds
+//! - [`timings`] - Per-crate compile times compared across targets
+//! - [`history`] - Durations of earlier builds, estimates for the next ones and a log of every build
+//! - [`i18n`] - Translated messages selected with `XCARGO_LANG`
+//! - [`logs`] - Disk-backed per-target build logs
+//! - [`installs`] - Record of installed targets, images and downloads for `xcargo uninstall`
+//! - [`cancel`] - Build timeouts and cancellation of running builds
+//! - [`state`] - Versioned state files written atomically and regenerated when unusable
+//! - [`support`] - Support bundles with diagnostics, redacted config and the last failed build
+//! - [`provision`] - Cross GCC installs through the host package manager or into `~/.xcargo/tools`
+//! - `logging` - `-v`/`XCARGO_LOG` console verbosity and `--log-file` JSON logs (`cli` feature)
+//! - `testing` - Fake toolchains and project fixtures (`test-util` feature)
 //!
 //! ## Cross-Compilation Strategies
 //!
@@ -202,11 +236,14 @@ pub mod build;
 pub mod container;
 
 /// Dependency management (OpenSSL, etc.)
-pub mod deps {}
+pub mod deps;
 
 /// Output and logging
 pub mod output;
 
+/// Path helpers for Windows long and UNC paths
+pub mod paths;
+
 /// Error types
 pub mod error;
 
@@ -219,6 +256,73 @@ pub mod plugin;
 /// System diagnostics
 pub mod doctor;
 
+/// JSON-RPC build server for editors
+#[cfg(feature = "remote")]
+pub mod daemon;
+
+/// Build capture and replay
+pub mod trace;
+
+/// Artifact signing and notarization
+#[cfg(feature = "dist")]
+pub mod sign;
+
+/// Per-build scratch directories
+pub mod scratch;
+
+/// Post-build binary verification
+pub mod verify;
+
+/// Binary size reports
+pub mod size;
+
+/// Per-crate compile times across targets
+pub mod timings;
+
+/// Benchmark results across targets
+pub mod bench;
+
+/// Code coverage of cross-compiled tests
+pub mod coverage;
+
+/// Build durations, time estimates and the log of every build
+pub mod history;
+
+/// Message catalog and locale selection
+pub mod i18n;
+
+/// Disk-backed per-target build logs
+pub mod logs;
+
+/// Record of what xcargo installed
+pub mod installs;
+
+/// Build timeouts and cooperative cancellation
+pub mod cancel;
+
+/// Crash-safe, versioned state files
+pub mod state;
+
+/// Support bundles for bug reports
+pub mod support;
+
+/// Cross GCC toolchain provisioning
+pub mod provision;
+
+/// Proxy and CA settings for downloads and container builds
+pub mod network;
+
+/// Quoting for commands run through a shell
+mod shell;
+
+/// Console and JSON file logging for the CLI
+#[cfg(feature = "cli")]
+pub mod logging;
+
+/// Fake toolchains and project fixtures for tests
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
+
 /// Prelude for convenient imports
 pub mod prelude {
     //! Convenient re-exports
@@ -230,13 +334,13 @@ pub mod prelude {
 
     pub use crate::build::{BuildOptions, Builder, CargoOperation};
     pub use crate::config::Config;
-    pub use crate::error::{Error, ExitCode, Result};
+    pub use crate::error::{Error, ErrorCode, ExitCode, Result};
     pub use crate::target::{Target, TargetRequirements, TargetTier};
     pub use crate::toolchain::{Toolchain, ToolchainManager};
 }
 
 // Re-exports
-pub use error::{Error, ExitCode, Result};
+pub use error::{Error, ErrorCode, ExitCode, Result};
 
 #[cfg(test)]
 mod tests {
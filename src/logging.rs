@@ -0,0 +1,199 @@
+//! Console and file logging for the CLI
+//!
+//! Installs a `tracing` subscriber with two layers:
+//!
+//! - The console layer prints user-facing messages ([`output`] events)
+//!   exactly as before, plus diagnostics at the level chosen with
+//!   `-v` (debug), `-vv` (trace) or `-vvv` (trace, including dependencies).
+//!   `XCARGO_LOG` takes `RUST_LOG`-style directives instead
+//!   (e.g., `XCARGO_LOG=xcargo::cancel=debug`).
+//! - With `--log-file <PATH>`, every event is also written to `PATH` as
+//!   JSON lines, including the full cargo output of each target (events
+//!   with target [`CARGO_OUTPUT_TARGET`] in the target's span), for
+//!   debugging a build after the fact.
+//!
+//! [`output`]: crate::output
+
+use crate::error::{Error, Result};
+use crate::output::{colors, is_plain, Message, MessageType, CARGO_OUTPUT_TARGET, OUTPUT_TARGET};
+use std::fmt::{self, Write};
+use std::fs::File;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+/// Environment variable with log filter directives
+pub const LOG_ENV: &str = "XCARGO_LOG";
+
+/// Set up logging for the rest of the process
+///
+/// `verbosity` is the number of `-v` flags. Fails if `log_file` cannot be
+/// created or a subscriber is already installed.
+pub fn init(verbosity: u8, log_file: Option<&Path>) -> Result<()> {
+    let env = std::env::var(LOG_ENV).ok().filter(|e| !e.trim().is_empty());
+    let console = ConsoleLayer.with_filter(filter(&console_directives(verbosity, env.as_deref())));
+
+    let file = match log_file {
+        Some(path) => {
+            let file = File::create(path).map_err(|e| {
+                Error::Config(format!("Cannot create log file {}: {e}", path.display()))
+            })?;
+            let layer = tracing_subscriber::fmt::layer()
+                .json()
+                .with_ansi(false)
+                .with_writer(Mutex::new(file))
+                .with_filter(filter(&file_directives(env.as_deref())));
+            Some(layer)
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(console)
+        .with(file)
+        .try_init()
+        .map_err(|e| Error::Config(format!("Cannot set up logging: {e}")))
+}
+
+/// Console filter for `verbosity` or `XCARGO_LOG` directives
+///
+/// Messages always show unless the directives turn them off; cargo output
+/// already reaches the terminal and is never repeated.
+#[must_use]
+pub fn console_directives(verbosity: u8, env: Option<&str>) -> String {
+    let level = env.unwrap_or(match verbosity {
+        0 => "info",
+        1 => "info,xcargo=debug",
+        2 => "info,xcargo=trace",
+        _ => "trace",
+    });
+    format!("{OUTPUT_TARGET}=trace,{level},{CARGO_OUTPUT_TARGET}=off")
+}
+
+/// Log file filter: `XCARGO_LOG` directives or everything from xcargo,
+/// always with messages and cargo output
+#[must_use]
+pub fn file_directives(env: Option<&str>) -> String {
+    format!(
+        "{OUTPUT_TARGET}=trace,{CARGO_OUTPUT_TARGET}=trace,{}",
+        env.unwrap_or("info,xcargo=trace")
+    )
+}
+
+/// Parse `directives`, falling back to the default console level
+fn filter(directives: &str) -> EnvFilter {
+    EnvFilter::try_new(directives).unwrap_or_else(|e| {
+        eprintln!("Ignoring invalid {LOG_ENV} ({e})");
+        EnvFilter::new(console_directives(0, None))
+    })
+}
+
+/// Prints messages like [`Message::print`] did and diagnostics to stderr
+struct ConsoleLayer;
+
+impl<S: Subscriber> Layer<S> for ConsoleLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let meta = event.metadata();
+
+        if meta.target() == OUTPUT_TARGET {
+            let kind = fields
+                .kind
+                .as_deref()
+                .and_then(MessageType::from_label)
+                .unwrap_or(MessageType::Info);
+            println!("{}", Message::new(kind, fields.message));
+        } else {
+            eprintln!("{}", diagnostic(*meta.level(), meta.target(), &fields));
+        }
+    }
+}
+
+/// One diagnostic line, e.g. `DEBUG xcargo::cancel: stopping child process pid=42`
+fn diagnostic(level: Level, target: &str, fields: &Fields) -> String {
+    let mut line = if is_plain() {
+        format!("{level} {target}: {}", fields.message)
+    } else {
+        format!(
+            "{}{level} {target}:{} {}",
+            colors::DIM,
+            colors::RESET,
+            fields.message
+        )
+    };
+    for (name, value) in &fields.extra {
+        let _ = write!(line, " {name}={value}");
+    }
+    line
+}
+
+/// Fields of an event
+#[derive(Default)]
+struct Fields {
+    message: String,
+    kind: Option<String>,
+    extra: Vec<(&'static str, String)>,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "kind" => self.kind = Some(value.to_string()),
+            "message" => self.message = value.to_string(),
+            name => self.extra.push((name, value.to_string())),
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{value:?}"),
+            name => self.extra.push((name, format!("{value:?}"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_console_directives() {
+        assert_eq!(
+            console_directives(0, None),
+            "xcargo::output=trace,info,xcargo::cargo=off"
+        );
+        assert!(console_directives(2, None).contains("xcargo=trace"));
+        assert_eq!(
+            console_directives(3, Some("xcargo::cancel=debug")),
+            "xcargo::output=trace,xcargo::cancel=debug,xcargo::cargo=off"
+        );
+        for verbosity in 0..4 {
+            EnvFilter::try_new(console_directives(verbosity, None)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_file_directives_keep_cargo_output() {
+        assert!(
+            file_directives(Some("warn")).starts_with("xcargo::output=trace,xcargo::cargo=trace")
+        );
+        EnvFilter::try_new(file_directives(None)).unwrap();
+    }
+
+    #[test]
+    fn test_diagnostic() {
+        let fields = Fields {
+            message: "stopping child process".to_string(),
+            kind: None,
+            extra: vec![("pid", "42".to_string())],
+        };
+        let line = diagnostic(Level::DEBUG, "xcargo::cancel", &fields);
+        assert!(line.contains("DEBUG xcargo::cancel:"));
+        assert!(line.ends_with("stopping child process pid=42"));
+    }
+}
@@ -0,0 +1,342 @@
+//! Disk-backed per-target build logs
+//!
+//! Long-running modes (the daemon, `xcargo logs --follow`) keep build
+//! output in a [`LogRing`] per target instead of in memory. A ring is a
+//! file holding the last `capacity` bytes of output: older lines are
+//! overwritten as new ones arrive, so disk use stays bounded no matter how
+//! long the server runs.
+//!
+//! Positions in a ring are byte offsets counted from the first line ever
+//! written. They keep growing as the ring wraps, so a reader can remember
+//! where it stopped and ask for everything after that with
+//! [`LogRing::read_from`]; lines overwritten in the meantime are skipped.
+//!
+//! ```no_run
+//! use xcargo::logs::{self, LogRing};
+//!
+//! # fn example() -> xcargo::Result<()> {
+//! let path = logs::log_path("x86_64-pc-windows-gnu")?;
+//! let mut ring = LogRing::create(&path, logs::DEFAULT_CAPACITY)?;
+//! ring.append("   Compiling app v0.1.0")?;
+//!
+//! for line in ring.tail(20)? {
+//!     println!("{line}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::build::clean;
+use crate::error::{Error, Result};
+use crate::paths;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Log location inside cargo's target directory
+const LOG_SUBDIR: &str = "xcargo/logs";
+
+/// Identifies ring files and their layout version
+const MAGIC: &[u8; 8] = b"XCLOGRB1";
+
+/// Header: magic, capacity, bytes written (little-endian `u64`s)
+const HEADER_SIZE: u64 = 24;
+
+/// Bytes of output kept per target (1 MiB)
+pub const DEFAULT_CAPACITY: u64 = 1024 * 1024;
+
+/// Lines read from a ring and the position to continue from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogChunk {
+    /// Complete lines, without line endings
+    pub lines: Vec<String>,
+    /// Position after the last line, for the next [`LogRing::read_from`]
+    pub next: u64,
+}
+
+/// A fixed-size log file that overwrites its oldest lines
+#[derive(Debug)]
+pub struct LogRing {
+    file: File,
+    capacity: u64,
+}
+
+impl LogRing {
+    /// Open the ring at `path` for writing, creating it if needed
+    ///
+    /// An existing ring with the same capacity keeps its contents; any
+    /// other file at `path` is replaced.
+    pub fn create(path: &Path, capacity: u64) -> Result<Self> {
+        if capacity == 0 {
+            return Err(Error::Config("Log capacity must be positive".to_string()));
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(paths::long_path(parent))?;
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(paths::long_path(path))?;
+        let mut ring = Self { file, capacity };
+        if ring.header().map(|(cap, _)| cap) != Some(capacity) {
+            ring.file.set_len(0)?;
+            ring.write_header(0)?;
+        }
+        Ok(ring)
+    }
+
+    /// Open an existing ring at `path` for reading
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(paths::long_path(path))?;
+        let mut ring = Self { file, capacity: 0 };
+        let (capacity, _) = ring
+            .header()
+            .ok_or_else(|| Error::Config(format!("{} is not an xcargo log", path.display())))?;
+        ring.capacity = capacity;
+        Ok(ring)
+    }
+
+    /// Bytes of output the ring keeps
+    #[must_use]
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// Position of the oldest byte still stored and the end position
+    pub fn window(&self) -> Result<(u64, u64)> {
+        let (_, written) = self
+            .header()
+            .ok_or_else(|| Error::Config("Corrupt log header".to_string()))?;
+        Ok((written.saturating_sub(self.capacity), written))
+    }
+
+    /// Append `line`, overwriting the oldest output once the ring is full
+    ///
+    /// Lines longer than the ring keep only their end.
+    pub fn append(&mut self, line: &str) -> Result<()> {
+        let mut bytes = line.trim_end_matches(['\r', '\n']).as_bytes().to_vec();
+        bytes.push(b'\n');
+        let max = usize::try_from(self.capacity).unwrap_or(usize::MAX);
+        if bytes.len() > max {
+            bytes.drain(..bytes.len() - max);
+        }
+
+        let (_, written) = self.window()?;
+        let offset = written % self.capacity;
+        // Bytes that fit before the end of the data area; the rest wraps
+        let first = bytes
+            .len()
+            .min(usize::try_from(self.capacity - offset).unwrap_or(usize::MAX));
+        self.file.seek(SeekFrom::Start(HEADER_SIZE + offset))?;
+        self.file.write_all(&bytes[..first])?;
+        if first < bytes.len() {
+            self.file.seek(SeekFrom::Start(HEADER_SIZE))?;
+            self.file.write_all(&bytes[first..])?;
+        }
+
+        self.write_header(written + bytes.len() as u64)
+    }
+
+    /// Complete lines from position `from` to the end
+    ///
+    /// If `from` has been overwritten, reading starts at the oldest complete
+    /// line still stored. A `from` past the end (e.g., the ring was
+    /// recreated) reads the whole ring.
+    pub fn read_from(&self, from: u64) -> Result<LogChunk> {
+        let (start, end) = self.window()?;
+        let requested = if from > end { 0 } else { from };
+        let from = requested.max(start);
+
+        let mut data = self.read_range(from, end)?;
+        // The line at `start` was partly overwritten: drop up to its end
+        if requested < start {
+            let cut = data
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(data.len(), |i| i + 1);
+            data.drain(..cut);
+        }
+
+        // The writer may have wrapped over what was just read
+        let (new_start, _) = self.window()?;
+        if new_start > from {
+            return self.read_from(from);
+        }
+
+        let lines = String::from_utf8_lossy(&data)
+            .lines()
+            .map(str::to_string)
+            .collect();
+        Ok(LogChunk { lines, next: end })
+    }
+
+    /// The last `count` lines
+    pub fn tail(&self, count: usize) -> Result<Vec<String>> {
+        let mut lines = self.read_from(0)?.lines;
+        lines.drain(..lines.len().saturating_sub(count));
+        Ok(lines)
+    }
+
+    /// Bytes between positions `from` and `to`, which must be stored
+    fn read_range(&self, from: u64, to: u64) -> Result<Vec<u8>> {
+        let mut data = vec![0u8; usize::try_from(to - from).unwrap_or(0)];
+        let offset = from % self.capacity;
+        let first = data
+            .len()
+            .min(usize::try_from(self.capacity - offset).unwrap_or(usize::MAX));
+
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(HEADER_SIZE + offset))?;
+        file.read_exact(&mut data[..first])?;
+        if first < data.len() {
+            file.seek(SeekFrom::Start(HEADER_SIZE))?;
+            file.read_exact(&mut data[first..])?;
+        }
+        Ok(data)
+    }
+
+    /// Capacity and bytes written, if the file has a valid header
+    fn header(&self) -> Option<(u64, u64)> {
+        let mut header = [0u8; 24];
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(0)).ok()?;
+        file.read_exact(&mut header).ok()?;
+        if &header[..8] != MAGIC {
+            return None;
+        }
+        let capacity = u64::from_le_bytes(header[8..16].try_into().ok()?);
+        let written = u64::from_le_bytes(header[16..24].try_into().ok()?);
+        (capacity > 0).then_some((capacity, written))
+    }
+
+    fn write_header(&mut self, written: u64) -> Result<()> {
+        let mut header = Vec::with_capacity(24);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&self.capacity.to_le_bytes());
+        header.extend_from_slice(&written.to_le_bytes());
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&header)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Directory holding the per-target logs (`<target-dir>/xcargo/logs`)
+pub fn log_dir() -> Result<PathBuf> {
+    Ok(clean::target_directory()?.join(LOG_SUBDIR))
+}
+
+/// Log file for `target`
+pub fn log_path(target: &str) -> Result<PathBuf> {
+    Ok(log_dir()?.join(format!("{target}.log")))
+}
+
+/// Targets with logs, most recently written first
+pub fn list() -> Result<Vec<(String, PathBuf)>> {
+    let Ok(entries) = fs::read_dir(paths::long_path(&log_dir()?)) else {
+        return Ok(Vec::new());
+    };
+
+    let mut logs: Vec<_> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let target = path
+                .file_name()?
+                .to_str()?
+                .strip_suffix(".log")?
+                .to_string();
+            let modified = entry.metadata().and_then(|meta| meta.modified()).ok()?;
+            Some((modified, target, path))
+        })
+        .collect();
+    logs.sort_by_key(|(modified, _, _)| std::cmp::Reverse(*modified));
+    Ok(logs
+        .into_iter()
+        .map(|(_, target, path)| (target, path))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_append_and_tail() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("logs/x86_64-unknown-linux-gnu.log");
+        let mut ring = LogRing::create(&path, 1024).unwrap();
+        for i in 0..5 {
+            ring.append(&format!("line {i}\n")).unwrap();
+        }
+
+        assert_eq!(ring.tail(2).unwrap(), vec!["line 3", "line 4"]);
+        assert_eq!(ring.window().unwrap(), (0, 35));
+
+        // Reopening keeps the contents
+        drop(ring);
+        let ring = LogRing::open(&path).unwrap();
+        assert_eq!(ring.capacity(), 1024);
+        assert_eq!(ring.tail(10).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_wraps_and_stays_bounded() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("ring.log");
+        let mut ring = LogRing::create(&path, 64).unwrap();
+        for i in 0..100 {
+            ring.append(&format!("line {i:03}")).unwrap();
+        }
+
+        assert!(fs::metadata(&path).unwrap().len() <= HEADER_SIZE + 64);
+        let (start, end) = ring.window().unwrap();
+        assert_eq!((start, end), (900 - 64, 900));
+
+        // Only complete lines come back after the wrap-around
+        let lines = ring.tail(100).unwrap();
+        assert_eq!(lines.last().unwrap(), "line 099");
+        assert!(lines.iter().all(|line| line.starts_with("line ")));
+        assert_eq!(lines.len(), 7);
+    }
+
+    #[test]
+    fn test_read_from_position() {
+        let temp = TempDir::new().unwrap();
+        let mut ring = LogRing::create(&temp.path().join("ring.log"), 64).unwrap();
+        ring.append("first").unwrap();
+        let chunk = ring.read_from(0).unwrap();
+        assert_eq!(chunk.lines, vec!["first"]);
+
+        ring.append("second").unwrap();
+        let next = ring.read_from(chunk.next).unwrap();
+        assert_eq!(next.lines, vec!["second"]);
+        assert!(ring.read_from(next.next).unwrap().lines.is_empty());
+
+        // Positions that were overwritten resume at the oldest full line
+        for i in 0..20 {
+            ring.append(&format!("line {i:02}")).unwrap();
+        }
+        let resumed = ring.read_from(next.next).unwrap();
+        assert_eq!(resumed.lines.last().unwrap(), "line 19");
+        assert!(resumed.lines.len() < 20);
+    }
+
+    #[test]
+    fn test_recreated_with_other_capacity() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("ring.log");
+        LogRing::create(&path, 64).unwrap().append("old").unwrap();
+
+        let ring = LogRing::create(&path, 128).unwrap();
+        assert!(ring.tail(10).unwrap().is_empty());
+        assert!(LogRing::create(&path, 0).is_err());
+
+        fs::write(temp.path().join("other.log"), "not a ring").unwrap();
+        assert!(LogRing::open(&temp.path().join("other.log")).is_err());
+    }
+}
@@ -1,26 +1,61 @@
 //! xcargo CLI entry point
 
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, Args, CommandFactory, Parser, Subcommand};
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+use clap_complete::env::Shells;
+use clap_complete::CompleteEnv;
 use inquire::{Confirm, InquireError, MultiSelect, Select};
-use std::path::Path;
-use xcargo::build::{BuildOptions, Builder, CargoOperation};
-use xcargo::config::Config;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use xcargo::build::artifacts::{self, ArtifactManifest};
+use xcargo::build::{clean, embedded, BuildOptions, Builder, CargoOperation, TargetSelection};
+use xcargo::cache::BuildCache;
+use xcargo::cancel::CancellationToken;
+use xcargo::config::{
+    CargoTargetConfig, Config, ConfigDiscovery, ConfigFormat, ConfigSource, CrossImport,
+    CROSS_FILE_NAME, MAX_RETRIES,
+};
+#[cfg(feature = "container")]
+use xcargo::container::{publish, registry, ContainerRuntime, RuntimeType};
+use xcargo::error::explain::{self, Explanation};
 use xcargo::error::Error;
-use xcargo::output::{helpers, tips};
-use xcargo::target::Target;
+use xcargo::installs::InstallLedger;
+use xcargo::logs::LogRing;
+use xcargo::output::progress::{format_bytes, format_timestamp};
+use xcargo::output::{helpers, tips, OutputMode};
+#[cfg(feature = "dist")]
+use xcargo::sign::{Credentials, Notarizer};
+use xcargo::target::search::{Pattern, TargetQuery};
+use xcargo::target::{RustTier, Target};
 use xcargo::toolchain::ToolchainManager;
 
 /// Result type for main using xcargo's error type
 type Result<T> = std::result::Result<T, Error>;
 
+/// Crates listed per binary by `xcargo size --by-crate` without --verbose
+const CRATE_LIMIT: usize = 10;
+
+/// Crates listed by `xcargo build --timings` without --verbose
+const TIMINGS_LIMIT: usize = 15;
+
+/// How often `xcargo logs --follow` checks for new output
+const LOG_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(200);
+
 /// Convert InquireError to our Error type
 fn prompt_err(e: InquireError) -> Error {
     Error::Prompt(e.to_string())
 }
 
-/// Print error with suggestion and hint, then exit with proper code
+/// Print error with its code, suggestion and hint, then exit with proper code
 fn exit_with_error(error: &Error) -> ! {
-    helpers::error(format!("{}", error));
+    let code = error.code();
+    tracing::debug!(
+        code = code.as_str(),
+        exit_code = error.exit_code(),
+        "{error}"
+    );
+    helpers::error(format!("[{code}] {}", error.localized()));
 
     if let Some(hint) = error.hint() {
         helpers::hint(hint);
@@ -42,22 +77,39 @@ struct Cli {
     #[command(subcommand)]
     command: Commands,
 
-    /// Enable verbose output
-    #[arg(short, long, global = true)]
-    verbose: bool,
+    /// Verbose output; repeat for more detail (-vv also cargo --verbose and trace logs, -vvv also command environments and dependency logs)
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Also write all log events, including cargo's output per target, to a JSON lines file
+    #[arg(long, global = true, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+
+    /// Record build inputs and executed commands to a replayable trace
+    #[arg(long, global = true, value_name = "TRACE")]
+    record: Option<PathBuf>,
+
+    /// Apply a [profiles.<NAME>] section of xcargo.toml (also XCARGO_PROFILE)
+    #[arg(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Output style; plain-accessible drops emoji, spinners and color for screen readers
+    #[arg(
+        long,
+        global = true,
+        value_name = "MODE",
+        default_value = "default",
+        value_parser = ["default", "plain-accessible"]
+    )]
+    output: String,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Build for target platform(s)
     Build {
-        /// Target triple (e.g., x86_64-pc-windows-gnu)
-        #[arg(short, long)]
-        target: Option<String>,
-
-        /// Build for all configured targets
-        #[arg(long, conflicts_with = "target")]
-        all: bool,
+        #[command(flatten)]
+        build: BuildArgs,
 
         /// Build in release mode
         #[arg(short, long)]
@@ -67,17 +119,52 @@ enum Commands {
         #[arg(long)]
         container: bool,
 
-        /// Force using Zig for cross-compilation
-        #[arg(long, conflicts_with = "no_zig")]
-        zig: bool,
+        /// glibc version for Zig to link *-linux-gnu targets against (e.g., 2.17)
+        #[arg(long, value_name = "VERSION", conflicts_with = "no_zig")]
+        glibc: Option<String>,
 
-        /// Disable Zig cross-compilation (use native toolchain or container)
-        #[arg(long, conflicts_with = "zig")]
-        no_zig: bool,
+        /// Link binaries statically (crt-static; a musl C compiler or Zig for *-musl)
+        #[arg(long = "static")]
+        static_link: bool,
 
-        /// Toolchain to use (e.g., stable, nightly)
+        /// Rustflags for this build, merged over configured ones (repeatable)
+        #[arg(long, value_name = "FLAGS", allow_hyphen_values = true)]
+        rustflags: Vec<String>,
+
+        #[command(flatten)]
+        selection: SelectionArgs,
+
+        /// Retry each failed target up to N times (overrides build.retries)
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(..=i64::from(MAX_RETRIES)))]
+        retries: Option<u32>,
+
+        /// Stop a target's build after this long, e.g. 15m or 1h30m
+        /// (overrides build.timeout)
+        #[arg(long, value_name = "DURATION", value_parser = parse_timeout)]
+        timeout: Option<Duration>,
+
+        /// Keep compiling a target's other crates after one fails (cargo's
+        /// --keep-going); with --all or --group the remaining targets are
+        /// built either way. Other commands take it after `--`
         #[arg(long)]
-        toolchain: Option<String>,
+        keep_going: bool,
+
+        /// Skip targets the build cache records as already built with the
+        /// same settings and sources (with --all or --group)
+        #[arg(long)]
+        resume: bool,
+
+        /// Report each crate's compile time per target (needs nightly cargo)
+        #[arg(long, conflicts_with = "explain")]
+        timings: bool,
+
+        /// Explain native dependency and toolchain decisions without building
+        #[arg(long)]
+        explain: bool,
+
+        /// Show why each target gets the native, Zig or container strategy, without building
+        #[arg(long, conflicts_with = "explain")]
+        explain_strategy: bool,
 
         /// Additional cargo arguments
         #[arg(last = true)]
@@ -85,11 +172,24 @@ enum Commands {
     },
 
     /// Manage targets
+    #[command(visible_alias = "targets")]
     Target {
         #[command(subcommand)]
         action: TargetAction,
     },
 
+    /// Manage toolchains
+    Toolchain {
+        #[command(subcommand)]
+        action: ToolchainAction,
+    },
+
+    /// Install the cross GCC a target links with (same as toolchain provision)
+    Provision {
+        #[command(flatten)]
+        args: ProvisionArgs,
+    },
+
     /// Initialize xcargo for a project
     Init {
         /// Interactive setup wizard
@@ -98,105 +198,2548 @@ enum Commands {
     },
 
     /// Display configuration
+    #[command(args_conflicts_with_subcommands = true)]
     Config {
+        #[command(subcommand)]
+        action: Option<ConfigAction>,
+
         /// Show default config
         #[arg(long)]
         default: bool,
+
+        /// List every effective setting with where it came from: the
+        /// defaults, ~/.xcargo/config.toml, xcargo.toml or a profile
+        #[arg(long, conflicts_with = "default")]
+        show_origin: bool,
+
+        /// Output format: toml, yaml or json
+        #[arg(long, default_value = "toml")]
+        format: String,
+    },
+
+    /// Move another tool's configuration over to xcargo.toml
+    Migrate {
+        #[command(subcommand)]
+        from: MigrateSource,
     },
 
     /// Check target(s) for errors without building
     Check {
-        /// Target triple (e.g., x86_64-pc-windows-gnu)
-        #[arg(short, long)]
-        target: Option<String>,
+        #[command(flatten)]
+        build: BuildArgs,
 
-        /// Check all configured targets
-        #[arg(long, conflicts_with = "target")]
-        all: bool,
+        /// Additional cargo arguments
+        #[arg(last = true)]
+        cargo_args: Vec<String>,
+    },
+
+    /// Run tests for target(s)
+    Test {
+        #[command(flatten)]
+        build: BuildArgs,
 
-        /// Force using Zig for cross-compilation
-        #[arg(long, conflicts_with = "no_zig")]
-        zig: bool,
+        /// Release mode
+        #[arg(short, long)]
+        release: bool,
 
-        /// Disable Zig cross-compilation
-        #[arg(long, conflicts_with = "zig")]
-        no_zig: bool,
+        /// Link binaries statically (crt-static; a musl C compiler or Zig for *-musl)
+        #[arg(long = "static")]
+        static_link: bool,
 
-        /// Toolchain to use (e.g., stable, nightly)
-        #[arg(long)]
-        toolchain: Option<String>,
+        /// Rustflags for this build, merged over configured ones (repeatable)
+        #[arg(long, value_name = "FLAGS", allow_hyphen_values = true)]
+        rustflags: Vec<String>,
 
         /// Additional cargo arguments
         #[arg(last = true)]
         cargo_args: Vec<String>,
     },
 
-    /// Run tests for target(s)
-    Test {
-        /// Target triple (e.g., x86_64-pc-windows-gnu)
+    /// Lint target(s) with clippy
+    Clippy {
+        #[command(flatten)]
+        build: BuildArgs,
+
+        /// Release mode
         #[arg(short, long)]
-        target: Option<String>,
+        release: bool,
 
-        /// Test all configured targets
-        #[arg(long, conflicts_with = "target")]
-        all: bool,
+        /// Additional cargo arguments; lint flags go after another `--`
+        /// (e.g., `-- --all-targets -- -D warnings`)
+        #[arg(last = true)]
+        cargo_args: Vec<String>,
+    },
+
+    /// Build documentation for target(s)
+    Doc {
+        #[command(flatten)]
+        build: BuildArgs,
 
         /// Release mode
         #[arg(short, long)]
         release: bool,
 
-        /// Force using Zig for cross-compilation
-        #[arg(long, conflicts_with = "no_zig")]
-        zig: bool,
+        /// Don't document dependencies
+        #[arg(long)]
+        no_deps: bool,
+
+        /// Additional cargo arguments
+        #[arg(last = true)]
+        cargo_args: Vec<String>,
+    },
+
+    /// Run benchmarks for target(s)
+    Bench {
+        #[command(flatten)]
+        build: BuildArgs,
 
-        /// Disable Zig cross-compilation
-        #[arg(long, conflicts_with = "zig")]
-        no_zig: bool,
+        /// Link binaries statically (crt-static; a musl C compiler or Zig for *-musl)
+        #[arg(long = "static")]
+        static_link: bool,
 
-        /// Toolchain to use (e.g., stable, nightly)
+        /// Rustflags for this build, merged over configured ones (repeatable)
+        #[arg(long, value_name = "FLAGS", allow_hyphen_values = true)]
+        rustflags: Vec<String>,
+
+        /// Only compare the stored results of earlier runs, without running
         #[arg(long)]
-        toolchain: Option<String>,
+        report: bool,
 
-        /// Additional cargo arguments
+        /// Additional cargo arguments (e.g., `-- --no-run` to only build
+        /// benchmarks)
+        #[arg(last = true)]
+        cargo_args: Vec<String>,
+    },
+
+    /// Measure the test coverage of target(s)
+    Coverage {
+        #[command(flatten)]
+        build: BuildArgs,
+
+        /// Release mode
+        #[arg(short, long)]
+        release: bool,
+
+        /// Also write an HTML report
+        #[arg(long)]
+        html: bool,
+
+        /// Rustflags for this build, merged over configured ones (repeatable)
+        #[arg(long, value_name = "FLAGS", allow_hyphen_values = true)]
+        rustflags: Vec<String>,
+
+        /// Additional cargo arguments (e.g., `-- --lib`)
         #[arg(last = true)]
         cargo_args: Vec<String>,
     },
 
+    /// Inspect and manage the build cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+
+    /// Remove per-target build output, the build cache or container images
+    ///
+    /// Without flags, removes target/<triple> for every cross target.
+    Clean {
+        /// Only remove target/<triple> for these targets
+        #[arg(short, long, add = ArgValueCandidates::new(installed_targets))]
+        target: Vec<String>,
+
+        /// Remove xcargo's build cache
+        #[arg(long)]
+        cache: bool,
+
+        /// Remove container images pulled for cross builds
+        #[arg(long)]
+        containers: bool,
+
+        /// Remove all target directories, the build cache and container images
+        #[arg(long)]
+        all: bool,
+
+        /// Run `cargo clean` with these arguments for each target instead
+        /// of removing target/<triple> (e.g., `-- -p my-crate`)
+        #[arg(last = true, conflicts_with_all = ["cache", "containers", "all"])]
+        cargo_args: Vec<String>,
+    },
+
+    /// Remove the rustup targets, toolchains, container images and
+    /// downloads that xcargo installed
+    ///
+    /// Only what xcargo added itself is removed; anything that was already
+    /// there before xcargo needed it is left alone.
+    Uninstall {
+        /// Only remove what was installed for this target
+        #[arg(short, long, add = ArgValueCandidates::new(installed_targets))]
+        target: Option<String>,
+
+        /// List what would be removed without removing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Check built binaries' format, architecture, linkage and dependencies
+    Verify {
+        /// Target triple the binaries were built for
+        #[arg(short, long, add = ArgValueCandidates::new(installed_targets))]
+        target: String,
+
+        /// Verify release binaries instead of debug ones
+        #[arg(short, long)]
+        release: bool,
+    },
+
+    /// Compare binary sizes across targets and against the previous build
+    ///
+    /// Targets without built binaries are built first. Defaults to the
+    /// configured default targets, or the host.
+    Size {
+        /// Target triples to compare (repeatable)
+        #[arg(short, long, add = ArgValueCandidates::new(installed_targets))]
+        target: Vec<String>,
+
+        /// Compare the targets of a group from [target-groups]
+        #[arg(long, conflicts_with = "target", add = ArgValueCandidates::new(target_groups))]
+        group: Option<String>,
+
+        /// Measure release binaries instead of debug ones
+        #[arg(short, long)]
+        release: bool,
+
+        /// Build every target before measuring, even if binaries exist
+        #[arg(long, conflicts_with = "no_build")]
+        build: bool,
+
+        /// Only measure binaries that are already built
+        #[arg(long)]
+        no_build: bool,
+
+        /// Show which crates contribute the most code to each binary
+        #[arg(long)]
+        by_crate: bool,
+    },
+
+    /// Show where build time goes: each target's phases and slowest crates
+    ///
+    /// Every build records the time its target spent preparing the
+    /// toolchain, fetching dependencies, compiling and post-processing.
+    /// Crates are listed for targets built with --timings.
+    Timings {
+        /// Only show these targets (repeatable)
+        #[arg(short, long, add = ArgValueCandidates::new(installed_targets))]
+        target: Vec<String>,
+
+        /// Also write an HTML report, by default to
+        /// target/xcargo/timings/report.html
+        #[arg(long, value_name = "FILE", num_args = 0..=1)]
+        html: Option<Option<PathBuf>>,
+    },
+
+    /// Show earlier builds and how their times develop
+    ///
+    /// Every build is recorded in ~/.xcargo/history.jsonl with its outcome,
+    /// duration, strategy and whether --resume found it in the build cache.
+    /// Lists the current project's builds and the average time of each
+    /// target over its last runs, compared with the runs before.
+    History {
+        /// Only show these targets (repeatable)
+        #[arg(short, long, add = ArgValueCandidates::new(installed_targets))]
+        target: Vec<String>,
+
+        /// Only show this operation (e.g., build, test)
+        #[arg(long)]
+        operation: Option<String>,
+
+        /// Only show builds that failed, were cancelled or timed out
+        #[arg(long)]
+        failed: bool,
+
+        /// Show the builds of every project, not just this one
+        #[arg(long)]
+        all_projects: bool,
+
+        /// Number of builds listed, and of runs averaged per target
+        #[arg(short = 'n', long, default_value_t = 10, value_name = "N")]
+        last: usize,
+
+        /// Print the matching builds as JSON lines
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Build firmware for an embedded target and flash it to a device
+    ///
+    /// Runs `[embedded] flash`, probe-rs by default.
+    Flash {
+        /// Target triple (e.g., thumbv7em-none-eabihf)
+        #[arg(short, long, add = ArgValueCandidates::new(installed_targets))]
+        target: String,
+
+        /// Chip to flash (overrides [targets.<triple>] chip)
+        #[arg(long)]
+        chip: Option<String>,
+
+        /// Flash a release build
+        #[arg(short, long)]
+        release: bool,
+
+        /// Binary to flash, when there are several
+        #[arg(long, value_name = "NAME")]
+        bin: Option<String>,
+
+        /// Flash what is already built instead of building first
+        #[arg(long)]
+        no_build: bool,
+    },
+
+    /// Package built Linux binaries as container images
+    #[cfg(feature = "container")]
+    Docker {
+        #[command(subcommand)]
+        action: DockerAction,
+    },
+
+    /// Manage the images container builds run in
+    #[cfg(feature = "container")]
+    Container {
+        #[command(subcommand)]
+        action: ContainerAction,
+    },
+
+    /// Show the build output the daemon logged for a target
+    ///
+    /// Defaults to the target whose log was written most recently.
+    Logs {
+        /// Target triple whose log to show
+        #[arg(short, long, add = ArgValueCandidates::new(installed_targets))]
+        target: Option<String>,
+
+        /// Number of lines to show
+        #[arg(short = 'n', long, default_value_t = 50)]
+        lines: usize,
+
+        /// Keep printing new output as it is logged
+        #[arg(short, long)]
+        follow: bool,
+    },
+
+    /// Notarize signed macOS artifacts with Apple and staple the ticket
+    ///
+    /// Without paths, notarizes the macOS artifacts in the configured
+    /// artifact directory.
+    #[cfg(feature = "dist")]
+    Notarize {
+        /// Signed artifacts to notarize (.app, .dmg, .pkg, .zip or binaries)
+        paths: Vec<PathBuf>,
+    },
+
+    /// Re-run the commands of a trace recorded with --record
+    Replay {
+        /// Trace file
+        trace: PathBuf,
+
+        /// Show the recorded commands without running them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Explain a cross-compilation failure and how to fix it
+    ///
+    /// Takes a code printed after a failed build (e.g., XC001), or error
+    /// output to look for known failures in (`-` reads it from stdin).
+    /// Without an argument, lists the known failures.
+    Explain {
+        /// Failure code, error output, or `-`
+        query: Option<String>,
+    },
+
     /// Check system setup and diagnose issues
-    Doctor,
+    Doctor {
+        /// Print the report as JSON (exits non-zero on critical failures)
+        #[arg(long)]
+        json: bool,
+
+        /// Also write the report to a file (YAML for .yaml/.yml, JSON otherwise)
+        #[arg(long, value_name = "PATH")]
+        report_file: Option<PathBuf>,
+
+        /// Skip network probes and report what builds can do offline
+        /// (default when CARGO_NET_OFFLINE is set)
+        #[arg(long)]
+        offline: bool,
+    },
+
+    /// Gather diagnostics into a tarball to attach to a bug report
+    ///
+    /// Includes the doctor report, the configuration with secrets redacted,
+    /// tool versions, recent build logs and the last failed cargo invocation.
+    Report {
+        /// Where to write the bundle (default: xcargo-report-<time>.tar.gz)
+        path: Option<PathBuf>,
+    },
+
+    /// Run a JSON-RPC build server for editor integrations
+    #[cfg(feature = "remote")]
+    Daemon {
+        /// Listen on a Unix socket instead of stdio
+        #[arg(long)]
+        socket: Option<PathBuf>,
+    },
+
+    /// Print a shell completion script
+    ///
+    /// Load it from your shell profile, e.g. `source <(xcargo completions bash)`.
+    /// Completions are computed by xcargo itself, so `--target` offers the
+    /// currently installed target triples.
+    Completions {
+        /// Shell to generate completions for
+        #[arg(value_parser = ["bash", "zsh", "fish", "powershell"])]
+        shell: String,
+    },
+
+    /// Generate man pages
+    Man {
+        /// Write a page per subcommand into this directory instead of
+        /// printing the main page
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+    },
 
     /// Show version information
     Version,
 }
 
+/// Target, toolchain and feature selection shared by the cargo commands
+#[derive(Args)]
+struct BuildArgs {
+    /// Target triple (e.g., x86_64-pc-windows-gnu)
+    #[arg(short, long, add = ArgValueCandidates::new(installed_targets))]
+    target: Option<String>,
+
+    /// Run for all configured targets
+    #[arg(long, conflicts_with = "target")]
+    all: bool,
+
+    /// Run for the targets of a group from [target-groups]
+    #[arg(long, conflicts_with_all = ["target", "all"], add = ArgValueCandidates::new(target_groups))]
+    group: Option<String>,
+
+    /// Force using Zig for cross-compilation
+    #[arg(long, conflicts_with = "no_zig")]
+    zig: bool,
+
+    /// Disable Zig cross-compilation (use native toolchain or container)
+    #[arg(long, conflicts_with = "zig")]
+    no_zig: bool,
+
+    /// Toolchain to use (e.g., stable, nightly)
+    #[arg(long)]
+    toolchain: Option<String>,
+
+    /// Never use the network: no target installs, image pulls or downloads
+    #[arg(long)]
+    offline: bool,
+
+    /// Never install toolchains, targets or components; fail when one is missing
+    #[arg(long)]
+    no_install: bool,
+
+    #[command(flatten)]
+    features: FeatureArgs,
+}
+
+impl BuildArgs {
+    /// Whether the command line alone says what to build, see
+    /// [`project_builder`]
+    fn standalone(&self) -> bool {
+        self.target.is_some() && !self.all && self.group.is_none()
+    }
+
+    /// Options for `operation` with these arguments; the caller fills in
+    /// the flags only some commands take
+    fn to_options(
+        &self,
+        operation: CargoOperation,
+        verbose: u8,
+        cargo_args: Vec<String>,
+    ) -> BuildOptions {
+        BuildOptions {
+            target: self.target.clone(),
+            cargo_args,
+            toolchain: self.toolchain.clone(),
+            verbose,
+            // None = auto
+            use_zig: if self.zig {
+                Some(true)
+            } else if self.no_zig {
+                Some(false)
+            } else {
+                None
+            },
+            operation,
+            features: self.features.features.clone(),
+            no_default_features: self.features.no_default_features,
+            all_features: self.features.all_features,
+            offline: self.offline,
+            no_install: self.no_install,
+            ..BuildOptions::default()
+        }
+    }
+}
+
+/// Cargo feature selection shared by the cargo commands
+#[derive(Args)]
+struct FeatureArgs {
+    /// Comma-separated list of features to activate
+    #[arg(short = 'F', long, value_delimiter = ',')]
+    features: Vec<String>,
+
+    /// Do not activate the `default` feature
+    #[arg(long)]
+    no_default_features: bool,
+
+    /// Activate all available features
+    #[arg(long, conflicts_with = "features")]
+    all_features: bool,
+}
+
+/// Binary, example and library selection for build
+#[derive(Args)]
+struct SelectionArgs {
+    /// Build only the named binary (repeatable)
+    #[arg(long = "bin", value_name = "NAME")]
+    bins: Vec<String>,
+
+    /// Build only the named example (repeatable)
+    #[arg(long = "example", value_name = "NAME")]
+    examples: Vec<String>,
+
+    /// Build only the library
+    #[arg(long)]
+    lib: bool,
+}
+
+#[cfg(feature = "container")]
+#[derive(Subcommand)]
+enum DockerAction {
+    /// Build an image holding a binary, multi-arch for several targets
+    ///
+    /// Defaults to the Linux targets among the configured default targets,
+    /// or the host. Uses [container] runtime; a multi-arch image that
+    /// isn't pushed is written to target/docker/<name>.oci.tar.
+    Build {
+        #[command(flatten)]
+        image: DockerImageArgs,
+
+        /// Push the image to its registry
+        #[arg(long)]
+        push: bool,
+    },
+
+    /// Build and push an image to its registry
+    ///
+    /// Logs in with XCARGO_REGISTRY_USER and XCARGO_REGISTRY_PASSWORD when
+    /// they are set, and otherwise uses the runtime's stored credentials.
+    Push {
+        #[command(flatten)]
+        image: DockerImageArgs,
+
+        /// Push one image per target, tagged <tag>-<arch>, and a manifest
+        /// list combining them under the tags
+        #[arg(long)]
+        multi_arch: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Check configuration files for unknown keys, unknown targets and
+    /// conflicting settings, listing every problem with its location
+    ///
+    /// Without files, checks the project's configuration merged over
+    /// ~/.xcargo/config.toml. Exits non-zero if anything is wrong, so it
+    /// can run as a pre-commit hook.
+    Validate {
+        /// Files to check on their own instead
+        files: Vec<PathBuf>,
+    },
+
+    /// Print a JSON Schema of the configuration format
+    ///
+    /// Save it and point taplo or Even Better TOML at it for completion
+    /// and validation in editors, e.g.
+    /// `xcargo config schema > xcargo.schema.json`.
+    Schema,
+
+    /// Show the linker, runner and rustflags a target builds with
+    ///
+    /// Merges xcargo.toml over the [target.<triple>] tables of
+    /// .cargo/config.toml, shows where each setting comes from, and warns
+    /// where xcargo.toml overrides cargo's configuration.
+    Effective {
+        /// Target triple
+        #[arg(short, long, add = ArgValueCandidates::new(installed_targets))]
+        target: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum MigrateSource {
+    /// Turn a Cross.toml into an xcargo.toml
+    ///
+    /// Carries over images, host variables passed through and pre-build
+    /// hooks, and lists the settings it could not. A Cross.toml is also
+    /// honored as is, under xcargo.toml, until you remove it.
+    Cross {
+        /// Cross.toml to read; defaults to the project's
+        file: Option<PathBuf>,
+
+        /// Where to write; defaults to xcargo.toml next to the Cross.toml
+        #[arg(long, value_name = "PATH")]
+        to: Option<PathBuf>,
+
+        /// Overwrite an existing xcargo.toml
+        #[arg(long)]
+        force: bool,
+
+        /// Print the configuration instead of writing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[cfg(feature = "container")]
+#[derive(Subcommand)]
+enum ContainerAction {
+    /// Pin build images to the digests their tags point at now
+    ///
+    /// Pulls each target's image and records it in [container.images] in
+    /// xcargo.toml as <image>:<tag>@sha256:<digest>, so builds keep using
+    /// exactly that image until the next update. Defaults to the targets
+    /// already listed there and the default targets.
+    UpdateImages {
+        /// Target triples whose images to update (repeatable)
+        #[arg(short, long, add = ArgValueCandidates::new(installed_targets))]
+        target: Vec<String>,
+    },
+}
+
+#[cfg(feature = "container")]
+#[derive(Args)]
+struct DockerImageArgs {
+    /// Linux target triples to include (repeatable)
+    #[arg(short, long, add = ArgValueCandidates::new(installed_targets))]
+    target: Vec<String>,
+
+    /// Package release binaries
+    #[arg(short, long)]
+    release: bool,
+
+    /// Binary to package, when there are several (overrides [docker] bin)
+    #[arg(long, value_name = "NAME")]
+    bin: Option<String>,
+
+    /// Image tag (repeatable; overrides [docker] tags)
+    #[arg(long = "tag", value_name = "TAG")]
+    tags: Vec<String>,
+
+    /// Base image (overrides [docker] base)
+    #[arg(long, value_name = "IMAGE")]
+    base: Option<String>,
+
+    /// Package what is already built instead of building first
+    #[arg(long)]
+    no_build: bool,
+}
+
+impl From<SelectionArgs> for TargetSelection {
+    fn from(args: SelectionArgs) -> Self {
+        Self {
+            bins: args.bins,
+            examples: args.examples,
+            lib: args.lib,
+            kinds: Vec::new(),
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum TargetAction {
     /// Add a target
     Add {
         /// Target name or triple
+        #[arg(add = ArgValueCandidates::new(available_targets))]
         target: String,
 
-        /// Toolchain to add target to
-        #[arg(long, default_value = "stable")]
-        toolchain: String,
-    },
+        /// Toolchain to add target to
+        #[arg(long, default_value = "stable")]
+        toolchain: String,
+    },
+
+    /// List targets
+    List {
+        /// Show only installed targets
+        #[arg(long)]
+        installed: bool,
+
+        /// Toolchain to list targets for
+        #[arg(long)]
+        toolchain: Option<String>,
+    },
+
+    /// Show target information
+    Info {
+        /// Target triple
+        #[arg(add = ArgValueCandidates::new(installed_targets))]
+        target: String,
+    },
+
+    /// Search the targets rustup knows
+    ///
+    /// Matches part of the triple, or a regular expression with --regex,
+    /// narrowed by --os, --arch and --env, which also take rustc's names
+    /// (e.g., --os macos, --arch arm).
+    Search {
+        /// Part of the triple to look for; every target without one
+        pattern: Option<String>,
+
+        /// Treat the pattern as a regular expression
+        #[arg(long)]
+        regex: bool,
+
+        /// Only targets for this operating system (e.g., linux, windows)
+        #[arg(long)]
+        os: Option<String>,
+
+        /// Only targets for this architecture (e.g., aarch64, x86_64)
+        #[arg(long)]
+        arch: Option<String>,
+
+        /// Only targets with this environment (e.g., gnu, musl, msvc)
+        #[arg(long)]
+        env: Option<String>,
+
+        /// Only installed targets
+        #[arg(long)]
+        installed: bool,
+    },
+
+    /// Manage target aliases
+    Alias {
+        #[command(subcommand)]
+        action: AliasAction,
+    },
+
+    /// Show which targets this machine can build, and how
+    ///
+    /// For each default target (or every target rustup knows with
+    /// --all): whether it is installed, and whether the native toolchain,
+    /// Zig and a container could build it, with the strategy `xcargo build
+    /// --all` would use.
+    Matrix {
+        /// Every target rustup knows instead of the default targets
+        #[arg(long)]
+        all: bool,
+
+        /// Print the matrix as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum AliasAction {
+    /// List built-in and user-defined aliases
+    List,
+
+    /// Define an alias in xcargo.toml
+    Add {
+        /// Alias name (e.g., rpi)
+        name: String,
+
+        /// Target triple the alias stands for
+        #[arg(add = ArgValueCandidates::new(available_targets))]
+        target: String,
+
+        /// Write to the global config (~/.xcargo/config.toml)
+        #[arg(long)]
+        global: bool,
+    },
+
+    /// Remove an alias from xcargo.toml
+    Remove {
+        /// Alias name
+        name: String,
+
+        /// Remove from the global config (~/.xcargo/config.toml)
+        #[arg(long)]
+        global: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ToolchainAction {
+    /// List installed toolchains
+    List,
+
+    /// Install a toolchain (e.g., stable, nightly, 1.80.0)
+    Install {
+        /// Channel or version
+        channel: String,
+    },
+
+    /// Make a toolchain the default
+    Default {
+        /// Channel or version
+        #[arg(add = ArgValueCandidates::new(installed_toolchains))]
+        channel: String,
+    },
+
+    /// Show each toolchain with its targets and components
+    Status,
+
+    /// Manage rustup components (rust-src, llvm-tools, rustfmt, ...)
+    Component {
+        #[command(subcommand)]
+        action: ComponentAction,
+    },
+
+    /// Install the cross GCC a target links with (MinGW-w64, aarch64 GCC)
+    Provision {
+        #[command(flatten)]
+        args: ProvisionArgs,
+    },
+}
+
+/// Target and source of the cross GCC for `provision`
+#[derive(Args)]
+struct ProvisionArgs {
+    /// Target triple or alias (x86_64-pc-windows-gnu, aarch64-unknown-linux-gnu)
+    #[arg(
+        required_unless_present_any = ["update_catalog", "target_flag"],
+        conflicts_with = "target_flag"
+    )]
+    target: Option<String>,
+
+    /// Target triple or alias, as an option
+    #[arg(long = "target", id = "target_flag", value_name = "TARGET", add = ArgValueCandidates::new(installed_targets))]
+    target_flag: Option<String>,
+
+    /// Download a prebuilt toolchain into ~/.xcargo/tools instead of using the package manager
+    #[arg(long)]
+    standalone: bool,
+
+    /// Fetch the current catalog of prebuilt toolchains first
+    #[arg(long)]
+    update_catalog: bool,
+}
+
+#[derive(Subcommand)]
+enum ComponentAction {
+    /// Add components to a toolchain
+    Add {
+        /// Component names (e.g., rust-src llvm-tools)
+        #[arg(required = true)]
+        components: Vec<String>,
+
+        /// Toolchain to add components to
+        #[arg(long, default_value = "stable")]
+        toolchain: String,
+    },
+
+    /// List components of a toolchain
+    List {
+        /// Show only installed components
+        #[arg(long)]
+        installed: bool,
+
+        /// Toolchain to list components for
+        #[arg(long, default_value = "stable")]
+        toolchain: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Show cache statistics
+    Stats {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Clear the cache
+    Clear {
+        /// Only clear the entry for this target
+        #[arg(long, add = ArgValueCandidates::new(installed_targets))]
+        target: Option<String>,
+    },
+
+    /// Show the cache entry for a target
+    Show {
+        /// Target triple
+        #[arg(add = ArgValueCandidates::new(installed_targets))]
+        target: String,
+    },
+}
+
+/// Parse `--timeout` values such as 90s, 15m or 1h30m
+fn parse_timeout(value: &str) -> std::result::Result<Duration, String> {
+    xcargo::cancel::parse_duration(value)
+        .ok_or_else(|| format!("'{value}' is not a duration (e.g., 90s, 15m, 1h30m)"))
+}
+
+/// Installed target triples, offered when completing `--target`
+fn installed_targets() -> Vec<CompletionCandidate> {
+    Target::detect_installed()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|target| CompletionCandidate::new(target.triple))
+        .collect()
+}
+
+/// Installed toolchains, offered when completing `toolchain default`
+fn installed_toolchains() -> Vec<CompletionCandidate> {
+    ToolchainManager::new()
+        .and_then(|manager| manager.list_toolchains())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|toolchain| CompletionCandidate::new(toolchain.name))
+        .collect()
+}
+
+/// Target groups from xcargo.toml, offered when completing `--group`
+fn target_groups() -> Vec<CompletionCandidate> {
+    let Ok(Some((config, _))) = Config::discover() else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = config.target_groups.into_keys().collect();
+    names.sort();
+    names.into_iter().map(CompletionCandidate::new).collect()
+}
+
+/// Targets rustup can install, offered when completing `target add`
+fn available_targets() -> Vec<CompletionCandidate> {
+    Target::list_available()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|target| CompletionCandidate::new(target.triple))
+        .collect()
+}
+
+/// Print the completion registration script for `shell`
+fn print_completions(shell: &str) -> Result<()> {
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(shell)
+        .ok_or_else(|| Error::Config(format!("Unsupported shell: {shell}")))?;
+    // Call back into the binary that generated the script, like `COMPLETE=<shell> xcargo`
+    let program = std::env::args()
+        .next()
+        .unwrap_or_else(|| "xcargo".to_string());
+
+    let mut stdout = std::io::stdout().lock();
+    completer.write_registration("COMPLETE", "xcargo", "xcargo", &program, &mut stdout)?;
+    Ok(())
+}
+
+/// Print the main man page, or write pages for all subcommands to `out_dir`
+fn print_man(out_dir: Option<&Path>) -> Result<()> {
+    let Some(dir) = out_dir else {
+        clap_mangen::Man::new(Cli::command()).render(&mut std::io::stdout().lock())?;
+        return Ok(());
+    };
+
+    std::fs::create_dir_all(dir)?;
+    clap_mangen::generate_to(Cli::command(), dir)?;
+    helpers::success(format!("Man pages written to {}", dir.display()));
+    Ok(())
+}
+
+/// Print the last `lines` lines of a target's log, then follow it
+fn run_logs(target: Option<&str>, lines: usize, follow: bool) -> Result<()> {
+    let path = match target {
+        Some(target) => {
+            let config = Config::effective()?;
+            let triple = Target::resolve_alias_with(target, &config.resolved_aliases())?;
+            xcargo::logs::log_path(&triple)?
+        }
+        None => match xcargo::logs::list()?.into_iter().next() {
+            Some((_, path)) => path,
+            None => {
+                helpers::info("No build logs yet");
+                helpers::tip("Logs are written by builds started through 'xcargo daemon'");
+                return Ok(());
+            }
+        },
+    };
+    if !path.exists() {
+        helpers::info(format!("No build log at {}", path.display()));
+        return Ok(());
+    }
+
+    let ring = LogRing::open(&path)?;
+    let chunk = ring.read_from(0)?;
+    for line in &chunk.lines[chunk.lines.len().saturating_sub(lines)..] {
+        println!("{line}");
+    }
+    if !follow {
+        return Ok(());
+    }
+
+    let mut next = chunk.next;
+    loop {
+        std::thread::sleep(LOG_POLL_INTERVAL);
+        // The daemon may have recreated the ring; reopen to see its contents
+        let chunk = LogRing::open(&path)?.read_from(next)?;
+        for line in &chunk.lines {
+            println!("{line}");
+        }
+        next = chunk.next;
+    }
+}
+
+/// Notarize `paths`, or the macOS artifacts in the artifact directory
+#[cfg(feature = "dist")]
+fn run_notarize(paths: Vec<PathBuf>) -> Result<()> {
+    helpers::section("Notarize");
+
+    let config = Config::effective()?;
+    let paths = if paths.is_empty() {
+        let Some(dir) = &config.artifacts.dir else {
+            return Err(Error::Config(
+                "No artifacts to notarize: pass paths or set [artifacts] dir".to_string(),
+            ));
+        };
+        let dir = Path::new(dir);
+        let manifest = ArtifactManifest::load(dir);
+        xcargo::sign::macos_artifacts(dir, &manifest)
+    } else {
+        paths
+    };
+    if paths.is_empty() {
+        helpers::info("No macOS artifacts to notarize");
+        return Ok(());
+    }
+
+    Notarizer::check_available()?;
+    let credentials = Credentials::resolve(&config.sign.notarize, |key| std::env::var(key).ok())?;
+    let notarizer = Notarizer::new(&config.sign.notarize, credentials);
+    for path in &paths {
+        notarizer.notarize(path)?;
+    }
+    Ok(())
+}
+
+/// Write a support bundle for a bug report
+fn run_report(path: Option<PathBuf>) -> Result<()> {
+    helpers::progress("Gathering diagnostics...");
+    let path = path.unwrap_or_else(|| {
+        let time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        PathBuf::from(format!("xcargo-report-{time}.tar.gz"))
+    });
+
+    let bundle = xcargo::support::create(&path)?;
+    helpers::success(format!(
+        "Support bundle written to {}",
+        bundle.path.display()
+    ));
+    for file in &bundle.files {
+        println!("  {file}");
+    }
+    for skipped in &bundle.skipped {
+        helpers::info(format!("Not included: {skipped}"));
+    }
+    helpers::tip("Look through the bundle before attaching it; secrets are redacted by name only");
+    Ok(())
+}
+
+/// Verify the binaries built for a target
+fn run_verify(target: &str, release: bool, verbose: bool) -> Result<()> {
+    let config = Config::effective()?;
+    let triple = Target::resolve_alias_with(target, &config.resolved_aliases())?;
+    let target = Target::from_triple(&triple)?;
+    let profile = if release { "release" } else { "debug" };
+
+    helpers::section(format!("Verifying {} ({profile})", target.triple));
+    let verifications = xcargo::verify::verify_target(&target, profile, &config)?;
+    if verifications.is_empty() {
+        helpers::warning(format!(
+            "No binaries found in target/{}/{profile}/",
+            target.triple
+        ));
+        helpers::tip(format!(
+            "Build them first: xcargo build --target {}{}",
+            target.triple,
+            if release { " --release" } else { "" }
+        ));
+        return Ok(());
+    }
+
+    let failed = xcargo::verify::print_report(&verifications, verbose);
+    if failed > 0 {
+        return Err(Error::Build(format!(
+            "{failed} of {} binary(ies) failed verification",
+            verifications.len()
+        )));
+    }
+    helpers::success(format!("{} binary(ies) verified", verifications.len()));
+    Ok(())
+}
+
+/// Build a binary for an embedded target and run the flash command on it
+fn run_flash(
+    target: &str,
+    chip: Option<&str>,
+    release: bool,
+    bin: Option<&str>,
+    no_build: bool,
+    verbose: u8,
+) -> Result<()> {
+    let config = Config::effective()?;
+    let triple = Target::resolve_alias_with(target, &config.resolved_aliases())?;
+    let target = Target::from_triple(&triple)?;
+    let profile = if release { "release" } else { "debug" };
+
+    if !no_build {
+        let options = BuildOptions {
+            target: Some(triple.clone()),
+            release,
+            verbose,
+            selection: TargetSelection {
+                bins: bin.map(ToString::to_string).into_iter().collect(),
+                ..TargetSelection::default()
+            },
+            ..BuildOptions::default()
+        };
+        project_builder(false)?.build(&options)?;
+    }
+
+    let mut binaries: Vec<PathBuf> = artifacts::built_binaries(&target, profile)?
+        .into_iter()
+        .filter(|path| bin.map_or(true, |bin| path.file_stem().is_some_and(|stem| stem == bin)))
+        .collect();
+    let elf = match binaries.len() {
+        0 => {
+            return Err(Error::Build(format!(
+                "No {} binary found in target/{triple}/{profile}/",
+                bin.unwrap_or("firmware")
+            )))
+        }
+        1 => binaries.remove(0),
+        _ => {
+            let names: Vec<String> = binaries
+                .iter()
+                .filter_map(|path| path.file_stem())
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .collect();
+            return Err(Error::Config(format!(
+                "Several binaries were built ({}); choose one with --bin",
+                names.join(", ")
+            )));
+        }
+    };
+
+    let chip = chip.map(ToString::to_string).or_else(|| {
+        config
+            .get_target_config(&triple)
+            .and_then(|target_config| target_config.chip.clone())
+    });
+    let template = &config.embedded.flash;
+    let command = embedded::flash_command(template, &elf, chip.as_deref(), &triple)?;
+
+    let formats = embedded::formats_used(template);
+    if !formats.is_empty() {
+        let objcopy = embedded::find_objcopy()?;
+        let family = config
+            .embedded
+            .uf2_family
+            .as_deref()
+            .map(embedded::uf2_family)
+            .transpose()?;
+        for format in formats {
+            embedded::write_image(&objcopy, &elf, format, family)?;
+        }
+    }
+
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| Error::Config("[embedded] flash is empty".to_string()))?;
+    helpers::section(format!("Flashing {}", elf.display()));
+    if verbose > 0 {
+        helpers::info(format!("Running: {}", command.join(" ")));
+    }
+    let status = std::process::Command::new(program)
+        .args(args)
+        .status()
+        .map_err(|e| Error::Toolchain(format!("Failed to run {program}: {e}")))?;
+    if !status.success() {
+        return Err(Error::Build(match status.code() {
+            Some(code) => format!("{program} exited with code {code}"),
+            None => format!("{program} was terminated"),
+        }));
+    }
+    helpers::success(format!("Flashed {} with {program}", elf.display()));
+    Ok(())
+}
+
+/// The image of a binary built for one or more Linux targets, building
+/// them first unless `--no-build` is given
+#[cfg(feature = "container")]
+fn docker_image(
+    config: &Config,
+    args: &DockerImageArgs,
+    push: bool,
+    verbose: u8,
+) -> Result<publish::ImageBuild> {
+    let profile = if args.release { "release" } else { "debug" };
+    let aliases = config.resolved_aliases();
+    let mut targets = Vec::new();
+    for triple in &args.target {
+        targets.push(Target::from_triple(&Target::resolve_alias_with(
+            triple, &aliases,
+        )?)?);
+    }
+    if targets.is_empty() {
+        for triple in &config.targets.default {
+            let target = Target::from_triple(&Target::resolve_alias_with(triple, &aliases)?)?;
+            if target.os == "linux" {
+                targets.push(target);
+            }
+        }
+    }
+    if targets.is_empty() {
+        let host = Target::detect_host()?;
+        if host.os != "linux" {
+            return Err(Error::Config(
+                "No Linux targets to package; pass one with --target".to_string(),
+            ));
+        }
+        targets.push(host);
+    }
+
+    let bin = args.bin.as_deref().or(config.docker.bin.as_deref());
+    let base = args.base.clone().unwrap_or(config.docker.base.clone());
+    if !args.no_build {
+        let builder = project_builder(false)?;
+        for target in &targets {
+            publish::platform(target)?;
+            let options = BuildOptions {
+                target: Some(target.triple.clone()),
+                release: args.release,
+                verbose,
+                selection: TargetSelection {
+                    bins: bin.map(ToString::to_string).into_iter().collect(),
+                    ..TargetSelection::default()
+                },
+                ..BuildOptions::default()
+            };
+            builder.build(&options)?;
+        }
+    }
+
+    let mut binaries = Vec::new();
+    let mut package = String::new();
+    for target in &targets {
+        let platform = publish::platform(target)?;
+        let (binary, owner) = publish::find_binary(target, profile, bin)?;
+        if base == publish::SCRATCH && !publish::runs_on_scratch(target) {
+            helpers::warning(format!(
+                "{} links against the C library, which a scratch image lacks; \
+                 build for a musl target or set [docker] base = \"gcr.io/distroless/cc-debian12\"",
+                target.triple
+            ));
+        }
+        binaries.push((platform, binary));
+        package = owner;
+    }
+    let name = binaries[0]
+        .1
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let version = artifacts::workspace_versions()?
+        .remove(&package)
+        .unwrap_or_default();
+    let templates = if args.tags.is_empty() {
+        &config.docker.tags
+    } else {
+        &args.tags
+    };
+    Ok(publish::ImageBuild {
+        base,
+        tags: publish::tags(templates, &name, &version)?,
+        name,
+        version,
+        binaries,
+        push,
+    })
+}
+
+/// The container runtime `[container] runtime` names, or the first one
+/// installed
+#[cfg(feature = "container")]
+fn container_runtime(config: &Config) -> Result<Box<dyn ContainerRuntime>> {
+    let runtime = RuntimeType::from_str(&config.container.runtime).unwrap_or(RuntimeType::Auto);
+    xcargo::container::create_runtime(runtime)
+}
+
+/// Stage `image` in `context` and build it
+#[cfg(feature = "container")]
+fn build_image(
+    runtime: &dyn ContainerRuntime,
+    image: &publish::ImageBuild,
+    context: &Path,
+    archive: &Path,
+    verbose: u8,
+) -> Result<()> {
+    helpers::section(format!("Building image {}", image.tags.join(", ")));
+    if verbose > 0 {
+        helpers::info(format!(
+            "Context: {} ({})",
+            context.display(),
+            runtime.name()
+        ));
+    }
+    image.build(runtime, context, archive)
+}
+
+/// Log the runtime in to the registries of `tags` that credentials are set
+/// for, warning about those it has no credentials for
+#[cfg(feature = "container")]
+fn registry_login(runtime: &dyn ContainerRuntime, tags: &[String]) -> Result<()> {
+    let mut hosts: Vec<&str> = tags.iter().map(|tag| registry::host(tag)).collect();
+    hosts.sort_unstable();
+    hosts.dedup();
+    for host in hosts {
+        if let Some(login) = registry::RegistryLogin::resolve(host, |key| std::env::var(key).ok()) {
+            helpers::progress(format!("Logging in to {host} as {}", login.user));
+            runtime.login(host, &login.user, &login.password)?;
+        } else if !registry::has_stored_credentials(runtime.name(), host) {
+            helpers::warning(format!("{} has no credentials for {host}", runtime.name()));
+            helpers::hint(format!(
+                "Run `{} login {host}` or set {} and {}",
+                runtime.name(),
+                registry::ENV_REGISTRY_USER,
+                registry::ENV_REGISTRY_PASSWORD
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Build a container image from a binary built for one or more Linux targets
+#[cfg(feature = "container")]
+fn run_docker_build(args: &DockerImageArgs, push: bool, verbose: u8) -> Result<()> {
+    let config = Config::effective()?;
+    let runtime = container_runtime(&config)?;
+    let image = docker_image(&config, args, push, verbose)?;
+    let docker_dir = artifacts::workspace_products()?.0.join("docker");
+    let archive = docker_dir.join(format!("{}.oci.tar", image.name));
+    if push {
+        registry_login(runtime.as_ref(), &image.tags)?;
+    }
+    build_image(
+        runtime.as_ref(),
+        &image,
+        &docker_dir.join(&image.name),
+        &archive,
+        verbose,
+    )?;
+
+    helpers::success(format!(
+        "Built {} for {}",
+        image.tags.join(", "),
+        image.platforms().join(", ")
+    ));
+    if image.push {
+        helpers::info("Pushed to the registry");
+    } else if image.is_multi_arch() {
+        helpers::info(format!("Multi-arch image written to {}", archive.display()));
+        helpers::tip("Pass --push to publish it to a registry instead");
+    }
+    Ok(())
+}
+
+/// Push an image, or with `multi_arch` one image per target and a manifest
+/// list combining them
+#[cfg(feature = "container")]
+fn run_docker_push(args: &DockerImageArgs, multi_arch: bool, verbose: u8) -> Result<()> {
+    if !multi_arch {
+        return run_docker_build(args, true, verbose);
+    }
+    let config = Config::effective()?;
+    let runtime = container_runtime(&config)?;
+    let image = docker_image(&config, args, true, verbose)?;
+    let docker_dir = artifacts::workspace_products()?.0.join("docker");
+    registry_login(runtime.as_ref(), &image.tags)?;
+
+    let mut images = Vec::new();
+    for arch_image in image.per_arch() {
+        let platform = arch_image.platforms().join("-").replace('/', "-");
+        let context = docker_dir.join(format!("{}-{platform}", image.name));
+        build_image(runtime.as_ref(), &arch_image, &context, &context, verbose)?;
+        images.push(arch_image.tags[0].clone());
+    }
+
+    let annotations = publish::annotations(&image.version, publish::rustc_version().as_deref());
+    helpers::section(format!("Pushing manifest list {}", image.tags.join(", ")));
+    runtime.push_manifest(&image.tags, &images, &annotations)?;
+    helpers::success(format!(
+        "Pushed {} for {}",
+        image.tags.join(", "),
+        images.join(", ")
+    ));
+    Ok(())
+}
+
+/// Pin the build images of `targets` to the digests their tags point at, in
+/// the project's xcargo.toml
+#[cfg(feature = "container")]
+fn run_update_images(targets: &[String]) -> Result<()> {
+    use xcargo::container::ContainerBuilder;
+
+    let path = ConfigDiscovery::find()?.unwrap_or_else(|| PathBuf::from("xcargo.toml"));
+    let mut config = if path.is_file() {
+        Config::from_file(&path)?
+    } else {
+        Config::default()
+    };
+    let runtime = RuntimeType::from_str(&config.container.runtime).unwrap_or(RuntimeType::Auto);
+    let builder =
+        ContainerBuilder::new(runtime)?.with_registry(config.container.registry.as_deref());
+
+    let aliases = config.resolved_aliases();
+    let mut triples = Vec::new();
+    for target in targets {
+        triples.push(Target::resolve_alias_with(target, &aliases)?);
+    }
+    if triples.is_empty() {
+        triples.extend(config.container.images.keys().cloned());
+        for target in &config.targets.default {
+            let triple = Target::resolve_alias_with(target, &aliases)?;
+            if builder.select_image(&triple).is_ok() {
+                triples.push(triple);
+            }
+        }
+    }
+    triples.sort();
+    triples.dedup();
+    if triples.is_empty() {
+        helpers::info("No targets with container images to update");
+        helpers::tip("Pass one with --target, or add targets to [targets] default");
+        return Ok(());
+    }
+
+    helpers::section("Updating container images");
+    let mut updated = 0;
+    for triple in &triples {
+        let image = match config.container.images.get(triple) {
+            Some(image) => image.clone(),
+            None => builder.select_image(triple)?.full_name(),
+        };
+        helpers::progress(format!("Pulling {image} for {triple}"));
+        let pinned = builder.pin(&image)?;
+        if pinned == image {
+            helpers::info(format!("{triple}: {image} is up to date"));
+        } else {
+            helpers::success(format!("{triple}: {pinned}"));
+            config.container.images.insert(triple.clone(), pinned);
+            updated += 1;
+        }
+    }
+
+    if updated > 0 {
+        config.save(&path)?;
+        helpers::success(format!("Pinned {updated} image(s) in {}", path.display()));
+    }
+    Ok(())
+}
+
+/// Measure the binaries of several targets and print a size comparison
+fn run_size(
+    targets: &[String],
+    group: Option<&str>,
+    release: bool,
+    build: bool,
+    no_build: bool,
+    by_crate: bool,
+    verbose: u8,
+) -> Result<()> {
+    let config = Config::effective()?;
+    let profile = if release { "release" } else { "debug" };
+    let mut triples = match group {
+        Some(name) => config.target_group(name)?.to_vec(),
+        None if targets.is_empty() => config.targets.default.clone(),
+        None => targets.to_vec(),
+    };
+    if triples.is_empty() {
+        triples.push(Target::detect_host()?.triple);
+    }
+
+    let builder = if no_build {
+        None
+    } else {
+        Some(Builder::with_config(config.clone())?)
+    };
+    let mut sizes = Vec::new();
+    for triple in &triples {
+        let triple = Target::resolve_alias_with(triple, &config.resolved_aliases())?;
+        let target = Target::from_triple(&triple)?;
+        let mut binaries = artifacts::built_binaries(&target, profile)?;
+
+        if let Some(builder) = builder.as_ref().filter(|_| build || binaries.is_empty()) {
+            let options = BuildOptions {
+                target: Some(triple.clone()),
+                release,
+                verbose,
+                ..BuildOptions::default()
+            };
+            if let Err(e) = builder.build(&options) {
+                helpers::error(format!("Build for {triple} failed: {e}"));
+                continue;
+            }
+            binaries = artifacts::built_binaries(&target, profile)?;
+        }
+
+        if binaries.is_empty() {
+            helpers::warning(format!("No binaries found in target/{triple}/{profile}/"));
+            continue;
+        }
+        for path in &binaries {
+            sizes.push(xcargo::size::measure(path, &triple, profile)?);
+        }
+    }
+
+    if sizes.is_empty() {
+        return Err(Error::Build(format!(
+            "No {profile} binaries to measure for {}",
+            triples.join(", ")
+        )));
+    }
+    xcargo::size::record_deltas(&mut sizes)?;
+
+    helpers::section(format!("Binary sizes ({profile})"));
+    xcargo::size::print_table(&sizes);
+    if verbose > 0 {
+        for size in &sizes {
+            xcargo::size::print_sections(size);
+        }
+    }
+    if by_crate {
+        print_crate_sizes(&sizes, verbose > 0)?;
+    }
+    helpers::tip("Symbols (debug info and symbol tables) can be removed with strip = true in [profile.release]");
+    Ok(())
+}
+
+/// Print each binary's code by crate (all crates with `verbose`)
+fn print_crate_sizes(sizes: &[xcargo::size::BinarySize], verbose: bool) -> Result<()> {
+    helpers::section("Code by crate");
+    let limit = if verbose { usize::MAX } else { CRATE_LIMIT };
+    let mut stripped = false;
+    for size in sizes {
+        let crates = xcargo::size::by_crate(&size.path)?;
+        if crates.is_empty() {
+            helpers::warning(format!(
+                "{} ({}) has no symbol table",
+                size.name(),
+                size.target
+            ));
+            stripped = true;
+            continue;
+        }
+        xcargo::size::print_crates(size, &crates, limit);
+    }
+    if stripped {
+        helpers::tip("Keep symbols for the analysis with strip = false in the profile (MSVC builds keep them in a .pdb, which is not read)");
+    }
+    Ok(())
+}
+
+/// Print the compile times recorded since `started` (all crates with
+/// `verbose`)
+fn print_timings(started: u64, verbose: bool) {
+    let limit = if verbose { usize::MAX } else { TIMINGS_LIMIT };
+    match xcargo::timings::timings_dir() {
+        Ok(dir) => {
+            xcargo::timings::print_report(&xcargo::timings::load_since(&dir, started), limit);
+        }
+        Err(e) => helpers::warning(format!("Could not read compile timings: {e}")),
+    }
+}
+
+/// Print the recorded build phases and crate compile times of `targets`
+/// (all when empty), and write the HTML report of the phases if asked to
+fn run_timings(targets: &[String], html: Option<Option<PathBuf>>, verbose: bool) -> Result<()> {
+    let dir = xcargo::timings::timings_dir()?;
+    let shown = |target: &str| targets.is_empty() || targets.iter().any(|t| t == target);
+
+    let mut builds = xcargo::timings::phases::load(&xcargo::timings::phases::phases_dir()?);
+    builds.retain(|build| shown(&build.target));
+    xcargo::timings::phases::print_report(&builds);
+
+    let mut crates = xcargo::timings::load_since(&dir, 0);
+    crates.retain(|timings| shown(&timings.target));
+    if !crates.is_empty() {
+        let limit = if verbose { usize::MAX } else { TIMINGS_LIMIT };
+        println!();
+        xcargo::timings::print_report(&crates, limit);
+    }
+
+    if let Some(path) = html {
+        let path = path.unwrap_or_else(|| dir.join("report.html"));
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, xcargo::timings::phases::html_report(&builds))?;
+        helpers::success(format!("Wrote {}", path.display()));
+    }
+    Ok(())
+}
+
+/// List the recorded builds `filter` matches, the last `last` of them, and
+/// the trend of each target
+fn run_history(filter: &xcargo::history::runs::Filter, last: usize, json: bool) -> Result<()> {
+    use xcargo::history::runs;
+
+    let recorded = filter.apply(runs::load(&runs::runs_path()?));
+    let shown = &recorded[recorded.len().saturating_sub(last)..];
+    if json {
+        for run in shown {
+            println!(
+                "{}",
+                serde_json::to_string(run).map_err(|e| Error::Config(e.to_string()))?
+            );
+        }
+        return Ok(());
+    }
+
+    runs::print_runs(shown);
+    // Failures alone say nothing about build times
+    if !shown.is_empty() && !filter.failed {
+        runs::print_trends(&runs::trends(&recorded, last), last);
+    }
+    Ok(())
+}
+
+/// Compare the benchmark results recorded since `started` across targets
+fn print_bench(started: u64) {
+    match xcargo::bench::bench_dir() {
+        Ok(dir) => xcargo::bench::print_report(&xcargo::bench::load_since(&dir, started)),
+        Err(e) => helpers::warning(format!("Could not read benchmark results: {e}")),
+    }
+}
+
+/// List, add or remove user-defined target aliases
+fn run_toolchain(action: ToolchainAction) -> Result<()> {
+    let manager = ToolchainManager::new()?;
+    match action {
+        ToolchainAction::List => {
+            helpers::section("Toolchains");
+            for toolchain in manager.list_toolchains()? {
+                println!("  {}{}", toolchain.name, toolchain_markers(&toolchain));
+            }
+            println!();
+            helpers::tip("Install another: xcargo toolchain install nightly");
+        }
+
+        ToolchainAction::Install { channel } => {
+            helpers::section("Install Toolchain");
+            if manager.is_toolchain_installed(&channel)? {
+                helpers::info(format!("Toolchain {channel} is already installed"));
+            } else {
+                manager.install_toolchain(&channel)?;
+            }
+        }
+
+        ToolchainAction::Default { channel } => {
+            manager.set_default_toolchain(&channel)?;
+            helpers::success(format!("Default toolchain is now {channel}"));
+        }
+
+        ToolchainAction::Status => {
+            helpers::section("Toolchain Status");
+            let host = Target::detect_host()?.triple;
+            // Host-specific components carry the host triple, e.g. cargo-x86_64-...
+            let suffix = format!("-{host}");
+            for toolchain in manager.list_toolchains()? {
+                helpers::info(format!(
+                    "{}{}",
+                    toolchain.name,
+                    toolchain_markers(&toolchain)
+                ));
+                let targets = manager.list_targets(&toolchain.name)?;
+                println!("  Targets:    {}", targets.join(", "));
+                let components: Vec<String> = manager
+                    .list_components(&toolchain.name)?
+                    .into_iter()
+                    .filter(|c| c.installed && !c.name.starts_with("rust-std"))
+                    .map(|c| c.name.strip_suffix(&suffix).unwrap_or(&c.name).to_string())
+                    .collect();
+                println!("  Components: {}", components.join(", "));
+                println!();
+            }
+            if let Ok(active) = manager.show_active_toolchain() {
+                helpers::info(format!("Active here: {active}"));
+            }
+        }
+
+        ToolchainAction::Component { action } => run_component(&manager, action)?,
+
+        ToolchainAction::Provision { args } => run_provision(&args)?,
+    }
+    Ok(())
+}
+
+/// Install the cross GCC for a target
+///
+/// Prebuilt toolchains come from the cached catalog, fetched again first
+/// with `--update-catalog`, or when it is a week old and not offline.
+fn run_provision(args: &ProvisionArgs) -> Result<()> {
+    use xcargo::provision::{self, catalog, Catalog, Provision};
+
+    let target = args.target.as_deref().or(args.target_flag.as_deref());
+    let standalone = args.standalone;
+
+    let config = config_or_defaults()?;
+    let catalog_url = config
+        .toolchain
+        .catalog_url
+        .as_deref()
+        .unwrap_or(catalog::DEFAULT_CATALOG_URL);
+    if args.update_catalog {
+        let catalog = Catalog::update(catalog_url, &config.network)?;
+        helpers::success(format!(
+            "Toolchain catalog updated: {} toolchains from {catalog_url}",
+            catalog.toolchains.len()
+        ));
+    } else if Catalog::is_stale() && !config.build.offline {
+        if let Err(e) = Catalog::update(catalog_url, &config.network) {
+            helpers::warning(format!("Could not update the toolchain catalog: {e}"));
+        }
+    }
+    let Some(target) = target else {
+        return Ok(());
+    };
+
+    let triple = Target::resolve_alias_with(target, &config.resolved_aliases())?;
+    helpers::section(format!("Provision {triple}"));
+
+    let provisionable = provision::provisionable();
+    if !provisionable.contains(&triple) {
+        return Err(Error::Toolchain(format!(
+            "No cross GCC to provision for {triple}; supported: {}",
+            provisionable.join(", ")
+        )));
+    }
+    if !standalone {
+        if let Some(linker) = provision::find_linker(&triple) {
+            helpers::success(format!("Already installed: {linker}"));
+            return Ok(());
+        }
+    }
+    let Some(provision) = Provision::plan(&triple, standalone) else {
+        helpers::hint("No supported package manager (apt, dnf, pacman, brew) was found");
+        return Err(Error::Toolchain(format!(
+            "Cannot provision a cross GCC for {triple} on this host"
+        )));
+    };
+
+    helpers::progress(format!("Running: {}", provision.describe()));
+    let linker = provision.run(&config.network)?;
+    helpers::success(format!("Linker ready: {linker}"));
+    helpers::tip(format!(
+        "xcargo build --target {triple} uses it automatically"
+    ));
+    Ok(())
+}
+
+/// " (active, default)" and the like, as rustup marks a toolchain
+fn toolchain_markers(toolchain: &xcargo::toolchain::Toolchain) -> String {
+    match (toolchain.is_active, toolchain.is_default) {
+        (true, true) => " (active, default)".to_string(),
+        (true, false) => " (active)".to_string(),
+        (false, true) => " (default)".to_string(),
+        (false, false) => String::new(),
+    }
+}
+
+fn run_component(manager: &ToolchainManager, action: ComponentAction) -> Result<()> {
+    match action {
+        ComponentAction::Add {
+            components,
+            toolchain,
+        } => {
+            helpers::section("Add Components");
+            for component in &components {
+                if manager.is_component_installed(&toolchain, component)? {
+                    helpers::info(format!("Component {component} is already installed"));
+                } else {
+                    manager.install_component(&toolchain, component)?;
+                }
+            }
+        }
+
+        ComponentAction::List {
+            installed,
+            toolchain,
+        } => {
+            helpers::section(format!("Components of {toolchain}"));
+            for component in manager.list_components(&toolchain)? {
+                if component.installed {
+                    println!("  {} {}", helpers::status_marker(true), component.name);
+                } else if !installed {
+                    println!("    {}", component.name);
+                }
+            }
+            println!();
+            helpers::tip(format!(
+                "Add one: xcargo toolchain component add rust-src --toolchain {toolchain}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn run_alias(action: AliasAction) -> Result<()> {
+    match action {
+        AliasAction::List => {
+            helpers::section("Target Aliases");
+
+            let project = ConfigDiscovery::find()?
+                .map(Config::from_file)
+                .transpose()?;
+            let global = Config::load_global()?;
+            for (label, config) in [("Project", project), ("Global", global)] {
+                let Some(config) = config.filter(|c| !c.aliases.is_empty()) else {
+                    continue;
+                };
+                helpers::info(format!("{label} aliases:"));
+                let mut aliases: Vec<_> = config.aliases.into_iter().collect();
+                aliases.sort();
+                for (name, triple) in aliases {
+                    println!("  {name:<18} {triple}");
+                }
+                println!();
+            }
+
+            helpers::info("Built-in aliases:");
+            for (name, triple) in Target::builtin_aliases() {
+                println!("  {name:<18} {triple}");
+            }
+            println!();
+            helpers::tip("Define your own: xcargo target alias add rpi aarch64-unknown-linux-gnu");
+        }
+
+        AliasAction::Add {
+            name,
+            target,
+            global,
+        } => {
+            let triple = Target::from_known_triple(&target)?.triple;
+            let path = alias_config_path(global)?;
+            let mut config = if path.is_file() {
+                Config::from_file(&path)?
+            } else {
+                Config::default()
+            };
+
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            config.aliases.insert(name.clone(), triple.clone());
+            config.save(&path)?;
+            helpers::success(format!(
+                "Alias {name} → {triple} saved to {}",
+                path.display()
+            ));
+        }
+
+        AliasAction::Remove { name, global } => {
+            let path = alias_config_path(global)?;
+            let mut config = if path.is_file() {
+                Config::from_file(&path)?
+            } else {
+                Config::default()
+            };
+
+            if config.aliases.remove(&name).is_none() {
+                return Err(Error::Config(format!(
+                    "No alias '{name}' in {}",
+                    path.display()
+                )));
+            }
+            config.save(&path)?;
+            helpers::success(format!("Alias {name} removed from {}", path.display()));
+        }
+    }
+    Ok(())
+}
+
+/// Config file that `xcargo target alias add/remove` edits
+fn alias_config_path(global: bool) -> Result<PathBuf> {
+    if global {
+        Config::global_path()
+            .ok_or_else(|| Error::Config("Could not determine home directory".to_string()))
+    } else {
+        match ConfigDiscovery::find()? {
+            Some(path) => Ok(path),
+            None => ConfigDiscovery::default_path(),
+        }
+    }
+}
+
+/// Show the explanation for a failure code, or those matching error output
+fn run_explain(query: Option<&str>) -> Result<()> {
+    let Some(query) = query else {
+        helpers::section("Known Cross-Compilation Failures");
+        for explanation in explain::EXPLANATIONS {
+            println!("  {}  {}", explanation.code, explanation.title);
+        }
+        println!();
+        helpers::tip("Run 'xcargo explain <code>' for causes and fixes");
+        return Ok(());
+    };
+
+    if let Some(explanation) = Explanation::find(query) {
+        explanation.print(None);
+        return Ok(());
+    }
+
+    let text = if query == "-" {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        query.to_string()
+    };
+    let found = explain::explain_output(&text);
+    if found.is_empty() {
+        helpers::info("No known cross-compilation failure recognized");
+        helpers::tip("Run 'xcargo explain' to list the known failures");
+    }
+    for (idx, explanation) in found.iter().enumerate() {
+        if idx > 0 {
+            println!();
+        }
+        explanation.print(None);
+    }
+    Ok(())
+}
+
+/// Re-run a recorded trace, or list its contents with `dry_run`
+fn run_replay(path: &Path, dry_run: bool) -> Result<()> {
+    let trace = xcargo::trace::BuildTrace::load(path)?;
+
+    helpers::section("Replay");
+    helpers::info(format!(
+        "Recorded by xcargo {} on {} ({})",
+        trace.xcargo_version,
+        trace.host,
+        format_timestamp(trace.created)
+    ));
+    helpers::info(format!("Command: {}", trace.args.join(" ")));
+
+    let differences = trace.tool_differences(&xcargo::trace::tool_versions());
+    for (tool, recorded, current) in &differences {
+        helpers::warning(format!("{tool}: recorded '{recorded}', now '{current}'"));
+    }
+    if !differences.is_empty() {
+        helpers::hint("Tool versions differ, so results may not match the recording");
+    }
+
+    for download in &trace.downloads {
+        helpers::info(format!(
+            "Downloaded {} (sha256 {})",
+            download.url, download.sha256
+        ));
+    }
+
+    if dry_run {
+        for command in &trace.commands {
+            println!("  {}", command.display());
+        }
+        return Ok(());
+    }
+
+    let mismatches = trace.replay()?;
+    if mismatches > 0 {
+        return Err(Error::Build(format!(
+            "{mismatches} command(s) exited differently than recorded"
+        )));
+    }
+    helpers::success(format!(
+        "Replayed {} command(s) with matching results",
+        trace.commands.len()
+    ));
+    Ok(())
+}
+
+/// How xcargo would build and test `target` on this host, with the
+/// commands that prepare for it
+fn print_target_plan(config: &Config, target: &Target) {
+    let triple = &target.triple;
+    let options = BuildOptions {
+        target: Some(triple.clone()),
+        ..BuildOptions::default()
+    };
+    // Without rustup there is no plan, only the static facts above
+    let plan = Builder::with_config(config.clone()).and_then(|builder| builder.plan(&options));
+    let runtime = match config.container.runtime.as_str() {
+        "podman" => "podman",
+        "nerdctl" | "containerd" => "nerdctl",
+        _ => "docker",
+    };
+
+    if let Ok(plan) = &plan {
+        helpers::info("On this host:");
+        println!(
+            "  Strategy:    {} ({})",
+            plan.path_description(),
+            plan.strategy.reason
+        );
+        println!(
+            "  Container:   {}",
+            plan.image.as_deref().unwrap_or("no image available")
+        );
+        let zig = match (plan.zig, which::which("zig").is_ok()) {
+            (true, true) => "supported",
+            (true, false) => "supported, but Zig is not installed",
+            (false, _) => "not supported",
+        };
+        println!("  Zig:         {zig}");
+        let runner = match plan.runner.as_deref() {
+            Some("native") => "runs natively".to_string(),
+            Some(runner) => runner.to_string(),
+            None => format!(
+                "none, tests only build (set CARGO_TARGET_{}_RUNNER)",
+                triple.to_uppercase().replace('-', "_")
+            ),
+        };
+        println!("  Test runner: {runner}");
+        println!();
+    }
+
+    helpers::tip(format!("Add this target: xcargo target add {triple}"));
+    if let Ok(plan) = &plan {
+        match (plan.path.as_str(), &plan.image) {
+            ("container", Some(image)) => {
+                helpers::tip(format!(
+                    "Pull its image ahead of time: {runtime} pull {image}"
+                ));
+            }
+            ("native", _) if plan.zig && !plan.native => {
+                helpers::tip(format!(
+                    "Cross-compile with Zig instead: xcargo build --target {triple} --zig"
+                ));
+            }
+            _ => {}
+        }
+    }
+    if !target.get_requirements().tools.is_empty() {
+        helpers::tip("Check for the tools it needs: xcargo doctor");
+    }
+    helpers::tip(format!(
+        "Build for this target: xcargo build --target {triple}"
+    ));
+    helpers::tip(format!(
+        "See the full plan, with native dependencies: xcargo build --target {triple} --explain"
+    ));
+}
+
+/// Builder running the hooks of the project's plugins
+///
+/// With `standalone`, the command-line settings suffice (an explicit
+/// `--target`), so a broken configuration falls back to the defaults.
+fn project_builder(standalone: bool) -> Result<Builder> {
+    let config = if standalone {
+        config_or_defaults()?
+    } else {
+        Config::effective()?
+    };
+    let plugins = xcargo::plugin::init(&config)?;
+    Ok(Builder::with_config(config)?.with_plugins(Arc::new(plugins)))
+}
+
+/// Project configuration, or the defaults when the file cannot be loaded
+fn config_or_defaults() -> Result<Config> {
+    let (config, problem) = Config::discover_or_default()?;
+    if let Some(problem) = problem {
+        helpers::warning(format!("Ignoring broken configuration: {problem}"));
+        helpers::hint(format!(
+            "Continuing with default settings and command-line options; fix {} to use it again",
+            problem.location()
+        ));
+    }
+    Ok(config)
+}
+
+/// Check configuration files, or the discovered configuration, and list
+/// every problem
+fn run_config_validate(files: &[PathBuf]) -> Result<()> {
+    helpers::section("Validating configuration");
+
+    let mut checked: Vec<Vec<ConfigSource>> = files
+        .iter()
+        .map(|file| ConfigSource::in_file(file))
+        .collect();
+    if files.is_empty() {
+        let layers: Vec<ConfigSource> = Config::global_path()
+            .filter(|path| path.is_file())
+            .map(ConfigSource::File)
+            .into_iter()
+            .chain(ConfigDiscovery::find_sources()?)
+            .collect();
+        if layers.is_empty() {
+            helpers::info("No xcargo.toml found, nothing to validate");
+            helpers::tip(tips::CONFIG_FILE);
+            return Ok(());
+        }
+        checked.push(layers);
+    }
+
+    let mut problems = Vec::new();
+    for layers in &checked {
+        problems.extend(xcargo::config::check(layers));
+        for source in layers {
+            helpers::progress(format!("Checked {source}"));
+        }
+    }
+
+    if problems.is_empty() {
+        helpers::success("Configuration is valid");
+        return Ok(());
+    }
+    for problem in &problems {
+        helpers::error(problem.to_string());
+    }
+    Err(Error::Config(format!(
+        "{} configuration problem{} found",
+        problems.len(),
+        if problems.len() == 1 { "" } else { "s" }
+    )))
+}
+
+/// Print the targets rustup knows that match `query`'s pattern, OS,
+/// architecture and environment, one per line with their tier on Rust's
+/// platform support page and whether they are installed
+fn run_target_search(query: &TargetQuery) -> Result<()> {
+    let found = query.search(Target::list_available_with_status()?);
+    if found.is_empty() {
+        helpers::info("No targets match");
+        return Ok(());
+    }
+
+    let width = found
+        .iter()
+        .map(|(target, _)| target.triple.len())
+        .chain(["TARGET".len()])
+        .max()
+        .unwrap_or(0);
+    println!("{:<width$}  {:<22}  INSTALLED", "TARGET", "RUST TIER");
+    for (target, installed) in &found {
+        let tier = target
+            .rust_tier
+            .map_or_else(|| "-".to_string(), |tier| tier.to_string());
+        println!(
+            "{:<width$}  {tier:<22}  {}",
+            target.triple,
+            helpers::status_marker(*installed)
+        );
+    }
+    println!();
+    let plural = if found.len() == 1 { "" } else { "s" };
+    helpers::hint(format!("{} target{plural}", found.len()));
+    Ok(())
+}
+
+/// Print which targets this machine can build, and how
+fn run_target_matrix(all: bool, json: bool) -> Result<()> {
+    let config = config_or_defaults()?;
+    let triples: Vec<String> = if all {
+        Target::list_available()?
+            .into_iter()
+            .map(|target| target.triple)
+            .collect()
+    } else {
+        let aliases = config.resolved_aliases();
+        config
+            .targets
+            .default
+            .iter()
+            .map(|target| Target::resolve_alias_with(target, &aliases))
+            .collect::<Result<_>>()?
+    };
+    if triples.is_empty() && !json {
+        helpers::info("No default targets configured");
+        helpers::tip("Use 'xcargo target matrix --all' for every target rustup knows");
+        return Ok(());
+    }
+
+    let builder = project_builder(true)?;
+    let rows = builder.matrix(&triples, &BuildOptions::default())?;
+    if json {
+        let output = serde_json::to_string_pretty(&rows)
+            .map_err(|e| Error::Config(format!("Failed to serialize matrix: {e}")))?;
+        println!("{output}");
+    } else {
+        helpers::section("Target Matrix");
+        xcargo::build::matrix::print(&rows);
+    }
+    Ok(())
+}
+
+/// Flags from `--rustflags` values, each holding one or more
+fn rustflag_args(values: &[String]) -> Vec<String> {
+    values
+        .iter()
+        .flat_map(|value| value.split_whitespace())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Print the linker, runner and rustflags in effect for `triple`, from
+/// xcargo's configuration over cargo's
+fn run_config_effective(triple: &str) -> Result<()> {
+    helpers::section(format!("Effective settings for {triple}"));
+
+    let config = Config::effective()?;
+    let cargo_config = CargoTargetConfig::find(triple)?;
+    let settings = config.target_settings(triple, &cargo_config);
+    if settings.is_empty() {
+        helpers::info("No linker, runner or rustflags configured");
+        return Ok(());
+    }
+
+    let width = settings
+        .iter()
+        .map(|setting| setting.origin.len())
+        .max()
+        .unwrap_or(0);
+    for setting in &settings {
+        println!(
+            "{:<width$}  {} = {}",
+            setting.origin, setting.key, setting.value
+        );
+    }
+    let conflicts: Vec<String> = settings
+        .iter()
+        .filter_map(|setting| setting.conflict(triple))
+        .collect();
+    if !conflicts.is_empty() {
+        println!();
+        for conflict in conflicts {
+            helpers::warning(conflict);
+        }
+    }
+    Ok(())
+}
+
+/// Turn a Cross.toml into an xcargo.toml
+fn run_migrate_cross(
+    file: Option<PathBuf>,
+    to: Option<PathBuf>,
+    force: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let file = match file {
+        Some(file) => file,
+        None => ConfigDiscovery::find_sources()?
+            .into_iter()
+            .find_map(|source| match source {
+                ConfigSource::Cross(path) => Some(path),
+                _ => None,
+            })
+            .ok_or_else(|| Error::Config(format!("No {CROSS_FILE_NAME} found for this project")))?,
+    };
+    let import = CrossImport::read(&file)?;
+    import.config().map_err(Error::Config)?;
+    let toml = import.to_toml()?;
+    if dry_run {
+        print!("{toml}");
+        return Ok(());
+    }
+
+    helpers::section("Migrating from cross");
+    let output = to.unwrap_or_else(|| {
+        file.parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(ConfigFormat::Toml.file_name())
+    });
+    if output.exists() && !force {
+        return Err(Error::Config(format!(
+            "{} already exists; pass --force to overwrite it, or --dry-run to print the configuration",
+            output.display()
+        )));
+    }
+    xcargo::state::write_atomic(&output, toml)
+        .map_err(|e| Error::Config(format!("Failed to write config file: {e}")))?;
+
+    helpers::success(format!(
+        "Wrote {} from {}",
+        output.display(),
+        file.display()
+    ));
+    for skipped in &import.skipped {
+        helpers::warning(format!("Not migrated: {skipped}"));
+    }
+    helpers::tip(format!(
+        "{} is still read under xcargo.toml; remove it once builds work",
+        file.display()
+    ));
+    Ok(())
+}
+
+/// List every effective setting and where it came from
+fn show_config_origins() -> Result<()> {
+    for source in ConfigDiscovery::find_sources()?.iter().rev() {
+        helpers::info(format!("Project: {source}"));
+    }
+    if let Some(path) = Config::global_path().filter(|p| p.is_file()) {
+        helpers::info(format!("Global:  {}", path.display()));
+    }
+    println!();
+
+    let settings = xcargo::config::effective_settings()?;
+    let width = settings
+        .iter()
+        .map(|setting| setting.origin.to_string().len())
+        .max()
+        .unwrap_or(0);
+    for setting in settings {
+        println!(
+            "{:<width$}  {} = {}",
+            setting.origin.to_string(),
+            setting.key,
+            setting.value
+        );
+    }
+    Ok(())
+}
+
+/// Print why each target gets its build strategy
+fn explain_strategies(
+    builder: &Builder,
+    options: &BuildOptions,
+    all: bool,
+    group: Option<&str>,
+) -> Result<()> {
+    helpers::section("xcargo explain-strategy");
+    if !all && group.is_none() {
+        return builder.explain_strategy(options);
+    }
+
+    let config = Config::effective()?;
+    let targets = match group {
+        Some(name) => config.target_group(name)?.to_vec(),
+        None => config.targets.default.clone(),
+    };
+    for triple in targets {
+        builder.explain_strategy(&BuildOptions {
+            target: Some(triple),
+            ..options.clone()
+        })?;
+    }
+    Ok(())
+}
+
+/// Run a cargo operation for one target, all default targets or a group
+fn run_operation(
+    builder: &Builder,
+    options: &BuildOptions,
+    all: bool,
+    group: Option<&str>,
+) -> Result<()> {
+    if !all && group.is_none() {
+        return builder.build(options);
+    }
+
+    let config = Config::effective()?;
+    let targets = match group {
+        Some(name) => config.target_group(name)?.to_vec(),
+        None => config.targets.default.clone(),
+    };
+
+    if targets.is_empty() {
+        helpers::error("No default targets configured");
+        helpers::hint(
+            "Add targets to xcargo.toml: [targets] default = [\"x86_64-unknown-linux-gnu\"]",
+        );
+        std::process::exit(1);
+    }
+
+    if config.build.parallel {
+        let rt = tokio::runtime::Runtime::new()?;
+        rt.block_on(builder.build_all_parallel(&targets, options))
+    } else {
+        builder.build_all(&targets, options)
+    }
+}
+
+/// Remove build output and xcargo state, reporting reclaimed space
+fn run_clean(
+    targets: Vec<String>,
+    cache: bool,
+    containers: bool,
+    all: bool,
+    cargo_args: Vec<String>,
+) -> Result<()> {
+    // Arguments for cargo hand cleaning over to `cargo clean` per target
+    if !cargo_args.is_empty() {
+        let builder = Builder::new()?;
+        let targets: Vec<Option<String>> = if targets.is_empty() {
+            vec![None]
+        } else {
+            targets.into_iter().map(Some).collect()
+        };
+        for target in targets {
+            builder.build(&BuildOptions {
+                target,
+                cargo_args: cargo_args.clone(),
+                operation: CargoOperation::Clean,
+                ..BuildOptions::default()
+            })?;
+        }
+        return Ok(());
+    }
+
+    helpers::section("xcargo clean");
+
+    let mut cleaned: Vec<clean::Cleaned> = Vec::new();
+
+    // Target directories: explicit targets, or all when nothing else was asked for
+    let clean_all_targets = all || (targets.is_empty() && !cache && !containers);
+    if clean_all_targets || !targets.is_empty() {
+        let target_dir = clean::target_directory()?;
+        let triples = if clean_all_targets {
+            clean::target_triples(&target_dir)
+        } else {
+            targets
+        };
+
+        for triple in &triples {
+            match clean::clean_target(&target_dir, triple)? {
+                Some(item) => cleaned.push(item),
+                None => helpers::info(format!("Nothing to clean for {triple}")),
+            }
+        }
+    }
+
+    if cache || all {
+        let build_cache = BuildCache::new()?;
+        if let Some(item) = clean::remove_dir(build_cache.cache_dir())? {
+            cleaned.push(item);
+        }
+    }
+
+    if containers || all {
+        cleaned.extend(remove_container_images()?);
+    }
+
+    if cleaned.is_empty() {
+        helpers::info("Nothing to clean");
+        return Ok(());
+    }
+
+    for item in &cleaned {
+        helpers::success(format!(
+            "Removed {} ({})",
+            item.item,
+            format_bytes(item.bytes)
+        ));
+    }
+    let total: u64 = cleaned.iter().map(|item| item.bytes).sum();
+    println!();
+    helpers::info(format!("Reclaimed {}", format_bytes(total)));
+
+    Ok(())
+}
+
+/// Undo what xcargo installed, for one target or everything
+fn run_uninstall(target: Option<&str>, dry_run: bool) -> Result<()> {
+    helpers::section("xcargo uninstall");
+
+    let target = match target {
+        Some(target) => {
+            let config = Config::effective()?;
+            Some(Target::resolve_alias_with(
+                target,
+                &config.resolved_aliases(),
+            )?)
+        }
+        None => None,
+    };
+
+    let path = InstallLedger::default_path()?;
+    let mut ledger = InstallLedger::load(&path);
+    let items = ledger.for_target(target.as_deref());
+    if items.is_empty() {
+        helpers::info("Nothing xcargo installed is left to remove");
+        return Ok(());
+    }
+
+    if dry_run {
+        helpers::info("Would remove:");
+        for item in &items {
+            println!("  {}", item.describe());
+        }
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for item in &items {
+        helpers::progress(format!("Removing {}", item.describe()));
+        match item.uninstall() {
+            Ok(()) => {
+                ledger.remove(item);
+                helpers::success(format!("Removed {}", item.describe()));
+            }
+            Err(e) => {
+                failed += 1;
+                helpers::warning(format!("Could not remove {}: {e}", item.describe()));
+            }
+        }
+    }
+    ledger.save(&path)?;
 
-    /// List targets
-    List {
-        /// Show only installed targets
-        #[arg(long)]
-        installed: bool,
+    if failed > 0 {
+        helpers::tip(
+            "Entries that could not be removed are kept; run 'xcargo uninstall' again to retry",
+        );
+    }
+    Ok(())
+}
 
-        /// Toolchain to list targets for
-        #[arg(long)]
-        toolchain: Option<String>,
-    },
+/// Remove the container images pulled for cross builds
+#[cfg(feature = "container")]
+fn remove_container_images() -> Result<Vec<clean::Cleaned>> {
+    use xcargo::container::{ContainerBuilder, RuntimeType};
+
+    let config = Config::effective()?;
+    let runtime = RuntimeType::from_str(&config.container.runtime).unwrap_or(RuntimeType::Auto);
+    let builder = match ContainerBuilder::new(runtime) {
+        Ok(builder) => builder.with_registry(config.container.registry.as_deref()),
+        Err(e) => {
+            helpers::warning(format!("Skipping container images: {e}"));
+            return Ok(Vec::new());
+        }
+    };
 
-    /// Show target information
-    Info {
-        /// Target triple
-        target: String,
-    },
+    Ok(builder
+        .remove_images()?
+        .into_iter()
+        .map(|(image, bytes)| clean::Cleaned { item: image, bytes })
+        .collect())
+}
+
+/// Remove the container images pulled for cross builds
+#[cfg(not(feature = "container"))]
+fn remove_container_images() -> Result<Vec<clean::Cleaned>> {
+    helpers::warning("Container support not enabled, skipping container images");
+    Ok(Vec::new())
 }
 
 /// Run basic non-interactive setup
@@ -233,17 +2776,22 @@ fn run_basic_setup() -> Result<()> {
 fn run_interactive_setup() -> Result<()> {
     use xcargo::output::colors;
 
-    println!(
-        "\n{}{}✨ xcargo Interactive Setup{}",
-        colors::BOLD,
-        colors::CYAN,
-        colors::RESET
-    );
-    println!(
-        "{}Let's configure cross-compilation for your project!{}\n",
-        colors::DIM,
-        colors::RESET
-    );
+    if xcargo::output::is_plain() {
+        println!("\nxcargo Interactive Setup");
+        println!("Let's configure cross-compilation for your project!\n");
+    } else {
+        println!(
+            "\n{}{}✨ xcargo Interactive Setup{}",
+            colors::BOLD,
+            colors::CYAN,
+            colors::RESET
+        );
+        println!(
+            "{}Let's configure cross-compilation for your project!{}\n",
+            colors::DIM,
+            colors::RESET
+        );
+    }
 
     // Check for existing config
     if Path::new("xcargo.toml").exists() {
@@ -352,7 +2900,7 @@ fn run_interactive_setup() -> Result<()> {
     config.save("xcargo.toml")?;
 
     println!();
-    helpers::success("✨ Configuration created successfully!");
+    helpers::success("Configuration created successfully!");
     println!();
 
     // Summary
@@ -406,6 +2954,10 @@ fn run_interactive_setup() -> Result<()> {
 }
 
 fn main() {
+    // Answer shell completion requests (`COMPLETE=<shell> xcargo ...`) before
+    // anything else writes to stdout
+    CompleteEnv::with_factory(Cli::command).complete();
+
     // Set up Ctrl+C handler for graceful shutdown
     setup_signal_handler();
 
@@ -415,11 +2967,26 @@ fn main() {
 }
 
 /// Set up signal handler for graceful shutdown on Ctrl+C
+///
+/// While cargo or a container runs, the first Ctrl+C cancels the build so
+/// it can stop its child processes and record the targets that finished;
+/// a second one kills cargo's process groups and exits immediately.
 fn setup_signal_handler() {
     ctrlc::set_handler(move || {
+        let token = CancellationToken::global();
+        if xcargo::cancel::running() > 0 && !token.is_cancelled() {
+            token.cancel();
+            eprintln!();
+            helpers::warning("Received interrupt signal (Ctrl+C), stopping the build...");
+            helpers::info("Press Ctrl+C again to exit immediately");
+            return;
+        }
+
         eprintln!("\n");
         helpers::warning("Received interrupt signal (Ctrl+C)");
         helpers::info("Cleaning up and shutting down gracefully...");
+        xcargo::cancel::kill_groups();
+        xcargo::scratch::remove_live();
 
         // Exit with code 130 (128 + SIGINT)
         std::process::exit(130);
@@ -429,45 +2996,82 @@ fn setup_signal_handler() {
 
 fn run() -> Result<()> {
     let cli = Cli::parse();
+    if let Some(mode) = OutputMode::parse(&cli.output) {
+        xcargo::output::set_mode(mode);
+    }
+    xcargo::logging::init(cli.verbose, cli.log_file.as_deref())?;
+    // Every command loads its configuration through discovery, which reads this
+    if let Some(profile) = &cli.profile {
+        std::env::set_var(xcargo::config::ENV_PROFILE, profile);
+    }
+
+    let Some(trace_path) = cli.record.clone() else {
+        return run_command(cli);
+    };
+
+    xcargo::trace::start(std::env::args().collect());
+    let result = run_command(cli);
+    // Failed builds are the interesting ones, so write the trace regardless
+    if let Some(trace) = xcargo::trace::finish(&trace_path)? {
+        helpers::info(format!(
+            "Recorded {} command(s) to {}",
+            trace.commands.len(),
+            trace_path.display()
+        ));
+    }
+    result
+}
 
+fn run_command(cli: Cli) -> Result<()> {
     match cli.command {
         Commands::Build {
-            target,
-            all,
+            build,
             release,
             container,
-            zig,
-            no_zig,
-            toolchain,
+            glibc,
+            static_link,
+            rustflags,
+            selection,
+            retries,
+            timeout,
+            keep_going,
+            resume,
+            timings,
+            explain,
+            explain_strategy,
             cargo_args,
         } => {
-            let builder = Builder::new()?;
-
-            // Determine Zig preference: None = auto, Some(true) = force, Some(false) = disable
-            let use_zig = if zig {
-                Some(true)
-            } else if no_zig {
-                Some(false)
-            } else {
-                None
-            };
+            let builder = project_builder(build.standalone())?;
 
             let options = BuildOptions {
-                target: target.clone(),
                 release,
-                cargo_args,
-                toolchain,
-                verbose: cli.verbose,
                 use_container: container,
-                use_zig,
-                operation: CargoOperation::Build,
+                glibc_version: glibc,
+                selection: selection.into(),
+                retries,
+                keep_going,
+                resume,
+                timeout,
+                timings,
+                static_link,
+                rustflags: rustflag_args(&rustflags),
+                ..build.to_options(CargoOperation::Build, cli.verbose, cargo_args)
             };
 
-            if all {
-                // Build for all configured targets
-                let config = Config::discover()?.map(|(c, _)| c).unwrap_or_default();
-
-                if config.targets.default.is_empty() {
+            let started = xcargo::timings::now();
+            let result = if explain {
+                builder.explain(&options)
+            } else if explain_strategy {
+                explain_strategies(&builder, &options, build.all, build.group.as_deref())
+            } else if build.all || build.group.is_some() {
+                // Build for all configured targets, or a group of them
+                let config = Config::effective()?;
+                let targets = match &build.group {
+                    Some(name) => config.target_group(name)?.to_vec(),
+                    None => config.targets.default.clone(),
+                };
+
+                if targets.is_empty() {
                     helpers::error("No default targets configured");
                     helpers::hint("Add targets to xcargo.toml: [targets] default = [\"x86_64-unknown-linux-gnu\"]");
                     helpers::tip(tips::CONFIG_FILE);
@@ -477,129 +3081,157 @@ fn run() -> Result<()> {
                 // Use parallel builds if enabled in config
                 if config.build.parallel {
                     let rt = tokio::runtime::Runtime::new()?;
-                    rt.block_on(builder.build_all_parallel(&config.targets.default, &options))?;
+                    rt.block_on(builder.build_all_parallel(&targets, &options))
                 } else {
-                    builder.build_all(&config.targets.default, &options)?;
+                    builder.build_all(&targets, &options)
                 }
             } else {
-                builder.build(&options)?;
+                builder.build(&options)
+            };
+
+            // Targets that built still report when others failed
+            if timings {
+                print_timings(started, cli.verbose > 0);
             }
+            result?;
         }
 
-        Commands::Check {
-            target,
-            all,
-            zig,
-            no_zig,
-            toolchain,
+        Commands::Check { build, cargo_args } => {
+            let builder = project_builder(build.standalone())?;
+
+            let options = build.to_options(CargoOperation::Check, cli.verbose, cargo_args);
+
+            run_operation(&builder, &options, build.all, build.group.as_deref())?;
+        }
+
+        Commands::Test {
+            build,
+            release,
+            static_link,
+            rustflags,
             cargo_args,
         } => {
-            let builder = Builder::new()?;
-
-            let use_zig = if zig {
-                Some(true)
-            } else if no_zig {
-                Some(false)
-            } else {
-                None
-            };
+            let builder = project_builder(build.standalone())?;
 
             let options = BuildOptions {
-                target: target.clone(),
-                release: false,
-                cargo_args,
-                toolchain,
-                verbose: cli.verbose,
-                use_container: false,
-                use_zig,
-                operation: CargoOperation::Check,
+                release,
+                static_link,
+                rustflags: rustflag_args(&rustflags),
+                ..build.to_options(CargoOperation::Test, cli.verbose, cargo_args)
             };
 
-            if all {
-                let config = Config::discover()?.map(|(c, _)| c).unwrap_or_default();
+            run_operation(&builder, &options, build.all, build.group.as_deref())?;
+        }
 
-                if config.targets.default.is_empty() {
-                    helpers::error("No default targets configured");
-                    helpers::hint("Add targets to xcargo.toml: [targets] default = [\"x86_64-unknown-linux-gnu\"]");
-                    std::process::exit(1);
-                }
+        Commands::Clippy {
+            build,
+            release,
+            cargo_args,
+        } => {
+            let builder = project_builder(build.standalone())?;
 
-                if config.build.parallel {
-                    let rt = tokio::runtime::Runtime::new()?;
-                    rt.block_on(builder.build_all_parallel(&config.targets.default, &options))?;
-                } else {
-                    builder.build_all(&config.targets.default, &options)?;
-                }
-            } else {
-                builder.build(&options)?;
-            }
+            let options = BuildOptions {
+                release,
+                ..build.to_options(CargoOperation::Clippy, cli.verbose, cargo_args)
+            };
+
+            run_operation(&builder, &options, build.all, build.group.as_deref())?;
         }
 
-        Commands::Test {
-            target,
-            all,
+        Commands::Doc {
+            build,
             release,
-            zig,
-            no_zig,
-            toolchain,
+            no_deps,
             cargo_args,
         } => {
-            let builder = Builder::new()?;
+            let builder = project_builder(build.standalone())?;
 
-            let use_zig = if zig {
-                Some(true)
-            } else if no_zig {
-                Some(false)
-            } else {
-                None
-            };
+            let mut cargo_args = cargo_args;
+            if no_deps {
+                cargo_args.insert(0, "--no-deps".to_string());
+            }
 
             let options = BuildOptions {
-                target: target.clone(),
                 release,
-                cargo_args,
-                toolchain,
-                verbose: cli.verbose,
-                use_container: false,
-                use_zig,
-                operation: CargoOperation::Test,
+                ..build.to_options(CargoOperation::Doc, cli.verbose, cargo_args)
             };
 
-            if all {
-                let config = Config::discover()?.map(|(c, _)| c).unwrap_or_default();
+            run_operation(&builder, &options, build.all, build.group.as_deref())?;
+        }
 
-                if config.targets.default.is_empty() {
-                    helpers::error("No default targets configured");
-                    helpers::hint("Add targets to xcargo.toml: [targets] default = [\"x86_64-unknown-linux-gnu\"]");
-                    std::process::exit(1);
-                }
+        Commands::Bench {
+            build,
+            static_link,
+            rustflags,
+            report,
+            cargo_args,
+        } => {
+            if report {
+                print_bench(0);
+                return Ok(());
+            }
+            let builder = project_builder(build.standalone())?;
 
-                if config.build.parallel {
-                    let rt = tokio::runtime::Runtime::new()?;
-                    rt.block_on(builder.build_all_parallel(&config.targets.default, &options))?;
+            let options = BuildOptions {
+                static_link,
+                rustflags: rustflag_args(&rustflags),
+                ..build.to_options(CargoOperation::Bench, cli.verbose, cargo_args)
+            };
+
+            let started = xcargo::timings::now();
+            let result = run_operation(&builder, &options, build.all, build.group.as_deref());
+            // Targets that ran still report when others failed
+            print_bench(started);
+            result?;
+        }
+
+        Commands::Coverage {
+            build,
+            release,
+            html,
+            rustflags,
+            cargo_args,
+        } => {
+            let builder = project_builder(build.standalone())?;
+
+            let options = BuildOptions {
+                release,
+                coverage: Some(if html {
+                    xcargo::coverage::Format::Html
                 } else {
-                    builder.build_all(&config.targets.default, &options)?;
-                }
-            } else {
-                builder.build(&options)?;
-            }
+                    xcargo::coverage::Format::Lcov
+                }),
+                rustflags: rustflag_args(&rustflags),
+                ..build.to_options(CargoOperation::Test, cli.verbose, cargo_args)
+            };
+
+            run_operation(&builder, &options, build.all, build.group.as_deref())?;
         }
 
+        Commands::Toolchain { action } => run_toolchain(action)?,
+        Commands::Provision { args } => run_provision(&args)?,
+
         Commands::Target { action } => match action {
             TargetAction::Add { target, toolchain } => {
                 helpers::section("Add Target");
 
                 let manager = ToolchainManager::new()?;
-                let target_triple = Target::resolve_alias(&target)?;
+                let config = config_or_defaults()?;
+                let target_triple =
+                    Target::resolve_alias_with(&target, &config.resolved_aliases())?;
+                Target::from_known_triple(&target_triple)?;
 
                 helpers::progress(format!(
                     "Adding target {} to toolchain {}...",
                     target_triple, toolchain
                 ));
 
-                manager.install_target(&toolchain, &target_triple)?;
-
-                helpers::success(format!("Target {} added successfully", target_triple));
+                if manager.is_target_installed(&toolchain, &target_triple)? {
+                    helpers::info(format!("Target {} is already installed", target_triple));
+                } else {
+                    manager.install_target(&toolchain, &target_triple)?;
+                    helpers::success(format!("Target {} added successfully", target_triple));
+                }
                 helpers::tip(format!(
                     "Use 'xcargo build --target {}' to build for this target",
                     target_triple
@@ -625,7 +3257,7 @@ fn run() -> Result<()> {
                                 println!("  No targets installed");
                             } else {
                                 for target in targets {
-                                    println!("  • {}", target);
+                                    println!("  {} {}", helpers::bullet(), target);
                                 }
                             }
                         }
@@ -635,22 +3267,34 @@ fn run() -> Result<()> {
                         }
                     }
                 } else {
+                    let bullet = helpers::bullet();
                     println!("Common cross-compilation targets:\n");
 
                     println!("Linux:");
-                    println!("  • x86_64-unknown-linux-gnu   (Linux x86_64)");
-                    println!("  • x86_64-unknown-linux-musl  (Linux x86_64, statically linked)");
-                    println!("  • aarch64-unknown-linux-gnu  (Linux ARM64)");
+                    println!("  {bullet} x86_64-unknown-linux-gnu   (Linux x86_64)");
+                    println!(
+                        "  {bullet} x86_64-unknown-linux-musl  (Linux x86_64, statically linked)"
+                    );
+                    println!("  {bullet} aarch64-unknown-linux-gnu  (Linux ARM64)");
                     println!();
 
                     println!("Windows:");
-                    println!("  • x86_64-pc-windows-gnu      (Windows x86_64, MinGW)");
-                    println!("  • x86_64-pc-windows-msvc     (Windows x86_64, MSVC)");
+                    println!("  {bullet} x86_64-pc-windows-gnu      (Windows x86_64, MinGW)");
+                    println!("  {bullet} x86_64-pc-windows-msvc     (Windows x86_64, MSVC)");
                     println!();
 
                     println!("macOS:");
-                    println!("  • x86_64-apple-darwin        (macOS x86_64)");
-                    println!("  • aarch64-apple-darwin       (macOS ARM64, M1/M2)");
+                    println!("  {bullet} x86_64-apple-darwin        (macOS x86_64)");
+                    println!("  {bullet} aarch64-apple-darwin       (macOS ARM64, M1/M2)");
+                    println!();
+
+                    println!("iOS (on macOS with Xcode):");
+                    println!("  {bullet} aarch64-apple-ios          (iOS devices)");
+                    println!(
+                        "  {bullet} aarch64-apple-ios-sim      (iOS simulator, Apple silicon)"
+                    );
+                    println!("  {bullet} x86_64-apple-ios           (iOS simulator, Intel)");
+                    println!("  {bullet} aarch64-apple-ios-macabi   (Mac Catalyst)");
                     println!();
 
                     helpers::hint("Use 'xcargo target list --installed' to see installed targets");
@@ -661,8 +3305,10 @@ fn run() -> Result<()> {
             TargetAction::Info { target } => {
                 helpers::section("Target Information");
 
-                let target_triple = Target::resolve_alias(&target)?;
-                match Target::from_triple(&target_triple) {
+                let config = config_or_defaults()?;
+                let target_triple =
+                    Target::resolve_alias_with(&target, &config.resolved_aliases())?;
+                match Target::from_known_triple(&target_triple) {
                     Ok(target) => {
                         println!("Triple:       {}", target.triple);
                         println!("Architecture: {}", target.arch);
@@ -671,7 +3317,26 @@ fn run() -> Result<()> {
                             "Environment:  {}",
                             target.env.as_deref().unwrap_or("default")
                         );
+                        if let Some(abi) = &target.abi {
+                            println!("ABI:          {abi}");
+                        }
+                        println!("Pointers:     {}-bit", target.pointer_width);
+                        if let Some(width) = target.max_atomic_width {
+                            println!("Atomics:      up to {width}-bit");
+                        }
                         println!("Tier:         {:?}", target.tier);
+                        if let Some(rust_tier) = target.rust_tier {
+                            println!("Rust tier:    {rust_tier} ({})", rust_tier.guarantees());
+                            let std = if RustTier::ships_std(&target.triple) {
+                                "installed with rustup"
+                            } else {
+                                "not shipped; core only, or build it with -Zbuild-std"
+                            };
+                            println!("Std:          {std}");
+                        }
+                        if let Some(sdk) = xcargo::toolchain::apple::AppleSdk::for_target(&target) {
+                            println!("Xcode SDK:    {}", sdk.name());
+                        }
                         println!();
 
                         let requirements = target.get_requirements();
@@ -700,21 +3365,38 @@ fn run() -> Result<()> {
                         }
 
                         println!();
-                        helpers::tip(format!(
-                            "Add this target: xcargo target add {}",
-                            target.triple
-                        ));
-                        helpers::tip(format!(
-                            "Build for this target: xcargo build --target {}",
-                            target.triple
-                        ));
-                    }
-                    Err(e) => {
-                        helpers::error(format!("Invalid target: {}", e));
-                        std::process::exit(1);
+                        print_target_plan(&config, &target);
                     }
+                    Err(e) => exit_with_error(&e),
                 }
             }
+
+            TargetAction::Alias { action } => {
+                run_alias(action)?;
+            }
+
+            TargetAction::Search {
+                pattern,
+                regex,
+                os,
+                arch,
+                env,
+                installed,
+            } => {
+                let pattern = pattern
+                    .map(|pattern| Pattern::new(&pattern, regex))
+                    .transpose()
+                    .map_err(|e| Error::Prompt(format!("Invalid pattern: {e}")))?;
+                run_target_search(&TargetQuery {
+                    pattern,
+                    os,
+                    arch,
+                    env,
+                    installed,
+                })?;
+            }
+
+            TargetAction::Matrix { all, json } => run_target_matrix(all, json)?,
         },
 
         Commands::Init { interactive } => {
@@ -725,16 +3407,61 @@ fn run() -> Result<()> {
             }
         }
 
-        Commands::Config { default } => {
+        Commands::Migrate {
+            from:
+                MigrateSource::Cross {
+                    file,
+                    to,
+                    force,
+                    dry_run,
+                },
+        } => run_migrate_cross(file, to, force, dry_run)?,
+
+        Commands::Config {
+            action: Some(ConfigAction::Validate { files }),
+            ..
+        } => run_config_validate(&files)?,
+
+        Commands::Config {
+            action: Some(ConfigAction::Effective { target }),
+            ..
+        } => run_config_effective(&target)?,
+
+        Commands::Config {
+            action: Some(ConfigAction::Schema),
+            ..
+        } => {
+            let schema = serde_json::to_string_pretty(&Config::schema())
+                .map_err(|e| Error::Config(format!("Failed to serialize schema: {e}")))?;
+            println!("{schema}");
+        }
+
+        Commands::Config {
+            action: None,
+            default,
+            show_origin,
+            format,
+        } => {
+            let Some(format) = ConfigFormat::from_name(&format) else {
+                helpers::error(format!("Unknown config format: {}", format));
+                helpers::hint("Use one of: toml, yaml, json");
+                std::process::exit(1);
+            };
+
             helpers::section("Configuration");
 
-            if default {
+            if show_origin {
+                show_config_origins()?;
+            } else if default {
                 let config = Config::default();
-                match config.to_toml() {
+                match config.to_format(format) {
                     Ok(toml) => {
                         println!("{}", toml);
                         println!();
-                        helpers::tip("Save this to xcargo.toml to customize your build");
+                        helpers::tip(format!(
+                            "Save this to {} to customize your build",
+                            format.file_name()
+                        ));
                     }
                     Err(e) => {
                         helpers::error(format!("Failed to generate config: {}", e));
@@ -743,10 +3470,23 @@ fn run() -> Result<()> {
                 }
             } else {
                 match Config::discover() {
-                    Ok(Some((config, path))) => {
-                        helpers::info(format!("Configuration from: {}", path.display()));
+                    Ok(Some((config, _))) => {
+                        let sources = ConfigDiscovery::find_sources()?;
+                        for (i, source) in sources.iter().rev().enumerate() {
+                            if i == 0 {
+                                helpers::info(format!("Configuration from: {source}"));
+                            } else {
+                                helpers::info(format!("Merged over: {source}"));
+                            }
+                        }
+                        if let Some(global) = Config::global_path().filter(|p| p.is_file()) {
+                            helpers::info(format!("Merged over: {}", global.display()));
+                        }
+                        if let Some(profile) = &config.profile {
+                            helpers::info(format!("With profile: {profile}"));
+                        }
                         println!();
-                        match config.to_toml() {
+                        match config.to_format(format) {
                             Ok(toml) => println!("{}", toml),
                             Err(e) => {
                                 helpers::error(format!("Failed to serialize config: {}", e));
@@ -755,10 +3495,16 @@ fn run() -> Result<()> {
                         }
                     }
                     Ok(None) => {
-                        helpers::info("No xcargo.toml found, using defaults");
+                        match Config::global_path().filter(|p| p.is_file()) {
+                            Some(global) => helpers::info(format!(
+                                "No xcargo.toml found, using {}",
+                                global.display()
+                            )),
+                            None => helpers::info("No xcargo.toml found, using defaults"),
+                        }
                         println!();
-                        let config = Config::default();
-                        match config.to_toml() {
+                        let config = Config::effective()?;
+                        match config.to_format(format) {
                             Ok(toml) => println!("{}", toml),
                             Err(e) => {
                                 helpers::error(format!("Failed to generate config: {}", e));
@@ -776,13 +3522,260 @@ fn run() -> Result<()> {
             }
         }
 
-        Commands::Doctor => {
-            xcargo::doctor::run()?;
+        Commands::Cache { action } => match action {
+            CacheAction::Stats { json } => {
+                let cache = BuildCache::new()?;
+                let stats = cache.stats();
+
+                if json {
+                    let output = serde_json::json!({
+                        "path": cache.cache_file_path(),
+                        "stats": stats,
+                        "entries": cache.entries(),
+                    });
+                    let output = serde_json::to_string_pretty(&output).map_err(|e| {
+                        Error::Config(format!("Failed to serialize cache stats: {e}"))
+                    })?;
+                    println!("{output}");
+                } else {
+                    helpers::section("Build Cache");
+                    println!("Location:   {}", cache.cache_file_path().display());
+                    println!("Entries:    {}", stats.total_entries);
+                    println!("Successful: {}", stats.successful_builds);
+                    println!("Failed:     {}", stats.failed_builds);
+
+                    if stats.total_entries > 0 {
+                        println!();
+                        for entry in cache.entries() {
+                            let status = helpers::status_marker(entry.success);
+                            println!(
+                                "  {} {:<32} {}",
+                                status,
+                                entry.target,
+                                format_timestamp(entry.timestamp)
+                            );
+                        }
+                    }
+                }
+            }
+
+            CacheAction::Clear { target } => {
+                let mut cache = BuildCache::new()?;
+
+                if let Some(target) = target {
+                    if cache.clear_target(&target) {
+                        cache.save()?;
+                        helpers::success(format!("Cleared cache entry for {}", target));
+                    } else {
+                        helpers::warning(format!("No cache entry for {}", target));
+                    }
+                } else {
+                    let count = cache.stats().total_entries;
+                    cache.clear();
+                    cache.save()?;
+                    helpers::success(format!("Cleared {} cache entries", count));
+                }
+            }
+
+            CacheAction::Show { target } => {
+                let cache = BuildCache::new()?;
+
+                match cache.get(&target) {
+                    Some(entry) => {
+                        helpers::section(format!("Cache entry: {}", entry.target));
+                        println!("Target:      {}", entry.target);
+                        println!(
+                            "Status:      {}",
+                            if entry.success { "success" } else { "failed" }
+                        );
+                        println!("Toolchain:   {}", entry.key.toolchain);
+                        if let Some(version) = &entry.key.rustc_version {
+                            println!("rustc:       {}", version);
+                        }
+                        println!("Profile:     {}", entry.key.profile);
+                        if !entry.key.features.is_empty() {
+                            println!("Features:    {}", entry.key.features.join(" "));
+                        }
+                        if let Some(version) = &entry.key.zig_version {
+                            println!("Zig:         {}", version);
+                        }
+                        println!("Source hash: {:016x}", entry.source_hash);
+                        println!("Key hash:    {:016x}", entry.key.hash());
+                        println!("Built at:    {}", format_timestamp(entry.timestamp));
+                    }
+                    None => {
+                        helpers::error(format!("No cache entry for {}", target));
+                        helpers::hint("Use 'xcargo cache stats' to list cached targets");
+                        std::process::exit(1);
+                    }
+                }
+            }
+        },
+
+        Commands::Clean {
+            target,
+            cache,
+            containers,
+            all,
+            cargo_args,
+        } => {
+            run_clean(target, cache, containers, all, cargo_args)?;
+        }
+
+        Commands::Uninstall { target, dry_run } => {
+            run_uninstall(target.as_deref(), dry_run)?;
+        }
+
+        Commands::Verify { target, release } => {
+            run_verify(&target, release, cli.verbose > 0)?;
+        }
+
+        Commands::Size {
+            target,
+            group,
+            release,
+            build,
+            no_build,
+            by_crate,
+        } => {
+            run_size(
+                &target,
+                group.as_deref(),
+                release,
+                build,
+                no_build,
+                by_crate,
+                cli.verbose,
+            )?;
+        }
+
+        Commands::Timings { target, html } => run_timings(&target, html, cli.verbose > 0)?,
+
+        Commands::History {
+            target,
+            operation,
+            failed,
+            all_projects,
+            last,
+            json,
+        } => {
+            let filter = xcargo::history::runs::Filter {
+                targets: target,
+                operation,
+                project: if all_projects {
+                    None
+                } else {
+                    Some(
+                        xcargo::history::project_dir()?
+                            .to_string_lossy()
+                            .into_owned(),
+                    )
+                },
+                failed,
+            };
+            run_history(&filter, last, json)?;
+        }
+
+        Commands::Flash {
+            target,
+            chip,
+            release,
+            bin,
+            no_build,
+        } => {
+            run_flash(
+                &target,
+                chip.as_deref(),
+                release,
+                bin.as_deref(),
+                no_build,
+                cli.verbose,
+            )?;
+        }
+
+        #[cfg(feature = "container")]
+        Commands::Docker { action } => match action {
+            DockerAction::Build { image, push } => run_docker_build(&image, push, cli.verbose)?,
+            DockerAction::Push { image, multi_arch } => {
+                run_docker_push(&image, multi_arch, cli.verbose)?;
+            }
+        },
+
+        #[cfg(feature = "container")]
+        Commands::Container { action } => match action {
+            ContainerAction::UpdateImages { target } => run_update_images(&target)?,
+        },
+
+        Commands::Logs {
+            target,
+            lines,
+            follow,
+        } => {
+            run_logs(target.as_deref(), lines, follow)?;
+        }
+
+        #[cfg(feature = "dist")]
+        Commands::Notarize { paths } => {
+            run_notarize(paths)?;
+        }
+
+        Commands::Replay { trace, dry_run } => {
+            run_replay(&trace, dry_run)?;
+        }
+
+        Commands::Explain { query } => {
+            run_explain(query.as_deref())?;
+        }
+
+        Commands::Doctor {
+            json,
+            report_file,
+            offline,
+        } => {
+            let offline = offline || xcargo::doctor::offline_from_env();
+            let result = xcargo::doctor::run_with(json, report_file.as_deref(), offline);
+            if json {
+                // Keep stdout valid JSON
+                if let Err(e) = result {
+                    std::process::exit(e.exit_code());
+                }
+            } else {
+                result?;
+            }
+        }
+
+        Commands::Report { path } => {
+            run_report(path)?;
+        }
+
+        #[cfg(feature = "remote")]
+        Commands::Daemon { socket } => match socket {
+            #[cfg(unix)]
+            Some(path) => xcargo::daemon::run_socket(&path)?,
+            #[cfg(not(unix))]
+            Some(_) => {
+                helpers::error("--socket is only supported on Unix hosts");
+                helpers::hint("Run 'xcargo daemon' without --socket to use stdio");
+                std::process::exit(1);
+            }
+            None => xcargo::daemon::run_stdio()?,
+        },
+
+        Commands::Completions { shell } => {
+            print_completions(&shell)?;
+        }
+
+        Commands::Man { out_dir } => {
+            print_man(out_dir.as_deref())?;
         }
 
         Commands::Version => {
             println!("xcargo {}", env!("CARGO_PKG_VERSION"));
-            println!("Cross-compilation, zero friction 🎯");
+            if xcargo::output::is_plain() {
+                println!("Cross-compilation, zero friction");
+            } else {
+                println!("Cross-compilation, zero friction 🎯");
+            }
             println!();
             println!("https://github.com/ibrahimcesar/xcargo");
         }
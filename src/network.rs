@@ -0,0 +1,192 @@
+//! Proxy and CA settings for xcargo's own downloads
+//!
+//! Standalone toolchains, the toolchain catalog and prebuilt dependencies
+//! are downloaded with curl, which already honors `HTTPS_PROXY` and
+//! friends. `[network] proxy` overrides them, and `[network] ca_bundle`
+//! trusts a corporate CA, for proxies that intercept TLS. Container builds
+//! get the host's proxy variables (`[network] passthrough`), the configured
+//! proxy and the CA bundle, mounted read-only, so cargo in the container
+//! reaches crates.io the same way.
+
+use crate::config::NetworkConfig;
+#[cfg(feature = "container")]
+use crate::container::ContainerConfig;
+use crate::error::{Error, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// Where the CA bundle is mounted in container builds
+#[cfg(feature = "container")]
+pub const CONTAINER_CA_BUNDLE: &str = "/etc/ssl/certs/xcargo-ca-bundle.pem";
+
+/// Variables pointing OpenSSL, curl and cargo at a CA bundle
+#[cfg(feature = "container")]
+const CA_BUNDLE_VARS: &[&str] = &["SSL_CERT_FILE", "CURL_CA_BUNDLE", "CARGO_HTTP_CAINFO"];
+
+/// Variables carrying the configured proxy into container builds
+#[cfg(feature = "container")]
+const PROXY_VARS: &[&str] = &["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy"];
+
+/// The configured CA bundle, which must exist
+fn ca_bundle(network: &NetworkConfig) -> Result<Option<&Path>> {
+    match network.ca_bundle.as_deref() {
+        Some(path) if !path.is_file() => Err(Error::Config(format!(
+            "CA bundle {} ([network] ca_bundle) does not exist",
+            path.display()
+        ))),
+        bundle => Ok(bundle),
+    }
+}
+
+/// curl, quiet and failing on HTTP errors, through the configured proxy and
+/// trusting the configured CA bundle
+///
+/// # Errors
+///
+/// Returns an error if `[network] ca_bundle` names a file that does not
+/// exist.
+pub fn curl(network: &NetworkConfig) -> Result<Command> {
+    let mut curl = Command::new("curl");
+    curl.arg("-fsSL");
+    if let Some(proxy) = &network.proxy {
+        curl.args(["--proxy", proxy]);
+    }
+    if let Some(bundle) = ca_bundle(network)? {
+        curl.arg("--cacert").arg(bundle);
+    }
+    Ok(curl)
+}
+
+/// Pass the proxy and CA settings on to a container build
+///
+/// Host variables named in `passthrough` come first, as `var` returns
+/// them; a configured proxy replaces the host's.
+///
+/// # Errors
+///
+/// Returns an error if `[network] ca_bundle` names a file that does not
+/// exist.
+#[cfg(feature = "container")]
+pub fn apply_to_container(
+    network: &NetworkConfig,
+    container: &mut ContainerConfig,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<()> {
+    for name in &network.passthrough {
+        if let Some(value) = var(name) {
+            container.env.push((name.clone(), value));
+        }
+    }
+    if let Some(proxy) = &network.proxy {
+        container
+            .env
+            .retain(|(name, _)| !PROXY_VARS.contains(&name.as_str()));
+        for name in PROXY_VARS {
+            container.env.push(((*name).to_string(), proxy.clone()));
+        }
+    }
+    if let Some(bundle) = ca_bundle(network)? {
+        container.volumes.push((
+            bundle.to_string_lossy().into_owned(),
+            format!("{CONTAINER_CA_BUNDLE}:ro"),
+        ));
+        for name in CA_BUNDLE_VARS {
+            container
+                .env
+                .push(((*name).to_string(), CONTAINER_CA_BUNDLE.to_string()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(command: &Command) -> Vec<String> {
+        command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_curl_uses_proxy_and_ca_bundle() {
+        assert_eq!(args(&curl(&NetworkConfig::default()).unwrap()), ["-fsSL"]);
+
+        let temp = tempfile::tempdir().unwrap();
+        let bundle = temp.path().join("corp.pem");
+        std::fs::write(&bundle, "").unwrap();
+        let network = NetworkConfig {
+            proxy: Some("http://proxy.corp:3128".to_string()),
+            ca_bundle: Some(bundle.clone()),
+            ..NetworkConfig::default()
+        };
+        assert_eq!(
+            args(&curl(&network).unwrap()),
+            [
+                "-fsSL",
+                "--proxy",
+                "http://proxy.corp:3128",
+                "--cacert",
+                &bundle.to_string_lossy()
+            ]
+        );
+
+        let missing = NetworkConfig {
+            ca_bundle: Some(temp.path().join("missing.pem")),
+            ..NetworkConfig::default()
+        };
+        assert!(curl(&missing).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "container")]
+    fn test_apply_to_container() {
+        let temp = tempfile::tempdir().unwrap();
+        let bundle = temp.path().join("corp.pem");
+        std::fs::write(&bundle, "").unwrap();
+        let host = |name: &str| {
+            ["HTTPS_PROXY", "NO_PROXY"]
+                .contains(&name)
+                .then(|| format!("host {name}"))
+        };
+
+        let mut container = ContainerConfig::default();
+        apply_to_container(&NetworkConfig::default(), &mut container, host).unwrap();
+        assert_eq!(
+            container.env,
+            [
+                ("HTTPS_PROXY".to_string(), "host HTTPS_PROXY".to_string()),
+                ("NO_PROXY".to_string(), "host NO_PROXY".to_string()),
+            ]
+        );
+        assert!(container.volumes.is_empty());
+
+        let network = NetworkConfig {
+            proxy: Some("http://proxy.corp:3128".to_string()),
+            ca_bundle: Some(bundle.clone()),
+            ..NetworkConfig::default()
+        };
+        let mut container = ContainerConfig::default();
+        apply_to_container(&network, &mut container, host).unwrap();
+        let env = |name: &str| {
+            container
+                .env
+                .iter()
+                .filter(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(env("HTTPS_PROXY"), ["http://proxy.corp:3128"]);
+        assert_eq!(env("NO_PROXY"), ["host NO_PROXY"]);
+        assert_eq!(env("CARGO_HTTP_CAINFO"), [CONTAINER_CA_BUNDLE]);
+        assert_eq!(
+            container.volumes,
+            [(
+                bundle.to_string_lossy().into_owned(),
+                format!("{CONTAINER_CA_BUNDLE}:ro")
+            )]
+        );
+    }
+}
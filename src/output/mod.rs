@@ -2,11 +2,27 @@
 //!
 //! This module provides utilities for displaying information, tips, hints,
 //! and progress to users in a delightful and informative way.
+//!
+//! Messages are `tracing` events with target [`OUTPUT_TARGET`] once a
+//! subscriber is installed (the CLI installs `xcargo::logging`, which
+//! renders them as before and can also write them to a JSON log file).
+//! Without a subscriber they are printed directly.
 
+#[cfg(feature = "tui")]
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
 
+/// Set while plain accessible output is active
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// `tracing` target of user-facing messages
+pub const OUTPUT_TARGET: &str = "xcargo::output";
+
+/// `tracing` target of lines printed by cargo, logged per target when enabled
+pub const CARGO_OUTPUT_TARGET: &str = "xcargo::cargo";
+
 /// Color codes for terminal output
 pub mod colors {
     /// Reset to default color
@@ -30,6 +46,83 @@ pub mod colors {
     pub const MAGENTA: &str = "\x1b[35m";
 }
 
+/// How output is rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    /// Colors, icons and spinners
+    #[default]
+    Default,
+    /// For screen readers and dumb terminals: no emoji, spinners, box
+    /// drawing or color, and every status spelled out in words
+    PlainAccessible,
+}
+
+impl OutputMode {
+    /// Parse a `--output` value
+    #[must_use]
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "default" => Some(Self::Default),
+            "plain-accessible" => Some(Self::PlainAccessible),
+            _ => None,
+        }
+    }
+
+    /// `--output` value for this mode
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::PlainAccessible => "plain-accessible",
+        }
+    }
+}
+
+/// Set the output mode for the rest of the process
+pub fn set_mode(mode: OutputMode) {
+    let plain = mode == OutputMode::PlainAccessible;
+    PLAIN.store(plain, Ordering::Relaxed);
+    if plain {
+        colored::control::set_override(false);
+    }
+}
+
+/// Remove terminal escape sequences (colors) from `text`
+#[must_use]
+pub fn strip_ansi(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // CSI sequences end with a letter
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+/// Current output mode
+#[must_use]
+pub fn mode() -> OutputMode {
+    if is_plain() {
+        OutputMode::PlainAccessible
+    } else {
+        OutputMode::Default
+    }
+}
+
+/// Check if plain accessible output is active
+#[must_use]
+pub fn is_plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
 /// Message type for different kinds of output
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MessageType {
@@ -91,6 +184,22 @@ impl MessageType {
             Self::Progress => "Progress",
         }
     }
+
+    /// Message type with `label`
+    #[must_use]
+    pub fn from_label(label: &str) -> Option<Self> {
+        [
+            Self::Success,
+            Self::Error,
+            Self::Warning,
+            Self::Info,
+            Self::Tip,
+            Self::Hint,
+            Self::Progress,
+        ]
+        .into_iter()
+        .find(|kind| kind.label() == label)
+    }
 }
 
 /// A formatted message with type, color, and content
@@ -145,24 +254,50 @@ impl Message {
         Self::new(MessageType::Progress, content)
     }
 
-    /// Print the message to stdout
+    /// Print the message to stdout, through the `tracing` subscriber if
+    /// one is installed
     pub fn print(&self) {
-        println!("{self}");
+        if !tracing::dispatcher::has_been_set() {
+            println!("{self}");
+            return;
+        }
+
+        let kind = self.msg_type.label();
+        match self.msg_type {
+            MessageType::Error => tracing::error!(target: OUTPUT_TARGET, kind, "{}", self.content),
+            MessageType::Warning => {
+                tracing::warn!(target: OUTPUT_TARGET, kind, "{}", self.content);
+            }
+            _ => tracing::info!(target: OUTPUT_TARGET, kind, "{}", self.content),
+        }
+    }
+
+    /// Render the message in `mode`
+    ///
+    /// Plain accessible output replaces the colored icon with the
+    /// message's label (e.g., "WARNING: ...").
+    #[must_use]
+    pub fn render(&self, mode: OutputMode) -> String {
+        match mode {
+            OutputMode::Default => format!(
+                "{}{}{} {}{}{}",
+                colors::BOLD,
+                self.msg_type.color(),
+                self.msg_type.icon(),
+                colors::RESET,
+                self.content,
+                colors::RESET
+            ),
+            OutputMode::PlainAccessible => {
+                format!("{}: {}", self.msg_type.label().to_uppercase(), self.content)
+            }
+        }
     }
 }
 
 impl fmt::Display for Message {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{}{}{} {}{}{}",
-            colors::BOLD,
-            self.msg_type.color(),
-            self.msg_type.icon(),
-            colors::RESET,
-            self.content,
-            colors::RESET
-        )
+        f.write_str(&self.render(mode()))
     }
 }
 
@@ -208,6 +343,10 @@ pub mod helpers {
     /// Print a section header
     pub fn section(title: impl Into<String>) {
         let title = title.into();
+        if super::is_plain() {
+            println!("\n{title}");
+            return;
+        }
         println!(
             "\n{}{}{}{}",
             colors::BOLD,
@@ -217,14 +356,52 @@ pub mod helpers {
         );
         println!("{}", "─".repeat(title.len()));
     }
+
+    /// Print a horizontal rule between blocks of output (omitted in plain mode)
+    pub fn separator() {
+        if !super::is_plain() {
+            println!("{}", "─".repeat(50));
+        }
+    }
+
+    /// List item marker: "-" in plain mode, otherwise a bullet
+    #[must_use]
+    pub fn bullet() -> &'static str {
+        if super::is_plain() {
+            "-"
+        } else {
+            "•"
+        }
+    }
+
+    /// "PASS" or "FAIL" in plain mode, otherwise a check mark or cross
+    #[must_use]
+    pub fn status_marker(success: bool) -> &'static str {
+        match (super::is_plain(), success) {
+            (true, true) => "PASS",
+            (true, false) => "FAIL",
+            (false, true) => "✓",
+            (false, false) => "✗",
+        }
+    }
 }
 
 /// Progress bar utilities for build operations
 pub mod progress {
-    use super::{colors, Duration, Instant, MultiProgress, ProgressBar, ProgressStyle};
+    use super::{colors, is_plain, Duration, Instant};
+    #[cfg(feature = "tui")]
+    use super::{MultiProgress, ProgressBar, ProgressStyle};
+
+    /// Whether progress is printed as lines rather than animated
+    ///
+    /// Without the `tui` feature there are no spinners.
+    fn lines_only() -> bool {
+        is_plain() || cfg!(not(feature = "tui"))
+    }
 
     /// A timed build progress tracker
     pub struct BuildProgress {
+        #[cfg(feature = "tui")]
         bar: ProgressBar,
         start_time: Instant,
         target: String,
@@ -234,20 +411,17 @@ pub mod progress {
         /// Create a new build progress spinner
         #[must_use]
         pub fn new(target: &str, operation: &str) -> Self {
-            let bar = ProgressBar::new_spinner();
-            bar.set_style(
-                ProgressStyle::default_spinner()
-                    .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏")
-                    .template(&format!(
-                        "{{spinner:.cyan}} {operation} {{msg:.bold}} [{{elapsed_precise}}]"
-                    ))
-                    .unwrap(),
-            );
-            bar.set_message(target.to_string());
-            bar.enable_steady_tick(Duration::from_millis(80));
+            if lines_only() {
+                println!("{operation} {target}");
+            }
 
             Self {
-                bar,
+                #[cfg(feature = "tui")]
+                bar: if lines_only() {
+                    ProgressBar::hidden()
+                } else {
+                    spinner(ProgressBar::new_spinner(), operation, target, "")
+                },
                 start_time: Instant::now(),
                 target: target.to_string(),
             }
@@ -273,38 +447,71 @@ pub mod progress {
 
         /// Update the message
         pub fn set_message(&self, msg: &str) {
+            #[cfg(feature = "tui")]
             self.bar.set_message(msg.to_string());
+            #[cfg(not(feature = "tui"))]
+            let _ = msg;
+        }
+
+        /// Show how long the build is expected to take (e.g., "~4m based
+        /// on last 3 builds")
+        pub fn show_estimate(&self, estimate: &str) {
+            if lines_only() {
+                println!("ETA {}: {estimate}", self.target);
+            } else {
+                #[cfg(feature = "tui")]
+                self.bar.set_message(format!(
+                    "{} {}{estimate}{}",
+                    self.target,
+                    colors::DIM,
+                    colors::RESET
+                ));
+            }
         }
 
         /// Mark as finished with success
         pub fn finish_success(&self) {
             let elapsed = self.start_time.elapsed();
-            self.bar.finish_with_message(format!(
-                "{}{}{} {} {}({}){}",
-                colors::GREEN,
-                "✓",
-                colors::RESET,
-                self.target,
-                colors::DIM,
-                format_duration(elapsed),
-                colors::RESET
-            ));
+            if lines_only() {
+                println!("PASS {} ({})", self.target, format_duration(elapsed));
+            } else {
+                #[cfg(feature = "tui")]
+                self.bar.finish_with_message(format!(
+                    "{}{}{} {} {}({}){}",
+                    colors::GREEN,
+                    "✓",
+                    colors::RESET,
+                    self.target,
+                    colors::DIM,
+                    format_duration(elapsed),
+                    colors::RESET
+                ));
+            }
         }
 
         /// Mark as finished with error
         pub fn finish_error(&self, error: &str) {
             let elapsed = self.start_time.elapsed();
-            self.bar.finish_with_message(format!(
-                "{}{}{} {} - {} {}({}){}",
-                colors::RED,
-                "✗",
-                colors::RESET,
-                self.target,
-                error,
-                colors::DIM,
-                format_duration(elapsed),
-                colors::RESET
-            ));
+            if lines_only() {
+                println!(
+                    "FAIL {} - {error} ({})",
+                    self.target,
+                    format_duration(elapsed)
+                );
+            } else {
+                #[cfg(feature = "tui")]
+                self.bar.finish_with_message(format!(
+                    "{}{}{} {} - {} {}({}){}",
+                    colors::RED,
+                    "✗",
+                    colors::RESET,
+                    self.target,
+                    error,
+                    colors::DIM,
+                    format_duration(elapsed),
+                    colors::RESET
+                ));
+            }
         }
 
         /// Get elapsed duration
@@ -316,6 +523,7 @@ pub mod progress {
 
     /// Multi-target progress tracker for parallel builds
     pub struct MultiTargetProgress {
+        #[cfg(feature = "tui")]
         multi: MultiProgress,
         start_time: Instant,
     }
@@ -325,26 +533,27 @@ pub mod progress {
         #[must_use]
         pub fn new() -> Self {
             Self {
+                #[cfg(feature = "tui")]
                 multi: MultiProgress::new(),
                 start_time: Instant::now(),
             }
         }
 
         /// Add a target progress bar
+        #[cfg(feature = "tui")]
         #[must_use]
         pub fn add_target(&self, target: &str, operation: &str) -> ProgressBar {
-            let bar = self.multi.add(ProgressBar::new_spinner());
-            bar.set_style(
-                ProgressStyle::default_spinner()
-                    .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏")
-                    .template(&format!(
-                        "  {{spinner:.cyan}} {operation} {{msg:.bold}} [{{elapsed_precise}}]"
-                    ))
-                    .unwrap(),
-            );
-            bar.set_message(target.to_string());
-            bar.enable_steady_tick(Duration::from_millis(80));
-            bar
+            if is_plain() {
+                println!("{operation} {target}");
+                return self.multi.add(ProgressBar::hidden());
+            }
+
+            spinner(
+                self.multi.add(ProgressBar::new_spinner()),
+                operation,
+                target,
+                "  ",
+            )
         }
 
         /// Get total elapsed time
@@ -357,6 +566,20 @@ pub mod progress {
         pub fn finish_summary(&self, successes: usize, failures: usize) {
             let elapsed = self.elapsed();
             println!();
+            if is_plain() {
+                if failures == 0 {
+                    println!(
+                        "PASS: All {successes} targets completed in {}",
+                        format_duration(elapsed)
+                    );
+                } else {
+                    println!(
+                        "FAIL: {successes} succeeded, {failures} failed in {}",
+                        format_duration(elapsed)
+                    );
+                }
+                return;
+            }
             if failures == 0 {
                 println!(
                     "{}{}✓{} All {} targets completed in {}",
@@ -386,6 +609,22 @@ pub mod progress {
         }
     }
 
+    /// Style `bar` as a ticking spinner for `operation` on `target`
+    #[cfg(feature = "tui")]
+    fn spinner(bar: ProgressBar, operation: &str, target: &str, indent: &str) -> ProgressBar {
+        bar.set_style(
+            ProgressStyle::default_spinner()
+                .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏")
+                .template(&format!(
+                    "{indent}{{spinner:.cyan}} {operation} {{msg:.bold}} [{{elapsed_precise}}]"
+                ))
+                .unwrap(),
+        );
+        bar.set_message(target.to_string());
+        bar.enable_steady_tick(Duration::from_millis(80));
+        bar
+    }
+
     /// Format a duration in a human-readable way
     #[must_use]
     pub fn format_duration(duration: Duration) -> String {
@@ -403,6 +642,45 @@ pub mod progress {
         }
     }
 
+    /// Format a byte count in a human-readable way
+    #[must_use]
+    pub fn format_bytes(bytes: u64) -> String {
+        const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+        if bytes < 1024 {
+            return format!("{bytes} B");
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let mut value = bytes as f64 / 1024.0;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        format!("{value:.1} {}", UNITS[unit])
+    }
+
+    /// Format a Unix timestamp (seconds) as a UTC date and time
+    #[must_use]
+    pub fn format_timestamp(secs: u64) -> String {
+        let days = i64::try_from(secs / 86_400).unwrap_or(i64::MAX);
+        let rem = secs % 86_400;
+        let (hour, minute, second) = (rem / 3600, (rem % 3600) / 60, rem % 60);
+
+        // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+
+        format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02}:{second:02} UTC")
+    }
+
     /// Simple timer for tracking operation duration
     pub struct Timer {
         start: Instant,
@@ -427,6 +705,14 @@ pub mod progress {
 
         /// Print elapsed time
         pub fn print_elapsed(&self) {
+            if is_plain() {
+                println!(
+                    "{} completed in {}",
+                    self.label,
+                    format_duration(self.elapsed())
+                );
+                return;
+            }
             println!(
                 "{}{}⏱{} {} completed in {}{}{}",
                 colors::BOLD,
@@ -441,36 +727,545 @@ pub mod progress {
     }
 }
 
+/// Collapsible log sections for CI providers
+///
+/// Cargo output for a single target can run to thousands of lines. When
+/// running under GitHub Actions, GitLab CI or Buildkite, each target's
+/// output is wrapped in the provider's log group markers so it folds in
+/// the web UI. Set `XCARGO_LOG_GROUPS=0` to disable.
+pub mod ci {
+    use crate::cancel::Stream;
+    use crate::timings::now;
+    use serde_json::Value;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// Set while a group is open; providers do not support nesting
+    static GROUP_OPEN: AtomicBool = AtomicBool::new(false);
+
+    /// Set once the TeamCity inspection type has been declared
+    static INSPECTION_DECLARED: AtomicBool = AtomicBool::new(false);
+
+    /// Cargo message format that keeps the rendered diagnostics
+    pub const JSON_MESSAGE_FORMAT: &str = "--message-format=json-diagnostic-rendered-ansi";
+
+    /// CI provider with log folding support
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum CiProvider {
+        /// GitHub Actions (`::group::`)
+        GitHub,
+        /// GitLab CI (`section_start` / `section_end`)
+        GitLab,
+        /// Buildkite (`---`)
+        Buildkite,
+        /// TeamCity (`##teamcity[blockOpened]`)
+        TeamCity,
+    }
+
+    impl CiProvider {
+        /// Detect the provider from the environment
+        #[must_use]
+        pub fn detect() -> Option<Self> {
+            Self::from_env(|key| std::env::var(key).ok())
+        }
+
+        /// Detect the provider using `lookup` to read variables
+        pub fn from_env(lookup: impl Fn(&str) -> Option<String>) -> Option<Self> {
+            if lookup("XCARGO_LOG_GROUPS").is_some_and(|v| v == "0" || v == "false") {
+                return None;
+            }
+
+            let is_set = |key: &str| lookup(key).is_some_and(|v| v == "true");
+            if is_set("GITHUB_ACTIONS") {
+                Some(Self::GitHub)
+            } else if is_set("GITLAB_CI") {
+                Some(Self::GitLab)
+            } else if is_set("BUILDKITE") {
+                Some(Self::Buildkite)
+            } else if lookup("TEAMCITY_VERSION").is_some_and(|v| !v.is_empty()) {
+                Some(Self::TeamCity)
+            } else {
+                None
+            }
+        }
+
+        /// Line that opens a collapsed group
+        #[must_use]
+        pub fn start_marker(&self, id: &str, title: &str, timestamp: u64) -> String {
+            match self {
+                Self::GitHub => format!("::group::{title}"),
+                Self::GitLab => {
+                    format!("\x1b[0Ksection_start:{timestamp}:{id}[collapsed=true]\r\x1b[0K{title}")
+                }
+                Self::Buildkite => format!("--- {title}"),
+                Self::TeamCity => {
+                    format!("##teamcity[blockOpened name='{}']", teamcity_escape(title))
+                }
+            }
+        }
+
+        /// Line that closes a group, if the provider needs one
+        #[must_use]
+        pub fn end_marker(&self, id: &str, title: &str, timestamp: u64) -> Option<String> {
+            match self {
+                Self::GitHub => Some("::endgroup::".to_string()),
+                Self::GitLab => Some(format!("\x1b[0Ksection_end:{timestamp}:{id}\r\x1b[0K")),
+                Self::Buildkite => None,
+                Self::TeamCity => Some(format!(
+                    "##teamcity[blockClosed name='{}']",
+                    teamcity_escape(title)
+                )),
+            }
+        }
+    }
+
+    /// Check if running on CI (`CI` set, or a known provider), using `lookup`
+    pub fn is_ci(lookup: impl Fn(&str) -> Option<String>) -> bool {
+        let is_set =
+            |key: &str| lookup(key).is_some_and(|v| !v.is_empty() && v != "false" && v != "0");
+        [
+            "CI",
+            "GITHUB_ACTIONS",
+            "GITLAB_CI",
+            "BUILDKITE",
+            "TEAMCITY_VERSION",
+        ]
+        .into_iter()
+        .any(is_set)
+    }
+
+    /// An open log group, closed when dropped
+    pub struct LogGroup {
+        provider: Option<CiProvider>,
+        id: String,
+        title: String,
+    }
+
+    impl LogGroup {
+        /// Open a group titled `title` if running under a supported CI provider
+        ///
+        /// Groups do not nest: while one is open (e.g., parallel builds),
+        /// further groups are no-ops.
+        pub fn start(title: impl Into<String>) -> Self {
+            let title = title.into();
+            let provider = CiProvider::detect().filter(|_| {
+                GROUP_OPEN
+                    .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+                    .is_ok()
+            });
+
+            let id = section_id(&title);
+            if let Some(provider) = provider {
+                println!("{}", provider.start_marker(&id, &title, now()));
+            }
+            Self {
+                provider,
+                id,
+                title,
+            }
+        }
+    }
+
+    impl Drop for LogGroup {
+        fn drop(&mut self) {
+            if let Some(provider) = self.provider {
+                if let Some(marker) = provider.end_marker(&self.id, &self.title, now()) {
+                    println!("{marker}");
+                }
+                GROUP_OPEN.store(false, Ordering::SeqCst);
+            }
+        }
+    }
+
+    /// Format of inline annotations for compiler diagnostics
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Annotations {
+        /// GitHub Actions workflow commands (`::error file=...,line=...::`)
+        GitHub,
+        /// TeamCity inspections (`##teamcity[inspection ...]`)
+        TeamCity,
+    }
+
+    impl Annotations {
+        /// Detect the annotation format from the environment
+        #[must_use]
+        pub fn detect() -> Option<Self> {
+            Self::from_env(|key| std::env::var(key).ok())
+        }
+
+        /// Detect the format using `lookup` to read variables
+        ///
+        /// `XCARGO_ANNOTATIONS` set to `github` or `teamcity` forces a
+        /// format, and `0` or `false` turns annotations off.
+        pub fn from_env(lookup: impl Fn(&str) -> Option<String>) -> Option<Self> {
+            match lookup("XCARGO_ANNOTATIONS").as_deref() {
+                Some("0" | "false") => return None,
+                Some("github") => return Some(Self::GitHub),
+                Some("teamcity") => return Some(Self::TeamCity),
+                _ => {}
+            }
+
+            if lookup("GITHUB_ACTIONS").is_some_and(|v| v == "true") {
+                Some(Self::GitHub)
+            } else if lookup("TEAMCITY_VERSION").is_some_and(|v| !v.is_empty()) {
+                Some(Self::TeamCity)
+            } else {
+                None
+            }
+        }
+
+        /// Annotation lines for `diagnostic`
+        #[must_use]
+        pub fn render(&self, diagnostic: &Diagnostic) -> Vec<String> {
+            match self {
+                Self::GitHub => {
+                    let mut properties = vec![
+                        format!("file={}", github_property(&diagnostic.file)),
+                        format!("line={}", diagnostic.line),
+                        format!("col={}", diagnostic.column),
+                    ];
+                    if diagnostic.end_line >= diagnostic.line {
+                        properties.push(format!("endLine={}", diagnostic.end_line));
+                    }
+                    if let Some(code) = &diagnostic.code {
+                        properties.push(format!("title={}", github_property(code)));
+                    }
+                    vec![format!(
+                        "::{} {}::{}",
+                        if diagnostic.is_error {
+                            "error"
+                        } else {
+                            "warning"
+                        },
+                        properties.join(","),
+                        github_data(&diagnostic.message)
+                    )]
+                }
+                Self::TeamCity => {
+                    let mut lines = Vec::new();
+                    if !INSPECTION_DECLARED.swap(true, Ordering::SeqCst) {
+                        lines.push(
+                            "##teamcity[inspectionType id='rustc' name='rustc' \
+                             category='Compiler diagnostics' description='Errors and warnings from rustc']"
+                                .to_string(),
+                        );
+                    }
+                    lines.push(format!(
+                        "##teamcity[inspection typeId='rustc' message='{}' file='{}' line='{}' SEVERITY='{}']",
+                        teamcity_escape(&diagnostic.message),
+                        teamcity_escape(&diagnostic.file),
+                        diagnostic.line,
+                        if diagnostic.is_error { "ERROR" } else { "WARNING" }
+                    ));
+                    lines
+                }
+            }
+        }
+
+        /// What to print for one line of cargo's stdout, see [`translate`]
+        #[must_use]
+        pub fn translate(&self, line: &str) -> Vec<(Stream, String)> {
+            translate(line, Some(*self))
+        }
+    }
+
+    /// What to print for one line of cargo's stdout when cargo runs with
+    /// [`JSON_MESSAGE_FORMAT`]
+    ///
+    /// Compiler messages become their rendered text on stderr, as cargo
+    /// would print it, followed by `annotations` for errors and warnings.
+    /// Other cargo messages are dropped and anything that is not a cargo
+    /// message (e.g., test output) is passed through.
+    #[must_use]
+    pub fn translate(line: &str, annotations: Option<Annotations>) -> Vec<(Stream, String)> {
+        let Ok(message) = serde_json::from_str::<Value>(line) else {
+            return vec![(Stream::Stdout, line.to_string())];
+        };
+        let Some(reason) = message.get("reason").and_then(Value::as_str) else {
+            return vec![(Stream::Stdout, line.to_string())];
+        };
+        if reason != "compiler-message" {
+            return Vec::new();
+        }
+
+        let mut shown = Vec::new();
+        if let Some(rendered) = message["message"]["rendered"].as_str() {
+            shown.push((Stream::Stderr, rendered.trim_end().to_string()));
+        }
+        let diagnostic = Diagnostic::from_message(&message["message"]);
+        if let (Some(annotations), Some(diagnostic)) = (annotations, diagnostic) {
+            shown.extend(
+                annotations
+                    .render(&diagnostic)
+                    .into_iter()
+                    .map(|line| (Stream::Stdout, line)),
+            );
+        }
+        shown
+    }
+
+    /// A compiler error or warning at a source location
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Diagnostic {
+        /// Error rather than warning
+        pub is_error: bool,
+        /// Message without the source snippet
+        pub message: String,
+        /// Error code (e.g., `E0425`), if any
+        pub code: Option<String>,
+        /// File, relative to the workspace root
+        pub file: String,
+        /// First line (1-based)
+        pub line: u64,
+        /// Last line
+        pub end_line: u64,
+        /// First column (1-based)
+        pub column: u64,
+    }
+
+    impl Diagnostic {
+        /// Parse the `message` of a cargo `compiler-message`
+        ///
+        /// Only errors and warnings with a primary span are annotated.
+        #[must_use]
+        pub fn from_message(message: &Value) -> Option<Self> {
+            let is_error = match message["level"].as_str()? {
+                "error" | "error: internal compiler error" => true,
+                "warning" => false,
+                _ => return None,
+            };
+            let span = message["spans"]
+                .as_array()?
+                .iter()
+                .find(|span| span["is_primary"].as_bool() == Some(true))?;
+
+            Some(Self {
+                is_error,
+                message: message["message"].as_str()?.to_string(),
+                code: message["code"]["code"].as_str().map(str::to_string),
+                file: span["file_name"].as_str()?.to_string(),
+                line: span["line_start"].as_u64()?,
+                end_line: span["line_end"].as_u64()?,
+                column: span["column_start"].as_u64()?,
+            })
+        }
+    }
+
+    /// Escape the message part of a GitHub workflow command
+    fn github_data(text: &str) -> String {
+        text.replace('%', "%25")
+            .replace('\r', "%0D")
+            .replace('\n', "%0A")
+    }
+
+    /// Escape a property value of a GitHub workflow command
+    fn github_property(text: &str) -> String {
+        github_data(text).replace(':', "%3A").replace(',', "%2C")
+    }
+
+    /// Escape a TeamCity service message value
+    fn teamcity_escape(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            match c {
+                '|' => escaped.push_str("||"),
+                '\'' => escaped.push_str("|'"),
+                '[' => escaped.push_str("|["),
+                ']' => escaped.push_str("|]"),
+                '\n' => escaped.push_str("|n"),
+                '\r' => escaped.push_str("|r"),
+                c => escaped.push(c),
+            }
+        }
+        escaped
+    }
+
+    /// GitLab section names only allow `[a-z0-9_.-]`
+    fn section_id(title: &str) -> String {
+        title
+            .to_lowercase()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+            move |key| {
+                vars.iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| (*v).to_string())
+            }
+        }
+
+        #[test]
+        fn test_detect_provider() {
+            assert_eq!(
+                CiProvider::from_env(env(&[("GITHUB_ACTIONS", "true")])),
+                Some(CiProvider::GitHub)
+            );
+            assert_eq!(
+                CiProvider::from_env(env(&[("GITLAB_CI", "true")])),
+                Some(CiProvider::GitLab)
+            );
+            assert_eq!(
+                CiProvider::from_env(env(&[("BUILDKITE", "true")])),
+                Some(CiProvider::Buildkite)
+            );
+            assert_eq!(
+                CiProvider::from_env(env(&[("TEAMCITY_VERSION", "2024.03")])),
+                Some(CiProvider::TeamCity)
+            );
+            assert_eq!(CiProvider::from_env(env(&[("CI", "true")])), None);
+            assert_eq!(
+                CiProvider::from_env(env(&[
+                    ("GITHUB_ACTIONS", "true"),
+                    ("XCARGO_LOG_GROUPS", "0")
+                ])),
+                None
+            );
+        }
+
+        #[test]
+        fn test_is_ci() {
+            assert!(is_ci(env(&[("CI", "true")])));
+            assert!(is_ci(env(&[("GITLAB_CI", "true")])));
+            assert!(!is_ci(env(&[("CI", "false")])));
+            assert!(!is_ci(env(&[])));
+        }
+
+        #[test]
+        fn test_markers() {
+            let id = section_id("build x86_64-pc-windows-gnu");
+            assert_eq!(id, "build_x86_64-pc-windows-gnu");
+
+            assert_eq!(
+                CiProvider::GitHub.start_marker(&id, "build", 0),
+                "::group::build"
+            );
+            assert!(CiProvider::GitLab
+                .start_marker(&id, "build", 42)
+                .contains("section_start:42:build_x86_64-pc-windows-gnu[collapsed=true]"));
+            assert!(CiProvider::GitLab
+                .end_marker(&id, "build", 43)
+                .unwrap()
+                .contains("section_end:43:build_x86_64-pc-windows-gnu"));
+            assert_eq!(CiProvider::Buildkite.end_marker(&id, "build", 0), None);
+            assert_eq!(
+                CiProvider::TeamCity.start_marker(&id, "build [x86]", 0),
+                "##teamcity[blockOpened name='build |[x86|]']"
+            );
+        }
+
+        const COMPILER_MESSAGE: &str = r#"{"reason":"compiler-message","package_id":"app 0.1.0","message":{"message":"cannot find value `x` in this scope","code":{"code":"E0425","explanation":null},"level":"error","spans":[{"file_name":"src/main.rs","byte_start":20,"byte_end":21,"line_start":2,"line_end":2,"column_start":20,"column_end":21,"is_primary":true,"text":[],"label":"not found in this scope"}],"children":[],"rendered":"error[E0425]: cannot find value `x` in this scope\n --> src/main.rs:2:20\n"}}"#;
+
+        #[test]
+        fn test_detect_annotations() {
+            assert_eq!(
+                Annotations::from_env(env(&[("GITHUB_ACTIONS", "true")])),
+                Some(Annotations::GitHub)
+            );
+            assert_eq!(
+                Annotations::from_env(env(&[("TEAMCITY_VERSION", "2024.03")])),
+                Some(Annotations::TeamCity)
+            );
+            assert_eq!(
+                Annotations::from_env(env(&[
+                    ("GITHUB_ACTIONS", "true"),
+                    ("XCARGO_ANNOTATIONS", "false")
+                ])),
+                None
+            );
+            assert_eq!(
+                Annotations::from_env(env(&[("XCARGO_ANNOTATIONS", "teamcity")])),
+                Some(Annotations::TeamCity)
+            );
+            assert_eq!(Annotations::from_env(env(&[("CI", "true")])), None);
+        }
+
+        #[test]
+        fn test_translate_compiler_message() {
+            let shown = Annotations::GitHub.translate(COMPILER_MESSAGE);
+            assert_eq!(
+                shown,
+                [
+                    (
+                        Stream::Stderr,
+                        "error[E0425]: cannot find value `x` in this scope\n --> src/main.rs:2:20"
+                            .to_string()
+                    ),
+                    (
+                        Stream::Stdout,
+                        "::error file=src/main.rs,line=2,col=20,endLine=2,title=E0425::cannot find value `x` in this scope"
+                            .to_string()
+                    ),
+                ]
+            );
+
+            let artifact = r#"{"reason":"compiler-artifact","package_id":"app 0.1.0"}"#;
+            assert!(Annotations::GitHub.translate(artifact).is_empty());
+            assert_eq!(
+                Annotations::GitHub.translate("test tests::it_works ... ok"),
+                [(Stream::Stdout, "test tests::it_works ... ok".to_string())]
+            );
+        }
+
+        #[test]
+        fn test_teamcity_inspection() {
+            let message: Value = serde_json::from_str(COMPILER_MESSAGE).unwrap();
+            let diagnostic = Diagnostic::from_message(&message["message"]).unwrap();
+            let lines = Annotations::TeamCity.render(&diagnostic);
+            assert_eq!(
+                lines.last().unwrap(),
+                "##teamcity[inspection typeId='rustc' message='cannot find value `x` in this scope' \
+                 file='src/main.rs' line='2' SEVERITY='ERROR']"
+            );
+        }
+
+        #[test]
+        fn test_escaping() {
+            assert_eq!(github_data("50%\ndone"), "50%25%0Adone");
+            assert_eq!(github_property("a:b,c"), "a%3Ab%2Cc");
+            assert_eq!(teamcity_escape("it's [ok] |"), "it|'s |[ok|] ||");
+        }
+    }
+}
+
 /// Common tips for xcargo users
 pub mod tips {
+    use crate::i18n::MessageId;
+
     /// Tip about installing targets
-    pub const INSTALL_TARGET: &str = "Use 'xcargo target add <triple>' to install a new target";
+    pub const INSTALL_TARGET: MessageId = MessageId("tips.install_target");
 
     /// Tip about checking installed targets
-    pub const LIST_TARGETS: &str = "Use 'xcargo target list' to see all available targets";
+    pub const LIST_TARGETS: MessageId = MessageId("tips.list_targets");
 
     /// Tip about configuration
-    pub const CONFIG_FILE: &str = "Create an xcargo.toml file to customize build behavior";
+    pub const CONFIG_FILE: MessageId = MessageId("tips.config_file");
 
     /// Tip about parallel builds
-    pub const PARALLEL_BUILDS: &str =
-        "Enable parallel builds in xcargo.toml with 'parallel = true' for faster builds";
+    pub const PARALLEL_BUILDS: MessageId = MessageId("tips.parallel_builds");
 
     /// Tip about caching
-    pub const BUILD_CACHE: &str =
-        "xcargo caches builds by default. Use '--no-cache' to force a clean build";
+    pub const BUILD_CACHE: MessageId = MessageId("tips.build_cache");
 
     /// Tip about container builds
-    pub const CONTAINER_BUILDS: &str =
-        "xcargo uses containers only when necessary. Set 'force_container = true' to always use containers";
+    pub const CONTAINER_BUILDS: MessageId = MessageId("tips.container_builds");
 
     /// Tip about native builds
-    pub const NATIVE_BUILDS: &str =
-        "Native builds are 2-3x faster than container builds when possible";
+    pub const NATIVE_BUILDS: MessageId = MessageId("tips.native_builds");
 
     /// Tip about profiles
-    pub const BUILD_PROFILES: &str =
-        "Define custom build profiles in xcargo.toml for different scenarios (CI, release, etc.)";
+    pub const BUILD_PROFILES: MessageId = MessageId("tips.build_profiles");
 }
 
 #[cfg(test)]
@@ -487,6 +1282,15 @@ mod tests {
         assert_eq!(MessageType::Hint.icon(), "→");
     }
 
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(
+            strip_ansi("\x1b[1m\x1b[32m   Compiling\x1b[0m app v0.1.0"),
+            "   Compiling app v0.1.0"
+        );
+        assert_eq!(strip_ansi("plain"), "plain");
+    }
+
     #[test]
     fn test_message_creation() {
         let msg = Message::success("Build completed");
@@ -497,10 +1301,45 @@ mod tests {
         assert_eq!(msg.msg_type, MessageType::Tip);
     }
 
+    #[test]
+    fn test_plain_accessible_render() {
+        assert_eq!(
+            OutputMode::parse("plain-accessible"),
+            Some(OutputMode::PlainAccessible)
+        );
+        assert_eq!(OutputMode::parse("fancy"), None);
+
+        let plain = Message::warning("Zig not found").render(OutputMode::PlainAccessible);
+        assert_eq!(plain, "WARNING: Zig not found");
+        assert!(plain.is_ascii());
+
+        let default = Message::warning("Zig not found").render(OutputMode::Default);
+        assert!(default.contains('⚠'));
+        assert!(default.contains(colors::YELLOW));
+    }
+
     #[test]
     fn test_message_display() {
         let msg = Message::info("Testing message");
         let output = format!("{msg}");
         assert!(output.contains("Testing message"));
     }
+
+    #[test]
+    fn test_format_bytes() {
+        use progress::format_bytes;
+
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        use progress::format_timestamp;
+
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00:00 UTC");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14 22:13:20 UTC");
+    }
 }
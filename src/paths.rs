@@ -0,0 +1,156 @@
+//! Path handling that works on Windows hosts
+//!
+//! Windows limits ordinary paths to 260 characters (`MAX_PATH`) unless they
+//! carry the `\\?\` extended-length prefix, `std::fs::canonicalize` returns
+//! such verbatim paths even for short ones, and network shares appear as
+//! UNC paths (`\\server\share\...`). These helpers keep paths usable for
+//! display, file operations and container volume mounts. On other hosts
+//! they leave paths unchanged.
+
+use crate::error::{Error, Result};
+use std::path::{Path, PathBuf};
+
+/// Longest path Windows APIs accept without the extended-length prefix
+pub const MAX_PATH: usize = 260;
+
+const VERBATIM: &str = r"\\?\";
+const VERBATIM_UNC: &str = r"\\?\UNC\";
+
+/// Canonicalize `path`, without the verbatim prefix where it is not needed
+pub fn canonicalize(path: &Path) -> Result<PathBuf> {
+    Ok(simplify(&std::fs::canonicalize(path)?))
+}
+
+/// Strip the verbatim prefix from paths short enough not to need it
+///
+/// `\\?\C:\src\app` becomes `C:\src\app` and `\\?\UNC\server\share`
+/// becomes `\\server\share`, which tools like cargo and Docker expect.
+#[must_use]
+pub fn simplify(path: &Path) -> PathBuf {
+    PathBuf::from(simplify_str(&path.to_string_lossy()))
+}
+
+/// Add the extended-length prefix when a path would exceed `MAX_PATH`
+///
+/// Use for file operations on paths that may be deeply nested, such as
+/// artifact outputs. Only has an effect on Windows hosts.
+#[must_use]
+pub fn long_path(path: &Path) -> PathBuf {
+    if cfg!(windows) {
+        PathBuf::from(extend_str(&path.to_string_lossy()))
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Check if a path is on a network share (`\\server\share`)
+#[must_use]
+pub fn is_unc(path: &str) -> bool {
+    path.starts_with(VERBATIM_UNC) || (path.starts_with(r"\\") && !path.starts_with(VERBATIM))
+}
+
+/// Host side of a container volume mount
+///
+/// Drive-letter paths are written as `/c/src/app`, since `C:\src\app:/project`
+/// is ambiguous in `-v` syntax. Network shares cannot be bind-mounted and
+/// return an error.
+pub fn container_mount_path(host: &str) -> Result<String> {
+    if is_unc(host) {
+        return Err(Error::Container(format!(
+            "Cannot mount {host} into a container: network shares are not supported, \
+             copy the project to a local drive or map the share to a drive letter"
+        )));
+    }
+
+    let path = host.strip_prefix(VERBATIM).unwrap_or(host);
+    let bytes = path.as_bytes();
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        let drive = (bytes[0] as char).to_ascii_lowercase();
+        let rest = path[2..].replace('\\', "/");
+        let rest = rest.trim_end_matches('/');
+        return Ok(format!("/{drive}{rest}"));
+    }
+
+    Ok(path.to_string())
+}
+
+fn simplify_str(path: &str) -> String {
+    let simplified = if let Some(rest) = path.strip_prefix(VERBATIM_UNC) {
+        format!(r"\\{rest}")
+    } else if let Some(rest) = path.strip_prefix(VERBATIM) {
+        // Only drive paths have a plain form; leave e.g. \\?\Volume{..} alone
+        if rest.as_bytes().get(1) == Some(&b':') {
+            rest.to_string()
+        } else {
+            return path.to_string();
+        }
+    } else {
+        return path.to_string();
+    };
+
+    if simplified.len() < MAX_PATH {
+        simplified
+    } else {
+        path.to_string()
+    }
+}
+
+fn extend_str(path: &str) -> String {
+    if path.len() < MAX_PATH || path.starts_with(VERBATIM) {
+        return path.to_string();
+    }
+
+    if let Some(rest) = path.strip_prefix(r"\\") {
+        format!("{VERBATIM_UNC}{rest}")
+    } else if path.as_bytes().get(1) == Some(&b':') {
+        // The prefix disables `/` translation, so normalize separators
+        format!("{VERBATIM}{}", path.replace('/', "\\"))
+    } else {
+        // Relative paths cannot be extended
+        path.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simplify() {
+        assert_eq!(simplify_str(r"\\?\C:\src\app"), r"C:\src\app");
+        assert_eq!(
+            simplify_str(r"\\?\UNC\server\share\app"),
+            r"\\server\share\app"
+        );
+        assert_eq!(simplify_str("/home/user/app"), "/home/user/app");
+
+        let long = format!(r"\\?\C:\{}", "a".repeat(MAX_PATH));
+        assert_eq!(simplify_str(&long), long);
+    }
+
+    #[test]
+    fn test_extend() {
+        assert_eq!(extend_str(r"C:\src\app"), r"C:\src\app");
+
+        let deep = format!(r"C:\{}", "a".repeat(MAX_PATH));
+        assert_eq!(extend_str(&deep), format!(r"\\?\{deep}"));
+
+        let share = format!(r"\\server\share\{}", "a".repeat(MAX_PATH));
+        assert!(extend_str(&share).starts_with(r"\\?\UNC\server\share\"));
+    }
+
+    #[test]
+    fn test_container_mount_path() {
+        assert_eq!(
+            container_mount_path(r"C:\Users\me\app").unwrap(),
+            "/c/Users/me/app"
+        );
+        assert_eq!(container_mount_path(r"\\?\D:\work\").unwrap(), "/d/work");
+        assert_eq!(
+            container_mount_path("/home/me/app").unwrap(),
+            "/home/me/app"
+        );
+        assert!(container_mount_path(r"\\server\share\app").is_err());
+        assert!(container_mount_path(r"\\?\UNC\server\share\app").is_err());
+    }
+}
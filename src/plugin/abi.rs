@@ -0,0 +1,413 @@
+//! C ABI between xcargo and plugins loaded from shared libraries
+//!
+//! A plugin compiled separately from xcargo (see [`super::local`]) may be
+//! built by a different compiler, so the two cannot share Rust trait
+//! objects. The library instead exports five `extern "C"` functions that
+//! exchange JSON strings: the ABI version, the plugin's
+//! [`PluginMetadata`], a call into one hook with the [`PluginContext`], a
+//! question to one of its build strategies, and a function freeing the
+//! strings it returned. [`export_plugin!`] generates them for a [`Plugin`]:
+//!
+//! ```rust,ignore
+//! use xcargo::error::{Error, Result};
+//! use xcargo::plugin::{Plugin, PluginContext};
+//!
+//! #[derive(Default)]
+//! struct RequireLicense;
+//!
+//! impl Plugin for RequireLicense {
+//!     fn name(&self) -> &str {
+//!         "require-license"
+//!     }
+//!
+//!     fn on_pre_build(&self, ctx: &PluginContext) -> Result<()> {
+//!         if ctx.project_root.join("LICENSE").exists() {
+//!             Ok(())
+//!         } else {
+//!             Err(Error::Config("LICENSE file missing".to_string()))
+//!         }
+//!     }
+//! }
+//!
+//! xcargo::export_plugin!(RequireLicense::default);
+//! ```
+//!
+//! [`export_plugin!`]: crate::export_plugin
+
+use super::context::{BuildCommand, PluginContext, PluginMetadata};
+use super::hooks::PluginHook;
+use super::traits::Plugin;
+use serde::{Deserialize, Serialize};
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+
+/// Version of this ABI; libraries built against another one are rejected
+pub const ABI_VERSION: u32 = 2;
+
+/// `extern "C" fn() -> u32`: the [`ABI_VERSION`] the library was built with
+pub const SYMBOL_ABI_VERSION: &[u8] = b"xcargo_plugin_abi_version\0";
+
+/// `extern "C" fn() -> *mut c_char`: the plugin's [`PluginMetadata`] as JSON
+pub const SYMBOL_INFO: &[u8] = b"xcargo_plugin_info\0";
+
+/// `extern "C" fn(hook, context, error) -> *mut c_char`: run a hook, with
+/// its name, the [`PluginContext`] as JSON and the build error (or null);
+/// returns null on success and the error message otherwise
+pub const SYMBOL_CALL: &[u8] = b"xcargo_plugin_call\0";
+
+/// `extern "C" fn(request) -> *mut c_char`: ask one of the plugin's build
+/// strategies, with a [`StrategyRequest`] as JSON; returns a
+/// [`StrategyResponse`] as JSON
+pub const SYMBOL_STRATEGY: &[u8] = b"xcargo_plugin_strategy\0";
+
+/// `extern "C" fn(*mut c_char)`: free a string returned by the library
+pub const SYMBOL_FREE: &[u8] = b"xcargo_plugin_free\0";
+
+/// Signature of [`SYMBOL_ABI_VERSION`]
+pub type AbiVersionFn = unsafe extern "C" fn() -> u32;
+/// Signature of [`SYMBOL_INFO`]
+pub type InfoFn = unsafe extern "C" fn() -> *mut c_char;
+/// Signature of [`SYMBOL_CALL`]
+pub type CallFn = unsafe extern "C" fn(*const c_char, *const c_char, *const c_char) -> *mut c_char;
+/// Signature of [`SYMBOL_STRATEGY`]
+pub type StrategyFn = unsafe extern "C" fn(*const c_char) -> *mut c_char;
+/// Signature of [`SYMBOL_FREE`]
+pub type FreeFn = unsafe extern "C" fn(*mut c_char);
+
+/// A question to a plugin's build strategy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "call", rename_all = "kebab-case")]
+pub enum StrategyRequest {
+    /// [`Plugin::strategy_available`]
+    Available {
+        /// Strategy name
+        strategy: String,
+        /// Build the strategy is asked about
+        context: PluginContext,
+    },
+    /// [`Plugin::strategy_command`]
+    Command {
+        /// Strategy name
+        strategy: String,
+        /// Build the command is for
+        context: PluginContext,
+        /// The cargo command xcargo would run
+        command: BuildCommand,
+    },
+}
+
+/// A plugin's answer to a [`StrategyRequest`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StrategyResponse {
+    /// The strategy can build the target, and why
+    Available(String),
+    /// The command to run
+    Command(BuildCommand),
+    /// What went wrong, or what the strategy is missing
+    Error(String),
+}
+
+/// Plugin side of the ABI, called by the functions [`export_plugin!`]
+/// generates
+///
+/// [`export_plugin!`]: crate::export_plugin
+#[doc(hidden)]
+pub mod guest {
+    use super::{
+        c_char, panic, AssertUnwindSafe, CStr, CString, Plugin, PluginContext, PluginHook,
+        PluginMetadata, StrategyRequest, StrategyResponse,
+    };
+
+    /// The plugin's metadata as JSON, or null if it panicked
+    #[must_use]
+    pub fn info(plugin: fn() -> &'static dyn Plugin) -> *mut c_char {
+        let json = panic::catch_unwind(|| {
+            let plugin = plugin();
+            let mut metadata =
+                PluginMetadata::new(plugin.name().to_string(), plugin.version().to_string());
+            metadata.description = plugin.description().to_string();
+            metadata.author = plugin.author().to_string();
+            metadata.strategies = plugin.strategies();
+            serde_json::to_string(&metadata).unwrap_or_default()
+        });
+        json.map_or(std::ptr::null_mut(), |json| into_raw(&json))
+    }
+
+    /// Run `hook` on the plugin, returning null on success or the error
+    ///
+    /// # Safety
+    ///
+    /// `hook` and `context` must be valid C strings, and `error` a valid C
+    /// string or null.
+    #[must_use]
+    pub unsafe fn call(
+        plugin: fn() -> &'static dyn Plugin,
+        hook: *const c_char,
+        context: *const c_char,
+        error: *const c_char,
+    ) -> *mut c_char {
+        let hook = CStr::from_ptr(hook).to_string_lossy().into_owned();
+        let context = CStr::from_ptr(context).to_string_lossy().into_owned();
+        let error =
+            (!error.is_null()).then(|| CStr::from_ptr(error).to_string_lossy().into_owned());
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let Some(hook) = PluginHook::from_name(&hook) else {
+                return Err(format!("Unknown hook '{hook}'"));
+            };
+            let ctx: PluginContext =
+                serde_json::from_str(&context).map_err(|e| format!("Invalid context: {e}"))?;
+            let plugin = plugin();
+            match &error {
+                Some(error) => hook.execute_with_error(plugin, &ctx, error),
+                None => hook.execute(plugin, &ctx),
+            }
+            .map_err(|e| e.to_string())
+        }));
+        match result {
+            Ok(Ok(())) => std::ptr::null_mut(),
+            Ok(Err(message)) => into_raw(&message),
+            Err(_) => into_raw("plugin panicked"),
+        }
+    }
+
+    /// Answer a [`StrategyRequest`] given as JSON with a
+    /// [`StrategyResponse`] as JSON
+    ///
+    /// # Safety
+    ///
+    /// `request` must be a valid C string.
+    #[must_use]
+    pub unsafe fn strategy(
+        plugin: fn() -> &'static dyn Plugin,
+        request: *const c_char,
+    ) -> *mut c_char {
+        let request = CStr::from_ptr(request).to_string_lossy().into_owned();
+
+        let response = panic::catch_unwind(AssertUnwindSafe(|| {
+            let request: StrategyRequest = match serde_json::from_str(&request) {
+                Ok(request) => request,
+                Err(e) => return StrategyResponse::Error(format!("Invalid request: {e}")),
+            };
+            let plugin = plugin();
+            let result = match request {
+                StrategyRequest::Available { strategy, context } => plugin
+                    .strategy_available(&strategy, &context)
+                    .map(StrategyResponse::Available),
+                StrategyRequest::Command {
+                    strategy,
+                    context,
+                    command,
+                } => plugin
+                    .strategy_command(&strategy, &context, command)
+                    .map(StrategyResponse::Command),
+            };
+            result.unwrap_or_else(|e| StrategyResponse::Error(e.to_string()))
+        }))
+        .unwrap_or_else(|_| StrategyResponse::Error("plugin panicked".to_string()));
+        into_raw(&serde_json::to_string(&response).unwrap_or_default())
+    }
+
+    /// Free a string returned by [`info`], [`call`] or [`strategy`]
+    ///
+    /// # Safety
+    ///
+    /// `string` must come from [`info`], [`call`] or [`strategy`] and not be
+    /// freed yet.
+    pub unsafe fn free(string: *mut c_char) {
+        if !string.is_null() {
+            drop(CString::from_raw(string));
+        }
+    }
+
+    fn into_raw(string: &str) -> *mut c_char {
+        CString::new(string.replace('\0', " "))
+            .unwrap_or_default()
+            .into_raw()
+    }
+}
+
+/// Export a [`Plugin`](crate::plugin::Plugin) from a `cdylib` crate so
+/// xcargo can load it, given a function creating it
+///
+/// See [`plugin::abi`](crate::plugin::abi) for an example.
+#[macro_export]
+macro_rules! export_plugin {
+    ($constructor:expr) => {
+        fn __xcargo_plugin() -> &'static dyn $crate::plugin::Plugin {
+            static PLUGIN: ::std::sync::OnceLock<::std::boxed::Box<dyn $crate::plugin::Plugin>> =
+                ::std::sync::OnceLock::new();
+            PLUGIN
+                .get_or_init(|| ::std::boxed::Box::new(($constructor)()))
+                .as_ref()
+        }
+
+        #[no_mangle]
+        pub extern "C" fn xcargo_plugin_abi_version() -> u32 {
+            $crate::plugin::abi::ABI_VERSION
+        }
+
+        #[no_mangle]
+        pub extern "C" fn xcargo_plugin_info() -> *mut ::std::os::raw::c_char {
+            $crate::plugin::abi::guest::info(__xcargo_plugin)
+        }
+
+        /// # Safety
+        ///
+        /// Called by xcargo with valid C strings
+        #[no_mangle]
+        pub unsafe extern "C" fn xcargo_plugin_call(
+            hook: *const ::std::os::raw::c_char,
+            context: *const ::std::os::raw::c_char,
+            error: *const ::std::os::raw::c_char,
+        ) -> *mut ::std::os::raw::c_char {
+            $crate::plugin::abi::guest::call(__xcargo_plugin, hook, context, error)
+        }
+
+        /// # Safety
+        ///
+        /// Called by xcargo with a valid C string
+        #[no_mangle]
+        pub unsafe extern "C" fn xcargo_plugin_strategy(
+            request: *const ::std::os::raw::c_char,
+        ) -> *mut ::std::os::raw::c_char {
+            $crate::plugin::abi::guest::strategy(__xcargo_plugin, request)
+        }
+
+        /// # Safety
+        ///
+        /// Called by xcargo with strings this library returned
+        #[no_mangle]
+        pub unsafe extern "C" fn xcargo_plugin_free(string: *mut ::std::os::raw::c_char) {
+            $crate::plugin::abi::guest::free(string)
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{Error, Result};
+
+    struct Veto;
+
+    impl Plugin for Veto {
+        fn name(&self) -> &'static str {
+            "veto"
+        }
+
+        fn version(&self) -> &'static str {
+            "2.0.0"
+        }
+
+        fn on_pre_build(&self, ctx: &PluginContext) -> Result<()> {
+            if ctx.release {
+                Err(Error::Config("no release builds".to_string()))
+            } else {
+                Ok(())
+            }
+        }
+
+        fn strategies(&self) -> Vec<String> {
+            vec!["remote-builder".to_string()]
+        }
+
+        fn strategy_command(
+            &self,
+            _strategy: &str,
+            _ctx: &PluginContext,
+            command: BuildCommand,
+        ) -> Result<BuildCommand> {
+            let mut args = vec!["builder".to_string(), command.program];
+            args.extend(command.args);
+            Ok(BuildCommand {
+                program: "ssh".to_string(),
+                args,
+                ..BuildCommand::default()
+            })
+        }
+    }
+
+    fn veto() -> &'static dyn Plugin {
+        &Veto
+    }
+
+    unsafe fn take(string: *mut c_char) -> Option<String> {
+        if string.is_null() {
+            return None;
+        }
+        let text = CStr::from_ptr(string).to_string_lossy().into_owned();
+        guest::free(string);
+        Some(text)
+    }
+
+    fn call(hook: &str, ctx: &PluginContext) -> Option<String> {
+        let hook = CString::new(hook).unwrap();
+        let ctx = CString::new(serde_json::to_string(ctx).unwrap()).unwrap();
+        unsafe {
+            take(guest::call(
+                veto,
+                hook.as_ptr(),
+                ctx.as_ptr(),
+                std::ptr::null(),
+            ))
+        }
+    }
+
+    #[test]
+    fn test_guest_info() {
+        let info = unsafe { take(guest::info(veto)) }.unwrap();
+        let metadata: PluginMetadata = serde_json::from_str(&info).unwrap();
+        assert_eq!(metadata.name, "veto");
+        assert_eq!(metadata.version, "2.0.0");
+        assert_eq!(metadata.strategies, ["remote-builder"]);
+    }
+
+    #[test]
+    fn test_guest_strategy() {
+        let ask = |request: &StrategyRequest| {
+            let request = CString::new(serde_json::to_string(request).unwrap()).unwrap();
+            let response = unsafe { take(guest::strategy(veto, request.as_ptr())) }.unwrap();
+            serde_json::from_str::<StrategyResponse>(&response).unwrap()
+        };
+        let context = PluginContext::new("aarch64-unknown-linux-gnu".to_string());
+
+        assert_eq!(
+            ask(&StrategyRequest::Available {
+                strategy: "remote-builder".to_string(),
+                context: context.clone(),
+            }),
+            StrategyResponse::Available("provided by plugin veto".to_string())
+        );
+        let response = ask(&StrategyRequest::Command {
+            strategy: "remote-builder".to_string(),
+            context,
+            command: BuildCommand {
+                program: "cargo".to_string(),
+                args: vec!["build".to_string()],
+                ..BuildCommand::default()
+            },
+        });
+        let StrategyResponse::Command(command) = response else {
+            panic!("unexpected response {response:?}");
+        };
+        assert_eq!(command.program, "ssh");
+        assert_eq!(command.args, ["builder", "cargo", "build"]);
+    }
+
+    #[test]
+    fn test_guest_call() {
+        let ctx = PluginContext::new("aarch64-unknown-linux-gnu".to_string());
+        assert_eq!(call("pre-build", &ctx), None);
+        assert_eq!(
+            call("pre-build", &ctx.clone().with_release(true)),
+            Some("Configuration error: no release builds".to_string())
+        );
+        assert_eq!(
+            call("pre-deploy", &ctx),
+            Some("Unknown hook 'pre-deploy'".to_string())
+        );
+    }
+}
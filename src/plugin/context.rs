@@ -1,12 +1,16 @@
 //! Plugin execution context
 
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsStr;
 use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
 
 /// Context passed to plugin hooks
 ///
 /// Contains information about the current build, target, and environment.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PluginContext {
     /// Target triple being built
     pub target: String,
@@ -30,6 +34,8 @@ pub struct PluginContext {
     pub use_zig: bool,
 
     /// Custom metadata that plugins can use to share state
+    ///
+    /// Pre-publish hooks find the artifact's path under `artifact`.
     pub metadata: HashMap<String, String>,
 }
 
@@ -103,8 +109,128 @@ impl PluginContext {
     }
 }
 
+/// A command to run, as plugin strategies receive and return it
+///
+/// xcargo passes the cargo command it would run itself, with the
+/// environment it set up; the plugin returns the command that builds
+/// instead, e.g. `ssh builder cargo build ...`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildCommand {
+    /// Program to run
+    pub program: String,
+
+    /// Arguments
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Environment variables set on top of xcargo's own
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+
+    /// Directory to run in, if not the current one
+    #[serde(default)]
+    pub current_dir: Option<PathBuf>,
+}
+
+impl BuildCommand {
+    /// The program, arguments, environment and directory of `cmd`
+    #[must_use]
+    pub fn from_command(cmd: &Command) -> Self {
+        let text = |value: &OsStr| value.to_string_lossy().into_owned();
+        Self {
+            program: text(cmd.get_program()),
+            args: cmd.get_args().map(text).collect(),
+            env: cmd
+                .get_envs()
+                .filter_map(|(key, value)| Some((text(key), text(value?))))
+                .collect(),
+            current_dir: cmd.get_current_dir().map(PathBuf::from),
+        }
+    }
+
+    /// A [`Command`] running this
+    #[must_use]
+    pub fn to_command(&self) -> Command {
+        let mut cmd = Command::new(&self.program);
+        cmd.args(&self.args).envs(&self.env);
+        if let Some(dir) = &self.current_dir {
+            cmd.current_dir(dir);
+        }
+        cmd
+    }
+}
+
+/// How a multi-target build ended, as the builds-finished hook receives it
+///
+/// It travels in the context's metadata, so plugins loaded from shared
+/// libraries get it too: `operation`, `succeeded` and `failed` (target
+/// triples separated by commas), `skipped` (a count), `cancelled`
+/// (`true` or `false`) and `elapsed_ms`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildsSummary {
+    /// Cargo operation (e.g., "build", "test")
+    pub operation: String,
+    /// Targets that built
+    pub succeeded: Vec<String>,
+    /// Targets that failed
+    pub failed: Vec<String>,
+    /// Targets left out: excluded, already built with `--resume`, or not
+    /// started after a failure or Ctrl-C
+    pub skipped: usize,
+    /// Whether the build was interrupted
+    pub cancelled: bool,
+    /// How long the whole build took
+    pub elapsed: Duration,
+}
+
+impl BuildsSummary {
+    /// Whether every target that ran succeeded
+    #[must_use]
+    pub fn success(&self) -> bool {
+        self.failed.is_empty() && !self.cancelled
+    }
+
+    /// `ctx` carrying this summary in its metadata
+    #[must_use]
+    pub fn to_context(&self, mut ctx: PluginContext) -> PluginContext {
+        let entries = [
+            ("operation", self.operation.clone()),
+            ("succeeded", self.succeeded.join(",")),
+            ("failed", self.failed.join(",")),
+            ("skipped", self.skipped.to_string()),
+            ("cancelled", self.cancelled.to_string()),
+            ("elapsed_ms", self.elapsed.as_millis().to_string()),
+        ];
+        for (key, value) in entries {
+            ctx.set_metadata(key.to_string(), value);
+        }
+        ctx
+    }
+
+    /// The summary carried by `ctx`; missing entries are left empty
+    #[must_use]
+    pub fn from_context(ctx: &PluginContext) -> Self {
+        let value = |key: &str| ctx.get_metadata(key).map_or("", String::as_str);
+        let targets = |key: &str| {
+            value(key)
+                .split(',')
+                .filter(|target| !target.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        Self {
+            operation: value("operation").to_string(),
+            succeeded: targets("succeeded"),
+            failed: targets("failed"),
+            skipped: value("skipped").parse().unwrap_or(0),
+            cancelled: value("cancelled") == "true",
+            elapsed: Duration::from_millis(value("elapsed_ms").parse().unwrap_or(0)),
+        }
+    }
+}
+
 /// Plugin metadata
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PluginMetadata {
     /// Plugin name
     pub name: String,
@@ -120,6 +246,10 @@ pub struct PluginMetadata {
 
     /// Whether plugin is enabled
     pub enabled: bool,
+
+    /// Build strategies the plugin provides
+    #[serde(default)]
+    pub strategies: Vec<String>,
 }
 
 impl PluginMetadata {
@@ -132,6 +262,7 @@ impl PluginMetadata {
             description: String::new(),
             author: String::new(),
             enabled: true,
+            strategies: Vec::new(),
         }
     }
 }
@@ -179,4 +310,42 @@ mod tests {
         assert_eq!(metadata.version, "1.0.0");
         assert!(metadata.enabled);
     }
+
+    #[test]
+    fn test_builds_summary_round_trip() {
+        let summary = BuildsSummary {
+            operation: "build".to_string(),
+            succeeded: vec![
+                "aarch64-unknown-linux-gnu".to_string(),
+                "x86_64-unknown-linux-musl".to_string(),
+            ],
+            failed: vec!["x86_64-pc-windows-gnu".to_string()],
+            skipped: 1,
+            cancelled: false,
+            elapsed: Duration::from_millis(754_250),
+        };
+        let ctx = summary.to_context(PluginContext::default());
+        assert_eq!(
+            ctx.get_metadata("succeeded").map(String::as_str),
+            Some("aarch64-unknown-linux-gnu,x86_64-unknown-linux-musl")
+        );
+        assert_eq!(BuildsSummary::from_context(&ctx), summary);
+        assert!(!summary.success());
+
+        let empty = BuildsSummary::from_context(&PluginContext::default());
+        assert!(empty.succeeded.is_empty() && empty.success());
+    }
+
+    #[test]
+    fn test_build_command_round_trip() {
+        let mut cmd = Command::new("cargo");
+        cmd.args(["build", "--target", "aarch64-unknown-linux-gnu"])
+            .env("RUSTFLAGS", "-Dwarnings");
+
+        let command = BuildCommand::from_command(&cmd);
+        assert_eq!(command.program, "cargo");
+        assert_eq!(command.args.len(), 3);
+        assert_eq!(command.env["RUSTFLAGS"], "-Dwarnings");
+        assert_eq!(BuildCommand::from_command(&command.to_command()), command);
+    }
 }
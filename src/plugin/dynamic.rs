@@ -0,0 +1,250 @@
+//! Plugins loaded from shared libraries through the [`abi`](super::abi)
+
+use super::abi::{
+    self, AbiVersionFn, CallFn, FreeFn, InfoFn, StrategyFn, StrategyRequest, StrategyResponse,
+};
+use super::context::{BuildCommand, PluginContext, PluginMetadata};
+use super::hooks::PluginHook;
+use super::traits::Plugin;
+use crate::error::{Error, Result};
+use libloading::Library;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::path::Path;
+
+/// A plugin in a shared library built with
+/// [`export_plugin!`](crate::export_plugin)
+pub struct DynamicPlugin {
+    metadata: PluginMetadata,
+    call: CallFn,
+    strategy: StrategyFn,
+    free: FreeFn,
+    /// Kept loaded while `call` and `free` may be used; dropped last
+    _library: Library,
+}
+
+impl DynamicPlugin {
+    /// Load the plugin in the library at `path`
+    ///
+    /// Fails if the library does not export the plugin functions or was
+    /// built against another ABI version.
+    pub fn load(path: &Path) -> Result<Self> {
+        let failed = |message: String| Error::Plugin {
+            plugin: path.display().to_string(),
+            message,
+        };
+
+        // SAFETY: loading runs the library's initializers; plugin libraries
+        // are built by xcargo from the project's own sources
+        let library = unsafe { Library::new(path) }
+            .map_err(|e| failed(format!("cannot load library: {e}")))?;
+
+        // SAFETY: the symbols have the signatures `export_plugin!` gives them
+        let version =
+            unsafe { symbol::<AbiVersionFn>(&library, abi::SYMBOL_ABI_VERSION) }.map_err(failed)?;
+
+        // SAFETY: this takes no arguments
+        let found = unsafe { version() };
+        if found != abi::ABI_VERSION {
+            return Err(failed(format!(
+                "built for plugin ABI {found}, this xcargo uses {}",
+                abi::ABI_VERSION
+            )));
+        }
+
+        // SAFETY: as above; the ABI version says which symbols exist
+        let (info, call, strategy, free) = unsafe {
+            (
+                symbol::<InfoFn>(&library, abi::SYMBOL_INFO).map_err(failed)?,
+                symbol::<CallFn>(&library, abi::SYMBOL_CALL).map_err(failed)?,
+                symbol::<StrategyFn>(&library, abi::SYMBOL_STRATEGY).map_err(failed)?,
+                symbol::<FreeFn>(&library, abi::SYMBOL_FREE).map_err(failed)?,
+            )
+        };
+        let info = unsafe { take(info(), free) }
+            .ok_or_else(|| failed("panicked while describing itself".to_string()))?;
+        let metadata: PluginMetadata = serde_json::from_str(&info)
+            .map_err(|e| failed(format!("invalid plugin metadata: {e}")))?;
+
+        Ok(Self {
+            metadata,
+            call,
+            strategy,
+            free,
+            _library: library,
+        })
+    }
+
+    /// Run `hook` in the library
+    fn run(&self, hook: PluginHook, ctx: &PluginContext, error: Option<&str>) -> Result<()> {
+        let failed = |message: String| Error::Plugin {
+            plugin: self.metadata.name.clone(),
+            message,
+        };
+        let c_string =
+            |text: &str| CString::new(text.replace('\0', " ")).map_err(|e| failed(e.to_string()));
+
+        let hook = c_string(hook.as_str())?;
+        let context = serde_json::to_string(ctx).map_err(|e| failed(e.to_string()))?;
+        let context = c_string(&context)?;
+        let error = error.map(c_string).transpose()?;
+        let error_ptr = error.as_ref().map_or(std::ptr::null(), |e| e.as_ptr());
+
+        // SAFETY: all arguments are valid C strings (or a null error), and
+        // the result is freed by the library that allocated it
+        match unsafe {
+            take(
+                (self.call)(hook.as_ptr(), context.as_ptr(), error_ptr),
+                self.free,
+            )
+        } {
+            None => Ok(()),
+            Some(message) => Err(failed(message)),
+        }
+    }
+
+    /// Ask one of the library's strategies
+    fn ask(&self, request: &StrategyRequest) -> Result<StrategyResponse> {
+        let failed = |message: String| Error::Plugin {
+            plugin: self.metadata.name.clone(),
+            message,
+        };
+        let request = serde_json::to_string(request).map_err(|e| failed(e.to_string()))?;
+        let request =
+            CString::new(request.replace('\0', " ")).map_err(|e| failed(e.to_string()))?;
+
+        // SAFETY: the request is a valid C string, and the result is freed
+        // by the library that allocated it
+        let response = unsafe { take((self.strategy)(request.as_ptr()), self.free) }
+            .ok_or_else(|| failed("returned no strategy response".to_string()))?;
+        match serde_json::from_str(&response) {
+            Ok(StrategyResponse::Error(message)) => Err(failed(message)),
+            Ok(response) => Ok(response),
+            Err(e) => Err(failed(format!("invalid strategy response: {e}"))),
+        }
+    }
+
+    fn unexpected(&self, response: &StrategyResponse) -> Error {
+        Error::Plugin {
+            plugin: self.metadata.name.clone(),
+            message: format!("unexpected strategy response {response:?}"),
+        }
+    }
+}
+
+impl Plugin for DynamicPlugin {
+    fn name(&self) -> &str {
+        &self.metadata.name
+    }
+
+    fn version(&self) -> &str {
+        &self.metadata.version
+    }
+
+    fn description(&self) -> &str {
+        &self.metadata.description
+    }
+
+    fn author(&self) -> &str {
+        &self.metadata.author
+    }
+
+    fn strategies(&self) -> Vec<String> {
+        self.metadata.strategies.clone()
+    }
+
+    fn strategy_available(&self, strategy: &str, ctx: &PluginContext) -> Result<String> {
+        match self.ask(&StrategyRequest::Available {
+            strategy: strategy.to_string(),
+            context: ctx.clone(),
+        })? {
+            StrategyResponse::Available(why) => Ok(why),
+            other => Err(self.unexpected(&other)),
+        }
+    }
+
+    fn strategy_command(
+        &self,
+        strategy: &str,
+        ctx: &PluginContext,
+        command: BuildCommand,
+    ) -> Result<BuildCommand> {
+        match self.ask(&StrategyRequest::Command {
+            strategy: strategy.to_string(),
+            context: ctx.clone(),
+            command,
+        })? {
+            StrategyResponse::Command(command) => Ok(command),
+            other => Err(self.unexpected(&other)),
+        }
+    }
+
+    fn on_pre_build(&self, ctx: &PluginContext) -> Result<()> {
+        self.run(PluginHook::PreBuild, ctx, None)
+    }
+
+    fn on_post_build(&self, ctx: &PluginContext) -> Result<()> {
+        self.run(PluginHook::PostBuild, ctx, None)
+    }
+
+    fn on_build_failed(&self, ctx: &PluginContext, error: &str) -> Result<()> {
+        self.run(PluginHook::BuildFailed, ctx, Some(error))
+    }
+
+    fn on_pre_toolchain_install(&self, ctx: &PluginContext) -> Result<()> {
+        self.run(PluginHook::PreToolchainInstall, ctx, None)
+    }
+
+    fn on_post_toolchain_install(&self, ctx: &PluginContext) -> Result<()> {
+        self.run(PluginHook::PostToolchainInstall, ctx, None)
+    }
+
+    fn on_pre_publish(&self, ctx: &PluginContext) -> Result<()> {
+        self.run(PluginHook::PrePublish, ctx, None)
+    }
+
+    fn on_builds_finished(&self, ctx: &PluginContext) -> Result<()> {
+        // Libraries built before the hook existed reject it by name
+        match self.run(PluginHook::BuildsFinished, ctx, None) {
+            Err(Error::Plugin { message, .. }) if message.starts_with("Unknown hook") => Ok(()),
+            result => result,
+        }
+    }
+
+    fn on_init(&self) -> Result<()> {
+        self.run(PluginHook::Init, &PluginContext::default(), None)
+    }
+
+    fn on_shutdown(&self) -> Result<()> {
+        self.run(PluginHook::Shutdown, &PluginContext::default(), None)
+    }
+}
+
+/// Function `name` (NUL-terminated) exported by `library`
+///
+/// # Safety
+///
+/// `T` must be the function's signature.
+unsafe fn symbol<T: Copy>(library: &Library, name: &[u8]) -> std::result::Result<T, String> {
+    library
+        .get::<T>(name)
+        .map(|function| *function)
+        .map_err(|_| {
+            let name = String::from_utf8_lossy(&name[..name.len() - 1]);
+            format!("library does not export {name}; is the plugin exported with export_plugin!?")
+        })
+}
+
+/// Copy a string returned by a plugin library and free it there
+///
+/// # Safety
+///
+/// `string` must be null or returned by the library `free` belongs to.
+unsafe fn take(string: *mut c_char, free: FreeFn) -> Option<String> {
+    if string.is_null() {
+        return None;
+    }
+    let text = CStr::from_ptr(string).to_string_lossy().into_owned();
+    free(string);
+    Some(text)
+}
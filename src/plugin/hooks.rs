@@ -18,6 +18,10 @@ pub enum PluginHook {
     PreToolchainInstall,
     /// After toolchain installation
     PostToolchainInstall,
+    /// Before an artifact is published
+    PrePublish,
+    /// After a multi-target build, once
+    BuildsFinished,
     /// On plugin initialization
     Init,
     /// On plugin shutdown
@@ -34,11 +38,31 @@ impl PluginHook {
             Self::BuildFailed => "build-failed",
             Self::PreToolchainInstall => "pre-toolchain-install",
             Self::PostToolchainInstall => "post-toolchain-install",
+            Self::PrePublish => "pre-publish",
+            Self::BuildsFinished => "builds-finished",
             Self::Init => "init",
             Self::Shutdown => "shutdown",
         }
     }
 
+    /// Hook for a name from [`Self::as_str`]
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            Self::PreBuild,
+            Self::PostBuild,
+            Self::BuildFailed,
+            Self::PreToolchainInstall,
+            Self::PostToolchainInstall,
+            Self::PrePublish,
+            Self::BuildsFinished,
+            Self::Init,
+            Self::Shutdown,
+        ]
+        .into_iter()
+        .find(|hook| hook.as_str() == name)
+    }
+
     /// Execute this hook on a plugin
     ///
     /// # Errors
@@ -54,6 +78,8 @@ impl PluginHook {
             }
             Self::PreToolchainInstall => plugin.on_pre_toolchain_install(ctx),
             Self::PostToolchainInstall => plugin.on_post_toolchain_install(ctx),
+            Self::PrePublish => plugin.on_pre_publish(ctx),
+            Self::BuildsFinished => plugin.on_builds_finished(ctx),
             Self::Init => plugin.on_init(),
             Self::Shutdown => plugin.on_shutdown(),
         }
@@ -93,8 +119,16 @@ mod tests {
             PluginHook::PostToolchainInstall.as_str(),
             "post-toolchain-install"
         );
+        assert_eq!(PluginHook::PrePublish.as_str(), "pre-publish");
+        assert_eq!(PluginHook::BuildsFinished.as_str(), "builds-finished");
         assert_eq!(PluginHook::Init.as_str(), "init");
         assert_eq!(PluginHook::Shutdown.as_str(), "shutdown");
+
+        assert_eq!(
+            PluginHook::from_name("pre-publish"),
+            Some(PluginHook::PrePublish)
+        );
+        assert_eq!(PluginHook::from_name("post-install"), None);
     }
 
     struct TestPlugin {
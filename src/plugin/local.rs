@@ -0,0 +1,327 @@
+//! Plugins kept as Rust crates inside the project
+//!
+//! A crate in `.xcargo/plugins` (or one per subdirectory of it) is compiled
+//! for the host as a `cdylib` the first time a build needs it and loaded
+//! through the [`abi`](super::abi), so a team can version bespoke build
+//! logic with the repository instead of publishing a plugin. The library is
+//! kept in `target/xcargo/plugins` under a hash of the crate's sources, and
+//! only rebuilt after they change.
+//!
+//! ```toml
+//! # .xcargo/plugins/require-license/Cargo.toml
+//! [package]
+//! name = "require-license"
+//! version = "0.1.0"
+//! edition = "2021"
+//!
+//! # Keep the plugin out of the project's workspace
+//! [workspace]
+//!
+//! [dependencies]
+//! xcargo = { version = "0.3", default-features = false }
+//! ```
+//!
+//! The crate's `src/lib.rs` implements [`Plugin`](super::Plugin) and ends
+//! with [`export_plugin!`](crate::export_plugin).
+
+use super::abi;
+use super::registry::PluginRegistry;
+use crate::error::{Error, Result};
+use crate::output::helpers;
+use crate::trace;
+use sha2::{Digest, Sha256};
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use walkdir::WalkDir;
+
+/// Directory of plugin crates, relative to the project root
+pub const PLUGINS_DIR: &str = ".xcargo/plugins";
+
+/// A plugin crate in the project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalPlugin {
+    /// Directory with the crate's `Cargo.toml`
+    pub dir: PathBuf,
+    /// Name of the crate's library, as in the compiled file's name
+    pub lib_name: String,
+}
+
+impl LocalPlugin {
+    /// Read the plugin crate in `dir`
+    pub fn read(dir: &Path) -> Result<Self> {
+        let manifest = fs::read_to_string(dir.join("Cargo.toml"))?;
+        let lib_name = lib_name(&manifest).ok_or_else(|| Error::Plugin {
+            plugin: dir.display().to_string(),
+            message: "Cargo.toml names no package".to_string(),
+        })?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            lib_name,
+        })
+    }
+
+    /// Hash of the crate's files (but `target` and hidden ones), this
+    /// xcargo's version and the plugin ABI version
+    pub fn source_hash(&self) -> Result<String> {
+        let mut hasher = Sha256::new();
+        hasher.update(env!("CARGO_PKG_VERSION"));
+        hasher.update(abi::ABI_VERSION.to_le_bytes());
+
+        let files = WalkDir::new(&self.dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| {
+                let name = entry.file_name().to_string_lossy();
+                entry.depth() == 0 || !(name == "target" || name.starts_with('.'))
+            });
+        for entry in files {
+            let entry = entry.map_err(|e| Error::Io(e.into()))?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(&self.dir).unwrap_or(entry.path());
+            hasher.update(relative.to_string_lossy().as_bytes());
+            hasher.update([0]);
+            hasher.update(fs::read(entry.path())?);
+            hasher.update([0]);
+        }
+
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// The compiled library in `cache_dir`, compiled first unless one for
+    /// the current sources is there already
+    pub fn library(&self, cache_dir: &Path) -> Result<PathBuf> {
+        let hash = self.source_hash()?;
+        let file_name = |suffix: &str| format!("{DLL_PREFIX}{}{suffix}{DLL_SUFFIX}", self.lib_name);
+        let library = cache_dir.join(file_name(&format!("-{}", &hash[..16])));
+        if library.is_file() {
+            return Ok(library);
+        }
+
+        let build_dir = cache_dir.join("build");
+        self.compile(&build_dir)?;
+        let built = build_dir.join("release").join(file_name(""));
+        if !built.is_file() {
+            return Err(self.failed(format!("cargo produced no {}", built.display())));
+        }
+
+        // Replace the libraries built from earlier sources
+        let stale_prefix = file_name("-");
+        let stale_prefix = stale_prefix.trim_end_matches(DLL_SUFFIX);
+        for entry in fs::read_dir(cache_dir)?.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with(stale_prefix) && name.ends_with(DLL_SUFFIX) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+        fs::copy(&built, &library)?;
+        Ok(library)
+    }
+
+    /// Compile the crate for the host into `build_dir`
+    fn compile(&self, build_dir: &Path) -> Result<()> {
+        helpers::progress(format!("Compiling plugin {}", self.lib_name));
+
+        let mut cmd = Command::new("cargo");
+        cmd.arg("rustc")
+            .args(["--release", "--lib", "--crate-type", "cdylib"])
+            .arg("--manifest-path")
+            .arg(self.dir.join("Cargo.toml"))
+            .arg("--target-dir")
+            .arg(build_dir)
+            // Plugins run in xcargo, so they are built for the host
+            .env_remove("CARGO_BUILD_TARGET");
+
+        let status = cmd.status()?;
+        trace::record_command(&cmd, status.code());
+        if status.success() {
+            Ok(())
+        } else {
+            Err(self.failed(format!("cargo failed to compile it ({status})")))
+        }
+    }
+
+    fn failed(&self, message: String) -> Error {
+        Error::Plugin {
+            plugin: self.lib_name.clone(),
+            message,
+        }
+    }
+}
+
+/// Name of the library a crate's `Cargo.toml` builds: `[lib] name`, or the
+/// package name with `-` replaced by `_`
+fn lib_name(manifest: &str) -> Option<String> {
+    let manifest: toml::Value = toml::from_str(manifest).ok()?;
+    let lib = manifest
+        .get("lib")
+        .and_then(|lib| lib.get("name"))
+        .and_then(toml::Value::as_str);
+    let package = manifest
+        .get("package")
+        .and_then(|package| package.get("name"))
+        .and_then(toml::Value::as_str);
+    lib.or(package).map(|name| name.replace('-', "_"))
+}
+
+/// The plugin crates in `project_root`: `.xcargo/plugins` if it is a crate
+/// itself, its subdirectories with a `Cargo.toml` otherwise
+pub fn discover(project_root: &Path) -> Result<Vec<LocalPlugin>> {
+    let dir = project_root.join(PLUGINS_DIR);
+    if dir.join("Cargo.toml").is_file() {
+        return Ok(vec![LocalPlugin::read(&dir)?]);
+    }
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+
+    let mut dirs: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.join("Cargo.toml").is_file())
+        .collect();
+    dirs.sort();
+    dirs.iter().map(|dir| LocalPlugin::read(dir)).collect()
+}
+
+/// Nearest directory, from the current one up, with plugin crates
+#[must_use]
+pub fn project_root() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors()
+        .find(|dir| dir.join(PLUGINS_DIR).is_dir())
+        .map(Path::to_path_buf)
+}
+
+/// Where compiled plugins of `project_root` are kept
+#[must_use]
+pub fn cache_dir(project_root: &Path) -> PathBuf {
+    std::env::var_os("CARGO_TARGET_DIR")
+        .map_or_else(|| project_root.join("target"), PathBuf::from)
+        .join("xcargo")
+        .join("plugins")
+}
+
+/// Compile, if needed, and register the plugin crates of `project_root`
+///
+/// Without the `plugins-dynamic` feature they cannot be loaded and are
+/// skipped with a warning.
+pub fn register(registry: &mut PluginRegistry, project_root: &Path) -> Result<()> {
+    let plugins = discover(project_root)?;
+    if plugins.is_empty() {
+        return Ok(());
+    }
+
+    #[cfg(feature = "plugins-dynamic")]
+    {
+        let cache_dir = cache_dir(project_root);
+        fs::create_dir_all(&cache_dir)?;
+        for plugin in plugins {
+            let library = plugin.library(&cache_dir)?;
+            registry.register(Box::new(super::DynamicPlugin::load(&library)?))?;
+        }
+    }
+    #[cfg(not(feature = "plugins-dynamic"))]
+    {
+        let _ = registry;
+        helpers::warning(format!(
+            "Skipping {} plugin(s) in {PLUGINS_DIR}: xcargo was built without the plugins-dynamic feature",
+            plugins.len()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_crate(dir: &Path, name: &str) {
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\n"),
+        )
+        .unwrap();
+        fs::write(dir.join("src/lib.rs"), "// plugin\n").unwrap();
+    }
+
+    #[test]
+    fn test_lib_name() {
+        assert_eq!(
+            lib_name("[package]\nname = \"require-license\"\n"),
+            Some("require_license".to_string())
+        );
+        assert_eq!(
+            lib_name("[package]\nname = \"a\"\n[lib]\nname = \"checks\"\n"),
+            Some("checks".to_string())
+        );
+        assert_eq!(lib_name("[workspace]\n"), None);
+    }
+
+    #[test]
+    fn test_discover() {
+        let project = TempDir::new().unwrap();
+        assert!(discover(project.path()).unwrap().is_empty());
+
+        let plugins = project.path().join(PLUGINS_DIR);
+        write_crate(&plugins.join("b-plugin"), "b-plugin");
+        write_crate(&plugins.join("a-plugin"), "a-plugin");
+        fs::create_dir_all(plugins.join("notes")).unwrap();
+        let found = discover(project.path()).unwrap();
+        let names: Vec<&str> = found.iter().map(|p| p.lib_name.as_str()).collect();
+        assert_eq!(names, ["a_plugin", "b_plugin"]);
+
+        // A crate at the top replaces the per-directory ones
+        write_crate(&plugins, "checks");
+        let found = discover(project.path()).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].dir, plugins);
+    }
+
+    #[test]
+    fn test_source_hash() {
+        let dir = TempDir::new().unwrap();
+        write_crate(dir.path(), "checks");
+        let plugin = LocalPlugin::read(dir.path()).unwrap();
+        let hash = plugin.source_hash().unwrap();
+        assert_eq!(hash.len(), 64);
+
+        // Build output and hidden files do not count
+        fs::create_dir_all(dir.path().join("target/release")).unwrap();
+        fs::write(dir.path().join("target/release/libchecks.so"), "x").unwrap();
+        fs::write(dir.path().join(".editorconfig"), "x").unwrap();
+        assert_eq!(plugin.source_hash().unwrap(), hash);
+
+        fs::write(dir.path().join("src/lib.rs"), "// changed\n").unwrap();
+        assert_ne!(plugin.source_hash().unwrap(), hash);
+    }
+
+    #[test]
+    fn test_library_is_cached_by_source_hash() {
+        let dir = TempDir::new().unwrap();
+        write_crate(&dir.path().join("plugin"), "checks");
+        let plugin = LocalPlugin::read(&dir.path().join("plugin")).unwrap();
+        let cache_dir = dir.path().join("cache");
+        fs::create_dir_all(&cache_dir).unwrap();
+
+        let hash = plugin.source_hash().unwrap();
+        let cached = cache_dir.join(format!("{DLL_PREFIX}checks-{}{DLL_SUFFIX}", &hash[..16]));
+        fs::write(&cached, "compiled").unwrap();
+        assert_eq!(plugin.library(&cache_dir).unwrap(), cached);
+    }
+
+    #[test]
+    fn test_cache_dir() {
+        if std::env::var_os("CARGO_TARGET_DIR").is_none() {
+            assert_eq!(
+                cache_dir(Path::new("/project")),
+                Path::new("/project/target/xcargo/plugins")
+            );
+        }
+    }
+}
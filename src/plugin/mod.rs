@@ -8,6 +8,12 @@
 //! - **Toolchain Plugins**: Add support for new toolchains (e.g., custom linkers)
 //! - **Build Hooks**: Execute code before/after build steps
 //! - **Target Plugins**: Add support for new target platforms
+//! - **Strategy Plugins**: Build targets their own way (e.g., on a remote
+//!   builder or in a `nix-shell`), see [`Plugin::strategies`]
+//!
+//! Plugins are registered in code, or kept as small crates in the project's
+//! `.xcargo/plugins` that xcargo compiles and loads itself ([`local`]).
+//! xcargo's own [`notify`] plugin announces finished multi-target builds.
 //!
 //! # Example
 //!
@@ -28,27 +34,46 @@
 //! }
 //! ```
 
+pub mod abi;
 mod context;
+#[cfg(feature = "plugins-dynamic")]
+mod dynamic;
 mod hooks;
+pub mod local;
+pub mod notify;
 mod registry;
 mod traits;
 
-pub use context::{PluginContext, PluginMetadata};
+pub use context::{BuildCommand, BuildsSummary, PluginContext, PluginMetadata};
+#[cfg(feature = "plugins-dynamic")]
+pub use dynamic::DynamicPlugin;
 pub use hooks::PluginHook;
 pub use registry::PluginRegistry;
 pub use traits::Plugin;
 
+use crate::config::Config;
 use crate::error::Result;
 
 /// Initialize the plugin system
 ///
 /// This should be called once at application startup to discover and
-/// register all available plugins.
-pub fn init() -> Result<PluginRegistry> {
-    let registry = PluginRegistry::new();
+/// register all available plugins. `config` decides which built-in plugins
+/// are registered.
+pub fn init(config: &Config) -> Result<PluginRegistry> {
+    let mut registry = PluginRegistry::new();
 
     // Built-in plugins are registered here
-    // External plugins will be registered via feature flags or config
+    if config.notifications.enabled() {
+        registry.register(Box::new(notify::NotificationPlugin::new(
+            config.notifications.clone(),
+            config.network.clone(),
+        )))?;
+    }
+
+    // Plugin crates kept in the project, compiled on first use
+    if let Some(root) = local::project_root() {
+        local::register(&mut registry, &root)?;
+    }
 
     Ok(registry)
 }
@@ -59,7 +84,12 @@ mod tests {
 
     #[test]
     fn test_plugin_init() {
-        let registry = init().unwrap();
+        let registry = init(&Config::default()).unwrap();
         assert_eq!(registry.count(), 0); // No plugins registered yet
+
+        let mut config = Config::default();
+        config.notifications.desktop = true;
+        let registry = init(&config).unwrap();
+        assert!(registry.contains(notify::NAME));
     }
 }
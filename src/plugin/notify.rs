@@ -0,0 +1,458 @@
+//! Built-in plugin announcing finished multi-target builds
+//!
+//! Long `--all` and `--group` builds are easy to lose track of. With
+//! `[notifications]` set up, the builds-finished hook shows a desktop
+//! notification and posts the result to webhooks in Slack's, Discord's or
+//! a plain JSON shape:
+//!
+//! ```toml
+//! [notifications]
+//! desktop = true
+//! min_duration = 120   # seconds; quicker builds stay quiet
+//!
+//! [[notifications.webhooks]]
+//! url = "https://hooks.slack.com/services/T000/B000/XXXX"
+//! ```
+//!
+//! Desktop notifications go through notify-send on Linux and the BSDs,
+//! osascript on macOS and PowerShell on Windows; webhooks through curl with
+//! the `[network]` proxy and CA settings. Failing to notify only warns.
+
+use super::context::{BuildsSummary, PluginContext};
+use super::traits::Plugin;
+use crate::config::{NetworkConfig, NotificationsConfig, WebhookConfig};
+use crate::error::{Error, Result};
+use crate::network;
+use crate::output::{helpers, progress::format_duration};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt;
+use std::fmt::Write as _;
+use std::io::ErrorKind;
+use std::process::Command;
+use std::time::Duration;
+
+/// Plugin name, as `xcargo plugin list` shows it
+pub const NAME: &str = "notifications";
+
+/// PowerShell's app ID, which Windows shows toasts for without registering
+/// one of xcargo's own
+const POWERSHELL_APP_ID: &str =
+    r"{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\WindowsPowerShell\v1.0\powershell.exe";
+
+/// Seconds a webhook may take to answer
+const WEBHOOK_TIMEOUT: &str = "15";
+
+/// Shape of a webhook's payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    /// `{"title": ..., "message": ..., "succeeded": [...], ...}`
+    Json,
+    /// Slack incoming webhook (`{"text": ...}`)
+    Slack,
+    /// Discord webhook (`{"content": ...}`)
+    Discord,
+}
+
+impl WebhookFormat {
+    /// Format of `webhook`: the configured one, or the one its URL suggests
+    #[must_use]
+    pub fn of(webhook: &WebhookConfig) -> Self {
+        if let Some(format) = webhook.format {
+            return format;
+        }
+        let url = webhook.url.to_ascii_lowercase();
+        if url.contains("hooks.slack.com/") {
+            Self::Slack
+        } else if url.contains("discord.com/api/webhooks/")
+            || url.contains("discordapp.com/api/webhooks/")
+        {
+            Self::Discord
+        } else {
+            Self::Json
+        }
+    }
+}
+
+impl fmt::Display for WebhookFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Json => "json",
+            Self::Slack => "slack",
+            Self::Discord => "discord",
+        })
+    }
+}
+
+/// What a notification says about a finished build
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    /// "xcargo build failed (app)"
+    pub title: String,
+    /// "1 of 5 targets failed after 12m 03s: x86_64-pc-windows-gnu"
+    pub message: String,
+}
+
+impl Notification {
+    /// Notification about `summary`, for the project named `project`
+    #[must_use]
+    pub fn new(summary: &BuildsSummary, project: &str) -> Self {
+        let outcome = if summary.cancelled {
+            "was cancelled"
+        } else if summary.failed.is_empty() {
+            "succeeded"
+        } else {
+            "failed"
+        };
+        let title = if project.is_empty() {
+            format!("xcargo {} {outcome}", summary.operation)
+        } else {
+            format!("xcargo {} {outcome} ({project})", summary.operation)
+        };
+
+        let ran = summary.succeeded.len() + summary.failed.len();
+        let elapsed = format_duration(summary.elapsed);
+        let mut message = if summary.failed.is_empty() {
+            format!(
+                "{} of {ran} target(s) succeeded in {elapsed}",
+                summary.succeeded.len()
+            )
+        } else {
+            format!(
+                "{} of {ran} target(s) failed after {elapsed}: {}",
+                summary.failed.len(),
+                summary.failed.join(", ")
+            )
+        };
+        if summary.skipped > 0 {
+            let _ = write!(message, "; {} skipped", summary.skipped);
+        }
+        Self { title, message }
+    }
+
+    /// Payload posted to a webhook in `format`
+    #[must_use]
+    pub fn payload(&self, format: WebhookFormat, summary: &BuildsSummary) -> String {
+        let payload = match format {
+            WebhookFormat::Slack => json!({
+                "text": format!("*{}*\n{}", self.title, self.message),
+            }),
+            WebhookFormat::Discord => json!({
+                "content": format!("**{}**\n{}", self.title, self.message),
+            }),
+            WebhookFormat::Json => json!({
+                "title": self.title,
+                "message": self.message,
+                "operation": summary.operation,
+                "success": summary.success(),
+                "cancelled": summary.cancelled,
+                "succeeded": summary.succeeded,
+                "failed": summary.failed,
+                "skipped": summary.skipped,
+                "elapsed_secs": summary.elapsed.as_secs(),
+            }),
+        };
+        payload.to_string()
+    }
+
+    /// Command showing this as a desktop notification on `os` (as
+    /// `std::env::consts::OS` names it)
+    #[must_use]
+    pub fn desktop_command(&self, os: &str, success: bool) -> Command {
+        match os {
+            "macos" => {
+                let quote = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+                let mut cmd = Command::new("osascript");
+                cmd.arg("-e").arg(format!(
+                    "display notification \"{}\" with title \"{}\"",
+                    quote(&self.message),
+                    quote(&self.title)
+                ));
+                cmd
+            }
+            "windows" => {
+                let quote = |text: &str| text.replace('\'', "''");
+                let script = format!(
+                    "$m = [Windows.UI.Notifications.ToastNotificationManager, \
+                     Windows.UI.Notifications, ContentType = WindowsRuntime]; \
+                     $t = $m::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+                     $n = $t.GetElementsByTagName('text'); \
+                     $n.Item(0).AppendChild($t.CreateTextNode('{}')) > $null; \
+                     $n.Item(1).AppendChild($t.CreateTextNode('{}')) > $null; \
+                     $m::CreateToastNotifier('{POWERSHELL_APP_ID}').Show([Windows.UI.Notifications.ToastNotification]::new($t))",
+                    quote(&self.title),
+                    quote(&self.message)
+                );
+                let mut cmd = Command::new("powershell");
+                cmd.args(["-NoProfile", "-NonInteractive", "-Command", &script]);
+                cmd
+            }
+            _ => {
+                let mut cmd = Command::new("notify-send");
+                cmd.args(["--app-name=xcargo", "--icon=utilities-terminal"]);
+                if !success {
+                    cmd.arg("--urgency=critical");
+                }
+                cmd.args([&self.title, &self.message]);
+                cmd
+            }
+        }
+    }
+}
+
+/// The built-in notification plugin, registered when `[notifications]`
+/// enables anything
+#[derive(Debug, Clone)]
+pub struct NotificationPlugin {
+    config: NotificationsConfig,
+    network: NetworkConfig,
+}
+
+impl NotificationPlugin {
+    /// Plugin notifying as `config` says, posting through `network`
+    #[must_use]
+    pub fn new(config: NotificationsConfig, network: NetworkConfig) -> Self {
+        Self { config, network }
+    }
+
+    /// Whether `summary` is worth a notification
+    #[must_use]
+    pub fn wanted(&self, summary: &BuildsSummary) -> bool {
+        let long = summary.elapsed >= Duration::from_secs(self.config.min_duration);
+        let ran = !summary.succeeded.is_empty() || !summary.failed.is_empty();
+        long && ran
+            && if summary.success() {
+                self.config.on_success
+            } else {
+                self.config.on_failure
+            }
+    }
+
+    /// Show `notification` on the desktop
+    fn desktop(notification: &Notification, success: bool) -> Result<()> {
+        let mut cmd = notification.desktop_command(std::env::consts::OS, success);
+        let program = cmd.get_program().to_string_lossy().into_owned();
+        let output = match cmd.output() {
+            Ok(output) => output,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(Error::Config(format!(
+                    "Desktop notifications need {program}, which is not installed"
+                )))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(Error::Config(format!(
+                "{program} failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )))
+        }
+    }
+
+    /// Post `notification` to `webhook`
+    fn post(
+        &self,
+        webhook: &WebhookConfig,
+        notification: &Notification,
+        summary: &BuildsSummary,
+    ) -> Result<()> {
+        let payload = notification.payload(WebhookFormat::of(webhook), summary);
+        let mut curl = network::curl(&self.network)?;
+        curl.args(["--max-time", WEBHOOK_TIMEOUT, "-X", "POST"])
+            .args(["-H", "Content-Type: application/json"])
+            .args(["--data-binary", &payload, &webhook.url]);
+        let output = curl.output().map_err(|e| {
+            Error::Config(format!(
+                "Webhooks are posted with curl, which failed to start: {e}"
+            ))
+        })?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(Error::Config(format!(
+                "curl failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )))
+        }
+    }
+}
+
+/// Scheme and host of `url`, to name a webhook without its secret path
+fn redact(url: &str) -> &str {
+    let start = url.find("://").map_or(0, |i| i + 3);
+    url[start..]
+        .find('/')
+        .map_or(url, |end| &url[..start + end])
+}
+
+impl Plugin for NotificationPlugin {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn version(&self) -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn description(&self) -> &'static str {
+        "Desktop and webhook notifications when multi-target builds finish"
+    }
+
+    fn on_builds_finished(&self, ctx: &PluginContext) -> Result<()> {
+        let summary = BuildsSummary::from_context(ctx);
+        if !self.wanted(&summary) {
+            return Ok(());
+        }
+        let project = ctx
+            .project_root
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let notification = Notification::new(&summary, &project);
+
+        if self.config.desktop {
+            if let Err(e) = Self::desktop(&notification, summary.success()) {
+                helpers::warning(format!("Could not show a desktop notification: {e}"));
+            }
+        }
+        for webhook in &self.config.webhooks {
+            if let Err(e) = self.post(webhook, &notification, &summary) {
+                helpers::warning(format!(
+                    "Could not notify webhook {}: {e}",
+                    redact(&webhook.url)
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(failed: &[&str]) -> BuildsSummary {
+        BuildsSummary {
+            operation: "build".to_string(),
+            succeeded: vec![
+                "aarch64-unknown-linux-gnu".to_string(),
+                "x86_64-unknown-linux-musl".to_string(),
+            ],
+            failed: failed.iter().map(ToString::to_string).collect(),
+            skipped: 0,
+            cancelled: false,
+            elapsed: Duration::from_secs(725),
+        }
+    }
+
+    fn webhook(url: &str) -> WebhookConfig {
+        WebhookConfig {
+            url: url.to_string(),
+            format: None,
+        }
+    }
+
+    #[test]
+    fn test_notification_text() {
+        let notification = Notification::new(&summary(&[]), "app");
+        assert_eq!(notification.title, "xcargo build succeeded (app)");
+        assert_eq!(
+            notification.message,
+            "2 of 2 target(s) succeeded in 12m 05s"
+        );
+
+        let mut failed = summary(&["x86_64-pc-windows-gnu"]);
+        failed.skipped = 1;
+        let notification = Notification::new(&failed, "");
+        assert_eq!(notification.title, "xcargo build failed");
+        assert_eq!(
+            notification.message,
+            "1 of 3 target(s) failed after 12m 05s: x86_64-pc-windows-gnu; 1 skipped"
+        );
+    }
+
+    #[test]
+    fn test_webhook_payloads() {
+        assert_eq!(
+            WebhookFormat::of(&webhook("https://hooks.slack.com/services/T/B/X")),
+            WebhookFormat::Slack
+        );
+        assert_eq!(
+            WebhookFormat::of(&webhook("https://discord.com/api/webhooks/1/abc")),
+            WebhookFormat::Discord
+        );
+        let mut generic = webhook("https://hooks.slack.com/services/T/B/X");
+        generic.format = Some(WebhookFormat::Json);
+        assert_eq!(WebhookFormat::of(&generic), WebhookFormat::Json);
+
+        let summary = summary(&["x86_64-pc-windows-gnu"]);
+        let notification = Notification::new(&summary, "app");
+        let slack: serde_json::Value =
+            serde_json::from_str(&notification.payload(WebhookFormat::Slack, &summary)).unwrap();
+        assert!(slack["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("*xcargo build failed (app)*\n1 of 3"));
+        let plain: serde_json::Value =
+            serde_json::from_str(&notification.payload(WebhookFormat::Json, &summary)).unwrap();
+        assert_eq!(plain["success"], false);
+        assert_eq!(plain["failed"][0], "x86_64-pc-windows-gnu");
+        assert_eq!(plain["elapsed_secs"], 725);
+    }
+
+    #[test]
+    fn test_desktop_commands() {
+        let notification = Notification {
+            title: "xcargo build failed".to_string(),
+            message: "it's \"broken\"".to_string(),
+        };
+        let args = |cmd: &Command| -> Vec<String> {
+            cmd.get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect()
+        };
+
+        let linux = notification.desktop_command("linux", false);
+        assert_eq!(linux.get_program(), "notify-send");
+        assert!(args(&linux).contains(&"--urgency=critical".to_string()));
+        assert_eq!(args(&linux).last().unwrap(), "it's \"broken\"");
+
+        let macos = notification.desktop_command("macos", true);
+        assert_eq!(
+            args(&macos)[1],
+            "display notification \"it's \\\"broken\\\"\" with title \"xcargo build failed\""
+        );
+
+        let windows = notification.desktop_command("windows", true);
+        assert!(args(&windows)[3].contains("CreateTextNode('it''s \"broken\"')"));
+    }
+
+    #[test]
+    fn test_wanted() {
+        let config = NotificationsConfig {
+            desktop: true,
+            on_success: false,
+            ..NotificationsConfig::default()
+        };
+        let plugin = NotificationPlugin::new(config, NetworkConfig::default());
+        assert!(!plugin.wanted(&summary(&[])));
+        assert!(plugin.wanted(&summary(&["x86_64-pc-windows-gnu"])));
+
+        let mut quick = summary(&["x86_64-pc-windows-gnu"]);
+        quick.elapsed = Duration::from_secs(20);
+        assert!(!plugin.wanted(&quick));
+    }
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(
+            redact("https://hooks.slack.com/services/T000/B000/XXXX"),
+            "https://hooks.slack.com"
+        );
+        assert_eq!(redact("https://ci.example"), "https://ci.example");
+    }
+}
@@ -3,6 +3,7 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::build::strategy::Strategy;
 use crate::error::{Error, Result};
 
 use super::context::PluginContext;
@@ -57,6 +58,19 @@ impl PluginRegistry {
                 "Plugin '{name}' is already registered"
             )));
         }
+        for strategy in plugin.strategies() {
+            if Strategy::from(strategy.clone()).is_builtin() {
+                return Err(Error::Config(format!(
+                    "Plugin '{name}' cannot provide the built-in strategy '{strategy}'"
+                )));
+            }
+            if let Some(other) = self.strategy_provider(&strategy) {
+                return Err(Error::Config(format!(
+                    "Plugin '{name}' provides the strategy '{strategy}', which '{}' already provides",
+                    other.name()
+                )));
+            }
+        }
 
         // Initialize the plugin
         plugin.on_init()?;
@@ -103,6 +117,31 @@ impl PluginRegistry {
         self.execution_order.clone()
     }
 
+    /// Strategies the registered plugins provide, with their plugin, in
+    /// execution order
+    #[must_use]
+    pub fn strategies(&self) -> Vec<(String, Arc<dyn Plugin>)> {
+        self.execution_order
+            .iter()
+            .filter_map(|name| self.plugins.get(name))
+            .flat_map(|plugin| {
+                plugin
+                    .strategies()
+                    .into_iter()
+                    .map(|strategy| (strategy, Arc::clone(plugin)))
+            })
+            .collect()
+    }
+
+    /// The plugin providing `strategy`, if one does
+    #[must_use]
+    pub fn strategy_provider(&self, strategy: &str) -> Option<Arc<dyn Plugin>> {
+        self.strategies()
+            .into_iter()
+            .find(|(name, _)| name == strategy)
+            .map(|(_, plugin)| plugin)
+    }
+
     /// Execute a hook on all registered plugins
     ///
     /// Plugins execute in the order they were registered.
@@ -177,6 +216,21 @@ mod tests {
         }
     }
 
+    struct StrategyPlugin {
+        name: String,
+        strategy: String,
+    }
+
+    impl Plugin for StrategyPlugin {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn strategies(&self) -> Vec<String> {
+            vec![self.strategy.clone()]
+        }
+    }
+
     #[test]
     fn test_registry_new() {
         let registry = PluginRegistry::new();
@@ -289,6 +343,34 @@ mod tests {
         assert_eq!(list, vec!["plugin2", "plugin1"]);
     }
 
+    #[test]
+    fn test_plugin_strategies() {
+        let mut registry = PluginRegistry::new();
+        let plugin = |name: &str, strategy: &str| {
+            Box::new(StrategyPlugin {
+                name: name.to_string(),
+                strategy: strategy.to_string(),
+            })
+        };
+
+        registry
+            .register(plugin("remote", "remote-builder"))
+            .unwrap();
+        assert_eq!(registry.strategies().len(), 1);
+        assert_eq!(
+            registry.strategy_provider("remote-builder").unwrap().name(),
+            "remote"
+        );
+        assert!(registry.strategy_provider("nix-shell").is_none());
+
+        // Strategy names are unique, and the built-in ones are taken
+        assert!(registry
+            .register(plugin("other", "remote-builder"))
+            .is_err());
+        assert!(registry.register(plugin("zig", "zig")).is_err());
+        assert_eq!(registry.count(), 1);
+    }
+
     #[test]
     fn test_execute_hook() {
         let mut registry = PluginRegistry::new();
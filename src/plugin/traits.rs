@@ -2,7 +2,7 @@
 
 use crate::error::Result;
 
-use super::context::PluginContext;
+use super::context::{BuildCommand, PluginContext};
 
 /// Main plugin trait that all plugins must implement
 ///
@@ -11,6 +11,7 @@ use super::context::PluginContext;
 /// - Post-build: After compilation completes
 /// - Pre-toolchain-install: Before toolchain installation
 /// - Post-toolchain-install: After toolchain installation
+/// - Pre-publish: For each artifact, before it may be published
 ///
 /// # Example
 ///
@@ -83,6 +84,59 @@ pub trait Plugin: Send + Sync {
         Ok(())
     }
 
+    /// Called for each artifact placed in the artifact directory, with its
+    /// path in the `artifact` metadata
+    ///
+    /// Return `Err` to keep the artifact from being published (e.g., a
+    /// malware scan or compliance check failed).
+    fn on_pre_publish(&self, _ctx: &PluginContext) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once when a multi-target build (`--all`, `--group` or several
+    /// `--target`s) has finished, however it ended
+    ///
+    /// `ctx.target` is empty; [`BuildsSummary::from_context`] reads which
+    /// targets succeeded, failed or were skipped and how long it took. The
+    /// build is over, so an `Err` only warns.
+    ///
+    /// [`BuildsSummary::from_context`]: super::BuildsSummary::from_context
+    fn on_builds_finished(&self, _ctx: &PluginContext) -> Result<()> {
+        Ok(())
+    }
+
+    /// Build strategies this plugin provides, e.g. `["remote-builder"]`
+    ///
+    /// They join the native → zig → container chain at their
+    /// `[strategies.<name>] priority` (0, the last resort, by default) for
+    /// the targets its `targets` patterns match, and can be chosen with
+    /// `[targets.<triple>] strategy = "<name>"`.
+    fn strategies(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Whether `strategy` can build `ctx.target` on this machine
+    ///
+    /// Return `Ok` with why it can, or `Err` with what is missing so the
+    /// chain moves on to the next strategy.
+    fn strategy_available(&self, _strategy: &str, _ctx: &PluginContext) -> Result<String> {
+        Ok(format!("provided by plugin {}", self.name()))
+    }
+
+    /// The command that builds `ctx.target` with `strategy`, given the
+    /// cargo command xcargo would run
+    ///
+    /// Artifacts are collected from `target/<triple>` afterwards, so a
+    /// strategy building elsewhere copies them back.
+    fn strategy_command(
+        &self,
+        _strategy: &str,
+        _ctx: &PluginContext,
+        command: BuildCommand,
+    ) -> Result<BuildCommand> {
+        Ok(command)
+    }
+
     /// Called when plugin is initialized
     fn on_init(&self) -> Result<()> {
         Ok(())
@@ -130,7 +184,27 @@ mod tests {
         assert!(plugin.on_pre_build(&ctx).is_ok());
         assert!(plugin.on_post_build(&ctx).is_ok());
         assert!(plugin.on_build_failed(&ctx, "test error").is_ok());
+        assert!(plugin.on_pre_publish(&ctx).is_ok());
         assert!(plugin.on_init().is_ok());
         assert!(plugin.on_shutdown().is_ok());
     }
+
+    #[test]
+    fn test_plugin_strategy_default_impl() {
+        let plugin = TestPlugin;
+        let ctx = PluginContext::default();
+        let command = BuildCommand {
+            program: "cargo".to_string(),
+            ..BuildCommand::default()
+        };
+
+        assert!(plugin.strategies().is_empty());
+        assert!(plugin.strategy_available("remote", &ctx).is_ok());
+        assert_eq!(
+            plugin
+                .strategy_command("remote", &ctx, command.clone())
+                .unwrap(),
+            command
+        );
+    }
 }
@@ -0,0 +1,252 @@
+//! Catalog of prebuilt cross toolchains
+//!
+//! Lists the toolchains `xcargo toolchain provision --standalone` can
+//! download: the ARM GNU toolchain, musl.cc's cross compilers and
+//! llvm-mingw, each for the hosts it is published for. A copy ships with
+//! xcargo; `--update-catalog`, or provisioning with a copy older than a
+//! week, fetches the current one from `[toolchain] catalog_url` into
+//! `~/.xcargo/catalog.json`. Every entry carries the `sha256` of its
+//! archive, and an archive without a valid one, or that doesn't match it,
+//! is never installed.
+
+use crate::config::NetworkConfig;
+use crate::error::{Error, Result};
+use crate::network;
+use crate::trace;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Where the catalog is fetched from unless `[toolchain] catalog_url` says:
+/// this release's tag, so what is fetched can't change under it
+pub const DEFAULT_CATALOG_URL: &str = concat!(
+    "https://raw.githubusercontent.com/ibrahimcesar/xcargo/v",
+    env!("CARGO_PKG_VERSION"),
+    "/src/provision/catalog.json"
+);
+
+/// The catalog shipped with xcargo
+const BUNDLED: &str = include_str!("catalog.json");
+
+/// Age after which provisioning fetches the catalog again
+const MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Prebuilt toolchains, by target and host
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Catalog {
+    /// Format version
+    pub version: u32,
+    /// The toolchains
+    pub toolchains: Vec<CatalogEntry>,
+}
+
+/// One prebuilt toolchain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    /// Who publishes it (e.g., "musl.cc")
+    pub name: String,
+    /// Rust target it links for
+    pub target: String,
+    /// Hosts it runs on, as `<arch>-<os>` (e.g., "x86_64-linux")
+    pub hosts: Vec<String>,
+    /// URL of a `.tar.gz` or `.tar.xz` archive
+    pub url: String,
+    /// SHA-256 of the archive, hex encoded
+    pub sha256: String,
+    /// Linker in the archive's `bin` directory
+    pub linker: String,
+}
+
+impl CatalogEntry {
+    /// The archive's SHA-256, unless the entry has none that is valid
+    #[must_use]
+    pub fn checksum(&self) -> Option<&str> {
+        let sha256 = self.sha256.trim();
+        (sha256.len() == 64 && sha256.chars().all(|c| c.is_ascii_hexdigit())).then_some(sha256)
+    }
+
+    /// Whether it runs on `host_os` / `host_arch` (`std::env::consts`)
+    #[must_use]
+    pub fn runs_on(&self, host_os: &str, host_arch: &str) -> bool {
+        let host = format!("{host_arch}-{host_os}");
+        self.hosts.contains(&host)
+    }
+}
+
+impl Catalog {
+    /// The catalog shipped with xcargo
+    ///
+    /// # Panics
+    ///
+    /// Never: the bundled catalog is checked by the tests.
+    #[must_use]
+    pub fn bundled() -> Self {
+        Self::parse(BUNDLED).expect("the bundled catalog parses")
+    }
+
+    /// Parse a catalog
+    pub fn parse(contents: &str) -> std::result::Result<Self, String> {
+        serde_json::from_str(contents).map_err(|e| e.to_string())
+    }
+
+    /// Where a fetched catalog is cached (`~/.xcargo/catalog.json`)
+    pub fn cache_path() -> Result<PathBuf> {
+        let home = dirs::home_dir()
+            .ok_or_else(|| Error::Config("Could not determine home directory".to_string()))?;
+        Ok(home.join(".xcargo").join("catalog.json"))
+    }
+
+    /// The cached catalog, or the bundled one when there is none or it
+    /// doesn't parse
+    #[must_use]
+    pub fn load() -> Self {
+        Self::cache_path()
+            .ok()
+            .and_then(|path| Self::load_from(&path))
+            .unwrap_or_else(Self::bundled)
+    }
+
+    fn load_from(path: &Path) -> Option<Self> {
+        let contents = std::fs::read_to_string(path).ok()?;
+        Self::parse(&contents).ok()
+    }
+
+    /// Whether the cached catalog is missing or older than a week
+    #[must_use]
+    pub fn is_stale() -> bool {
+        let modified = Self::cache_path()
+            .and_then(|path| Ok(std::fs::metadata(path)?.modified()?))
+            .ok();
+        modified.map_or(true, |modified| {
+            SystemTime::now()
+                .duration_since(modified)
+                .is_ok_and(|age| age > MAX_AGE)
+        })
+    }
+
+    /// Fetch the catalog at `url` and cache it
+    ///
+    /// The cache is only replaced by a catalog that parses.
+    pub fn update(url: &str, network: &NetworkConfig) -> Result<Self> {
+        let path = Self::cache_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let download = path.with_extension("json.partial");
+        let mut curl = network::curl(network)?;
+        curl.arg("-o").arg(&download).arg(url);
+        let status = curl
+            .status()
+            .map_err(|e| Error::Toolchain(format!("Failed to run curl: {e}")))?;
+        trace::record_command(&curl, status.code());
+        if !status.success() {
+            let _ = std::fs::remove_file(&download);
+            return Err(Error::Toolchain(format!(
+                "Failed to download the toolchain catalog from {url}"
+            )));
+        }
+
+        let catalog = std::fs::read_to_string(&download)
+            .map_err(Error::from)
+            .and_then(|contents| {
+                Self::parse(&contents).map_err(|e| {
+                    Error::Toolchain(format!("The toolchain catalog at {url} is invalid: {e}"))
+                })
+            });
+        match catalog {
+            Ok(catalog) => {
+                std::fs::rename(&download, &path)?;
+                Ok(catalog)
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&download);
+                Err(e)
+            }
+        }
+    }
+
+    /// The toolchain for `triple` that runs on this kind of host
+    #[must_use]
+    pub fn find(&self, triple: &str, host_os: &str, host_arch: &str) -> Option<&CatalogEntry> {
+        self.toolchains
+            .iter()
+            .find(|entry| entry.target == triple && entry.runs_on(host_os, host_arch))
+    }
+
+    /// Targets with a toolchain that runs on this kind of host, sorted
+    #[must_use]
+    pub fn targets(&self, host_os: &str, host_arch: &str) -> Vec<&str> {
+        let mut targets: Vec<&str> = self
+            .toolchains
+            .iter()
+            .filter(|entry| entry.runs_on(host_os, host_arch))
+            .map(|entry| entry.target.as_str())
+            .collect();
+        targets.sort_unstable();
+        targets.dedup();
+        targets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_catalog() {
+        let catalog = Catalog::bundled();
+        let mingw = catalog
+            .find("x86_64-pc-windows-gnu", "linux", "x86_64")
+            .unwrap();
+        assert_eq!(mingw.name, "musl.cc");
+        assert_eq!(mingw.linker, "x86_64-w64-mingw32-gcc");
+        assert!(catalog
+            .find("x86_64-pc-windows-gnu", "macos", "aarch64")
+            .is_none());
+        assert!(catalog
+            .find("aarch64-pc-windows-gnullvm", "macos", "aarch64")
+            .is_some());
+
+        let targets = catalog.targets("linux", "x86_64");
+        assert!(targets.contains(&"aarch64-unknown-linux-gnu"));
+        assert!(targets.contains(&"riscv64gc-unknown-linux-musl"));
+        assert!(!targets.contains(&"wasm32-unknown-unknown"));
+        for entry in &catalog.toolchains {
+            assert!(entry.url.starts_with("https://"), "{}", entry.url);
+        }
+    }
+
+    #[test]
+    fn test_load_cached_catalog() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("catalog.json");
+        std::fs::write(
+            &path,
+            r#"{"version": 1, "toolchains": [{"name": "corp", "target": "x86_64-unknown-freebsd", "hosts": ["x86_64-linux"], "url": "https://mirror.corp.example/freebsd.tar.xz", "sha256": "ab12", "linker": "x86_64-unknown-freebsd-clang"}]}"#,
+        )
+        .unwrap();
+        let catalog = Catalog::load_from(&path).unwrap();
+        let entry = catalog
+            .find("x86_64-unknown-freebsd", "linux", "x86_64")
+            .unwrap();
+        assert_eq!(entry.sha256, "ab12");
+        assert_eq!(entry.checksum(), None);
+
+        std::fs::write(&path, "{").unwrap();
+        assert!(Catalog::load_from(&path).is_none());
+    }
+
+    #[test]
+    fn test_entries_need_a_checksum() {
+        let entry = r#"{"name": "corp", "target": "x86_64-unknown-freebsd", "hosts": ["x86_64-linux"], "url": "https://mirror.corp.example/freebsd.tar.xz", "linker": "cc"}"#;
+        assert!(Catalog::parse(&format!(r#"{{"version": 1, "toolchains": [{entry}]}}"#)).is_err());
+
+        let sha256 = "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855";
+        let mut entry: CatalogEntry =
+            serde_json::from_str(&entry.replace(r#""linker""#, &format!(r#""sha256": "{sha256}", "linker""#)))
+                .unwrap();
+        assert_eq!(entry.checksum(), Some(sha256));
+        entry.sha256 = String::new();
+        assert_eq!(entry.checksum(), None);
+    }
+}
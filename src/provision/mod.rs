@@ -0,0 +1,400 @@
+//! Cross GCC toolchains for targets that link with GCC
+//!
+//! `x86_64-pc-windows-gnu` links with MinGW-w64 and
+//! `aarch64-unknown-linux-gnu` with an AArch64 cross GCC. When the linker
+//! is missing, xcargo can install it with the host's package manager (apt,
+//! dnf, pacman or Homebrew) or download a prebuilt standalone toolchain
+//! into `~/.xcargo/tools/<triple>`. Standalone toolchains come from the
+//! [`catalog`], which also covers targets without packages, such as
+//! `armv7-unknown-linux-gnueabihf`, the musl targets and the `gnullvm`
+//! Windows targets. Builds pick up a provisioned linker without any
+//! configuration.
+//!
+//! ```no_run
+//! use xcargo::config::NetworkConfig;
+//! use xcargo::provision::Provision;
+//!
+//! # fn example() -> xcargo::Result<()> {
+//! if let Some(provision) = Provision::plan("x86_64-pc-windows-gnu", false) {
+//!     println!("{}", provision.describe());
+//!     let linker = provision.run(&NetworkConfig::default())?;
+//!     println!("Linker: {linker}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod catalog;
+
+pub use catalog::{Catalog, CatalogEntry};
+
+use crate::config::NetworkConfig;
+use crate::deps::Fetch;
+use crate::error::{Error, Result};
+use crate::trace;
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Targets xcargo can provision a cross GCC for
+pub const SUPPORTED: &[&str] = &["x86_64-pc-windows-gnu", "aarch64-unknown-linux-gnu"];
+
+/// A host package manager
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    /// Debian, Ubuntu and derivatives
+    Apt,
+    /// Fedora, RHEL and derivatives
+    Dnf,
+    /// Arch Linux and derivatives
+    Pacman,
+    /// Homebrew on macOS
+    Brew,
+}
+
+impl PackageManager {
+    /// Package managers in the order they are looked for
+    pub const ALL: [Self; 4] = [Self::Apt, Self::Dnf, Self::Pacman, Self::Brew];
+
+    /// Program that installs packages
+    #[must_use]
+    pub fn program(self) -> &'static str {
+        match self {
+            Self::Apt => "apt-get",
+            Self::Dnf => "dnf",
+            Self::Pacman => "pacman",
+            Self::Brew => "brew",
+        }
+    }
+
+    /// The package manager of this host, if it has a known one
+    ///
+    /// Homebrew is only used on macOS, where the system has no other.
+    #[must_use]
+    pub fn detect() -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .filter(|manager| (*manager == Self::Brew) == cfg!(target_os = "macos"))
+            .find(|manager| which::which(manager.program()).is_ok())
+    }
+
+    /// Command installing `packages`, without `sudo`
+    #[must_use]
+    pub fn install_args(self, packages: &[&str]) -> Vec<String> {
+        let mut args: Vec<String> = match self {
+            Self::Apt => vec!["apt-get", "install", "-y"],
+            Self::Dnf => vec!["dnf", "install", "-y"],
+            Self::Pacman => vec!["pacman", "-S", "--needed", "--noconfirm"],
+            Self::Brew => vec!["brew", "install"],
+        }
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+        args.extend(packages.iter().map(|package| (*package).to_string()));
+        args
+    }
+
+    /// Whether installing needs root, through `sudo`
+    #[must_use]
+    pub fn needs_root(self) -> bool {
+        self != Self::Brew
+    }
+}
+
+impl fmt::Display for PackageManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Apt => "apt",
+            other => other.program(),
+        })
+    }
+}
+
+/// Packages providing a target's cross GCC
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packages {
+    /// Package names
+    pub names: Vec<&'static str>,
+    /// Homebrew tap the packages come from
+    pub tap: Option<&'static str>,
+    /// Linker the packages install
+    pub linker: &'static str,
+}
+
+/// Packages providing the cross GCC for `triple` from `manager`
+#[must_use]
+pub fn packages(triple: &str, manager: PackageManager) -> Option<Packages> {
+    let (names, tap, linker) = match (triple, manager) {
+        ("x86_64-pc-windows-gnu", PackageManager::Apt) => {
+            (vec!["gcc-mingw-w64-x86-64"], None, "x86_64-w64-mingw32-gcc")
+        }
+        ("x86_64-pc-windows-gnu", PackageManager::Dnf) => {
+            (vec!["mingw64-gcc"], None, "x86_64-w64-mingw32-gcc")
+        }
+        ("x86_64-pc-windows-gnu", PackageManager::Pacman) => {
+            (vec!["mingw-w64-gcc"], None, "x86_64-w64-mingw32-gcc")
+        }
+        ("x86_64-pc-windows-gnu", PackageManager::Brew) => {
+            (vec!["mingw-w64"], None, "x86_64-w64-mingw32-gcc")
+        }
+        ("aarch64-unknown-linux-gnu", PackageManager::Apt | PackageManager::Dnf) => {
+            (vec!["gcc-aarch64-linux-gnu"], None, "aarch64-linux-gnu-gcc")
+        }
+        ("aarch64-unknown-linux-gnu", PackageManager::Pacman) => {
+            (vec!["aarch64-linux-gnu-gcc"], None, "aarch64-linux-gnu-gcc")
+        }
+        ("aarch64-unknown-linux-gnu", PackageManager::Brew) => (
+            vec!["aarch64-unknown-linux-gnu"],
+            Some("messense/macos-cross-toolchains"),
+            "aarch64-unknown-linux-gnu-gcc",
+        ),
+        _ => return None,
+    };
+    Some(Packages { names, tap, linker })
+}
+
+/// A prebuilt toolchain downloaded into `~/.xcargo/tools`
+pub type Standalone = CatalogEntry;
+
+/// The prebuilt toolchain for `triple` in the catalog, on hosts it is
+/// published for
+#[must_use]
+pub fn standalone(triple: &str, host_os: &str, host_arch: &str) -> Option<Standalone> {
+    Catalog::load().find(triple, host_os, host_arch).cloned()
+}
+
+/// Targets with a package or a prebuilt toolchain for this host, sorted
+#[must_use]
+pub fn provisionable() -> Vec<String> {
+    let catalog = Catalog::load();
+    let mut targets: Vec<String> = SUPPORTED
+        .iter()
+        .copied()
+        .chain(catalog.targets(std::env::consts::OS, std::env::consts::ARCH))
+        .map(str::to_string)
+        .collect();
+    targets.sort();
+    targets.dedup();
+    targets
+}
+
+/// File in a standalone toolchain's directory naming its linker
+const LINKER_MARKER: &str = ".xcargo-linker";
+
+/// Directory holding standalone toolchains (`~/.xcargo/tools`)
+pub fn tools_dir() -> Result<PathBuf> {
+    let home = dirs::home_dir()
+        .ok_or_else(|| Error::Config("Could not determine home directory".to_string()))?;
+    Ok(home.join(".xcargo").join("tools"))
+}
+
+/// A cross GCC for `triple` that is already installed
+///
+/// Linkers on `PATH` come first, from any package manager, then a
+/// standalone toolchain in `~/.xcargo/tools`, returned as a full path: the
+/// linker recorded when it was provisioned, or any catalog linker found in
+/// its `bin` directory.
+#[must_use]
+pub fn find_linker(triple: &str) -> Option<String> {
+    let on_path = PackageManager::ALL
+        .into_iter()
+        .filter_map(|manager| packages(triple, manager))
+        .map(|packages| packages.linker)
+        .find(|linker| which::which(linker).is_ok());
+    if let Some(linker) = on_path {
+        return Some(linker.to_string());
+    }
+
+    let dir = tools_dir().ok()?.join(triple);
+    let recorded = std::fs::read_to_string(dir.join(LINKER_MARKER))
+        .ok()
+        .map(|linker| linker.trim().to_string());
+    recorded
+        .into_iter()
+        .chain(
+            Catalog::load()
+                .toolchains
+                .into_iter()
+                .filter(|entry| entry.target == triple)
+                .map(|entry| entry.linker),
+        )
+        .map(|linker| dir.join("bin").join(linker))
+        .find(|path| path.is_file())
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
+/// How a cross GCC gets installed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Method {
+    /// Packages from the host's package manager
+    Packages(PackageManager, Packages),
+    /// A prebuilt toolchain downloaded into `~/.xcargo/tools`
+    Standalone(Standalone),
+}
+
+/// How to install the cross GCC of one target on this host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provision {
+    /// Target triple
+    pub target: String,
+    /// How it is installed
+    pub method: Method,
+}
+
+impl Provision {
+    /// Plan installing the cross GCC for `triple`
+    ///
+    /// The host's package manager is preferred; a standalone toolchain is
+    /// used without one, or when `standalone` asks for it. `None` when
+    /// neither is available for this target on this host.
+    #[must_use]
+    pub fn plan(triple: &str, standalone: bool) -> Option<Self> {
+        let packages = PackageManager::detect()
+            .filter(|_| !standalone)
+            .and_then(|manager| Some(Method::Packages(manager, packages(triple, manager)?)));
+        let method = packages.or_else(|| {
+            self::standalone(triple, std::env::consts::OS, std::env::consts::ARCH)
+                .map(Method::Standalone)
+        })?;
+        Some(Self {
+            target: triple.to_string(),
+            method,
+        })
+    }
+
+    /// Where a standalone toolchain goes
+    pub fn dest(&self) -> Result<PathBuf> {
+        Ok(tools_dir()?.join(&self.target))
+    }
+
+    /// The commands or download that install it, for display
+    #[must_use]
+    pub fn describe(&self) -> String {
+        match &self.method {
+            Method::Packages(manager, packages) => self
+                .commands(*manager, packages)
+                .iter()
+                .map(|args| args.join(" "))
+                .collect::<Vec<_>>()
+                .join(" && "),
+            Method::Standalone(standalone) => {
+                format!(
+                    "download {} into ~/.xcargo/tools/{}",
+                    standalone.url, self.target
+                )
+            }
+        }
+    }
+
+    /// Package manager commands, with `sudo` where it is needed and present
+    fn commands(&self, manager: PackageManager, packages: &Packages) -> Vec<Vec<String>> {
+        let sudo = manager.needs_root() && which::which("sudo").is_ok();
+        let mut commands = Vec::new();
+        if let Some(tap) = packages.tap {
+            commands.push(vec!["brew".to_string(), "tap".to_string(), tap.to_string()]);
+        }
+        let mut install = manager.install_args(&packages.names);
+        if sudo {
+            install.insert(0, "sudo".to_string());
+        }
+        commands.push(install);
+        commands
+    }
+
+    /// Install the cross GCC, returning the linker to use
+    ///
+    /// A standalone toolchain is only installed with a valid checksum in
+    /// the catalog that its archive matches, and its linker recorded for
+    /// [`find_linker`]. Downloads use the proxy and CA bundle of `network`.
+    pub fn run(&self, network: &NetworkConfig) -> Result<String> {
+        match &self.method {
+            Method::Packages(manager, packages) => {
+                for args in self.commands(*manager, packages) {
+                    let mut command = Command::new(&args[0]);
+                    command.args(&args[1..]);
+                    let status = command
+                        .status()
+                        .map_err(|e| Error::Toolchain(format!("Failed to run {}: {e}", args[0])))?;
+                    trace::record_command(&command, status.code());
+                    if !status.success() {
+                        return Err(Error::Toolchain(format!(
+                            "Failed to install the cross GCC for {}: {} exited with {status}",
+                            self.target,
+                            args.join(" ")
+                        )));
+                    }
+                }
+                Ok(packages.linker.to_string())
+            }
+            Method::Standalone(standalone) => {
+                let Some(sha256) = standalone.checksum() else {
+                    return Err(Error::Toolchain(format!(
+                        "The catalog has no valid sha256 for {} ({}); refusing to install it unverified",
+                        standalone.name, standalone.url
+                    )));
+                };
+                let dest = self.dest()?;
+                Fetch {
+                    url: standalone.url.clone(),
+                    dest: dest.clone(),
+                    target: self.target.clone(),
+                    sha256: Some(sha256.to_string()),
+                }
+                .ensure(network)?;
+                let linker = dest.join("bin").join(&standalone.linker);
+                if !linker.is_file() {
+                    return Err(Error::Toolchain(format!(
+                        "The toolchain downloaded from {} has no bin/{}",
+                        standalone.url, standalone.linker
+                    )));
+                }
+                std::fs::write(dest.join(LINKER_MARKER), &standalone.linker)?;
+                Ok(linker.to_string_lossy().into_owned())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packages() {
+        for triple in SUPPORTED {
+            for manager in PackageManager::ALL {
+                assert!(packages(triple, manager).is_some(), "{triple} {manager}");
+            }
+        }
+        assert!(packages("x86_64-apple-darwin", PackageManager::Apt).is_none());
+
+        let brew = packages("aarch64-unknown-linux-gnu", PackageManager::Brew).unwrap();
+        assert_eq!(brew.tap, Some("messense/macos-cross-toolchains"));
+        assert_eq!(brew.linker, "aarch64-unknown-linux-gnu-gcc");
+    }
+
+    #[test]
+    fn test_install_args() {
+        assert_eq!(
+            PackageManager::Pacman.install_args(&["mingw-w64-gcc"]),
+            ["pacman", "-S", "--needed", "--noconfirm", "mingw-w64-gcc"]
+        );
+        assert!(!PackageManager::Brew.needs_root());
+        assert_eq!(PackageManager::Apt.to_string(), "apt");
+    }
+
+    #[test]
+    fn test_standalone_only_on_x86_64_linux() {
+        assert!(standalone("x86_64-pc-windows-gnu", "linux", "x86_64").is_some());
+        assert!(standalone("x86_64-pc-windows-gnu", "macos", "aarch64").is_none());
+        assert!(standalone("riscv64gc-unknown-linux-gnu", "linux", "x86_64").is_none());
+    }
+
+    #[test]
+    fn test_standalone_from_catalog() {
+        let armv7 = standalone("armv7-unknown-linux-gnueabihf", "linux", "aarch64").unwrap();
+        assert!(armv7.url.contains("aarch64-arm-none-linux-gnueabihf"));
+        assert_eq!(armv7.linker, "arm-none-linux-gnueabihf-gcc");
+        assert!(standalone("aarch64-pc-windows-gnullvm", "macos", "aarch64").is_some());
+    }
+}
@@ -0,0 +1,210 @@
+//! Per-build scratch directories
+//!
+//! Wrapper scripts, generated configs and extracted sysroots that only
+//! live for one build go in a [`ScratchDir`] under
+//! `<target-dir>/xcargo/tmp/`, never in the project root. A scratch
+//! directory is removed when it is dropped, whether the build succeeded or
+//! failed; the CLI's Ctrl-C handler calls [`remove_live`] before exiting.
+//! Directories left behind by killed processes are swept after a day.
+//!
+//! ```no_run
+//! use xcargo::scratch::ScratchDir;
+//!
+//! # fn example() -> xcargo::Result<()> {
+//! let scratch = ScratchDir::for_build("x86_64-pc-windows-gnu")?;
+//! let config = scratch.write("config.toml", "[build]\n")?;
+//! // ... use `config` for the duration of the build ...
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::build::clean;
+use crate::error::Result;
+use crate::paths;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// Scratch space location inside cargo's target directory
+const SCRATCH_SUBDIR: &str = "xcargo/tmp";
+
+/// Age after which leftovers from other processes are removed
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Scratch directories that still exist, removed on interrupt
+static LIVE: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Distinguishes scratch directories created by one process
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A temporary directory for one build, removed on drop
+#[derive(Debug)]
+pub struct ScratchDir {
+    path: PathBuf,
+    keep: bool,
+}
+
+impl ScratchDir {
+    /// Create a scratch directory for a build of `label` (e.g., a target triple)
+    /// under `<target-dir>/xcargo/tmp/`
+    ///
+    /// Falls back to the system temp directory when cargo's target
+    /// directory cannot be determined.
+    pub fn for_build(label: &str) -> Result<Self> {
+        let root = clean::target_directory().map_or_else(
+            |_| std::env::temp_dir().join("xcargo"),
+            |dir| dir.join(SCRATCH_SUBDIR),
+        );
+        Self::create_in(&root, label)
+    }
+
+    /// Create a scratch directory for `label` inside `root`
+    pub fn create_in(root: &Path, label: &str) -> Result<Self> {
+        sweep_stale(root);
+
+        let name = format!(
+            "{}-{}-{}",
+            sanitize(label),
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = root.join(name);
+        fs::create_dir_all(paths::long_path(&path))?;
+        lock_live().push(path.clone());
+
+        Ok(Self { path, keep: false })
+    }
+
+    /// Directory path
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Path for a file named `name` inside the directory
+    #[must_use]
+    pub fn join(&self, name: impl AsRef<Path>) -> PathBuf {
+        self.path.join(name)
+    }
+
+    /// Write `contents` to a file named `name`, returning its path
+    pub fn write(&self, name: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<PathBuf> {
+        let path = self.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(paths::long_path(parent))?;
+        }
+        fs::write(paths::long_path(&path), contents)?;
+        Ok(path)
+    }
+
+    /// Keep the directory after drop (for debugging), returning its path
+    #[must_use]
+    pub fn keep(mut self) -> PathBuf {
+        self.keep = true;
+        unregister(&self.path);
+        self.path.clone()
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        if self.keep {
+            return;
+        }
+        let _ = fs::remove_dir_all(paths::long_path(&self.path));
+        unregister(&self.path);
+    }
+}
+
+/// Remove every scratch directory that still exists (e.g., on interrupt)
+pub fn remove_live() {
+    for path in lock_live().drain(..) {
+        let _ = fs::remove_dir_all(paths::long_path(&path));
+    }
+}
+
+fn lock_live() -> std::sync::MutexGuard<'static, Vec<PathBuf>> {
+    LIVE.lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+fn unregister(path: &Path) {
+    lock_live().retain(|live| live != path);
+}
+
+/// Remove directories in `root` older than [`STALE_AFTER`]
+fn sweep_stale(root: &Path) {
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+    let now = SystemTime::now();
+    for entry in entries.flatten() {
+        let stale = entry
+            .metadata()
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age > STALE_AFTER);
+        if stale {
+            let _ = fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+/// Keep directory names portable
+fn sanitize(label: &str) -> String {
+    label
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_removed_on_drop() {
+        let temp = TempDir::new().unwrap();
+        let scratch = ScratchDir::create_in(temp.path(), "x86_64-pc-windows-gnu").unwrap();
+        let file = scratch.write("wrappers/cc", "#!/bin/sh\n").unwrap();
+        assert!(file.is_file());
+        assert!(scratch.path().starts_with(temp.path()));
+
+        let path = scratch.path().to_path_buf();
+        drop(scratch);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_keep_and_registry() {
+        let temp = TempDir::new().unwrap();
+        let first = ScratchDir::create_in(temp.path(), "kept").unwrap();
+        let second = ScratchDir::create_in(temp.path(), "kept").unwrap();
+        assert_ne!(first.path(), second.path());
+
+        let kept = first.keep();
+        drop(second);
+        assert!(kept.is_dir());
+
+        let live = ScratchDir::create_in(temp.path(), "live").unwrap();
+        let path = live.path().to_path_buf();
+        assert!(lock_live().contains(&path));
+        drop(live);
+        assert!(!lock_live().contains(&path));
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize("my target/spec.json"), "my_target_spec.json");
+    }
+}
@@ -0,0 +1,20 @@
+//! Quoting for commands run through `sh -c`
+//!
+//! Container builds, remote hosts, Nix shells and the fake toolchains all
+//! pass commands to a POSIX shell as one string.
+
+/// Quote `text` as a single shell word
+pub(crate) fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        assert_eq!(quote("a b"), "'a b'");
+    }
+}
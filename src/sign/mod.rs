@@ -0,0 +1,22 @@
+//! Release signing steps for built artifacts
+//!
+//! Artifacts are expected to be code signed (e.g., with `codesign
+//! --options runtime`) before they are notarized.
+
+pub mod notarize;
+
+use crate::build::artifacts::ArtifactManifest;
+use std::path::{Path, PathBuf};
+
+pub use notarize::{Credentials, Notarizer, SubmissionStatus};
+
+/// macOS artifacts listed in the manifest in `dir`
+#[must_use]
+pub fn macos_artifacts(dir: &Path, manifest: &ArtifactManifest) -> Vec<PathBuf> {
+    manifest
+        .artifacts
+        .iter()
+        .filter(|artifact| artifact.target.contains("-apple-darwin"))
+        .map(|artifact| dir.join(&artifact.path))
+        .collect()
+}
@@ -0,0 +1,421 @@
+//! Apple notarization with `xcrun notarytool`
+//!
+//! Notarizing a signed macOS artifact takes three steps:
+//!
+//! 1. `notarytool submit` uploads it (bare binaries are zipped first, since
+//!    Apple only accepts `.zip`, `.pkg` and `.dmg` uploads)
+//! 2. `notarytool info` is polled until Apple accepts or rejects it; on
+//!    rejection the `notarytool log` is fetched and returned in the error
+//! 3. `stapler staple` attaches the ticket to `.app`, `.pkg` and `.dmg`
+//!    artifacts so Gatekeeper can verify them offline. Bare binaries cannot
+//!    carry a ticket and are checked online instead.
+//!
+//! Credentials come from a keychain profile created with
+//! `xcrun notarytool store-credentials`, or from environment variables
+//! (see [`Credentials::resolve`]).
+
+use crate::config::NotarizeConfig;
+use crate::error::{Error, Result};
+use crate::output::helpers;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::{Duration, Instant};
+
+/// Apple ID used with an app-specific password
+pub const ENV_APPLE_ID: &str = "APPLE_ID";
+/// Developer team ID
+pub const ENV_TEAM_ID: &str = "APPLE_TEAM_ID";
+/// App-specific password for [`ENV_APPLE_ID`]
+pub const ENV_PASSWORD: &str = "APPLE_APP_SPECIFIC_PASSWORD";
+/// Path to an App Store Connect API key (`AuthKey_<id>.p8`)
+pub const ENV_API_KEY: &str = "APPLE_API_KEY";
+/// App Store Connect API key ID
+pub const ENV_API_KEY_ID: &str = "APPLE_API_KEY_ID";
+/// App Store Connect API issuer ID
+pub const ENV_API_ISSUER: &str = "APPLE_API_ISSUER";
+
+/// How notarytool authenticates with Apple
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Credentials {
+    /// Profile stored in the keychain by `notarytool store-credentials`
+    KeychainProfile(String),
+    /// Apple ID with an app-specific password
+    AppleId {
+        /// Apple ID email
+        apple_id: String,
+        /// Developer team ID
+        team_id: String,
+        /// App-specific password
+        password: String,
+    },
+    /// App Store Connect API key
+    ApiKey {
+        /// Path to the `.p8` key file
+        key: String,
+        /// Key ID
+        key_id: String,
+        /// Issuer ID
+        issuer: String,
+    },
+}
+
+impl Credentials {
+    /// Pick credentials from config or the environment
+    ///
+    /// In order: `[sign.notarize] keychain_profile`, then an API key
+    /// (`APPLE_API_KEY`, `APPLE_API_KEY_ID`, `APPLE_API_ISSUER`), then an
+    /// Apple ID (`APPLE_ID`, `APPLE_TEAM_ID`, `APPLE_APP_SPECIFIC_PASSWORD`).
+    pub fn resolve(
+        config: &NotarizeConfig,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self> {
+        if let Some(profile) = &config.keychain_profile {
+            return Ok(Self::KeychainProfile(profile.clone()));
+        }
+
+        if let (Some(key), Some(key_id), Some(issuer)) = (
+            lookup(ENV_API_KEY),
+            lookup(ENV_API_KEY_ID),
+            lookup(ENV_API_ISSUER),
+        ) {
+            return Ok(Self::ApiKey {
+                key,
+                key_id,
+                issuer,
+            });
+        }
+
+        if let (Some(apple_id), Some(team_id), Some(password)) = (
+            lookup(ENV_APPLE_ID),
+            lookup(ENV_TEAM_ID),
+            lookup(ENV_PASSWORD),
+        ) {
+            return Ok(Self::AppleId {
+                apple_id,
+                team_id,
+                password,
+            });
+        }
+
+        Err(Error::Config(format!(
+            "No notarization credentials: set [sign.notarize] keychain_profile, \
+             or {ENV_API_KEY}/{ENV_API_KEY_ID}/{ENV_API_ISSUER}, \
+             or {ENV_APPLE_ID}/{ENV_TEAM_ID}/{ENV_PASSWORD}"
+        )))
+    }
+
+    /// notarytool authentication arguments
+    #[must_use]
+    pub fn args(&self) -> Vec<String> {
+        match self {
+            Self::KeychainProfile(profile) => {
+                vec!["--keychain-profile".to_string(), profile.clone()]
+            }
+            Self::AppleId {
+                apple_id,
+                team_id,
+                password,
+            } => vec![
+                "--apple-id".to_string(),
+                apple_id.clone(),
+                "--team-id".to_string(),
+                team_id.clone(),
+                "--password".to_string(),
+                password.clone(),
+            ],
+            Self::ApiKey {
+                key,
+                key_id,
+                issuer,
+            } => vec![
+                "--key".to_string(),
+                key.clone(),
+                "--key-id".to_string(),
+                key_id.clone(),
+                "--issuer".to_string(),
+                issuer.clone(),
+            ],
+        }
+    }
+}
+
+/// State of a notarization submission
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubmissionStatus {
+    /// Still being processed
+    InProgress,
+    /// Notarized; the ticket can be stapled
+    Accepted,
+    /// Rejected because of problems in the artifact (see the log)
+    Invalid,
+    /// Rejected by Apple
+    Rejected,
+    /// A status this version of xcargo does not know
+    Other(String),
+}
+
+impl SubmissionStatus {
+    /// Parse notarytool's status string
+    #[must_use]
+    pub fn parse(status: &str) -> Self {
+        match status {
+            "In Progress" => Self::InProgress,
+            "Accepted" => Self::Accepted,
+            "Invalid" => Self::Invalid,
+            "Rejected" => Self::Rejected,
+            other => Self::Other(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for SubmissionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InProgress => write!(f, "In Progress"),
+            Self::Accepted => write!(f, "Accepted"),
+            Self::Invalid => write!(f, "Invalid"),
+            Self::Rejected => write!(f, "Rejected"),
+            Self::Other(status) => write!(f, "{status}"),
+        }
+    }
+}
+
+/// Submits artifacts for notarization and staples the result
+pub struct Notarizer<'a> {
+    config: &'a NotarizeConfig,
+    credentials: Credentials,
+}
+
+impl<'a> Notarizer<'a> {
+    /// Create a notarizer with resolved credentials
+    #[must_use]
+    pub fn new(config: &'a NotarizeConfig, credentials: Credentials) -> Self {
+        Self {
+            config,
+            credentials,
+        }
+    }
+
+    /// Check that `xcrun notarytool` is available (macOS with Xcode tools)
+    pub fn check_available() -> Result<()> {
+        let available = Command::new("xcrun")
+            .args(["--find", "notarytool"])
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if available {
+            Ok(())
+        } else {
+            Err(Error::Toolchain(
+                "Notarization requires macOS with Xcode 13+ (xcrun notarytool not found)"
+                    .to_string(),
+            ))
+        }
+    }
+
+    /// Submit `path`, wait for Apple's verdict and staple the ticket
+    pub fn notarize(&self, path: &Path) -> Result<()> {
+        let upload = upload_path(path);
+        if upload != path {
+            run(Command::new("ditto")
+                .args(["-c", "-k", "--keepParent"])
+                .arg(path)
+                .arg(&upload))?;
+        }
+
+        let submitted = self.submit(&upload);
+        if upload != path {
+            let _ = std::fs::remove_file(&upload);
+        }
+        let id = submitted?;
+        helpers::info(format!("Submitted {} (id {id})", path.display()));
+
+        self.wait(&id, path)?;
+        helpers::success(format!("Notarized {}", path.display()));
+
+        if !self.config.staple {
+            return Ok(());
+        }
+        if is_stapleable(path) {
+            run(Command::new("xcrun").args(["stapler", "staple"]).arg(path))?;
+            helpers::success(format!("Stapled ticket to {}", path.display()));
+        } else {
+            helpers::hint(format!(
+                "{} cannot carry a ticket; Gatekeeper checks it online",
+                path.display()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Upload a file, returning the submission ID
+    fn submit(&self, upload: &Path) -> Result<String> {
+        let output = run(self.notarytool("submit").arg(upload).args([
+            "--no-wait",
+            "--output-format",
+            "json",
+        ]))?;
+        parse_field(&output, "id")
+    }
+
+    /// Poll until the submission is no longer in progress
+    fn wait(&self, id: &str, path: &Path) -> Result<()> {
+        let started = Instant::now();
+        let timeout = Duration::from_secs(self.config.timeout);
+        let interval = Duration::from_secs(self.config.poll_interval);
+
+        loop {
+            let output = run(self
+                .notarytool("info")
+                .arg(id)
+                .args(["--output-format", "json"]))?;
+            match SubmissionStatus::parse(&parse_field(&output, "status")?) {
+                SubmissionStatus::Accepted => return Ok(()),
+                SubmissionStatus::InProgress => {
+                    if started.elapsed() >= timeout {
+                        return Err(Error::Build(format!(
+                            "Notarization of {} still in progress after {}s \
+                             (check later with: xcrun notarytool info {id})",
+                            path.display(),
+                            self.config.timeout
+                        )));
+                    }
+                    helpers::progress(format!(
+                        "Waiting for Apple ({}s)...",
+                        started.elapsed().as_secs()
+                    ));
+                    std::thread::sleep(interval);
+                }
+                status => {
+                    let log = self
+                        .log(id)
+                        .unwrap_or_else(|e| format!("(could not fetch log: {e})"));
+                    return Err(Error::Build(format!(
+                        "Notarization of {} failed with status {status} (id {id}):\n{log}",
+                        path.display()
+                    )));
+                }
+            }
+        }
+    }
+
+    /// Fetch the notarization log for a submission
+    fn log(&self, id: &str) -> Result<String> {
+        let output = run(self.notarytool("log").arg(id))?;
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    fn notarytool(&self, subcommand: &str) -> Command {
+        let mut cmd = Command::new("xcrun");
+        cmd.args(["notarytool", subcommand])
+            .args(self.credentials.args());
+        cmd
+    }
+}
+
+/// Check if a ticket can be stapled to `path` (`.app`, `.pkg` or `.dmg`)
+#[must_use]
+pub fn is_stapleable(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("app" | "pkg" | "dmg")
+    )
+}
+
+/// File to upload for `path`: itself if Apple accepts it, else a `.zip` next to it
+#[must_use]
+pub fn upload_path(path: &Path) -> PathBuf {
+    if let Some("zip" | "pkg" | "dmg") = path.extension().and_then(|ext| ext.to_str()) {
+        return path.to_path_buf();
+    }
+
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".notarize.zip");
+    path.with_file_name(name)
+}
+
+/// Read a string field from notarytool's JSON output
+fn parse_field(output: &Output, field: &str) -> Result<String> {
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| Error::Build(format!("Unexpected notarytool output: {e}")))?;
+    json.get(field)
+        .and_then(serde_json::Value::as_str)
+        .map(String::from)
+        .ok_or_else(|| Error::Build(format!("notarytool output has no '{field}': {json}")))
+}
+
+/// Run a command, failing with its stderr on a non-zero exit
+fn run(cmd: &mut Command) -> Result<Output> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let output = cmd
+        .output()
+        .map_err(|e| Error::Toolchain(format!("Failed to run {program}: {e}")))?;
+    if output.status.success() {
+        Ok(output)
+    } else {
+        Err(Error::Build(format!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_credentials_precedence() {
+        let env: HashMap<&str, &str> = HashMap::from([
+            (ENV_APPLE_ID, "dev@example.com"),
+            (ENV_TEAM_ID, "ABCDE12345"),
+            (ENV_PASSWORD, "abcd-efgh-ijkl-mnop"),
+        ]);
+        let lookup = |key: &str| env.get(key).map(|v| (*v).to_string());
+
+        let mut config = NotarizeConfig::default();
+        let creds = Credentials::resolve(&config, lookup).unwrap();
+        assert!(matches!(creds, Credentials::AppleId { .. }));
+        assert_eq!(creds.args()[0], "--apple-id");
+
+        config.keychain_profile = Some("xcargo-notary".to_string());
+        let creds = Credentials::resolve(&config, lookup).unwrap();
+        assert_eq!(
+            creds.args(),
+            vec![
+                "--keychain-profile".to_string(),
+                "xcargo-notary".to_string()
+            ]
+        );
+
+        assert!(Credentials::resolve(&NotarizeConfig::default(), |_| None).is_err());
+    }
+
+    #[test]
+    fn test_submission_status() {
+        assert_eq!(
+            SubmissionStatus::parse("In Progress"),
+            SubmissionStatus::InProgress
+        );
+        assert_eq!(
+            SubmissionStatus::parse("Accepted"),
+            SubmissionStatus::Accepted
+        );
+        assert_eq!(SubmissionStatus::parse("Invalid").to_string(), "Invalid");
+    }
+
+    #[test]
+    fn test_upload_and_staple_paths() {
+        assert_eq!(
+            upload_path(Path::new("dist/app")),
+            PathBuf::from("dist/app.notarize.zip")
+        );
+        assert_eq!(
+            upload_path(Path::new("dist/App.dmg")),
+            PathBuf::from("dist/App.dmg")
+        );
+        assert!(is_stapleable(Path::new("dist/App.app")));
+        assert!(!is_stapleable(Path::new("dist/app")));
+    }
+}
@@ -0,0 +1,597 @@
+//! Binary size reports
+//!
+//! Measures built binaries, splits their size into code, data and
+//! symbols (debug info and symbol tables, which `strip` removes), and
+//! compares each one with the previous build of the same file. Sizes are
+//! remembered in `<target-dir>/xcargo/sizes.json`; a binary's entry only
+//! moves on when the file changes, so measuring twice without rebuilding
+//! keeps showing the change from the build before.
+//!
+//! [`by_crate`] goes one level deeper, in the style of cargo-bloat: it
+//! reads the binary's symbol table and attributes each function's code to
+//! the crate it was compiled from, which shows why the same program is
+//! larger on one target than on another.
+//!
+//! ```no_run
+//! use xcargo::size;
+//!
+//! # fn example() -> xcargo::Result<()> {
+//! let mut sizes = vec![size::measure(
+//!     "target/x86_64-unknown-linux-musl/release/app".as_ref(),
+//!     "x86_64-unknown-linux-musl",
+//!     "release",
+//! )?];
+//! size::record_deltas(&mut sizes)?;
+//! size::print_table(&sizes);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::build::clean;
+use crate::cache;
+use crate::error::Result;
+use crate::output::progress::format_bytes;
+use crate::output::{self, helpers};
+use crate::paths;
+use crate::state::{self, State};
+use crate::verify::{self, BinaryInfo, Section};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Size history location inside cargo's target directory
+const HISTORY_FILE: &str = "xcargo/sizes.json";
+
+/// Crates of the standard library, reported together as "std"
+const STD_CRATES: &[&str] = &[
+    "std",
+    "core",
+    "alloc",
+    "proc_macro",
+    "panic_unwind",
+    "panic_abort",
+    "compiler_builtins",
+    "std_detect",
+];
+
+/// Crate reported for C, assembly and other non-Rust functions
+pub const UNKNOWN_CRATE: &str = "[unknown]";
+
+/// What a section holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionKind {
+    /// Machine code
+    Code,
+    /// Constants, initialized data, unwind tables and other loaded data
+    Data,
+    /// Debug info and symbol tables
+    Symbols,
+}
+
+impl SectionKind {
+    /// Classify a section by name
+    #[must_use]
+    pub fn classify(name: &str) -> Self {
+        const CODE: &[&str] = &[
+            ".text",
+            ".init",
+            ".fini",
+            ".plt",
+            ".plt.got",
+            ".plt.sec",
+            ".iplt",
+            "__TEXT,__text",
+            "__TEXT,__stubs",
+            "__TEXT,__auth_stubs",
+            "__TEXT,__stub_helper",
+        ];
+        const SYMBOLS: &[&str] = &[".symtab", ".strtab", "__LINKEDIT"];
+
+        if CODE.contains(&name) || name.starts_with(".text.") {
+            Self::Code
+        } else if SYMBOLS.contains(&name)
+            || name.starts_with(".debug")
+            || name.starts_with(".zdebug")
+            || name.starts_with("__DWARF,")
+        {
+            Self::Symbols
+        } else {
+            Self::Data
+        }
+    }
+}
+
+/// Bytes of a binary by section kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Breakdown {
+    /// Machine code
+    pub code: u64,
+    /// Loaded data
+    pub data: u64,
+    /// Debug info and symbol tables
+    pub symbols: u64,
+    /// Headers, padding and anything outside a section
+    pub other: u64,
+}
+
+impl Breakdown {
+    /// Split `total` file bytes across `sections`
+    #[must_use]
+    pub fn new(total: u64, sections: &[Section]) -> Self {
+        let mut breakdown = Self::default();
+        for section in sections {
+            match SectionKind::classify(&section.name) {
+                SectionKind::Code => breakdown.code += section.size,
+                SectionKind::Data => breakdown.data += section.size,
+                SectionKind::Symbols => breakdown.symbols += section.size,
+            }
+        }
+        breakdown.other = total.saturating_sub(breakdown.code + breakdown.data + breakdown.symbols);
+        breakdown
+    }
+}
+
+/// Size of one built binary
+#[derive(Debug, Clone)]
+pub struct BinarySize {
+    /// Target triple
+    pub target: String,
+    /// Build profile ("debug" or "release")
+    pub profile: String,
+    /// Binary path
+    pub path: PathBuf,
+    /// File size in bytes
+    pub size: u64,
+    /// Size by section kind (`None` when the format is not ELF, PE or Mach-O)
+    pub breakdown: Option<Breakdown>,
+    /// Sections in the file
+    pub sections: Vec<Section>,
+    /// Change from the previous build, once recorded
+    pub delta: Option<i64>,
+}
+
+impl BinarySize {
+    /// File name of the binary
+    #[must_use]
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+    }
+
+    /// Key identifying the binary in the size history
+    fn key(&self) -> String {
+        format!("{}/{}/{}", self.target, self.profile, self.name())
+    }
+}
+
+/// Measure the binary at `path` built for `target` in `profile`
+pub fn measure(path: &Path, target: &str, profile: &str) -> Result<BinarySize> {
+    let data = fs::read(paths::long_path(path))?;
+    let size = u64::try_from(data.len()).unwrap_or(u64::MAX);
+    let sections = BinaryInfo::parse(&data).map(|info| info.sections).ok();
+
+    Ok(BinarySize {
+        target: target.to_string(),
+        profile: profile.to_string(),
+        path: path.to_path_buf(),
+        size,
+        breakdown: sections.as_deref().map(|s| Breakdown::new(size, s)),
+        sections: sections.unwrap_or_default(),
+        delta: None,
+    })
+}
+
+/// Code attributed to one crate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateSize {
+    /// Crate name ("std" for the standard library)
+    pub name: String,
+    /// Bytes of code in its functions
+    pub size: u64,
+    /// Number of functions
+    pub functions: usize,
+}
+
+/// Crate a function symbol belongs to
+///
+/// Trait impls count toward the implementing type's crate, unless that is
+/// the standard library (e.g., `<std::io::Error as app::Report>` is `app`).
+#[must_use]
+pub fn crate_of(symbol: &str) -> String {
+    let Ok(demangled) = rustc_demangle::try_demangle(symbol) else {
+        return UNKNOWN_CRATE.to_string();
+    };
+    let name = format!("{demangled:#}");
+
+    let krate = match qualified_self(&name) {
+        Some((self_type, Some(trait_path))) => match path_crate(self_type) {
+            Some(krate) if !STD_CRATES.contains(&krate) => Some(krate),
+            _ => path_crate(trait_path).or_else(|| path_crate(self_type)),
+        },
+        Some((self_type, None)) => path_crate(self_type),
+        None => path_crate(&name),
+    };
+    match krate {
+        Some(krate) if STD_CRATES.contains(&krate) => "std".to_string(),
+        Some(krate) => krate.to_string(),
+        None => UNKNOWN_CRATE.to_string(),
+    }
+}
+
+/// Split `<Type as Trait>::f` into the type and trait (or `<Type>::f` into
+/// the type alone)
+fn qualified_self(name: &str) -> Option<(&str, Option<&str>)> {
+    let inner = name.strip_prefix('<')?;
+    let mut depth = 0usize;
+    let mut split = None;
+    for (index, c) in inner.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            '>' if depth == 0 => {
+                let self_type = &inner[..split.unwrap_or(index)];
+                let trait_path = split.map(|at| &inner[at + 4..index]);
+                return Some((self_type, trait_path));
+            }
+            '>' => depth -= 1,
+            ' ' if depth == 0 && inner[index..].starts_with(" as ") => split = Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// First segment of a path, skipping references, pointers and `dyn`
+///
+/// Paths without a crate (primitives such as `u8`) return `None`.
+fn path_crate(path: &str) -> Option<&str> {
+    let mut path = path;
+    loop {
+        path = path.trim_start_matches(|c: char| !(c.is_alphanumeric() || c == '_'));
+        let Some(rest) = ["mut ", "const ", "dyn ", "impl "]
+            .iter()
+            .find_map(|keyword| path.strip_prefix(keyword))
+        else {
+            break;
+        };
+        path = rest;
+    }
+    let (krate, _) = path.split_once("::")?;
+    krate
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '_')
+        .then_some(krate)
+}
+
+/// Attribute the code of the binary at `path` to crates, largest first
+///
+/// Returns an empty list when the binary has no symbol table (e.g., it
+/// was stripped or built by MSVC).
+pub fn by_crate(path: &Path) -> Result<Vec<CrateSize>> {
+    let data = fs::read(paths::long_path(path))?;
+    let mut crates: BTreeMap<String, CrateSize> = BTreeMap::new();
+    for function in verify::functions(&data)? {
+        let name = crate_of(&function.name);
+        let entry = crates.entry(name.clone()).or_insert(CrateSize {
+            name,
+            size: 0,
+            functions: 0,
+        });
+        entry.size += function.size;
+        entry.functions += 1;
+    }
+
+    let mut crates: Vec<CrateSize> = crates.into_values().collect();
+    crates.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    Ok(crates)
+}
+
+/// A measured build of one binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Snapshot {
+    /// Hash of the file's size and modification time
+    hash: u64,
+    /// File size in bytes
+    size: u64,
+}
+
+/// The latest and previous build of one binary
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SizeRecord {
+    current: Snapshot,
+    #[serde(default)]
+    previous: Option<Snapshot>,
+}
+
+/// Sizes of earlier builds, keyed by `<triple>/<profile>/<file>`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeHistory {
+    #[serde(default)]
+    binaries: BTreeMap<String, SizeRecord>,
+}
+
+impl State for SizeHistory {
+    const SCHEMA: u32 = 1;
+    const DESCRIPTION: &'static str = "size history";
+}
+
+impl SizeHistory {
+    /// Load the history from `path`, or an empty one if there is none or it
+    /// is unreadable
+    #[must_use]
+    pub fn load(path: &Path) -> Self {
+        state::load(path)
+    }
+
+    /// Write the history to `path`
+    pub fn save(&self, path: &Path) -> Result<()> {
+        state::save(path, self)
+    }
+
+    /// Record a build of `key`, returning the change in bytes from the
+    /// build before it
+    ///
+    /// A `hash` equal to the latest recorded one is the same build measured
+    /// again and does not replace it.
+    pub fn record(&mut self, key: &str, hash: u64, size: u64) -> Option<i64> {
+        let snapshot = Snapshot { hash, size };
+        let record = self
+            .binaries
+            .entry(key.to_string())
+            .and_modify(|record| {
+                if record.current.hash != hash {
+                    record.previous = Some(record.current);
+                    record.current = snapshot;
+                }
+            })
+            .or_insert(SizeRecord {
+                current: snapshot,
+                previous: None,
+            });
+
+        let previous = record.previous?;
+        Some(i64::try_from(record.current.size).ok()? - i64::try_from(previous.size).ok()?)
+    }
+}
+
+/// Path of the size history for the current project
+pub fn history_path() -> Result<PathBuf> {
+    Ok(clean::target_directory()?.join(HISTORY_FILE))
+}
+
+/// Fill in each binary's change from its previous build and remember the
+/// new sizes
+pub fn record_deltas(sizes: &mut [BinarySize]) -> Result<()> {
+    let path = history_path()?;
+    let mut history = SizeHistory::load(&path);
+    for size in sizes.iter_mut() {
+        let hash = cache::hash_file(&size.path).unwrap_or_default();
+        size.delta = history.record(&size.key(), hash, size.size);
+    }
+    history.save(&path)
+}
+
+/// Signed, human-readable size change ("+12.0 KB", "-512 B", "0 B")
+#[must_use]
+pub fn format_delta(delta: i64) -> String {
+    let bytes = format_bytes(delta.unsigned_abs());
+    match delta.signum() {
+        1 => format!("+{bytes}"),
+        -1 => format!("-{bytes}"),
+        _ => bytes,
+    }
+}
+
+/// Print a comparison table of binary sizes
+pub fn print_table(sizes: &[BinarySize]) {
+    let rows: Vec<[String; 7]> = sizes.iter().map(row).collect();
+
+    if output::is_plain() {
+        for (size, row) in sizes.iter().zip(&rows) {
+            println!(
+                "{} {}: size {}, code {}, data {}, symbols {}, change {}",
+                size.target, row[1], row[2], row[3], row[4], row[5], row[6]
+            );
+        }
+        return;
+    }
+
+    let header = [
+        "Target", "Binary", "Size", "Code", "Data", "Symbols", "Change",
+    ]
+    .map(String::from);
+    let mut widths = header.clone().map(|cell| cell.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    // Target and binary names align left, sizes right
+    let line = |cells: &[String; 7]| {
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(column, (cell, width))| {
+                if column < 2 {
+                    format!("{cell:<width$}")
+                } else {
+                    format!("{cell:>width$}")
+                }
+            })
+            .collect();
+        format!("  {}", cells.join("  "))
+    };
+    println!("{}", line(&header));
+    for row in &rows {
+        println!("{}", line(row));
+    }
+}
+
+/// Print every section of a binary, largest first
+pub fn print_sections(size: &BinarySize) {
+    let mut sections: Vec<&Section> = size.sections.iter().filter(|s| s.size > 0).collect();
+    sections.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+
+    helpers::info(format!("{} ({})", size.name(), size.target));
+    let width = sections.iter().map(|s| s.name.len()).max().unwrap_or(0);
+    for section in sections {
+        println!(
+            "    {:<width$}  {:>10}",
+            section.name,
+            format_bytes(section.size)
+        );
+    }
+}
+
+/// Print the crates contributing most code to a binary
+///
+/// Shares are of the binary's code sections; `limit` caps the crates shown.
+pub fn print_crates(size: &BinarySize, crates: &[CrateSize], limit: usize) {
+    helpers::info(format!("{} ({})", size.name(), size.target));
+    let total: u64 = crates.iter().map(|c| c.size).sum();
+    let code = size.breakdown.map_or(total, |b| b.code).max(total);
+
+    for krate in crates.iter().take(limit) {
+        #[allow(clippy::cast_precision_loss)]
+        let share = krate.size as f64 * 100.0 / code.max(1) as f64;
+        println!(
+            "    {share:>5.1}%  {:>10}  {} ({} functions)",
+            format_bytes(krate.size),
+            krate.name,
+            krate.functions
+        );
+    }
+    if crates.len() > limit {
+        let rest: u64 = crates[limit..].iter().map(|c| c.size).sum();
+        println!(
+            "    {:>6}  {:>10}  {} more crates",
+            "",
+            format_bytes(rest),
+            crates.len() - limit
+        );
+    }
+}
+
+fn row(size: &BinarySize) -> [String; 7] {
+    let part = |bytes: fn(&Breakdown) -> u64| {
+        size.breakdown
+            .as_ref()
+            .map_or_else(|| "-".to_string(), |b| format_bytes(bytes(b)))
+    };
+    [
+        size.target.clone(),
+        size.name(),
+        format_bytes(size.size),
+        part(|b| b.code),
+        part(|b| b.data),
+        part(|b| b.symbols),
+        size.delta.map_or_else(|| "-".to_string(), format_delta),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(SectionKind::classify(".text"), SectionKind::Code);
+        assert_eq!(SectionKind::classify("__TEXT,__text"), SectionKind::Code);
+        assert_eq!(SectionKind::classify(".rodata"), SectionKind::Data);
+        assert_eq!(SectionKind::classify("__TEXT,__cstring"), SectionKind::Data);
+        assert_eq!(SectionKind::classify(".debug_info"), SectionKind::Symbols);
+        assert_eq!(
+            SectionKind::classify("__DWARF,__debug_line"),
+            SectionKind::Symbols
+        );
+        assert_eq!(SectionKind::classify(".symtab"), SectionKind::Symbols);
+    }
+
+    #[test]
+    fn test_breakdown() {
+        let sections = vec![
+            Section {
+                name: ".text".to_string(),
+                size: 600,
+            },
+            Section {
+                name: ".rodata".to_string(),
+                size: 200,
+            },
+            Section {
+                name: ".debug_info".to_string(),
+                size: 150,
+            },
+        ];
+        let breakdown = Breakdown::new(1000, &sections);
+        assert_eq!(
+            breakdown,
+            Breakdown {
+                code: 600,
+                data: 200,
+                symbols: 150,
+                other: 50,
+            }
+        );
+    }
+
+    #[test]
+    fn test_history_deltas() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("xcargo/sizes.json");
+        let mut history = SizeHistory::load(&path);
+        let key = "x86_64-unknown-linux-gnu/release/app";
+
+        assert_eq!(history.record(key, 1, 1000), None);
+        // Measuring the same build again keeps the first size
+        assert_eq!(history.record(key, 1, 1000), None);
+        assert_eq!(history.record(key, 2, 1200), Some(200));
+        assert_eq!(history.record(key, 2, 1200), Some(200));
+
+        history.save(&path).unwrap();
+        let mut history = SizeHistory::load(&path);
+        assert_eq!(history.record(key, 3, 900), Some(-300));
+    }
+
+    #[test]
+    fn test_crate_of() {
+        // Legacy mangling
+        assert_eq!(
+            crate_of("_ZN5serde2de7Visitor10visit_bool17h0123456789abcdefE"),
+            "serde"
+        );
+        assert_eq!(
+            crate_of("_ZN3std2io5stdio6_print17h0123456789abcdefE"),
+            "std"
+        );
+        // v0 mangling of `app::main`
+        assert_eq!(crate_of("_RNvCs1234_3app4main"), "app");
+        assert_eq!(crate_of("memcpy"), UNKNOWN_CRATE);
+    }
+
+    #[test]
+    fn test_trait_impls() {
+        assert_eq!(
+            qualified_self("<a::B as c::D>::f"),
+            Some(("a::B", Some("c::D")))
+        );
+        assert_eq!(qualified_self("<a::B<T>>::new"), Some(("a::B<T>", None)));
+        assert_eq!(qualified_self("a::b"), None);
+
+        assert_eq!(path_crate("&mut dyn toml::de::Error"), Some("toml"));
+        assert_eq!(path_crate("[u8]"), None);
+        assert_eq!(path_crate("u8"), None);
+    }
+
+    #[test]
+    fn test_format_delta() {
+        assert_eq!(format_delta(2048), "+2.0 KB");
+        assert_eq!(format_delta(-512), "-512 B");
+        assert_eq!(format_delta(0), "0 B");
+    }
+}
@@ -0,0 +1,287 @@
+//! Crash-safe, versioned state files
+//!
+//! xcargo keeps state between runs: the build cache, binary size history
+//! and the record of what it installed. These files are written with
+//! [`write_atomic`], so a crash or Ctrl-C mid-write leaves either the old or
+//! the new contents and never half a file.
+//!
+//! State types implement [`State`] and are stored as
+//! `{"schema": N, "data": ...}`. [`load`] never fails a build over a state
+//! file: one that is unreadable, corrupted or written by a newer xcargo is
+//! moved aside to `<file>.bak` with a warning and the state starts fresh.
+//! Files written before schemas were introduced are read as schema 0.
+//!
+//! ```no_run
+//! use serde::{Deserialize, Serialize};
+//! use std::path::Path;
+//! use xcargo::state::{self, State};
+//!
+//! #[derive(Default, Serialize, Deserialize)]
+//! struct Counter {
+//!     runs: u64,
+//! }
+//!
+//! impl State for Counter {
+//!     const SCHEMA: u32 = 1;
+//!     const DESCRIPTION: &'static str = "run counter";
+//! }
+//!
+//! # fn example() -> xcargo::Result<()> {
+//! let path = Path::new("target/xcargo/runs.json");
+//! let mut counter: Counter = state::load(path);
+//! counter.runs += 1;
+//! state::save(path, &counter)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::output::helpers;
+use crate::paths;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Distinguishes temporary files written by one process
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Data persisted between xcargo runs
+pub trait State: Serialize + DeserializeOwned + Default {
+    /// Schema version written with the data; bump on incompatible changes
+    const SCHEMA: u32;
+
+    /// What the file holds, for messages (e.g., "build cache")
+    const DESCRIPTION: &'static str;
+
+    /// Convert data written with an older `schema` (0 for files from before
+    /// schemas were introduced)
+    ///
+    /// Returns `None` when the data cannot be carried over, which starts the
+    /// state fresh. By default the old data is read as the current type.
+    #[must_use]
+    fn migrate(schema: u32, data: Value) -> Option<Self> {
+        let _ = schema;
+        serde_json::from_value(data).ok()
+    }
+}
+
+/// On-disk form of a [`State`]
+#[derive(Serialize, Deserialize)]
+struct Stored<T> {
+    schema: u32,
+    data: T,
+}
+
+/// Why a state file was not used
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Unusable {
+    /// Could not be read or parsed
+    Corrupted(String),
+    /// Written by a newer xcargo
+    Newer(u32),
+}
+
+/// Load `T` from `path`
+///
+/// A missing file gives the default state. A file that cannot be used is
+/// moved aside to `<file>.bak`, with a warning, and the default is returned.
+#[must_use]
+pub fn load<T: State>(path: &Path) -> T {
+    let path = paths::long_path(path);
+    if !path.exists() {
+        return T::default();
+    }
+
+    match read::<T>(&path) {
+        Ok(state) => state,
+        Err(Unusable::Corrupted(e)) => {
+            discard(&path, T::DESCRIPTION, &format!("is unreadable ({e})"));
+            T::default()
+        }
+        Err(Unusable::Newer(schema)) => {
+            discard(
+                &path,
+                T::DESCRIPTION,
+                &format!("was written by a newer xcargo (schema {schema})"),
+            );
+            T::default()
+        }
+    }
+}
+
+/// Set aside a state file that cannot be used, moving it to `<file>.bak`
+/// and warning that `what` at `path` `reason`
+///
+/// For files with their own format that are read without [`load`].
+pub fn discard(path: &Path, what: &str, reason: &str) {
+    helpers::warning(format!(
+        "The {what} at {} {reason}; starting fresh",
+        path.display()
+    ));
+    let backup = sibling(path, "bak");
+    if fs::rename(path, &backup).is_ok() {
+        helpers::hint(format!("The old file was kept as {}", backup.display()));
+    }
+}
+
+/// Save `state` to `path` atomically, with its schema version
+pub fn save<T: State>(path: &Path, state: &T) -> Result<()> {
+    let stored = Stored {
+        schema: T::SCHEMA,
+        data: state,
+    };
+    let json = serde_json::to_string_pretty(&stored)
+        .map_err(|e| Error::Config(format!("Failed to serialize {}: {e}", T::DESCRIPTION)))?;
+    write_atomic(path, json)
+}
+
+/// Replace the contents of `path` so that readers, and the file after a
+/// crash, see either the old or the new contents
+///
+/// The data is written and flushed to a temporary file next to `path`,
+/// which is then renamed over it. Parent directories are created.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let path = paths::long_path(path);
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+
+    let temp = sibling(
+        &path,
+        &format!(
+            "tmp-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ),
+    );
+    let written = File::create(&temp).and_then(|mut file| {
+        file.write_all(contents.as_ref())?;
+        file.sync_all()
+    });
+    let result = written.and_then(|()| fs::rename(&temp, &path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    Ok(result?)
+}
+
+fn read<T: State>(path: &Path) -> std::result::Result<T, Unusable> {
+    let contents = fs::read_to_string(path).map_err(|e| Unusable::Corrupted(e.to_string()))?;
+    let value: Value =
+        serde_json::from_str(&contents).map_err(|e| Unusable::Corrupted(e.to_string()))?;
+
+    let (schema, data) = match value {
+        Value::Object(mut map)
+            if map.len() == 2 && map.contains_key("schema") && map.contains_key("data") =>
+        {
+            let schema = map
+                .get("schema")
+                .and_then(Value::as_u64)
+                .and_then(|s| u32::try_from(s).ok())
+                .ok_or_else(|| Unusable::Corrupted("invalid schema".to_string()))?;
+            (schema, map.remove("data").unwrap_or_default())
+        }
+        // Written before schemas were introduced
+        legacy => (0, legacy),
+    };
+
+    if schema > T::SCHEMA {
+        return Err(Unusable::Newer(schema));
+    }
+    if schema == T::SCHEMA {
+        return serde_json::from_value(data).map_err(|e| Unusable::Corrupted(e.to_string()));
+    }
+    T::migrate(schema, data)
+        .ok_or_else(|| Unusable::Corrupted(format!("schema {schema} could not be migrated")))
+}
+
+/// `<file>.<suffix>` next to `path`
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Sample {
+        values: BTreeMap<String, u64>,
+    }
+
+    impl State for Sample {
+        const SCHEMA: u32 = 2;
+        const DESCRIPTION: &'static str = "sample state";
+    }
+
+    fn sample() -> Sample {
+        Sample {
+            values: BTreeMap::from([("a".to_string(), 1)]),
+        }
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("nested/state.json");
+        assert_eq!(load::<Sample>(&path), Sample::default());
+
+        save(&path, &sample()).unwrap();
+        assert_eq!(load::<Sample>(&path), sample());
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("\"schema\": 2"));
+        // No temporary files are left behind
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_corrupted_file_is_set_aside() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("state.json");
+        fs::write(&path, "{\"schema\": 2, \"data\": {\"values\": {\"a\"").unwrap();
+
+        assert_eq!(load::<Sample>(&path), Sample::default());
+        assert!(!path.exists());
+        assert!(temp.path().join("state.json.bak").is_file());
+    }
+
+    #[test]
+    fn test_newer_schema_is_set_aside() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("state.json");
+        fs::write(&path, r#"{"schema": 3, "data": {"entries": []}}"#).unwrap();
+
+        assert_eq!(load::<Sample>(&path), Sample::default());
+        assert!(temp.path().join("state.json.bak").is_file());
+    }
+
+    #[test]
+    fn test_legacy_file_is_migrated() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("state.json");
+        fs::write(&path, r#"{"values": {"a": 1}}"#).unwrap();
+        assert_eq!(load::<Sample>(&path), sample());
+
+        fs::write(&path, r#"{"values": "not a map"}"#).unwrap();
+        assert_eq!(load::<Sample>(&path), Sample::default());
+    }
+
+    #[test]
+    fn test_write_atomic_replaces() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("file.txt");
+        write_atomic(&path, "old").unwrap();
+        write_atomic(&path, "new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+    }
+}
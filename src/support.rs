@@ -0,0 +1,309 @@
+//! Support bundles for bug reports
+//!
+//! `xcargo report` gathers what is needed to look into a problem into one
+//! `.tar.gz`: the doctor report, the project's configuration with secrets
+//! redacted, tool versions, the recent per-target build logs and the last
+//! cargo invocation that failed. Builds record that invocation in
+//! `target/xcargo/last-failure.json` of the nearest package (or under
+//! `CARGO_TARGET_DIR`), with the environment it ran in.
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use xcargo::support;
+//!
+//! # fn example() -> xcargo::Result<()> {
+//! let bundle = support::create(Path::new("xcargo-report.tar.gz"))?;
+//! for file in &bundle.files {
+//!     println!("{file}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::config::{ConfigDiscovery, ConfigFormat};
+use crate::doctor::{self, ReportFormat};
+use crate::error::{Error, Result};
+use crate::logs::{self, LogRing};
+use crate::scratch::ScratchDir;
+use crate::state::{self, State};
+use crate::timings::now;
+use crate::trace::{self, TracedCommand};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Last failed invocation, inside cargo's target directory
+const LAST_FAILURE_FILE: &str = "xcargo/last-failure.json";
+
+/// Per-target logs included, most recently written first
+const MAX_LOGS: usize = 5;
+
+/// Lines included from the end of each log
+const LOG_LINES: usize = 500;
+
+/// A cargo invocation that failed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LastFailure {
+    /// Target triple being built
+    pub target: String,
+    /// Unix timestamp of the failure
+    pub timestamp: u64,
+    /// The command and its exit code
+    pub command: TracedCommand,
+    /// Every variable the command ran with, secrets redacted
+    pub env: BTreeMap<String, String>,
+}
+
+impl State for LastFailure {
+    const SCHEMA: u32 = 1;
+    const DESCRIPTION: &'static str = "last failed build";
+}
+
+/// A support bundle written by [`create`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bundle {
+    /// The `.tar.gz` file
+    pub path: PathBuf,
+    /// Files in the bundle
+    pub files: Vec<String>,
+    /// What was left out, and why
+    pub skipped: Vec<String>,
+}
+
+/// Path of the last failed invocation for the current project
+///
+/// Found without asking cargo, since it is written when a build fails.
+pub fn last_failure_path() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("CARGO_TARGET_DIR") {
+        return Ok(PathBuf::from(dir).join(LAST_FAILURE_FILE));
+    }
+    let cwd = std::env::current_dir()?;
+    cwd.ancestors()
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .map(|dir| dir.join("target").join(LAST_FAILURE_FILE))
+        .ok_or_else(|| Error::Config("No Cargo.toml found".to_string()))
+}
+
+/// Record that `cmd`, building `target`, exited with `exit_code`
+///
+/// Best effort: a failed build is never made worse by a failed record.
+pub fn record_failure(target: &str, cmd: &Command, exit_code: Option<i32>) {
+    let command = TracedCommand::from_command(cmd, exit_code);
+    let failure = LastFailure {
+        target: target.to_string(),
+        timestamp: now(),
+        env: command.full_env(),
+        command,
+    };
+    if let Err(e) = last_failure_path().and_then(|path| state::save(&path, &failure)) {
+        tracing::debug!(error = %e, "could not record the failed invocation");
+    }
+}
+
+/// The current project's last failed invocation, if one was recorded
+#[must_use]
+pub fn last_failure() -> Option<LastFailure> {
+    let failure: LastFailure = state::load(&last_failure_path().ok()?);
+    (!failure.command.program.is_empty()).then_some(failure)
+}
+
+/// Gather a support bundle into the `.tar.gz` file `output`
+///
+/// Parts that are unavailable (no configuration, no logs, no failed build)
+/// are listed in [`Bundle::skipped`] instead of failing the bundle.
+pub fn create(output: &Path) -> Result<Bundle> {
+    let name = format!("xcargo-report-{}", now());
+    let scratch = ScratchDir::for_build("report")?;
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    let mut add = |file: String, contents: &str| -> Result<()> {
+        scratch.write(Path::new(&name).join(&file), contents)?;
+        files.push(file);
+        Ok(())
+    };
+
+    add(
+        "doctor.json".to_string(),
+        &doctor::collect().to_format(ReportFormat::Json)?,
+    )?;
+    add("versions.json".to_string(), &to_json(&versions())?)?;
+
+    match ConfigDiscovery::find()? {
+        Some(path) => match redacted_config(&path) {
+            Ok(config) => add(file_name(&path), &config)?,
+            Err(e) => skipped.push(format!("{}: {e}", file_name(&path))),
+        },
+        None => skipped.push("xcargo.toml: no configuration file found".to_string()),
+    }
+
+    let logs = logs::list().unwrap_or_default();
+    if logs.is_empty() {
+        skipped.push("logs: no per-target build logs".to_string());
+    }
+    for (target, path) in logs.into_iter().take(MAX_LOGS) {
+        let mut lines = LogRing::open(&path)?.tail(LOG_LINES)?.join("\n");
+        lines.push('\n');
+        add(format!("logs/{target}.log"), &lines)?;
+    }
+
+    match last_failure() {
+        Some(failure) => add("last-failure.json".to_string(), &to_json(&failure)?)?,
+        None => skipped.push("last-failure.json: no failed build recorded".to_string()),
+    }
+
+    // tar runs in the scratch directory, so the output path must not be relative
+    let path = std::env::current_dir()?.join(output);
+    let mut tar = Command::new("tar");
+    tar.arg("-czf")
+        .arg(&path)
+        .arg("-C")
+        .arg(scratch.path())
+        .arg(&name);
+    let status = tar
+        .status()
+        .map_err(|e| Error::Toolchain(format!("Failed to run tar: {e}")))?;
+    trace::record_command(&tar, status.code());
+    if !status.success() {
+        return Err(Error::Toolchain(format!(
+            "Failed to create {}",
+            path.display()
+        )));
+    }
+
+    Ok(Bundle {
+        path,
+        files,
+        skipped,
+    })
+}
+
+/// The configuration file at `path`, in its own format, with the values of
+/// secret-looking keys replaced by [`trace::REDACTED`]
+///
+/// Comments are not kept, since the file is parsed to find the keys.
+pub fn redacted_config(path: &Path) -> Result<String> {
+    let text = fs::read_to_string(path)?;
+    let invalid = |e: String| Error::Config(format!("Cannot read {}: {e}", path.display()));
+    let format = ConfigFormat::from_path(path);
+
+    let mut config: Value = match format {
+        ConfigFormat::Toml => toml::from_str(&text).map_err(|e| invalid(e.to_string()))?,
+        ConfigFormat::Yaml => serde_yaml::from_str(&text).map_err(|e| invalid(e.to_string()))?,
+        ConfigFormat::Json => serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))?,
+    };
+    redact(&mut config);
+
+    match format {
+        ConfigFormat::Toml => toml::to_string_pretty(&config).map_err(|e| invalid(e.to_string())),
+        ConfigFormat::Yaml => serde_yaml::to_string(&config).map_err(|e| invalid(e.to_string())),
+        ConfigFormat::Json => to_json(&config),
+    }
+}
+
+/// Replace the values of secret-looking keys in `value`, at any depth
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if trace::is_secret(key) && !value.is_object() {
+                    *value = Value::String(trace::REDACTED.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// xcargo's version, the host and the versions of the build tools
+fn versions() -> Value {
+    serde_json::json!({
+        "xcargo": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "tools": trace::tool_versions(),
+    })
+}
+
+fn to_json(value: &impl Serialize) -> Result<String> {
+    serde_json::to_string_pretty(value)
+        .map_err(|e| Error::Config(format!("Cannot serialize report: {e}")))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map_or_else(|| "xcargo.toml".into(), |name| name.to_string_lossy())
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_redacted_config() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("xcargo.toml");
+        fs::write(
+            &path,
+            r#"
+[targets]
+default = ["aarch64-unknown-linux-gnu"]
+
+[targets."aarch64-unknown-linux-gnu"]
+linker = "aarch64-linux-gnu-gcc"
+env = { REGISTRY_TOKEN = "cio_secret", CC = "clang" }
+
+[notarize]
+password = "hunter2"
+"#,
+        )
+        .unwrap();
+
+        let redacted = redacted_config(&path).unwrap();
+        assert!(!redacted.contains("cio_secret"));
+        assert!(!redacted.contains("hunter2"));
+        assert!(redacted.contains(trace::REDACTED));
+        assert!(redacted.contains("aarch64-linux-gnu-gcc"));
+        assert!(redacted.contains("clang"));
+        let parsed: toml::Value = toml::from_str(&redacted).unwrap();
+        assert_eq!(
+            parsed["notarize"]["password"].as_str(),
+            Some(trace::REDACTED)
+        );
+
+        let yaml = dir.path().join("xcargo.yaml");
+        fs::write(&yaml, "build:\n  api_key: abc\n  parallel: true\n").unwrap();
+        let redacted = redacted_config(&yaml).unwrap();
+        assert!(!redacted.contains("abc"));
+        assert!(redacted.contains("parallel: true"));
+    }
+
+    #[test]
+    fn test_last_failure_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(LAST_FAILURE_FILE);
+        let mut cmd = Command::new("cargo");
+        cmd.args(["build", "--target", "aarch64-unknown-linux-gnu"])
+            .env("CARGO_REGISTRY_TOKEN", "cio_secret");
+        let command = TracedCommand::from_command(&cmd, Some(101));
+        let failure = LastFailure {
+            target: "aarch64-unknown-linux-gnu".to_string(),
+            timestamp: 1,
+            env: command.full_env(),
+            command,
+        };
+        state::save(&path, &failure).unwrap();
+
+        let loaded: LastFailure = state::load(&path);
+        assert_eq!(loaded, failure);
+        assert_eq!(loaded.command.exit_code, Some(101));
+        assert_eq!(loaded.env["CARGO_REGISTRY_TOKEN"], trace::REDACTED);
+    }
+}
@@ -0,0 +1,192 @@
+//! Rust's own support tiers
+//!
+//! Separate from xcargo's [`TargetTier`](super::TargetTier), which says how
+//! xcargo builds a target: the Rust project's tier says whether rustup
+//! ships a standard library for it and whether the compiler is tested on
+//! it. Targets rustc knows but the table doesn't list are tier 3.
+
+use std::fmt;
+
+/// Targets of tiers 1 and 2, generated from
+/// <https://doc.rust-lang.org/nightly/rustc/platform-support.html>:
+/// `1`, `2-host` (tier 2 with host tools), `2`, or `2-core` (tier 2
+/// without a standard library)
+const TIERS: &str = "\
+aarch64-apple-darwin              1
+aarch64-pc-windows-msvc           1
+aarch64-unknown-linux-gnu         1
+i686-pc-windows-msvc              1
+i686-unknown-linux-gnu            1
+x86_64-pc-windows-gnu             1
+x86_64-pc-windows-msvc            1
+x86_64-unknown-linux-gnu          1
+aarch64-pc-windows-gnullvm        2-host
+aarch64-unknown-linux-musl        2-host
+arm-unknown-linux-gnueabi         2-host
+arm-unknown-linux-gnueabihf       2-host
+armv7-unknown-linux-gnueabihf     2-host
+i686-pc-windows-gnu               2-host
+loongarch64-unknown-linux-gnu     2-host
+loongarch64-unknown-linux-musl    2-host
+powerpc-unknown-linux-gnu         2-host
+powerpc64-unknown-linux-gnu       2-host
+powerpc64le-unknown-linux-gnu     2-host
+powerpc64le-unknown-linux-musl    2-host
+riscv64gc-unknown-linux-gnu       2-host
+s390x-unknown-linux-gnu           2-host
+x86_64-apple-darwin               2-host
+x86_64-pc-windows-gnullvm         2-host
+x86_64-unknown-freebsd            2-host
+x86_64-unknown-illumos            2-host
+x86_64-unknown-linux-musl         2-host
+x86_64-unknown-netbsd             2-host
+aarch64-apple-ios                 2
+aarch64-apple-ios-macabi          2
+aarch64-apple-ios-sim             2
+aarch64-linux-android             2
+aarch64-unknown-fuchsia           2
+arm-linux-androideabi             2
+arm-unknown-linux-musleabi        2
+arm-unknown-linux-musleabihf      2
+armv5te-unknown-linux-gnueabi     2
+armv7-linux-androideabi           2
+armv7-unknown-linux-gnueabi       2
+armv7-unknown-linux-musleabi      2
+armv7-unknown-linux-musleabihf    2
+i586-unknown-linux-gnu            2
+i686-linux-android                2
+i686-unknown-freebsd              2
+i686-unknown-linux-musl           2
+riscv64gc-unknown-linux-musl      2
+sparc64-unknown-linux-gnu         2
+sparcv9-sun-solaris               2
+thumbv7neon-linux-androideabi     2
+wasm32-unknown-emscripten         2
+wasm32-unknown-unknown            2
+wasm32-wasip1                     2
+wasm32-wasip1-threads             2
+wasm32-wasip2                     2
+x86_64-apple-ios                  2
+x86_64-apple-ios-macabi           2
+x86_64-fortanix-unknown-sgx       2
+x86_64-linux-android              2
+x86_64-pc-solaris                 2
+x86_64-unknown-fuchsia            2
+x86_64-unknown-linux-gnux32       2
+x86_64-unknown-redox              2
+aarch64-unknown-none              2-core
+aarch64-unknown-none-softfloat    2-core
+armebv7r-none-eabi                2-core
+armebv7r-none-eabihf              2-core
+armv7a-none-eabi                  2-core
+armv7r-none-eabi                  2-core
+armv7r-none-eabihf                2-core
+loongarch64-unknown-none          2-core
+nvptx64-nvidia-cuda               2-core
+riscv32i-unknown-none-elf         2-core
+riscv32im-unknown-none-elf        2-core
+riscv32imac-unknown-none-elf      2-core
+riscv32imafc-unknown-none-elf     2-core
+riscv32imc-unknown-none-elf       2-core
+riscv64gc-unknown-none-elf        2-core
+riscv64imac-unknown-none-elf      2-core
+thumbv6m-none-eabi                2-core
+thumbv7em-none-eabi               2-core
+thumbv7em-none-eabihf             2-core
+thumbv7m-none-eabi                2-core
+thumbv8m.base-none-eabi           2-core
+thumbv8m.main-none-eabi           2-core
+thumbv8m.main-none-eabihf         2-core
+wasm32v1-none                     2-core
+x86_64-unknown-none               2-core
+";
+
+/// A target's tier on Rust's platform support page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RustTier {
+    /// Guaranteed to work: built and tested on every change
+    Tier1,
+    /// Guaranteed to build, compiler and cargo included
+    Tier2WithHostTools,
+    /// Guaranteed to build
+    Tier2,
+    /// Supported in the code base, without official builds
+    Tier3,
+}
+
+impl RustTier {
+    /// The tier of `triple` in the table: tier 1 or 2, `None` otherwise
+    #[must_use]
+    pub fn of(triple: &str) -> Option<Self> {
+        match Self::entry(triple)? {
+            "1" => Some(Self::Tier1),
+            "2-host" => Some(Self::Tier2WithHostTools),
+            _ => Some(Self::Tier2),
+        }
+    }
+
+    /// Whether rustup ships a standard library for `triple`; `false` for
+    /// targets with only `core` and `alloc`, and for tier 3, where std has
+    /// to be built with `-Zbuild-std` if it exists at all
+    #[must_use]
+    pub fn ships_std(triple: &str) -> bool {
+        Self::entry(triple).is_some_and(|tier| tier != "2-core")
+    }
+
+    fn entry(triple: &str) -> Option<&'static str> {
+        TIERS.lines().find_map(|line| {
+            let mut fields = line.split_whitespace();
+            (fields.next() == Some(triple))
+                .then(|| fields.next())
+                .flatten()
+        })
+    }
+
+    /// What the Rust project guarantees at this tier
+    #[must_use]
+    pub fn guarantees(self) -> &'static str {
+        match self {
+            Self::Tier1 => "builds and passes the test suite on every change",
+            Self::Tier2WithHostTools => {
+                "builds on every change, and rustc and cargo run on it; tests are not run"
+            }
+            Self::Tier2 => "builds on every change; tests are not run",
+            Self::Tier3 => "no official builds or tests; it may not build",
+        }
+    }
+}
+
+impl fmt::Display for RustTier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tier1 => write!(f, "Tier 1"),
+            Self::Tier2WithHostTools => write!(f, "Tier 2 with host tools"),
+            Self::Tier2 => write!(f, "Tier 2"),
+            Self::Tier3 => write!(f, "Tier 3"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rust_tier() {
+        assert_eq!(
+            RustTier::of("x86_64-unknown-linux-gnu"),
+            Some(RustTier::Tier1)
+        );
+        assert_eq!(
+            RustTier::of("armv7-unknown-linux-gnueabihf"),
+            Some(RustTier::Tier2WithHostTools)
+        );
+        assert_eq!(RustTier::of("aarch64-linux-android"), Some(RustTier::Tier2));
+        assert_eq!(RustTier::of("thumbv6m-none-eabi"), Some(RustTier::Tier2));
+        assert_eq!(RustTier::of("aarch64-unknown-linux-gnu_ilp32"), None);
+
+        assert!(RustTier::ships_std("wasm32-wasip1"));
+        assert!(!RustTier::ships_std("thumbv6m-none-eabi"));
+        assert!(!RustTier::ships_std("x86_64-unknown-haiku"));
+    }
+}
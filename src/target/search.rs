@@ -0,0 +1,179 @@
+//! Searching the targets rustup knows
+//!
+//! Backs `xcargo target search`: a substring or regular expression over
+//! the triple, narrowed by OS, architecture and environment. Filters match
+//! either the triple's spelling or rustc's (`--os macos` finds `*-darwin`,
+//! `--arch arm` finds `thumbv7em-none-eabihf`, `--env musl` finds
+//! `*-musleabihf`).
+
+use super::Target;
+use regex::Regex;
+
+/// What a search matches triples against
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// Part of the triple, ignoring case
+    Substring(String),
+    /// A regular expression over the triple
+    Regex(Regex),
+}
+
+impl Pattern {
+    /// A pattern from the command line, a regular expression if `regex`
+    ///
+    /// # Errors
+    ///
+    /// Returns the parser's message if `regex` is set and `pattern` is not
+    /// a valid regular expression.
+    pub fn new(pattern: &str, regex: bool) -> std::result::Result<Self, String> {
+        if regex {
+            Regex::new(pattern)
+                .map(Self::Regex)
+                .map_err(|e| e.to_string())
+        } else {
+            Ok(Self::Substring(pattern.to_lowercase()))
+        }
+    }
+
+    fn matches(&self, triple: &str) -> bool {
+        match self {
+            Self::Substring(part) => triple.to_lowercase().contains(part),
+            Self::Regex(regex) => regex.is_match(triple),
+        }
+    }
+}
+
+/// A target search: the pattern and filters, all of which must match
+#[derive(Debug, Clone, Default)]
+pub struct TargetQuery {
+    /// Pattern over the triple; every triple matches without one
+    pub pattern: Option<Pattern>,
+    /// Operating system, e.g. "linux", "macos", "none"
+    pub os: Option<String>,
+    /// Architecture, e.g. "aarch64", "arm", "x86"
+    pub arch: Option<String>,
+    /// Environment, e.g. "gnu", "musl", "msvc"
+    pub env: Option<String>,
+    /// Only targets installed with rustup
+    pub installed: bool,
+}
+
+impl TargetQuery {
+    /// Whether `target`, installed or not, matches
+    #[must_use]
+    pub fn matches(&self, target: &Target, installed: bool) -> bool {
+        if self.installed && !installed {
+            return false;
+        }
+        if let Some(pattern) = &self.pattern {
+            if !pattern.matches(&target.triple) {
+                return false;
+            }
+        }
+        let is = |filter: &Option<String>, values: &[Option<&str>]| {
+            filter.as_deref().map_or(true, |wanted| {
+                values
+                    .iter()
+                    .flatten()
+                    .any(|value| value.eq_ignore_ascii_case(wanted))
+            })
+        };
+        is(&self.os, &[Some(&target.os)])
+            && is(
+                &self.arch,
+                &[Some(&target.arch), Some(target.triple_arch())],
+            )
+            && is(&self.env, &[target.env.as_deref()])
+    }
+
+    /// The targets of `available` that match, with whether each is
+    /// installed, sorted by triple
+    #[must_use]
+    pub fn search(&self, available: Vec<(Target, bool)>) -> Vec<(Target, bool)> {
+        let mut found: Vec<(Target, bool)> = available
+            .into_iter()
+            .filter(|(target, installed)| self.matches(target, *installed))
+            .collect();
+        found.sort_by(|(a, _), (b, _)| a.triple.cmp(&b.triple));
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn available() -> Vec<(Target, bool)> {
+        [
+            ("x86_64-unknown-linux-gnu", true),
+            ("aarch64-unknown-linux-musl", false),
+            ("armv7-unknown-linux-musleabihf", false),
+            ("aarch64-apple-darwin", false),
+            ("thumbv7em-none-eabihf", true),
+            ("aarch64-linux-android", false),
+        ]
+        .into_iter()
+        .map(|(triple, installed)| (Target::from_triple(triple).unwrap(), installed))
+        .collect()
+    }
+
+    fn triples(query: &TargetQuery) -> Vec<String> {
+        query
+            .search(available())
+            .into_iter()
+            .map(|(target, _)| target.triple)
+            .collect()
+    }
+
+    #[test]
+    fn test_search_targets() {
+        let query = TargetQuery {
+            pattern: Some(Pattern::new("AARCH64", false).unwrap()),
+            ..TargetQuery::default()
+        };
+        assert_eq!(
+            triples(&query),
+            [
+                "aarch64-apple-darwin",
+                "aarch64-linux-android",
+                "aarch64-unknown-linux-musl"
+            ]
+        );
+
+        let query = TargetQuery {
+            pattern: Some(Pattern::new("^aarch64-.*-linux", true).unwrap()),
+            ..TargetQuery::default()
+        };
+        assert_eq!(triples(&query), ["aarch64-unknown-linux-musl"]);
+        assert!(Pattern::new("(", true).is_err());
+
+        // Filters use rustc's spelling; architectures the triple's too
+        let query = TargetQuery {
+            env: Some("musl".to_string()),
+            ..TargetQuery::default()
+        };
+        assert_eq!(
+            triples(&query),
+            [
+                "aarch64-unknown-linux-musl",
+                "armv7-unknown-linux-musleabihf"
+            ]
+        );
+        let query = TargetQuery {
+            os: Some("macos".to_string()),
+            ..TargetQuery::default()
+        };
+        assert_eq!(triples(&query), ["aarch64-apple-darwin"]);
+        let query = TargetQuery {
+            arch: Some("arm".to_string()),
+            installed: true,
+            ..TargetQuery::default()
+        };
+        assert_eq!(triples(&query), ["thumbv7em-none-eabihf"]);
+        let query = TargetQuery {
+            arch: Some("armv7".to_string()),
+            ..TargetQuery::default()
+        };
+        assert_eq!(triples(&query), ["armv7-unknown-linux-musleabihf"]);
+    }
+}
@@ -0,0 +1,262 @@
+//! Target metadata from rustc's target specifications
+//!
+//! Splitting a triple on `-` guesses wrong for triples without a vendor
+//! (`aarch64-linux-android`) or without an OS (`thumbv6m-none-eabi`), and
+//! says nothing about pointer width or atomics. rustc knows: a nightly
+//! rustc prints a target's specification with `--print target-spec-json`,
+//! read once per target. Stable rustc can't print them, so a table of
+//! common targets stands in, and other triples are parsed into the same
+//! names (see [`Target`](super::Target)). Custom targets given as a
+//! `*.json` spec are read from the file.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+
+/// Common targets, for when rustc can't print their specs: triple, arch,
+/// vendor, os, env, abi, pointer width and max atomic width, with `-` for
+/// unset
+pub(super) const BUILTIN_SPECS: &str = "\
+x86_64-unknown-linux-gnu       x86_64    unknown linux      gnu    -      64 64
+x86_64-unknown-linux-musl      x86_64    unknown linux      musl   -      64 64
+i686-unknown-linux-gnu         x86       unknown linux      gnu    -      32 64
+aarch64-unknown-linux-gnu      aarch64   unknown linux      gnu    -      64 128
+aarch64-unknown-linux-musl     aarch64   unknown linux      musl   -      64 128
+armv7-unknown-linux-gnueabihf  arm       unknown linux      gnu    eabihf 32 64
+armv7-unknown-linux-musleabihf arm       unknown linux      musl   eabihf 32 64
+arm-unknown-linux-gnueabihf    arm       unknown linux      gnu    eabihf 32 64
+riscv64gc-unknown-linux-gnu    riscv64   unknown linux      gnu    -      64 64
+powerpc64le-unknown-linux-gnu  powerpc64 unknown linux      gnu    elfv2  64 64
+s390x-unknown-linux-gnu        s390x     unknown linux      gnu    -      64 128
+x86_64-pc-windows-gnu          x86_64    pc      windows    gnu    -      64 128
+x86_64-pc-windows-msvc         x86_64    pc      windows    msvc   -      64 128
+i686-pc-windows-gnu            x86       pc      windows    gnu    -      32 64
+i686-pc-windows-msvc           x86       pc      windows    msvc   -      32 64
+aarch64-pc-windows-msvc        aarch64   pc      windows    msvc   -      64 128
+x86_64-apple-darwin            x86_64    apple   macos      -      -      64 128
+aarch64-apple-darwin           aarch64   apple   macos      -      -      64 128
+aarch64-apple-ios              aarch64   apple   ios        -      -      64 128
+aarch64-apple-ios-sim          aarch64   apple   ios        sim    sim    64 128
+x86_64-apple-ios               x86_64    apple   ios        sim    sim    64 128
+aarch64-apple-ios-macabi       aarch64   apple   ios        macabi macabi 64 128
+x86_64-apple-ios-macabi        x86_64    apple   ios        macabi macabi 64 128
+aarch64-linux-android          aarch64   unknown android    -      -      64 128
+armv7-linux-androideabi        arm       unknown android    -      eabi   32 64
+i686-linux-android             x86       unknown android    -      -      32 64
+x86_64-linux-android           x86_64    unknown android    -      -      64 64
+x86_64-unknown-freebsd         x86_64    unknown freebsd    -      -      64 64
+x86_64-unknown-netbsd          x86_64    unknown netbsd     -      -      64 64
+x86_64-unknown-illumos         x86_64    unknown illumos    -      -      64 64
+wasm32-unknown-unknown         wasm32    unknown unknown    -      -      32 64
+wasm32-unknown-emscripten      wasm32    unknown emscripten -      -      32 64
+wasm32-wasip1                  wasm32    unknown wasi       p1     -      32 64
+wasm32-wasip1-threads          wasm32    unknown wasi       p1     -      32 64
+wasm32-wasip2                  wasm32    unknown wasi       p2     -      32 64
+thumbv6m-none-eabi             arm       unknown none       -      eabi   32 32
+thumbv7em-none-eabi            arm       unknown none       -      eabi   32 32
+thumbv7em-none-eabihf          arm       unknown none       -      eabihf 32 32
+riscv32imac-unknown-none-elf   riscv32   unknown none       -      -      32 32
+aarch64-unknown-none           aarch64   unknown none       -      -      64 128
+x86_64-unknown-none            x86_64    unknown none       -      -      64 64
+";
+
+/// What rustc's specification says about a target
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TargetSpec {
+    /// Architecture family (e.g., "arm" for `thumbv7em-none-eabihf`)
+    pub arch: String,
+    /// Vendor, "unknown" when the triple names none
+    pub vendor: String,
+    /// Operating system as rustc's `target_os` (e.g., "macos", "none")
+    pub os: String,
+    /// Environment as rustc's `target_env` (e.g., "gnu", "p1")
+    pub env: Option<String>,
+    /// ABI as rustc's `target_abi` (e.g., "eabihf")
+    pub abi: Option<String>,
+    /// Width of pointers, in bits
+    pub pointer_width: u32,
+    /// Widest atomic operation, in bits; 0 when there are none
+    pub max_atomic_width: u32,
+}
+
+impl TargetSpec {
+    /// The spec of `triple`, or of the custom target spec file it names
+    ///
+    /// Returns `None` for targets neither rustc nor the built-in table
+    /// know about.
+    #[must_use]
+    pub fn for_triple(triple: &str) -> Option<Self> {
+        let path = Path::new(triple);
+        if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+        {
+            let contents = std::fs::read_to_string(path).ok()?;
+            return Self::from_json(&serde_json::from_str(&contents).ok()?);
+        }
+        rustc_spec(triple).or_else(|| Self::builtin(triple))
+    }
+
+    /// The spec of `triple` from the built-in table
+    #[must_use]
+    pub fn builtin(triple: &str) -> Option<Self> {
+        let line = BUILTIN_SPECS
+            .lines()
+            .find(|line| line.split_whitespace().next() == Some(triple))?;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let optional = |value: &str| (value != "-").then(|| value.to_string());
+        Some(Self {
+            arch: fields[1].to_string(),
+            vendor: fields[2].to_string(),
+            os: fields[3].to_string(),
+            env: optional(fields[4]),
+            abi: optional(fields[5]),
+            pointer_width: fields[6].parse().ok()?,
+            max_atomic_width: fields[7].parse().ok()?,
+        })
+    }
+
+    /// Read a spec in rustc's JSON form, where unset keys take rustc's
+    /// defaults
+    fn from_json(spec: &Value) -> Option<Self> {
+        let text = |key: &str| {
+            spec.get(key)
+                .and_then(Value::as_str)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        // A number in recent rustc, a string in older releases
+        let width = |key: &str| {
+            spec.get(key).and_then(|value| {
+                value
+                    .as_u64()
+                    .or_else(|| value.as_str()?.parse().ok())
+                    .and_then(|width| u32::try_from(width).ok())
+            })
+        };
+        let pointer_width = width("target-pointer-width")?;
+        Some(Self {
+            arch: text("arch")?,
+            vendor: text("vendor").unwrap_or_else(|| "unknown".to_string()),
+            os: text("os").unwrap_or_else(|| "none".to_string()),
+            env: text("env"),
+            abi: text("abi"),
+            pointer_width,
+            max_atomic_width: width("max-atomic-width").unwrap_or(pointer_width),
+        })
+    }
+}
+
+/// rustc's spec for `triple`, read once per triple
+///
+/// Printing a spec needs `-Z unstable-options`, so only a nightly rustc is
+/// asked. `None` on stable, or when rustc doesn't know the triple.
+fn rustc_spec(triple: &str) -> Option<TargetSpec> {
+    static SPECS: OnceLock<Mutex<HashMap<String, Option<TargetSpec>>>> = OnceLock::new();
+    if !rustc_is_nightly() {
+        return None;
+    }
+    let mut specs = SPECS
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    specs
+        .entry(triple.to_string())
+        .or_insert_with(|| {
+            Command::new("rustc")
+                .args(["-Z", "unstable-options", "--print", "target-spec-json"])
+                .args(["--target", triple])
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .and_then(|output| serde_json::from_slice::<Value>(&output.stdout).ok())
+                .and_then(|spec| TargetSpec::from_json(&spec))
+        })
+        .clone()
+}
+
+/// Whether the active rustc is a nightly (or locally built) one
+fn rustc_is_nightly() -> bool {
+    static NIGHTLY: OnceLock<bool> = OnceLock::new();
+    *NIGHTLY.get_or_init(|| {
+        Command::new("rustc")
+            .arg("--version")
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            .is_ok_and(|version| version.contains("-nightly") || version.contains("-dev"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_builtin_specs() {
+        let android = TargetSpec::builtin("aarch64-linux-android").unwrap();
+        assert_eq!(android.vendor, "unknown");
+        assert_eq!(android.os, "android");
+        assert_eq!(android.pointer_width, 64);
+
+        let armv7 = TargetSpec::builtin("armv7-unknown-linux-gnueabihf").unwrap();
+        assert_eq!(armv7.arch, "arm");
+        assert_eq!(armv7.env.as_deref(), Some("gnu"));
+        assert_eq!(armv7.abi.as_deref(), Some("eabihf"));
+        assert_eq!(armv7.pointer_width, 32);
+        assert_eq!(armv7.max_atomic_width, 64);
+
+        assert!(TargetSpec::builtin("x86_64-unknown-haiku-nonsense").is_none());
+
+        // The table agrees with the rustc running the tests
+        for line in BUILTIN_SPECS.lines() {
+            let triple = line.split_whitespace().next().unwrap();
+            if let Some(spec) = rustc_spec(triple) {
+                assert_eq!(TargetSpec::builtin(triple), Some(spec), "{triple}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_spec_from_json() {
+        let spec = TargetSpec::from_json(&json!({
+            "arch": "arm",
+            "os": "none",
+            "abi": "eabi",
+            "target-pointer-width": "32",
+            "max-atomic-width": 0,
+        }))
+        .unwrap();
+        assert_eq!(spec.vendor, "unknown");
+        assert_eq!(spec.env, None);
+        assert_eq!(spec.pointer_width, 32);
+        assert_eq!(spec.max_atomic_width, 0);
+
+        // Without a max atomic width, atomics are pointer-sized
+        let spec = TargetSpec::from_json(&json!({
+            "arch": "x86_64",
+            "target-pointer-width": 64,
+        }))
+        .unwrap();
+        assert_eq!(spec.os, "none");
+        assert_eq!(spec.max_atomic_width, 64);
+
+        assert!(TargetSpec::from_json(&json!({ "arch": "x86_64" })).is_none());
+    }
+
+    #[test]
+    fn test_custom_spec_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("x86_64-kernel.json");
+        std::fs::write(
+            &path,
+            r#"{"arch": "x86_64", "os": "none", "target-pointer-width": "64", "llvm-target": "x86_64-unknown-none"}"#,
+        )
+        .unwrap();
+        let spec = TargetSpec::for_triple(path.to_str().unwrap()).unwrap();
+        assert_eq!(spec.arch, "x86_64");
+        assert_eq!(spec.os, "none");
+    }
+}
@@ -0,0 +1,138 @@
+//! "Did you mean" suggestions for mistyped target triples
+//!
+//! Candidates are ranked by two measures:
+//!
+//! 1. Missing components: every component of the input appears, in order,
+//!    in the candidate (`x86_64-linux-gnu` → `x86_64-unknown-linux-gnu`).
+//!    Ranked by the number of extra components.
+//! 2. Typos: the edit distance is small relative to the input length
+//!    (`aarch64-unknwon-linux-gnu` → `aarch64-unknown-linux-gnu`).
+
+/// Most suggestions returned for one input
+pub const MAX_SUGGESTIONS: usize = 3;
+
+/// Candidates closest to `input`, best match first
+#[must_use]
+pub fn suggest<S: AsRef<str>>(input: &str, candidates: &[S]) -> Vec<String> {
+    let original = input.trim();
+    let input = original.to_lowercase();
+    if input.is_empty() {
+        return Vec::new();
+    }
+
+    let mut scored: Vec<(usize, &str)> = candidates
+        .iter()
+        .map(AsRef::as_ref)
+        .filter(|candidate| *candidate != original)
+        .filter_map(|candidate| score(&input, candidate).map(|score| (score, candidate)))
+        .collect();
+    scored.sort_unstable();
+
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate.to_string())
+        .collect()
+}
+
+/// Levenshtein distance between two strings
+#[must_use]
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+/// Lower is better; `None` if `candidate` is not a plausible match
+fn score(input: &str, candidate: &str) -> Option<usize> {
+    if let Some(extra) = missing_components(input, candidate) {
+        return Some(extra);
+    }
+
+    let distance = edit_distance(input, candidate);
+    let threshold = (input.chars().count() / 6).max(2);
+    (distance <= threshold).then_some(distance)
+}
+
+/// Number of extra components if all of `input`'s appear in `candidate`
+fn missing_components(input: &str, candidate: &str) -> Option<usize> {
+    let wanted: Vec<&str> = input.split('-').collect();
+    let mut available = candidate.split('-');
+    let mut total = 0;
+
+    for part in &wanted {
+        loop {
+            total += 1;
+            if available.next()? == *part {
+                break;
+            }
+        }
+    }
+
+    Some(total + available.count() - wanted.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRIPLES: &[&str] = &[
+        "aarch64-apple-darwin",
+        "aarch64-unknown-linux-gnu",
+        "aarch64-unknown-linux-musl",
+        "x86_64-pc-windows-gnu",
+        "x86_64-pc-windows-msvc",
+        "x86_64-unknown-linux-gnu",
+        "x86_64-unknown-linux-gnux32",
+        "x86_64-unknown-linux-musl",
+    ];
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("linux", "linux"), 0);
+    }
+
+    #[test]
+    fn test_missing_vendor() {
+        assert_eq!(
+            suggest("x86_64-linux-gnu", TRIPLES),
+            vec!["x86_64-unknown-linux-gnu"]
+        );
+        assert_eq!(
+            suggest("x86_64-windows-msvc", TRIPLES),
+            vec!["x86_64-pc-windows-msvc"]
+        );
+    }
+
+    #[test]
+    fn test_typos() {
+        assert_eq!(
+            suggest("aarch64-unknwon-linux-gnu", TRIPLES)[0],
+            "aarch64-unknown-linux-gnu"
+        );
+        assert_eq!(
+            suggest("X86_64-PC-Windows-GNU", TRIPLES)[0],
+            "x86_64-pc-windows-gnu"
+        );
+    }
+
+    #[test]
+    fn test_no_plausible_match() {
+        assert!(suggest("mips-sony-psx", TRIPLES).is_empty());
+        assert!(suggest("", TRIPLES).is_empty());
+        assert!(suggest("linux", TRIPLES).len() <= MAX_SUGGESTIONS);
+    }
+}
@@ -0,0 +1,453 @@
+//! Test support: fake toolchains and project fixtures
+//!
+//! Enabled with the `test-util` feature, for xcargo's own integration
+//! tests and for plugin authors who want to test against xcargo without
+//! real toolchains installed.
+//!
+//! [`FakeTools`] puts shims for `rustup`, `cargo`, `docker` and any other
+//! program in a directory that becomes the only entry of `PATH`. Each shim
+//! records how it was invoked and answers with canned output, so builds,
+//! strategy selection and error paths run the same way on every machine.
+//! [`Project`] creates a throwaway Cargo project to run them in.
+//!
+//! ```no_run
+//! use xcargo::testing::{FakeTools, Project, Response};
+//!
+//! # fn example() -> xcargo::Result<()> {
+//! let project = Project::binary("app")?;
+//! let mut tools = FakeTools::new("x86_64-unknown-linux-gnu")?;
+//! tools.respond("cargo", &["build"], Response::failure(101, "error: could not compile `app`"))?;
+//!
+//! let status = tools
+//!     .command("target/debug/xcargo")
+//!     .args(["build", "--target", "aarch64-unknown-linux-gnu"])
+//!     .current_dir(project.path())
+//!     .status()?;
+//! assert!(!status.success());
+//! assert_eq!(
+//!     tools.invocations("cargo")?[0],
+//!     ["build", "--target", "aarch64-unknown-linux-gnu"]
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::shell::quote;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::TempDir;
+
+/// File the shims append their invocations to
+const INVOCATION_LOG: &str = "invocations";
+
+/// Separates the arguments of one recorded invocation
+const ARG_SEPARATOR: char = '\x1f';
+
+/// Ends one recorded invocation
+const RECORD_SEPARATOR: char = '\x1e';
+
+/// What a shim prints and returns for a matching invocation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Response {
+    /// Text written to stdout
+    pub stdout: String,
+    /// Text written to stderr
+    pub stderr: String,
+    /// Exit code
+    pub code: i32,
+    /// Seconds to wait before answering
+    pub delay: u32,
+}
+
+impl Response {
+    /// Exit successfully, printing `stdout`
+    #[must_use]
+    pub fn ok(stdout: impl Into<String>) -> Self {
+        Self {
+            stdout: stdout.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Exit with `code`, printing `stderr`
+    #[must_use]
+    pub fn failure(code: i32, stderr: impl Into<String>) -> Self {
+        Self {
+            stderr: stderr.into(),
+            code,
+            ..Self::default()
+        }
+    }
+
+    /// Wait `seconds` before answering (e.g., to run into a timeout)
+    #[must_use]
+    pub fn delayed(mut self, seconds: u32) -> Self {
+        self.delay = seconds;
+        self
+    }
+}
+
+/// A canned response for invocations starting with `prefix`
+#[derive(Debug, Clone)]
+struct Rule {
+    prefix: Vec<String>,
+    response: Response,
+}
+
+/// Shims for external programs, isolated from the real `PATH` and home
+///
+/// Shims are POSIX shell scripts, so they run on Unix hosts. A shim
+/// answers with the first rule, in the order they were added, whose
+/// arguments start the invocation; anything else exits successfully with
+/// no output. Programs without a shim are not found at all.
+#[derive(Debug)]
+pub struct FakeTools {
+    dir: TempDir,
+    programs: BTreeMap<String, Vec<Rule>>,
+}
+
+impl FakeTools {
+    /// Shims for a working Rust setup on `host`
+    ///
+    /// `rustc -vV` reports `host`, `rustup` and `cargo` report versions, and
+    /// the stable toolchain is installed with only the host target. Other
+    /// invocations (e.g., `rustup target add`, `cargo build`) succeed
+    /// without output. Docker, Podman and Zig are absent
+    /// until added with [`FakeTools::respond`] or [`FakeTools::docker`].
+    pub fn new(host: &str) -> Result<Self> {
+        let mut tools = Self::empty()?;
+        tools.respond(
+            "rustc",
+            &["-vV"],
+            Response::ok(format!(
+                "rustc 1.80.0 (051478957 2024-07-21)\nbinary: rustc\nhost: {host}\nrelease: 1.80.0\n"
+            )),
+        )?;
+        tools.respond(
+            "rustc",
+            &["--version"],
+            Response::ok("rustc 1.80.0 (051478957 2024-07-21)\n"),
+        )?;
+        tools.respond(
+            "rustup",
+            &["--version"],
+            Response::ok("rustup 1.27.1 (54dd3d00f 2024-04-24)\n"),
+        )?;
+        tools.respond(
+            "rustup",
+            &["toolchain", "list"],
+            Response::ok(format!("stable-{host} (default)\n")),
+        )?;
+        tools.respond(
+            "rustup",
+            &["target", "list", "--installed"],
+            Response::ok(format!("{host}\n")),
+        )?;
+        tools.respond(
+            "cargo",
+            &["--version"],
+            Response::ok("cargo 1.80.0 (376290515 2024-07-16)\n"),
+        )?;
+        Ok(tools)
+    }
+
+    /// A shim directory with no programs
+    pub fn empty() -> Result<Self> {
+        let dir = TempDir::new()?;
+        fs::create_dir_all(dir.path().join("bin"))?;
+        fs::create_dir_all(dir.path().join("home"))?;
+        Ok(Self {
+            dir,
+            programs: BTreeMap::new(),
+        })
+    }
+
+    /// Answer invocations of `program` whose arguments start with `prefix`
+    ///
+    /// An empty `prefix` matches every invocation.
+    pub fn respond(&mut self, program: &str, prefix: &[&str], response: Response) -> Result<()> {
+        let rules = self.programs.entry(program.to_string()).or_default();
+        rules.push(Rule {
+            prefix: prefix.iter().map(|arg| (*arg).to_string()).collect(),
+            response,
+        });
+        self.install(program)
+    }
+
+    /// Add a running Docker on which every command succeeds
+    pub fn docker(&mut self) -> Result<()> {
+        self.respond(
+            "docker",
+            &["--version"],
+            Response::ok("Docker version 27.0.3, build 7d4bcd8\n"),
+        )
+    }
+
+    /// Directory holding the shims
+    #[must_use]
+    pub fn bin_dir(&self) -> PathBuf {
+        self.dir.path().join("bin")
+    }
+
+    /// Home directory for commands, so user config and plugins stay out
+    #[must_use]
+    pub fn home_dir(&self) -> PathBuf {
+        self.dir.path().join("home")
+    }
+
+    /// A command for `program` that sees only the shims
+    ///
+    /// `PATH` holds the shim directory alone, `HOME` an empty directory, and
+    /// variables that change xcargo's behavior (`XCARGO_LANG`,
+    /// `CARGO_TARGET_DIR`, CI detection) are removed.
+    pub fn command(&self, program: impl AsRef<OsStr>) -> Command {
+        let mut command = Command::new(program);
+        command
+            .env("PATH", self.bin_dir())
+            .env("HOME", self.home_dir())
+            .env("USERPROFILE", self.home_dir())
+            .env_remove(crate::i18n::LANG_VAR)
+            .env_remove("CARGO_TARGET_DIR")
+            .env_remove("RUSTUP_TOOLCHAIN");
+        for ci in [
+            "GITHUB_ACTIONS",
+            "GITLAB_CI",
+            "BUILDKITE",
+            "TEAMCITY_VERSION",
+            "XCARGO_ANNOTATIONS",
+            "XCARGO_LOG",
+        ] {
+            command.env_remove(ci);
+        }
+        command
+    }
+
+    /// Arguments of each invocation of `program`, oldest first
+    pub fn invocations(&self, program: &str) -> Result<Vec<Vec<String>>> {
+        Ok(self
+            .all_invocations()?
+            .into_iter()
+            .filter(|(name, _)| name == program)
+            .map(|(_, args)| args)
+            .collect())
+    }
+
+    /// Every recorded invocation as (program, arguments), oldest first
+    pub fn all_invocations(&self) -> Result<Vec<(String, Vec<String>)>> {
+        let log = self.dir.path().join(INVOCATION_LOG);
+        if !log.exists() {
+            return Ok(Vec::new());
+        }
+        Ok(fs::read_to_string(log)?
+            .split(RECORD_SEPARATOR)
+            .filter(|record| !record.is_empty())
+            .map(|record| {
+                let mut parts = record.split(ARG_SEPARATOR).map(str::to_string);
+                let program = parts.next().unwrap_or_default();
+                (program, parts.collect())
+            })
+            .collect())
+    }
+
+    /// Forget the invocations recorded so far
+    pub fn clear_invocations(&self) -> Result<()> {
+        let log = self.dir.path().join(INVOCATION_LOG);
+        if log.exists() {
+            fs::remove_file(log)?;
+        }
+        Ok(())
+    }
+
+    /// Write the shim for `program` from its rules
+    fn install(&self, program: &str) -> Result<()> {
+        if program.is_empty() || program.contains(['/', '\\']) {
+            return Err(Error::Config(format!("Invalid shim name: {program}")));
+        }
+
+        let log = self.dir.path().join(INVOCATION_LOG);
+        let mut script = format!(
+            "#!/bin/sh\n\
+             # Shim installed by xcargo::testing\n\
+             {{ printf '%s' {name}; for arg in \"$@\"; do printf '\\037%s' \"$arg\"; done; printf '\\036'; }} >> {log}\n\
+             args=\" $* \"\n",
+            name = quote(program),
+            log = quote(&log.to_string_lossy()),
+        );
+        for rule in self.programs.get(program).into_iter().flatten() {
+            // Match whole arguments: " build --target " starts " build --target x "
+            let prefix = format!(" {} ", rule.prefix.join(" "));
+            let prefix = if rule.prefix.is_empty() { " " } else { &prefix };
+            let _ = write!(
+                script,
+                "case \"$args\" in {}*)\n    {}printf '%s' {}\n    printf '%s' {} >&2\n    exit {}\n    ;;\nesac\n",
+                quote(prefix),
+                delay(rule.response.delay),
+                quote(&rule.response.stdout),
+                quote(&rule.response.stderr),
+                rule.response.code,
+            );
+        }
+        script.push_str("exit 0\n");
+
+        let path = self.bin_dir().join(program);
+        fs::write(&path, script)?;
+        make_executable(&path)
+    }
+}
+
+/// Shell line waiting `seconds`
+///
+/// `sleep` is called by path since `PATH` holds only the shims, and is
+/// detached from the shim's output so a killed shim leaves no pipe open.
+fn delay(seconds: u32) -> String {
+    if seconds == 0 {
+        return String::new();
+    }
+    format!("/bin/sleep {seconds} </dev/null >/dev/null 2>&1\n    ")
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Err(Error::Config(
+        "Fake toolchains need a Unix shell to run".to_string(),
+    ))
+}
+
+/// A throwaway Cargo project, removed on drop
+#[derive(Debug)]
+pub struct Project {
+    dir: TempDir,
+}
+
+impl Project {
+    /// A binary crate named `name` that prints a greeting
+    pub fn binary(name: &str) -> Result<Self> {
+        let project = Self::empty()?;
+        project.file("Cargo.toml", &manifest(name))?;
+        project.file(
+            "src/main.rs",
+            "fn main() {\n    println!(\"Hello, world!\");\n}\n",
+        )?;
+        Ok(project)
+    }
+
+    /// A library crate named `name`
+    pub fn library(name: &str) -> Result<Self> {
+        let project = Self::empty()?;
+        project.file("Cargo.toml", &manifest(name))?;
+        project.file(
+            "src/lib.rs",
+            "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n",
+        )?;
+        Ok(project)
+    }
+
+    /// An empty directory
+    pub fn empty() -> Result<Self> {
+        Ok(Self {
+            dir: TempDir::new()?,
+        })
+    }
+
+    /// Add an `xcargo.toml`
+    pub fn config(self, contents: &str) -> Result<Self> {
+        self.file("xcargo.toml", contents)?;
+        Ok(self)
+    }
+
+    /// Write `contents` to `path` inside the project, creating directories
+    pub fn file(&self, path: impl AsRef<Path>, contents: &str) -> Result<PathBuf> {
+        let path = self.dir.path().join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    /// Project root
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+/// `Cargo.toml` for a package named `name`
+fn manifest(name: &str) -> String {
+    format!(
+        "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\n"
+    )
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shim_responds_and_records() {
+        let mut tools = FakeTools::new("x86_64-unknown-linux-gnu").unwrap();
+        tools
+            .respond(
+                "cargo",
+                &["build"],
+                Response::failure(101, "error: it's broken"),
+            )
+            .unwrap();
+
+        let output = tools.command("rustc").arg("-vV").output().unwrap();
+        assert!(String::from_utf8_lossy(&output.stdout).contains("host: x86_64-unknown-linux-gnu"));
+
+        let output = tools
+            .command("cargo")
+            .args(["build", "--target", "a b"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(101));
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr),
+            "error: it's broken"
+        );
+
+        // `buildx` does not start with the `build` argument
+        let output = tools.command("cargo").arg("buildx").output().unwrap();
+        assert!(output.status.success());
+
+        assert_eq!(
+            tools.invocations("cargo").unwrap(),
+            vec![vec!["build", "--target", "a b"], vec!["buildx"]]
+        );
+        tools.clear_invocations().unwrap();
+        assert!(tools.all_invocations().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_isolated_path() {
+        let tools = FakeTools::empty().unwrap();
+        assert!(tools.command("sh").arg("-c").arg("true").output().is_err());
+        assert!(tools.command("docker").output().is_err());
+    }
+
+    #[test]
+    fn test_project() {
+        let project = Project::binary("app")
+            .unwrap()
+            .config("[targets]\ndefault = []\n")
+            .unwrap();
+        assert!(project.path().join("src/main.rs").is_file());
+        assert!(project.path().join("xcargo.toml").is_file());
+        let contents = fs::read_to_string(project.path().join("Cargo.toml")).unwrap();
+        assert!(contents.contains("name = \"app\""));
+    }
+}
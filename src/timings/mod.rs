@@ -0,0 +1,488 @@
+//! Compile time reports across targets
+//!
+//! `xcargo build --timings` runs cargo with `--timings=json`, which reports
+//! how long each crate took to compile. The times are kept per target in
+//! `<target-dir>/xcargo/timings/<triple>.json` and combined into one table
+//! of the slowest crates with a column per target. Crates that only cost
+//! time on some platforms (e.g., `windows-sys` on Windows targets) stand
+//! out, which shows where pruning a dependency pays off.
+//!
+//! `--timings=json` is unstable, so collecting times needs a nightly cargo.
+//! Every build also records the time its target spent in each phase
+//! (toolchain, dependencies, compile, post-process), see [`phases`].
+//!
+//! ```no_run
+//! use xcargo::timings;
+//!
+//! # fn example() -> xcargo::Result<()> {
+//! let recorded = timings::load_since(&timings::timings_dir()?, 0);
+//! timings::print_report(&recorded, 10);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod phases;
+
+pub use phases::{Phase, Stopwatch, TargetPhases};
+
+use crate::build::clean;
+use crate::error::{Error, Result};
+use crate::output::{self, helpers};
+use crate::state::{self, State};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Timings location inside cargo's target directory
+const TIMINGS_DIR: &str = "xcargo/timings";
+
+/// Cargo arguments that print a `timing-info` message per compiled unit
+pub const CARGO_ARGS: [&str; 2] = ["--timings=json", "-Zunstable-options"];
+
+/// Compile time of one unit (a crate's library, binary or build script)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnitTiming {
+    /// Package the unit belongs to
+    pub package: String,
+    /// Cargo target within the package (e.g., the library or a binary)
+    pub target: String,
+    /// What cargo did (e.g., "build", "check", "run-custom-build")
+    pub mode: String,
+    /// Seconds the unit took
+    pub duration: f64,
+    /// Seconds until its metadata was ready for dependents, if pipelined
+    #[serde(default)]
+    pub rmeta_time: Option<f64>,
+}
+
+impl UnitTiming {
+    /// Parse one line of cargo output, if it is a `timing-info` message
+    #[must_use]
+    pub fn from_line(line: &str) -> Option<Self> {
+        if !line.starts_with('{') {
+            return None;
+        }
+        let message: Value = serde_json::from_str(line).ok()?;
+        if message.get("reason")?.as_str()? != "timing-info" {
+            return None;
+        }
+
+        Some(Self {
+            package: package_name(message.get("package_id")?.as_str()?),
+            target: message
+                .pointer("/target/name")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            mode: message
+                .get("mode")
+                .and_then(Value::as_str)
+                .unwrap_or("build")
+                .to_string(),
+            duration: message.get("duration")?.as_f64()?,
+            rmeta_time: message.get("rmeta_time").and_then(Value::as_f64),
+        })
+    }
+}
+
+/// Package name from a cargo package ID
+///
+/// Handles both the spec form (`registry+https://...#windows-sys@0.52.0`,
+/// `path+file:///work/app#0.1.0`) and the older `windows-sys 0.52.0 (...)`.
+#[must_use]
+pub fn package_name(package_id: &str) -> String {
+    let Some((url, fragment)) = package_id.split_once('#') else {
+        return package_id
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string();
+    };
+    match fragment.split_once('@') {
+        Some((name, _)) => name.to_string(),
+        // The name is left out when it matches the last path segment
+        None => url
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+/// Compile times of one target's build
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TargetTimings {
+    /// Target triple
+    pub target: String,
+    /// Cargo profile ("debug" or "release")
+    pub profile: String,
+    /// When the build finished, in seconds since the Unix epoch
+    pub recorded: u64,
+    /// Every unit cargo compiled
+    pub units: Vec<UnitTiming>,
+}
+
+impl State for TargetTimings {
+    const SCHEMA: u32 = 1;
+    const DESCRIPTION: &'static str = "compile timings";
+}
+
+impl TargetTimings {
+    /// Times of a build of `target` that finished now
+    #[must_use]
+    pub fn new(target: &str, profile: &str, units: Vec<UnitTiming>) -> Self {
+        Self {
+            target: target.to_string(),
+            profile: profile.to_string(),
+            recorded: now(),
+            units,
+        }
+    }
+
+    /// Seconds spent compiling all units
+    ///
+    /// Units compile in parallel, so this is more than the build took.
+    #[must_use]
+    pub fn total(&self) -> f64 {
+        self.units.iter().map(|unit| unit.duration).sum()
+    }
+}
+
+/// Gathers `timing-info` messages from cargo output read on other threads
+#[derive(Debug, Clone, Default)]
+pub struct Collector {
+    units: Arc<Mutex<Vec<UnitTiming>>>,
+}
+
+impl Collector {
+    /// Create an empty collector
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep `line` if it is a timing message, returning whether it was
+    pub fn accept(&self, line: &str) -> bool {
+        let Some(unit) = UnitTiming::from_line(line) else {
+            return false;
+        };
+        self.units
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .push(unit);
+        true
+    }
+
+    /// Units collected so far
+    #[must_use]
+    pub fn units(&self) -> Vec<UnitTiming> {
+        self.units
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone()
+    }
+}
+
+/// Seconds since the Unix epoch
+#[must_use]
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Directory of the current project's timings
+pub fn timings_dir() -> Result<PathBuf> {
+    Ok(clean::target_directory()?.join(TIMINGS_DIR))
+}
+
+/// Write one target's timings to `dir`, replacing its previous ones
+pub fn save(dir: &Path, timings: &TargetTimings) -> Result<()> {
+    state::save(&dir.join(format!("{}.json", timings.target)), timings)
+}
+
+/// Timings in `dir` recorded at or after `since` (seconds since the Unix
+/// epoch), sorted by target
+#[must_use]
+pub fn load_since(dir: &Path, since: u64) -> Vec<TargetTimings> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut loaded: Vec<TargetTimings> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .map(|path| state::load::<TargetTimings>(&path))
+        .filter(|timings| !timings.units.is_empty() && timings.recorded >= since)
+        .collect();
+    loaded.sort_by(|a, b| a.target.cmp(&b.target));
+    loaded
+}
+
+/// Fail unless cargo for `toolchain` (`None` = active) is a nightly, which
+/// `--timings=json` needs
+pub fn require_nightly(toolchain: Option<&str>) -> Result<()> {
+    let mut cmd = Command::new("cargo");
+    if let Some(toolchain) = toolchain {
+        cmd.arg(format!("+{toolchain}"));
+    }
+    let version = cmd
+        .arg("--version")
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).into_owned())
+        .unwrap_or_default();
+    if version.contains("-nightly") || version.contains("-dev") {
+        return Ok(());
+    }
+
+    helpers::hint("Build with a nightly toolchain, e.g. --toolchain nightly");
+    Err(Error::Toolchain(format!(
+        "--timings needs a nightly cargo, as --timings=json is unstable (found {})",
+        version.trim().strip_prefix("cargo ").unwrap_or("no cargo")
+    )))
+}
+
+/// Time one crate took on each target
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrateTime {
+    /// Package name
+    pub name: String,
+    /// Seconds per target triple, for the targets that compiled it
+    pub seconds: BTreeMap<String, f64>,
+}
+
+impl CrateTime {
+    /// Seconds across all targets
+    #[must_use]
+    pub fn total(&self) -> f64 {
+        self.seconds.values().sum()
+    }
+}
+
+/// Compile time per crate and target, slowest crates first
+///
+/// A crate's build script, library and binaries count together.
+#[must_use]
+pub fn by_crate(timings: &[TargetTimings]) -> Vec<CrateTime> {
+    let mut crates: BTreeMap<&str, BTreeMap<String, f64>> = BTreeMap::new();
+    for target in timings {
+        for unit in &target.units {
+            *crates
+                .entry(&unit.package)
+                .or_default()
+                .entry(target.target.clone())
+                .or_default() += unit.duration;
+        }
+    }
+
+    let mut crates: Vec<CrateTime> = crates
+        .into_iter()
+        .map(|(name, seconds)| CrateTime {
+            name: name.to_string(),
+            seconds,
+        })
+        .collect();
+    crates.sort_by(|a, b| b.total().total_cmp(&a.total()));
+    crates
+}
+
+/// Print the slowest crates with their time on each target
+///
+/// `limit` caps the crates shown. A `-` marks a target that did not
+/// compile the crate at all.
+pub fn print_report(timings: &[TargetTimings], limit: usize) {
+    if timings.is_empty() {
+        helpers::warning("No compile timings were recorded");
+        return;
+    }
+
+    helpers::section("Compile Times");
+    for target in timings {
+        helpers::info(format!(
+            "{} ({}): {} across {} units",
+            target.target,
+            target.profile,
+            seconds(target.total()),
+            target.units.len()
+        ));
+    }
+
+    let crates = by_crate(timings);
+    let shown = &crates[..crates.len().min(limit)];
+    let cell = |krate: &CrateTime, target: &str| {
+        krate
+            .seconds
+            .get(target)
+            .map_or_else(|| "-".to_string(), |s| seconds(*s))
+    };
+
+    if output::is_plain() {
+        for krate in shown {
+            let times: Vec<String> = timings
+                .iter()
+                .map(|t| format!("{} {}", t.target, cell(krate, &t.target)))
+                .collect();
+            println!(
+                "{}: {}, total {}",
+                krate.name,
+                times.join(", "),
+                seconds(krate.total())
+            );
+        }
+    } else {
+        let header: Vec<String> = std::iter::once("Crate".to_string())
+            .chain(timings.iter().map(|t| t.target.clone()))
+            .chain(std::iter::once("Total".to_string()))
+            .collect();
+        let rows: Vec<Vec<String>> = shown
+            .iter()
+            .map(|krate| {
+                std::iter::once(krate.name.clone())
+                    .chain(timings.iter().map(|t| cell(krate, &t.target)))
+                    .chain(std::iter::once(seconds(krate.total())))
+                    .collect()
+            })
+            .collect();
+        let mut widths: Vec<usize> = header.iter().map(String::len).collect();
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        // Crate names align left, times right
+        let line = |cells: &[String]| {
+            let cells: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(column, (cell, width))| {
+                    if column == 0 {
+                        format!("{cell:<width$}")
+                    } else {
+                        format!("{cell:>width$}")
+                    }
+                })
+                .collect();
+            format!("  {}", cells.join("  "))
+        };
+        println!("{}", line(&header));
+        for row in &rows {
+            println!("{}", line(row));
+        }
+    }
+
+    if crates.len() > shown.len() {
+        let rest: f64 = crates[shown.len()..].iter().map(CrateTime::total).sum();
+        println!(
+            "  {} more crates, {} in total",
+            crates.len() - shown.len(),
+            seconds(rest)
+        );
+    }
+}
+
+/// Seconds with one decimal ("12.3s")
+fn seconds(secs: f64) -> String {
+    format!("{secs:.1}s")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn unit(package: &str, duration: f64) -> UnitTiming {
+        UnitTiming {
+            package: package.to_string(),
+            target: package.replace('-', "_"),
+            mode: "build".to_string(),
+            duration,
+            rmeta_time: None,
+        }
+    }
+
+    #[test]
+    fn test_from_line() {
+        let line = r#"{"reason":"timing-info","package_id":"registry+https://github.com/rust-lang/crates.io-index#windows-sys@0.52.0","target":{"kind":["lib"],"name":"windows_sys"},"mode":"build","duration":4.25,"rmeta_time":1.5}"#;
+        assert_eq!(
+            UnitTiming::from_line(line),
+            Some(UnitTiming {
+                package: "windows-sys".to_string(),
+                target: "windows_sys".to_string(),
+                mode: "build".to_string(),
+                duration: 4.25,
+                rmeta_time: Some(1.5),
+            })
+        );
+
+        let artifact = r#"{"reason":"compiler-artifact","package_id":"app 0.1.0"}"#;
+        assert_eq!(UnitTiming::from_line(artifact), None);
+        assert_eq!(UnitTiming::from_line("   Compiling app v0.1.0"), None);
+    }
+
+    #[test]
+    fn test_package_name() {
+        assert_eq!(
+            package_name("registry+https://github.com/rust-lang/crates.io-index#libc@0.2.155"),
+            "libc"
+        );
+        assert_eq!(package_name("path+file:///work/app#0.1.0"), "app");
+        assert_eq!(
+            package_name("serde 1.0.204 (registry+https://github.com/rust-lang/crates.io-index)"),
+            "serde"
+        );
+    }
+
+    #[test]
+    fn test_by_crate() {
+        let linux = TargetTimings::new(
+            "x86_64-unknown-linux-gnu",
+            "debug",
+            vec![unit("app", 2.0), unit("libc", 1.0)],
+        );
+        let windows = TargetTimings::new(
+            "x86_64-pc-windows-gnu",
+            "debug",
+            vec![unit("app", 2.5), unit("windows-sys", 6.0), unit("app", 0.5)],
+        );
+
+        let crates = by_crate(&[linux, windows]);
+        let names: Vec<&str> = crates.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["windows-sys", "app", "libc"]);
+        assert!((crates[1].seconds["x86_64-pc-windows-gnu"] - 3.0).abs() < 1e-9);
+        assert!((crates[1].total() - 5.0).abs() < 1e-9);
+        assert!(!crates[0].seconds.contains_key("x86_64-unknown-linux-gnu"));
+    }
+
+    #[test]
+    fn test_save_and_load_since() {
+        let temp = TempDir::new().unwrap();
+        let mut old = TargetTimings::new("aarch64-apple-darwin", "debug", vec![unit("app", 1.0)]);
+        old.recorded = 100;
+        save(temp.path(), &old).unwrap();
+        let new = TargetTimings::new("x86_64-pc-windows-gnu", "debug", vec![unit("app", 1.0)]);
+        save(temp.path(), &new).unwrap();
+
+        assert_eq!(load_since(temp.path(), 0).len(), 2);
+        assert_eq!(load_since(temp.path(), 200), vec![new]);
+        assert!(load_since(&temp.path().join("missing"), 0).is_empty());
+    }
+
+    #[test]
+    fn test_collector() {
+        let collector = Collector::new();
+        let shared = collector.clone();
+        assert!(!shared.accept("warning: unused variable"));
+        assert!(shared.accept(
+            r#"{"reason":"timing-info","package_id":"app 0.1.0","target":{"name":"app"},"mode":"build","duration":0.5}"#
+        ));
+        assert_eq!(collector.units().len(), 1);
+    }
+}
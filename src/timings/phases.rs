@@ -0,0 +1,444 @@
+//! Where each target's build time goes
+//!
+//! Every successful build records how long it spent in each phase:
+//! preparing the toolchain (rustup, the linker, Zig), fetching native
+//! dependencies, compiling and post-processing (artifacts, exports,
+//! verification and gates). The last build of each target and operation is
+//! kept in `<target-dir>/xcargo/timings/phases/`. `xcargo timings` prints
+//! them with the targets that took longest first, and can write an HTML
+//! report with a bar per target, like cargo's `--timings` report, to see
+//! which targets dominate CI time.
+//!
+//! Cargo compiles and links in one run, so linking counts toward the
+//! compile phase. Container builds are timed as a whole, as compile.
+
+use super::{now, seconds};
+use crate::error::Result;
+use crate::history;
+use crate::output::{self, helpers};
+use crate::state::{self, State};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Phases location next to the build history, in cargo's target directory
+const PHASES_DIR: &str = "timings/phases";
+
+/// A phase of one target's build
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Phase {
+    /// Toolchain, target, linker and Zig setup
+    Toolchain,
+    /// Native dependencies: downloads, sysroots, vendored builds
+    Dependencies,
+    /// Cargo compiling and linking
+    Compile,
+    /// Artifacts, exports, verification and gates
+    PostProcess,
+}
+
+impl Phase {
+    /// Every phase, in the order a build goes through them
+    pub const ALL: [Phase; 4] = [
+        Phase::Toolchain,
+        Phase::Dependencies,
+        Phase::Compile,
+        Phase::PostProcess,
+    ];
+
+    /// Name in reports
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            Phase::Toolchain => "toolchain",
+            Phase::Dependencies => "dependencies",
+            Phase::Compile => "compile",
+            Phase::PostProcess => "post-process",
+        }
+    }
+
+    /// Color of the phase's bars in the HTML report
+    fn color(self) -> &'static str {
+        match self {
+            Phase::Toolchain => "#9b59b6",
+            Phase::Dependencies => "#e67e22",
+            Phase::Compile => "#3498db",
+            Phase::PostProcess => "#2ecc71",
+        }
+    }
+}
+
+/// Seconds spent in one phase
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PhaseTime {
+    /// The phase
+    pub phase: Phase,
+    /// Seconds spent in it
+    pub seconds: f64,
+}
+
+/// Phase times of the last build of one target and operation
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TargetPhases {
+    /// Target triple
+    pub target: String,
+    /// Cargo operation ("build", "test", ...)
+    pub operation: String,
+    /// Cargo profile ("debug" or "release")
+    pub profile: String,
+    /// When the build finished, in seconds since the Unix epoch
+    pub recorded: u64,
+    /// Time spent in each phase the build went through
+    pub phases: Vec<PhaseTime>,
+}
+
+impl State for TargetPhases {
+    const SCHEMA: u32 = 1;
+    const DESCRIPTION: &'static str = "build phase timings";
+}
+
+impl TargetPhases {
+    /// Seconds spent in `phase`
+    #[must_use]
+    pub fn seconds(&self, phase: Phase) -> f64 {
+        self.phases
+            .iter()
+            .filter(|time| time.phase == phase)
+            .map(|time| time.seconds)
+            .sum()
+    }
+
+    /// Seconds the whole build took
+    #[must_use]
+    pub fn total(&self) -> f64 {
+        self.phases.iter().map(|time| time.seconds).sum()
+    }
+
+    /// "aarch64-unknown-linux-gnu build (release)"
+    #[must_use]
+    pub fn describe(&self) -> String {
+        format!("{} {} ({})", self.target, self.operation, self.profile)
+    }
+}
+
+/// Times the phases of one build as it goes through them
+#[derive(Debug)]
+pub struct Stopwatch {
+    lap_started: Instant,
+    phases: Vec<PhaseTime>,
+}
+
+impl Stopwatch {
+    /// Start timing a build
+    #[must_use]
+    pub fn start() -> Self {
+        Self {
+            lap_started: Instant::now(),
+            phases: Vec::new(),
+        }
+    }
+
+    /// Count the time since the last lap toward `phase`
+    pub fn lap(&mut self, phase: Phase) {
+        let seconds = self.lap_started.elapsed().as_secs_f64();
+        self.lap_started = Instant::now();
+        match self.phases.iter_mut().find(|time| time.phase == phase) {
+            Some(time) => time.seconds += seconds,
+            None => self.phases.push(PhaseTime { phase, seconds }),
+        }
+    }
+
+    /// Phase times of the build of `target` that finished now
+    #[must_use]
+    pub fn finish(self, target: &str, operation: &str, profile: &str) -> TargetPhases {
+        TargetPhases {
+            target: target.to_string(),
+            operation: operation.to_string(),
+            profile: profile.to_string(),
+            recorded: now(),
+            phases: self.phases,
+        }
+    }
+}
+
+/// Directory of the current project's phase times, found without asking
+/// cargo (see [`history::xcargo_dir`])
+pub fn phases_dir() -> Result<PathBuf> {
+    Ok(history::xcargo_dir()?.join(PHASES_DIR))
+}
+
+/// Write a build's phase times to `dir`, replacing the last ones of its
+/// target and operation
+pub fn save(dir: &Path, phases: &TargetPhases) -> Result<()> {
+    let file = format!("{}.{}.json", phases.target, phases.operation);
+    state::save(&dir.join(file), phases)
+}
+
+/// Phase times in `dir`, longest build first
+#[must_use]
+pub fn load(dir: &Path) -> Vec<TargetPhases> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut loaded: Vec<TargetPhases> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .map(|path| state::load::<TargetPhases>(&path))
+        .filter(|phases| !phases.phases.is_empty())
+        .collect();
+    loaded.sort_by(|a, b| {
+        b.total()
+            .total_cmp(&a.total())
+            .then_with(|| a.target.cmp(&b.target))
+    });
+    loaded
+}
+
+/// Share of `part` in `total`, in percent
+fn percent(part: f64, total: f64) -> f64 {
+    if total > 0.0 {
+        part * 100.0 / total
+    } else {
+        0.0
+    }
+}
+
+/// Print each build's time per phase, longest first, with its share of the
+/// time of all builds
+pub fn print_report(builds: &[TargetPhases]) {
+    if builds.is_empty() {
+        helpers::warning("No build phases were recorded yet; build a target first");
+        return;
+    }
+
+    helpers::section("Build Phases");
+    let all: f64 = builds.iter().map(TargetPhases::total).sum();
+    if output::is_plain() {
+        for build in builds {
+            let phases: Vec<String> = Phase::ALL
+                .iter()
+                .map(|phase| format!("{} {}", phase.label(), seconds(build.seconds(*phase))))
+                .collect();
+            println!(
+                "{}: {}, total {} ({:.0}%)",
+                build.describe(),
+                phases.join(", "),
+                seconds(build.total()),
+                percent(build.total(), all)
+            );
+        }
+    } else {
+        let header: Vec<String> = std::iter::once("Build".to_string())
+            .chain(Phase::ALL.iter().map(|phase| phase.label().to_string()))
+            .chain(["Total".to_string(), "Share".to_string()])
+            .collect();
+        let rows: Vec<Vec<String>> = builds
+            .iter()
+            .map(|build| {
+                std::iter::once(build.describe())
+                    .chain(
+                        Phase::ALL
+                            .iter()
+                            .map(|phase| seconds(build.seconds(*phase))),
+                    )
+                    .chain([
+                        seconds(build.total()),
+                        format!("{:.0}%", percent(build.total(), all)),
+                    ])
+                    .collect()
+            })
+            .collect();
+        let mut widths: Vec<usize> = header.iter().map(String::len).collect();
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        // Builds align left, times right
+        let line = |cells: &[String]| {
+            let cells: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(column, (cell, width))| {
+                    if column == 0 {
+                        format!("{cell:<width$}")
+                    } else {
+                        format!("{cell:>width$}")
+                    }
+                })
+                .collect();
+            format!("  {}", cells.join("  "))
+        };
+        println!("{}", line(&header));
+        for row in &rows {
+            println!("{}", line(row));
+        }
+    }
+
+    let phases: Vec<String> = Phase::ALL
+        .iter()
+        .map(|phase| {
+            let spent: f64 = builds.iter().map(|build| build.seconds(*phase)).sum();
+            format!("{} {:.0}%", phase.label(), percent(spent, all))
+        })
+        .collect();
+    helpers::info(format!(
+        "{} across {} builds: {}",
+        seconds(all),
+        builds.len(),
+        phases.join(", ")
+    ));
+}
+
+/// A standalone HTML page with a bar per build, split into its phases and
+/// scaled to the longest build, and the times as a table
+#[must_use]
+pub fn html_report(builds: &[TargetPhases]) -> String {
+    let longest = builds.iter().map(TargetPhases::total).fold(0.0, f64::max);
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>xcargo build phases</title>\n<style>\n\
+         body { font-family: sans-serif; margin: 2em; }\n\
+         .bars { display: grid; grid-template-columns: max-content 1fr max-content; gap: 6px 12px; align-items: center; }\n\
+         .bar { display: flex; height: 20px; }\n\
+         .bar div { height: 100%; }\n\
+         .legend span { display: inline-block; width: 12px; height: 12px; margin: 0 4px 0 12px; }\n\
+         table { border-collapse: collapse; margin-top: 2em; }\n\
+         th, td { padding: 4px 12px; text-align: right; border-bottom: 1px solid #ddd; }\n\
+         th:first-child, td:first-child { text-align: left; }\n\
+         </style>\n</head>\n<body>\n<h1>Build phases</h1>\n<p class=\"legend\">",
+    );
+    for phase in Phase::ALL {
+        let _ = write!(
+            html,
+            "<span style=\"background: {}\"></span>{}",
+            phase.color(),
+            phase.label()
+        );
+    }
+    html.push_str("</p>\n<div class=\"bars\">\n");
+    for build in builds {
+        let _ = write!(
+            html,
+            "<div>{}</div>\n<div class=\"bar\">",
+            escape(&build.describe())
+        );
+        for phase in Phase::ALL {
+            let spent = build.seconds(phase);
+            if spent > 0.0 {
+                let _ = write!(
+                    html,
+                    "<div style=\"width: {:.2}%; background: {}\" title=\"{}: {}\"></div>",
+                    percent(spent, longest),
+                    phase.color(),
+                    phase.label(),
+                    seconds(spent)
+                );
+            }
+        }
+        let _ = writeln!(html, "</div>\n<div>{}</div>", seconds(build.total()));
+    }
+    html.push_str("</div>\n<table>\n<tr><th>Build</th>");
+    for phase in Phase::ALL {
+        let _ = write!(html, "<th>{}</th>", phase.label());
+    }
+    html.push_str("<th>Total</th></tr>\n");
+    for build in builds {
+        let _ = write!(html, "<tr><td>{}</td>", escape(&build.describe()));
+        for phase in Phase::ALL {
+            let _ = write!(html, "<td>{}</td>", seconds(build.seconds(phase)));
+        }
+        let _ = writeln!(html, "<td>{}</td></tr>", seconds(build.total()));
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+/// `text` with HTML's special characters escaped
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn build(target: &str, times: &[(Phase, f64)]) -> TargetPhases {
+        TargetPhases {
+            target: target.to_string(),
+            operation: "build".to_string(),
+            profile: "release".to_string(),
+            recorded: 100,
+            phases: times
+                .iter()
+                .map(|(phase, seconds)| PhaseTime {
+                    phase: *phase,
+                    seconds: *seconds,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_stopwatch_adds_up_laps() {
+        let mut stopwatch = Stopwatch::start();
+        stopwatch.lap(Phase::Toolchain);
+        stopwatch.lap(Phase::Compile);
+        stopwatch.lap(Phase::Compile);
+        let phases = stopwatch.finish("x86_64-unknown-linux-gnu", "test", "debug");
+        let recorded: Vec<Phase> = phases.phases.iter().map(|time| time.phase).collect();
+        assert_eq!(recorded, [Phase::Toolchain, Phase::Compile]);
+        assert_eq!(phases.operation, "test");
+        assert!(phases.total() >= 0.0);
+    }
+
+    #[test]
+    fn test_save_and_load_longest_first() {
+        let temp = TempDir::new().unwrap();
+        let musl = build(
+            "aarch64-unknown-linux-musl",
+            &[(Phase::Toolchain, 2.0), (Phase::Compile, 40.0)],
+        );
+        let windows = build(
+            "x86_64-pc-windows-gnu",
+            &[(Phase::Compile, 90.0), (Phase::PostProcess, 5.0)],
+        );
+        save(temp.path(), &musl).unwrap();
+        save(temp.path(), &windows).unwrap();
+        // A later build of a target replaces its earlier one
+        save(temp.path(), &musl).unwrap();
+
+        let loaded = load(temp.path());
+        assert_eq!(loaded, [windows.clone(), musl]);
+        assert!((loaded[0].seconds(Phase::Compile) - 90.0).abs() < 1e-9);
+        assert!((loaded[0].total() - 95.0).abs() < 1e-9);
+        assert!(load(&temp.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn test_html_report() {
+        let builds = [
+            build("x86_64-pc-windows-gnu", &[(Phase::Compile, 90.0)]),
+            build(
+                "aarch64<test>",
+                &[(Phase::Compile, 30.0), (Phase::PostProcess, 15.0)],
+            ),
+        ];
+        let html = html_report(&builds);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("width: 100.00%"));
+        assert!(html.contains("width: 33.33%"));
+        assert!(html.contains("aarch64&lt;test&gt; build (release)"));
+        assert!(html.contains("<td>90.0s</td>"));
+    }
+}
@@ -0,0 +1,357 @@
+//! Xcode SDKs and simulators for iOS targets on macOS hosts
+//!
+//! iOS device, iOS simulator and Mac Catalyst targets link against
+//! different Xcode SDKs, and an `SDKROOT` left over from a macOS build (or
+//! set by Xcode) makes C code and the linker pick the wrong one. xcargo
+//! asks `xcrun` for the SDK each target needs and hands C compilers a
+//! matching `-target`. Tests for simulator targets run in an iOS simulator
+//! through `xcrun simctl spawn`.
+
+use crate::error::{Error, Result};
+use crate::target::Target;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// An Xcode SDK
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppleSdk {
+    /// iOS devices (`aarch64-apple-ios`)
+    IPhoneOs,
+    /// The iOS simulator (`aarch64-apple-ios-sim`, `x86_64-apple-ios`)
+    IPhoneSimulator,
+    /// macOS, which Mac Catalyst (`*-apple-ios-macabi`) builds against
+    MacOsx,
+}
+
+impl AppleSdk {
+    /// SDK an iOS target builds against, `None` for other targets
+    #[must_use]
+    pub fn for_target(target: &Target) -> Option<Self> {
+        if target.vendor != "apple" || target.os != "ios" {
+            return None;
+        }
+        Some(if is_mac_catalyst(target) {
+            Self::MacOsx
+        } else if is_simulator(target) {
+            Self::IPhoneSimulator
+        } else {
+            Self::IPhoneOs
+        })
+    }
+
+    /// Name `xcrun --sdk` takes
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::IPhoneOs => "iphoneos",
+            Self::IPhoneSimulator => "iphonesimulator",
+            Self::MacOsx => "macosx",
+        }
+    }
+
+    /// Location of the SDK, from `xcrun`
+    pub fn path(self) -> Result<PathBuf> {
+        let output = Command::new("xcrun")
+            .args(["--sdk", self.name(), "--show-sdk-path"])
+            .output()
+            .map_err(|e| Error::Toolchain(format!("Failed to run xcrun: {e}")))?;
+        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !output.status.success() || path.is_empty() {
+            return Err(Error::Toolchain(format!(
+                "The {} SDK is not installed: {}",
+                self.name(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(PathBuf::from(path))
+    }
+}
+
+/// Check if `target` runs in the iOS simulator: `*-apple-ios-sim`, and
+/// the Intel triples, which have no devices
+#[must_use]
+pub fn is_simulator(target: &Target) -> bool {
+    target.os == "ios"
+        && match target.env.as_deref() {
+            Some(env) => env == "sim",
+            None => matches!(target.arch.as_str(), "x86_64" | "x86"),
+        }
+}
+
+/// Check if `target` is Mac Catalyst (`*-apple-ios-macabi`)
+#[must_use]
+pub fn is_mac_catalyst(target: &Target) -> bool {
+    target.os == "ios" && target.env.as_deref() == Some("macabi")
+}
+
+/// Oldest iOS version rustc builds `target` for, unless
+/// `IPHONEOS_DEPLOYMENT_TARGET` says otherwise
+#[must_use]
+pub fn default_deployment_target(target: &Target) -> &'static str {
+    match (target.arch.as_str(), target.env.as_deref()) {
+        ("aarch64", Some("sim" | "macabi")) => "14.0",
+        ("x86_64", Some("macabi")) => "13.1",
+        _ => "10.0",
+    }
+}
+
+/// clang's `-target` for an iOS target (e.g., `arm64-apple-ios14.0-simulator`)
+#[must_use]
+pub fn clang_target(target: &Target, deployment_target: &str) -> String {
+    let arch = match target.triple_arch() {
+        "aarch64" => "arm64",
+        other => other,
+    };
+    let suffix = if is_mac_catalyst(target) {
+        "-macabi"
+    } else if is_simulator(target) {
+        "-simulator"
+    } else {
+        ""
+    };
+    format!("{arch}-apple-ios{deployment_target}{suffix}")
+}
+
+/// Variables pointing rustc and C compilers at the SDK in `sdk_path`
+///
+/// `CFLAGS_<triple>` and `CXXFLAGS_<triple>` get `-target` and `-isysroot`
+/// ahead of any flags in `env` for the target or in `CFLAGS`/`CXXFLAGS`.
+#[must_use]
+pub fn environment<S: BuildHasher>(
+    target: &Target,
+    sdk_path: &std::path::Path,
+    env: &HashMap<String, String, S>,
+) -> Vec<(String, String)> {
+    let deployment_target = env
+        .get("IPHONEOS_DEPLOYMENT_TARGET")
+        .map_or_else(|| default_deployment_target(target), String::as_str);
+    let flags = format!(
+        "-target {} -isysroot {}",
+        clang_target(target, deployment_target),
+        sdk_path.display()
+    );
+    let suffix = target.triple.replace('-', "_");
+
+    let mut vars = vec![("SDKROOT".to_string(), sdk_path.display().to_string())];
+    for name in ["CFLAGS", "CXXFLAGS"] {
+        let key = format!("{name}_{suffix}");
+        let value = match env.get(&key).or_else(|| env.get(name)) {
+            Some(existing) if !existing.is_empty() => format!("{flags} {existing}"),
+            _ => flags.clone(),
+        };
+        vars.push((key, value));
+    }
+    vars
+}
+
+/// An iOS simulator device from `xcrun simctl`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Simulator {
+    /// Device name (e.g., "iPhone 15")
+    pub name: String,
+    /// Device identifier
+    pub udid: String,
+    /// "Booted", "Shutdown", ...
+    pub state: String,
+    /// Whether its runtime is installed
+    #[serde(default)]
+    pub is_available: bool,
+}
+
+impl Simulator {
+    /// Check if the device is running
+    #[must_use]
+    pub fn is_booted(&self) -> bool {
+        self.state == "Booted"
+    }
+
+    /// Cargo runner executing test binaries on this device
+    #[must_use]
+    pub fn runner(&self) -> String {
+        format!("xcrun simctl spawn {}", self.udid)
+    }
+
+    /// Start the device unless it is running
+    pub fn boot(&self) -> Result<()> {
+        if self.is_booted() {
+            return Ok(());
+        }
+        let output = Command::new("xcrun")
+            .args(["simctl", "boot", &self.udid])
+            .output()
+            .map_err(|e| Error::Toolchain(format!("Failed to run xcrun simctl: {e}")))?;
+        if !output.status.success() {
+            return Err(Error::Toolchain(format!(
+                "Failed to boot simulator {}: {}",
+                self.name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// iOS simulator devices with an installed runtime
+pub fn list_simulators() -> Result<Vec<Simulator>> {
+    let output = Command::new("xcrun")
+        .args(["simctl", "list", "devices", "available", "--json"])
+        .output()
+        .map_err(|e| Error::Toolchain(format!("Failed to run xcrun simctl: {e}")))?;
+    if !output.status.success() {
+        return Err(Error::Toolchain(format!(
+            "xcrun simctl list failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    parse_simctl_devices(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse `xcrun simctl list devices --json` output, keeping iOS devices
+pub fn parse_simctl_devices(json: &str) -> Result<Vec<Simulator>> {
+    #[derive(Deserialize)]
+    struct Devices {
+        devices: HashMap<String, Vec<Simulator>>,
+    }
+
+    let devices: Devices = serde_json::from_str(json)
+        .map_err(|e| Error::Toolchain(format!("Failed to parse simctl output: {e}")))?;
+    let mut runtimes: Vec<(String, Vec<Simulator>)> = devices
+        .devices
+        .into_iter()
+        .filter(|(runtime, _)| runtime.contains("SimRuntime.iOS"))
+        .collect();
+    // Newest runtime first
+    runtimes.sort_by(|(a, _), (b, _)| b.cmp(a));
+    Ok(runtimes
+        .into_iter()
+        .flat_map(|(_, devices)| devices)
+        .filter(|device| device.is_available)
+        .collect())
+}
+
+/// Device to run tests on: `preferred` (a name or UDID), else a running
+/// device, else an iPhone
+#[must_use]
+pub fn choose_simulator<'a>(
+    devices: &'a [Simulator],
+    preferred: Option<&str>,
+) -> Option<&'a Simulator> {
+    if let Some(preferred) = preferred {
+        let matching = |device: &&Simulator| device.udid == preferred || device.name == preferred;
+        return devices
+            .iter()
+            .filter(matching)
+            .find(|device| device.is_booted())
+            .or_else(|| devices.iter().find(matching));
+    }
+    devices
+        .iter()
+        .find(|device| device.is_booted())
+        .or_else(|| {
+            devices
+                .iter()
+                .find(|device| device.name.starts_with("iPhone"))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(triple: &str) -> Target {
+        Target::from_triple(triple).unwrap()
+    }
+
+    #[test]
+    fn test_sdk_for_target() {
+        let sdk = |triple: &str| AppleSdk::for_target(&target(triple));
+        assert_eq!(sdk("aarch64-apple-ios"), Some(AppleSdk::IPhoneOs));
+        assert_eq!(
+            sdk("aarch64-apple-ios-sim"),
+            Some(AppleSdk::IPhoneSimulator)
+        );
+        assert_eq!(sdk("x86_64-apple-ios"), Some(AppleSdk::IPhoneSimulator));
+        assert_eq!(sdk("aarch64-apple-ios-macabi"), Some(AppleSdk::MacOsx));
+        assert_eq!(sdk("x86_64-apple-ios-macabi"), Some(AppleSdk::MacOsx));
+        assert_eq!(sdk("aarch64-apple-darwin"), None);
+        assert_eq!(AppleSdk::IPhoneSimulator.name(), "iphonesimulator");
+    }
+
+    #[test]
+    fn test_clang_target() {
+        let clang = |triple: &str| {
+            let target = target(triple);
+            clang_target(&target, default_deployment_target(&target))
+        };
+        assert_eq!(clang("aarch64-apple-ios"), "arm64-apple-ios10.0");
+        assert_eq!(
+            clang("aarch64-apple-ios-sim"),
+            "arm64-apple-ios14.0-simulator"
+        );
+        assert_eq!(clang("x86_64-apple-ios"), "x86_64-apple-ios10.0-simulator");
+        assert_eq!(
+            clang("x86_64-apple-ios-macabi"),
+            "x86_64-apple-ios13.1-macabi"
+        );
+    }
+
+    #[test]
+    fn test_environment() {
+        let sim = target("aarch64-apple-ios-sim");
+        let sdk = std::path::Path::new("/Xcode/iPhoneSimulator.sdk");
+        let mut env = HashMap::new();
+        env.insert("IPHONEOS_DEPLOYMENT_TARGET".to_string(), "15.0".to_string());
+        env.insert("CFLAGS".to_string(), "-O2".to_string());
+
+        let vars: HashMap<String, String> = environment(&sim, sdk, &env).into_iter().collect();
+        assert_eq!(vars["SDKROOT"], "/Xcode/iPhoneSimulator.sdk");
+        assert_eq!(
+            vars["CFLAGS_aarch64_apple_ios_sim"],
+            "-target arm64-apple-ios15.0-simulator -isysroot /Xcode/iPhoneSimulator.sdk -O2"
+        );
+        assert_eq!(
+            vars["CXXFLAGS_aarch64_apple_ios_sim"],
+            "-target arm64-apple-ios15.0-simulator -isysroot /Xcode/iPhoneSimulator.sdk"
+        );
+    }
+
+    #[test]
+    fn test_parse_and_choose_simulator() {
+        let json = r#"{"devices": {
+            "com.apple.CoreSimulator.SimRuntime.iOS-17-2": [
+                {"name": "iPad Air", "udid": "A", "state": "Shutdown", "isAvailable": true},
+                {"name": "iPhone 15", "udid": "B", "state": "Shutdown", "isAvailable": true}
+            ],
+            "com.apple.CoreSimulator.SimRuntime.iOS-16-4": [
+                {"name": "iPhone 14", "udid": "C", "state": "Booted", "isAvailable": true},
+                {"name": "iPhone 8", "udid": "D", "state": "Shutdown", "isAvailable": false}
+            ],
+            "com.apple.CoreSimulator.SimRuntime.watchOS-10-2": [
+                {"name": "Apple Watch", "udid": "E", "state": "Booted", "isAvailable": true}
+            ]
+        }}"#;
+        let devices = parse_simctl_devices(json).unwrap();
+        let udids: Vec<&str> = devices.iter().map(|d| d.udid.as_str()).collect();
+        assert_eq!(udids, ["A", "B", "C"]);
+
+        assert_eq!(choose_simulator(&devices, None).unwrap().udid, "C");
+        assert_eq!(
+            choose_simulator(&devices, Some("iPhone 15")).unwrap().udid,
+            "B"
+        );
+        assert_eq!(
+            choose_simulator(&devices, Some("A")).unwrap().name,
+            "iPad Air"
+        );
+        assert!(choose_simulator(&devices, Some("iPhone 99")).is_none());
+        assert_eq!(
+            choose_simulator(&devices[..2], None).unwrap().name,
+            "iPhone 15"
+        );
+        assert_eq!(devices[1].runner(), "xcrun simctl spawn B");
+    }
+}
@@ -0,0 +1,322 @@
+//! Cargo version detection and feature gating
+//!
+//! Some cargo features xcargo passes through (`--config` on the command
+//! line, the sparse registry protocol, `strip` in profiles) only exist in
+//! newer cargo releases. The active cargo's version is probed once per
+//! toolchain and checked against what a build uses, so an old toolchain
+//! fails early with an upgrade suggestion instead of a confusing cargo
+//! error.
+
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+
+/// A cargo release version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CargoVersion {
+    /// Major version
+    pub major: u32,
+    /// Minor version
+    pub minor: u32,
+    /// Patch version
+    pub patch: u32,
+}
+
+impl CargoVersion {
+    /// Create a version
+    #[must_use]
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parse `cargo --version` output (e.g., "cargo 1.80.0 (376290515 2024-07-16)")
+    ///
+    /// Pre-release suffixes such as `-nightly` are ignored.
+    #[must_use]
+    pub fn parse(output: &str) -> Option<Self> {
+        let version = output
+            .trim()
+            .strip_prefix("cargo ")?
+            .split_whitespace()
+            .next()?;
+        let version = version.split('-').next()?;
+        let mut parts = version.split('.').map(str::parse::<u32>);
+
+        Some(Self::new(
+            parts.next()?.ok()?,
+            parts.next()?.ok()?,
+            parts.next().and_then(std::result::Result::ok).unwrap_or(0),
+        ))
+    }
+}
+
+impl fmt::Display for CargoVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// A cargo feature that needs a minimum cargo version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    /// `strip` in `[profile.*]`
+    ProfileStrip,
+    /// `--timings`
+    Timings,
+    /// `--config KEY=VALUE` on the command line
+    ConfigCli,
+    /// The sparse registry protocol
+    SparseIndex,
+    /// The `[lints]` table in Cargo.toml
+    LintsTable,
+    /// `--keep-going`
+    KeepGoing,
+}
+
+impl Capability {
+    /// All known capabilities
+    pub const ALL: &'static [Capability] = &[
+        Capability::ProfileStrip,
+        Capability::Timings,
+        Capability::ConfigCli,
+        Capability::SparseIndex,
+        Capability::LintsTable,
+        Capability::KeepGoing,
+    ];
+
+    /// First cargo version with this feature on stable
+    #[must_use]
+    pub fn min_version(self) -> CargoVersion {
+        match self {
+            Capability::ProfileStrip => CargoVersion::new(1, 59, 0),
+            Capability::Timings => CargoVersion::new(1, 60, 0),
+            Capability::ConfigCli => CargoVersion::new(1, 63, 0),
+            Capability::SparseIndex => CargoVersion::new(1, 68, 0),
+            Capability::LintsTable | Capability::KeepGoing => CargoVersion::new(1, 74, 0),
+        }
+    }
+
+    /// Human-readable feature name
+    #[must_use]
+    pub fn description(self) -> &'static str {
+        match self {
+            Capability::ProfileStrip => "`strip` in Cargo.toml profiles",
+            Capability::Timings => "`--timings`",
+            Capability::ConfigCli => "`--config` on the command line",
+            Capability::SparseIndex => "the sparse registry protocol",
+            Capability::LintsTable => "the `[lints]` table in Cargo.toml",
+            Capability::KeepGoing => "`--keep-going`",
+        }
+    }
+
+    /// Capabilities used by cargo arguments, the manifest and the environment
+    ///
+    /// `manifest` is the contents of Cargo.toml, if there is one; `env`
+    /// looks up environment variables.
+    pub fn required_by<F>(args: &[String], manifest: Option<&str>, env: F) -> Vec<Capability>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut used = Vec::new();
+
+        for arg in args {
+            let capability = match arg.split('=').next().unwrap_or(arg) {
+                "--config" => Capability::ConfigCli,
+                "--timings" => Capability::Timings,
+                "--keep-going" => Capability::KeepGoing,
+                _ => continue,
+            };
+            if !used.contains(&capability) {
+                used.push(capability);
+            }
+        }
+
+        if let Some(manifest) = manifest.and_then(|m| m.parse::<toml::Table>().ok()) {
+            let strips = manifest
+                .get("profile")
+                .and_then(toml::Value::as_table)
+                .is_some_and(|profiles| {
+                    profiles
+                        .values()
+                        .any(|profile| profile.get("strip").is_some())
+                });
+            if strips {
+                used.push(Capability::ProfileStrip);
+            }
+
+            let lints = manifest.contains_key("lints")
+                || manifest
+                    .get("workspace")
+                    .is_some_and(|workspace| workspace.get("lints").is_some());
+            if lints {
+                used.push(Capability::LintsTable);
+            }
+        }
+
+        if env("CARGO_REGISTRIES_CRATES_IO_PROTOCOL").as_deref() == Some("sparse") {
+            used.push(Capability::SparseIndex);
+        }
+
+        used.sort_by_key(|capability| capability.min_version());
+        used
+    }
+}
+
+/// What the active cargo supports
+#[derive(Debug, Clone)]
+pub struct CargoCapabilities {
+    /// Toolchain the version was probed for (`None` = active)
+    toolchain: Option<String>,
+    /// Detected version; `None` if it could not be determined
+    version: Option<CargoVersion>,
+}
+
+impl CargoCapabilities {
+    /// Probe cargo's version for `toolchain`, at most once per process
+    #[must_use]
+    pub fn detect(toolchain: Option<&str>) -> Self {
+        static PROBED: OnceLock<Mutex<HashMap<Option<String>, Option<CargoVersion>>>> =
+            OnceLock::new();
+
+        let key = toolchain.map(str::to_string);
+        let mut probed = PROBED
+            .get_or_init(Mutex::default)
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let version = *probed
+            .entry(key.clone())
+            .or_insert_with(|| Self::probe(toolchain));
+
+        Self {
+            toolchain: key,
+            version,
+        }
+    }
+
+    /// Capabilities of a known cargo version
+    #[must_use]
+    pub fn from_version(version: CargoVersion) -> Self {
+        Self {
+            toolchain: None,
+            version: Some(version),
+        }
+    }
+
+    fn probe(toolchain: Option<&str>) -> Option<CargoVersion> {
+        let mut cmd = Command::new("cargo");
+        if let Some(toolchain) = toolchain {
+            cmd.arg(format!("+{toolchain}"));
+        }
+        let output = cmd.arg("--version").output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        CargoVersion::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Detected cargo version
+    #[must_use]
+    pub fn version(&self) -> Option<CargoVersion> {
+        self.version
+    }
+
+    /// Check if cargo supports `capability`
+    ///
+    /// When the version is unknown, everything is assumed to be supported
+    /// and cargo gets to report problems itself.
+    #[must_use]
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.version
+            .map_or(true, |version| version >= capability.min_version())
+    }
+
+    /// Fail with an upgrade suggestion if `capability` is not supported
+    pub fn require(&self, capability: Capability) -> Result<()> {
+        match self.version {
+            Some(found) if !self.supports(capability) => Err(Error::CargoTooOld {
+                feature: capability.description().to_string(),
+                required: capability.min_version().to_string(),
+                found: found.to_string(),
+                upgrade_hint: format!(
+                    "Upgrade with: rustup update {}",
+                    self.toolchain.as_deref().unwrap_or("stable")
+                ),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check every capability in `used`, failing on the first unsupported one
+    pub fn require_all(&self, used: &[Capability]) -> Result<()> {
+        used.iter()
+            .try_for_each(|capability| self.require(*capability))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(
+            CargoVersion::parse("cargo 1.80.0 (376290515 2024-07-16)\n"),
+            Some(CargoVersion::new(1, 80, 0))
+        );
+        assert_eq!(
+            CargoVersion::parse("cargo 1.83.0-nightly (ad074abe3 2024-10-04)"),
+            Some(CargoVersion::new(1, 83, 0))
+        );
+        assert_eq!(CargoVersion::parse("rustc 1.80.0"), None);
+    }
+
+    #[test]
+    fn test_required_by() {
+        let args = vec![
+            "--config=build.jobs=2".to_string(),
+            "--keep-going".to_string(),
+        ];
+        let manifest = "[package]\nname = \"app\"\n\n[profile.release]\nstrip = true\n";
+        let used = Capability::required_by(&args, Some(manifest), |_| None);
+        assert_eq!(
+            used,
+            vec![
+                Capability::ProfileStrip,
+                Capability::ConfigCli,
+                Capability::KeepGoing
+            ]
+        );
+
+        let used = Capability::required_by(&[], None, |key| {
+            (key == "CARGO_REGISTRIES_CRATES_IO_PROTOCOL").then(|| "sparse".to_string())
+        });
+        assert_eq!(used, vec![Capability::SparseIndex]);
+    }
+
+    #[test]
+    fn test_require() {
+        let old = CargoCapabilities::from_version(CargoVersion::new(1, 70, 0));
+        assert!(old.supports(Capability::SparseIndex));
+        match old.require(Capability::LintsTable) {
+            Err(Error::CargoTooOld {
+                required, found, ..
+            }) => {
+                assert_eq!(required, "1.74.0");
+                assert_eq!(found, "1.70.0");
+            }
+            other => panic!("expected CargoTooOld, got {other:?}"),
+        }
+
+        let unknown = CargoCapabilities {
+            toolchain: None,
+            version: None,
+        };
+        assert!(unknown.require(Capability::KeepGoing).is_ok());
+    }
+}
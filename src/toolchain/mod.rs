@@ -3,8 +3,13 @@
 //! This module handles Rust toolchain detection, installation, and management
 //! through rustup integration.
 
+pub mod apple;
+pub mod cargo;
+pub mod msvc;
+#[cfg(feature = "zig")]
 pub mod zig;
 use crate::error::{Error, Result};
+use crate::installs::{self, Installed};
 use crate::target::Target;
 use std::process::Command;
 use std::str;
@@ -15,13 +20,133 @@ pub struct Toolchain {
     /// Toolchain name (e.g., "stable", "nightly", "1.70.0")
     pub name: String,
 
-    /// Whether this is the default/active toolchain
+    /// Whether this is the default toolchain
     pub is_default: bool,
 
+    /// Whether this toolchain is active here (the default, or an override
+    /// such as `rust-toolchain.toml`); only rustup 1.28 and later say so
+    pub is_active: bool,
+
     /// Installed targets for this toolchain
     pub targets: Vec<String>,
 }
 
+/// Rust sources, needed to rebuild the standard library with `-Zbuild-std`
+pub const RUST_SRC: &str = "rust-src";
+
+/// LLVM tools behind `rust-objcopy`, `rust-strip` and the other
+/// cargo-binutils commands
+pub const LLVM_TOOLS: &str = "llvm-tools";
+
+/// cargo-binutils commands that run a tool from [`LLVM_TOOLS`]
+const BINUTILS: &[&str] = &[
+    "rust-objcopy",
+    "rust-strip",
+    "rust-objdump",
+    "rust-nm",
+    "rust-size",
+    "rust-readobj",
+    "rust-ar",
+];
+
+/// A rustup component of a toolchain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Component {
+    /// Component name, with the host triple for host-specific components
+    /// (e.g., "rust-src", "llvm-tools-x86_64-unknown-linux-gnu")
+    pub name: String,
+
+    /// Whether it is installed
+    pub installed: bool,
+}
+
+impl Component {
+    /// Whether this is `component`, which names host-specific components
+    /// without their triple (e.g., "llvm-tools")
+    #[must_use]
+    pub fn is(&self, component: &str) -> bool {
+        self.name == component
+            || self
+                .name
+                .strip_prefix(component)
+                .and_then(|rest| rest.strip_prefix('-'))
+                .is_some_and(|triple| triple.split('-').count() >= 3)
+    }
+}
+
+/// Parse `rustup toolchain list` output, whose markers are "(default)"
+/// in older rustup and e.g. "(active, default)" in newer
+fn parse_toolchains(output: &str) -> Vec<Toolchain> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (name, markers) = line.split_once(' ').unwrap_or((line, ""));
+            let marked = |marker: &str| {
+                markers
+                    .trim_matches(|c| c == '(' || c == ')' || c == ' ')
+                    .split(", ")
+                    .any(|m| m == marker)
+            };
+            Toolchain {
+                name: name.to_string(),
+                is_default: marked("default"),
+                is_active: marked("active"),
+                targets: Vec::new(), // Will be populated if needed
+            }
+        })
+        .collect()
+}
+
+/// Parse `rustup component list` output
+fn parse_components(output: &str) -> Vec<Component> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let installed = line.ends_with("(installed)") || line.ends_with("(default)");
+            let name = line.split_whitespace().next().unwrap_or(line);
+            Component {
+                name: name.to_string(),
+                installed,
+            }
+        })
+        .collect()
+}
+
+/// Whether cargo arguments rebuild the standard library (`-Zbuild-std`)
+pub fn uses_build_std<'a>(args: impl IntoIterator<Item = &'a String>) -> bool {
+    let mut previous_z = false;
+    for arg in args {
+        if arg.starts_with("-Zbuild-std") || (previous_z && arg.starts_with("build-std")) {
+            return true;
+        }
+        previous_z = arg == "-Z";
+    }
+    false
+}
+
+/// Whether a command runs a cargo-binutils tool (`rust-objcopy ...` or
+/// `cargo objcopy ...`), which needs [`LLVM_TOOLS`]
+#[must_use]
+pub fn uses_llvm_tools(command: &[String]) -> bool {
+    let program = |arg: &String| {
+        std::path::Path::new(arg)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    match command {
+        [first, ..] if BINUTILS.contains(&program(first).as_str()) => true,
+        [first, second, ..] if program(first) == "cargo" => {
+            BINUTILS.contains(&format!("rust-{second}").as_str())
+        }
+        _ => false,
+    }
+}
+
 /// Toolchain manager for rustup operations
 pub struct ToolchainManager {
     /// Path to rustup binary
@@ -97,24 +222,23 @@ impl ToolchainManager {
         let stdout = str::from_utf8(&output.stdout)
             .map_err(|e| Error::Toolchain(format!("Invalid UTF-8 in rustup output: {e}")))?;
 
-        let mut toolchains = Vec::new();
-        for line in stdout.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
+        Ok(parse_toolchains(stdout))
+    }
 
-            let is_default = line.contains("(default)");
-            let name = line.replace("(default)", "").trim().to_string();
+    /// Make `toolchain` the default (`rustup default`)
+    pub fn set_default_toolchain(&self, toolchain: &str) -> Result<()> {
+        let output = Command::new(&self.rustup_path)
+            .args(["default", toolchain])
+            .output()
+            .map_err(|e| Error::Toolchain(format!("Failed to set default toolchain: {e}")))?;
 
-            toolchains.push(Toolchain {
-                name,
-                is_default,
-                targets: Vec::new(), // Will be populated if needed
-            });
+        if !output.status.success() {
+            let stderr = str::from_utf8(&output.stderr).unwrap_or("<invalid UTF-8>");
+            return Err(Error::Toolchain(format!(
+                "Failed to make '{toolchain}' the default toolchain: {stderr}"
+            )));
         }
-
-        Ok(toolchains)
+        Ok(())
     }
 
     /// Get the default/active toolchain
@@ -169,6 +293,9 @@ impl ToolchainManager {
 
     /// Install a target for a specific toolchain
     ///
+    /// The target is recorded for `xcargo uninstall`, so only call this
+    /// when it is missing (see [`Self::ensure_target`]).
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -198,6 +325,23 @@ impl ToolchainManager {
         }
 
         helpers::success(format!("Installed target {target}"));
+        installs::record(Installed::target(target, toolchain));
+        Ok(())
+    }
+
+    /// Remove a target from a toolchain
+    pub fn remove_target(&self, toolchain: &str, target: &str) -> Result<()> {
+        let output = Command::new(&self.rustup_path)
+            .args(["target", "remove", target, "--toolchain", toolchain])
+            .output()
+            .map_err(|e| Error::Toolchain(format!("Failed to remove target: {e}")))?;
+
+        if !output.status.success() {
+            let stderr = str::from_utf8(&output.stderr).unwrap_or("<invalid UTF-8>");
+            return Err(Error::Toolchain(format!(
+                "Failed to remove target '{target}' from toolchain '{toolchain}': {stderr}"
+            )));
+        }
         Ok(())
     }
 
@@ -211,6 +355,8 @@ impl ToolchainManager {
 
     /// Install a toolchain if not already installed
     ///
+    /// The toolchain is recorded for `xcargo uninstall`.
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -240,6 +386,23 @@ impl ToolchainManager {
         }
 
         helpers::success(format!("Installed toolchain {toolchain}"));
+        installs::record(Installed::toolchain(toolchain));
+        Ok(())
+    }
+
+    /// Uninstall a toolchain
+    pub fn uninstall_toolchain(&self, toolchain: &str) -> Result<()> {
+        let output = Command::new(&self.rustup_path)
+            .args(["toolchain", "uninstall", toolchain])
+            .output()
+            .map_err(|e| Error::Toolchain(format!("Failed to uninstall toolchain: {e}")))?;
+
+        if !output.status.success() {
+            let stderr = str::from_utf8(&output.stderr).unwrap_or("<invalid UTF-8>");
+            return Err(Error::Toolchain(format!(
+                "Failed to uninstall toolchain '{toolchain}': {stderr}"
+            )));
+        }
         Ok(())
     }
 
@@ -257,6 +420,82 @@ impl ToolchainManager {
         self.install_toolchain(toolchain)
     }
 
+    /// List the components of a toolchain, installed or available
+    pub fn list_components(&self, toolchain: &str) -> Result<Vec<Component>> {
+        let output = Command::new(&self.rustup_path)
+            .args(["component", "list", "--toolchain", toolchain])
+            .output()
+            .map_err(|e| Error::Toolchain(format!("Failed to list components: {e}")))?;
+
+        if !output.status.success() {
+            return Err(Error::Toolchain(format!(
+                "Failed to list components for toolchain '{toolchain}'"
+            )));
+        }
+
+        let stdout = str::from_utf8(&output.stdout)
+            .map_err(|e| Error::Toolchain(format!("Invalid UTF-8 in rustup output: {e}")))?;
+        Ok(parse_components(stdout))
+    }
+
+    /// Check if a component is installed for a toolchain
+    pub fn is_component_installed(&self, toolchain: &str, component: &str) -> Result<bool> {
+        let components = self.list_components(toolchain)?;
+        Ok(components.iter().any(|c| c.installed && c.is(component)))
+    }
+
+    /// Install a component (e.g., `rust-src`) for a toolchain
+    ///
+    /// The component is recorded for `xcargo uninstall`, so only call this
+    /// when it is missing (see [`Self::ensure_component`]).
+    pub fn install_component(&self, toolchain: &str, component: &str) -> Result<()> {
+        use crate::output::helpers;
+
+        helpers::progress(format!(
+            "Installing component {component} for toolchain {toolchain}"
+        ));
+
+        let output = Command::new(&self.rustup_path)
+            .args(["component", "add", component, "--toolchain", toolchain])
+            .output()
+            .map_err(|e| Error::Toolchain(format!("Failed to install component: {e}")))?;
+
+        if !output.status.success() {
+            let stderr = str::from_utf8(&output.stderr).unwrap_or("<invalid UTF-8>");
+            return Err(Error::Toolchain(format!(
+                "Failed to install component '{component}' for toolchain '{toolchain}': {stderr}"
+            )));
+        }
+
+        helpers::success(format!("Installed component {component}"));
+        installs::record(Installed::component(component, toolchain));
+        Ok(())
+    }
+
+    /// Remove a component from a toolchain
+    pub fn remove_component(&self, toolchain: &str, component: &str) -> Result<()> {
+        let output = Command::new(&self.rustup_path)
+            .args(["component", "remove", component, "--toolchain", toolchain])
+            .output()
+            .map_err(|e| Error::Toolchain(format!("Failed to remove component: {e}")))?;
+
+        if !output.status.success() {
+            let stderr = str::from_utf8(&output.stderr).unwrap_or("<invalid UTF-8>");
+            return Err(Error::Toolchain(format!(
+                "Failed to remove component '{component}' from toolchain '{toolchain}': {stderr}"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Ensure a component is installed, installing it if necessary
+    pub fn ensure_component(&self, toolchain: &str, component: &str) -> Result<()> {
+        if self.is_component_installed(toolchain, component)? {
+            return Ok(());
+        }
+        self.install_component(toolchain, component)
+    }
+
     /// Prepare environment for cross-compilation to a target
     ///
     /// This ensures:
@@ -335,6 +574,57 @@ impl ToolchainManager {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_toolchains() {
+        let toolchains = parse_toolchains(
+            "stable-x86_64-unknown-linux-gnu (active, default)\nnightly-x86_64-unknown-linux-gnu\n",
+        );
+        assert_eq!(toolchains[0].name, "stable-x86_64-unknown-linux-gnu");
+        assert!(toolchains[0].is_default && toolchains[0].is_active);
+        assert_eq!(toolchains[1].name, "nightly-x86_64-unknown-linux-gnu");
+        assert!(!toolchains[1].is_default && !toolchains[1].is_active);
+
+        // Older rustup marks only the default
+        let toolchains = parse_toolchains("stable-aarch64-apple-darwin (default)\n");
+        assert!(toolchains[0].is_default && !toolchains[0].is_active);
+    }
+
+    #[test]
+    fn test_parse_components() {
+        let components = parse_components(
+            "cargo-x86_64-unknown-linux-gnu (installed)\n\
+             llvm-tools-x86_64-unknown-linux-gnu\n\
+             rust-src (installed)\n\
+             rust-std-x86_64-unknown-linux-gnu (default)\n",
+        );
+        assert_eq!(components.len(), 4);
+        assert!(components[0].installed);
+        assert!(!components[1].installed);
+        assert!(components[1].is(LLVM_TOOLS));
+        assert!(components[2].is(RUST_SRC));
+        assert!(!components[2].is("rust"));
+        assert!(components[3].installed);
+    }
+
+    #[test]
+    fn test_uses_build_std() {
+        let args = |list: &[&str]| list.iter().map(|a| (*a).to_string()).collect::<Vec<_>>();
+        assert!(uses_build_std(&args(&["-Zbuild-std=core,alloc"])));
+        assert!(uses_build_std(&args(&["-Z", "build-std"])));
+        assert!(!uses_build_std(&args(&["-Z", "timings"])));
+        assert!(!uses_build_std(&args(&["--release"])));
+    }
+
+    #[test]
+    fn test_uses_llvm_tools() {
+        let command = |list: &[&str]| list.iter().map(|a| (*a).to_string()).collect::<Vec<_>>();
+        assert!(uses_llvm_tools(&command(&["rust-objcopy", "-O", "binary"])));
+        assert!(uses_llvm_tools(&command(&["/usr/bin/rust-strip", "x"])));
+        assert!(uses_llvm_tools(&command(&["cargo", "objcopy", "--"])));
+        assert!(!uses_llvm_tools(&command(&["objcopy", "{artifact}"])));
+        assert!(!uses_llvm_tools(&command(&["cargo", "build"])));
+    }
+
     #[test]
     fn test_find_rustup() {
         // This test will only pass if rustup is installed
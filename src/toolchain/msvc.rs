@@ -0,0 +1,213 @@
+//! Native MSVC environment detection for Windows hosts
+//!
+//! MSVC targets need `link.exe`, the CRT and the Windows SDK on `PATH`,
+//! `LIB` and `INCLUDE`. Outside a "Developer Command Prompt" those are not
+//! set, so xcargo finds Visual Studio / Build Tools via `vswhere` and
+//! imports the environment produced by `vcvarsall.bat`.
+
+use crate::error::{Error, Result};
+use crate::target::Target;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// x64/x86 C++ build tools component
+pub const COMPONENT_VC_TOOLS: &str = "Microsoft.VisualStudio.Component.VC.Tools.x86.x64";
+/// ARM64 C++ build tools component
+pub const COMPONENT_VC_TOOLS_ARM64: &str = "Microsoft.VisualStudio.Component.VC.Tools.ARM64";
+
+/// A Visual Studio or Build Tools installation
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VsInstallation {
+    /// Installation root
+    pub installation_path: PathBuf,
+    /// Version (e.g., "17.9.34607.119")
+    pub installation_version: String,
+    /// Product name (e.g., "Visual Studio Build Tools 2022")
+    #[serde(default)]
+    pub display_name: String,
+}
+
+impl VsInstallation {
+    /// Path to `vcvarsall.bat`
+    #[must_use]
+    pub fn vcvarsall(&self) -> PathBuf {
+        self.installation_path
+            .join("VC")
+            .join("Auxiliary")
+            .join("Build")
+            .join("vcvarsall.bat")
+    }
+
+    /// Import the environment `vcvarsall.bat` sets up for `target`
+    pub fn environment(&self, host: &Target, target: &Target) -> Result<HashMap<String, String>> {
+        let arch = vcvars_arch(&host.arch, &target.arch).ok_or_else(|| {
+            Error::Toolchain(format!(
+                "No MSVC tools for {} on a {} host",
+                target.triple, host.arch
+            ))
+        })?;
+
+        let output = Command::new("cmd")
+            .arg("/C")
+            .arg(format!(
+                "\"{}\" {arch} >nul && set",
+                self.vcvarsall().display()
+            ))
+            .output()
+            .map_err(|e| Error::Toolchain(format!("Failed to run vcvarsall.bat: {e}")))?;
+
+        if !output.status.success() {
+            return Err(Error::Toolchain(format!(
+                "vcvarsall.bat {arch} failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(parse_set_output(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+/// Locate `vswhere.exe` in its fixed install location or `PATH`
+#[must_use]
+pub fn find_vswhere() -> Option<PathBuf> {
+    std::env::var_os("ProgramFiles(x86)")
+        .map(|dir| {
+            Path::new(&dir)
+                .join("Microsoft Visual Studio")
+                .join("Installer")
+                .join("vswhere.exe")
+        })
+        .filter(|path| path.is_file())
+        .or_else(|| which::which("vswhere").ok())
+}
+
+/// Find the newest installation that has all `components`
+pub fn find_installation(components: &[&str]) -> Result<Option<VsInstallation>> {
+    let Some(vswhere) = find_vswhere() else {
+        return Ok(None);
+    };
+
+    let mut cmd = Command::new(vswhere);
+    cmd.args(["-latest", "-products", "*", "-format", "json", "-utf8"]);
+    for component in components {
+        cmd.args(["-requires", component]);
+    }
+
+    let output = cmd
+        .output()
+        .map_err(|e| Error::Toolchain(format!("Failed to run vswhere: {e}")))?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    Ok(parse_vswhere(&String::from_utf8_lossy(&output.stdout))?
+        .into_iter()
+        .next())
+}
+
+/// Locate the Windows 10/11 SDK library directory
+#[must_use]
+pub fn find_windows_sdk() -> Option<PathBuf> {
+    let dir = Path::new(&std::env::var_os("ProgramFiles(x86)")?)
+        .join("Windows Kits")
+        .join("10")
+        .join("Lib");
+    dir.is_dir().then_some(dir)
+}
+
+/// Check if the MSVC environment is already active (Developer Command Prompt)
+#[must_use]
+pub fn environment_active() -> bool {
+    std::env::var_os("VCINSTALLDIR").is_some()
+}
+
+/// Components a target needs in addition to the x64 tools
+#[must_use]
+pub fn required_components(target: &Target) -> Vec<&'static str> {
+    let mut components = vec![COMPONENT_VC_TOOLS];
+    if target.arch == "aarch64" {
+        components.push(COMPONENT_VC_TOOLS_ARM64);
+    }
+    components
+}
+
+/// `vcvarsall.bat` architecture argument for a host/target pair
+#[must_use]
+pub fn vcvars_arch(host_arch: &str, target_arch: &str) -> Option<&'static str> {
+    match (host_arch, target_arch) {
+        ("x86_64", "x86_64") => Some("x64"),
+        ("x86_64", "x86") => Some("x64_x86"),
+        ("x86_64", "aarch64") => Some("x64_arm64"),
+        ("aarch64", "aarch64") => Some("arm64"),
+        ("aarch64", "x86_64") => Some("arm64_x64"),
+        ("aarch64", "x86") => Some("arm64_x86"),
+        ("x86", "x86") => Some("x86"),
+        ("x86", "x86_64") => Some("x86_x64"),
+        _ => None,
+    }
+}
+
+/// Parse `vswhere -format json` output
+pub fn parse_vswhere(json: &str) -> Result<Vec<VsInstallation>> {
+    serde_json::from_str(json)
+        .map_err(|e| Error::Toolchain(format!("Failed to parse vswhere output: {e}")))
+}
+
+/// Parse `set` output (`NAME=value` lines) into a map
+#[must_use]
+pub fn parse_set_output(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .filter(|(key, _)| !key.is_empty())
+        .map(|(key, value)| (key.to_string(), value.trim_end_matches('\r').to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vcvars_arch() {
+        assert_eq!(vcvars_arch("x86_64", "x86_64"), Some("x64"));
+        assert_eq!(vcvars_arch("x86_64", "aarch64"), Some("x64_arm64"));
+        assert_eq!(vcvars_arch("aarch64", "aarch64"), Some("arm64"));
+        assert_eq!(vcvars_arch("x86_64", "x86"), Some("x64_x86"));
+        assert_eq!(vcvars_arch("x86_64", "riscv64"), None);
+    }
+
+    #[test]
+    fn test_parse_vswhere() {
+        let json = r#"[{
+            "installationPath": "C:\\Program Files (x86)\\Microsoft Visual Studio\\2022\\BuildTools",
+            "installationVersion": "17.9.34607.119",
+            "displayName": "Visual Studio Build Tools 2022"
+        }]"#;
+        let installs = parse_vswhere(json).unwrap();
+        assert_eq!(installs.len(), 1);
+        assert_eq!(installs[0].installation_version, "17.9.34607.119");
+        assert!(installs[0].vcvarsall().ends_with("vcvarsall.bat"));
+    }
+
+    #[test]
+    fn test_parse_set_output() {
+        let env = parse_set_output(
+            "INCLUDE=C:\\VC\\include;C:\\SDK\\ucrt\r\nPath=C:\\VC\\bin\r\n=C:=C:\\\r\n",
+        );
+        assert_eq!(env.get("INCLUDE").unwrap(), "C:\\VC\\include;C:\\SDK\\ucrt");
+        assert_eq!(env.get("Path").unwrap(), "C:\\VC\\bin");
+        assert_eq!(env.len(), 2);
+    }
+
+    #[test]
+    fn test_required_components() {
+        let arm = Target::from_triple("aarch64-pc-windows-msvc").unwrap();
+        assert!(required_components(&arm).contains(&COMPONENT_VC_TOOLS_ARM64));
+        let x64 = Target::from_triple("x86_64-pc-windows-msvc").unwrap();
+        assert_eq!(required_components(&x64), vec![COMPONENT_VC_TOOLS]);
+    }
+}
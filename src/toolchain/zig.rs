@@ -1,8 +1,21 @@
 //! Zig-based cross-compilation support
-
+//!
+//! For `*-linux-gnu` targets Zig can link against a specific glibc
+//! version (`-target x86_64-linux-gnu.2.17`), so binaries run on older
+//! distributions without building in a container.
+//!
+//! Cargo and cc-rs run the compiler through wrapper scripts in
+//! `~/.xcargo/zig-wrappers`, which fix up what raw `zig cc` gets wrong
+//! the way cargo-zigbuild does: cc-rs's `--target=<rust triple>` is
+//! dropped, `-lgcc_eh` becomes Zig's libunwind for `*-windows-gnu`, musl's
+//! self-contained CRT objects give way to Zig's own, and macOS links see
+//! the SDK's frameworks through `SDKROOT`.
+
+use crate::build::glibc::GlibcVersion;
 use crate::error::{Error, Result};
 use crate::target::Target;
 use std::collections::HashMap;
+use std::fmt::Write as _;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -91,16 +104,18 @@ impl ZigToolchain {
             "i686-unknown-linux-gnu" => true,
             "arm-unknown-linux-gnueabihf" => true,
 
-            // musl targets (known issues with static linking)
-            "x86_64-unknown-linux-musl" => true, // Works but may have duplicate symbol issues
+            // musl targets, linked with Zig's CRT rather than rustc's
+            "x86_64-unknown-linux-musl" => true,
             "aarch64-unknown-linux-musl" => true,
 
             // Windows targets
             "x86_64-pc-windows-gnu" => true,
             "i686-pc-windows-gnu" => true,
 
-            // macOS targets (not supported - Zig can't build for macOS on non-macOS)
-            triple if triple.contains("apple-darwin") => false,
+            // macOS targets, with an SDK for the frameworks to link against
+            "x86_64-apple-darwin" | "aarch64-apple-darwin" => {
+                cfg!(target_os = "macos") || std::env::var_os("SDKROOT").is_some()
+            }
 
             // WebAssembly (may work but untested)
             triple if triple.contains("wasm32") => false,
@@ -128,6 +143,20 @@ impl ZigToolchain {
         Self::supports_target_name(&target.triple)
     }
 
+    /// Get the Zig target for a Rust target, optionally pinned to a glibc version
+    ///
+    /// The glibc version is only applied to `*-linux-gnu*` targets.
+    #[must_use]
+    pub fn zig_target(target: &Target, glibc: Option<GlibcVersion>) -> Option<String> {
+        let zig_target = Self::zig_target_for_rust_target(target)?;
+        Some(match glibc {
+            Some(version) if zig_target.contains("-linux-gnu") => {
+                format!("{zig_target}.{version}")
+            }
+            _ => zig_target,
+        })
+    }
+
     /// Get the Zig target triple for a Rust target
     ///
     /// Converts Rust target triple to Zig target triple format
@@ -142,6 +171,8 @@ impl ZigToolchain {
             "i686-unknown-linux-gnu" => Some("i386-linux-gnu".to_string()),
             "x86_64-pc-windows-gnu" => Some("x86_64-windows-gnu".to_string()),
             "i686-pc-windows-gnu" => Some("i686-windows-gnu".to_string()),
+            "x86_64-apple-darwin" => Some("x86_64-macos".to_string()),
+            "aarch64-apple-darwin" => Some("aarch64-macos".to_string()),
             _ => None,
         }
     }
@@ -151,8 +182,12 @@ impl ZigToolchain {
     /// Creates executable wrapper scripts that invoke `zig cc -target <target>` and `zig ar`.
     /// These wrappers are needed because Cargo expects a single executable path for CC/AR,
     /// not a command with arguments.
-    pub fn create_wrappers(&self, target: &Target) -> Result<HashMap<String, PathBuf>> {
-        let zig_target = Self::zig_target_for_rust_target(target).ok_or_else(|| {
+    pub fn create_wrappers(
+        &self,
+        target: &Target,
+        glibc: Option<GlibcVersion>,
+    ) -> Result<HashMap<String, PathBuf>> {
+        let zig_target = Self::zig_target(target, glibc).ok_or_else(|| {
             Error::Toolchain(format!("Target {} not supported by Zig", target.triple))
         })?;
 
@@ -164,11 +199,13 @@ impl ZigToolchain {
         let mut wrappers = HashMap::new();
 
         // Create CC wrapper
-        let cc_wrapper_path = self.cache_dir.join(format!("{}-cc", target.triple));
+        // Name by Zig target so wrappers for different glibc versions coexist
+        let cc_wrapper_path = self.cache_dir.join(format!("{zig_target}-cc"));
+        // Batch files can't rewrite arguments, so the fixes need a POSIX shell
         let cc_wrapper_content = if cfg!(windows) {
             format!("@echo off\nzig cc -target {zig_target} %*\n")
         } else {
-            format!("#!/bin/sh\nexec zig cc -target {zig_target} \"$@\"\n")
+            Self::cc_script(&self.zig_path, target, &zig_target)
         };
 
         fs::write(&cc_wrapper_path, cc_wrapper_content)
@@ -220,10 +257,53 @@ impl ZigToolchain {
         Ok(wrappers)
     }
 
+    /// The `zig cc` wrapper for `target`, as a POSIX shell script
+    ///
+    /// Arguments raw `zig cc` rejects or links wrongly are rewritten before
+    /// Zig sees them.
+    fn cc_script(zig: &Path, target: &Target, zig_target: &str) -> String {
+        // cc-rs passes rustc's triple; `-target` below gives Zig its own
+        let mut fixes = vec!["--target=*) ;;"];
+        if target.os == "windows" && target.env.as_deref() == Some("gnu") {
+            // Zig has no libgcc; its libunwind provides the unwinder
+            fixes.push("-lgcc_eh|-lgcc_s) set -- \"$@\" -lunwind ;;");
+            fixes.push("-lgcc|-l:libpthread.a|-Wl,--disable-auto-image-base) ;;");
+        }
+        if target.env.as_deref() == Some("musl") {
+            // Zig links its own CRT; rustc's would define the symbols twice
+            fixes.push("*/self-contained/crt*.o) ;;");
+        }
+        let darwin = target.os == "macos";
+        if darwin {
+            // `-target` picks the architecture
+            fixes.push("-arch) skip=1 ;;");
+        }
+
+        let mut script = format!(
+            "#!/bin/sh\n# zig cc for {zig_target}, generated by xcargo\nskip=\nfor arg do\n    shift\n    if [ -n \"$skip\" ]; then\n        skip=\n        continue\n    fi\n    case \"$arg\" in\n"
+        );
+        for fix in fixes {
+            let _ = writeln!(script, "        {fix}");
+        }
+        script.push_str("        *) set -- \"$@\" \"$arg\" ;;\n    esac\ndone\n");
+        if darwin {
+            script.push_str(
+                "sdk=\"${SDKROOT:-$(xcrun --sdk macosx --show-sdk-path 2>/dev/null)}\"\nif [ -n \"$sdk\" ]; then\n    set -- --sysroot=\"$sdk\" -F\"$sdk/System/Library/Frameworks\" -L\"$sdk/usr/lib\" -I\"$sdk/usr/include\" \"$@\"\nfi\n",
+            );
+        }
+        let _ = writeln!(
+            script,
+            "exec \"{}\" cc -target {zig_target} \"$@\"",
+            zig.display()
+        );
+        script
+    }
+
     /// Get environment variables for cross-compiling to a target
     ///
     /// Returns a `HashMap` of environment variables that should be set when
-    /// cross-compiling to the target using Zig.
+    /// cross-compiling to the target using Zig. With `glibc`, `*-linux-gnu`
+    /// binaries link against that glibc version.
     ///
     /// # Examples
     ///
@@ -234,7 +314,7 @@ impl ZigToolchain {
     /// # fn example() -> xcargo::Result<()> {
     /// let zig = ZigToolchain::detect()?.expect("Zig not found");
     /// let target = Target::from_triple("x86_64-unknown-linux-gnu")?;
-    /// let env = zig.environment_for_target(&target)?;
+    /// let env = zig.environment_for_target(&target, None)?;
     ///
     /// for (key, value) in env {
     ///     println!("{}={}", key, value.display());
@@ -242,7 +322,11 @@ impl ZigToolchain {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn environment_for_target(&self, target: &Target) -> Result<HashMap<String, PathBuf>> {
+    pub fn environment_for_target(
+        &self,
+        target: &Target,
+        glibc: Option<GlibcVersion>,
+    ) -> Result<HashMap<String, PathBuf>> {
         if !self.supports_target(target) {
             return Err(Error::Toolchain(format!(
                 "Target {} is not supported by Zig",
@@ -251,7 +335,7 @@ impl ZigToolchain {
         }
 
         // Create wrapper scripts
-        let wrappers = self.create_wrappers(target)?;
+        let wrappers = self.create_wrappers(target, glibc)?;
 
         let mut env = HashMap::new();
 
@@ -288,7 +372,7 @@ impl ZigToolchain {
     #[must_use]
     pub fn info(&self) -> String {
         format!(
-            "Zig {} ({})\nSupports: Linux (x86_64, aarch64, armv7), Windows (x86_64, i686)\nLimitations: musl may have linking issues, macOS/wasm not supported",
+            "Zig {} ({})\nSupports: Linux (x86_64, aarch64, armv7), Windows (x86_64, i686), macOS with an SDK in SDKROOT\nLimitations: wasm not supported",
             self.version,
             self.zig_path.display()
         )
@@ -315,9 +399,11 @@ mod tests {
             let target = Target::from_triple("x86_64-unknown-linux-gnu").unwrap();
             assert!(zig.supports_target(&target));
 
-            // macOS targets should not be supported (on non-macOS hosts)
+            // macOS targets need an SDK
             let target = Target::from_triple("x86_64-apple-darwin").unwrap();
-            assert!(!zig.supports_target(&target));
+            if !cfg!(target_os = "macos") && std::env::var_os("SDKROOT").is_none() {
+                assert!(!zig.supports_target(&target));
+            }
         }
     }
 
@@ -332,11 +418,98 @@ mod tests {
         assert_eq!(zig_target, Some("aarch64-linux-gnu".to_string()));
     }
 
+    #[test]
+    fn test_zig_target_with_glibc() {
+        let glibc = GlibcVersion::parse("2.17");
+        let target = Target::from_triple("x86_64-unknown-linux-gnu").unwrap();
+        assert_eq!(
+            ZigToolchain::zig_target(&target, glibc),
+            Some("x86_64-linux-gnu.2.17".to_string())
+        );
+
+        let target = Target::from_triple("armv7-unknown-linux-gnueabihf").unwrap();
+        assert_eq!(
+            ZigToolchain::zig_target(&target, glibc),
+            Some("arm-linux-gnueabihf.2.17".to_string())
+        );
+
+        // musl and Windows have no glibc
+        let target = Target::from_triple("x86_64-unknown-linux-musl").unwrap();
+        assert_eq!(
+            ZigToolchain::zig_target(&target, glibc),
+            Some("x86_64-linux-musl".to_string())
+        );
+        let target = Target::from_triple("x86_64-pc-windows-gnu").unwrap();
+        assert_eq!(
+            ZigToolchain::zig_target(&target, glibc),
+            Some("x86_64-windows-gnu".to_string())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cc_wrapper_fixes_arguments() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::tempdir().unwrap();
+        let zig_path = temp.path().join("zig");
+        fs::write(&zig_path, "#!/bin/sh\necho \"$@\"\n").unwrap();
+        fs::set_permissions(&zig_path, fs::Permissions::from_mode(0o755)).unwrap();
+        let zig = ZigToolchain {
+            zig_path,
+            version: "0.13.0".to_string(),
+            cache_dir: temp.path().join("zig-wrappers"),
+        };
+        let run = |triple: &str, args: &[&str]| {
+            let target = Target::from_triple(triple).unwrap();
+            let wrappers = zig.create_wrappers(&target, None).unwrap();
+            let output = Command::new(&wrappers["CC"])
+                .args(args)
+                .env_remove("SDKROOT")
+                .output()
+                .unwrap();
+            String::from_utf8(output.stdout).unwrap().trim().to_string()
+        };
+
+        assert_eq!(
+            run(
+                "x86_64-pc-windows-gnu",
+                &[
+                    "--target=x86_64-pc-windows-gnu",
+                    "main.o",
+                    "-lgcc_eh",
+                    "-lgcc",
+                    "-lkernel32"
+                ]
+            ),
+            "cc -target x86_64-windows-gnu main.o -lunwind -lkernel32"
+        );
+        assert_eq!(
+            run(
+                "x86_64-unknown-linux-musl",
+                &[
+                    "/sysroot/lib/self-contained/crt1.o",
+                    "main.o",
+                    "-o",
+                    "app with space"
+                ]
+            ),
+            "cc -target x86_64-linux-musl main.o -o app with space"
+        );
+        assert_eq!(
+            run(
+                "aarch64-apple-darwin",
+                &["-arch", "arm64", "-framework", "Security"]
+            ),
+            "cc -target aarch64-macos -framework Security"
+        );
+    }
+
     #[test]
     fn test_create_wrappers() {
         if let Ok(Some(zig)) = ZigToolchain::detect() {
             let target = Target::from_triple("x86_64-unknown-linux-gnu").unwrap();
-            let wrappers = zig.create_wrappers(&target);
+            let wrappers = zig.create_wrappers(&target, None);
 
             if wrappers.is_ok() {
                 let wrappers = wrappers.unwrap();
@@ -0,0 +1,528 @@
+//! Build capture and replay
+//!
+//! `xcargo --record trace.json build ...` captures everything that went into
+//! a build: the environment, tool versions, every external command xcargo
+//! ran (cargo, container runs, downloads) and hashes of downloaded files.
+//! `xcargo replay trace.json` runs the same commands again, verbatim, in
+//! the recorded environment, which helps pin down nondeterministic
+//! failures.
+//!
+//! Recording is process-global: commands are only captured between
+//! [`start`] and [`finish`].
+
+use crate::error::{Error, Result};
+use crate::output::helpers;
+use crate::state;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Trace format version
+const TRACE_VERSION: u32 = 1;
+
+/// Placeholder stored instead of secret environment values
+pub const REDACTED: &str = "<redacted>";
+
+/// Parts of variable names whose values are never written to a trace
+const SECRET_MARKERS: &[&str] = &[
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "CREDENTIAL",
+    "API_KEY",
+    "PRIVATE_KEY",
+    "ACCESS_KEY",
+    // Proxy URLs may carry a user and password
+    "PROXY",
+];
+
+/// Tools whose versions are captured, with the argument that prints it
+const TOOLS: &[(&str, &str)] = &[
+    ("rustc", "--version"),
+    ("cargo", "--version"),
+    ("rustup", "--version"),
+    ("zig", "version"),
+    ("docker", "--version"),
+    ("podman", "--version"),
+    ("sccache", "--version"),
+];
+
+/// The active recording, if any
+static RECORDER: Mutex<Option<BuildTrace>> = Mutex::new(None);
+
+/// An external command xcargo ran
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TracedCommand {
+    /// Program name or path
+    pub program: String,
+    /// Arguments
+    pub args: Vec<String>,
+    /// Variables set (`Some`) or removed (`None`) on top of the trace env
+    pub env: Vec<(String, Option<String>)>,
+    /// Working directory, if different from the trace's
+    pub cwd: Option<PathBuf>,
+    /// Exit code (`None` if killed by a signal or not started)
+    pub exit_code: Option<i32>,
+}
+
+/// A file xcargo downloaded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TracedDownload {
+    /// Source URL
+    pub url: String,
+    /// Where the file was saved
+    pub path: PathBuf,
+    /// SHA-256 of the file, hex encoded
+    pub sha256: String,
+}
+
+/// Everything needed to inspect and replay a build
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildTrace {
+    /// Trace format version
+    pub version: u32,
+    /// xcargo version that recorded the trace
+    pub xcargo_version: String,
+    /// Unix timestamp of the recording
+    pub created: u64,
+    /// Host platform (`os-arch`)
+    pub host: String,
+    /// Working directory
+    pub cwd: PathBuf,
+    /// xcargo command line
+    pub args: Vec<String>,
+    /// Process environment, with secrets redacted
+    pub env: BTreeMap<String, String>,
+    /// Tool versions (e.g., "rustc" => "rustc 1.80.0 (...)")
+    pub tools: BTreeMap<String, String>,
+    /// External commands, in execution order
+    pub commands: Vec<TracedCommand>,
+    /// Downloaded files
+    pub downloads: Vec<TracedDownload>,
+}
+
+impl BuildTrace {
+    /// Capture the current environment and tool versions
+    #[must_use]
+    pub fn capture(args: Vec<String>) -> Self {
+        Self {
+            version: TRACE_VERSION,
+            xcargo_version: env!("CARGO_PKG_VERSION").to_string(),
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            host: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+            cwd: std::env::current_dir().unwrap_or_default(),
+            args,
+            env: redact_env(std::env::vars()),
+            tools: tool_versions(),
+            commands: Vec::new(),
+            downloads: Vec::new(),
+        }
+    }
+
+    /// Load a trace from a JSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let trace: Self = serde_json::from_str(&contents)
+            .map_err(|e| Error::Config(format!("Failed to parse trace {}: {e}", path.display())))?;
+        if trace.version > TRACE_VERSION {
+            return Err(Error::Config(format!(
+                "Trace version {} is newer than supported ({TRACE_VERSION}), update xcargo",
+                trace.version
+            )));
+        }
+        Ok(trace)
+    }
+
+    /// Write the trace as JSON
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Config(format!("Failed to serialize trace: {e}")))?;
+        state::write_atomic(path, json)
+    }
+
+    /// Tools whose version differs from the current system, as
+    /// `(tool, recorded, current)`
+    #[must_use]
+    pub fn tool_differences(
+        &self,
+        current: &BTreeMap<String, String>,
+    ) -> Vec<(String, String, String)> {
+        let missing = "not installed".to_string();
+        self.tools
+            .keys()
+            .chain(current.keys())
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .filter_map(|tool| {
+                let recorded = self.tools.get(tool).unwrap_or(&missing);
+                let now = current.get(tool).unwrap_or(&missing);
+                (recorded != now).then(|| (tool.clone(), recorded.clone(), now.clone()))
+            })
+            .collect()
+    }
+
+    /// Build the command to replay `traced` in the recorded environment
+    ///
+    /// Redacted variables take their value from the current environment.
+    #[must_use]
+    pub fn replay_command(&self, traced: &TracedCommand) -> Command {
+        let mut cmd = Command::new(&traced.program);
+        cmd.args(&traced.args).env_clear();
+
+        for (key, value) in &self.env {
+            if value == REDACTED {
+                if let Ok(current) = std::env::var(key) {
+                    cmd.env(key, current);
+                }
+            } else {
+                cmd.env(key, value);
+            }
+        }
+        for (key, value) in &traced.env {
+            match value {
+                Some(value) => cmd.env(key, value),
+                None => cmd.env_remove(key),
+            };
+        }
+        cmd.current_dir(traced.cwd.as_ref().unwrap_or(&self.cwd));
+        cmd
+    }
+
+    /// Run every recorded command again, reporting exit codes that differ
+    ///
+    /// Returns the number of commands whose exit code changed.
+    pub fn replay(&self) -> Result<usize> {
+        let mut mismatches = 0;
+        for (idx, traced) in self.commands.iter().enumerate() {
+            helpers::progress(format!(
+                "[{}/{}] {}",
+                idx + 1,
+                self.commands.len(),
+                traced.display()
+            ));
+
+            let status = self
+                .replay_command(traced)
+                .status()
+                .map_err(|e| Error::Build(format!("Failed to run {}: {e}", traced.program)))?;
+
+            if status.code() == traced.exit_code {
+                helpers::success(format!(
+                    "exit code {} (as recorded)",
+                    code_str(status.code())
+                ));
+            } else {
+                mismatches += 1;
+                helpers::warning(format!(
+                    "exit code {}, recorded {}",
+                    code_str(status.code()),
+                    code_str(traced.exit_code)
+                ));
+            }
+        }
+        Ok(mismatches)
+    }
+}
+
+impl TracedCommand {
+    /// Capture a command and its outcome
+    #[must_use]
+    pub fn from_command(cmd: &Command, exit_code: Option<i32>) -> Self {
+        Self {
+            program: cmd.get_program().to_string_lossy().into_owned(),
+            args: cmd
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+            env: cmd
+                .get_envs()
+                .map(|(key, value)| {
+                    let key = key.to_string_lossy().into_owned();
+                    let value = value.map(|v| {
+                        if is_secret(&key) {
+                            REDACTED.to_string()
+                        } else {
+                            v.to_string_lossy().into_owned()
+                        }
+                    });
+                    (key, value)
+                })
+                .collect(),
+            cwd: cmd.get_current_dir().map(Path::to_path_buf),
+            exit_code,
+        }
+    }
+
+    /// Every variable the command runs with: this process's environment
+    /// with the command's changes applied, secrets redacted
+    #[must_use]
+    pub fn full_env(&self) -> BTreeMap<String, String> {
+        self.env_over(std::env::vars())
+    }
+
+    fn env_over(&self, base: impl Iterator<Item = (String, String)>) -> BTreeMap<String, String> {
+        let mut env = redact_env(base);
+        for (key, value) in &self.env {
+            match value {
+                Some(value) => env.insert(key.clone(), value.clone()),
+                None => env.remove(key),
+            };
+        }
+        env
+    }
+
+    /// Shell-like rendering for display
+    #[must_use]
+    pub fn display(&self) -> String {
+        std::iter::once(self.program.as_str())
+            .chain(self.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Start recording; later commands are captured until [`finish`]
+pub fn start(args: Vec<String>) {
+    let trace = BuildTrace::capture(args);
+    *RECORDER
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = Some(trace);
+}
+
+/// Check if a recording is active
+#[must_use]
+pub fn is_recording() -> bool {
+    RECORDER
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .is_some()
+}
+
+/// Record an executed command, if recording
+pub fn record_command(cmd: &Command, exit_code: Option<i32>) {
+    let mut recorder = RECORDER
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    if let Some(trace) = recorder.as_mut() {
+        trace
+            .commands
+            .push(TracedCommand::from_command(cmd, exit_code));
+    }
+}
+
+/// Record a downloaded file and its hash, if recording
+pub fn record_download(url: &str, path: &Path) {
+    if !is_recording() {
+        return;
+    }
+    let Ok(sha256) = sha256_file(path) else {
+        return;
+    };
+
+    let mut recorder = RECORDER
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    if let Some(trace) = recorder.as_mut() {
+        trace.downloads.push(TracedDownload {
+            url: url.to_string(),
+            path: path.to_path_buf(),
+            sha256,
+        });
+    }
+}
+
+/// Stop recording and write the trace to `path`
+pub fn finish(path: &Path) -> Result<Option<BuildTrace>> {
+    let trace = RECORDER
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .take();
+    if let Some(trace) = &trace {
+        trace.save(path)?;
+    }
+    Ok(trace)
+}
+
+/// Versions of the build tools installed on this system
+#[must_use]
+pub fn tool_versions() -> BTreeMap<String, String> {
+    TOOLS
+        .iter()
+        .filter_map(|(tool, arg)| {
+            let path = which::which(tool).ok()?;
+            let output = Command::new(path).arg(arg).output().ok()?;
+            let version = String::from_utf8_lossy(&output.stdout);
+            let version = version.lines().next()?.trim();
+            (output.status.success() && !version.is_empty())
+                .then(|| ((*tool).to_string(), version.to_string()))
+        })
+        .collect()
+}
+
+/// SHA-256 of a file, hex encoded
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    let hex = hasher
+        .finalize()
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        });
+    Ok(hex)
+}
+
+/// Check if a variable name looks like it holds a secret
+pub(crate) fn is_secret(key: &str) -> bool {
+    let key = key.to_uppercase();
+    SECRET_MARKERS.iter().any(|marker| key.contains(marker))
+}
+
+/// Collect `vars`, replacing the values of secret ones with [`REDACTED`]
+pub(crate) fn redact_env(vars: impl Iterator<Item = (String, String)>) -> BTreeMap<String, String> {
+    vars.map(|(key, value)| {
+        let value = if is_secret(&key) {
+            REDACTED.to_string()
+        } else {
+            value
+        };
+        (key, value)
+    })
+    .collect()
+}
+
+fn code_str(code: Option<i32>) -> String {
+    code.map_or_else(|| "none".to_string(), |code| code.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_redact_env() {
+        let env = redact_env(
+            [
+                ("PATH".to_string(), "/usr/bin".to_string()),
+                ("GITHUB_TOKEN".to_string(), "ghp_abc".to_string()),
+                ("AWS_SECRET_ACCESS_KEY".to_string(), "xyz".to_string()),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(env["PATH"], "/usr/bin");
+        assert_eq!(env["GITHUB_TOKEN"], REDACTED);
+        assert_eq!(env["AWS_SECRET_ACCESS_KEY"], REDACTED);
+    }
+
+    #[test]
+    fn test_traced_command() {
+        let mut cmd = Command::new("cargo");
+        cmd.args(["build", "--target", "x86_64-pc-windows-gnu"])
+            .env("CARGO_TARGET_DIR", "/tmp/out")
+            .env("NPM_TOKEN", "secret")
+            .env_remove("RUSTFLAGS");
+
+        let traced = TracedCommand::from_command(&cmd, Some(101));
+        assert_eq!(
+            traced.display(),
+            "cargo build --target x86_64-pc-windows-gnu"
+        );
+        assert!(traced
+            .env
+            .contains(&("CARGO_TARGET_DIR".to_string(), Some("/tmp/out".to_string()))));
+        assert!(traced
+            .env
+            .contains(&("NPM_TOKEN".to_string(), Some(REDACTED.to_string()))));
+        assert!(traced.env.contains(&("RUSTFLAGS".to_string(), None)));
+    }
+
+    #[test]
+    fn test_traced_command_env_over_base() {
+        let mut cmd = Command::new("cargo");
+        cmd.env("CARGO_TARGET_DIR", "/tmp/out")
+            .env_remove("RUSTFLAGS");
+        let traced = TracedCommand::from_command(&cmd, None);
+
+        let env = traced.env_over(
+            [
+                ("PATH".to_string(), "/usr/bin".to_string()),
+                ("RUSTFLAGS".to_string(), "-Dwarnings".to_string()),
+                ("CARGO_REGISTRY_TOKEN".to_string(), "cio_abc".to_string()),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(env["PATH"], "/usr/bin");
+        assert_eq!(env["CARGO_TARGET_DIR"], "/tmp/out");
+        assert_eq!(env["CARGO_REGISTRY_TOKEN"], REDACTED);
+        assert!(!env.contains_key("RUSTFLAGS"));
+    }
+
+    #[test]
+    fn test_save_load_and_replay_command() {
+        let temp = TempDir::new().unwrap();
+        let mut trace = BuildTrace::capture(vec!["xcargo".to_string(), "build".to_string()]);
+        trace.env = BTreeMap::from([("LANG".to_string(), "C".to_string())]);
+
+        let mut cmd = Command::new("cargo");
+        cmd.arg("build").env("FOO", "bar");
+        trace
+            .commands
+            .push(TracedCommand::from_command(&cmd, Some(0)));
+
+        let path = temp.path().join("trace.json");
+        trace.save(&path).unwrap();
+        let loaded = BuildTrace::load(&path).unwrap();
+        assert_eq!(loaded, trace);
+
+        let replay = loaded.replay_command(&loaded.commands[0]);
+        let envs: Vec<_> = replay.get_envs().collect();
+        assert!(envs.contains(&(
+            std::ffi::OsStr::new("LANG"),
+            Some(std::ffi::OsStr::new("C"))
+        )));
+        assert!(envs.contains(&(
+            std::ffi::OsStr::new("FOO"),
+            Some(std::ffi::OsStr::new("bar"))
+        )));
+    }
+
+    #[test]
+    fn test_tool_differences() {
+        let mut trace = BuildTrace::capture(Vec::new());
+        trace.tools = BTreeMap::from([
+            ("rustc".to_string(), "rustc 1.80.0".to_string()),
+            ("zig".to_string(), "0.13.0".to_string()),
+        ]);
+        let current = BTreeMap::from([
+            ("rustc".to_string(), "rustc 1.81.0".to_string()),
+            ("zig".to_string(), "0.13.0".to_string()),
+        ]);
+
+        let diffs = trace.tool_differences(&current);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].0, "rustc");
+    }
+
+    #[test]
+    fn test_sha256_file() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("data");
+        fs::write(&path, b"abc").unwrap();
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
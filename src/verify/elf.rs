@@ -0,0 +1,386 @@
+//! ELF header inspection
+
+use super::{BinaryFormat, BinaryInfo, Bytes, Linkage, Section, Symbol};
+use crate::build::glibc;
+
+/// `PT_LOAD`: segment mapped into memory
+const PT_LOAD: u32 = 1;
+/// `PT_INTERP`: path of the dynamic loader
+const PT_INTERP: u32 = 3;
+/// `SHT_SYMTAB`: full symbol table (removed by stripping)
+const SHT_SYMTAB: u32 = 2;
+/// `SHT_DYNAMIC`: dynamic linking table
+const SHT_DYNAMIC: u32 = 6;
+/// `SHT_NOBITS`: section with no file contents (e.g., `.bss`)
+const SHT_NOBITS: u32 = 8;
+/// `DT_NEEDED`: name of a needed shared library
+const DT_NEEDED: u64 = 1;
+/// `STT_FUNC`: symbol type of functions
+const STT_FUNC: u8 = 2;
+
+/// Architecture for an `e_machine` value
+fn arch(machine: u16, is_64: bool) -> String {
+    let arch = match (machine, is_64) {
+        (3, _) => "x86",
+        (62, _) => "x86_64",
+        (40, _) => "arm",
+        (183, _) => "aarch64",
+        (8, false) => "mips",
+        (8, true) => "mips64",
+        (20, _) => "powerpc",
+        (21, _) => "powerpc64",
+        (22, _) => "s390x",
+        (43, _) => "sparc64",
+        (243, false) => "riscv32",
+        (243, true) => "riscv64",
+        (258, _) => "loongarch64",
+        _ => return format!("machine {machine}"),
+    };
+    arch.to_string()
+}
+
+/// Identification and section header table of an ELF file
+struct Elf<'a> {
+    bytes: Bytes<'a>,
+    is_64: bool,
+    shoff: usize,
+    shentsize: usize,
+    shnum: usize,
+    shstrndx: usize,
+}
+
+impl<'a> Elf<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        let is_64 = match data.get(4)? {
+            1 => false,
+            2 => true,
+            _ => return None,
+        };
+        let mut elf = Self {
+            bytes: Bytes::new(data, *data.get(5)? == 1),
+            is_64,
+            shoff: 0,
+            shentsize: 0,
+            shnum: 0,
+            shstrndx: 0,
+        };
+        let (shoff, table) = if is_64 { (0x28, 0x3a) } else { (0x20, 0x2e) };
+        elf.shoff = elf.word(shoff)?;
+        elf.shentsize = usize::from(elf.bytes.u16(table)?);
+        elf.shnum = usize::from(elf.bytes.u16(table + 2)?);
+        elf.shstrndx = usize::from(elf.bytes.u16(table + 4)?);
+        Some(elf)
+    }
+
+    /// Address-sized value at `offset`
+    fn word(&self, offset: usize) -> Option<usize> {
+        if self.is_64 {
+            self.bytes.offset64(offset)
+        } else {
+            self.bytes.offset32(offset)
+        }
+    }
+
+    /// Offset of the header of section `index`
+    fn header(&self, index: usize) -> Option<usize> {
+        self.shoff.checked_add(index.checked_mul(self.shentsize)?)
+    }
+
+    /// Type, file offset, size and linked section of section `index`
+    fn section(&self, index: usize) -> Option<(u32, usize, usize, usize)> {
+        let header = self.header(index)?;
+        let (offset, size, link) = if self.is_64 {
+            (
+                self.word(header + 0x18)?,
+                self.word(header + 0x20)?,
+                self.bytes.u32(header + 0x28)?,
+            )
+        } else {
+            (
+                self.word(header + 0x10)?,
+                self.word(header + 0x14)?,
+                self.bytes.u32(header + 0x18)?,
+            )
+        };
+        Some((
+            self.bytes.u32(header + 4)?,
+            offset,
+            size,
+            usize::try_from(link).ok()?,
+        ))
+    }
+}
+
+/// Read format, architecture, linkage and needed libraries
+pub(super) fn inspect(data: &[u8]) -> Option<BinaryInfo> {
+    let elf = Elf::parse(data)?;
+    let (bytes, is_64) = (elf.bytes, elf.is_64);
+    let word = |offset: usize| elf.word(offset);
+
+    let machine = bytes.u16(0x12)?;
+
+    // Program headers: a PT_INTERP entry means the dynamic loader runs it
+    let (phoff, phentsize, phnum) = if is_64 {
+        (word(0x20)?, bytes.u16(0x36)?, bytes.u16(0x38)?)
+    } else {
+        (word(0x1c)?, bytes.u16(0x2a)?, bytes.u16(0x2c)?)
+    };
+    let mut has_interp = false;
+    for index in 0..usize::from(phnum) {
+        let header = phoff.checked_add(index * usize::from(phentsize))?;
+        if bytes.u32(header)? == PT_INTERP {
+            has_interp = true;
+        }
+    }
+
+    // Section headers: DT_NEEDED entries of the dynamic section
+    let section = |index: usize| elf.section(index);
+
+    let mut libraries = Vec::new();
+    for index in 0..elf.shnum {
+        let (kind, offset, size, link) = section(index)?;
+        if kind != SHT_DYNAMIC {
+            continue;
+        }
+        let strtab = section(link)?.1;
+        let entry_size = if is_64 { 16 } else { 8 };
+        for entry in (offset..offset.checked_add(size)?).step_by(entry_size) {
+            let (tag, value) = if is_64 {
+                (bytes.u64(entry)?, bytes.offset64(entry + 8)?)
+            } else {
+                (u64::from(bytes.u32(entry)?), bytes.offset32(entry + 4)?)
+            };
+            match tag {
+                0 => break,
+                DT_NEEDED => libraries.push(bytes.c_str(strtab.checked_add(value)?)?.to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    // Section names live in the section header string table
+    let mut sections = Vec::new();
+    if elf.shnum > 0 {
+        let names = section(elf.shstrndx)?.1;
+        for index in 1..elf.shnum {
+            let header = elf.header(index)?;
+            let name = bytes.c_str(names.checked_add(bytes.offset32(header)?)?)?;
+            let (kind, _, size, _) = section(index)?;
+            let size = if kind == SHT_NOBITS { 0 } else { size };
+            sections.push(Section::new(name, u64::try_from(size).ok()?));
+        }
+    }
+
+    let linkage = if has_interp || !libraries.is_empty() {
+        Linkage::Dynamic
+    } else {
+        Linkage::Static
+    };
+    let min_os = glibc::required_versions(data)
+        .ok()
+        .and_then(|mut versions| versions.pop())
+        .map(|version| format!("glibc {version}"));
+
+    Some(BinaryInfo {
+        format: BinaryFormat::Elf,
+        archs: vec![arch(machine, is_64)],
+        linkage,
+        libraries,
+        min_os,
+        sections,
+    })
+}
+
+/// Smallest alignment of the loadable segments, the largest page size the
+/// binary can be mapped with; `None` without loadable segments
+pub(super) fn load_alignment(data: &[u8]) -> Option<u64> {
+    let elf = Elf::parse(data)?;
+    let bytes = elf.bytes;
+    let (phoff, phentsize, phnum) = if elf.is_64 {
+        (elf.word(0x20)?, bytes.u16(0x36)?, bytes.u16(0x38)?)
+    } else {
+        (elf.word(0x1c)?, bytes.u16(0x2a)?, bytes.u16(0x2c)?)
+    };
+
+    let mut alignment = None;
+    for index in 0..usize::from(phnum) {
+        let header = phoff.checked_add(index * usize::from(phentsize))?;
+        if bytes.u32(header)? != PT_LOAD {
+            continue;
+        }
+        let align = if elf.is_64 {
+            bytes.u64(header + 0x30)?
+        } else {
+            u64::from(bytes.u32(header + 0x1c)?)
+        };
+        alignment = Some(alignment.map_or(align, |min: u64| min.min(align)));
+    }
+    alignment
+}
+
+/// Read the defined functions of the symbol table with their sizes
+pub(super) fn functions(data: &[u8]) -> Option<Vec<Symbol>> {
+    let elf = Elf::parse(data)?;
+    let bytes = elf.bytes;
+    let entry_size = if elf.is_64 { 24 } else { 16 };
+
+    let mut functions = Vec::new();
+    for index in 0..elf.shnum {
+        let (kind, offset, size, link) = elf.section(index)?;
+        if kind != SHT_SYMTAB {
+            continue;
+        }
+        let names = elf.section(link)?.1;
+        for entry in (offset..offset.checked_add(size)?).step_by(entry_size) {
+            let (info, shndx, size) = if elf.is_64 {
+                (
+                    *data.get(entry + 4)?,
+                    bytes.u16(entry + 6)?,
+                    bytes.u64(entry + 16)?,
+                )
+            } else {
+                (
+                    *data.get(entry + 12)?,
+                    bytes.u16(entry + 14)?,
+                    u64::from(bytes.u32(entry + 8)?),
+                )
+            };
+            if info & 0xf != STT_FUNC || shndx == 0 || size == 0 {
+                continue;
+            }
+            let name = bytes.c_str(names.checked_add(bytes.offset32(entry)?)?)?;
+            functions.push(Symbol::new(name, size));
+        }
+    }
+    Some(functions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arch() {
+        assert_eq!(arch(62, true), "x86_64");
+        assert_eq!(arch(243, true), "riscv64");
+        assert_eq!(arch(243, false), "riscv32");
+        assert_eq!(arch(9999, true), "machine 9999");
+    }
+
+    #[test]
+    fn test_minimal_static_elf() {
+        // 64-bit little-endian aarch64 header with no program or section headers
+        let mut data = vec![0u8; 64];
+        data[..4].copy_from_slice(b"\x7fELF");
+        data[4] = 2;
+        data[5] = 1;
+        data[0x12..0x14].copy_from_slice(&183u16.to_le_bytes());
+
+        let info = inspect(&data).unwrap();
+        assert_eq!(info.archs, vec!["aarch64"]);
+        assert_eq!(info.linkage, Linkage::Static);
+        assert!(info.libraries.is_empty());
+        assert_eq!(info.min_os, None);
+        assert!(info.sections.is_empty());
+    }
+
+    #[test]
+    fn test_load_alignment() {
+        // Program headers at 0x40: PT_LOAD aligned to 16 KiB, PT_INTERP, and
+        // PT_LOAD aligned to 4 KiB
+        let mut data = vec![0u8; 0x40 + 3 * 56];
+        data[..4].copy_from_slice(b"\x7fELF");
+        data[4] = 2;
+        data[5] = 1;
+        data[0x20..0x28].copy_from_slice(&0x40u64.to_le_bytes());
+        data[0x36..0x38].copy_from_slice(&56u16.to_le_bytes());
+        data[0x38..0x3a].copy_from_slice(&2u16.to_le_bytes());
+        for (index, (kind, align)) in [(PT_LOAD, 0x4000u64), (PT_INTERP, 1), (PT_LOAD, 0x1000)]
+            .into_iter()
+            .enumerate()
+        {
+            let header = 0x40 + index * 56;
+            data[header..header + 4].copy_from_slice(&kind.to_le_bytes());
+            data[header + 0x30..header + 0x38].copy_from_slice(&align.to_le_bytes());
+        }
+        assert_eq!(load_alignment(&data), Some(0x4000));
+
+        data[0x38..0x3a].copy_from_slice(&3u16.to_le_bytes());
+        assert_eq!(load_alignment(&data), Some(0x1000));
+    }
+
+    #[test]
+    fn test_sections() {
+        // Section headers at 0x100: null, .text (0x40 bytes), .bss, .shstrtab
+        let names = b"\0.text\0.bss\0.shstrtab\0";
+        let mut data = vec![0u8; 0x200];
+        data[..4].copy_from_slice(b"\x7fELF");
+        data[4] = 2;
+        data[5] = 1;
+        data[0x12..0x14].copy_from_slice(&62u16.to_le_bytes());
+        data[0x28..0x30].copy_from_slice(&0x100u64.to_le_bytes());
+        data[0x3a..0x3c].copy_from_slice(&64u16.to_le_bytes());
+        data[0x3c..0x3e].copy_from_slice(&4u16.to_le_bytes());
+        data[0x3e..0x40].copy_from_slice(&3u16.to_le_bytes());
+        data[0x80..0x80 + names.len()].copy_from_slice(names);
+
+        let headers = [
+            (1u32, 1u32, 0x40u64, 0x40u64),
+            (7, 8, 0, 0x1000),
+            (12, 3, 0x80, 24),
+        ];
+        for (index, (name, kind, offset, size)) in headers.into_iter().enumerate() {
+            let header = 0x100 + (index + 1) * 64;
+            data[header..header + 4].copy_from_slice(&name.to_le_bytes());
+            data[header + 4..header + 8].copy_from_slice(&kind.to_le_bytes());
+            data[header + 0x18..header + 0x20].copy_from_slice(&offset.to_le_bytes());
+            data[header + 0x20..header + 0x28].copy_from_slice(&size.to_le_bytes());
+        }
+
+        let info = inspect(&data).unwrap();
+        assert_eq!(
+            info.sections,
+            vec![
+                Section::new(".text", 0x40),
+                Section::new(".bss", 0),
+                Section::new(".shstrtab", 24),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_functions() {
+        // Section headers at 0x100: null, .symtab (linked to 2), .strtab
+        let names = b"\0main\0data\0";
+        let mut data = vec![0u8; 0x200];
+        data[..4].copy_from_slice(b"\x7fELF");
+        data[4] = 2;
+        data[5] = 1;
+        data[0x28..0x30].copy_from_slice(&0x100u64.to_le_bytes());
+        data[0x3a..0x3c].copy_from_slice(&64u16.to_le_bytes());
+        data[0x3c..0x3e].copy_from_slice(&3u16.to_le_bytes());
+        data[0x80..0x80 + names.len()].copy_from_slice(names);
+
+        let symtab = 0x100 + 64;
+        data[symtab + 4..symtab + 8].copy_from_slice(&SHT_SYMTAB.to_le_bytes());
+        data[symtab + 0x18..symtab + 0x20].copy_from_slice(&0x40u64.to_le_bytes());
+        data[symtab + 0x20..symtab + 0x28].copy_from_slice(&48u64.to_le_bytes());
+        data[symtab + 0x28..symtab + 0x2c].copy_from_slice(&2u32.to_le_bytes());
+        let strtab = 0x100 + 128;
+        data[strtab + 0x18..strtab + 0x20].copy_from_slice(&0x80u64.to_le_bytes());
+
+        // A function in section 1 and a data object
+        for (index, (name, info, size)) in [(1u32, STT_FUNC, 0x90u64), (6, 1, 8)]
+            .into_iter()
+            .enumerate()
+        {
+            let entry = 0x40 + index * 24;
+            data[entry..entry + 4].copy_from_slice(&name.to_le_bytes());
+            data[entry + 4] = info;
+            data[entry + 6..entry + 8].copy_from_slice(&1u16.to_le_bytes());
+            data[entry + 16..entry + 24].copy_from_slice(&size.to_le_bytes());
+        }
+
+        assert_eq!(functions(&data).unwrap(), vec![Symbol::new("main", 0x90)]);
+    }
+}
@@ -0,0 +1,412 @@
+//! Mach-O header inspection, including universal (fat) binaries
+
+use super::{BinaryFormat, BinaryInfo, Bytes, Linkage, Section, Symbol};
+
+/// 32-bit and 64-bit thin magic numbers
+const MH_MAGIC: u32 = 0xfeed_face;
+const MH_MAGIC_64: u32 = 0xfeed_facf;
+/// Universal binary magic numbers (always big-endian)
+const FAT_MAGIC: u32 = 0xcafe_babe;
+const FAT_MAGIC_64: u32 = 0xcafe_babf;
+
+/// Load commands
+const LC_SEGMENT: u32 = 0x1;
+const LC_SYMTAB: u32 = 0x2;
+const LC_SEGMENT_64: u32 = 0x19;
+const LC_LOAD_DYLIB: u32 = 0xc;
+const LC_LOAD_DYLINKER: u32 = 0xe;
+const LC_VERSION_MIN_MACOSX: u32 = 0x24;
+const LC_VERSION_MIN_IPHONEOS: u32 = 0x25;
+const LC_BUILD_VERSION: u32 = 0x32;
+const LC_LOAD_WEAK_DYLIB: u32 = 0x8000_0018;
+const LC_REEXPORT_DYLIB: u32 = 0x8000_001f;
+/// Section types with no file contents (`S_ZEROFILL`, `S_GB_ZEROFILL`,
+/// `S_THREAD_LOCAL_ZEROFILL`)
+const ZEROFILL_TYPES: [u32; 3] = [0x1, 0xc, 0x12];
+/// `n_type` bits: debugging entry, and the type of symbols defined in a section
+const N_STAB: u8 = 0xe0;
+const N_SECT: u8 = 0xe;
+
+/// Check if `magic` (the first four bytes) starts a Mach-O file
+pub(super) fn is_macho(magic: &[u8]) -> bool {
+    let Ok(magic) = <[u8; 4]>::try_from(magic) else {
+        return false;
+    };
+    let le = u32::from_le_bytes(magic);
+    let be = u32::from_be_bytes(magic);
+    [MH_MAGIC, MH_MAGIC_64].contains(&le)
+        || [MH_MAGIC, MH_MAGIC_64, FAT_MAGIC, FAT_MAGIC_64].contains(&be)
+}
+
+/// Architecture for a `cputype` value
+fn arch(cputype: u32) -> String {
+    let arch = match cputype {
+        7 => "x86",
+        0x0100_0007 => "x86_64",
+        12 => "arm",
+        0x0100_000c => "aarch64",
+        0x0200_000c => "arm64_32",
+        _ => return format!("cputype {cputype:#x}"),
+    };
+    arch.to_string()
+}
+
+/// Read architectures, linked dylibs and the minimum OS version
+///
+/// For universal binaries, every slice's architecture is listed and the
+/// other details come from the first slice.
+pub(super) fn inspect(data: &[u8]) -> Option<BinaryInfo> {
+    let fat = Bytes::new(data, false);
+    match fat.u32(0)? {
+        magic @ (FAT_MAGIC | FAT_MAGIC_64) => {
+            // fat_arch: cputype, cpusubtype, offset, size, align (+ reserved)
+            let entry_size = if magic == FAT_MAGIC_64 { 32 } else { 20 };
+            let count = fat.offset32(4)?;
+            let mut slices = Vec::new();
+            for index in 0..count {
+                let entry = 8usize.checked_add(index.checked_mul(entry_size)?)?;
+                let offset = if magic == FAT_MAGIC_64 {
+                    fat.offset64(entry + 8)?
+                } else {
+                    fat.offset32(entry + 8)?
+                };
+                slices.push(inspect_thin(data.get(offset..)?)?);
+            }
+
+            let mut slices = slices.into_iter();
+            let mut info = slices.next()?;
+            info.archs.extend(slices.flat_map(|slice| slice.archs));
+            Some(info)
+        }
+        _ => inspect_thin(data),
+    }
+}
+
+fn inspect_thin(data: &[u8]) -> Option<BinaryInfo> {
+    let magic = <[u8; 4]>::try_from(data.get(..4)?).ok()?;
+    let little_endian = [MH_MAGIC, MH_MAGIC_64].contains(&u32::from_le_bytes(magic));
+    let bytes = Bytes::new(data, little_endian);
+    let is_64 = bytes.u32(0)? == MH_MAGIC_64;
+
+    let cputype = bytes.u32(4)?;
+    let commands = bytes.u32(16)?;
+    let mut command = if is_64 { 32 } else { 28 };
+
+    let mut libraries = Vec::new();
+    let mut has_dylinker = false;
+    let mut min_os = None;
+    let mut sections = Vec::new();
+    for _ in 0..commands {
+        let cmd = bytes.u32(command)?;
+        let size = bytes.offset32(command + 4)?;
+        match cmd {
+            LC_LOAD_DYLIB | LC_LOAD_WEAK_DYLIB | LC_REEXPORT_DYLIB => {
+                let name = command.checked_add(bytes.offset32(command + 8)?)?;
+                libraries.push(bytes.c_str(name)?.to_string());
+            }
+            LC_LOAD_DYLINKER => has_dylinker = true,
+            LC_SEGMENT | LC_SEGMENT_64 => {
+                segment(&bytes, command, cmd == LC_SEGMENT_64, &mut sections)?;
+            }
+            LC_BUILD_VERSION => {
+                let platform = match bytes.u32(command + 8)? {
+                    1 => "macOS",
+                    2 | 7 => "iOS",
+                    3 | 8 => "tvOS",
+                    4 | 9 => "watchOS",
+                    6 => "Mac Catalyst",
+                    11 | 12 => "visionOS",
+                    _ => "OS",
+                };
+                min_os = Some(format!("{platform} {}", version(bytes.u32(command + 12)?)));
+            }
+            LC_VERSION_MIN_MACOSX => {
+                min_os = Some(format!("macOS {}", version(bytes.u32(command + 8)?)));
+            }
+            LC_VERSION_MIN_IPHONEOS => {
+                min_os = Some(format!("iOS {}", version(bytes.u32(command + 8)?)));
+            }
+            _ => {}
+        }
+        if size == 0 {
+            return None;
+        }
+        command = command.checked_add(size)?;
+    }
+
+    let linkage = if has_dylinker || !libraries.is_empty() {
+        Linkage::Dynamic
+    } else {
+        Linkage::Static
+    };
+
+    Some(BinaryInfo {
+        format: BinaryFormat::MachO,
+        archs: vec![arch(cputype)],
+        linkage,
+        libraries,
+        min_os,
+        sections,
+    })
+}
+
+/// Add the sections of the segment command at `command`, or the segment
+/// itself when it has none (e.g., `__LINKEDIT`)
+fn segment(bytes: &Bytes, command: usize, is_64: bool, sections: &mut Vec<Section>) -> Option<()> {
+    let segname = bytes.fixed_str(command + 8, 16)?;
+    let (filesize, nsects, first, entry_size) = if is_64 {
+        (
+            bytes.u64(command + 48)?,
+            bytes.u32(command + 64)?,
+            command + 72,
+            80,
+        )
+    } else {
+        (
+            u64::from(bytes.u32(command + 36)?),
+            bytes.u32(command + 48)?,
+            command + 56,
+            68,
+        )
+    };
+
+    if nsects == 0 {
+        if filesize > 0 {
+            sections.push(Section::new(segname, filesize));
+        }
+        return Some(());
+    }
+
+    for index in 0..usize::try_from(nsects).ok()? {
+        let section = first.checked_add(index.checked_mul(entry_size)?)?;
+        let name = bytes.fixed_str(section, 16)?;
+        let (size, flags) = if is_64 {
+            (bytes.u64(section + 40)?, bytes.u32(section + 64)?)
+        } else {
+            (
+                u64::from(bytes.u32(section + 36)?),
+                bytes.u32(section + 56)?,
+            )
+        };
+        let size = if ZEROFILL_TYPES.contains(&(flags & 0xff)) {
+            0
+        } else {
+            size
+        };
+        sections.push(Section::new(format!("{segname},{name}"), size));
+    }
+    Some(())
+}
+
+/// Read the functions defined in `__text` sections with their sizes
+///
+/// For universal binaries, the first slice is read.
+pub(super) fn functions(data: &[u8]) -> Option<Vec<Symbol>> {
+    let fat = Bytes::new(data, false);
+    let data = match fat.u32(0)? {
+        FAT_MAGIC => data.get(fat.offset32(16)?..)?,
+        FAT_MAGIC_64 => data.get(fat.offset64(16)?..)?,
+        _ => data,
+    };
+
+    let magic = <[u8; 4]>::try_from(data.get(..4)?).ok()?;
+    let bytes = Bytes::new(
+        data,
+        [MH_MAGIC, MH_MAGIC_64].contains(&u32::from_le_bytes(magic)),
+    );
+    let is_64 = bytes.u32(0)? == MH_MAGIC_64;
+    let commands = bytes.u32(16)?;
+    let mut command = if is_64 { 32 } else { 28 };
+
+    // Sections are numbered from 1 across all segments
+    let mut section_ends = vec![0];
+    let mut text_sections = Vec::new();
+    let mut symtab = None;
+    for _ in 0..commands {
+        let cmd = bytes.u32(command)?;
+        match cmd {
+            LC_SEGMENT | LC_SEGMENT_64 => {
+                let (nsects, first, entry_size) = if is_64 {
+                    (bytes.u32(command + 64)?, command + 72, 80)
+                } else {
+                    (bytes.u32(command + 48)?, command + 56, 68)
+                };
+                for index in 0..usize::try_from(nsects).ok()? {
+                    let section = first.checked_add(index.checked_mul(entry_size)?)?;
+                    let (address, size) = if is_64 {
+                        (bytes.u64(section + 32)?, bytes.u64(section + 40)?)
+                    } else {
+                        (
+                            u64::from(bytes.u32(section + 32)?),
+                            u64::from(bytes.u32(section + 36)?),
+                        )
+                    };
+                    if bytes.fixed_str(section, 16)? == "__text" {
+                        text_sections.push(section_ends.len());
+                    }
+                    section_ends.push(address.checked_add(size)?);
+                }
+            }
+            LC_SYMTAB => {
+                symtab = Some((
+                    bytes.offset32(command + 8)?,
+                    bytes.offset32(command + 12)?,
+                    bytes.offset32(command + 16)?,
+                ));
+            }
+            _ => {}
+        }
+        let size = bytes.offset32(command + 4)?;
+        if size == 0 {
+            return None;
+        }
+        command = command.checked_add(size)?;
+    }
+
+    let Some((symoff, nsyms, stroff)) = symtab else {
+        return Some(Vec::new());
+    };
+    let entry_size = if is_64 { 16 } else { 12 };
+    let mut symbols = Vec::new();
+    for index in 0..nsyms {
+        let entry = symoff.checked_add(index.checked_mul(entry_size)?)?;
+        let kind = *data.get(entry + 4)?;
+        let section = usize::from(*data.get(entry + 5)?);
+        if kind & N_STAB != 0 || kind & N_SECT != N_SECT || !text_sections.contains(&section) {
+            continue;
+        }
+        let address = if is_64 {
+            bytes.u64(entry + 8)?
+        } else {
+            u64::from(bytes.u32(entry + 8)?)
+        };
+        let name = bytes.c_str(stroff.checked_add(bytes.offset32(entry)?)?)?;
+        // C-level names carry a leading underscore
+        let name = name.strip_prefix('_').unwrap_or(name);
+        symbols.push((name, section, address));
+    }
+    Some(super::sized_by_address(symbols, &section_ends))
+}
+
+/// Format a packed `xxxx.yy.zz` version
+fn version(packed: u32) -> String {
+    let (major, minor, patch) = (packed >> 16, (packed >> 8) & 0xff, packed & 0xff);
+    if patch == 0 {
+        format!("{major}.{minor}")
+    } else {
+        format!("{major}.{minor}.{patch}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 64-bit arm64 image linking libSystem, built for macOS 11.0
+    fn sample() -> Vec<u8> {
+        let mut data = vec![0u8; 32];
+        data[..4].copy_from_slice(&MH_MAGIC_64.to_le_bytes());
+        data[4..8].copy_from_slice(&0x0100_000cu32.to_le_bytes());
+        data[16..20].copy_from_slice(&4u32.to_le_bytes());
+
+        // LC_BUILD_VERSION: platform macOS, minos 11.0
+        let mut build = Vec::new();
+        for value in [LC_BUILD_VERSION, 24, 1, 11 << 16, 0, 0] {
+            build.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend(build);
+
+        // LC_LOAD_DYLIB with the name right after the 24-byte dylib command
+        let name = b"/usr/lib/libSystem.B.dylib\0\0\0\0\0\0";
+        for value in [LC_LOAD_DYLIB, 24 + 32, 24, 0, 0, 0] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(name);
+
+        // __TEXT with one __text section, then a section-less __LINKEDIT
+        let mut text = [0u8; 72 + 80];
+        text[..4].copy_from_slice(&LC_SEGMENT_64.to_le_bytes());
+        text[4..8].copy_from_slice(&152u32.to_le_bytes());
+        text[8..14].copy_from_slice(b"__TEXT");
+        text[64..68].copy_from_slice(&1u32.to_le_bytes());
+        text[72..79].copy_from_slice(b"__text\0");
+        text[72 + 40..72 + 48].copy_from_slice(&0x1234u64.to_le_bytes());
+        data.extend_from_slice(&text);
+
+        let mut linkedit = [0u8; 72];
+        linkedit[..4].copy_from_slice(&LC_SEGMENT_64.to_le_bytes());
+        linkedit[4..8].copy_from_slice(&72u32.to_le_bytes());
+        linkedit[8..18].copy_from_slice(b"__LINKEDIT");
+        linkedit[48..56].copy_from_slice(&0x400u64.to_le_bytes());
+        data.extend_from_slice(&linkedit);
+        data
+    }
+
+    #[test]
+    fn test_inspect() {
+        let data = sample();
+        assert!(is_macho(&data[..4]));
+
+        let info = inspect(&data).unwrap();
+        assert_eq!(info.archs, vec!["aarch64"]);
+        assert_eq!(info.libraries, vec!["/usr/lib/libSystem.B.dylib"]);
+        assert_eq!(info.linkage, Linkage::Dynamic);
+        assert_eq!(info.min_os.as_deref(), Some("macOS 11.0"));
+        assert_eq!(
+            info.sections,
+            vec![
+                Section::new("__TEXT,__text", 0x1234),
+                Section::new("__LINKEDIT", 0x400),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_universal() {
+        let thin = sample();
+        let mut data = Vec::new();
+        data.extend_from_slice(&FAT_MAGIC.to_be_bytes());
+        data.extend_from_slice(&1u32.to_be_bytes());
+        let size = u32::try_from(thin.len()).unwrap();
+        for value in [0x0100_000c, 0, 64, size, 0] {
+            data.extend_from_slice(&u32::to_be_bytes(value));
+        }
+        data.resize(64, 0);
+        data.extend(thin);
+
+        assert!(is_macho(&data[..4]));
+        assert_eq!(inspect(&data).unwrap().archs, vec!["aarch64"]);
+    }
+
+    #[test]
+    fn test_functions() {
+        let mut data = sample();
+        data[16..20].copy_from_slice(&5u32.to_le_bytes());
+
+        // LC_SYMTAB with three entries and their names right after it
+        let symoff = u32::try_from(data.len()).unwrap() + 24;
+        let stroff = symoff + 3 * 16;
+        for value in [LC_SYMTAB, 24, symoff, 3, stroff, 32] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        // name offset, type, section, address: a debugging entry is skipped
+        for (name, kind, address) in [(1u32, 0xfu8, 0x100u64), (7, 0xf, 0x1000), (1, 0x24, 0)] {
+            data.extend_from_slice(&name.to_le_bytes());
+            data.extend_from_slice(&[kind, 1, 0, 0]);
+            data.extend_from_slice(&address.to_le_bytes());
+        }
+        data.extend_from_slice(b"\0_main\0_helper\0");
+
+        assert_eq!(
+            functions(&data).unwrap(),
+            vec![Symbol::new("main", 0xf00), Symbol::new("helper", 0x234)]
+        );
+        assert!(functions(&sample()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_version() {
+        assert_eq!(version(0x000a_0f00), "10.15");
+        assert_eq!(version(0x000b_0001), "11.0.1");
+    }
+}
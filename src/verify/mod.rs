@@ -0,0 +1,777 @@
+//! Post-build artifact verification
+//!
+//! Inspects the headers of built binaries to catch problems a successful
+//! link does not: the wrong file format or architecture (a host binary
+//! that slipped into a cross build), dynamic linkage where a static binary
+//! was expected, shared library dependencies that will not exist on the
+//! target system, and a minimum OS version newer than intended.
+//!
+//! ```no_run
+//! use xcargo::config::Config;
+//! use xcargo::target::Target;
+//! use xcargo::verify;
+//!
+//! # fn example() -> xcargo::Result<()> {
+//! let target = Target::from_triple("x86_64-unknown-linux-musl")?;
+//! let report = verify::verify_file(
+//!     "target/x86_64-unknown-linux-musl/release/app".as_ref(),
+//!     &target,
+//!     &Config::default(),
+//! )?;
+//! for finding in &report.findings {
+//!     println!("{}", finding.message);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod elf;
+pub mod macho;
+pub mod pe;
+
+use crate::build::artifacts;
+use crate::build::glibc::{self, GlibcVersion};
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::output::helpers;
+use crate::target::Target;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Executable file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryFormat {
+    /// Linux, Android and the BSDs
+    Elf,
+    /// Windows
+    Pe,
+    /// macOS and iOS
+    MachO,
+}
+
+impl BinaryFormat {
+    /// Format binaries for `target` should have (`None` for WebAssembly and
+    /// other targets that are not checked)
+    #[must_use]
+    pub fn for_target(target: &Target) -> Option<Self> {
+        match target.os.as_str() {
+            "windows" => Some(Self::Pe),
+            "macos" | "ios" | "tvos" | "watchos" | "visionos" => Some(Self::MachO),
+            _ if target.arch.starts_with("wasm") => None,
+            "none" | "unknown" => None,
+            _ => Some(Self::Elf),
+        }
+    }
+}
+
+impl fmt::Display for BinaryFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Elf => "ELF",
+            Self::Pe => "PE",
+            Self::MachO => "Mach-O",
+        })
+    }
+}
+
+/// How a binary is linked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Linkage {
+    /// No dynamic loader or shared library dependencies
+    Static,
+    /// Loaded by the dynamic loader with shared libraries
+    Dynamic,
+}
+
+impl fmt::Display for Linkage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Static => "statically linked",
+            Self::Dynamic => "dynamically linked",
+        })
+    }
+}
+
+/// What a binary's headers say about it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryInfo {
+    /// File format
+    pub format: BinaryFormat,
+    /// Architectures, normalized as in [`normalize_arch`] (more than one
+    /// for universal Mach-O binaries)
+    pub archs: Vec<String>,
+    /// Static or dynamic linkage
+    pub linkage: Linkage,
+    /// Shared libraries the binary loads (DLLs, sonames or dylib paths)
+    pub libraries: Vec<String>,
+    /// Minimum OS version (e.g., "glibc 2.17", "macOS 11.0", "Windows 6.0")
+    pub min_os: Option<String>,
+    /// Sections stored in the file, in header order
+    pub sections: Vec<Section>,
+}
+
+/// A section of a binary and the bytes it takes up in the file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// Section name (`segment,section` for Mach-O, e.g., "`__TEXT,__text`")
+    pub name: String,
+    /// Bytes stored in the file (0 for zero-filled sections like `.bss`)
+    pub size: u64,
+}
+
+impl Section {
+    fn new(name: impl Into<String>, size: u64) -> Self {
+        Self {
+            name: name.into(),
+            size,
+        }
+    }
+}
+
+/// A function in a binary's symbol table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// Symbol name as stored (mangled for Rust functions)
+    pub name: String,
+    /// Bytes of code
+    pub size: u64,
+}
+
+impl Symbol {
+    fn new(name: impl Into<String>, size: u64) -> Self {
+        Self {
+            name: name.into(),
+            size,
+        }
+    }
+}
+
+/// Read the functions in the symbol table of `data`
+///
+/// Returns an empty list for stripped binaries. PE and Mach-O symbol
+/// tables have no sizes, so a function is taken to extend to the next
+/// symbol in its section.
+pub fn functions(data: &[u8]) -> Result<Vec<Symbol>> {
+    let functions = match data.get(..4) {
+        Some([0x7f, b'E', b'L', b'F']) => elf::functions(data),
+        Some([b'M', b'Z', ..]) => pe::functions(data),
+        Some(magic) if macho::is_macho(magic) => macho::functions(data),
+        _ => return Err(Error::Build("Not an ELF, PE or Mach-O binary".to_string())),
+    };
+    functions.ok_or_else(|| Error::Build("Malformed symbol table".to_string()))
+}
+
+/// Size symbols by the gap to the next one in the same section
+///
+/// `symbols` holds (name, section, address) and `section_ends` the end
+/// address of each section, indexed like the symbols' sections.
+fn sized_by_address(mut symbols: Vec<(&str, usize, u64)>, section_ends: &[u64]) -> Vec<Symbol> {
+    symbols.sort_by_key(|&(_, section, address)| (section, address));
+    let mut sized = Vec::with_capacity(symbols.len());
+    for (index, &(name, section, address)) in symbols.iter().enumerate() {
+        let end = match symbols.get(index + 1) {
+            Some(&(_, next_section, next)) if next_section == section => next,
+            _ => section_ends.get(section).copied().unwrap_or(address),
+        };
+        // Aliases share an address; only the last one gets the bytes
+        sized.push(Symbol::new(name, end.saturating_sub(address)));
+    }
+    sized.retain(|symbol| symbol.size > 0);
+    sized
+}
+
+impl BinaryInfo {
+    /// Read binary headers from `data`
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let info = match data.get(..4) {
+            Some([0x7f, b'E', b'L', b'F']) => elf::inspect(data),
+            Some([b'M', b'Z', ..]) => pe::inspect(data),
+            Some(magic) if macho::is_macho(magic) => macho::inspect(data),
+            _ => return Err(Error::Build("Not an ELF, PE or Mach-O binary".to_string())),
+        };
+        info.ok_or_else(|| Error::Build("Malformed binary headers".to_string()))
+    }
+
+    /// One-line summary (e.g., "ELF `x86_64`, dynamically linked, glibc 2.17")
+    #[must_use]
+    pub fn summary(&self) -> String {
+        let mut summary = format!("{} {}, {}", self.format, self.archs.join("+"), self.linkage);
+        if let Some(min_os) = &self.min_os {
+            summary.push_str(", ");
+            summary.push_str(min_os);
+        }
+        summary
+    }
+}
+
+/// How serious a finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The binary will not run where it is meant to
+    Error,
+    /// The binary may not run on every intended system
+    Warning,
+}
+
+/// A problem found in a binary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// How serious it is
+    pub severity: Severity,
+    /// What is wrong
+    pub message: String,
+}
+
+impl Finding {
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+
+    fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+        }
+    }
+}
+
+/// Verification result for one binary
+#[derive(Debug, Clone)]
+pub struct Verification {
+    /// Binary path
+    pub path: PathBuf,
+    /// What the headers say
+    pub info: BinaryInfo,
+    /// Problems found
+    pub findings: Vec<Finding>,
+}
+
+impl Verification {
+    /// Check if any finding is an error
+    #[must_use]
+    pub fn has_errors(&self) -> bool {
+        self.findings
+            .iter()
+            .any(|finding| finding.severity == Severity::Error)
+    }
+}
+
+/// Page size 64-bit Android devices may use, which shared libraries must
+/// be aligned to from Android 15
+pub const ANDROID_PAGE_SIZE: u64 = 16384;
+
+/// Smallest alignment of an ELF binary's loadable segments, `None` for
+/// other formats
+#[must_use]
+pub fn load_alignment(data: &[u8]) -> Option<u64> {
+    if data.get(..4) != Some(b"\x7fELF") {
+        return None;
+    }
+    elf::load_alignment(data)
+}
+
+/// Whether `target` is a 64-bit Android target, whose libraries need
+/// [`ANDROID_PAGE_SIZE`] alignment
+#[must_use]
+pub fn needs_16k_pages(target: &Target) -> bool {
+    target.os.starts_with("android") && matches!(target.arch.as_str(), "aarch64" | "x86_64")
+}
+
+/// Verify the binary at `path` built for `target`
+///
+/// Allowed libraries come from `[verify] allowed_libraries` and the glibc
+/// limit from `[targets.<triple>] min_glibc`.
+pub fn verify_file(path: &Path, target: &Target, config: &Config) -> Result<Verification> {
+    let data = std::fs::read(path)?;
+    let info = BinaryInfo::parse(&data)?;
+    let shared_library = path
+        .extension()
+        .is_some_and(|ext| ext == "so" || ext == "dll" || ext == "dylib");
+    let findings = check(&info, target, config, &data, shared_library);
+    Ok(Verification {
+        path: path.to_path_buf(),
+        info,
+        findings,
+    })
+}
+
+/// Verify every binary and shared library built for `target` in `profile`
+pub fn verify_target(target: &Target, profile: &str, config: &Config) -> Result<Vec<Verification>> {
+    artifacts::built_binaries(target, profile)?
+        .iter()
+        .map(|path| verify_file(path, target, config))
+        .collect()
+}
+
+/// Print verification results, returning how many binaries have errors
+#[must_use]
+pub fn print_report(verifications: &[Verification], verbose: bool) -> usize {
+    let mut failed = 0;
+    for verification in verifications {
+        let path = verification.path.display();
+        if verification.has_errors() {
+            failed += 1;
+            helpers::error(format!("{path}: {}", verification.info.summary()));
+        } else {
+            helpers::success(format!("{path}: {}", verification.info.summary()));
+        }
+        if verbose && !verification.info.libraries.is_empty() {
+            helpers::info(format!(
+                "Libraries: {}",
+                verification.info.libraries.join(", ")
+            ));
+        }
+        for finding in &verification.findings {
+            match finding.severity {
+                Severity::Error => helpers::error(format!("{path} {}", finding.message)),
+                Severity::Warning => helpers::warning(format!("{path} {}", finding.message)),
+            }
+        }
+    }
+    failed
+}
+
+/// Compare what a binary's headers say with what `target` needs
+fn check(
+    info: &BinaryInfo,
+    target: &Target,
+    config: &Config,
+    data: &[u8],
+    shared_library: bool,
+) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    if let Some(expected) = BinaryFormat::for_target(target) {
+        if info.format != expected {
+            findings.push(Finding::error(format!(
+                "is {}, expected {expected} for {}",
+                info.format, target.triple
+            )));
+            // Nothing else is comparable across formats
+            return findings;
+        }
+    }
+
+    let arch = normalize_arch(&target.arch);
+    if !info.archs.contains(&arch) {
+        findings.push(Finding::error(format!(
+            "is built for {}, expected {arch}",
+            info.archs.join("+")
+        )));
+    }
+
+    let target_config = config.get_target_config(&target.triple);
+    let wants_static = target
+        .env
+        .as_deref()
+        .is_some_and(|env| env.starts_with("musl"))
+        || target_config.is_some_and(|c| c.static_link)
+        || target_config
+            .and_then(|c| c.rustflags.as_ref())
+            .is_some_and(|flags| flags.iter().any(|flag| flag.contains("+crt-static")));
+    if wants_static
+        && !shared_library
+        && info.format == BinaryFormat::Elf
+        && info.linkage == Linkage::Dynamic
+    {
+        findings.push(Finding::error(format!(
+            "is dynamically linked ({}), expected a static binary",
+            if info.libraries.is_empty() {
+                "uses a dynamic loader".to_string()
+            } else {
+                info.libraries.join(", ")
+            }
+        )));
+    }
+
+    for library in &info.libraries {
+        if !is_system_library(&target.os, library)
+            && !is_allowed(&config.verify.allowed_libraries, library)
+        {
+            findings.push(Finding::warning(format!(
+                "depends on {library}, which is not a system library \
+                 (add it to [verify] allowed_libraries if it ships with the binary)"
+            )));
+        }
+    }
+
+    if let Some(min) = target_config
+        .and_then(|c| c.min_glibc.as_deref())
+        .and_then(GlibcVersion::parse)
+    {
+        let required = glibc::required_versions(data)
+            .ok()
+            .and_then(|mut versions| versions.pop());
+        if let Some(required) = required.filter(|required| *required > min) {
+            findings.push(Finding::warning(format!(
+                "requires glibc {required} (min_glibc is {min})"
+            )));
+        }
+    }
+
+    if needs_16k_pages(target) {
+        if let Some(alignment) = load_alignment(data).filter(|a| *a < ANDROID_PAGE_SIZE) {
+            findings.push(Finding::warning(format!(
+                "is aligned to {alignment}-byte pages; Android 15 devices with 16 KB pages \
+                 need {ANDROID_PAGE_SIZE} (link with -Wl,-z,max-page-size=16384)"
+            )));
+        }
+    }
+
+    if info.format == BinaryFormat::MachO {
+        if let (Some(min_os), Ok(deployment)) = (
+            info.min_os.as_deref(),
+            std::env::var("MACOSX_DEPLOYMENT_TARGET"),
+        ) {
+            let built = min_os.rsplit(' ').next().unwrap_or(min_os);
+            if version_parts(built) > version_parts(&deployment) {
+                findings.push(Finding::warning(format!(
+                    "requires {min_os}, newer than MACOSX_DEPLOYMENT_TARGET={deployment}"
+                )));
+            }
+        }
+    }
+
+    findings
+}
+
+/// Canonical architecture name for a target triple's arch component
+///
+/// `i686` becomes `x86`, `armv7`/`thumbv7neon` become `arm`, `riscv64gc`
+/// becomes `riscv64`, and so on, matching what binary headers can express.
+#[must_use]
+pub fn normalize_arch(arch: &str) -> String {
+    let normalized = match arch {
+        "i386" | "i586" | "i686" | "x86" => "x86",
+        "arm64" | "arm64e" | "aarch64" => "aarch64",
+        "powerpc64le" => "powerpc64",
+        "mips64el" => "mips64",
+        "mipsel" => "mips",
+        "sparcv9" => "sparc64",
+        _ if arch.starts_with("arm") || arch.starts_with("thumb") => "arm",
+        _ if arch.starts_with("riscv64") => "riscv64",
+        _ if arch.starts_with("riscv32") => "riscv32",
+        _ => arch,
+    };
+    normalized.to_string()
+}
+
+/// Check if `library` is provided by the base system of `os`
+#[must_use]
+pub fn is_system_library(os: &str, library: &str) -> bool {
+    const LINUX: &[&str] = &[
+        "libc.so",
+        "libm.so",
+        "libdl.so",
+        "libpthread.so",
+        "librt.so",
+        "libutil.so",
+        "libresolv.so",
+        "libgcc_s.so",
+        "ld-linux",
+        "ld64.so",
+        "ld-musl",
+    ];
+    const ANDROID: &[&str] = &[
+        "libc.so",
+        "libm.so",
+        "libdl.so",
+        "liblog.so",
+        "libandroid.so",
+    ];
+    const BSD: &[&str] = &[
+        "libc.so",
+        "libm.so",
+        "libthr.so",
+        "libpthread.so",
+        "libgcc_s.so",
+        "libexecinfo.so",
+        "libkvm.so",
+        "libutil.so",
+        "libprocstat.so",
+    ];
+    const WINDOWS: &[&str] = &[
+        "kernel32.dll",
+        "ntdll.dll",
+        "advapi32.dll",
+        "bcrypt.dll",
+        "bcryptprimitives.dll",
+        "userenv.dll",
+        "ws2_32.dll",
+        "msvcrt.dll",
+        "ucrtbase.dll",
+        "vcruntime140.dll",
+        "shell32.dll",
+        "ole32.dll",
+        "oleaut32.dll",
+        "user32.dll",
+        "gdi32.dll",
+        "secur32.dll",
+        "crypt32.dll",
+        "ncrypt.dll",
+        "iphlpapi.dll",
+        "dbghelp.dll",
+        "synchronization.dll",
+        "api-ms-win-",
+    ];
+
+    match os {
+        "windows" => {
+            let library = library.to_lowercase();
+            WINDOWS.iter().any(|system| library.starts_with(system))
+        }
+        "macos" | "ios" | "tvos" | "watchos" | "visionos" => {
+            library.starts_with("/usr/lib/") || library.starts_with("/System/Library/")
+        }
+        "android" => ANDROID.iter().any(|system| library.starts_with(system)),
+        "freebsd" | "netbsd" | "openbsd" | "dragonfly" => {
+            BSD.iter().any(|system| library.starts_with(system))
+        }
+        _ => LINUX.iter().any(|system| library.starts_with(system)),
+    }
+}
+
+/// Check `library` against `[verify] allowed_libraries` file name prefixes
+fn is_allowed(allowed: &[String], library: &str) -> bool {
+    let name = library.rsplit(['/', '\\']).next().unwrap_or(library);
+    allowed
+        .iter()
+        .any(|prefix| name.starts_with(prefix.as_str()))
+}
+
+/// Numeric components of a dotted version, for comparison
+fn version_parts(version: &str) -> Vec<u32> {
+    version
+        .split('.')
+        .map_while(|part| part.trim().parse().ok())
+        .collect()
+}
+
+/// Bounds-checked integer reads shared by the format parsers
+#[derive(Clone, Copy)]
+struct Bytes<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> Bytes<'a> {
+    fn new(data: &'a [u8], little_endian: bool) -> Self {
+        Self {
+            data,
+            little_endian,
+        }
+    }
+
+    fn array<const N: usize>(&self, offset: usize) -> Option<[u8; N]> {
+        self.data
+            .get(offset..offset.checked_add(N)?)?
+            .try_into()
+            .ok()
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes = self.array(offset)?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes = self.array(offset)?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn u64(&self, offset: usize) -> Option<u64> {
+        let bytes = self.array(offset)?;
+        Some(if self.little_endian {
+            u64::from_le_bytes(bytes)
+        } else {
+            u64::from_be_bytes(bytes)
+        })
+    }
+
+    /// `u32` widened to an offset
+    fn offset32(&self, offset: usize) -> Option<usize> {
+        usize::try_from(self.u32(offset)?).ok()
+    }
+
+    /// `u64` narrowed to an offset
+    fn offset64(&self, offset: usize) -> Option<usize> {
+        usize::try_from(self.u64(offset)?).ok()
+    }
+
+    /// NUL-terminated string at `offset`
+    fn c_str(&self, offset: usize) -> Option<&'a str> {
+        let bytes = self.data.get(offset..)?;
+        let end = bytes.iter().position(|&b| b == 0)?;
+        std::str::from_utf8(&bytes[..end]).ok()
+    }
+
+    /// NUL-padded string in a fixed-size field of `len` bytes
+    fn fixed_str(&self, offset: usize, len: usize) -> Option<&'a str> {
+        let bytes = self.data.get(offset..offset.checked_add(len)?)?;
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(len);
+        std::str::from_utf8(&bytes[..end]).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_arch() {
+        assert_eq!(normalize_arch("i686"), "x86");
+        assert_eq!(normalize_arch("armv7"), "arm");
+        assert_eq!(normalize_arch("thumbv7neon"), "arm");
+        assert_eq!(normalize_arch("riscv64gc"), "riscv64");
+        assert_eq!(normalize_arch("x86_64"), "x86_64");
+    }
+
+    #[test]
+    fn test_format_for_target() {
+        let format = |triple| BinaryFormat::for_target(&Target::from_triple(triple).unwrap());
+        assert_eq!(format("x86_64-pc-windows-gnu"), Some(BinaryFormat::Pe));
+        assert_eq!(format("aarch64-apple-darwin"), Some(BinaryFormat::MachO));
+        assert_eq!(format("aarch64-linux-android"), Some(BinaryFormat::Elf));
+        assert_eq!(format("wasm32-unknown-unknown"), None);
+    }
+
+    #[test]
+    fn test_system_libraries() {
+        assert!(is_system_library("linux", "libc.so.6"));
+        assert!(is_system_library("linux", "ld-linux-x86-64.so.2"));
+        assert!(!is_system_library("linux", "libssl.so.3"));
+        assert!(is_system_library("windows", "KERNEL32.dll"));
+        assert!(is_system_library(
+            "windows",
+            "api-ms-win-core-synch-l1-2-0.dll"
+        ));
+        assert!(!is_system_library("windows", "libssl-3-x64.dll"));
+        assert!(is_system_library("macos", "/usr/lib/libSystem.B.dylib"));
+        assert!(!is_system_library(
+            "macos",
+            "/opt/homebrew/lib/libssl.3.dylib"
+        ));
+
+        let allowed = vec!["libssl".to_string()];
+        assert!(is_allowed(&allowed, "/opt/homebrew/lib/libssl.3.dylib"));
+        assert!(!is_allowed(&allowed, "libcrypto.so.3"));
+    }
+
+    #[test]
+    fn test_check_findings() {
+        let musl = Target::from_triple("x86_64-unknown-linux-musl").unwrap();
+        let info = BinaryInfo {
+            format: BinaryFormat::Elf,
+            archs: vec!["aarch64".to_string()],
+            linkage: Linkage::Dynamic,
+            libraries: vec!["libc.so.6".to_string(), "libfoo.so.1".to_string()],
+            min_os: None,
+            sections: Vec::new(),
+        };
+        let findings = check(&info, &musl, &Config::default(), &[], false);
+        assert_eq!(findings.len(), 3);
+        assert!(findings[0].message.contains("expected x86_64"));
+        assert!(findings[1].message.contains("expected a static binary"));
+        assert_eq!(findings[2].severity, Severity::Warning);
+        assert!(findings[2].message.contains("libfoo.so.1"));
+
+        let windows = Target::from_triple("x86_64-pc-windows-gnu").unwrap();
+        let findings = check(&info, &windows, &Config::default(), &[], false);
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].message.contains("expected PE"));
+    }
+
+    #[test]
+    fn test_check_android_page_alignment() {
+        let android = Target::from_triple("aarch64-linux-android").unwrap();
+        let info = BinaryInfo {
+            format: BinaryFormat::Elf,
+            archs: vec!["aarch64".to_string()],
+            linkage: Linkage::Dynamic,
+            libraries: vec!["libc.so".to_string()],
+            min_os: None,
+            sections: Vec::new(),
+        };
+        // One PT_LOAD segment aligned to 4 KiB
+        let mut data = vec![0u8; 0x40 + 56];
+        data[..4].copy_from_slice(b"\x7fELF");
+        data[4] = 2;
+        data[5] = 1;
+        data[0x20..0x28].copy_from_slice(&0x40u64.to_le_bytes());
+        data[0x36..0x38].copy_from_slice(&56u16.to_le_bytes());
+        data[0x38..0x3a].copy_from_slice(&1u16.to_le_bytes());
+        data[0x40..0x44].copy_from_slice(&1u32.to_le_bytes());
+        data[0x70..0x78].copy_from_slice(&0x1000u64.to_le_bytes());
+
+        let findings = check(&info, &android, &Config::default(), &data, false);
+        assert!(findings
+            .iter()
+            .any(|f| f.severity == Severity::Warning && f.message.contains("4096-byte pages")));
+
+        data[0x70..0x78].copy_from_slice(&0x4000u64.to_le_bytes());
+        let findings = check(&info, &android, &Config::default(), &data, false);
+        assert!(!findings.iter().any(|f| f.message.contains("pages")));
+    }
+
+    #[test]
+    fn test_host_binary() {
+        let Ok(host) = Target::detect_host() else {
+            return;
+        };
+        let exe = std::env::current_exe().unwrap();
+        let report = verify_file(&exe, &host, &Config::default()).unwrap();
+        assert_eq!(Some(report.info.format), BinaryFormat::for_target(&host));
+        assert!(report.info.archs.contains(&normalize_arch(&host.arch)));
+        assert!(!report.has_errors(), "{:?}", report.findings);
+    }
+
+    #[test]
+    fn test_host_functions() {
+        // Test binaries keep their symbol table, except MSVC's (in a .pdb)
+        if cfg!(target_env = "msvc") {
+            return;
+        }
+        let exe = std::env::current_exe().unwrap();
+        let functions = functions(&std::fs::read(exe).unwrap()).unwrap();
+        assert!(functions.iter().all(|f| f.size > 0));
+        assert!(functions
+            .iter()
+            .any(|f| f.name.contains("test_host_functions")));
+    }
+
+    #[test]
+    fn test_sized_by_address() {
+        let symbols = vec![
+            ("b", 1, 0x30),
+            ("a", 1, 0x10),
+            ("alias", 1, 0x10),
+            ("c", 2, 0x8),
+        ];
+        let sized = sized_by_address(symbols, &[0, 0x40, 0x20]);
+        assert_eq!(
+            sized,
+            vec![
+                Symbol::new("alias", 0x20),
+                Symbol::new("b", 0x10),
+                Symbol::new("c", 0x18),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_not_a_binary() {
+        assert!(BinaryInfo::parse(b"#!/bin/sh\n").is_err());
+        assert!(BinaryInfo::parse(b"\x7fELF").is_err());
+    }
+}
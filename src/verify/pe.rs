@@ -0,0 +1,259 @@
+//! PE (Windows) header inspection
+
+use super::{BinaryFormat, BinaryInfo, Bytes, Linkage, Section, Symbol};
+
+/// Optional header magic of 64-bit images
+const PE32_PLUS: u16 = 0x20b;
+/// Size of a section table entry
+const SECTION_SIZE: usize = 40;
+/// Size of an import directory entry
+const IMPORT_DESCRIPTOR_SIZE: usize = 20;
+/// Size of a COFF symbol table entry
+const SYMBOL_SIZE: usize = 18;
+/// COFF symbol type of functions (`DTYPE_FUNCTION` << 4)
+const FUNCTION_TYPE: u16 = 0x20;
+
+/// Architecture for a COFF `Machine` value
+fn arch(machine: u16) -> String {
+    let arch = match machine {
+        0x14c => "x86",
+        0x8664 => "x86_64",
+        0x1c0 | 0x1c4 => "arm",
+        0xaa64 => "aarch64",
+        _ => return format!("machine {machine:#x}"),
+    };
+    arch.to_string()
+}
+
+/// Read architecture, imported DLLs and the subsystem version
+pub(super) fn inspect(data: &[u8]) -> Option<BinaryInfo> {
+    let bytes = Bytes::new(data, true);
+    let (coff, section_count, section_table) = headers(&bytes)?;
+    let machine = bytes.u16(coff)?;
+    let optional = coff + 20;
+
+    let is_64 = bytes.u16(optional)? == PE32_PLUS;
+    let subsystem = (bytes.u16(optional + 48)?, bytes.u16(optional + 50)?);
+    let data_directories = optional + if is_64 { 112 } else { 96 };
+
+    // Map a relative virtual address to a file offset through the section table
+    let file_offset = |rva: usize| -> Option<usize> {
+        (0..section_count).find_map(|index| {
+            let section = section_table + index * SECTION_SIZE;
+            let virtual_size = bytes.offset32(section + 8)?;
+            let address = bytes.offset32(section + 12)?;
+            let raw_size = bytes.offset32(section + 16)?;
+            let raw_offset = bytes.offset32(section + 20)?;
+            let end = address.checked_add(virtual_size.max(raw_size))?;
+            (address..end)
+                .contains(&rva)
+                .then(|| raw_offset + (rva - address))
+        })
+    };
+
+    // Import directory (data directory 1): one descriptor per DLL
+    let mut libraries = Vec::new();
+    let import_rva = bytes.offset32(data_directories + 8)?;
+    if import_rva != 0 {
+        let mut descriptor = file_offset(import_rva)?;
+        loop {
+            let name_rva = bytes.offset32(descriptor + 12)?;
+            if name_rva == 0 {
+                break;
+            }
+            libraries.push(bytes.c_str(file_offset(name_rva)?)?.to_string());
+            descriptor = descriptor.checked_add(IMPORT_DESCRIPTOR_SIZE)?;
+        }
+    }
+
+    // Names longer than 8 bytes (e.g., MinGW's `.debug_info`) are stored as
+    // "/<offset>" into the string table after the COFF symbol table
+    let strings = string_table(&bytes, coff)?;
+    let mut sections = Vec::new();
+    for index in 0..section_count {
+        let section = section_table + index * SECTION_SIZE;
+        let name = bytes.fixed_str(section, 8)?;
+        let name = match name.strip_prefix('/').and_then(|n| n.parse::<usize>().ok()) {
+            Some(offset) => bytes.c_str(strings.checked_add(offset)?)?,
+            None => name,
+        };
+        let size = bytes.u32(section + 16)?;
+        sections.push(Section::new(name, u64::from(size)));
+    }
+
+    let linkage = if libraries.is_empty() {
+        Linkage::Static
+    } else {
+        Linkage::Dynamic
+    };
+
+    Some(BinaryInfo {
+        format: BinaryFormat::Pe,
+        archs: vec![arch(machine)],
+        linkage,
+        libraries,
+        min_os: Some(format!("Windows {}.{}", subsystem.0, subsystem.1)),
+        sections,
+    })
+}
+
+/// COFF header offset, section count and section table offset
+fn headers(bytes: &Bytes) -> Option<(usize, usize, usize)> {
+    let pe = bytes.offset32(0x3c)?;
+    if bytes.array::<4>(pe)? != *b"PE\0\0" {
+        return None;
+    }
+    let coff = pe + 4;
+    let section_count = usize::from(bytes.u16(coff + 2)?);
+    let optional_size = usize::from(bytes.u16(coff + 16)?);
+    Some((coff, section_count, (coff + 20).checked_add(optional_size)?))
+}
+
+/// Offset of the string table, which follows the COFF symbol table
+fn string_table(bytes: &Bytes, coff: usize) -> Option<usize> {
+    bytes
+        .offset32(coff + 8)?
+        .checked_add(bytes.offset32(coff + 12)?.checked_mul(SYMBOL_SIZE)?)
+}
+
+/// Read the functions of the COFF symbol table kept by MinGW linkers
+///
+/// MSVC puts symbols in a separate PDB, so its binaries have none here.
+pub(super) fn functions(data: &[u8]) -> Option<Vec<Symbol>> {
+    let bytes = Bytes::new(data, true);
+    let (coff, section_count, section_table) = headers(&bytes)?;
+    let symbol_table = bytes.offset32(coff + 8)?;
+    let symbol_count = bytes.offset32(coff + 12)?;
+    if symbol_table == 0 {
+        return Some(Vec::new());
+    }
+    let strings = string_table(&bytes, coff)?;
+
+    // Symbol values are offsets into their (1-based) section
+    let mut section_ends = vec![0];
+    for index in 0..section_count {
+        let section = section_table + index * SECTION_SIZE;
+        section_ends.push(u64::from(bytes.u32(section + 8)?));
+    }
+
+    let mut symbols = Vec::new();
+    let mut index = 0;
+    while index < symbol_count {
+        let symbol = symbol_table.checked_add(index.checked_mul(SYMBOL_SIZE)?)?;
+        let value = bytes.u32(symbol + 8)?;
+        let section = bytes.u16(symbol + 12)?;
+        let kind = bytes.u16(symbol + 14)?;
+        let aux = usize::from(*data.get(symbol + 17)?);
+        index += 1 + aux;
+
+        // Section numbers above the table are special (absolute, debug)
+        if kind != FUNCTION_TYPE || section == 0 || usize::from(section) > section_count {
+            continue;
+        }
+        let name = if bytes.u32(symbol)? == 0 {
+            bytes.c_str(strings.checked_add(bytes.offset32(symbol + 4)?)?)?
+        } else {
+            bytes.fixed_str(symbol, 8)?
+        };
+        symbols.push((name, usize::from(section), u64::from(value)));
+    }
+    Some(super::sized_by_address(symbols, &section_ends))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 64-bit image with one section holding an import of `kernel32.dll`
+    fn sample() -> Vec<u8> {
+        let mut data = vec![0u8; 0x400];
+        data[..2].copy_from_slice(b"MZ");
+        data[0x3c..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        data[0x80..0x84].copy_from_slice(b"PE\0\0");
+
+        // COFF header: machine, one section, optional header size
+        data[0x84..0x86].copy_from_slice(&0x8664u16.to_le_bytes());
+        data[0x86..0x88].copy_from_slice(&1u16.to_le_bytes());
+        data[0x94..0x96].copy_from_slice(&240u16.to_le_bytes());
+
+        // Optional header: PE32+, subsystem 6.0, import directory at 0x1000
+        let optional = 0x98;
+        data[optional..optional + 2].copy_from_slice(&PE32_PLUS.to_le_bytes());
+        data[optional + 48..optional + 50].copy_from_slice(&6u16.to_le_bytes());
+        data[optional + 120..optional + 124].copy_from_slice(&0x1000u32.to_le_bytes());
+
+        // Section: RVA 0x1000 maps to file offset 0x200
+        let section = optional + 240;
+        data[section..section + 6].copy_from_slice(b".idata");
+        data[section + 8..section + 12].copy_from_slice(&0x200u32.to_le_bytes());
+        data[section + 12..section + 16].copy_from_slice(&0x1000u32.to_le_bytes());
+        data[section + 16..section + 20].copy_from_slice(&0x200u32.to_le_bytes());
+        data[section + 20..section + 24].copy_from_slice(&0x200u32.to_le_bytes());
+
+        // One import descriptor naming the DLL at RVA 0x1100
+        data[0x200 + 12..0x200 + 16].copy_from_slice(&0x1100u32.to_le_bytes());
+        data[0x300..0x30c].copy_from_slice(b"KERNEL32.dll");
+        data
+    }
+
+    #[test]
+    fn test_inspect() {
+        let info = inspect(&sample()).unwrap();
+        assert_eq!(info.archs, vec!["x86_64"]);
+        assert_eq!(info.libraries, vec!["KERNEL32.dll"]);
+        assert_eq!(info.linkage, Linkage::Dynamic);
+        assert_eq!(info.min_os.as_deref(), Some("Windows 6.0"));
+        assert_eq!(info.sections, vec![Section::new(".idata", 0x200)]);
+    }
+
+    #[test]
+    fn test_long_section_name() {
+        let mut data = sample();
+        // No symbols; the string table starts at 0x380 with its size field
+        data[0x8c..0x90].copy_from_slice(&0x380u32.to_le_bytes());
+        data[0x384..0x390].copy_from_slice(b".debug_info\0");
+        let section = 0x98 + 240;
+        data[section..section + 8].copy_from_slice(b"/4\0\0\0\0\0\0");
+
+        let info = inspect(&data).unwrap();
+        assert_eq!(info.sections[0].name, ".debug_info");
+    }
+
+    #[test]
+    fn test_functions() {
+        let mut data = sample();
+        // Three symbols at 0x380: two functions in section 1, one in a
+        // special section; the string table follows them
+        data[0x8c..0x90].copy_from_slice(&0x380u32.to_le_bytes());
+        data[0x90..0x94].copy_from_slice(&3u32.to_le_bytes());
+        let symbols: [(&[u8; 8], u32, u16); 3] = [
+            (b"second\0\0", 0x80, 1),
+            (b"\0\0\0\0\x04\0\0\0", 0x20, 1),
+            (b"abs\0\0\0\0\0", 0x10, 0xffff),
+        ];
+        for (index, (name, value, section)) in symbols.into_iter().enumerate() {
+            let symbol = 0x380 + index * SYMBOL_SIZE;
+            data[symbol..symbol + 8].copy_from_slice(name);
+            data[symbol + 8..symbol + 12].copy_from_slice(&value.to_le_bytes());
+            data[symbol + 12..symbol + 14].copy_from_slice(&section.to_le_bytes());
+            data[symbol + 14..symbol + 16].copy_from_slice(&FUNCTION_TYPE.to_le_bytes());
+        }
+        let strings = 0x380 + 3 * SYMBOL_SIZE;
+        data[strings + 4..strings + 19].copy_from_slice(b"a_long_function");
+
+        assert_eq!(
+            functions(&data).unwrap(),
+            vec![
+                Symbol::new("a_long_function", 0x60),
+                Symbol::new("second", 0x180),
+            ]
+        );
+        assert!(functions(&sample()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_truncated() {
+        let data = sample();
+        assert!(inspect(&data[..0x90]).is_none());
+    }
+}
@@ -103,9 +103,14 @@ fn test_build_options_cargo_args() {
 #[test]
 fn test_build_options_verbose() {
     let mut options = BuildOptions::default();
-    options.verbose = true;
+    assert!(!options.cargo_verbose());
 
-    assert!(options.verbose);
+    options.verbose = 2;
+    assert!(options.cargo_verbose());
+    assert!(!options.dumps_commands());
+
+    options.verbose = 3;
+    assert!(options.dumps_commands());
 }
 
 #[test]
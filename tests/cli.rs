@@ -47,7 +47,9 @@ fn test_build_help() {
         .success()
         .stdout(predicate::str::contains("--target"))
         .stdout(predicate::str::contains("--release"))
-        .stdout(predicate::str::contains("--zig"));
+        .stdout(predicate::str::contains("--zig"))
+        .stdout(predicate::str::contains("--bin"))
+        .stdout(predicate::str::contains("--example"));
 }
 
 #[test]
@@ -70,6 +72,36 @@ fn test_test_help() {
         .stdout(predicate::str::contains("--release"));
 }
 
+#[test]
+fn test_clippy_help() {
+    xcargo()
+        .args(["clippy", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--target"))
+        .stdout(predicate::str::contains("--group"));
+}
+
+#[test]
+fn test_doc_help() {
+    xcargo()
+        .args(["doc", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--target"))
+        .stdout(predicate::str::contains("--no-deps"));
+}
+
+#[test]
+fn test_bench_help() {
+    xcargo()
+        .args(["bench", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--target"))
+        .stdout(predicate::str::contains("--release").not());
+}
+
 // ============================================================================
 // Target Commands
 // ============================================================================
@@ -101,7 +133,9 @@ fn test_target_info_valid() {
         .success()
         .stdout(predicate::str::contains("Triple"))
         .stdout(predicate::str::contains("Architecture"))
-        .stdout(predicate::str::contains("OS"));
+        .stdout(predicate::str::contains("OS"))
+        .stdout(predicate::str::contains("Rust tier:    Tier 1"))
+        .stdout(predicate::str::contains("Pointers:     64-bit"));
 }
 
 #[test]
@@ -115,13 +149,70 @@ fn test_target_info_with_alias() {
 
 #[test]
 fn test_target_info_unknown() {
-    // Currently xcargo allows unknown targets (it parses them as Container tier)
-    // This behavior may change in the future to be more strict
+    // Triples rustc does not know are rejected
     xcargo()
         .args(["target", "info", "unknown-target-triple"])
         .assert()
+        .code(3)
+        .stdout(predicate::str::contains("[X0302] Invalid target"));
+}
+
+#[test]
+fn test_target_alias_add_list_remove() {
+    let temp_dir = TempDir::new().unwrap();
+
+    xcargo()
+        .args(["target", "alias", "add", "rpi", "aarch64-unknown-linux-gnu"])
+        .current_dir(temp_dir.path())
+        .env("HOME", temp_dir.path())
+        .assert()
+        .success();
+    let config = fs::read_to_string(temp_dir.path().join("xcargo.toml")).unwrap();
+    assert!(config.contains("rpi = \"aarch64-unknown-linux-gnu\""));
+
+    xcargo()
+        .args(["target", "alias", "list"])
+        .current_dir(temp_dir.path())
+        .env("HOME", temp_dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("rpi"))
+        .stdout(predicate::str::contains("linux-arm64"));
+
+    xcargo()
+        .args(["target", "info", "rpi"])
+        .current_dir(temp_dir.path())
+        .env("HOME", temp_dir.path())
+        .assert()
         .success()
-        .stdout(predicate::str::contains("Container")); // Unknown targets are Container tier
+        .stdout(predicate::str::contains("aarch64-unknown-linux-gnu"));
+
+    xcargo()
+        .args(["target", "alias", "remove", "rpi"])
+        .current_dir(temp_dir.path())
+        .env("HOME", temp_dir.path())
+        .assert()
+        .success();
+    xcargo()
+        .args(["target", "alias", "remove", "rpi"])
+        .current_dir(temp_dir.path())
+        .env("HOME", temp_dir.path())
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_target_alias_global() {
+    let temp_dir = TempDir::new().unwrap();
+
+    xcargo()
+        .args(["target", "alias", "add", "--global", "pi", "armv7-unknown-linux-gnueabihf"])
+        .current_dir(temp_dir.path())
+        .env("HOME", temp_dir.path())
+        .assert()
+        .success();
+    assert!(temp_dir.path().join(".xcargo/config.toml").is_file());
+    assert!(!temp_dir.path().join("xcargo.toml").exists());
 }
 
 // ============================================================================
@@ -151,6 +242,225 @@ fn test_config_no_file() {
         .stdout(predicate::str::contains("No xcargo.toml found"));
 }
 
+#[test]
+fn test_config_show_origin() {
+    let home = TempDir::new().unwrap();
+    fs::create_dir(home.path().join(".xcargo")).unwrap();
+    fs::write(
+        home.path().join(".xcargo/config.toml"),
+        "[container]\nruntime = \"podman\"\n\n[build]\ncache = false\n",
+    )
+    .unwrap();
+    let project = TempDir::new().unwrap();
+    fs::write(
+        project.path().join("xcargo.toml"),
+        "[build]\ncache = true\n\n[profiles.ci]\nparallel = false\n",
+    )
+    .unwrap();
+
+    xcargo()
+        .args(["config", "--show-origin", "--profile", "ci"])
+        .current_dir(project.path())
+        .env("HOME", home.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "global      container.runtime = \"podman\"",
+        ))
+        .stdout(predicate::str::contains("project     build.cache = true"))
+        .stdout(predicate::str::contains(
+            "profile ci  build.parallel = false",
+        ))
+        .stdout(predicate::str::contains("default     build.retries = 0"));
+
+    // Without a project file, the global settings still apply
+    let elsewhere = TempDir::new().unwrap();
+    xcargo()
+        .arg("config")
+        .current_dir(elsewhere.path())
+        .env("HOME", home.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("runtime = \"podman\""));
+}
+
+#[test]
+fn test_config_validate() {
+    let home = TempDir::new().unwrap();
+    let project = TempDir::new().unwrap();
+    fs::write(
+        project.path().join("xcargo.toml"),
+        "[targets]\ndefault = [\"x86_64-unknown-linux-gnu\"]\n\n[build]\nparralel = true\n",
+    )
+    .unwrap();
+
+    xcargo()
+        .args(["config", "validate"])
+        .current_dir(project.path())
+        .env("HOME", home.path())
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "xcargo.toml:5:1: build.parralel: Unknown key (did you mean `parallel`?)",
+        ));
+
+    fs::write(
+        project.path().join("xcargo.toml"),
+        "[build]\nparallel = true\n",
+    )
+    .unwrap();
+    xcargo()
+        .args(["config", "validate"])
+        .current_dir(project.path())
+        .env("HOME", home.path())
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_config_schema() {
+    let output = xcargo().args(["config", "schema"]).output().unwrap();
+    assert!(output.status.success());
+    let schema: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(schema["title"], "xcargo configuration");
+    assert!(schema["properties"]["container"].is_object());
+}
+
+#[test]
+fn test_config_effective() {
+    let home = TempDir::new().unwrap();
+    let project = TempDir::new().unwrap();
+    fs::create_dir(project.path().join(".cargo")).unwrap();
+    fs::write(
+        project.path().join(".cargo").join("config.toml"),
+        "[target.aarch64-unknown-linux-gnu]\nlinker = \"clang\"\nrunner = \"qemu-aarch64\"\n",
+    )
+    .unwrap();
+    fs::write(
+        project.path().join("xcargo.toml"),
+        "[targets.aarch64-unknown-linux-gnu]\nlinker = \"aarch64-linux-gnu-gcc\"\n",
+    )
+    .unwrap();
+
+    xcargo()
+        .args(["config", "effective", "-t", "aarch64-unknown-linux-gnu"])
+        .current_dir(project.path())
+        .env("HOME", home.path())
+        .env_remove("CARGO_HOME")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "xcargo.toml         linker = aarch64-linux-gnu-gcc",
+        ))
+        .stdout(predicate::str::contains(
+            ".cargo/config.toml  runner = qemu-aarch64",
+        ))
+        .stdout(predicate::str::contains(
+            "overriding clang from .cargo/config.toml",
+        ));
+}
+
+#[test]
+fn test_config_from_cargo_metadata() {
+    let home = TempDir::new().unwrap();
+    let project = TempDir::new().unwrap();
+    fs::write(
+        project.path().join("Cargo.toml"),
+        "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[package.metadata.xcargo.build]\nretries = 2\ncache = false\n\n[workspace.metadata.xcargo.container]\nruntime = \"podman\"\n",
+    )
+    .unwrap();
+    fs::write(
+        project.path().join("xcargo.toml"),
+        "[build]\ncache = true\n",
+    )
+    .unwrap();
+
+    xcargo()
+        .args(["config", "--show-origin"])
+        .current_dir(project.path())
+        .env("HOME", home.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "workspace  container.runtime = \"podman\"",
+        ))
+        .stdout(predicate::str::contains("package    build.retries = 2"))
+        .stdout(predicate::str::contains("project    build.cache = true"));
+
+    fs::write(
+        project.path().join("Cargo.toml"),
+        "[package]\nname = \"app\"\n\n[package.metadata.xcargo.build]\nretires = 2\n",
+    )
+    .unwrap();
+    xcargo()
+        .args(["config", "validate"])
+        .current_dir(project.path())
+        .env("HOME", home.path())
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "Cargo.toml:5:1: package.metadata.xcargo.build.retires: Unknown key (did you mean `retries`?)",
+        ));
+}
+
+#[test]
+fn test_migrate_cross() {
+    let home = TempDir::new().unwrap();
+    let project = TempDir::new().unwrap();
+    fs::write(
+        project.path().join("Cargo.toml"),
+        "[package]\nname = \"app\"\nversion = \"0.1.0\"\n",
+    )
+    .unwrap();
+    fs::write(
+        project.path().join("Cross.toml"),
+        "[build.env]\npassthrough = [\"APP_TOKEN\"]\n\n[target.aarch64-unknown-linux-gnu]\nimage = \"example/aarch64:1\"\ndockerfile = \"./Dockerfile\"\n",
+    )
+    .unwrap();
+
+    xcargo()
+        .args(["migrate", "cross", "--dry-run"])
+        .current_dir(project.path())
+        .env("HOME", home.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("passthrough = [\"APP_TOKEN\"]"))
+        .stdout(predicate::str::contains(
+            "aarch64-unknown-linux-gnu = \"example/aarch64:1\"",
+        ));
+    assert!(!project.path().join("xcargo.toml").exists());
+
+    xcargo()
+        .args(["migrate", "cross"])
+        .current_dir(project.path())
+        .env("HOME", home.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Not migrated: target.aarch64-unknown-linux-gnu.dockerfile",
+        ));
+    let written = fs::read_to_string(project.path().join("xcargo.toml")).unwrap();
+    assert!(written.contains("[container.images]"), "{written}");
+
+    // An existing xcargo.toml is kept unless --force
+    xcargo()
+        .args(["migrate", "cross"])
+        .current_dir(project.path())
+        .env("HOME", home.path())
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("already exists"));
+    xcargo()
+        .args(["config", "--show-origin"])
+        .current_dir(project.path())
+        .env("HOME", home.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "project  container.passthrough = [\"APP_TOKEN\"]",
+        ));
+}
+
 // ============================================================================
 // Init Command
 // ============================================================================
@@ -207,6 +517,33 @@ fn test_build_conflicting_args() {
         .stderr(predicate::str::contains("cannot be used with"));
 }
 
+#[test]
+fn test_build_unknown_group() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(
+        temp_dir.path().join("xcargo.toml"),
+        "[target-groups]\ndesktop = [\"x86_64-unknown-linux-gnu\"]\n",
+    )
+    .unwrap();
+
+    xcargo()
+        .args(["build", "--group", "mobile"])
+        .current_dir(temp_dir.path())
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Unknown target group 'mobile'"))
+        .stdout(predicate::str::contains("available: desktop"));
+}
+
+#[test]
+fn test_build_group_conflicts_with_all() {
+    xcargo()
+        .args(["build", "--all", "--group", "desktop"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
 #[test]
 fn test_build_zig_conflicting_args() {
     // --zig and --no-zig are mutually exclusive
@@ -258,3 +595,206 @@ fn test_verbose_flag_global() {
 fn test_verbose_flag_long() {
     xcargo().args(["--verbose", "version"]).assert().success();
 }
+
+#[test]
+fn test_verbose_flag_repeated() {
+    xcargo().args(["-vvv", "version"]).assert().success();
+}
+
+#[test]
+fn test_log_file_flag() {
+    let temp = TempDir::new().unwrap();
+    let log = temp.path().join("xcargo.log");
+    xcargo()
+        .args(["--log-file", log.to_str().unwrap(), "version"])
+        .assert()
+        .success();
+    assert!(log.exists());
+
+    xcargo()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--log-file"));
+}
+
+// ============================================================================
+// Completions and Man Pages
+// ============================================================================
+
+#[test]
+fn test_completions_command() {
+    for shell in ["bash", "zsh", "fish", "powershell"] {
+        xcargo()
+            .args(["completions", shell])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("COMPLETE"));
+    }
+}
+
+#[test]
+fn test_completions_unknown_shell() {
+    xcargo().args(["completions", "tcsh"]).assert().failure();
+}
+
+#[test]
+fn test_complete_subcommands() {
+    xcargo()
+        .env("COMPLETE", "fish")
+        .args(["--", "xcargo", "comp"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("completions"));
+}
+
+#[test]
+fn test_man_command() {
+    xcargo()
+        .arg("man")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(".TH xcargo 1"));
+}
+
+#[test]
+fn test_man_out_dir() {
+    let temp = TempDir::new().unwrap();
+    xcargo()
+        .args(["man", "--out-dir"])
+        .arg(temp.path())
+        .assert()
+        .success();
+
+    assert!(temp.path().join("xcargo.1").exists());
+    assert!(temp.path().join("xcargo-build.1").exists());
+}
+
+#[test]
+fn test_plain_accessible_output() {
+    xcargo()
+        .args(["--output", "plain-accessible", "target", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("  - x86_64-unknown-linux-gnu"))
+        .stdout(predicate::str::contains("HINT: "))
+        .stdout(predicate::str::is_match("^[[:ascii:]]*$").unwrap());
+
+    xcargo()
+        .args(["--output", "fancy", "version"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_verify_help() {
+    xcargo()
+        .args(["verify", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--target"))
+        .stdout(predicate::str::contains("--release"));
+}
+
+#[test]
+fn test_size_help() {
+    xcargo()
+        .args(["size", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--target"))
+        .stdout(predicate::str::contains("--no-build"))
+        .stdout(predicate::str::contains("--by-crate"));
+}
+
+#[test]
+fn test_logs_help() {
+    xcargo()
+        .args(["logs", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--follow"))
+        .stdout(predicate::str::contains("--lines"));
+}
+
+#[test]
+fn test_uninstall_help() {
+    xcargo()
+        .args(["uninstall", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--target"))
+        .stdout(predicate::str::contains("--dry-run"));
+}
+
+#[test]
+fn test_build_timeout_help() {
+    xcargo()
+        .args(["build", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--timeout <DURATION>"));
+}
+
+#[test]
+fn test_build_timings_help() {
+    xcargo()
+        .args(["build", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--timings"));
+}
+
+#[test]
+fn test_doctor_json_and_report_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let report = temp_dir.path().join("doctor.yaml");
+
+    xcargo()
+        .args(["doctor", "--json", "--report-file"])
+        .arg(&report)
+        .assert()
+        .stdout(predicate::str::starts_with("{"))
+        .stdout(predicate::str::contains("\"xcargo_version\""))
+        .stdout(predicate::str::contains("\"name\": \"cargo\""))
+        .stdout(predicate::str::contains("System Diagnostics").not());
+
+    let yaml = std::fs::read_to_string(&report).unwrap();
+    assert!(yaml.contains("checks:"));
+    assert!(yaml.contains("environment:"));
+}
+
+#[test]
+fn test_doctor_offline_skips_network_probes() {
+    xcargo()
+        .args(["doctor", "--json", "--offline"])
+        .assert()
+        .stdout(predicate::str::contains("\"name\": \"offline dependencies\""))
+        .stdout(predicate::str::contains("\"name\": \"offline targets\""))
+        .stdout(predicate::str::contains("\"name\": \"crates.io\"").not());
+}
+
+#[test]
+fn test_explain_code() {
+    xcargo()
+        .args(["explain", "xc001"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("vendored"));
+
+    xcargo()
+        .arg("explain")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("XC010"));
+}
+
+#[test]
+fn test_explain_output_from_stdin() {
+    xcargo()
+        .args(["explain", "-"])
+        .write_stdin("  = note: /usr/bin/ld: cannot find -lsqlite3\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("XC005"));
+}
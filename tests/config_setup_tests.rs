@@ -161,6 +161,7 @@ fn test_config_target_specific_settings() -> Result<()> {
         force_container: None,
         env: std::collections::HashMap::new(),
         rustflags: None,
+        ..Default::default()
     };
 
     config.targets.custom.insert(target_triple.clone(), target_config);
@@ -329,6 +329,7 @@ fn test_report_with_checks_without_suggestions() {
         status: CheckStatus::Pass,
         message: "OK".to_string(),
         suggestion: None,
+        version: None,
     });
 
     report.add_check(CheckResult {
@@ -336,6 +337,7 @@ fn test_report_with_checks_without_suggestions() {
         status: CheckStatus::Warning,
         message: "Warning without suggestion".to_string(),
         suggestion: None,
+        version: None,
     });
 
     report.display();
@@ -24,6 +24,7 @@ fn test_report_with_passing_checks() {
         status: CheckStatus::Pass,
         message: "OK".to_string(),
         suggestion: None,
+        version: None,
     });
 
     report.add_check(CheckResult {
@@ -31,6 +32,7 @@ fn test_report_with_passing_checks() {
         status: CheckStatus::Pass,
         message: "OK".to_string(),
         suggestion: None,
+        version: None,
     });
 
     let summary = report.summary();
@@ -49,6 +51,7 @@ fn test_report_with_warnings() {
         status: CheckStatus::Warning,
         message: "Minor issue - Details here".to_string(),
         suggestion: Some("Fix this".to_string()),
+        version: None,
     });
 
     let summary = report.summary();
@@ -67,6 +70,8 @@ fn test_report_with_failures() {
         message: "Something failed".to_string(),
         
         suggestion: None,
+        
+        version: None,
     });
 
     let summary = report.summary();
@@ -84,6 +89,7 @@ fn test_report_with_critical_failures() {
         status: CheckStatus::Critical,
         message: "Critical failure - System cannot function".to_string(),
         suggestion: Some("Reinstall".to_string()),
+        version: None,
     });
 
     let summary = report.summary();
@@ -102,6 +108,8 @@ fn test_report_mixed_statuses() {
         message: "OK".to_string(),
         
         suggestion: None,
+        
+        version: None,
     });
 
     report.add_check(CheckResult {
@@ -110,6 +118,8 @@ fn test_report_mixed_statuses() {
         message: "Warning".to_string(),
         
         suggestion: None,
+        
+        version: None,
     });
 
     report.add_check(CheckResult {
@@ -118,6 +128,8 @@ fn test_report_mixed_statuses() {
         message: "Failed".to_string(),
         
         suggestion: None,
+        
+        version: None,
     });
 
     report.add_check(CheckResult {
@@ -126,6 +138,8 @@ fn test_report_mixed_statuses() {
         message: "Critical".to_string(),
         
         suggestion: None,
+        
+        version: None,
     });
 
     let summary = report.summary();
@@ -144,6 +158,7 @@ fn test_check_result_with_message() {
         status: CheckStatus::Pass,
         message: "Success - Additional details".to_string(),
         suggestion: None,
+        version: None,
     };
 
     assert_eq!(check.name, "Detailed Check");
@@ -160,6 +175,8 @@ fn test_check_result_with_suggestion() {
         message: "Minor issue".to_string(),
         
         suggestion: Some("Try this fix".to_string()),
+        
+        version: None,
     };
 
     assert_eq!(check.status, CheckStatus::Warning);
@@ -184,6 +201,8 @@ fn test_report_display() {
         message: "OK".to_string(),
         
         suggestion: None,
+        
+        version: None,
     });
 
     // Just verify display doesn't panic
@@ -200,6 +219,8 @@ fn test_multiple_critical_failures() {
         message: "Error 1".to_string(),
         
         suggestion: None,
+        
+        version: None,
     });
 
     report.add_check(CheckResult {
@@ -208,6 +229,8 @@ fn test_multiple_critical_failures() {
         message: "Error 2".to_string(),
         
         suggestion: None,
+        
+        version: None,
     });
 
     assert!(report.has_critical_failures());
@@ -225,6 +248,8 @@ fn test_no_critical_failures_with_other_statuses() {
         message: "OK".to_string(),
         
         suggestion: None,
+        
+        version: None,
     });
 
     report.add_check(CheckResult {
@@ -233,6 +258,8 @@ fn test_no_critical_failures_with_other_statuses() {
         message: "Warning".to_string(),
         
         suggestion: None,
+        
+        version: None,
     });
 
     report.add_check(CheckResult {
@@ -241,6 +268,8 @@ fn test_no_critical_failures_with_other_statuses() {
         message: "Failed".to_string(),
         
         suggestion: None,
+        
+        version: None,
     });
 
     assert!(!report.has_critical_failures());
@@ -257,6 +286,8 @@ fn test_summary_with_only_warnings() {
             message: "Warning".to_string(),
             
             suggestion: None,
+            
+            version: None,
         });
     }
 
@@ -276,6 +307,8 @@ fn test_check_result_debug_format() {
         message: "OK".to_string(),
         
         suggestion: None,
+        
+        version: None,
     };
 
     let debug_str = format!("{:?}", check);
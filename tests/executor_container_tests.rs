@@ -44,10 +44,11 @@ fn test_container_flag_explicit() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: true,
+        verbose: 1,
         use_container: true, // Explicitly request container
         use_zig: Some(false),
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -73,10 +74,11 @@ fn test_container_not_requested() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: false,
+        verbose: 0,
         use_container: false, // No container
         use_zig: Some(false),
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -107,10 +109,11 @@ fn test_container_with_cross_target() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: true,
+        verbose: 1,
         use_container: true, // Use container for cross-compilation
         use_zig: Some(false),
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -150,10 +153,11 @@ fn test_container_with_release_mode() -> Result<()> {
         release: true, // Release build in container
         cargo_args: vec![],
         toolchain: None,
-        verbose: false,
+        verbose: 0,
         use_container: true,
         use_zig: Some(false),
         operation: CargoOperation::Build,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -178,10 +182,11 @@ fn test_container_with_cargo_args() -> Result<()> {
         release: false,
         cargo_args: vec!["--all-features".to_string()],
         toolchain: None,
-        verbose: false,
+        verbose: 0,
         use_container: true,
         use_zig: Some(false),
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -205,10 +210,11 @@ fn test_container_priority_over_zig() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: true,
+        verbose: 1,
         use_container: true, // Container should take priority
         use_zig: Some(true), // Even if Zig requested
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -233,10 +239,11 @@ fn test_native_build_fallback() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: false,
+        verbose: 0,
         use_container: false, // No container
         use_zig: Some(false), // No Zig
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -22,17 +22,18 @@ fn test_build_options_with_all_fields() {
         release: true,
         cargo_args: vec!["--all-features".to_string()],
         toolchain: Some("nightly".to_string()),
-        verbose: true,
+        verbose: 1,
         use_container: true,
         use_zig: Some(true),
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     assert_eq!(options.target, Some("x86_64-unknown-linux-musl".to_string()));
     assert!(options.release);
     assert_eq!(options.cargo_args.len(), 1);
     assert_eq!(options.toolchain, Some("nightly".to_string()));
-    assert!(options.verbose);
+    assert_eq!(options.verbose, 1);
     assert!(options.use_container);
     assert_eq!(options.use_zig, Some(true));
     assert_eq!(options.operation, CargoOperation::Check);
@@ -109,10 +110,11 @@ fn test_build_options_partial_eq() {
         release: true,
         cargo_args: vec![],
         toolchain: None,
-        verbose: false,
+        verbose: 0,
         use_container: false,
         use_zig: None,
         operation: CargoOperation::Build,
+        ..Default::default()
     };
 
     let options2 = BuildOptions {
@@ -120,10 +122,11 @@ fn test_build_options_partial_eq() {
         release: true,
         cargo_args: vec![],
         toolchain: None,
-        verbose: false,
+        verbose: 0,
         use_container: false,
         use_zig: None,
         operation: CargoOperation::Build,
+        ..Default::default()
     };
 
     // Verify they have the same values (manual comparison since BuildOptions doesn't derive PartialEq)
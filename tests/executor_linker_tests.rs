@@ -43,10 +43,11 @@ fn test_build_with_host_linker() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: true, // Enable verbose to see linker messages
+        verbose: 1, // Enable verbose to see linker messages
         use_container: false,
         use_zig: Some(false),
         operation: CargoOperation::Check, // Use check for faster test
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -70,10 +71,11 @@ fn test_linker_detection_verbose_mode() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: true, // Verbose mode shows linker detection
+        verbose: 1, // Verbose mode shows linker detection
         use_container: false,
         use_zig: Some(false),
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -106,10 +108,11 @@ fn test_cross_compile_without_linker() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: true,
+        verbose: 1,
         use_container: false,
         use_zig: Some(false), // Disable Zig to test linker detection
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -169,10 +172,11 @@ fn test_build_with_explicit_toolchain() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: Some("stable".to_string()),
-        verbose: false,
+        verbose: 0,
         use_container: false,
         use_zig: Some(false),
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -196,10 +200,11 @@ fn test_build_with_nightly_toolchain() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: Some("nightly".to_string()),
-        verbose: false,
+        verbose: 0,
         use_container: false,
         use_zig: Some(false),
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -244,10 +249,11 @@ fn test_multiple_targets_with_linkers() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: false,
+        verbose: 0,
         use_container: false,
         use_zig: Some(false),
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     let result = builder.build_all(&targets, &options);
@@ -269,10 +275,11 @@ fn test_target_preparation_with_toolchain() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: Some("stable".to_string()),
-        verbose: false,
+        verbose: 0,
         use_container: false,
         use_zig: Some(false),
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -45,10 +45,11 @@ fn test_zig_disabled_via_flag() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: true, // See Zig disabled message
+        verbose: 1, // See Zig disabled message
         use_container: false,
         use_zig: Some(false), // Explicitly disable Zig
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -72,10 +73,11 @@ fn test_zig_auto_mode_same_os() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: true,
+        verbose: 1,
         use_container: false,
         use_zig: None, // Auto mode - should NOT use Zig for same OS
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -99,10 +101,11 @@ fn test_zig_forced_flag() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: true,
+        verbose: 1,
         use_container: false,
         use_zig: Some(true), // Force Zig even for same OS
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -136,10 +139,11 @@ fn test_zig_cross_os_auto_detection() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: true,
+        verbose: 1,
         use_container: false,
         use_zig: None, // Auto mode - should TRY to use Zig for cross-OS
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -202,10 +206,11 @@ fn test_zig_with_verbose_output() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: true, // Should show Zig detection messages
+        verbose: 1, // Should show Zig detection messages
         use_container: false,
         use_zig: Some(true), // Try to use Zig
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -230,10 +235,11 @@ fn test_zig_unsupported_target_with_force() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: true,
+        verbose: 1,
         use_container: false,
         use_zig: Some(true), // Force Zig for unsupported target
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -260,10 +266,11 @@ fn test_zig_with_release_build() -> Result<()> {
         release: true, // Release mode
         cargo_args: vec![],
         toolchain: None,
-        verbose: false,
+        verbose: 0,
         use_container: false,
         use_zig: Some(true),
         operation: CargoOperation::Build, // Full build
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -294,10 +301,11 @@ fn test_zig_detection_with_different_operations() -> Result<()> {
             release: false,
             cargo_args: vec![],
             toolchain: None,
-            verbose: false,
+            verbose: 0,
             use_container: false,
             use_zig: None, // Auto mode
             operation: op,
+            ..Default::default()
         };
 
         let result = builder.build(&options);
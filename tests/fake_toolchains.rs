@@ -0,0 +1,2364 @@
+// End-to-end tests of the CLI against fake toolchains (xcargo::testing)
+// Shims record every rustup/cargo/docker invocation, so these tests check
+// exactly what xcargo runs without needing real cross toolchains.
+#![cfg(unix)]
+
+use std::process::Output;
+use xcargo::testing::{FakeTools, Project, Response};
+
+const HOST: &str = "x86_64-unknown-linux-gnu";
+
+fn xcargo(tools: &FakeTools, project: &Project, args: &[&str]) -> Output {
+    tools
+        .command(env!("CARGO_BIN_EXE_xcargo"))
+        .args(args)
+        .current_dir(project.path())
+        .output()
+        .unwrap()
+}
+
+fn text(output: &Output) -> String {
+    format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
+
+#[test]
+fn test_cross_build_installs_target_and_runs_cargo() {
+    let project = Project::binary("app").unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &["build", "--target", "aarch64-unknown-linux-gnu"],
+    );
+    assert!(output.status.success(), "{}", text(&output));
+
+    let rustup = tools.invocations("rustup").unwrap();
+    assert!(rustup.contains(&vec![
+        "target".to_string(),
+        "add".to_string(),
+        "aarch64-unknown-linux-gnu".to_string(),
+        "--toolchain".to_string(),
+        "stable".to_string(),
+    ]));
+    assert_eq!(
+        tools.invocations("cargo").unwrap(),
+        vec![vec!["build", "--target", "aarch64-unknown-linux-gnu"]]
+    );
+}
+
+#[test]
+fn test_no_install_fails_on_missing_target() {
+    let project = Project::binary("app").unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &[
+            "build",
+            "--no-install",
+            "--target",
+            "aarch64-unknown-linux-gnu",
+        ],
+    );
+    assert!(!output.status.success());
+    assert!(text(&output).contains("--no-install forbids installing it"));
+    assert!(text(&output).contains("rustup target add aarch64-unknown-linux-gnu"));
+    assert!(tools.invocations("cargo").unwrap().is_empty());
+    let rustup = tools.invocations("rustup").unwrap();
+    assert!(!rustup
+        .iter()
+        .any(|args| args.starts_with(&["target".to_string(), "add".to_string()])));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_toolchain_provision_uses_package_manager() {
+    let project = Project::binary("app").unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools.respond("apt-get", &[], Response::ok("")).unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &["toolchain", "provision", "x86_64-pc-windows-gnu"],
+    );
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("Linker ready: x86_64-w64-mingw32-gcc"));
+    assert_eq!(
+        tools.invocations("apt-get").unwrap(),
+        vec![vec!["install", "-y", "gcc-mingw-w64-x86-64"]]
+    );
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &["toolchain", "provision", "wasm32-unknown-unknown"],
+    );
+    assert!(!output.status.success());
+    assert!(text(&output).contains("No cross GCC to provision"));
+}
+
+#[test]
+fn test_build_uses_provisioned_standalone_linker() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let project = Project::binary("app")
+        .unwrap()
+        .config("[container]\nuse_when = \"never\"\n")
+        .unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+    let target = "x86_64-pc-windows-gnu";
+    let bin = tools
+        .home_dir()
+        .join(".xcargo/tools")
+        .join(target)
+        .join("bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    let linker = bin.join("x86_64-w64-mingw32-gcc");
+    std::fs::write(&linker, "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(&linker, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = xcargo(&tools, &project, &["build", "-v", "--target", target]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains(&format!(
+        "Setting CARGO_TARGET_X86_64_PC_WINDOWS_GNU_LINKER={}",
+        linker.display()
+    )));
+}
+
+#[test]
+fn test_toolchain_provision_from_catalog() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config("[toolchain]\ncatalog_url = \"https://mirror.example.invalid/catalog.json\"\n")
+        .unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools
+        .respond("curl", &[], Response::failure(22, ""))
+        .unwrap();
+    let host = format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS);
+    std::fs::create_dir_all(tools.home_dir().join(".xcargo")).unwrap();
+    let cache = |sha256: &str| {
+        std::fs::write(
+            tools.home_dir().join(".xcargo/catalog.json"),
+            format!(
+                r#"{{"version": 1, "toolchains": [{{"name": "corp", "target": "x86_64-unknown-freebsd", "hosts": ["{host}"], "url": "https://example.invalid/freebsd.tar.xz", "sha256": "{sha256}", "linker": "x86_64-unknown-freebsd-clang"}}]}}"#
+            ),
+        )
+        .unwrap();
+    };
+
+    // A fresh cached catalog is used as is, and an entry without a
+    // checksum is never downloaded
+    cache("");
+    let output = xcargo(
+        &tools,
+        &project,
+        &["toolchain", "provision", "x86_64-unknown-freebsd"],
+    );
+    assert!(!output.status.success());
+    assert!(text(&output).contains(
+        "download https://example.invalid/freebsd.tar.xz into ~/.xcargo/tools/x86_64-unknown-freebsd"
+    ));
+    assert!(text(&output).contains("The catalog has no valid sha256 for corp"));
+    assert!(tools.invocations("curl").unwrap().is_empty());
+
+    cache(&"ab".repeat(32));
+    let output = xcargo(
+        &tools,
+        &project,
+        &["provision", "--target", "x86_64-unknown-freebsd"],
+    );
+    assert!(!output.status.success());
+    assert!(text(&output).contains("Failed to download https://example.invalid/freebsd.tar.xz"));
+    assert_eq!(tools.invocations("curl").unwrap().len(), 1);
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &["toolchain", "provision", "--update-catalog"],
+    );
+    assert!(!output.status.success());
+    assert!(text(&output).contains(
+        "Failed to download the toolchain catalog from https://mirror.example.invalid/catalog.json"
+    ));
+}
+
+#[test]
+fn test_downloads_use_network_settings() {
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools
+        .respond("curl", &[], Response::failure(22, ""))
+        .unwrap();
+    let bundle = tools.home_dir().join("corp.pem");
+    std::fs::write(&bundle, "").unwrap();
+    let project = Project::binary("app")
+        .unwrap()
+        .config(&format!(
+            "[network]\nproxy = \"http://proxy.corp.invalid:3128\"\nca_bundle = \"{}\"\n",
+            bundle.display()
+        ))
+        .unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &["toolchain", "provision", "--update-catalog"],
+    );
+    assert!(!output.status.success());
+    let curl = tools.invocations("curl").unwrap();
+    assert_eq!(
+        curl[0][..5],
+        [
+            "-fsSL",
+            "--proxy",
+            "http://proxy.corp.invalid:3128",
+            "--cacert",
+            &bundle.to_string_lossy()
+        ]
+    );
+}
+
+#[test]
+fn test_cargo_config_target_settings_are_honored() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config("[container]\nuse_when = \"never\"\n\n[targets.x86_64-pc-windows-gnu]\nrustflags = [\"-Clink-arg=-s\"]\n")
+        .unwrap();
+    project
+        .file(
+            ".cargo/config.toml",
+            "[target.x86_64-pc-windows-gnu]\nlinker = \"x86_64-w64-mingw32-gcc\"\nrustflags = [\"-Ctarget-feature=+crt-static\"]\n",
+        )
+        .unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+    let args = ["build", "-v", "--target", "x86_64-pc-windows-gnu"];
+
+    // Cargo uses its configured linker, and keeps its rustflags
+    let output = xcargo(&tools, &project, &args);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(!text(&output).contains("CARGO_TARGET_X86_64_PC_WINDOWS_GNU_LINKER"));
+    assert!(text(&output).contains("RUSTFLAGS=-C target-feature=+crt-static -C link-arg=-s"));
+
+    let project = project
+        .config("[container]\nuse_when = \"never\"\n\n[targets.x86_64-pc-windows-gnu]\nlinker = \"clang\"\n")
+        .unwrap();
+    let output = xcargo(&tools, &project, &args);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains(
+        "xcargo.toml sets linker = clang for x86_64-pc-windows-gnu, overriding x86_64-w64-mingw32-gcc"
+    ));
+    assert!(text(&output).contains("CARGO_TARGET_X86_64_PC_WINDOWS_GNU_LINKER=clang"));
+}
+
+#[test]
+fn test_rustflags_are_merged() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config("[container]\nuse_when = \"never\"\n\n[targets.x86_64-pc-windows-gnu]\nrustflags = [\"-C\", \"opt-level=2\", \"--cfg\", \"ci\"]\n")
+        .unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &[
+            "build",
+            "-v",
+            "--target",
+            "x86_64-pc-windows-gnu",
+            "--rustflags",
+            "-Copt-level=3 --cfg=ci",
+        ],
+    );
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("RUSTFLAGS=-C opt-level=3 --cfg ci"));
+    assert!(text(&output).contains("--cfg ci (xcargo.toml)"));
+    assert!(text(&output)
+        .contains("Rustflag -C opt-level=3 (--rustflags) overrides -C opt-level=2 (xcargo.toml)"));
+}
+
+#[test]
+fn test_target_matrix() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config(&format!(
+            "[targets]\ndefault = [\"{HOST}\", \"x86_64-pc-windows-gnu\"]\n\n[container]\nuse_when = \"never\"\n"
+        ))
+        .unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+
+    let output = xcargo(&tools, &project, &["targets", "matrix", "--json"]);
+    assert!(output.status.success(), "{}", text(&output));
+    let rows: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(rows[0]["target"], HOST);
+    assert_eq!(rows[0]["installed"], true);
+    assert_eq!(rows[0]["buildable"], true);
+    assert_eq!(rows[1]["target"], "x86_64-pc-windows-gnu");
+    assert_eq!(rows[1]["installed"], false);
+    assert_eq!(rows[1]["zig"]["reason"], "Zig is not installed");
+    assert_eq!(rows[1]["buildable"], false);
+
+    let output = xcargo(&tools, &project, &["target", "matrix"]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("x86_64-pc-windows-gnu cannot be built here"));
+}
+
+#[test]
+fn test_target_search() {
+    let project = Project::binary("app").unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools
+        .respond(
+            "rustup",
+            &["target", "list"],
+            Response::ok(
+                "aarch64-linux-android\naarch64-unknown-linux-gnu (installed)\naarch64-unknown-linux-musl\nx86_64-unknown-linux-musl\n",
+            ),
+        )
+        .unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &[
+            "target", "search", "aarch64", "--os", "linux", "--env", "musl",
+        ],
+    );
+    assert!(output.status.success(), "{}", text(&output));
+    let out = text(&output);
+    assert!(
+        out.contains("aarch64-unknown-linux-musl  Tier 2 with host tools"),
+        "{out}"
+    );
+    assert!(!out.contains("x86_64-unknown-linux-musl"), "{out}");
+    assert!(!out.contains("aarch64-linux-android"), "{out}");
+
+    let output = xcargo(&tools, &project, &["target", "search", "--installed"]);
+    let out = text(&output);
+    assert!(out.contains("aarch64-unknown-linux-gnu  Tier 1"), "{out}");
+    assert!(out.contains("1 target"), "{out}");
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &["target", "search", "^x86_64-.*-musl$", "--regex"],
+    );
+    assert!(text(&output).contains("x86_64-unknown-linux-musl"));
+    let output = xcargo(&tools, &project, &["target", "search", "wasm"]);
+    assert!(text(&output).contains("No targets match"));
+}
+
+#[test]
+fn test_static_musl_build_needs_musl_compiler() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config("[container]\nuse_when = \"never\"\n")
+        .unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    let metadata = format!(
+        r#"{{"target_directory":"{}","packages":[{{"name":"app","targets":[{{"name":"app","kind":["bin"]}}]}}]}}"#,
+        project.path().join("target").display()
+    );
+    tools
+        .respond("cargo", &["metadata"], Response::ok(metadata))
+        .unwrap();
+    let args = ["build", "-v", "--target", "linux-musl", "--static"];
+
+    let output = xcargo(&tools, &project, &args);
+    assert!(!output.status.success());
+    assert!(text(&output).contains("need Zig or a musl C compiler"));
+    assert!(!tools
+        .invocations("cargo")
+        .unwrap()
+        .iter()
+        .any(|args| args[0] == "build"));
+
+    tools.respond("musl-gcc", &[], Response::ok("")).unwrap();
+    let output = xcargo(&tools, &project, &args);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("CARGO_TARGET_X86_64_UNKNOWN_LINUX_MUSL_LINKER=musl-gcc"));
+    assert!(text(&output).contains("CC_x86_64_unknown_linux_musl=musl-gcc"));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_static_build_rejects_dynamic_binaries() {
+    let project = Project::binary("app").unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    let metadata = format!(
+        r#"{{"target_directory":"{}","packages":[{{"name":"app","targets":[{{"name":"app","kind":["bin"]}}]}}]}}"#,
+        project.path().join("target").display()
+    );
+    tools
+        .respond("cargo", &["metadata"], Response::ok(metadata))
+        .unwrap();
+
+    let output = xcargo(&tools, &project, &["build", "-v", "--static"]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("-C target-feature=+crt-static"));
+
+    // The fake cargo builds nothing; put a dynamically linked binary in place
+    let binary = project
+        .file(format!("target/{HOST}/debug/app"), "")
+        .unwrap();
+    std::fs::copy(env!("CARGO_BIN_EXE_xcargo"), binary).unwrap();
+    let output = xcargo(&tools, &project, &["build", "--static"]);
+    assert!(!output.status.success());
+    assert!(text(&output).contains("are dynamically linked, but a static build was asked for"));
+}
+
+#[test]
+fn test_offline_build_lists_what_is_missing() {
+    let project = Project::binary("app").unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &[
+            "build",
+            "--offline",
+            "--target",
+            "aarch64-unknown-linux-gnu",
+        ],
+    );
+    assert!(!output.status.success());
+    assert!(text(&output).contains("rustup target add aarch64-unknown-linux-gnu"));
+    assert!(tools.invocations("cargo").unwrap().is_empty());
+    let rustup = tools.invocations("rustup").unwrap();
+    assert!(!rustup
+        .iter()
+        .any(|args| args.starts_with(&["target".to_string(), "add".to_string()])));
+
+    let output = xcargo(&tools, &project, &["build", "--offline", "--target", HOST]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert_eq!(
+        tools.invocations("cargo").unwrap(),
+        vec![vec!["build", "--target", HOST, "--offline"]]
+    );
+}
+
+#[test]
+fn test_offline_config_applies_to_container_builds() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config("[build]\noffline = true\n[container]\nuse_when = \"always\"\n")
+        .unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools.docker().unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &["build", "--target", "aarch64-unknown-linux-gnu"],
+    );
+    assert!(!output.status.success());
+    assert!(text(&output).contains("docker pull"), "{}", text(&output));
+    let docker = tools.invocations("docker").unwrap();
+    assert!(!docker
+        .iter()
+        .any(|args| args.first().is_some_and(|a| a == "pull")));
+}
+
+#[test]
+fn test_update_images_pins_digests() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config("[targets]\ndefault = [\"aarch64-unknown-linux-gnu\"]\n")
+        .unwrap();
+    let image = "ghcr.io/cross-rs/aarch64-unknown-linux-gnu";
+    let digest = format!("sha256:{}", "3c".repeat(32));
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools.docker().unwrap();
+    tools
+        .respond(
+            "docker",
+            &["image", "inspect"],
+            Response::ok(format!("{image}@{digest}\n")),
+        )
+        .unwrap();
+
+    let output = xcargo(&tools, &project, &["container", "update-images"]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(tools
+        .invocations("docker")
+        .unwrap()
+        .contains(&vec!["pull".to_string(), format!("{image}:latest")]));
+    let config = std::fs::read_to_string(project.path().join("xcargo.toml")).unwrap();
+    assert!(
+        config.contains(&format!("{image}:latest@{digest}")),
+        "{config}"
+    );
+
+    let output = xcargo(&tools, &project, &["container", "update-images"]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("is up to date"), "{}", text(&output));
+}
+
+#[test]
+fn test_registry_mirror_logs_in_before_pulling() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config(
+            "[container]\nuse_when = \"always\"\n\
+             registry = \"registry.corp.example/cross-rs\"\n",
+        )
+        .unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools.docker().unwrap();
+
+    let output = tools
+        .command(env!("CARGO_BIN_EXE_xcargo"))
+        .args(["build", "--target", "aarch64-unknown-linux-gnu"])
+        .env("XCARGO_REGISTRY_USER", "ci")
+        .env("XCARGO_REGISTRY_PASSWORD", "secret")
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", text(&output));
+
+    let docker = tools.invocations("docker").unwrap();
+    let position = |command: &[&str]| docker.iter().position(|args| args == command);
+    let login = position(&[
+        "login",
+        "registry.corp.example",
+        "--username",
+        "ci",
+        "--password-stdin",
+    ])
+    .expect("logged in to the mirror");
+    let pull = position(&[
+        "pull",
+        "registry.corp.example/cross-rs/aarch64-unknown-linux-gnu:latest",
+    ])
+    .expect("pulled from the mirror");
+    assert!(login < pull);
+}
+
+#[test]
+fn test_container_build_runs_as_the_invoking_user() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config("[container]\nuse_when = \"always\"\n")
+        .unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools.docker().unwrap();
+    tools
+        .respond("id", &["-u"], Response::ok("1000\n"))
+        .unwrap();
+    tools.respond("id", &["-g"], Response::ok("100\n")).unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &["build", "--target", "aarch64-unknown-linux-gnu"],
+    );
+    assert!(output.status.success(), "{}", text(&output));
+    let docker = tools.invocations("docker").unwrap();
+    let run = docker
+        .iter()
+        .find(|args| args.first().is_some_and(|a| a == "run"))
+        .unwrap();
+    assert!(
+        run.windows(2).any(|pair| pair == ["--user", "1000:100"]),
+        "{run:?}"
+    );
+    assert!(run.windows(2).any(|pair| pair == ["-e", "CARGO_HOME"]));
+    assert!(run.iter().any(|arg| arg.ends_with(":/cargo")));
+
+    project
+        .file(
+            "xcargo.toml",
+            "[container]\nuse_when = \"always\"\nrun_as_user = false\n",
+        )
+        .unwrap();
+    let output = xcargo(
+        &tools,
+        &project,
+        &["build", "--target", "aarch64-unknown-linux-gnu"],
+    );
+    assert!(output.status.success(), "{}", text(&output));
+    let docker = tools.invocations("docker").unwrap();
+    let run = docker
+        .iter()
+        .rfind(|args| args.first().is_some_and(|a| a == "run"))
+        .unwrap();
+    assert!(!run.contains(&"--user".to_string()));
+}
+
+#[test]
+fn test_cross_toml_is_honored() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config("[container]\nuse_when = \"always\"\n")
+        .unwrap();
+    project
+        .file(
+            "Cross.toml",
+            "[build]\npre-build = [\"apt-get update\"]\n\n[build.env]\npassthrough = [\"APP_TOKEN\", \"APP_UNSET\"]\n\n[target.aarch64-unknown-linux-gnu]\nimage = \"example/aarch64:1\"\n",
+        )
+        .unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools.docker().unwrap();
+
+    let output = tools
+        .command(env!("CARGO_BIN_EXE_xcargo"))
+        .args(["build", "--target", "aarch64-unknown-linux-gnu"])
+        .current_dir(project.path())
+        .env("APP_TOKEN", "secret")
+        .env_remove("APP_UNSET")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", text(&output));
+    let docker = tools.invocations("docker").unwrap();
+    let run = docker
+        .iter()
+        .find(|args| args.first().is_some_and(|a| a == "run"))
+        .unwrap();
+    // Pre-build hooks run as root, as cross runs them
+    assert!(!run.contains(&"--user".to_string()), "{run:?}");
+    // Passed by name, so the value stays out of docker's argv
+    assert!(run.windows(2).any(|pair| pair == ["-e", "APP_TOKEN"]));
+    assert!(!run.iter().any(|arg| arg.contains("secret")));
+    assert!(!run.iter().any(|arg| arg.starts_with("APP_UNSET")));
+    let image = run
+        .iter()
+        .position(|arg| arg == "example/aarch64:1")
+        .unwrap();
+    assert_eq!(run[image + 1..image + 3], ["sh", "-c"]);
+    assert!(
+        run[image + 3].starts_with("apt-get update && exec 'cargo' 'build'"),
+        "{run:?}"
+    );
+}
+
+#[test]
+fn test_pull_policy_never_needs_the_image() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config("[container]\nuse_when = \"always\"\npull_policy = \"never\"\n")
+        .unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools.docker().unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &["build", "--target", "aarch64-unknown-linux-gnu"],
+    );
+    assert!(!output.status.success());
+    assert!(
+        text(&output).contains("pull_policy is \"never\""),
+        "{}",
+        text(&output)
+    );
+    let docker = tools.invocations("docker").unwrap();
+    assert!(!docker
+        .iter()
+        .any(|args| args.first().is_some_and(|a| a == "pull")));
+}
+
+#[test]
+fn test_build_std_installs_rust_src() {
+    let project = Project::binary("app").unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools
+        .respond(
+            "rustup",
+            &["component", "list"],
+            Response::ok("llvm-tools-x86_64-unknown-linux-gnu (installed)\nrust-src\n"),
+        )
+        .unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &["build", "--target", HOST, "--", "-Zbuild-std=core"],
+    );
+    assert!(output.status.success(), "{}", text(&output));
+    let rustup = tools.invocations("rustup").unwrap();
+    assert!(rustup.contains(&vec![
+        "component".to_string(),
+        "add".to_string(),
+        "rust-src".to_string(),
+        "--toolchain".to_string(),
+        "stable".to_string(),
+    ]));
+
+    // Installed components are left alone
+    let output = xcargo(
+        &tools,
+        &project,
+        &["toolchain", "component", "add", "llvm-tools"],
+    );
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("llvm-tools is already installed"));
+    let adds = tools
+        .invocations("rustup")
+        .unwrap()
+        .into_iter()
+        .filter(|args| args.starts_with(&["component".to_string(), "add".to_string()]))
+        .count();
+    assert_eq!(adds, 1);
+}
+
+#[test]
+fn test_toolchain_commands_drive_rustup() {
+    let project = Project::binary("app").unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+
+    let output = xcargo(&tools, &project, &["toolchain", "list"]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains(&format!("stable-{HOST} (default)")));
+
+    let output = xcargo(&tools, &project, &["toolchain", "install", "stable"]);
+    assert!(text(&output).contains("stable is already installed"));
+    let output = xcargo(&tools, &project, &["toolchain", "install", "nightly"]);
+    assert!(output.status.success(), "{}", text(&output));
+    let output = xcargo(&tools, &project, &["toolchain", "default", "nightly"]);
+    assert!(output.status.success(), "{}", text(&output));
+
+    let rustup = tools.invocations("rustup").unwrap();
+    assert!(rustup.contains(&vec![
+        "toolchain".to_string(),
+        "install".to_string(),
+        "nightly".to_string(),
+    ]));
+    assert!(!rustup.contains(&vec![
+        "toolchain".to_string(),
+        "install".to_string(),
+        "stable".to_string(),
+    ]));
+    assert!(rustup.contains(&vec!["default".to_string(), "nightly".to_string()]));
+
+    let output = xcargo(&tools, &project, &["toolchain", "status"]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains(&format!("Targets:    {HOST}")));
+}
+
+#[test]
+fn test_uninstall_removes_only_what_xcargo_installed() {
+    let project = Project::binary("app").unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+    let target = "aarch64-unknown-linux-gnu";
+
+    let output = xcargo(&tools, &project, &["build", "--target", target]);
+    assert!(output.status.success(), "{}", text(&output));
+    tools.clear_invocations().unwrap();
+
+    let output = xcargo(&tools, &project, &["uninstall", "--dry-run"]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("rustup target aarch64-unknown-linux-gnu (stable)"));
+    assert!(tools.invocations("rustup").unwrap().is_empty());
+
+    let output = xcargo(&tools, &project, &["uninstall", "--target", target]);
+    assert!(output.status.success(), "{}", text(&output));
+    let rustup = tools.invocations("rustup").unwrap();
+    let remove = ["target", "remove", target, "--toolchain", "stable"];
+    assert!(rustup.iter().any(|args| args == &remove));
+
+    // The ledger entry is gone; the preinstalled host target was never in it
+    let output = xcargo(&tools, &project, &["uninstall"]);
+    assert!(text(&output).contains("Nothing xcargo installed"));
+}
+
+#[test]
+fn test_build_timeout_stops_cargo() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config("[build]\ntimeout = \"30m\"\n")
+        .unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools
+        .respond("cargo", &["build"], Response::ok("").delayed(30))
+        .unwrap();
+
+    // --timeout overrides build.timeout
+    let started = std::time::Instant::now();
+    let output = xcargo(
+        &tools,
+        &project,
+        &["build", "--target", HOST, "--timeout", "1s"],
+    );
+    assert!(!output.status.success());
+    assert!(
+        text(&output).contains("Timed out after"),
+        "{}",
+        text(&output)
+    );
+    assert!(started.elapsed() < std::time::Duration::from_secs(20));
+
+    let output = xcargo(&tools, &project, &["build", "--timeout", "soon"]);
+    assert!(!output.status.success());
+    assert!(text(&output).contains("not a duration"));
+}
+
+#[test]
+fn test_log_file_captures_cargo_output() {
+    let project = Project::binary("app").unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools
+        .respond(
+            "cargo",
+            &["build"],
+            Response::failure(101, "error[E0425]: cannot find value `x`"),
+        )
+        .unwrap();
+    let log = project.path().join("build.log");
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &[
+            "build",
+            "--target",
+            HOST,
+            "--log-file",
+            log.to_str().unwrap(),
+        ],
+    );
+    assert!(!output.status.success());
+    // Cargo's output still reaches the terminal
+    assert!(
+        text(&output).contains("cannot find value"),
+        "{}",
+        text(&output)
+    );
+
+    let events: Vec<serde_json::Value> = std::fs::read_to_string(&log)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let cargo_line = events
+        .iter()
+        .find(|event| event["target"] == "xcargo::cargo")
+        .expect("cargo output logged");
+    assert_eq!(
+        cargo_line["fields"]["message"],
+        "error[E0425]: cannot find value `x`"
+    );
+    assert_eq!(cargo_line["fields"]["stream"], "stderr");
+    assert_eq!(cargo_line["span"]["triple"], HOST);
+    assert!(events
+        .iter()
+        .any(|event| event["target"] == "xcargo::output" && event["fields"]["kind"] == "Progress"));
+}
+
+#[test]
+fn test_github_annotations_for_compiler_errors() {
+    let project = Project::binary("app").unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    let message = r#"{"reason":"compiler-message","package_id":"app 0.1.0","message":{"message":"cannot find value `x` in this scope","code":{"code":"E0425","explanation":null},"level":"error","spans":[{"file_name":"src/main.rs","byte_start":20,"byte_end":21,"line_start":2,"line_end":2,"column_start":20,"column_end":21,"is_primary":true,"text":[],"label":null}],"children":[],"rendered":"error[E0425]: cannot find value `x` in this scope\n"}}"#;
+    tools
+        .respond(
+            "cargo",
+            &["build"],
+            Response {
+                stdout: format!("{message}\n"),
+                ..Response::failure(101, "error: could not compile `app`")
+            },
+        )
+        .unwrap();
+
+    let output = tools
+        .command(env!("CARGO_BIN_EXE_xcargo"))
+        .args(["build", "--target", HOST])
+        .env("GITHUB_ACTIONS", "true")
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(
+            "::error file=src/main.rs,line=2,col=20,endLine=2,title=E0425::cannot find value `x` in this scope"
+        ),
+        "{}",
+        text(&output)
+    );
+    assert!(stdout.contains("::group::xcargo build"));
+    // The rendered diagnostic replaces the raw JSON
+    assert!(!stdout.contains("compiler-message"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("error[E0425]"));
+
+    let cargo = tools.invocations("cargo").unwrap();
+    assert!(cargo
+        .iter()
+        .any(|args| args.contains(&"--message-format=json-diagnostic-rendered-ansi".to_string())));
+}
+
+#[test]
+fn test_linker_failure_is_explained() {
+    let project = Project::binary("app").unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools
+        .respond(
+            "cargo",
+            &["build"],
+            Response::failure(
+                101,
+                "error: linking with `cc` failed: exit status: 1\n  = note: /usr/bin/ld: cannot find -lssl: No such file or directory\n",
+            ),
+        )
+        .unwrap();
+
+    let output = xcargo(&tools, &project, &["build", "--target", HOST]);
+    assert!(!output.status.success());
+    let shown = text(&output);
+    // Cargo's output is still shown, followed by the explanation
+    assert!(shown.contains("cannot find -lssl"), "{shown}");
+    assert!(shown.contains("(XC001)"), "{shown}");
+    assert!(shown.contains("`vendored` feature"), "{shown}");
+    assert!(shown.contains("xcargo explain XC001"), "{shown}");
+}
+
+#[test]
+fn test_timings_report_crates_per_target() {
+    let project = Project::binary("app").unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools
+        .respond(
+            "cargo",
+            &["+nightly", "--version"],
+            Response::ok("cargo 1.82.0-nightly (2f738d617 2024-08-13)\n"),
+        )
+        .unwrap();
+    let timing = |package: &str, duration: f64| {
+        format!(
+            r#"{{"reason":"timing-info","package_id":"registry+https://github.com/rust-lang/crates.io-index#{package}@1.0.0","target":{{"name":"{package}"}},"mode":"build","duration":{duration}}}"#
+        )
+    };
+    tools
+        .respond(
+            "cargo",
+            &["+nightly", "build"],
+            Response::ok(format!(
+                "{}\n{}\n",
+                timing("windows-sys", 12.5),
+                timing("serde", 3.25)
+            )),
+        )
+        .unwrap();
+    let target_dir = project.path().join("target");
+
+    let output = tools
+        .command(env!("CARGO_BIN_EXE_xcargo"))
+        .args(["build", "--timings", "--toolchain", "nightly"])
+        .args(["--target", "aarch64-unknown-linux-gnu"])
+        .env("CARGO_TARGET_DIR", &target_dir)
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", text(&output));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("timing-info"));
+    assert!(stdout.contains("aarch64-unknown-linux-gnu (debug): 15.8s across 2 units"));
+    let windows_sys = stdout.find("windows-sys").unwrap();
+    assert!(windows_sys < stdout.find("serde").unwrap());
+    assert!(stdout[windows_sys..]
+        .lines()
+        .next()
+        .unwrap()
+        .contains("12.5s"));
+    assert!(target_dir
+        .join("xcargo/timings/aarch64-unknown-linux-gnu.json")
+        .is_file());
+
+    let build = tools
+        .invocations("cargo")
+        .unwrap()
+        .into_iter()
+        .find(|args| args.contains(&"build".to_string()))
+        .unwrap();
+    assert!(build.contains(&"--timings=json".to_string()));
+    assert!(build.contains(&"-Zunstable-options".to_string()));
+}
+
+#[test]
+fn test_bench_compares_targets() {
+    let project = Project::binary("app").unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    let metadata = format!(
+        r#"{{"workspace_root":"{}","target_directory":"{}","packages":[]}}"#,
+        project.path().display(),
+        project.path().join("target").display()
+    );
+    tools
+        .respond("cargo", &["metadata"], Response::ok(metadata))
+        .unwrap();
+    tools
+        .respond(
+            "cargo",
+            &["bench"],
+            Response::ok("running 1 test\ntest parse ... bench:       1,250 ns/iter (+/- 40)\n"),
+        )
+        .unwrap();
+
+    let output = xcargo(&tools, &project, &["bench"]);
+    assert!(output.status.success(), "{}", text(&output));
+    let shown = text(&output);
+    assert!(shown.contains("test parse ... bench:"), "{shown}");
+    assert!(shown.contains("Benchmarks"), "{shown}");
+    assert!(shown.contains("1.25 µs"), "{shown}");
+    assert!(project
+        .path()
+        .join(format!(".xcargo/bench/{HOST}.json"))
+        .is_file());
+
+    // Nothing here runs aarch64 binaries, so its benchmarks are only built
+    let output = xcargo(
+        &tools,
+        &project,
+        &["bench", "--target", "aarch64-unknown-linux-gnu"],
+    );
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("only building its benchmarks"));
+    let bench = tools
+        .invocations("cargo")
+        .unwrap()
+        .into_iter()
+        .rfind(|args| args[0] == "bench")
+        .unwrap();
+    assert!(bench.contains(&"--no-run".to_string()));
+
+    let output = xcargo(&tools, &project, &["bench", "--report"]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("parse"));
+}
+
+#[test]
+fn test_coverage_instruments_tests() {
+    let project = Project::binary("app").unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    let target_dir = project.path().join("target");
+    let metadata = format!(
+        r#"{{"target_directory":"{}","packages":[]}}"#,
+        target_dir.display()
+    );
+    tools
+        .respond("cargo", &["metadata"], Response::ok(metadata))
+        .unwrap();
+
+    // The fake tests write no profiles, so there is nothing to merge
+    let output = xcargo(&tools, &project, &["coverage", "-v"]);
+    assert!(!output.status.success());
+    let shown = text(&output);
+    assert!(
+        shown.contains("-C instrument-coverage (xcargo coverage)"),
+        "{shown}"
+    );
+    assert!(shown.contains("wrote no coverage data"), "{shown}");
+    let profraw = target_dir.join(format!("xcargo/coverage/{HOST}/profraw"));
+    assert!(profraw.is_dir());
+    let test = tools
+        .invocations("cargo")
+        .unwrap()
+        .into_iter()
+        .find(|args| args[0] == "test")
+        .unwrap();
+    assert!(test.contains(&HOST.to_string()));
+
+    // Nothing here runs aarch64 binaries
+    let output = xcargo(
+        &tools,
+        &project,
+        &["coverage", "--target", "aarch64-unknown-linux-gnu"],
+    );
+    assert!(!output.status.success());
+    assert!(text(&output).contains("Nothing can run aarch64-unknown-linux-gnu tests here"));
+
+    let output = xcargo(&tools, &project, &["coverage", "--target", "wasm32-wasip1"]);
+    assert!(!output.status.success());
+    assert!(text(&output).contains("WebAssembly has no profiler runtime"));
+}
+
+#[test]
+fn test_timings_report_build_phases() {
+    let project = Project::binary("app").unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+    let target_dir = project.path().join("target");
+    let run = |args: &[&str]| {
+        tools
+            .command(env!("CARGO_BIN_EXE_xcargo"))
+            .args(args)
+            .env("CARGO_TARGET_DIR", &target_dir)
+            .current_dir(project.path())
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["timings"]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("No build phases were recorded yet"));
+
+    let output = run(&["build", "--target", "aarch64-unknown-linux-gnu"]);
+    assert!(output.status.success(), "{}", text(&output));
+    let output = run(&["test"]);
+    assert!(output.status.success(), "{}", text(&output));
+
+    let output = run(&["timings", "--html"]);
+    assert!(output.status.success(), "{}", text(&output));
+    let shown = text(&output);
+    assert!(shown.contains("Build Phases"), "{shown}");
+    assert!(
+        shown.contains("aarch64-unknown-linux-gnu build (debug)"),
+        "{shown}"
+    );
+    assert!(shown.contains(&format!("{HOST} test (debug)")), "{shown}");
+    assert!(shown.contains("across 2 builds"), "{shown}");
+    let report = std::fs::read_to_string(target_dir.join("xcargo/timings/report.html")).unwrap();
+    assert!(report.contains("aarch64-unknown-linux-gnu build (debug)"));
+
+    let output = run(&["timings", "--target", HOST]);
+    assert!(!text(&output).contains("aarch64-unknown-linux-gnu"));
+}
+
+#[test]
+fn test_history_lists_builds_and_trends() {
+    let project = Project::binary("app").unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools
+        .respond(
+            "cargo",
+            &["test"],
+            Response::failure(101, "error: test failed"),
+        )
+        .unwrap();
+
+    let output = xcargo(&tools, &project, &["history"]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("No builds were recorded yet"));
+
+    for _ in 0..2 {
+        let output = xcargo(&tools, &project, &["build", "--target", HOST]);
+        assert!(output.status.success(), "{}", text(&output));
+    }
+    let output = xcargo(&tools, &project, &["test", "--target", HOST]);
+    assert!(!output.status.success());
+
+    let output = xcargo(&tools, &project, &["history"]);
+    assert!(output.status.success(), "{}", text(&output));
+    let shown = text(&output);
+    assert!(shown.contains(&format!("{HOST} build (debug)")), "{shown}");
+    assert!(shown.contains("native"), "{shown}");
+    assert!(shown.contains("failed"), "{shown}");
+    assert!(shown.contains("over 2 run(s)"), "{shown}");
+    assert!(shown.contains("0 of 1 succeeded"), "{shown}");
+
+    let output = xcargo(&tools, &project, &["history", "--failed", "--json"]);
+    let lines: Vec<String> = text(&output).lines().map(str::to_string).collect();
+    assert_eq!(lines.len(), 1, "{lines:?}");
+    assert!(lines[0].contains("\"outcome\":\"failed\""), "{}", lines[0]);
+    assert!(lines[0].contains("\"operation\":\"test\""), "{}", lines[0]);
+
+    let other = Project::binary("other").unwrap();
+    let output = xcargo(&tools, &other, &["history"]);
+    assert!(text(&output).contains("No builds were recorded yet"));
+    let output = xcargo(&tools, &other, &["history", "--all-projects", "-n", "1"]);
+    assert!(text(&output).contains(&format!("{HOST} test (debug)")));
+    assert!(tools.home_dir().join(".xcargo/history.jsonl").is_file());
+}
+
+#[test]
+fn test_timings_need_nightly() {
+    let project = Project::binary("app").unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+
+    let output = xcargo(&tools, &project, &["build", "--timings", "--target", HOST]);
+    assert!(!output.status.success());
+    assert!(
+        text(&output).contains("needs a nightly cargo"),
+        "{}",
+        text(&output)
+    );
+    assert!(!tools
+        .invocations("cargo")
+        .unwrap()
+        .iter()
+        .any(|args| args.contains(&"build".to_string())));
+}
+
+#[test]
+fn test_doc_runs_cargo_doc_without_linker() {
+    let project = Project::binary("app").unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &["doc", "--target", "aarch64-unknown-linux-gnu", "--no-deps"],
+    );
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(!text(&output).contains("linker"), "{}", text(&output));
+
+    assert_eq!(
+        tools.invocations("cargo").unwrap(),
+        vec![vec![
+            "doc",
+            "--target",
+            "aarch64-unknown-linux-gnu",
+            "--no-deps"
+        ]]
+    );
+}
+
+#[test]
+fn test_clean_with_cargo_args_runs_cargo_clean() {
+    let project = Project::binary("app").unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &["clean", "--target", HOST, "--", "-p", "app"],
+    );
+    assert!(output.status.success(), "{}", text(&output));
+    assert_eq!(
+        tools.invocations("cargo").unwrap(),
+        vec![vec!["clean", "--target", HOST, "-p", "app"]]
+    );
+}
+
+#[test]
+fn test_host_target_builds_natively() {
+    let project = Project::binary("app").unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+
+    let output = xcargo(&tools, &project, &["build", "--target", HOST]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("Native build"));
+    assert_eq!(
+        tools.invocations("cargo").unwrap(),
+        vec![vec!["build", "--target", HOST]]
+    );
+
+    // An explicit --zig still applies to the host; Zig is not installed
+    let output = xcargo(&tools, &project, &["build", "--target", HOST, "--zig"]);
+    assert!(!output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("Zig"), "{}", text(&output));
+    assert_eq!(tools.invocations("cargo").unwrap().len(), 1);
+
+    let output = xcargo(&tools, &project, &["build", "--target", HOST, "--explain"]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("native build (target is the host)"));
+}
+
+#[test]
+fn test_broken_config_falls_back_for_explicit_target() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config("[targets]\ndefault = [\"x86_64-unknown-linux-gnu\"\n[build]\nparallel = true\n")
+        .unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+
+    let output = xcargo(&tools, &project, &["build", "--target", HOST]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("Ignoring broken configuration"));
+    assert!(text(&output).contains("xcargo.toml:3:1"));
+    assert_eq!(
+        tools.invocations("cargo").unwrap(),
+        vec![vec!["build", "--target", HOST]]
+    );
+
+    // The default targets come from the broken file
+    let output = xcargo(&tools, &project, &["build", "--all"]);
+    assert!(!output.status.success());
+    assert!(text(&output).contains("[X0002]"));
+    assert!(text(&output).contains("xcargo.toml:3:1"));
+}
+
+#[test]
+fn test_notifications_when_builds_finish() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config(&format!(
+            "[targets]\ndefault = [\"{HOST}\", \"aarch64-unknown-linux-gnu\"]\n\n\
+             [notifications]\ndesktop = true\nmin_duration = 0\n\n\
+             [[notifications.webhooks]]\nurl = \"https://hooks.slack.com/services/T/B/X\"\n"
+        ))
+        .unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools
+        .respond(
+            "cargo",
+            &["build", "--target", "aarch64-unknown-linux-gnu"],
+            Response::failure(101, "error: could not compile `app`"),
+        )
+        .unwrap();
+    tools.respond("notify-send", &[], Response::ok("")).unwrap();
+    tools
+        .respond("curl", &[], Response::failure(22, "HTTP 404"))
+        .unwrap();
+
+    let output = xcargo(&tools, &project, &["build", "--all", "--keep-going"]);
+    assert!(!output.status.success());
+
+    let desktop = tools.invocations("notify-send").unwrap();
+    assert_eq!(desktop.len(), 1, "{}", text(&output));
+    assert!(desktop[0].contains(&"--urgency=critical".to_string()));
+    let name = project.path().file_name().unwrap().to_string_lossy();
+    assert!(desktop[0].contains(&format!("xcargo build failed ({name})")));
+
+    let posted = tools.invocations("curl").unwrap();
+    assert_eq!(posted.len(), 1);
+    assert_eq!(
+        posted[0].last().unwrap(),
+        "https://hooks.slack.com/services/T/B/X"
+    );
+    assert!(posted[0]
+        .iter()
+        .any(|arg| arg.starts_with("{\"text\":") && arg.contains("aarch64-unknown-linux-gnu")));
+    // The webhook's secret path stays out of the warning
+    let shown = text(&output);
+    assert!(
+        shown.contains("Could not notify webhook https://hooks.slack.com:"),
+        "{shown}"
+    );
+    assert!(!shown.contains("/services/T/B/X"), "{shown}");
+
+    // Single-target builds are not announced
+    let output = xcargo(&tools, &project, &["build", "--target", HOST]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert_eq!(tools.invocations("notify-send").unwrap().len(), 1);
+}
+
+#[test]
+fn test_target_info_shows_plan_on_this_host() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config(
+            "[container]\nuse_when = \"never\"\n\n\
+             [targets.\"aarch64-unknown-linux-gnu\".env]\n\
+             CARGO_TARGET_AARCH64_UNKNOWN_LINUX_GNU_RUNNER = \"qemu-aarch64\"\n",
+        )
+        .unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &["target", "info", "aarch64-unknown-linux-gnu"],
+    );
+    assert!(output.status.success(), "{}", text(&output));
+    let out = text(&output);
+    assert!(out.contains("Strategy:    native toolchain"), "{out}");
+    assert!(out.contains("Container:   ghcr.io/cross-rs/aarch64-unknown-linux-gnu"));
+    assert!(out.contains("Zig:         supported, but Zig is not installed"));
+    assert!(out.contains("Test runner: qemu-aarch64"));
+    assert!(out.contains("xcargo build --target aarch64-unknown-linux-gnu --zig"));
+
+    let output = xcargo(&tools, &project, &["target", "info", HOST]);
+    assert!(text(&output).contains("Strategy:    native build (target is the host)"));
+    assert!(text(&output).contains("Test runner: runs natively"));
+}
+
+#[test]
+fn test_release_and_cargo_args_passed_through() {
+    let project = Project::binary("app").unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &["build", "--target", HOST, "--release", "--", "--locked"],
+    );
+    assert!(output.status.success(), "{}", text(&output));
+
+    let cargo = tools.invocations("cargo").unwrap();
+    assert_eq!(cargo.len(), 1);
+    assert_eq!(cargo[0][..3], ["build", "--target", HOST]);
+    assert!(cargo[0].contains(&"--release".to_string()));
+    assert!(cargo[0].contains(&"--locked".to_string()));
+}
+
+#[test]
+fn test_verbosity_levels() {
+    let project = Project::binary("app").unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+    let target = "aarch64-unknown-linux-gnu";
+    let build = |verbosity: &str| {
+        tools.clear_invocations().unwrap();
+        let output = tools
+            .command(env!("CARGO_BIN_EXE_xcargo"))
+            .args(["build", "--target", target, verbosity])
+            .env("CARGO_REGISTRY_TOKEN", "cio_secret")
+            .current_dir(project.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", text(&output));
+        let passed = tools.invocations("cargo").unwrap()[0].contains(&"--verbose".to_string());
+        (text(&output), passed)
+    };
+
+    let (output, passed) = build("-v");
+    assert!(output.contains(&format!("Executing: cargo build --target {target}")));
+    assert!(!output.contains("CARGO_REGISTRY_TOKEN"));
+    assert!(!passed);
+
+    let (output, passed) = build("-vv");
+    assert!(output.contains(&format!(
+        "Executing: cargo build --target {target} --verbose"
+    )));
+    assert!(!output.contains("CARGO_REGISTRY_TOKEN"));
+    assert!(passed);
+
+    let (output, passed) = build("-vvv");
+    assert!(output.contains("CARGO_REGISTRY_TOKEN=<redacted>"));
+    assert!(!output.contains("cio_secret"));
+    assert!(passed);
+}
+
+#[test]
+fn test_bin_and_example_selection() {
+    let project = Project::binary("app").unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+
+    let args = format!("build --target {HOST} --bin app --example demo -- --locked");
+    let args: Vec<&str> = args.split(' ').collect();
+    let output = xcargo(&tools, &project, &args);
+    assert!(output.status.success(), "{}", text(&output));
+
+    let cargo = tools.invocations("cargo").unwrap();
+    assert_eq!(
+        cargo[0].join(" "),
+        format!("build --target {HOST} --bin app --example demo --locked")
+    );
+}
+
+#[test]
+fn test_cargo_failure_fails_the_build() {
+    let project = Project::binary("app").unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools
+        .respond(
+            "cargo",
+            &["build"],
+            Response::failure(101, "error: could not compile `app`\n"),
+        )
+        .unwrap();
+
+    let output = xcargo(&tools, &project, &["build", "--target", HOST]);
+    assert!(!output.status.success());
+    let text = text(&output);
+    assert!(text.contains("could not compile"), "{text}");
+    assert!(text.contains("Build failed"), "{text}");
+}
+
+#[test]
+fn test_multi_target_retries_keep_going_and_resume() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config(&format!(
+            "[targets]\ndefault = [\"{HOST}\", \"aarch64-unknown-linux-gnu\"]\n\n\
+             [build]\nparallel = false\nretries = 1\nretry_delay = 0\n"
+        ))
+        .unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools
+        .respond(
+            "cargo",
+            &["build", "--target", HOST],
+            Response::failure(101, "error: could not compile `app`\n"),
+        )
+        .unwrap();
+
+    // The first target fails on both attempts and the other is still built
+    let output = xcargo(&tools, &project, &["build", "--all"]);
+    assert!(!output.status.success());
+    assert!(text(&output).contains("--resume"), "{}", text(&output));
+    let cargo = tools.invocations("cargo").unwrap();
+    assert_eq!(cargo.len(), 3);
+    assert_eq!(cargo[2][2], "aarch64-unknown-linux-gnu");
+
+    // --keep-going is passed on to cargo, once its version has it
+    tools.clear_invocations().unwrap();
+    let args = ["build", "--all", "--retries", "0", "--keep-going"];
+    let output = xcargo(&tools, &project, &args);
+    assert!(!output.status.success());
+    let cargo = tools.invocations("cargo").unwrap();
+    assert_eq!(cargo[0], vec!["--version"]);
+    assert_eq!(cargo.len(), 3);
+    assert!(cargo[1..]
+        .iter()
+        .all(|args| args.contains(&"--keep-going".to_string())));
+
+    // Only the target that failed is built again
+    tools.clear_invocations().unwrap();
+    let output = xcargo(&tools, &project, &[&args[..], &["--resume"]].concat());
+    assert!(text(&output).contains("already built"), "{}", text(&output));
+    assert_eq!(
+        tools.invocations("cargo").unwrap(),
+        vec![
+            vec!["--version"],
+            vec![
+                "build",
+                "--target",
+                HOST,
+                "--keep-going",
+                "--message-format=json-diagnostic-rendered-ansi"
+            ]
+        ]
+    );
+}
+
+#[test]
+fn test_keep_going_is_a_build_flag() {
+    let project = Project::binary("app").unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+
+    for command in ["check", "clippy", "doc", "bench"] {
+        let output = xcargo(&tools, &project, &[command, "--keep-going"]);
+        assert_eq!(output.status.code(), Some(2), "{}", text(&output));
+        assert!(text(&output).contains("--keep-going"), "{}", text(&output));
+    }
+    assert!(tools.invocations("cargo").unwrap().is_empty());
+
+    // After `--` it reaches cargo as given, once
+    let output = xcargo(
+        &tools,
+        &project,
+        &["clippy", "--target", HOST, "--", "--keep-going"],
+    );
+    assert!(output.status.success(), "{}", text(&output));
+    let cargo = tools.invocations("cargo").unwrap();
+    let clippy = cargo.iter().find(|args| args[0] == "clippy").unwrap();
+    assert_eq!(
+        clippy.iter().filter(|arg| *arg == "--keep-going").count(),
+        1,
+        "{clippy:?}"
+    );
+}
+
+#[test]
+fn test_same_error_on_several_targets_is_summarized_once() {
+    let mut tools = FakeTools::new(HOST).unwrap();
+    let message = r#"{"reason":"compiler-message","package_id":"app 0.1.0","message":{"message":"failed to resolve: use of undeclared crate or module `libc`","code":{"code":"E0433","explanation":null},"level":"error","spans":[{"file_name":"src/main.rs","byte_start":4,"byte_end":8,"line_start":1,"line_end":1,"column_start":5,"column_end":9,"is_primary":true,"text":[],"label":null}],"children":[],"rendered":"error[E0433]: failed to resolve: use of undeclared crate or module `libc`\n"}}"#;
+    let other = "aarch64-unknown-linux-gnu";
+    for target in [HOST, other] {
+        tools
+            .respond(
+                "cargo",
+                &["build", "--target", target],
+                Response {
+                    stdout: format!("{message}\n"),
+                    ..Response::failure(101, "error: could not compile `app`")
+                },
+            )
+            .unwrap();
+    }
+
+    // Sequential builds print each target's errors as they happen, parallel
+    // builds only in the summary
+    for (parallel, rendered) in [(false, 3), (true, 1)] {
+        let project = Project::binary("app")
+            .unwrap()
+            .config(&format!(
+                "[targets]\ndefault = [\"{HOST}\", \"{other}\"]\n\n\
+                 [build]\nparallel = {parallel}\n"
+            ))
+            .unwrap();
+        let output = xcargo(&tools, &project, &["build", "--all", "--keep-going"]);
+        assert!(!output.status.success());
+        let shown = text(&output);
+        assert!(shown.contains("Compiler Errors by Target"), "{shown}");
+        assert!(
+            shown.contains(&format!("failed on {HOST} and {other}"))
+                || shown.contains(&format!("failed on {other} and {HOST}")),
+            "{shown}"
+        );
+        assert_eq!(shown.matches("error[E0433]").count(), rendered, "{shown}");
+        assert!(!shown.contains("compiler-message"), "{shown}");
+    }
+}
+
+#[test]
+fn test_target_kinds_limit_cross_builds_and_tests() {
+    let cross = "aarch64-unknown-linux-gnu";
+    let project = Project::binary("app")
+        .unwrap()
+        .config(&format!(
+            "[targets.\"{cross}\"]\nkinds = [\"bin\", \"example\"]\ntest_kinds = [\"lib\"]\n"
+        ))
+        .unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+
+    for command in ["build", "test"] {
+        let output = xcargo(&tools, &project, &[command, "--target", cross]);
+        assert!(output.status.success(), "{}", text(&output));
+    }
+    // The host runs everything, and an explicit selection wins
+    let output = xcargo(&tools, &project, &["test", "--target", HOST]);
+    assert!(output.status.success(), "{}", text(&output));
+    let args = ["build", "--target", cross, "--bin", "app"];
+    let output = xcargo(&tools, &project, &args);
+    assert!(output.status.success(), "{}", text(&output));
+
+    assert_eq!(
+        tools.invocations("cargo").unwrap(),
+        vec![
+            vec!["build", "--target", cross, "--bins", "--examples"],
+            vec!["test", "--target", cross, "--lib"],
+            vec!["test", "--target", HOST],
+            vec!["build", "--target", cross, "--bin", "app"],
+        ]
+    );
+}
+
+#[test]
+fn test_earlier_builds_estimate_build_time() {
+    let other = "aarch64-unknown-linux-gnu";
+    let tools = FakeTools::new(HOST).unwrap();
+    for parallel in [false, true] {
+        let project = Project::binary("app")
+            .unwrap()
+            .config(&format!(
+                "[targets]\ndefault = [\"{HOST}\", \"{other}\"]\n\n\
+                 [build]\nparallel = {parallel}\n"
+            ))
+            .unwrap();
+
+        let output = xcargo(&tools, &project, &["build", "--all"]);
+        assert!(output.status.success(), "{}", text(&output));
+        assert!(!text(&output).contains("Estimated total"));
+
+        let output = xcargo(&tools, &project, &["build", "--all"]);
+        let shown = text(&output);
+        assert!(
+            shown.contains(&format!("{other}: ~1s based on the last build")),
+            "{shown}"
+        );
+        assert!(shown.contains("Estimated total: ~1s"), "{shown}");
+        // Release builds have their own history
+        let output = xcargo(&tools, &project, &["build", "--all", "--release"]);
+        assert!(!text(&output).contains("Estimated total"));
+    }
+}
+
+#[test]
+fn test_missing_rustup_is_reported() {
+    let project = Project::binary("app").unwrap();
+    let mut tools = FakeTools::empty().unwrap();
+    tools
+        .respond("rustc", &["-vV"], Response::ok(format!("host: {HOST}\n")))
+        .unwrap();
+
+    let output = xcargo(&tools, &project, &["build", "--target", HOST]);
+    assert!(!output.status.success());
+    assert!(text(&output).contains("rustup"), "{}", text(&output));
+    assert!(tools.invocations("cargo").unwrap().is_empty());
+}
+
+#[test]
+fn test_zig_selected_for_other_os() {
+    // Containers would win for another OS when the feature is on
+    let project = Project::binary("app")
+        .unwrap()
+        .config("[container]\nuse_when = \"never\"\n")
+        .unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    let args = ["build", "--target", "x86_64-pc-windows-gnu", "--explain"];
+
+    let output = xcargo(&tools, &project, &args);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(!text(&output).contains("Zig"), "{}", text(&output));
+
+    tools
+        .respond("zig", &["version"], Response::ok("0.13.0\n"))
+        .unwrap();
+    let output = xcargo(&tools, &project, &args);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("via Zig"), "{}", text(&output));
+
+    // Zig cannot link Apple targets, so it is not picked for them
+    let args = ["build", "--target", "x86_64-apple-darwin", "--explain"];
+    let output = xcargo(&tools, &project, &args);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(!text(&output).contains("via Zig"), "{}", text(&output));
+    // Explaining never builds
+    assert!(tools.invocations("cargo").unwrap().is_empty());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_explain_strategy() {
+    let project = Project::binary("app").unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+    let args = [
+        "build",
+        "--target",
+        "aarch64-unknown-linux-gnu",
+        "--explain-strategy",
+    ];
+
+    // No cross linker, no Zig, no container runtime
+    let output = xcargo(&tools, &project, &args);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(
+        text(&output).contains("nothing in the chain is available"),
+        "{}",
+        text(&output)
+    );
+    assert!(
+        text(&output).contains("✗ native     linker aarch64-linux-gnu-gcc is not installed"),
+        "{}",
+        text(&output)
+    );
+    assert!(
+        text(&output).contains("✗ zig        Zig is not installed"),
+        "{}",
+        text(&output)
+    );
+
+    let project = project
+        .config("[targets.\"aarch64-unknown-linux-gnu\"]\nstrategy = \"container\"\n")
+        .unwrap();
+    let output = xcargo(&tools, &project, &args);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(
+        text(&output).contains(
+            "aarch64-unknown-linux-gnu: container (chosen by [targets.\"aarch64-unknown-linux-gnu\"] strategy)"
+        ),
+        "{}",
+        text(&output)
+    );
+    assert!(tools.invocations("cargo").unwrap().is_empty());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_nix_shell_strategy() {
+    let project = Project::binary("app").unwrap();
+    project.file("flake.nix", "{ }\n").unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    let args = ["build", "--target", "aarch64-unknown-linux-gnu"];
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &[&args[..], &["--explain-strategy"]].concat(),
+    );
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(
+        text(&output).contains("✗ nix        nix is not installed"),
+        "{}",
+        text(&output)
+    );
+
+    tools
+        .respond("nix", &["--version"], Response::ok("nix (Nix) 2.24.0\n"))
+        .unwrap();
+    let output = xcargo(&tools, &project, &args);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(tools.invocations("cargo").unwrap().is_empty());
+    let nix = tools.invocations("nix").unwrap();
+    let dir = project.path().canonicalize().unwrap();
+    assert_eq!(
+        nix[0][..4],
+        ["develop", dir.to_str().unwrap(), "--command", "cargo"]
+    );
+    assert!(nix[0].contains(&"aarch64-unknown-linux-gnu".to_string()));
+
+    // Turned off, the chain goes on to native
+    let project = project.config("[strategy.nix]\nenable = false\n").unwrap();
+    let output = xcargo(
+        &tools,
+        &project,
+        &[&args[..], &["--explain-strategy"]].concat(),
+    );
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(!text(&output).contains("nix"), "{}", text(&output));
+}
+
+#[test]
+fn test_project_config_is_used() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config("[targets]\ndefault = [\"aarch64-unknown-linux-musl\"]\n")
+        .unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+
+    let output = xcargo(&tools, &project, &["build"]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert_eq!(
+        tools.invocations("cargo").unwrap(),
+        vec![vec!["build", "--target", "aarch64-unknown-linux-musl"]]
+    );
+}
+
+#[test]
+fn test_profile_overrides_targets_and_strategy() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config(
+            "[targets]\ndefault = [\"x86_64-unknown-linux-gnu\"]\n\n\
+             [profiles.dev]\ntargets = [\"aarch64-unknown-linux-musl\"]\n\n\
+             [profiles.ci]\ninherits = \"dev\"\nstrategy = \"container\"\n",
+        )
+        .unwrap();
+    let tools = FakeTools::new(HOST).unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &["build", "--all", "--explain-strategy", "--profile", "ci"],
+    );
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(
+        text(&output).contains("aarch64-unknown-linux-musl: container (chosen by profile 'ci')"),
+        "{}",
+        text(&output)
+    );
+
+    let output = tools
+        .command(env!("CARGO_BIN_EXE_xcargo"))
+        .arg("build")
+        .env("XCARGO_PROFILE", "dev")
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", text(&output));
+    assert_eq!(
+        tools.invocations("cargo").unwrap(),
+        vec![vec!["build", "--target", "aarch64-unknown-linux-musl"]]
+    );
+
+    let output = xcargo(&tools, &project, &["build", "--profile", "nightly"]);
+    assert!(!output.status.success());
+    assert!(
+        text(&output).contains("Unknown profile 'nightly' (available: ci, dev)"),
+        "{}",
+        text(&output)
+    );
+}
+
+#[test]
+fn test_targets_are_distributed_across_hosts() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config(
+            r#"
+[targets]
+default = ["aarch64-unknown-linux-musl", "aarch64-apple-darwin"]
+
+[container]
+use_when = "never"
+
+[[hosts]]
+name = "here"
+targets = ["*-linux-*"]
+
+[[hosts]]
+name = "mac-mini"
+kind = "ssh"
+address = "ci@mac-mini"
+dir = "/src/app"
+targets = ["*-apple-*"]
+"#,
+        )
+        .unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools
+        .respond("ssh", &[], Response::ok("Build completed\n"))
+        .unwrap();
+    tools.respond("scp", &[], Response::ok("")).unwrap();
+
+    let output = xcargo(&tools, &project, &["build", "--all"]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(
+        text(&output).contains("[mac-mini] Build completed"),
+        "{}",
+        text(&output)
+    );
+    assert!(
+        text(&output).contains("mac-mini (ssh): 1 of 1 built"),
+        "{}",
+        text(&output)
+    );
+
+    // Only the Linux target is built here
+    let cargo = tools.invocations("cargo").unwrap();
+    assert_eq!(cargo.len(), 1);
+    assert_eq!(
+        cargo[0][..3],
+        ["build", "--target", "aarch64-unknown-linux-musl"]
+    );
+    assert_eq!(
+        tools.invocations("ssh").unwrap(),
+        vec![vec![
+            "-o",
+            "BatchMode=yes",
+            "ci@mac-mini",
+            "cd '/src/app' && xcargo 'build' '--target' 'aarch64-apple-darwin'"
+        ]]
+    );
+    assert_eq!(
+        tools.invocations("scp").unwrap()[0][4],
+        "ci@mac-mini:/src/app/target/aarch64-apple-darwin/debug"
+    );
+}
+
+#[test]
+fn test_artifact_gate_failure_is_recorded() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config(
+            "[artifacts]\ndir = \"dist\"\n\n[[artifacts.gates]]\nname = \"malware-scan\"\ncommand = [\"scanner\", \"{artifact}\"]\n",
+        )
+        .unwrap();
+    let target = "aarch64-unknown-linux-gnu";
+    project
+        .file(format!("target/{target}/debug/app"), "binary")
+        .unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    let metadata = format!(
+        r#"{{"target_directory":"{}","packages":[{{"name":"app","targets":[{{"name":"app","kind":["bin"]}}]}}]}}"#,
+        project.path().join("target").display()
+    );
+    tools
+        .respond("cargo", &["metadata"], Response::ok(metadata))
+        .unwrap();
+    tools
+        .respond("scanner", &[], Response::failure(1, "infected"))
+        .unwrap();
+
+    let output = xcargo(&tools, &project, &["build", "--target", target]);
+    assert!(!output.status.success());
+    assert!(
+        text(&output).contains("failed gate 'malware-scan': scanner exited with code 1"),
+        "{}",
+        text(&output)
+    );
+
+    let scanned = &tools.invocations("scanner").unwrap()[0];
+    assert!(scanned[0].ends_with(&format!("dist/{target}/debug/app")));
+    let manifest = std::fs::read_to_string(project.path().join("dist/artifacts.json")).unwrap();
+    assert!(manifest.contains(r#""name": "malware-scan""#));
+    assert!(manifest.contains(r#""passed": false"#));
+}
+
+#[cfg(feature = "container")]
+#[test]
+fn test_container_argv() {
+    let project = Project::binary("app").unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools.docker().unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &[
+            "build",
+            "--container",
+            "--target",
+            "aarch64-unknown-linux-gnu",
+        ],
+    );
+    assert!(output.status.success(), "{}", text(&output));
+
+    let run = tools
+        .invocations("docker")
+        .unwrap()
+        .into_iter()
+        .find(|args| args.first().map(String::as_str) == Some("run"))
+        .expect("docker run");
+    assert!(run.contains(&"--rm".to_string()));
+    assert!(run.iter().any(|arg| arg.starts_with(&format!(
+        "{}:",
+        project.path().canonicalize().unwrap().display()
+    ))));
+    assert!(run.contains(&"aarch64-unknown-linux-gnu".to_string()));
+}
+
+#[test]
+fn test_project_plugins_are_compiled_for_the_host() {
+    let project = Project::binary("app").unwrap();
+    project
+        .file(
+            ".xcargo/plugins/checks/Cargo.toml",
+            "[package]\nname = \"release-checks\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+    project
+        .file(".xcargo/plugins/checks/src/lib.rs", "// plugin\n")
+        .unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    tools
+        .respond("cargo", &["rustc"], Response::failure(101, "error[E0425]"))
+        .unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &["build", "--target", "aarch64-unknown-linux-gnu"],
+    );
+    assert_eq!(output.status.code(), Some(5), "{}", text(&output));
+    assert!(text(&output).contains("[X0701] Plugin 'release_checks' failed"));
+
+    // Compiled as a host cdylib, and nothing built before the plugin loads
+    let cargo = tools.invocations("cargo").unwrap();
+    assert_eq!(cargo.len(), 1);
+    assert_eq!(
+        cargo[0][..5],
+        ["rustc", "--release", "--lib", "--crate-type", "cdylib"]
+    );
+    assert!(!cargo[0].contains(&"--target".to_string()));
+}
+
+#[test]
+fn test_report_bundles_last_failed_invocation() {
+    let project = Project::binary("app").unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    let target = "aarch64-unknown-linux-gnu";
+    tools
+        .respond("cargo", &["build"], Response::failure(101, "linker failed"))
+        .unwrap();
+    tools.respond("tar", &[], Response::ok("")).unwrap();
+
+    let output = tools
+        .command(env!("CARGO_BIN_EXE_xcargo"))
+        .args(["build", "--target", target])
+        .env("CARGO_REGISTRY_TOKEN", "cio_secret")
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let failure =
+        std::fs::read_to_string(project.path().join("target/xcargo/last-failure.json")).unwrap();
+    assert!(failure.contains("\"exit_code\": 101"), "{failure}");
+    assert!(failure.contains(target));
+    assert!(!failure.contains("cio_secret"));
+
+    let output = xcargo(&tools, &project, &["report", "bundle.tar.gz"]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("last-failure.json"));
+    assert!(text(&output).contains("Not included: xcargo.toml"));
+
+    let tar = tools.invocations("tar").unwrap();
+    assert_eq!(tar.len(), 1);
+    assert_eq!(tar[0][0], "-czf");
+    assert!(tar[0][1].ends_with("bundle.tar.gz"));
+    assert!(tar[0][4].starts_with("xcargo-report-"));
+}
+
+#[test]
+fn test_wasm_pipeline_runs_wasm_bindgen() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config("[artifacts.wasm]\ndir = \"pkg\"\nbindgen_target = \"bundler\"\n")
+        .unwrap();
+    let target = "wasm32-unknown-unknown";
+    project
+        .file(format!("target/{target}/debug/web_app.wasm"), "module")
+        .unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    let metadata = format!(
+        r#"{{"target_directory":"{}","packages":[{{"name":"web-app","targets":[{{"name":"web-app","kind":["cdylib"]}}]}}]}}"#,
+        project.path().join("target").display()
+    );
+    tools
+        .respond("cargo", &["metadata"], Response::ok(metadata))
+        .unwrap();
+    tools
+        .respond(
+            "wasm-bindgen",
+            &["--version"],
+            Response::ok("wasm-bindgen 0.2.92\n"),
+        )
+        .unwrap();
+
+    let output = xcargo(&tools, &project, &["build", "--target", target]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(
+        text(&output).contains("1 WebAssembly module exported to pkg"),
+        "{}",
+        text(&output)
+    );
+    let bindgen = tools.invocations("wasm-bindgen").unwrap();
+    let module = project
+        .path()
+        .join(format!("target/{target}/debug/web_app.wasm"));
+    assert_eq!(
+        bindgen[1],
+        [
+            "--target",
+            "bundler",
+            "--out-dir",
+            "pkg",
+            module.to_str().unwrap()
+        ]
+    );
+    // Debug builds are not optimized
+    assert!(tools.invocations("wasm-opt").unwrap().is_empty());
+}
+
+#[test]
+fn test_wasi_tests_run_in_a_runtime() {
+    let project = Project::binary("app").unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    let args = ["test", "--target", "wasm32-wasip1", "-v"];
+
+    let output = xcargo(&tools, &project, &args);
+    assert!(!output.status.success(), "{}", text(&output));
+    assert!(
+        text(&output).contains("needs a WASI runtime"),
+        "{}",
+        text(&output)
+    );
+    assert!(tools.invocations("cargo").unwrap().is_empty());
+
+    tools
+        .respond(
+            "wasmtime",
+            &["--version"],
+            Response::ok("wasmtime 25.0.0\n"),
+        )
+        .unwrap();
+    let output = xcargo(&tools, &project, &args);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(
+        text(&output).contains("CARGO_TARGET_WASM32_WASIP1_RUNNER=wasmtime run --dir=."),
+        "{}",
+        text(&output)
+    );
+}
+
+#[test]
+fn test_flash_runs_probe_rs() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config(
+            "[container]\nuse_when = \"never\"\n\n\
+             [targets.\"thumbv7em-none-eabihf\"]\nchip = \"STM32F407VGTx\"\n",
+        )
+        .unwrap();
+    let target = "thumbv7em-none-eabihf";
+    let elf = project
+        .file(format!("target/{target}/release/blinky"), "firmware")
+        .unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    let metadata = format!(
+        r#"{{"target_directory":"{}","packages":[{{"name":"blinky","targets":[{{"name":"blinky","kind":["bin"]}}]}}]}}"#,
+        project.path().join("target").display()
+    );
+    tools
+        .respond("cargo", &["metadata"], Response::ok(metadata))
+        .unwrap();
+    tools
+        .respond("probe-rs", &["download"], Response::ok(""))
+        .unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &["flash", "--target", target, "--release"],
+    );
+    assert!(output.status.success(), "{}", text(&output));
+    let cargo = tools.invocations("cargo").unwrap();
+    assert!(cargo.iter().any(|args| args[0] == "build"));
+    assert_eq!(
+        tools.invocations("probe-rs").unwrap(),
+        [["download", "--chip", "STM32F407VGTx", elf.to_str().unwrap()]]
+    );
+
+    // Without a chip there is nothing to tell probe-rs
+    project
+        .file("target/thumbv6m-none-eabi/debug/blinky", "firmware")
+        .unwrap();
+    let output = xcargo(
+        &tools,
+        &project,
+        &["flash", "--target", "thumbv6m-none-eabi", "--no-build"],
+    );
+    assert!(!output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("needs a chip"), "{}", text(&output));
+}
+
+#[test]
+fn test_docker_build_makes_a_multi_arch_image() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config(
+            "[container]\nuse_when = \"never\"\n\n\
+             [docker]\ntags = [\"ghcr.io/acme/{name}:{version}\"]\n",
+        )
+        .unwrap();
+    let targets = ["x86_64-unknown-linux-musl", "aarch64-unknown-linux-musl"];
+    for target in targets {
+        project
+            .file(format!("target/{target}/release/app"), "binary")
+            .unwrap();
+    }
+    let mut tools = FakeTools::new(HOST).unwrap();
+    let metadata = format!(
+        r#"{{"target_directory":"{}","packages":[{{"name":"app","version":"1.4.0","targets":[{{"name":"app","kind":["bin"]}}]}}]}}"#,
+        project.path().join("target").display()
+    );
+    tools
+        .respond("cargo", &["metadata"], Response::ok(metadata))
+        .unwrap();
+    tools
+        .respond("docker", &["buildx"], Response::ok(""))
+        .unwrap();
+
+    let output = xcargo(
+        &tools,
+        &project,
+        &[
+            "docker",
+            "build",
+            "--target",
+            targets[0],
+            "--target",
+            targets[1],
+            "--release",
+            "--no-build",
+        ],
+    );
+    assert!(output.status.success(), "{}", text(&output));
+
+    let context = project.path().join("target/docker/app");
+    let archive = project.path().join("target/docker/app.oci.tar");
+    let docker = tools.invocations("docker").unwrap();
+    assert_eq!(docker[0], ["--version"]);
+    assert_eq!(
+        docker[1..],
+        [[
+            "buildx",
+            "build",
+            "--platform",
+            "linux/amd64,linux/arm64",
+            "--tag",
+            "ghcr.io/acme/app:1.4.0",
+            "--output",
+            &format!("type=oci,dest={}", archive.display()),
+            context.to_str().unwrap(),
+        ]]
+    );
+    assert!(context.join("linux/arm64/app").is_file());
+    let dockerfile = std::fs::read_to_string(context.join("Dockerfile")).unwrap();
+    assert!(
+        dockerfile.contains("COPY --chmod=755 ${TARGETPLATFORM}/app"),
+        "{dockerfile}"
+    );
+
+    // Only Linux binaries go in an image
+    let output = xcargo(
+        &tools,
+        &project,
+        &[
+            "docker",
+            "build",
+            "--target",
+            "x86_64-pc-windows-gnu",
+            "--no-build",
+        ],
+    );
+    assert!(!output.status.success(), "{}", text(&output));
+    assert!(
+        text(&output).contains("need a Linux target"),
+        "{}",
+        text(&output)
+    );
+}
+
+#[test]
+fn test_docker_push_assembles_a_manifest_list() {
+    let project = Project::binary("app").unwrap();
+    let targets = ["x86_64-unknown-linux-musl", "aarch64-unknown-linux-musl"];
+    for target in targets {
+        project
+            .file(format!("target/{target}/debug/app"), "binary")
+            .unwrap();
+    }
+    let mut tools = FakeTools::new(HOST).unwrap();
+    let metadata = format!(
+        r#"{{"target_directory":"{}","packages":[{{"name":"app","version":"2.0.1","targets":[{{"name":"app","kind":["bin"]}}]}}]}}"#,
+        project.path().join("target").display()
+    );
+    tools
+        .respond("cargo", &["metadata"], Response::ok(metadata))
+        .unwrap();
+    tools
+        .respond("docker", &["buildx"], Response::ok(""))
+        .unwrap();
+    tools
+        .respond("docker", &["login"], Response::ok(""))
+        .unwrap();
+
+    let output = tools
+        .command(env!("CARGO_BIN_EXE_xcargo"))
+        .args(["docker", "push", "--multi-arch", "--no-build"])
+        .args(["--target", targets[0], "--target", targets[1]])
+        .args(["--tag", "ghcr.io/acme/app:{version}"])
+        .env("XCARGO_REGISTRY_USER", "bot")
+        .env("XCARGO_REGISTRY_PASSWORD", "secret")
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", text(&output));
+
+    let docker = tools.invocations("docker").unwrap();
+    assert_eq!(docker[0], ["--version"]);
+    assert_eq!(
+        docker[1],
+        ["login", "ghcr.io", "--username", "bot", "--password-stdin"]
+    );
+    assert!(docker[2].contains(&"ghcr.io/acme/app:2.0.1-amd64".to_string()));
+    assert!(docker[2].contains(&"--push".to_string()));
+    assert!(docker[3].contains(&"ghcr.io/acme/app:2.0.1-arm64".to_string()));
+    let manifest = &docker[4];
+    assert_eq!(manifest[..3], ["buildx", "imagetools", "create"]);
+    assert!(manifest.contains(&"ghcr.io/acme/app:2.0.1".to_string()));
+    assert!(manifest
+        .iter()
+        .any(|arg| arg.starts_with("index:io.github.xcargo.version=")));
+    assert_eq!(
+        manifest[manifest.len() - 2..],
+        [
+            "ghcr.io/acme/app:2.0.1-amd64",
+            "ghcr.io/acme/app:2.0.1-arm64"
+        ]
+    );
+}
+
+#[test]
+fn test_android_export_lays_out_jni_libs() {
+    let project = Project::binary("app")
+        .unwrap()
+        .config("[container]\nuse_when = \"never\"\n\n[artifacts.android]\ndir = \"android\"\n")
+        .unwrap();
+    let target = "aarch64-linux-android";
+    project
+        .file(format!("target/{target}/debug/libcore_ffi.so"), "library")
+        .unwrap();
+    let mut tools = FakeTools::new(HOST).unwrap();
+    let metadata = format!(
+        r#"{{"target_directory":"{}","packages":[{{"name":"core-ffi","targets":[{{"name":"core-ffi","kind":["cdylib"]}}]}}]}}"#,
+        project.path().join("target").display()
+    );
+    tools
+        .respond("cargo", &["metadata"], Response::ok(metadata))
+        .unwrap();
+
+    let output = xcargo(&tools, &project, &["build", "--target", target]);
+    assert!(output.status.success(), "{}", text(&output));
+    assert!(text(&output).contains("1 Android library exported"));
+
+    let android = project.path().join("android");
+    assert!(android.join("jniLibs/arm64-v8a/libcore_ffi.so").is_file());
+    let cmake = std::fs::read_to_string(android.join("rust-libs.cmake")).unwrap();
+    assert!(cmake.contains("rust::core_ffi"));
+    let config = std::fs::read_to_string(android.join("xcargo-android.toml")).unwrap();
+    assert!(config.contains(r#"arm64-v8a = "aarch64-linux-android""#));
+}
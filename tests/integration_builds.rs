@@ -91,10 +91,11 @@ fn test_build_host_target() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: false,
+        verbose: 0,
         use_container: false,
         use_zig: None,
         operation: CargoOperation::Build,
+        ..Default::default()
     };
 
     // This should succeed for the host target
@@ -123,10 +124,11 @@ fn test_check_operation() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: false,
+        verbose: 0,
         use_container: false,
         use_zig: None,
         operation: CargoOperation::Check,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -152,10 +154,11 @@ fn test_test_operation() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: false,
+        verbose: 0,
         use_container: false,
         use_zig: None,
         operation: CargoOperation::Test,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -181,10 +184,11 @@ fn test_release_build() -> Result<()> {
         release: true, // Release mode
         cargo_args: vec![],
         toolchain: None,
-        verbose: false,
+        verbose: 0,
         use_container: false,
         use_zig: None,
         operation: CargoOperation::Build,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -218,10 +222,11 @@ fn test_verbose_build() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: true, // Verbose output
+        verbose: 1, // Verbose output
         use_container: false,
         use_zig: None,
         operation: CargoOperation::Build,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -247,10 +252,11 @@ fn test_build_with_cargo_args() -> Result<()> {
         release: false,
         cargo_args: vec!["--lib".to_string()], // Build only the library
         toolchain: None,
-        verbose: false,
+        verbose: 0,
         use_container: false,
         use_zig: None,
         operation: CargoOperation::Build,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -277,10 +283,11 @@ fn test_missing_cargo_toml() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: false,
+        verbose: 0,
         use_container: false,
         use_zig: None,
         operation: CargoOperation::Build,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -311,10 +318,11 @@ fn test_build_no_target_specified() -> Result<()> {
         release: false,
         cargo_args: vec![],
         toolchain: None,
-        verbose: false,
+        verbose: 0,
         use_container: false,
         use_zig: None,
         operation: CargoOperation::Build,
+        ..Default::default()
     };
 
     let result = builder.build(&options);
@@ -260,6 +260,15 @@ fn test_target_info_invalid_target() {
         .stdout(predicate::str::contains("Invalid target"));
 }
 
+#[test]
+fn test_target_info_suggests_close_triple() {
+    xcargo()
+        .args(["target", "info", "x86_64-linux-gnu"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("x86_64-unknown-linux-gnu"));
+}
+
 // ============================================================================
 // Build Options Combinations
 // ============================================================================
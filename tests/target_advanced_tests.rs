@@ -463,9 +463,9 @@ fn test_target_tier_display() {
 #[test]
 fn test_parse_complex_android_triple() {
     let target = Target::from_triple("armv7-linux-androideabi").unwrap();
-    assert_eq!(target.arch, "armv7");
-    assert_eq!(target.vendor, "linux");
-    assert_eq!(target.os, "androideabi");
+    assert_eq!(target.arch, "arm");
+    assert_eq!(target.vendor, "unknown");
+    assert_eq!(target.os, "android");
 }
 
 #[test]
@@ -479,9 +479,10 @@ fn test_parse_ios_sim_triple() {
 
 #[test]
 fn test_parse_target_with_multiple_env_parts() {
-    // Some targets have complex environment strings
+    // rustc splits "gnueabihf" into the environment and the ABI
     let target = Target::from_triple("armv7-unknown-linux-gnueabihf").unwrap();
-    assert_eq!(target.arch, "armv7");
+    assert_eq!(target.arch, "arm");
     assert_eq!(target.os, "linux");
-    assert_eq!(target.env, Some("gnueabihf".to_string()));
+    assert_eq!(target.env, Some("gnu".to_string()));
+    assert_eq!(target.abi.as_deref(), Some("eabihf"));
 }
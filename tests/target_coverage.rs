@@ -181,9 +181,9 @@ fn test_target_parse_musl() -> Result<()> {
 fn test_target_parse_arm() -> Result<()> {
     let target = Target::from_triple("armv7-unknown-linux-gnueabihf")?;
 
-    assert_eq!(target.arch, "armv7");
+    assert_eq!(target.arch, "arm");
     assert_eq!(target.os, "linux");
-    assert_eq!(target.env, Some("gnueabihf".to_string()));
+    assert_eq!(target.env, Some("gnu".to_string()));
     Ok(())
 }
 
@@ -34,7 +34,7 @@ fn test_parse_macos_triple() -> Result<()> {
     let target = Target::from_triple("aarch64-apple-darwin")?;
     assert_eq!(target.arch, "aarch64");
     assert_eq!(target.vendor, "apple");
-    assert_eq!(target.os, "darwin");
+    assert_eq!(target.os, "macos");
     assert!(target.env.is_none(), "macOS targets don't have env component");
     Ok(())
 }
@@ -73,7 +73,7 @@ fn test_parse_wasm_triple() -> Result<()> {
 fn test_parse_android_triple() -> Result<()> {
     let target = Target::from_triple("aarch64-linux-android")?;
     assert_eq!(target.arch, "aarch64");
-    assert_eq!(target.vendor, "linux");
+    assert_eq!(target.vendor, "unknown");
     assert_eq!(target.os, "android");
     assert!(target.env.is_none());
     Ok(())
@@ -92,10 +92,10 @@ fn test_parse_ios_triple() -> Result<()> {
 #[test]
 fn test_parse_armv7_triple() -> Result<()> {
     let target = Target::from_triple("armv7-unknown-linux-gnueabihf")?;
-    assert_eq!(target.arch, "armv7");
+    assert_eq!(target.arch, "arm");
     assert_eq!(target.vendor, "unknown");
     assert_eq!(target.os, "linux");
-    assert_eq!(target.env, Some("gnueabihf".to_string()));
+    assert_eq!(target.env, Some("gnu".to_string()));
     Ok(())
 }
 
@@ -120,7 +120,7 @@ fn test_parse_netbsd_triple() -> Result<()> {
 #[test]
 fn test_parse_riscv_triple() -> Result<()> {
     let target = Target::from_triple("riscv64gc-unknown-linux-gnu")?;
-    assert_eq!(target.arch, "riscv64gc");
+    assert_eq!(target.arch, "riscv64");
     assert_eq!(target.os, "linux");
     Ok(())
 }
@@ -128,17 +128,18 @@ fn test_parse_riscv_triple() -> Result<()> {
 #[test]
 fn test_parse_embedded_arm_triple() -> Result<()> {
     let target = Target::from_triple("thumbv7em-none-eabi")?;
-    assert_eq!(target.arch, "thumbv7em");
-    assert_eq!(target.vendor, "none");
-    assert_eq!(target.os, "eabi");
+    assert_eq!(target.arch, "arm");
+    assert_eq!(target.vendor, "unknown");
+    assert_eq!(target.os, "none");
     assert!(target.env.is_none());
+    assert_eq!(target.abi.as_deref(), Some("eabi"));
     Ok(())
 }
 
 #[test]
 fn test_parse_powerpc_triple() -> Result<()> {
     let target = Target::from_triple("powerpc64le-unknown-linux-gnu")?;
-    assert_eq!(target.arch, "powerpc64le");
+    assert_eq!(target.arch, "powerpc64");
     assert_eq!(target.os, "linux");
     Ok(())
 }
@@ -307,11 +308,12 @@ fn test_target_debug_format() -> Result<()> {
 
 #[test]
 fn test_parse_multipart_env() -> Result<()> {
-    // Some targets have multi-part env like "gnueabihf"
+    // rustc splits "gnueabihf" into the environment and the ABI
     let target = Target::from_triple("arm-unknown-linux-gnueabihf")?;
     assert_eq!(target.arch, "arm");
     assert_eq!(target.os, "linux");
-    assert_eq!(target.env, Some("gnueabihf".to_string()));
+    assert_eq!(target.env, Some("gnu".to_string()));
+    assert_eq!(target.abi.as_deref(), Some("eabihf"));
 
     Ok(())
 }
@@ -365,7 +367,7 @@ fn test_musl_target_requirements() -> Result<()> {
 #[test]
 fn test_parse_i686_triple() -> Result<()> {
     let target = Target::from_triple("i686-unknown-linux-gnu")?;
-    assert_eq!(target.arch, "i686");
+    assert_eq!(target.arch, "x86");
     assert_eq!(target.os, "linux");
 
     Ok(())